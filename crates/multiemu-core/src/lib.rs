@@ -0,0 +1,39 @@
+//! The emulation core of multiemu, without the gui or cli
+//!
+//! A [machine::Machine] is built out of components with [machine::MachineBuilder], memory goes through
+//! [memory::MemoryTranslationTable] and the [scheduler] decides who runs when. The definitions for every supported system
+//! are in [definitions], looked up by [rom::system::GameSystem] through [machine::definition::MACHINE_DEFINITIONS]
+//!
+//! ```no_run
+//! use multiemu_core::{
+//!     machine::Machine,
+//!     rom::{manager::RomManager, system::{GameSystem, OtherSystem}},
+//! };
+//! use std::sync::Arc;
+//!
+//! let rom_manager = Arc::new(RomManager::new(None).unwrap());
+//! let mut machine = Machine::from_system(
+//!     vec![],
+//!     rom_manager,
+//!     GameSystem::Other(OtherSystem::Chip8),
+//! )
+//! .unwrap();
+//! machine.run();
+//! ```
+//!
+//! Only what is reexported here is meant to be used from outside, the rest of the multiemu crate can change at any time
+
+pub use multiemu::{component, definitions, input, machine, memory, processor, rom, scheduler};
+
+/// What display components hand their framebuffers out as
+pub use multiemu::runtime::rendering_backend;
+
+/// Everything needed to put together a new machine
+pub mod prelude {
+    pub use multiemu::{
+        component::{Component, ComponentId, FromConfig, ResetKind},
+        machine::{ComponentBuilder, Machine, MachineBuildError, MachineBuilder},
+        memory::{AddressSpaceId, MemoryTranslationTable},
+        rom::{id::RomId, manager::RomManager, system::GameSystem},
+    };
+}
@@ -1,6 +1,9 @@
 use cfg_aliases::cfg_aliases;
 
 fn main() {
+    #[cfg(feature = "vulkan")]
+    compile_shaders();
+
     cfg_aliases! {
         // Means a desktop platform, indicates we will use winit/cpal/gilrs/vulkan. Android is considered a desktop platform here cuz yeah
         platform_desktop: {
@@ -31,5 +34,55 @@ fn main() {
                 feature = "vulkan"
             )
         },
+        // Experimental community components compiled to WASM
+        wasm_plugins: {
+            all(
+                any(
+                    target_family = "unix",
+                    target_os = "windows"
+                ),
+                not(target_os = "horizon"),
+                feature = "wasm_plugins"
+            )
+        },
     }
 }
+
+/// Turns the GLSL compute shaders into SPIR-V the vulkan renderer can include
+#[cfg(feature = "vulkan")]
+fn compile_shaders() {
+    use naga::{
+        back::spv,
+        front::glsl,
+        valid::{Capabilities, ValidationFlags, Validator},
+        ShaderStage,
+    };
+    use std::{env, fs, path::PathBuf};
+
+    let output_directory = PathBuf::from(env::var("OUT_DIR").unwrap());
+
+    for shader in ["xbr2x", "crt_lite"] {
+        let source_path = format!("shaders/{}.comp", shader);
+        println!("cargo:rerun-if-changed={}", source_path);
+
+        let source = fs::read_to_string(&source_path).unwrap();
+        let module = glsl::Frontend::default()
+            .parse(&glsl::Options::from(ShaderStage::Compute), &source)
+            .unwrap_or_else(|err| panic!("{}", err.emit_to_string(&source)));
+        let module_info = Validator::new(ValidationFlags::all(), Capabilities::PUSH_CONSTANT)
+            .validate(&module)
+            .unwrap_or_else(|err| panic!("{}", err.emit_to_string(&source)));
+        let words = spv::write_vec(&module, &module_info, &spv::Options::default(), None).unwrap();
+
+        fs::write(
+            output_directory.join(format!("{}.spv", shader)),
+            words_to_bytes(&words),
+        )
+        .unwrap();
+    }
+}
+
+#[cfg(feature = "vulkan")]
+fn words_to_bytes(words: &[u32]) -> Vec<u8> {
+    words.iter().flat_map(|word| word.to_le_bytes()).collect()
+}
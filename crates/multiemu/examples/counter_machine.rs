@@ -0,0 +1,271 @@
+//! A toy "counter" system showing how a new machine is put together
+//!
+//! It has three components
+//!
+//! - A single byte register sitting on the bus
+//! - A processor that increments that register 60 times a second
+//! - A display that shows the register as a row of 8 lit or unlit pixels
+//!
+//! Run it with `cargo run --example counter_machine`
+
+use multiemu::{
+    component::{
+        display::DisplayComponent,
+        memory::MemoryComponent,
+        schedulable::{RunContext, SchedulableComponent},
+        Component, ComponentId, FromConfig, ResetKind,
+    },
+    machine::{ComponentBuilder, Machine, MachineBuildError},
+    memory::{AddressSpaceId, MemoryTranslationTable, ReadMemoryRecord, WriteMemoryRecord},
+    rom::{manager::RomManager, system::GameSystem},
+    runtime::rendering_backend::{
+        DisplayComponentFramebuffer, DisplayComponentInitializationData, RenderSurface,
+    },
+};
+use nalgebra::DMatrix;
+use num::rational::Ratio;
+use palette::Srgba;
+use rangemap::RangeMap;
+use std::{
+    sync::{Arc, Mutex, OnceLock},
+    time::Duration,
+};
+
+const COUNTER_ADDRESS_SPACE_ID: AddressSpaceId = 0;
+const COUNTER_ADDRESS: usize = 0x0;
+
+/// The memory component, one byte of storage
+#[derive(Debug)]
+struct CounterRegister {
+    value: Mutex<u8>,
+}
+
+impl Component for CounterRegister {
+    fn reset(&self, _kind: ResetKind) {
+        *self.value.lock().unwrap() = 0;
+    }
+}
+
+impl FromConfig for CounterRegister {
+    type Config = ();
+
+    fn from_config(
+        component_builder: &mut ComponentBuilder<Self>,
+        _config: Self::Config,
+    ) -> Result<(), MachineBuildError> {
+        component_builder
+            .set_component(Self {
+                value: Mutex::new(0),
+            })
+            .set_memory([(
+                COUNTER_ADDRESS_SPACE_ID,
+                COUNTER_ADDRESS..COUNTER_ADDRESS + 1,
+            )]);
+
+        Ok(())
+    }
+}
+
+impl MemoryComponent for CounterRegister {
+    fn read_memory(
+        &self,
+        address: usize,
+        buffer: &mut [u8],
+        _address_space: AddressSpaceId,
+        errors: &mut RangeMap<usize, ReadMemoryRecord>,
+    ) {
+        // We are only one byte wide so anything bigger is out of bounds
+        if buffer.len() != 1 {
+            errors.insert(address..address + buffer.len(), ReadMemoryRecord::Denied);
+            return;
+        }
+
+        buffer[0] = *self.value.lock().unwrap();
+    }
+
+    fn write_memory(
+        &self,
+        address: usize,
+        buffer: &[u8],
+        _address_space: AddressSpaceId,
+        errors: &mut RangeMap<usize, WriteMemoryRecord>,
+    ) {
+        if buffer.len() != 1 {
+            errors.insert(address..address + buffer.len(), WriteMemoryRecord::Denied);
+            return;
+        }
+
+        *self.value.lock().unwrap() = buffer[0];
+    }
+}
+
+/// The display component, the renderer decides how big it ends up on screen
+#[derive(Debug, Default)]
+struct CounterDisplay {
+    surface: OnceLock<Box<dyn RenderSurface>>,
+}
+
+impl CounterDisplay {
+    fn draw(&self, value: u8) {
+        // Nothing to draw on until the renderer has set us up
+        let Some(surface) = self.surface.get() else {
+            return;
+        };
+
+        let frame = DMatrix::from_fn(8, 1, |x, _| {
+            if value & (0x80 >> x) != 0 {
+                Srgba::new(0xff, 0xff, 0xff, 0xff)
+            } else {
+                Srgba::new(0, 0, 0, 0xff)
+            }
+        });
+        surface.upload(&frame);
+        surface.commit();
+    }
+}
+
+impl Component for CounterDisplay {}
+
+impl FromConfig for CounterDisplay {
+    type Config = ();
+
+    fn from_config(
+        component_builder: &mut ComponentBuilder<Self>,
+        _config: Self::Config,
+    ) -> Result<(), MachineBuildError> {
+        component_builder
+            .set_component(Self::default())
+            .set_display();
+
+        Ok(())
+    }
+}
+
+impl DisplayComponent for CounterDisplay {
+    fn set_display_data(&self, display_data: DisplayComponentInitializationData) {
+        // Works the same whichever backend is drawing
+        self.surface.set(display_data.create_surface(8, 1)).unwrap();
+    }
+
+    fn get_framebuffer(&self) -> DisplayComponentFramebuffer {
+        self.surface.get().unwrap().framebuffer()
+    }
+}
+
+#[derive(Debug)]
+struct CounterProcessorConfig {
+    display: ComponentId,
+}
+
+/// The processor, reaches memory over the bus and talks to the display directly like real hardware often would
+#[derive(Debug)]
+struct CounterProcessor {
+    display: Arc<CounterDisplay>,
+    memory_translation_table: OnceLock<Arc<MemoryTranslationTable>>,
+}
+
+impl Component for CounterProcessor {
+    fn set_memory_translation_table(&self, memory_translation_table: Arc<MemoryTranslationTable>) {
+        self.memory_translation_table
+            .set(memory_translation_table)
+            .unwrap();
+    }
+}
+
+impl FromConfig for CounterProcessor {
+    type Config = CounterProcessorConfig;
+
+    fn from_config(
+        component_builder: &mut ComponentBuilder<Self>,
+        config: Self::Config,
+    ) -> Result<(), MachineBuildError> {
+        let display = component_builder
+            .machine()
+            .get_component::<CounterDisplay>(config.display)?;
+
+        component_builder
+            .set_component(Self {
+                display,
+                memory_translation_table: OnceLock::default(),
+            })
+            .set_schedulable(Ratio::from_integer(60), [], [config.display]);
+
+        Ok(())
+    }
+}
+
+impl SchedulableComponent for CounterProcessor {
+    fn run(&self, period: u64, _context: RunContext) {
+        let memory_translation_table = self.memory_translation_table.get().unwrap();
+        let mut value = [0];
+
+        for _ in 0..period {
+            memory_translation_table
+                .read(COUNTER_ADDRESS, &mut value, COUNTER_ADDRESS_SPACE_ID)
+                .unwrap();
+            value[0] = value[0].wrapping_add(1);
+            memory_translation_table
+                .write(COUNTER_ADDRESS, &value, COUNTER_ADDRESS_SPACE_ID)
+                .unwrap();
+        }
+
+        self.display.draw(value[0]);
+    }
+}
+
+/// Equivalent of the functions in `definitions` that build each real system
+fn counter_machine(rom_manager: Arc<RomManager>) -> Result<Machine, MachineBuildError> {
+    let machine = Machine::build(GameSystem::Unknown, rom_manager);
+    let machine = machine.insert_bus(COUNTER_ADDRESS_SPACE_ID, 8);
+
+    let (machine, _) = machine.default_component::<CounterRegister>()?;
+    let (machine, display_component_id) = machine.default_component::<CounterDisplay>()?;
+    let (machine, _) = machine.build_component::<CounterProcessor>(CounterProcessorConfig {
+        display: display_component_id,
+    })?;
+
+    machine.build()
+}
+
+fn main() {
+    tracing_subscriber::fmt::init();
+
+    let rom_manager = Arc::new(RomManager::new(None).unwrap());
+    let mut machine = counter_machine(rom_manager).expect("The counter machine is wired wrong");
+
+    // The frontend would normally do this through its rendering backend
+    for component_info in machine.display_components() {
+        component_info
+            .component
+            .set_display_data(DisplayComponentInitializationData::Software);
+    }
+
+    // A frontend calls run to keep up with real time, here we want exactly half a second of emulated time
+    for _ in 0..30 {
+        machine.run_frame(Duration::from_secs(1) / 60);
+    }
+
+    let mut value = [0];
+    machine
+        .memory_translation_table
+        .read(COUNTER_ADDRESS, &mut value, COUNTER_ADDRESS_SPACE_ID)
+        .unwrap();
+
+    let DisplayComponentFramebuffer::Software(framebuffer) = machine
+        .display_components()
+        .next()
+        .unwrap()
+        .component
+        .get_framebuffer()
+    else {
+        unreachable!()
+    };
+    let screen: String = framebuffer
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|pixel| if pixel.red == 0 { '.' } else { '#' })
+        .collect();
+
+    println!("Counter is at {} and the screen shows {}", value[0], screen);
+}
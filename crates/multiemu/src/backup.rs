@@ -0,0 +1,180 @@
+//! Bundles everything a user would miss into one archive, for backups and moving to another machine
+//!
+//! Roms are left out since they can be huge and are easy to get back, the database entries pointing at them are not.
+//! Given a passphrase every entry is encrypted with AES-256, since saves and the config can say a lot about someone
+
+use crate::{
+    config::{GlobalConfig, GLOBAL_CONFIG},
+    rom::manager::RomManager,
+};
+use ron::ser::PrettyConfig;
+use std::{
+    error::Error,
+    fs::{create_dir_all, File},
+    io::{Read, Write},
+    path::{Path, PathBuf},
+};
+use walkdir::WalkDir;
+use zip::{write::FileOptions, AesMode, CompressionMethod, ZipArchive, ZipWriter};
+
+const CONFIG_ENTRY: &str = "config.ron";
+const DATABASE_ENTRY: &str = "database";
+const SAVES_ENTRY: &str = "saves";
+const SNAPSHOTS_ENTRY: &str = "snapshots";
+
+pub fn export_backup(
+    path: impl AsRef<Path>,
+    passphrase: Option<&str>,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let path = path.as_ref();
+    let (config, database_file, save_directory, snapshot_directory) = {
+        let global_config_guard = GLOBAL_CONFIG.read().unwrap();
+
+        (
+            ron::ser::to_string_pretty(&*global_config_guard, PrettyConfig::default())?,
+            global_config_guard.database_file.clone(),
+            global_config_guard.save_directory.clone(),
+            global_config_guard.snapshot_directory.clone(),
+        )
+    };
+
+    if let Some(parent) = path.parent() {
+        create_dir_all(parent)?;
+    }
+
+    let temporary_path = path.with_extension("partial");
+    let mut archive = ZipWriter::new(File::create(&temporary_path)?);
+    let mut options = FileOptions::<()>::default().compression_method(CompressionMethod::Deflated);
+
+    if let Some(passphrase) = passphrase {
+        options = options.with_aes_encryption(AesMode::Aes256, passphrase);
+    }
+
+    archive.start_file(CONFIG_ENTRY, options)?;
+    archive.write_all(config.as_bytes())?;
+
+    if database_file.is_file() {
+        archive.start_file(DATABASE_ENTRY, options)?;
+        std::io::copy(&mut File::open(&database_file)?, &mut archive)?;
+    }
+
+    for (entry_prefix, directory) in [
+        (SAVES_ENTRY, save_directory),
+        (SNAPSHOTS_ENTRY, snapshot_directory),
+    ] {
+        if !directory.is_dir() {
+            continue;
+        }
+
+        for entry in WalkDir::new(&directory) {
+            let entry = entry?;
+
+            if !entry.file_type().is_file() {
+                continue;
+            }
+
+            // Zip entries always use forward slashes
+            let entry_name = entry
+                .path()
+                .strip_prefix(&directory)?
+                .iter()
+                .fold(PathBuf::from(entry_prefix), |entry_name, component| {
+                    entry_name.join(component)
+                })
+                .to_string_lossy()
+                .replace('\\', "/");
+
+            archive.start_file(entry_name, options)?;
+            std::io::copy(&mut File::open(entry.path())?, &mut archive)?;
+        }
+    }
+
+    archive.finish()?.flush()?;
+    std::fs::rename(temporary_path, path)?;
+
+    tracing::info!("Exported backup to {}", path.display());
+
+    Ok(())
+}
+
+/// Restores a backup on top of what we have, the config from the backup replaces ours except for where things are stored
+///
+/// Encrypted backups need the passphrase they were exported with
+pub fn import_backup(
+    path: impl AsRef<Path>,
+    passphrase: Option<&str>,
+    rom_manager: &RomManager,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let path = path.as_ref();
+    let mut archive = ZipArchive::new(File::open(path)?)?;
+    let (save_directory, snapshot_directory) = {
+        let global_config_guard = GLOBAL_CONFIG.read().unwrap();
+
+        (
+            global_config_guard.save_directory.clone(),
+            global_config_guard.snapshot_directory.clone(),
+        )
+    };
+    let mut imported_config = None;
+
+    for index in 0..archive.len() {
+        let mut entry = match passphrase {
+            Some(passphrase) => archive.by_index_decrypt(index, passphrase.as_bytes())?,
+            None => archive.by_index(index)?,
+        };
+
+        if !entry.is_file() {
+            continue;
+        }
+
+        // Refuse anything trying to escape where we put it
+        let Some(entry_path) = entry.enclosed_name() else {
+            tracing::warn!("Skipping suspicious backup entry {}", entry.name());
+            continue;
+        };
+
+        if entry_path == Path::new(CONFIG_ENTRY) {
+            let mut config = String::new();
+            entry.read_to_string(&mut config)?;
+
+            imported_config = Some(ron::from_str::<GlobalConfig>(&config)?);
+        } else if entry_path == Path::new(DATABASE_ENTRY) {
+            // Our database is open, so merge the backup into it instead of replacing it
+            let temporary_database = std::env::temp_dir()
+                .join(format!("multiemu_backup_database_{}", std::process::id()));
+            std::io::copy(&mut entry, &mut File::create(&temporary_database)?)?;
+
+            let imported_entries = rom_manager.load_database(&temporary_database);
+            let _ = std::fs::remove_file(&temporary_database);
+            imported_entries?;
+        } else if let Ok(relative_path) = entry_path.strip_prefix(SAVES_ENTRY) {
+            extract_entry(&mut entry, &save_directory.join(relative_path))?;
+        } else if let Ok(relative_path) = entry_path.strip_prefix(SNAPSHOTS_ENTRY) {
+            extract_entry(&mut entry, &snapshot_directory.join(relative_path))?;
+        } else {
+            tracing::warn!("Skipping unknown backup entry {}", entry_path.display());
+        }
+    }
+
+    if let Some(mut imported_config) = imported_config {
+        let mut global_config_guard = GLOBAL_CONFIG.write().unwrap();
+
+        imported_config.adopt_local_paths(&global_config_guard);
+        *global_config_guard = imported_config;
+        global_config_guard.save().map_err(|err| err.to_string())?;
+    }
+
+    tracing::info!("Imported backup from {}", path.display());
+
+    Ok(())
+}
+
+fn extract_entry(entry: &mut impl Read, destination: &Path) -> Result<(), std::io::Error> {
+    if let Some(parent) = destination.parent() {
+        create_dir_all(parent)?;
+    }
+
+    std::io::copy(entry, &mut File::create(destination)?)?;
+
+    Ok(())
+}
@@ -0,0 +1,270 @@
+//! Tells the OS to open ROMs and multiemu:// links with us
+
+use clap::Subcommand;
+use std::{env, error::Error, path::PathBuf};
+
+/// Scheme of links that launch a ROM from the library, like `multiemu://rom/<rom id>`
+pub const URL_SCHEME: &str = "multiemu";
+
+/// Extensions we take over, along with the mime type they are registered as
+pub const ASSOCIATED_EXTENSIONS: &[(&str, &str)] = &[
+    ("nes", "application/x-nes-rom"),
+    ("gb", "application/x-gameboy-rom"),
+    ("ch8", "application/x-chip8-rom"),
+];
+
+#[derive(Clone, Debug, Subcommand)]
+pub enum AssociateAction {
+    #[command(about = Some("Opens ROMs and multiemu:// links with this executable"))]
+    Register,
+    #[command(about = Some("Undoes what register did"))]
+    Unregister,
+}
+
+pub fn associate_register() -> Result<(), Box<dyn Error>> {
+    let executable = env::current_exe()?;
+    tracing::info!("Registering {} as the ROM handler", executable.display());
+
+    platform::register(&executable)
+}
+
+pub fn associate_unregister() -> Result<(), Box<dyn Error>> {
+    platform::unregister()
+}
+
+/// Runs a helper program, only warning if it is missing since the files are already in place
+#[cfg(any(
+    all(target_family = "unix", not(target_os = "macos")),
+    target_os = "windows"
+))]
+fn run_helper(program: &str, arguments: &[&str]) -> Result<(), Box<dyn Error>> {
+    match std::process::Command::new(program).args(arguments).status() {
+        Ok(status) if status.success() => Ok(()),
+        Ok(status) => Err(format!("{} exited with {}", program, status).into()),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            tracing::warn!("{} is not installed, skipping it", program);
+            Ok(())
+        }
+        Err(err) => Err(err.into()),
+    }
+}
+
+#[cfg(all(target_family = "unix", not(target_os = "macos")))]
+mod platform {
+    use super::{run_helper, ASSOCIATED_EXTENSIONS, URL_SCHEME};
+    use std::{
+        error::Error,
+        fs::{create_dir_all, remove_file, write},
+        path::{Path, PathBuf},
+    };
+
+    const DESKTOP_ENTRY: &str = "multiemu.desktop";
+
+    fn data_directory() -> Result<PathBuf, Box<dyn Error>> {
+        dirs::data_dir().ok_or_else(|| "Could not find the user data directory".into())
+    }
+
+    fn mime_types() -> Vec<String> {
+        ASSOCIATED_EXTENSIONS
+            .iter()
+            .map(|(_, mime_type)| mime_type.to_string())
+            .chain([format!("x-scheme-handler/{}", URL_SCHEME)])
+            .collect()
+    }
+
+    pub fn register(executable: &Path) -> Result<(), Box<dyn Error>> {
+        let data_directory = data_directory()?;
+        let applications_directory = data_directory.join("applications");
+        let mime_directory = data_directory.join("mime");
+        let mime_packages_directory = mime_directory.join("packages");
+        create_dir_all(&applications_directory)?;
+        create_dir_all(&mime_packages_directory)?;
+
+        // Not every one of these has a type the system already knows about
+        let mut mime_package = String::from(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<mime-info xmlns=\"http://www.freedesktop.org/standards/shared-mime-info\">\n",
+        );
+        for (extension, mime_type) in ASSOCIATED_EXTENSIONS {
+            mime_package.push_str(&format!(
+                "  <mime-type type=\"{}\">\n    <glob pattern=\"*.{}\"/>\n  </mime-type>\n",
+                mime_type, extension
+            ));
+        }
+        mime_package.push_str("</mime-info>\n");
+        write(mime_packages_directory.join("multiemu.xml"), mime_package)?;
+
+        let mime_types = mime_types();
+        write(
+            applications_directory.join(DESKTOP_ENTRY),
+            format!(
+                "[Desktop Entry]\nType=Application\nName=MultiEMU\nExec=\"{}\" %u\nTerminal=false\nCategories=Game;Emulator;\nMimeType={};\n",
+                executable.display(),
+                mime_types.join(";")
+            ),
+        )?;
+
+        run_helper(
+            "update-mime-database",
+            &[mime_directory.to_string_lossy().as_ref()],
+        )?;
+        run_helper(
+            "update-desktop-database",
+            &[applications_directory.to_string_lossy().as_ref()],
+        )?;
+
+        let mut arguments = vec!["default", DESKTOP_ENTRY];
+        arguments.extend(mime_types.iter().map(String::as_str));
+        run_helper("xdg-mime", &arguments)
+    }
+
+    pub fn unregister() -> Result<(), Box<dyn Error>> {
+        let data_directory = data_directory()?;
+        let mime_directory = data_directory.join("mime");
+        let applications_directory = data_directory.join("applications");
+
+        for path in [
+            applications_directory.join(DESKTOP_ENTRY),
+            mime_directory.join("packages").join("multiemu.xml"),
+        ] {
+            if path.is_file() {
+                remove_file(path)?;
+            }
+        }
+
+        run_helper(
+            "update-mime-database",
+            &[mime_directory.to_string_lossy().as_ref()],
+        )?;
+        run_helper(
+            "update-desktop-database",
+            &[applications_directory.to_string_lossy().as_ref()],
+        )
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use super::{run_helper, ASSOCIATED_EXTENSIONS, URL_SCHEME};
+    use std::{error::Error, path::Path};
+
+    /// Everything goes under the current user, so no elevation is needed
+    const CLASSES_KEY: &str = r"HKCU\Software\Classes";
+    const PROGRAM_ID: &str = "MultiEMU.Rom";
+
+    fn set_value(key: &str, name: Option<&str>, value: &str) -> Result<(), Box<dyn Error>> {
+        let key = format!(r"{}\{}", CLASSES_KEY, key);
+        let mut arguments = vec!["add", key.as_str()];
+
+        match name {
+            Some(name) => arguments.extend(["/v", name]),
+            None => arguments.push("/ve"),
+        }
+
+        arguments.extend(["/d", value, "/f"]);
+        run_helper("reg", &arguments)
+    }
+
+    fn delete_key(key: &str) -> Result<(), Box<dyn Error>> {
+        let key = format!(r"{}\{}", CLASSES_KEY, key);
+
+        // Fails if it is already gone, which is what we wanted anyway
+        let _ = run_helper("reg", &["delete", key.as_str(), "/f"]);
+
+        Ok(())
+    }
+
+    pub fn register(executable: &Path) -> Result<(), Box<dyn Error>> {
+        let command = format!("\"{}\" \"%1\"", executable.display());
+
+        set_value(PROGRAM_ID, None, "MultiEMU ROM")?;
+        set_value(
+            &format!(r"{}\shell\open\command", PROGRAM_ID),
+            None,
+            &command,
+        )?;
+
+        for (extension, _) in ASSOCIATED_EXTENSIONS {
+            set_value(&format!(".{}", extension), None, PROGRAM_ID)?;
+        }
+
+        set_value(URL_SCHEME, None, "URL:MultiEMU")?;
+        set_value(URL_SCHEME, Some("URL Protocol"), "")?;
+        set_value(
+            &format!(r"{}\shell\open\command", URL_SCHEME),
+            None,
+            &command,
+        )
+    }
+
+    pub fn unregister() -> Result<(), Box<dyn Error>> {
+        // Other programs share the extension keys, and they point at nothing once our program id is gone
+        delete_key(PROGRAM_ID)?;
+        delete_key(URL_SCHEME)
+    }
+}
+
+/// macOS only takes associations from the Info.plist of an app bundle
+#[cfg(not(any(
+    all(target_family = "unix", not(target_os = "macos")),
+    target_os = "windows"
+)))]
+mod platform {
+    use std::{error::Error, path::Path};
+
+    pub fn register(_executable: &Path) -> Result<(), Box<dyn Error>> {
+        Err("File associations on this platform come from how the app is packaged".into())
+    }
+
+    pub fn unregister() -> Result<(), Box<dyn Error>> {
+        Err("File associations on this platform come from how the app is packaged".into())
+    }
+}
+
+/// The ROM id a `multiemu://rom/<rom id>` link points at
+pub fn parse_rom_url(url: &str) -> Option<&str> {
+    url.strip_prefix(URL_SCHEME)?
+        .strip_prefix("://rom/")
+        .map(|rom_id| rom_id.trim_end_matches('/'))
+}
+
+/// Desktop entries are allowed to hand over local files as `file://` urls instead of paths
+pub fn parse_file_url(url: &str) -> Option<PathBuf> {
+    let path = url.strip_prefix("file://")?.as_bytes();
+    let mut decoded = Vec::with_capacity(path.len());
+    let mut index = 0;
+
+    while index < path.len() {
+        let escaped = (path[index] == b'%')
+            .then(|| path.get(index + 1..index + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                index += 3;
+            }
+            None => {
+                decoded.push(path[index]);
+                index += 1;
+            }
+        }
+    }
+
+    String::from_utf8(decoded).ok().map(PathBuf::from)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn urls() {
+        assert_eq!(parse_rom_url("multiemu://rom/0123abcd/"), Some("0123abcd"));
+        assert_eq!(parse_rom_url("https://rom/0123abcd"), None);
+        assert_eq!(
+            parse_file_url("file:///home/user/My%20Games/game.nes"),
+            Some(PathBuf::from("/home/user/My Games/game.nes"))
+        );
+    }
+}
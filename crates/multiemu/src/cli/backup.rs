@@ -0,0 +1,37 @@
+use crate::{
+    backup::{export_backup, import_backup},
+    config::GLOBAL_CONFIG,
+    rom::manager::RomManager,
+};
+use clap::Subcommand;
+use std::{error::Error, path::PathBuf};
+
+#[derive(Clone, Debug, Subcommand)]
+pub enum BackupAction {
+    #[command(about = Some("Writes the config, database, saves and snapshots to a zip archive"))]
+    Export {
+        path: PathBuf,
+        /// Encrypts the archive with AES-256
+        #[clap(short, long)]
+        passphrase: Option<String>,
+    },
+    #[command(about = Some("Restores a backup archive on top of the current data"))]
+    Import {
+        path: PathBuf,
+        /// Needed if the archive was exported with one
+        #[clap(short, long)]
+        passphrase: Option<String>,
+    },
+}
+
+pub fn backup_export(path: PathBuf, passphrase: Option<String>) -> Result<(), Box<dyn Error>> {
+    export_backup(path, passphrase.as_deref()).map_err(|err| err as Box<dyn Error>)
+}
+
+pub fn backup_import(path: PathBuf, passphrase: Option<String>) -> Result<(), Box<dyn Error>> {
+    // Importing takes the config lock itself, so don't hold onto it
+    let database_file = GLOBAL_CONFIG.try_read()?.database_file.clone();
+    let rom_manager = RomManager::new(Some(&database_file))?;
+
+    import_backup(path, passphrase.as_deref(), &rom_manager).map_err(|err| err as Box<dyn Error>)
+}
@@ -17,7 +17,7 @@ pub fn database_native_import(paths: Vec<PathBuf>) -> Result<(), Box<dyn Error>>
 
     paths
         .into_par_iter()
-        .try_for_each(|path| rom_manager.load_database(path))
+        .try_for_each(|path| rom_manager.load_database(path).map(|_| ()))
         .map_err(|err| err as Box<dyn Error>)?;
 
     Ok(())
@@ -0,0 +1,84 @@
+use crate::{
+    component::input::EmulatedGamepadTypeId, config::GLOBAL_CONFIG, input::profile::InputProfile,
+    rom::system::GameSystem,
+};
+use clap::Subcommand;
+use std::{error::Error, path::PathBuf};
+
+#[derive(Clone, Debug, Subcommand)]
+pub enum InputAction {
+    #[command(about = Some("Writes a configured controller mapping to a shareable profile"))]
+    Export {
+        system: GameSystem,
+        path: PathBuf,
+        /// Only needed if the system has more than one kind of gamepad configured
+        #[clap(short, long)]
+        gamepad_type: Option<String>,
+    },
+    #[command(about = Some("Replaces a controller mapping with the one in a profile"))]
+    Import { path: PathBuf },
+}
+
+pub fn input_export(
+    system: GameSystem,
+    path: PathBuf,
+    gamepad_type: Option<String>,
+) -> Result<(), Box<dyn Error>> {
+    let global_config_guard = GLOBAL_CONFIG.try_read()?;
+
+    let configured_gamepad_types: Vec<_> = global_config_guard
+        .gamepad_configs
+        .get(&system)
+        .map(|gamepad_configs| gamepad_configs.keys().cloned().collect())
+        .unwrap_or_default();
+
+    let gamepad_type = match (gamepad_type, configured_gamepad_types.as_slice()) {
+        (Some(gamepad_type), _) => EmulatedGamepadTypeId::from(gamepad_type),
+        (None, [gamepad_type]) => gamepad_type.clone(),
+        (None, []) => {
+            return Err(format!("No controller mappings are configured for {}", system).into())
+        }
+        (None, gamepad_types) => {
+            return Err(format!(
+                "{} has multiple gamepad types configured, pick one of {:?}",
+                system, gamepad_types
+            )
+            .into())
+        }
+    };
+
+    let profile = InputProfile::from_config(&global_config_guard, system, gamepad_type.clone())
+        .ok_or_else(|| {
+            format!(
+                "No controller mapping configured for {} on {}",
+                gamepad_type, system
+            )
+        })?;
+
+    profile.save(&path)?;
+    tracing::info!(
+        "Exported {} mapping for {} to {}",
+        gamepad_type,
+        system,
+        path.display()
+    );
+
+    Ok(())
+}
+
+pub fn input_import(path: PathBuf) -> Result<(), Box<dyn Error>> {
+    let profile = InputProfile::load(&path)?;
+    let mut global_config_guard = GLOBAL_CONFIG.try_write()?;
+
+    tracing::info!(
+        "Importing {} mapping for {} from {}",
+        profile.gamepad_type,
+        profile.system,
+        path.display()
+    );
+
+    profile.apply_to_config(&mut global_config_guard);
+    global_config_guard.save()?;
+
+    Ok(())
+}
@@ -0,0 +1,58 @@
+use crate::{
+    machine::definition::MACHINE_DEFINITIONS,
+    rom::{manager::RomManager, system::GameSystem},
+};
+use clap::Subcommand;
+use std::{error::Error, sync::Arc};
+
+#[derive(Clone, Debug, Subcommand)]
+pub enum MachineAction {
+    #[command(about = Some("Builds machine definitions without any roms and reports likely mistakes in them"))]
+    Lint {
+        /// Every system with a definition if none are given
+        systems: Vec<GameSystem>,
+    },
+}
+
+pub fn machine_lint(systems: Vec<GameSystem>) -> Result<(), Box<dyn Error>> {
+    // Nothing gets loaded or saved, so there is no need for the real database
+    let rom_manager = Arc::new(RomManager::new(None)?);
+    let definitions = MACHINE_DEFINITIONS.read().unwrap();
+
+    let systems = if systems.is_empty() {
+        let mut systems: Vec<_> = definitions.systems().collect();
+        systems.sort();
+        systems
+    } else {
+        systems
+    };
+
+    let mut issue_count = 0;
+
+    for system in systems {
+        let definition = definitions
+            .get(system)
+            .ok_or_else(|| format!("{} is not supported by this emulator", system))?;
+
+        let machine = definition.construct(system, Vec::default(), rom_manager.clone())?;
+        let issues = machine.lint();
+
+        for issue in issues.iter() {
+            tracing::warn!("{}: {}", system, issue);
+        }
+        tracing::info!(
+            "{} definition of {} has {} issues",
+            definition.name(),
+            system,
+            issues.len()
+        );
+
+        issue_count += issues.len();
+    }
+
+    if issue_count != 0 {
+        return Err(format!("Found {} issues in machine definitions", issue_count).into());
+    }
+
+    Ok(())
+}
@@ -1,14 +1,35 @@
+use associate::{associate_register, associate_unregister, AssociateAction};
+use backup::{backup_export, backup_import, BackupAction};
 use clap::{Parser, Subcommand, ValueEnum};
 use database::{
     native::{database_native_import, NativeAction},
     nointro::{database_nointro_import, NoIntroAction},
     DatabaseAction,
 };
-use rom::{import::rom_import, run::rom_run, RomAction};
+use input::{input_export, input_import, InputAction};
+use machine::{machine_lint, MachineAction};
+use rom::{
+    debug::{
+        rom_debug_capture, rom_debug_compare, rom_debug_dump_memory, rom_debug_load_memory,
+        DebugAction,
+    },
+    import::rom_import,
+    inspect::rom_inspect,
+    notes::rom_export_notes,
+    run::rom_run,
+    test_all::rom_test_all,
+    RomAction, RomSpecification,
+};
+use state::{state_list, state_load, state_save, StateAction};
 use std::error::Error;
 
+pub mod associate;
+pub mod backup;
 pub mod database;
+pub mod input;
+pub mod machine;
 pub mod rom;
+pub mod state;
 
 // pub mod run_rom;
 
@@ -22,6 +43,17 @@ pub enum DatabaseType {
 pub struct Cli {
     #[clap(subcommand)]
     pub action: Option<CliAction>,
+    /// ROM to identify and launch right away, for opening files or multiemu:// links with multiemu
+    #[clap(value_name = "ROM")]
+    pub rom: Option<RomSpecification>,
+    /// Store all data next to the executable instead of in the user profile
+    ///
+    /// Creating a portable.txt next to the executable does the same
+    #[clap(long, global = true)]
+    pub portable: bool,
+    /// Profile to use instead of the one last selected in the menu
+    #[clap(long, global = true)]
+    pub profile: Option<String>,
 }
 
 #[derive(Clone, Debug, Subcommand)]
@@ -36,6 +68,31 @@ pub enum CliAction {
         #[clap(subcommand)]
         action: RomAction,
     },
+    #[command(about = Some("Commands relating to controller mappings"))]
+    Input {
+        #[clap(subcommand)]
+        action: InputAction,
+    },
+    #[command(about = Some("Commands relating to machine definitions"))]
+    Machine {
+        #[clap(subcommand)]
+        action: MachineAction,
+    },
+    #[command(about = Some("Commands relating to backing up and restoring user data"))]
+    Backup {
+        #[clap(subcommand)]
+        action: BackupAction,
+    },
+    #[command(about = Some("Commands relating to savestates, run headlessly"))]
+    State {
+        #[clap(subcommand)]
+        action: StateAction,
+    },
+    #[command(about = Some("Commands relating to opening ROMs and links from the OS"))]
+    Associate {
+        #[clap(subcommand)]
+        action: AssociateAction,
+    },
 }
 
 pub fn handle_cli(cli_action: CliAction) -> Result<(), Box<dyn Error>> {
@@ -60,8 +117,149 @@ pub fn handle_cli(cli_action: CliAction) -> Result<(), Box<dyn Error>> {
             RomAction::Run {
                 roms,
                 forced_system,
+                dump_av,
+            } => {
+                rom_run(roms, forced_system, dump_av)?;
+            }
+            RomAction::TestAll {
+                system,
+                frames,
+                report,
+            } => {
+                rom_test_all(system, frames, report)?;
+            }
+            RomAction::ExportNotes { path } => {
+                rom_export_notes(path)?;
+            }
+            RomAction::Inspect { rom } => {
+                rom_inspect(rom)?;
+            }
+            RomAction::Debug { action } => match action {
+                DebugAction::DumpMemory {
+                    rom,
+                    address_space,
+                    range,
+                    file,
+                    state,
+                    frames,
+                    forced_system,
+                } => {
+                    rom_debug_dump_memory(
+                        rom,
+                        address_space,
+                        range,
+                        file,
+                        state,
+                        frames,
+                        forced_system,
+                    )?;
+                }
+                DebugAction::LoadMemory {
+                    rom,
+                    address_space,
+                    address,
+                    file,
+                    state,
+                    save_as,
+                    forced_system,
+                } => {
+                    rom_debug_load_memory(
+                        rom,
+                        address_space,
+                        address,
+                        file,
+                        state,
+                        save_as,
+                        forced_system,
+                    )?;
+                }
+                DebugAction::Capture {
+                    rom,
+                    directory,
+                    screenshot_on,
+                    state_on,
+                    once,
+                    frames,
+                    state,
+                    forced_system,
+                } => {
+                    rom_debug_capture(
+                        rom,
+                        directory,
+                        screenshot_on,
+                        state_on,
+                        once,
+                        frames,
+                        state,
+                        forced_system,
+                    )?;
+                }
+                DebugAction::Compare {
+                    rom,
+                    references,
+                    heatmaps,
+                    frames,
+                    state,
+                    forced_system,
+                } => {
+                    rom_debug_compare(rom, references, heatmaps, frames, state, forced_system)?;
+                }
+            },
+        },
+        CliAction::Input { action } => match action {
+            InputAction::Export {
+                system,
+                path,
+                gamepad_type,
+            } => {
+                input_export(system, path, gamepad_type)?;
+            }
+            InputAction::Import { path } => {
+                input_import(path)?;
+            }
+        },
+        CliAction::Machine { action } => match action {
+            MachineAction::Lint { systems } => {
+                machine_lint(systems)?;
+            }
+        },
+        CliAction::Backup { action } => match action {
+            BackupAction::Export { path, passphrase } => {
+                backup_export(path, passphrase)?;
+            }
+            BackupAction::Import { path, passphrase } => {
+                backup_import(path, passphrase)?;
+            }
+        },
+        CliAction::State { action } => match action {
+            StateAction::Save {
+                rom,
+                name,
+                frames,
+                forced_system,
+                trace_memory,
+            } => {
+                state_save(rom, name, frames, forced_system, trace_memory)?;
+            }
+            StateAction::Load {
+                rom,
+                name,
+                frames,
+                save_as,
+                trace_memory,
             } => {
-                rom_run(roms, forced_system)?;
+                state_load(rom, name, frames, save_as, trace_memory)?;
+            }
+            StateAction::List { rom } => {
+                state_list(rom)?;
+            }
+        },
+        CliAction::Associate { action } => match action {
+            AssociateAction::Register => {
+                associate_register()?;
+            }
+            AssociateAction::Unregister => {
+                associate_unregister()?;
             }
         },
     }
@@ -0,0 +1,253 @@
+use crate::{
+    cli::state::{headless_machine, library, rom_system, run_frames, save},
+    machine::{serialization::Savestate, Machine},
+    memory::{parse_address, parse_address_range, AddressSpaceId},
+    rom::{id::RomId, system::GameSystem},
+    runtime::capture::{CaptureKind, CaptureRule, CaptureTrigger},
+};
+use clap::Subcommand;
+use std::{
+    error::Error,
+    fs::File,
+    io::{BufReader, BufWriter},
+    ops::Range,
+    path::PathBuf,
+};
+
+#[derive(Clone, Debug, Subcommand)]
+pub enum DebugAction {
+    #[command(about = Some("Writes a range of an address space to a file without side effects, unmapped bytes are written as zero"))]
+    DumpMemory {
+        rom: RomId,
+        address_space: AddressSpaceId,
+        /// Like 0x0000..0x0800, end exclusive
+        #[clap(value_parser = parse_address_range)]
+        range: Range<usize>,
+        file: PathBuf,
+        /// State to load before dumping instead of booting fresh
+        #[clap(long)]
+        state: Option<String>,
+        /// How long to run before dumping
+        #[clap(long, default_value_t = 0)]
+        frames: u32,
+        #[clap(short, long)]
+        forced_system: Option<GameSystem>,
+    },
+    #[command(about = Some("Writes a file into an address space through the normal write path"))]
+    LoadMemory {
+        rom: RomId,
+        address_space: AddressSpaceId,
+        #[clap(value_parser = parse_address)]
+        address: usize,
+        file: PathBuf,
+        #[clap(long)]
+        state: Option<String>,
+        /// Name to save the state under once loaded, since otherwise the result is thrown away
+        #[clap(long)]
+        save_as: Option<String>,
+        #[clap(short, long)]
+        forced_system: Option<GameSystem>,
+    },
+    #[command(about = Some("Runs headlessly, taking screenshots or states whenever something happens in the machine"))]
+    Capture {
+        rom: RomId,
+        directory: PathBuf,
+        /// Like write:0:0x0200 or frame:120, may be given more than once
+        #[clap(long)]
+        screenshot_on: Vec<CaptureTrigger>,
+        #[clap(long)]
+        state_on: Vec<CaptureTrigger>,
+        /// Only capture the first time each trigger fires
+        #[clap(long)]
+        once: bool,
+        #[clap(long, default_value_t = 600)]
+        frames: u32,
+        #[clap(long)]
+        state: Option<String>,
+        #[clap(short, long)]
+        forced_system: Option<GameSystem>,
+    },
+    #[command(about = Some("Runs headlessly, diffing every frame against reference frames and writing heatmaps of where they differ"))]
+    Compare {
+        rom: RomId,
+        /// Named like frame_<n>_display_<d>.png, counted from the first frame run
+        references: PathBuf,
+        /// Where the heatmaps go, a diff directory inside of the references if not given
+        #[clap(long)]
+        heatmaps: Option<PathBuf>,
+        #[clap(long, default_value_t = 600)]
+        frames: u32,
+        #[clap(long)]
+        state: Option<String>,
+        #[clap(short, long)]
+        forced_system: Option<GameSystem>,
+    },
+}
+
+/// Either loads the state or boots the ROM fresh
+fn debug_machine(
+    rom_id: RomId,
+    state: Option<String>,
+    forced_system: Option<GameSystem>,
+) -> Result<Machine, Box<dyn Error>> {
+    let rom_manager = library()?;
+
+    match state {
+        Some(state) => {
+            let savestate = Savestate::load(Savestate::path(rom_id, &state))?;
+            let mut machine = headless_machine(
+                savestate.user_specified_roms.clone(),
+                savestate.system,
+                rom_manager,
+            )?;
+            machine.restore_savestate(savestate)?;
+
+            Ok(machine)
+        }
+        None => {
+            let system = rom_system(&rom_manager, rom_id, forced_system)?;
+
+            Ok(headless_machine(vec![rom_id], system, rom_manager)?)
+        }
+    }
+}
+
+pub fn rom_debug_dump_memory(
+    rom_id: RomId,
+    address_space: AddressSpaceId,
+    range: Range<usize>,
+    path: PathBuf,
+    state: Option<String>,
+    frames: u32,
+    forced_system: Option<GameSystem>,
+) -> Result<(), Box<dyn Error>> {
+    let mut machine = debug_machine(rom_id, state, forced_system)?;
+    run_frames(&mut machine, frames, None)?;
+
+    let missing = machine.memory_translation_table.dump_range(
+        range.clone(),
+        address_space,
+        BufWriter::new(File::create(&path)?),
+    )?;
+
+    tracing::info!(
+        "Dumped {:#x?} of address space {} to {}",
+        range,
+        address_space,
+        path.display()
+    );
+
+    if missing != 0 {
+        tracing::warn!(
+            "{} bytes had nothing behind them and were written as zero",
+            missing
+        );
+    }
+
+    Ok(())
+}
+
+pub fn rom_debug_load_memory(
+    rom_id: RomId,
+    address_space: AddressSpaceId,
+    address: usize,
+    path: PathBuf,
+    state: Option<String>,
+    save_as: Option<String>,
+    forced_system: Option<GameSystem>,
+) -> Result<(), Box<dyn Error>> {
+    let machine = debug_machine(rom_id, state, forced_system)?;
+
+    let written = machine.memory_translation_table.restore_range(
+        address,
+        address_space,
+        BufReader::new(File::open(&path)?),
+    )?;
+
+    tracing::info!(
+        "Loaded {} bytes from {} to {:#x} of address space {}",
+        written,
+        path.display(),
+        address,
+        address_space
+    );
+
+    match save_as {
+        Some(save_as) => save(&machine, rom_id, &save_as),
+        None => Ok(()),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn rom_debug_capture(
+    rom_id: RomId,
+    directory: PathBuf,
+    screenshot_on: Vec<CaptureTrigger>,
+    state_on: Vec<CaptureTrigger>,
+    once: bool,
+    frames: u32,
+    state: Option<String>,
+    forced_system: Option<GameSystem>,
+) -> Result<(), Box<dyn Error>> {
+    let rules = Vec::from_iter(
+        screenshot_on
+            .into_iter()
+            .map(|trigger| (trigger, CaptureKind::Screenshot))
+            .chain(
+                state_on
+                    .into_iter()
+                    .map(|trigger| (trigger, CaptureKind::State)),
+            )
+            .map(|(trigger, kind)| CaptureRule {
+                trigger,
+                kind,
+                once,
+            }),
+    );
+
+    if rules.is_empty() {
+        return Err("Nothing to capture on, give --screenshot-on or --state-on".into());
+    }
+
+    let mut machine = debug_machine(rom_id, state, forced_system)?;
+    machine.start_capturing(directory, rules)?;
+    run_frames(&mut machine, frames, None)
+}
+
+pub fn rom_debug_compare(
+    rom_id: RomId,
+    references: PathBuf,
+    heatmaps: Option<PathBuf>,
+    frames: u32,
+    state: Option<String>,
+    forced_system: Option<GameSystem>,
+) -> Result<(), Box<dyn Error>> {
+    let heatmaps = heatmaps.unwrap_or_else(|| references.join("diff"));
+
+    let mut machine = debug_machine(rom_id, state, forced_system)?;
+    machine.start_comparing_frames(&references, &heatmaps)?;
+    run_frames(&mut machine, frames, None)?;
+
+    let summary = machine
+        .frame_comparison_summary()
+        .ok_or("The frame comparison stopped partway through")?;
+
+    tracing::info!(
+        "Compared {} frames, {} of them differed",
+        summary.compared_frames,
+        summary.differing_frames
+    );
+
+    match summary.first_difference {
+        Some(difference) => Err(format!(
+            "Frame {} first differed on display {} in {} of {} pixels, heatmaps are in {}",
+            difference.frame,
+            difference.display,
+            difference.differing_pixels,
+            difference.total_pixels,
+            heatmaps.display()
+        )
+        .into()),
+        None => Ok(()),
+    }
+}
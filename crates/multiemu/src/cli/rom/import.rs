@@ -1,11 +1,17 @@
 use crate::{
     config::{GlobalConfig, GLOBAL_CONFIG},
-    rom::{id::RomId, info::RomInfo, manager::RomManager},
+    rom::{
+        id::RomId,
+        info::RomInfo,
+        manager::RomManager,
+        storage::{self, RomStoragePolicy},
+    },
 };
 use rayon::iter::{ParallelBridge, ParallelIterator};
 use std::{
     error::Error,
     fs::{self, File},
+    io::Read,
     ops::Deref,
     path::{Path, PathBuf},
 };
@@ -83,10 +89,7 @@ fn process_file(
                         rom.system,
                         hash_string
                     );
-                    let internal_store_path = global_config.roms_directory.join(hash_string);
-                    let mut file = File::create(internal_store_path)?;
-
-                    std::io::copy(&mut zip_entry, &mut file)?;
+                    store_rom(global_config, hash, &mut zip_entry)?;
                 } else {
                     tracing::warn!(
                         "Could not identify ROM inside zip archive {} at {} with hash {}",
@@ -113,9 +116,10 @@ fn process_file(
             hash_string
         );
         let internal_store_path = global_config.roms_directory.join(hash_string);
-        let _ = fs::remove_file(&internal_store_path);
 
         if symlink {
+            remove_stored_rom(global_config, hash)?;
+
             #[cfg(unix)]
             std::os::unix::fs::symlink(path, internal_store_path)?;
 
@@ -125,7 +129,7 @@ fn process_file(
             #[cfg(not(any(unix, windows)))]
             panic!("Unsupported platform for symlinking");
         } else {
-            fs::copy(path, internal_store_path)?;
+            store_rom(global_config, hash, &mut File::open(path)?)?;
         }
     } else {
         tracing::warn!(
@@ -137,3 +141,36 @@ fn process_file(
 
     Ok(())
 }
+
+/// Copies a ROM into the store according to the configured storage policy
+fn store_rom(
+    global_config: &GlobalConfig,
+    hash: RomId,
+    rom: &mut impl Read,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    // Whichever form it was in before would shadow the new one on the next scan
+    remove_stored_rom(global_config, hash)?;
+
+    match global_config.rom_storage_policy {
+        RomStoragePolicy::Plain => {
+            std::io::copy(
+                rom,
+                &mut File::create(global_config.roms_directory.join(hash.to_string()))?,
+            )?;
+        }
+        RomStoragePolicy::Compressed => {
+            storage::store_compressed(hash, rom, &global_config.roms_directory)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Clears out both the plain and compressed copies of a ROM
+fn remove_stored_rom(
+    global_config: &GlobalConfig,
+    hash: RomId,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let _ = fs::remove_file(global_config.roms_directory.join(hash.to_string()));
+    storage::remove_compressed(hash, &global_config.roms_directory)
+}
@@ -0,0 +1,34 @@
+use super::RomSpecification;
+use crate::{
+    cli::state::library,
+    definitions::nes::cartidge,
+    rom::{header::RomHeader, manager::RomRequirement},
+};
+use std::{error::Error, fs::File};
+
+pub fn rom_inspect(rom: RomSpecification) -> Result<(), Box<dyn Error>> {
+    let file = match &rom {
+        RomSpecification::Path(path) => File::open(path)?,
+        RomSpecification::Id(rom_id) => library()?
+            .open(*rom_id, RomRequirement::Required)
+            .ok_or_else(|| format!("{} is not in the library", rom_id))?,
+    };
+
+    let Some(header) = RomHeader::read(file)? else {
+        return Err("No header this knows how to read".into());
+    };
+
+    tracing::info!("{} header", header.system());
+    for (name, value) in header.fields() {
+        tracing::info!("{}: {}", name, value);
+    }
+
+    if let RomHeader::Ines(header) = &header {
+        match cartidge::check_mapper(header) {
+            Ok(()) => tracing::info!("Mapper is supported"),
+            Err(err) => tracing::warn!("{}", err),
+        }
+    }
+
+    Ok(())
+}
@@ -1,9 +1,15 @@
+use super::associate::{parse_file_url, parse_rom_url};
 use crate::rom::{id::RomId, system::GameSystem};
 use clap::{Subcommand, ValueEnum};
+use debug::DebugAction;
 use std::{error::Error, path::PathBuf, str::FromStr};
 
+pub mod debug;
 pub mod import;
+pub mod inspect;
+pub mod notes;
 pub mod run;
+pub mod test_all;
 
 #[derive(Debug, Clone)]
 pub enum RomSpecification {
@@ -15,7 +21,11 @@ impl FromStr for RomSpecification {
     type Err = Box<dyn Error + Send + Sync>;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let path = PathBuf::from(s);
+        if let Some(rom_id) = parse_rom_url(s) {
+            return Ok(RomSpecification::Id(RomId::from_str(rom_id)?));
+        }
+
+        let path = parse_file_url(s).unwrap_or_else(|| PathBuf::from(s));
         if path.is_file() {
             return Ok(RomSpecification::Path(path));
         }
@@ -40,5 +50,28 @@ pub enum RomAction {
         roms: Vec<RomSpecification>,
         #[clap(short, long)]
         forced_system: Option<GameSystem>,
+        /// Write every emulated frame to this directory as raw video, paced by emulated time so none are dropped
+        #[clap(long)]
+        dump_av: Option<PathBuf>,
+    },
+    #[command(about = Some("Runs every ROM in the library for a system headlessly and writes a compatibility report"))]
+    TestAll {
+        #[clap(short, long)]
+        system: GameSystem,
+        /// How long to run each ROM for
+        #[clap(long, default_value_t = 600)]
+        frames: u32,
+        /// Where to write the report instead of the compatibility directory of the profile, which the menu shows
+        #[clap(long)]
+        report: Option<PathBuf>,
+    },
+    #[command(about = Some("Writes the rating and notes of every ROM that has them, as set in the library view"))]
+    ExportNotes { path: PathBuf },
+    #[command(about = Some("Shows what the header of a ROM says, checking its checksums"))]
+    Inspect { rom: RomSpecification },
+    #[command(about = Some("Commands for poking at a machine headlessly"))]
+    Debug {
+        #[clap(subcommand)]
+        action: DebugAction,
     },
 }
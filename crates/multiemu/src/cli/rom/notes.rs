@@ -0,0 +1,12 @@
+use crate::{config::GLOBAL_CONFIG, rom::manager::RomManager};
+use std::{error::Error, path::PathBuf};
+
+pub fn rom_export_notes(path: PathBuf) -> Result<(), Box<dyn Error>> {
+    let global_config_guard = GLOBAL_CONFIG.try_read()?;
+    let rom_manager = RomManager::new(Some(&global_config_guard.database_file))?;
+
+    let count = rom_manager.export_rom_notes(&path)?;
+    tracing::info!("Exported {} notes to {}", count, path.display());
+
+    Ok(())
+}
@@ -10,12 +10,14 @@ use crate::{
 use std::{
     error::Error,
     fs::{create_dir_all, File},
+    path::PathBuf,
     sync::Arc,
 };
 
 pub fn rom_run(
     roms: Vec<RomSpecification>,
     forced_system: Option<GameSystem>,
+    dump_av: Option<PathBuf>,
 ) -> Result<(), Box<dyn Error>> {
     let global_config_guard = GLOBAL_CONFIG.read().unwrap();
     let rom_manager = RomManager::new(Some(&global_config_guard.database_file))?;
@@ -30,13 +32,27 @@ pub fn rom_run(
         match rom {
             RomSpecification::Id(rom_id) => user_specified_roms.push(rom_id),
             RomSpecification::Path(rom_path) => {
+                let mut rom_file = File::open(&rom_path)?;
+                let rom_id = RomId::from_read(&mut rom_file);
+
+                user_specified_roms.push(rom_id);
+                rom_manager.rom_paths.insert(rom_id, rom_path.clone());
+
+                // The database knows better than guessing from the file, and has a proper name for it
+                if let Some(rom_info) = transaction.get().primary::<RomInfo>(rom_id)? {
+                    tracing::info!(
+                        "{} is {} for {}",
+                        rom_path.display(),
+                        rom_info.name.as_deref().unwrap_or("an unnamed ROM"),
+                        rom_info.system
+                    );
+                    continue;
+                }
+
                 let Some(system) = GameSystem::guess(&rom_path) else {
                     return Err(format!("{} is not a valid rom", rom_path.display()).into());
                 };
 
-                let mut rom_file = File::open(&rom_path)?;
-                let rom_id = RomId::from_read(&mut rom_file);
-
                 let rom_info = RomInfo {
                     name: Some(rom_path.to_string_lossy().to_string()),
                     id: rom_id,
@@ -44,7 +60,6 @@ pub fn rom_run(
                     region: None,
                 };
 
-                user_specified_roms.push(rom_id);
                 if let Err(e) = transaction.insert(rom_info) {
                     if let native_db::db_type::Error::DuplicateKey { key_name: _ } = e {
                         tracing::warn!(
@@ -54,15 +69,18 @@ pub fn rom_run(
                         return Err(e.into());
                     }
                 }
-
-                rom_manager.rom_paths.insert(rom_id, rom_path);
             }
         }
     }
 
     transaction.commit()?;
 
-    let graphics_setting = global_config_guard.graphics_setting;
+    // Dumping reads the framebuffers back, which only software rendering can do
+    let graphics_setting = if dump_av.is_some() {
+        GraphicsSettings::Software
+    } else {
+        global_config_guard.graphics_setting
+    };
     drop(global_config_guard);
     let rom_manager = Arc::new(rom_manager);
 
@@ -72,6 +90,7 @@ pub fn rom_run(
                 user_specified_roms,
                 forced_system,
                 rom_manager,
+                dump_av,
             );
         }
         #[cfg(graphics_vulkan)]
@@ -82,6 +101,7 @@ pub fn rom_run(
                 user_specified_roms,
                 forced_system,
                 rom_manager,
+                dump_av,
             );
         }
     }
@@ -0,0 +1,113 @@
+use crate::{
+    config::GLOBAL_CONFIG,
+    machine::definition::MACHINE_DEFINITIONS,
+    rom::{
+        compatibility::{test_rom, CompatibilityReport, CompatibilityResult},
+        info::RomInfo,
+        manager::RomManager,
+        system::GameSystem,
+    },
+};
+use std::{collections::HashMap, error::Error, fs::create_dir_all, path::PathBuf, sync::Arc};
+
+pub fn rom_test_all(
+    system: GameSystem,
+    frames: u32,
+    report_path: Option<PathBuf>,
+) -> Result<(), Box<dyn Error>> {
+    let global_config_guard = GLOBAL_CONFIG.read().unwrap();
+    let mut rom_manager = RomManager::new(Some(&global_config_guard.database_file))?;
+    create_dir_all(&global_config_guard.roms_directory)?;
+    rom_manager.load_roms(&global_config_guard.roms_directory)?;
+    drop(global_config_guard);
+
+    let definition = MACHINE_DEFINITIONS
+        .read()
+        .unwrap()
+        .get(system)
+        .ok_or_else(|| format!("{} is not supported by this emulator", system))?;
+
+    let mut roms = Vec::new();
+    let transaction = rom_manager.rom_information.r_transaction()?;
+
+    for rom_id in rom_manager.rom_paths.iter().map(|entry| *entry.key()) {
+        if let Some(rom_info) = transaction.get().primary::<RomInfo>(rom_id)? {
+            if rom_info.system == system {
+                roms.push(rom_info);
+            }
+        }
+    }
+    drop(transaction);
+    roms.sort_by(|a, b| a.name.cmp(&b.name).then(a.id.cmp(&b.id)));
+
+    if roms.is_empty() {
+        return Err(format!("There are no {} ROMs in the library", system).into());
+    }
+
+    let report_path = report_path.unwrap_or_else(|| CompatibilityReport::path(system));
+    // Kept around to point out ROMs that look different since the last run
+    let previous_hashes: HashMap<_, _> = CompatibilityReport::load(&report_path)
+        .map(|report| {
+            report
+                .results
+                .into_iter()
+                .map(|result| (result.rom_id, result.frame_hash))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let rom_count = roms.len();
+    let rom_manager = Arc::new(rom_manager);
+    let mut report = CompatibilityReport {
+        system,
+        frames,
+        results: Vec::with_capacity(rom_count),
+    };
+
+    for (index, rom_info) in roms.into_iter().enumerate() {
+        let name = rom_info
+            .name
+            .clone()
+            .unwrap_or_else(|| rom_info.id.to_string());
+        let (status, frame_hash) = test_rom(
+            definition.as_ref(),
+            system,
+            rom_info.id,
+            rom_manager.clone(),
+            frames,
+        );
+
+        tracing::info!("[{}/{}] {}: {}", index + 1, rom_count, name, status);
+
+        if let Some(previous_hash) = previous_hashes.get(&rom_info.id) {
+            if *previous_hash != frame_hash {
+                tracing::warn!("{} shows something different than last time", name);
+            }
+        }
+
+        report.results.push(CompatibilityResult {
+            rom_id: rom_info.id,
+            name: rom_info.name,
+            status,
+            frame_hash,
+        });
+    }
+
+    report.save(&report_path)?;
+
+    let summary = report.summary();
+    tracing::info!(
+        "{} working, {} blank screen, {} faulted, {} crashed, {} unsupported, report written to {}",
+        summary.working,
+        summary.blank_screen,
+        summary.faulted,
+        summary.crashed,
+        summary.unsupported,
+        report_path.display()
+    );
+    for (feature, count) in report.missing_features() {
+        tracing::info!("{} ROMs need {}", count, feature);
+    }
+
+    Ok(())
+}
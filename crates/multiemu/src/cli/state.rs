@@ -0,0 +1,189 @@
+use crate::{
+    config::GLOBAL_CONFIG,
+    machine::{serialization::Savestate, Machine, MachineBuildError},
+    memory::AccessTraceSink,
+    rom::{id::RomId, info::RomInfo, manager::RomManager, system::GameSystem},
+    runtime::rendering_backend::DisplayComponentInitializationData,
+};
+use clap::Subcommand;
+use std::{error::Error, fs::create_dir_all, path::PathBuf, sync::Arc};
+
+#[derive(Clone, Debug, Subcommand)]
+pub enum StateAction {
+    #[command(about = Some("Boots a ROM from the library headlessly, runs it and saves a state"))]
+    Save {
+        rom: RomId,
+        /// Name to save the state under
+        name: String,
+        /// How long to run before saving
+        #[clap(long, default_value_t = 0)]
+        frames: u32,
+        /// System to run the ROM as instead of the one the database knows it as
+        #[clap(short, long)]
+        forced_system: Option<GameSystem>,
+        /// File to log every memory access made while running to
+        #[clap(long)]
+        trace_memory: Option<PathBuf>,
+    },
+    #[command(about = Some("Loads a state headlessly and keeps running it, saving again if asked"))]
+    Load {
+        rom: RomId,
+        name: String,
+        #[clap(long, default_value_t = 0)]
+        frames: u32,
+        /// Name to save the state under once done running
+        #[clap(long)]
+        save_as: Option<String>,
+        #[clap(long)]
+        trace_memory: Option<PathBuf>,
+    },
+    #[command(about = Some("Lists the states a ROM has"))]
+    List { rom: RomId },
+}
+
+pub(crate) fn library() -> Result<Arc<RomManager>, Box<dyn Error>> {
+    let global_config_guard = GLOBAL_CONFIG.read().unwrap();
+    let mut rom_manager = RomManager::new(Some(&global_config_guard.database_file))?;
+    create_dir_all(&global_config_guard.roms_directory)?;
+    rom_manager.load_roms(&global_config_guard.roms_directory)?;
+
+    Ok(Arc::new(rom_manager))
+}
+
+/// Builds a machine with nothing to show it on
+pub(crate) fn headless_machine(
+    user_specified_roms: Vec<RomId>,
+    system: GameSystem,
+    rom_manager: Arc<RomManager>,
+) -> Result<Machine, MachineBuildError> {
+    let machine = Machine::from_system(user_specified_roms, rom_manager, system)?;
+
+    for component_info in machine.display_components() {
+        component_info
+            .component
+            .set_display_data(DisplayComponentInitializationData::Software);
+    }
+
+    Ok(machine)
+}
+
+/// The system the database knows a ROM as, unless the user forced one
+pub(crate) fn rom_system(
+    rom_manager: &RomManager,
+    rom_id: RomId,
+    forced_system: Option<GameSystem>,
+) -> Result<GameSystem, Box<dyn Error>> {
+    match forced_system {
+        Some(system) => Ok(system),
+        None => Ok(rom_manager
+            .rom_information
+            .r_transaction()?
+            .get()
+            .primary::<RomInfo>(rom_id)?
+            .map(|rom_info| rom_info.system)
+            .ok_or_else(|| format!("{} is not in the database, try forcing a system", rom_id))?),
+    }
+}
+
+pub(crate) fn run_frames(
+    machine: &mut Machine,
+    frames: u32,
+    trace_memory: Option<PathBuf>,
+) -> Result<(), Box<dyn Error>> {
+    let frame_duration = machine.frame_duration();
+
+    if let Some(path) = &trace_memory {
+        machine
+            .memory_translation_table
+            .set_access_trace(Some(AccessTraceSink::file(path)?));
+    }
+
+    let mut result = Ok(());
+    for frame in 0..frames {
+        machine.run_frame(frame_duration);
+
+        if let Some(fault) = machine.fault() {
+            result = Err(format!("Machine faulted on frame {}: {}", frame, fault).into());
+            break;
+        }
+    }
+
+    // Stopping flushes the file, which we want even when it faulted since that is the interesting part
+    if let Some(path) = trace_memory {
+        machine.memory_translation_table.set_access_trace(None);
+        tracing::info!("Wrote memory access trace to {}", path.display());
+    }
+
+    result
+}
+
+pub(crate) fn save(machine: &Machine, rom_id: RomId, name: &str) -> Result<(), Box<dyn Error>> {
+    let path = Savestate::path(rom_id, name);
+    machine.savestate().save(&path)?;
+    tracing::info!("Saved state {} to {}", name, path.display());
+
+    Ok(())
+}
+
+pub fn state_save(
+    rom_id: RomId,
+    name: String,
+    frames: u32,
+    forced_system: Option<GameSystem>,
+    trace_memory: Option<PathBuf>,
+) -> Result<(), Box<dyn Error>> {
+    let rom_manager = library()?;
+    let system = rom_system(&rom_manager, rom_id, forced_system)?;
+
+    let mut machine = headless_machine(vec![rom_id], system, rom_manager)?;
+    run_frames(&mut machine, frames, trace_memory)?;
+
+    save(&machine, rom_id, &name)
+}
+
+pub fn state_load(
+    rom_id: RomId,
+    name: String,
+    frames: u32,
+    save_as: Option<String>,
+    trace_memory: Option<PathBuf>,
+) -> Result<(), Box<dyn Error>> {
+    let rom_manager = library()?;
+    let savestate = Savestate::load(Savestate::path(rom_id, &name))?;
+
+    let mut machine = headless_machine(
+        savestate.user_specified_roms.clone(),
+        savestate.system,
+        rom_manager,
+    )?;
+    machine.restore_savestate(savestate)?;
+    run_frames(&mut machine, frames, trace_memory)?;
+
+    tracing::info!("Ran state {} for {} frames", name, frames);
+
+    match save_as {
+        Some(save_as) => save(&machine, rom_id, &save_as),
+        None => Ok(()),
+    }
+}
+
+pub fn state_list(rom_id: RomId) -> Result<(), Box<dyn Error>> {
+    let savestates = Savestate::list(rom_id)?;
+
+    if savestates.is_empty() {
+        tracing::info!("{} has no states", rom_id);
+    }
+
+    for (name, savestate) in savestates {
+        tracing::info!(
+            "{}: {} made at {} (unix time) by version {}, components {}",
+            name,
+            savestate.system,
+            savestate.created,
+            savestate.emulator_version,
+            savestate.components.join(", ")
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,16 @@
+use super::Component;
+
+/// What one audio component made over a frame, see [crate::machine::Machine::frame_audio]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AudioChunk {
+    pub sample_rate: u32,
+    pub samples: Vec<f32>,
+}
+
+/// Anything that shapes the sound, like phase accumulators, noise generators and envelopes, belongs in the snapshot so
+/// a loaded state sounds the same every time. Samples still queued belong to the state being left, so loading a
+/// snapshot drops them, see [crate::runtime::audio::AdaptiveAudioBuffer::flush] for the host side of that
+pub trait AudioComponent: Component {
+    /// Mono samples produced since the last call, oldest first, at the rate given to [crate::machine::ComponentBuilder::set_audio]
+    fn drain_samples(&self) -> Vec<f32>;
+}
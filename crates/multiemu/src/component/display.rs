@@ -6,4 +6,6 @@ use crate::runtime::rendering_backend::{
 pub trait DisplayComponent: Component {
     fn set_display_data(&self, display_data: DisplayComponentInitializationData);
     fn get_framebuffer(&self) -> DisplayComponentFramebuffer;
+    /// Shows the current state again, for when it changed without the component running, like while rewinding
+    fn redraw(&self) {}
 }
@@ -0,0 +1,8 @@
+use super::Component;
+use std::time::Duration;
+
+/// Components that only run when they asked to be woken, see [crate::scheduler::EventQueue]
+pub trait EventDrivenComponent: Component {
+    /// `now` is the emulated time of the tick the wakeup landed on
+    fn wake(&self, now: Duration);
+}
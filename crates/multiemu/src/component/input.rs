@@ -17,6 +17,12 @@ impl EmulatedGamepadTypeId {
     }
 }
 
+impl From<String> for EmulatedGamepadTypeId {
+    fn from(id: String) -> Self {
+        Self(Cow::Owned(id))
+    }
+}
+
 impl AsRef<str> for EmulatedGamepadTypeId {
     fn as_ref(&self) -> &str {
         &self.0
@@ -0,0 +1,44 @@
+use super::Component;
+use std::fmt::Display;
+
+/// Something a debugger can show, numbers are shown in hex at their full width
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DebugValue {
+    Flag(bool),
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+    Text(String),
+}
+
+impl Display for DebugValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DebugValue::Flag(value) => write!(f, "{}", if *value { "Set" } else { "Clear" }),
+            DebugValue::U8(value) => write!(f, "{:#04x}", value),
+            DebugValue::U16(value) => write!(f, "{:#06x}", value),
+            DebugValue::U32(value) => write!(f, "{:#010x}", value),
+            DebugValue::U64(value) => write!(f, "{:#018x}", value),
+            DebugValue::Text(value) => value.fmt(f),
+        }
+    }
+}
+
+/// For components with insides worth looking at, like the registers of a processor
+pub trait IntrospectableComponent: Component {
+    /// Everything worth showing by name, in the order it should be shown
+    fn debug_state(&self) -> Vec<(String, DebugValue)>;
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn values_show_at_full_width() {
+        assert_eq!(DebugValue::U8(0xa).to_string(), "0x0a");
+        assert_eq!(DebugValue::U16(0xa).to_string(), "0x000a");
+        assert_eq!(DebugValue::Flag(true).to_string(), "Set");
+    }
+}
@@ -0,0 +1,52 @@
+use super::Component;
+use crate::rom::id::RomId;
+use serde::{Deserialize, Serialize};
+use std::{borrow::Cow, error::Error, fmt::Display};
+use thiserror::Error;
+
+#[derive(Serialize, Deserialize, Debug, Hash, PartialEq, Eq, PartialOrd, Ord, Clone)]
+#[serde(transparent)]
+pub struct MediaSlotId(Cow<'static, str>);
+
+impl MediaSlotId {
+    pub const fn new(id: &'static str) -> Self {
+        Self(Cow::Borrowed(id))
+    }
+}
+
+impl AsRef<str> for MediaSlotId {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Display for MediaSlotId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MediaSlotMetadata {
+    /// If the machine has to be reset after the media in this slot changes, like pulling a cartridge
+    pub requires_reset: bool,
+}
+
+pub trait MediaComponent: Component {
+    /// Inserts a ROM into the slot, or ejects whatever is there if [None]
+    ///
+    /// Whatever was inserted before should stay put if this fails
+    fn swap_media(&self, slot: &MediaSlotId, rom_id: Option<RomId>) -> Result<(), MediaSwapError>;
+}
+
+#[derive(Error, Debug)]
+pub enum MediaSwapError {
+    #[error("This machine has no media slot named {0}")]
+    UnknownSlot(MediaSlotId),
+    #[error("ROM {0} is not available")]
+    MissingRom(RomId),
+    #[error("Could not read the media: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Could not understand the media: {0}")]
+    InvalidMedia(Box<dyn Error + Send + Sync>),
+}
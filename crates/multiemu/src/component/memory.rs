@@ -21,7 +21,8 @@ pub trait MemoryComponent: Component {
         errors: &mut RangeMap<usize, WriteMemoryRecord>,
     );
 
-    // Its like read_memory but without the restriction on the size of the buffer and it cannot cause a state change
+    // Its like read_memory but without the restriction on the size of the buffer and it cannot cause a state change,
+    // so anything where reading does something has to override this
     fn preview_memory(
         &self,
         address: usize,
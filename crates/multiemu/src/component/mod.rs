@@ -1,34 +1,120 @@
-use crate::machine::ComponentBuilder;
+use crate::machine::{fault::FaultReporter, ComponentBuilder, MachineBuildError};
 use crate::memory::MemoryTranslationTable;
 use downcast_rs::DowncastSync;
 use serde::{Deserialize, Serialize};
 use std::any::Any;
 use std::fmt::Debug;
 use std::sync::Arc;
+use thiserror::Error;
 
+pub mod audio;
 pub mod display;
+pub mod event_driven;
 pub mod input;
+pub mod introspectable;
+pub mod media;
 pub mod memory;
 pub mod schedulable;
 
+/// How much of the machine a reset puts back, see [crate::machine::Machine::reset]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ResetKind {
+    /// Like pressing the reset button, memory keeps what it had
+    Soft,
+    /// Like turning it off and on again
+    Hard,
+}
+
 // Basic supertrait for all components
 pub trait Component: Any + Debug + Send + Sync + DowncastSync {
-    fn reset(&self) {}
+    fn reset(&self, _kind: ResetKind) {}
     fn save_snapshot(&self) -> rmpv::Value {
         rmpv::Value::Nil
     }
-    fn load_snapshot(&self, _snapshot: rmpv::Value) {}
+    /// Bump this whenever what [Component::save_snapshot] writes changes shape
+    fn snapshot_version(&self) -> u32 {
+        0
+    }
+    /// Turns a snapshot from an older [Component::snapshot_version] into the current shape
+    fn migrate_snapshot(
+        &self,
+        version: u32,
+        _snapshot: rmpv::Value,
+    ) -> Result<rmpv::Value, SnapshotError> {
+        Err(SnapshotError::UnsupportedVersion {
+            found: version,
+            current: self.snapshot_version(),
+        })
+    }
+    fn load_snapshot(&self, _snapshot: rmpv::Value) -> Result<(), SnapshotError> {
+        Ok(())
+    }
+    /// Counts up whenever what [Component::save_snapshot] would return changes, so unchanged state can be reused
+    ///
+    /// [None] means it has to be snapshotted every time
+    fn snapshot_generation(&self) -> Option<u64> {
+        None
+    }
     fn set_memory_translation_table(&self, _memory_translation_table: Arc<MemoryTranslationTable>) {
     }
+    /// Components that can hit unrecoverable errors should hold onto this and report them instead of panicking
+    fn set_fault_reporter(&self, _fault_reporter: Arc<FaultReporter>) {}
+    /// The machine is being torn down, write out or let go of anything that lives outside of it
+    fn shutdown(&self) {}
+}
+
+/// Why a component could not take a snapshot it was handed
+#[derive(Error, Debug)]
+pub enum SnapshotError {
+    #[error("Snapshot is not laid out how this component expects: {0}")]
+    Decode(#[from] rmpv::ext::Error),
+    #[error("Snapshot holds {found} bytes where this component has {expected}")]
+    SizeMismatch { expected: usize, found: usize },
+    #[error("Snapshot is version {found} and there is no way to get it to version {current}")]
+    UnsupportedVersion { found: u32, current: u32 },
 }
 
-// An initializable component
+/// An initializable component
+///
+/// ```
+/// use multiemu::{
+///     component::{Component, FromConfig},
+///     machine::{ComponentBuilder, MachineBuildError},
+/// };
+/// use std::sync::Mutex;
+///
+/// #[derive(Debug)]
+/// struct Latch {
+///     value: Mutex<u8>,
+/// }
+///
+/// impl Component for Latch {}
+///
+/// impl FromConfig for Latch {
+///     // The value the latch powers on holding
+///     type Config = u8;
+///
+///     fn from_config(
+///         component_builder: &mut ComponentBuilder<Self>,
+///         config: Self::Config,
+///     ) -> Result<(), MachineBuildError> {
+///         component_builder.set_component(Self {
+///             value: Mutex::new(config),
+///         });
+///
+///         Ok(())
+///     }
+/// }
+/// ```
 pub trait FromConfig: Component + Sized {
     type Config: Debug;
 
-    /// Make a new component from the config
-    fn from_config(component_builder: &mut ComponentBuilder<Self>, config: Self::Config);
+    /// Make a new component from the config, failing if other components it needs are missing or not what it expects
+    fn from_config(
+        component_builder: &mut ComponentBuilder<Self>,
+        config: Self::Config,
+    ) -> Result<(), MachineBuildError>;
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct ComponentId(pub u16);
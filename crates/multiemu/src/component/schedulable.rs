@@ -1,5 +1,16 @@
 use super::Component;
+use std::time::Duration;
+
+/// Where a run falls in emulated time, for components that need to know what time it is like timers and logs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RunContext {
+    /// Emulated time since the machine started, as of the start of this run
+    pub timestamp: Duration,
+    /// How many times the component ran before this run, counted at its own frequency so each component has its own
+    /// clock domain
+    pub cycles: u64,
+}
 
 pub trait SchedulableComponent: Component {
-    fn run(&self, period: u64);
+    fn run(&self, period: u64, context: RunContext);
 }
@@ -1,10 +1,27 @@
+#[cfg(platform_desktop)]
+use crate::rom::storage::RomStoragePolicy;
 use crate::{
     component::input::EmulatedGamepadTypeId,
+    definitions::misc::memory::persistent::DEFAULT_FLUSH_INTERVAL,
     input::{
         hotkey::{Hotkey, DEFAULT_HOTKEYS},
+        manager::InputManager,
+        paste::DEFAULT_PASTE_KEY_FRAMES,
+        registry::split_keyboard_bindings,
         Input,
     },
-    rom::system::GameSystem,
+    machine::rewind::DEFAULT_REWIND_BUDGET,
+    profile::PROFILE_DIRECTORY,
+    rom::{id::RomId, system::GameSystem},
+    runtime::{
+        frame_presentation::{MotionSmoothing, RefreshRateSync},
+        input_display::InputDisplayConfig,
+        power_profile::PowerProfile,
+        video_filter::{VideoFilter, VideoFilterParameters},
+        video_preset::{ScalingMode, VideoPreset},
+    },
+    scheduler::{Pacing, DEFAULT_MAX_CATCH_UP, DEFAULT_WATCHDOG_BUDGET},
+    tas::DEFAULT_GREENZONE_BUDGET,
 };
 use indexmap::IndexMap;
 use ron::ser::PrettyConfig;
@@ -15,23 +32,45 @@ use serde_with::DefaultOnError;
 use std::{
     collections::BTreeSet,
     sync::{LazyLock, RwLock},
+    time::Duration,
 };
 use std::{
-    fs::{create_dir_all, File},
+    fs::{copy, create_dir_all, read_dir, remove_dir_all, remove_file, rename, File},
     ops::Deref,
-    path::PathBuf,
+    path::{Path, PathBuf},
 };
-use strum::{Display, EnumIter};
+use strum::{Display, EnumIter, IntoEnumIterator};
+use thiserror::Error;
+use walkdir::WalkDir;
+
+/// Placing this next to the executable turns on portable mode, same as passing `--portable`
+#[cfg(platform_desktop)]
+pub const PORTABLE_MARKER_FILE: &str = "portable.txt";
 
-/// The directory where we store our runtime files is platform specific
+/// The directory where we store our runtime files is platform specific, unless we are in portable mode
 #[cfg(platform_desktop)]
-pub static STORAGE_DIRECTORY: LazyLock<PathBuf> =
-    LazyLock::new(|| dirs::data_dir().unwrap().join("multiemu"));
+pub static STORAGE_DIRECTORY: LazyLock<PathBuf> = LazyLock::new(|| {
+    portable_storage_directory().unwrap_or_else(|| dirs::data_dir().unwrap().join("multiemu"))
+});
 #[cfg(platform_3ds)]
 pub static STORAGE_DIRECTORY: LazyLock<PathBuf> = LazyLock::new(|| PathBuf::from("sdmc:/multiemu"));
 
+/// Portable mode keeps everything beside the executable, for USB stick installs and shared machines
+#[cfg(platform_desktop)]
+fn portable_storage_directory() -> Option<PathBuf> {
+    let executable_directory = std::env::current_exe().ok()?.parent()?.to_path_buf();
+
+    // This is touched before clap parses anything, so look for the flag ourselves
+    let portable = std::env::args()
+        .skip(1)
+        .any(|argument| argument == "--portable")
+        || executable_directory.join(PORTABLE_MARKER_FILE).is_file();
+
+    portable.then(|| executable_directory.join("data"))
+}
+
 pub static CONFIG_LOCATION: LazyLock<PathBuf> =
-    LazyLock::new(|| STORAGE_DIRECTORY.join("config.ron"));
+    LazyLock::new(|| PROFILE_DIRECTORY.join("config.ron"));
 
 #[derive(Serialize, Deserialize, Debug, Clone, Copy, EnumIter, Display, PartialEq, Eq)]
 pub enum GraphicsSettings {
@@ -48,6 +87,59 @@ impl Default for GraphicsSettings {
     }
 }
 
+/// The user configurable locations we keep data in
+#[derive(Debug, Clone, Copy, EnumIter, Display, PartialEq, Eq, Hash)]
+pub enum StoragePath {
+    Roms,
+    Saves,
+    Snapshots,
+    Database,
+}
+
+impl StoragePath {
+    /// The database is a single file, everything else is a directory
+    pub fn is_directory(&self) -> bool {
+        !matches!(self, StoragePath::Database)
+    }
+}
+
+/// How much memory, in bytes, each feature that keeps machine states around can take up
+#[serde_inline_default]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryBudgets {
+    pub tas_greenzone: usize,
+    /// See [crate::machine::rewind::RewindBuffer], 0 turns rewinding off
+    #[serde_inline_default(DEFAULT_REWIND_BUDGET)]
+    pub rewind: usize,
+}
+
+impl Default for MemoryBudgets {
+    fn default() -> Self {
+        Self {
+            tas_greenzone: DEFAULT_GREENZONE_BUDGET,
+            rewind: DEFAULT_REWIND_BUDGET,
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum StoragePathError {
+    #[error("Path is empty")]
+    Empty,
+    #[error("Path must be absolute")]
+    NotAbsolute,
+    #[error("Path exists and is not a directory")]
+    NotADirectory,
+    #[error("Path exists and is a directory")]
+    NotAFile,
+    #[error("Path is already used for {0}")]
+    InUse(StoragePath),
+    #[error("Cannot move data into {0} as it is not empty")]
+    DestinationNotEmpty(PathBuf),
+    #[error("Failed to move data: {0}")]
+    Io(#[from] std::io::Error),
+}
+
 #[serde_as]
 #[serde_inline_default]
 #[derive(Serialize, Deserialize, Debug)]
@@ -55,52 +147,276 @@ pub struct GlobalConfig {
     #[serde(default)]
     pub gamepad_configs:
         IndexMap<GameSystem, IndexMap<EmulatedGamepadTypeId, IndexMap<Input, Input>>>,
+    /// Keyboard bindings for each player when a system has more than one, these win over
+    /// [GlobalConfig::gamepad_configs] for keys
+    #[serde(default)]
+    pub keyboard_splits: IndexMap<GameSystem, Vec<IndexMap<Input, Input>>>,
     #[serde_inline_default(DEFAULT_HOTKEYS.clone())]
     pub hotkeys: IndexMap<BTreeSet<Input>, Hotkey>,
     #[serde(default)]
     pub graphics_setting: GraphicsSettings,
     #[serde_inline_default(true)]
     pub vsync: bool,
+    #[serde(default)]
+    pub video_filter: VideoFilter,
+    /// Overrides [GlobalConfig::video_filter] for specific games
+    #[serde(default)]
+    pub game_video_filters: IndexMap<RomId, VideoFilter>,
+    #[serde(default)]
+    pub video_filter_parameters: VideoFilterParameters,
+    #[serde(default)]
+    pub scaling_mode: ScalingMode,
+    /// TODO: Carried along by presets, but nothing draws it yet
+    #[serde(default)]
+    pub bezel: Option<PathBuf>,
+    /// Whole looks attached to specific games, these win over [GlobalConfig::game_video_filters]
+    #[serde(default)]
+    pub game_video_presets: IndexMap<RomId, VideoPreset>,
+    #[serde(default)]
+    pub motion_smoothing: MotionSmoothing,
+    #[serde(default)]
+    pub refresh_rate_sync: RefreshRateSync,
+    #[serde(default)]
+    pub power_profile: PowerProfile,
+    /// See [crate::scheduler::Scheduler::set_max_catch_up]
+    #[serde_inline_default(DEFAULT_MAX_CATCH_UP)]
+    pub max_catch_up: Duration,
+    /// See [crate::scheduler::Scheduler::set_pacing]
+    #[serde(default)]
+    pub pacing: Pacing,
+    /// See [crate::scheduler::Watchdog::set_budget]
+    #[serde_inline_default(Some(DEFAULT_WATCHDOG_BUDGET))]
+    pub watchdog_budget: Option<Duration>,
+    #[serde(default)]
+    pub memory_budgets: MemoryBudgets,
+    #[serde(default)]
+    pub input_display: InputDisplayConfig,
+    /// Frames each key pasted into a computer is held for, and then let go for
+    #[serde_inline_default(DEFAULT_PASTE_KEY_FRAMES)]
+    pub paste_key_frames: u32,
+    /// Nothing talks to the internet if this is off, which right now is just the update check
+    ///
+    /// Off until the user turns it on, even with the update check compiled in
+    #[serde(default)]
+    pub network_access: bool,
+    /// Keep a record of what gets played for the statistics page, see [crate::rom::statistics]
+    #[serde(default)]
+    pub usage_statistics: bool,
     #[serde_inline_default(STORAGE_DIRECTORY.clone())]
     pub file_browser_home: PathBuf,
-    #[serde_inline_default(STORAGE_DIRECTORY.join("log"))]
+    #[serde_inline_default(PROFILE_DIRECTORY.join("log"))]
     pub log_location: PathBuf,
-    #[serde_inline_default(STORAGE_DIRECTORY.join("database"))]
+    #[serde_inline_default(PROFILE_DIRECTORY.join("database"))]
     pub database_file: PathBuf,
-    #[serde_inline_default(STORAGE_DIRECTORY.join("saves"))]
+    #[serde_inline_default(PROFILE_DIRECTORY.join("saves"))]
     pub save_directory: PathBuf,
-    #[serde_inline_default(STORAGE_DIRECTORY.join("snapshot"))]
+    /// Emulated time between battery backed memory being written out, see
+    /// [crate::definitions::misc::memory::persistent::PersistentMemory]
+    #[serde_inline_default(DEFAULT_FLUSH_INTERVAL)]
+    pub save_flush_interval: Duration,
+    #[serde_inline_default(PROFILE_DIRECTORY.join("snapshot"))]
     pub snapshot_directory: PathBuf,
-    #[serde_inline_default(STORAGE_DIRECTORY.join("roms"))]
+    #[serde_inline_default(PROFILE_DIRECTORY.join("roms"))]
     pub roms_directory: PathBuf,
+    #[cfg(platform_desktop)]
+    #[serde(default)]
+    pub rom_storage_policy: RomStoragePolicy,
 }
 
 impl Default for GlobalConfig {
     fn default() -> Self {
         Self {
             gamepad_configs: Default::default(),
+            keyboard_splits: IndexMap::default(),
             hotkeys: DEFAULT_HOTKEYS.clone(),
             graphics_setting: GraphicsSettings::default(),
             vsync: true,
+            video_filter: VideoFilter::default(),
+            game_video_filters: IndexMap::default(),
+            video_filter_parameters: VideoFilterParameters::default(),
+            scaling_mode: ScalingMode::default(),
+            bezel: None,
+            game_video_presets: IndexMap::default(),
+            motion_smoothing: MotionSmoothing::default(),
+            refresh_rate_sync: RefreshRateSync::default(),
+            power_profile: PowerProfile::default(),
+            max_catch_up: DEFAULT_MAX_CATCH_UP,
+            pacing: Pacing::default(),
+            watchdog_budget: Some(DEFAULT_WATCHDOG_BUDGET),
+            memory_budgets: MemoryBudgets::default(),
+            input_display: InputDisplayConfig::default(),
+            paste_key_frames: DEFAULT_PASTE_KEY_FRAMES,
+            network_access: false,
+            usage_statistics: false,
             file_browser_home: STORAGE_DIRECTORY.clone(),
-            log_location: STORAGE_DIRECTORY.join("log"),
-            database_file: STORAGE_DIRECTORY.join("database"),
-            save_directory: STORAGE_DIRECTORY.join("saves"),
-            snapshot_directory: STORAGE_DIRECTORY.join("snapshot"),
-            roms_directory: STORAGE_DIRECTORY.join("roms"),
+            log_location: PROFILE_DIRECTORY.join("log"),
+            database_file: PROFILE_DIRECTORY.join("database"),
+            save_directory: PROFILE_DIRECTORY.join("saves"),
+            save_flush_interval: DEFAULT_FLUSH_INTERVAL,
+            snapshot_directory: PROFILE_DIRECTORY.join("snapshot"),
+            roms_directory: PROFILE_DIRECTORY.join("roms"),
+            #[cfg(platform_desktop)]
+            rom_storage_policy: RomStoragePolicy::default(),
         }
     }
 }
 
 impl GlobalConfig {
+    /// Makes sure every gamepad of a machine about to run has bindings, splitting the keyboard if there is more than
+    /// one player
+    pub fn seed_gamepad_configs(&mut self, system: GameSystem, input_manager: &InputManager) {
+        for (gamepad_type, metadata) in input_manager.gamepad_types.iter() {
+            self.gamepad_configs
+                .entry(system)
+                .or_default()
+                .entry(gamepad_type.clone())
+                .or_insert_with(|| IndexMap::from_iter(metadata.default_bindings.clone()));
+        }
+
+        if !self.keyboard_splits.contains_key(&system) {
+            let emulated_gamepads = input_manager.emulated_gamepads();
+
+            if let Some(split_bindings) = split_keyboard_bindings(
+                emulated_gamepads
+                    .iter()
+                    .map(|(_, gamepad_type)| gamepad_type),
+            ) {
+                tracing::info!("Splitting the keyboard between players for {}", system);
+
+                self.keyboard_splits.insert(
+                    system,
+                    split_bindings
+                        .into_iter()
+                        .map(IndexMap::from_iter)
+                        .collect(),
+                );
+            }
+        }
+    }
+
     pub fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
-        create_dir_all(STORAGE_DIRECTORY.deref())?;
+        create_dir_all(PROFILE_DIRECTORY.deref())?;
         let config_file = File::create(CONFIG_LOCATION.deref())?;
         ron::ser::to_writer_pretty(config_file, self, PrettyConfig::default())?;
 
         Ok(())
     }
 
+    pub fn storage_path(&self, storage_path: StoragePath) -> &Path {
+        match storage_path {
+            StoragePath::Roms => &self.roms_directory,
+            StoragePath::Saves => &self.save_directory,
+            StoragePath::Snapshots => &self.snapshot_directory,
+            StoragePath::Database => &self.database_file,
+        }
+    }
+
+    fn storage_path_mut(&mut self, storage_path: StoragePath) -> &mut PathBuf {
+        match storage_path {
+            StoragePath::Roms => &mut self.roms_directory,
+            StoragePath::Saves => &mut self.save_directory,
+            StoragePath::Snapshots => &mut self.snapshot_directory,
+            StoragePath::Database => &mut self.database_file,
+        }
+    }
+
+    pub fn validate_storage_path(
+        &self,
+        storage_path: StoragePath,
+        path: &Path,
+    ) -> Result<(), StoragePathError> {
+        if path.as_os_str().is_empty() {
+            return Err(StoragePathError::Empty);
+        }
+
+        if !path.is_absolute() {
+            return Err(StoragePathError::NotAbsolute);
+        }
+
+        if storage_path.is_directory() && path.exists() && !path.is_dir() {
+            return Err(StoragePathError::NotADirectory);
+        }
+
+        if !storage_path.is_directory() && path.is_dir() {
+            return Err(StoragePathError::NotAFile);
+        }
+
+        if let Some(other_storage_path) = StoragePath::iter().find(|other_storage_path| {
+            *other_storage_path != storage_path && self.storage_path(*other_storage_path) == path
+        }) {
+            return Err(StoragePathError::InUse(other_storage_path));
+        }
+
+        Ok(())
+    }
+
+    /// Points a storage path somewhere else, optionally bringing along whatever was at the old location
+    pub fn change_storage_path(
+        &mut self,
+        storage_path: StoragePath,
+        path: PathBuf,
+        migrate: bool,
+    ) -> Result<(), StoragePathError> {
+        self.validate_storage_path(storage_path, &path)?;
+
+        let old_path = self.storage_path(storage_path);
+
+        if migrate && old_path.exists() && old_path != path {
+            if !is_empty_location(&path)? {
+                return Err(StoragePathError::DestinationNotEmpty(path));
+            }
+
+            tracing::info!(
+                "Moving {} data from {} to {}",
+                storage_path,
+                old_path.display(),
+                path.display()
+            );
+            move_storage_data(old_path, &path)?;
+        }
+
+        *self.storage_path_mut(storage_path) = path;
+
+        Ok(())
+    }
+
+    /// Takes on the paths of another config, for when a config is brought over from another machine
+    pub fn adopt_local_paths(&mut self, local: &GlobalConfig) {
+        for storage_path in StoragePath::iter() {
+            *self.storage_path_mut(storage_path) = local.storage_path(storage_path).to_path_buf();
+        }
+
+        self.file_browser_home = local.file_browser_home.clone();
+        self.log_location = local.log_location.clone();
+    }
+
+    /// The look to use for a game, with no filter at all if the power profile cannot afford it
+    pub fn video_preset_for(&self, rom_id: RomId) -> VideoPreset {
+        let mut video_preset = self
+            .game_video_presets
+            .get(&rom_id)
+            .cloned()
+            .unwrap_or_else(|| {
+                let mut video_preset = VideoPreset::from_config(self, "Default");
+                if let Some(video_filter) = self.game_video_filters.get(&rom_id) {
+                    video_preset.video_filter = *video_filter;
+                }
+
+                video_preset
+            });
+
+        if video_preset.video_filter.cost() > self.power_profile.settings().max_video_filter_cost {
+            video_preset.video_filter = VideoFilter::None;
+        }
+
+        video_preset
+    }
+
+    /// If presenting should wait on vsync, after the power profile has its say
+    pub fn vsync(&self) -> bool {
+        self.power_profile.settings().vsync.unwrap_or(self.vsync)
+    }
+
     pub fn load() -> Result<Self, Box<dyn std::error::Error>> {
         let config_file = File::open(CONFIG_LOCATION.deref())?;
         let config = ron::de::from_reader(config_file)?;
@@ -109,6 +425,89 @@ impl GlobalConfig {
     }
 }
 
+fn is_empty_location(path: &Path) -> Result<bool, std::io::Error> {
+    if path.is_dir() {
+        Ok(read_dir(path)?.next().is_none())
+    } else {
+        Ok(!path.exists())
+    }
+}
+
+fn move_storage_data(from: &Path, to: &Path) -> Result<(), std::io::Error> {
+    if let Some(parent) = to.parent() {
+        create_dir_all(parent)?;
+    }
+
+    // Renaming does not work across filesystems, so fall back to copying
+    if rename(from, to).is_ok() {
+        return Ok(());
+    }
+
+    if from.is_file() {
+        copy(from, to)?;
+        return remove_file(from);
+    }
+
+    for entry in WalkDir::new(from) {
+        let entry = entry?;
+        let destination = to.join(entry.path().strip_prefix(from).unwrap());
+
+        if entry.file_type().is_dir() {
+            create_dir_all(destination)?;
+        } else {
+            copy(entry.path(), destination)?;
+        }
+    }
+
+    remove_dir_all(from)
+}
+
 /// FIXME: This is a mutable singleton out of lazyness
 pub static GLOBAL_CONFIG: LazyLock<RwLock<GlobalConfig>> =
     LazyLock::new(|| RwLock::new(GlobalConfig::load().unwrap_or_default()));
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::fs::{read_to_string, write};
+
+    #[test]
+    fn storage_path_migration() {
+        let test_directory = std::env::temp_dir().join(format!(
+            "multiemu_storage_path_migration_{}",
+            std::process::id()
+        ));
+        let old_saves = test_directory.join("old_saves");
+        let new_saves = test_directory.join("nested").join("new_saves");
+
+        create_dir_all(old_saves.join("chip8")).unwrap();
+        write(old_saves.join("chip8").join("game.sav"), "save").unwrap();
+
+        let mut config = GlobalConfig {
+            save_directory: old_saves.clone(),
+            ..Default::default()
+        };
+
+        assert!(matches!(
+            config.validate_storage_path(StoragePath::Saves, Path::new("relative")),
+            Err(StoragePathError::NotAbsolute)
+        ));
+        assert!(matches!(
+            config.validate_storage_path(StoragePath::Snapshots, &old_saves),
+            Err(StoragePathError::InUse(StoragePath::Saves))
+        ));
+
+        config
+            .change_storage_path(StoragePath::Saves, new_saves.clone(), true)
+            .unwrap();
+
+        assert_eq!(config.save_directory, new_saves);
+        assert!(!old_saves.exists());
+        assert_eq!(
+            read_to_string(new_saves.join("chip8").join("game.sav")).unwrap(),
+            "save"
+        );
+
+        remove_dir_all(test_directory).unwrap();
+    }
+}
@@ -1,40 +1,206 @@
-use std::sync::Mutex;
+use std::sync::{
+    atomic::{AtomicU32, Ordering},
+    Arc, Mutex,
+};
 
 use crate::{
-    component::{schedulable::SchedulableComponent, Component, FromConfig},
-    machine::ComponentBuilder,
+    component::{
+        audio::AudioComponent,
+        schedulable::{RunContext, SchedulableComponent},
+        Component, FromConfig, ResetKind, SnapshotError,
+    },
+    machine::{message::Mailbox, ComponentBuilder, MachineBuildError},
 };
 use num::rational::Ratio;
+use ringbuffer::{AllocRingBuffer, RingBuffer};
+use serde::{Deserialize, Serialize};
+
+/// A multiple of the timer rate, so every tick is a whole number of samples
+pub const CHIP8_AUDIO_SAMPLE_RATE: u32 = 48000;
+const SAMPLES_PER_TICK: usize = CHIP8_AUDIO_SAMPLE_RATE as usize / 60;
+/// The original interpreters never said what pitch, so this is just a pleasant buzz
+const TONE_FREQUENCY: u32 = 440;
+const AMPLITUDE: f32 = 0.25;
+
+/// What the CPU can ask of the sound timer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Chip8AudioMessage {
+    SetTimer(u8),
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Chip8AudioSnapshot {
+    pub sound_timer: u8,
+    pub phase: u32,
+}
 
 #[derive(Debug)]
 pub struct Chip8Audio {
     // The CPU will set this according to what the program wants
     sound_timer: Mutex<u8>,
+    mailbox: Arc<Mailbox<Chip8AudioMessage>>,
+    /// Where in the square wave we are, in units of one [CHIP8_AUDIO_SAMPLE_RATE]th of a cycle
+    phase: AtomicU32,
+    /// Whatever plays these drains them, the oldest are dropped if nothing does
+    samples: Mutex<AllocRingBuffer<f32>>,
 }
 
-impl Chip8Audio {
-    pub fn set(&self, value: u8) {
-        *self.sound_timer.lock().unwrap() = value;
+impl Component for Chip8Audio {
+    fn reset(&self, _kind: ResetKind) {
+        self.mailbox.clear();
+        *self.sound_timer.lock().unwrap() = 0;
+        self.phase.store(0, Ordering::Relaxed);
+        self.samples.lock().unwrap().clear();
     }
-}
 
-impl Component for Chip8Audio {}
+    fn save_snapshot(&self) -> rmpv::Value {
+        rmpv::ext::to_value(Chip8AudioSnapshot {
+            sound_timer: *self.sound_timer.lock().unwrap(),
+            phase: self.phase.load(Ordering::Relaxed),
+        })
+        .unwrap()
+    }
+
+    fn snapshot_version(&self) -> u32 {
+        1
+    }
+
+    fn migrate_snapshot(
+        &self,
+        version: u32,
+        _snapshot: rmpv::Value,
+    ) -> Result<rmpv::Value, SnapshotError> {
+        match version {
+            // Nothing was saved before, so the best we can do is silence
+            0 => Ok(rmpv::ext::to_value(Chip8AudioSnapshot::default())?),
+            _ => Err(SnapshotError::UnsupportedVersion {
+                found: version,
+                current: self.snapshot_version(),
+            }),
+        }
+    }
+
+    fn load_snapshot(&self, state: rmpv::Value) -> Result<(), SnapshotError> {
+        let snapshot: Chip8AudioSnapshot = rmpv::ext::from_value(state)?;
+
+        *self.sound_timer.lock().unwrap() = snapshot.sound_timer;
+        self.phase.store(snapshot.phase, Ordering::Relaxed);
+        self.samples.lock().unwrap().clear();
+
+        Ok(())
+    }
+}
 
 impl FromConfig for Chip8Audio {
     type Config = ();
 
-    fn from_config(component_builder: &mut ComponentBuilder<Self>, _config: Self::Config) {
+    fn from_config(
+        component_builder: &mut ComponentBuilder<Self>,
+        _config: Self::Config,
+    ) -> Result<(), MachineBuildError> {
+        let mailbox = component_builder.mailbox();
+
         component_builder
             .set_component(Self {
                 sound_timer: Mutex::new(0),
+                mailbox,
+                phase: AtomicU32::new(0),
+                // A second worth
+                samples: Mutex::new(AllocRingBuffer::new(CHIP8_AUDIO_SAMPLE_RATE as usize)),
             })
-            .set_schedulable(Ratio::from_integer(60), [], []);
+            .set_schedulable(Ratio::from_integer(60), [], [])
+            .set_audio(CHIP8_AUDIO_SAMPLE_RATE)
+            .set_label("audio");
+
+        Ok(())
+    }
+}
+
+impl AudioComponent for Chip8Audio {
+    fn drain_samples(&self) -> Vec<f32> {
+        self.samples.lock().unwrap().drain().collect()
     }
 }
 
 impl SchedulableComponent for Chip8Audio {
-    fn run(&self, period: u64) {
+    fn run(&self, period: u64, _context: RunContext) {
         let mut sound_timer_guard = self.sound_timer.lock().unwrap();
-        *sound_timer_guard = sound_timer_guard.saturating_sub(period.try_into().unwrap_or(u8::MAX));
+        let mut samples_guard = self.samples.lock().unwrap();
+        let mut phase = self.phase.load(Ordering::Relaxed);
+
+        // Nothing can hear the difference between being set now or at the start of this tick
+        for message in self.mailbox.receive() {
+            match message {
+                Chip8AudioMessage::SetTimer(value) => *sound_timer_guard = value,
+            }
+        }
+
+        for _ in 0..period {
+            // The buzzer sounds for as long as the timer is counting down
+            if *sound_timer_guard == 0 {
+                phase = 0;
+                samples_guard.extend(std::iter::repeat(0.0).take(SAMPLES_PER_TICK));
+                continue;
+            }
+
+            for _ in 0..SAMPLES_PER_TICK {
+                samples_guard.push(if phase < CHIP8_AUDIO_SAMPLE_RATE / 2 {
+                    AMPLITUDE
+                } else {
+                    -AMPLITUDE
+                });
+
+                phase = (phase + TONE_FREQUENCY) % CHIP8_AUDIO_SAMPLE_RATE;
+            }
+
+            *sound_timer_guard -= 1;
+        }
+
+        self.phase.store(phase, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testing::ComponentHarness;
+
+    #[test]
+    fn buzzes_while_the_timer_runs() {
+        let harness = ComponentHarness::build().component::<Chip8Audio>(());
+
+        harness.run(1);
+        assert_eq!(harness.component().drain_samples(), [0.0; SAMPLES_PER_TICK]);
+
+        *harness.component().sound_timer.lock().unwrap() = 2;
+        harness.run(3);
+
+        let samples = harness.component().drain_samples();
+        assert_eq!(samples.len(), SAMPLES_PER_TICK * 3);
+        assert!(samples[..SAMPLES_PER_TICK * 2]
+            .iter()
+            .all(|sample| sample.abs() == AMPLITUDE));
+        assert!(samples[SAMPLES_PER_TICK * 2..]
+            .iter()
+            .all(|sample| *sample == 0.0));
+    }
+
+    #[test]
+    fn snapshots_keep_the_waveform() {
+        let harness = ComponentHarness::build().component::<Chip8Audio>(());
+        *harness.component().sound_timer.lock().unwrap() = 10;
+        harness.run(1);
+        harness.component().drain_samples();
+
+        let snapshot = harness.component().save_snapshot();
+        harness.run(1);
+        let expected = harness.component().drain_samples();
+
+        harness.component().reset(ResetKind::Hard);
+        harness.run(1);
+        harness.component().load_snapshot(snapshot).unwrap();
+        harness.run(1);
+
+        assert_eq!(harness.component().drain_samples(), expected);
     }
 }
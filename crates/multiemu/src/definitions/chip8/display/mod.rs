@@ -1,10 +1,14 @@
 use super::Chip8Kind;
 use crate::{
     component::{
-        display::DisplayComponent, schedulable::SchedulableComponent, Component, FromConfig,
+        display::DisplayComponent,
+        schedulable::{RunContext, SchedulableComponent},
+        Component, FromConfig, ResetKind, SnapshotError,
+    },
+    machine::{ComponentBuilder, MachineBuildError},
+    runtime::rendering_backend::{
+        DisplayComponentFramebuffer, DisplayComponentInitializationData, RenderSurface,
     },
-    machine::ComponentBuilder,
-    runtime::rendering_backend::{DisplayComponentFramebuffer, DisplayComponentInitializationData},
 };
 use bitvec::{order::Msb0, view::BitView};
 use nalgebra::{DMatrix, DMatrixViewMut, Point2, Vector2};
@@ -12,26 +16,10 @@ use num::rational::Ratio;
 use palette::Srgba;
 use serde::{Deserialize, Serialize};
 use std::sync::{
-    atomic::{AtomicBool, Ordering},
-    Arc, Mutex, OnceLock,
+    atomic::{AtomicBool, AtomicU64, Ordering},
+    Mutex, RwLock,
 };
 
-#[cfg(platform_desktop)]
-mod desktop;
-#[cfg(graphics_vulkan)]
-use desktop::vulkan::VulkanState;
-
-mod software;
-use software::SoftwareState;
-
-#[derive(Debug)]
-#[non_exhaustive]
-enum InternalState {
-    #[cfg(graphics_vulkan)]
-    Vulkan(VulkanState),
-    Software(SoftwareState),
-}
-
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Chip8DisplaySnapshot {
     screen_buffer: DMatrix<Srgba<u8>>,
@@ -40,8 +28,12 @@ pub struct Chip8DisplaySnapshot {
 #[derive(Debug)]
 pub struct Chip8Display {
     config: Chip8DisplayConfig,
-    state: OnceLock<InternalState>,
+    screen: Mutex<DMatrix<Srgba<u8>>>,
+    /// Replaced whenever the frontend hands us new display data
+    surface: RwLock<Option<Box<dyn RenderSurface>>>,
     modified: AtomicBool,
+    /// See [Component::snapshot_generation]
+    generation: AtomicU64,
 }
 
 impl Chip8Display {
@@ -59,61 +51,62 @@ impl Chip8Display {
         };
 
         self.modified.store(true, Ordering::Relaxed);
+        self.generation.fetch_add(1, Ordering::Relaxed);
 
-        match self.state.get() {
-            #[cfg(graphics_vulkan)]
-            Some(InternalState::Vulkan(vulkan_state)) => vulkan_state.draw_sprite(position, sprite),
-            Some(InternalState::Software(software_state)) => {
-                software_state.draw_sprite(position, sprite)
-            }
-            _ => panic!("Internal state not initialized"),
-        }
+        draw_sprite_common(position, sprite, self.screen.lock().unwrap().as_view_mut())
     }
 
     pub fn clear_display(&self) {
         tracing::trace!("Clearing display");
 
-        match self.state.get() {
-            #[cfg(graphics_vulkan)]
-            Some(InternalState::Vulkan(vulkan_state)) => vulkan_state.clear_display(),
-            Some(InternalState::Software(software_state)) => software_state.clear_display(),
-            _ => panic!("Internal state not initialized"),
+        self.modified.store(true, Ordering::Relaxed);
+        self.generation.fetch_add(1, Ordering::Relaxed);
+        self.screen.lock().unwrap().fill(Srgba::new(0, 0, 0, 255));
+    }
+
+    fn commit_display(&self) {
+        if let Some(surface) = self.surface.read().unwrap().as_ref() {
+            surface.upload(&self.screen.lock().unwrap());
+            surface.commit();
         }
     }
 }
 
 impl Component for Chip8Display {
-    fn reset(&self) {
+    fn reset(&self, _kind: ResetKind) {
         self.clear_display();
     }
 
     fn save_snapshot(&self) -> rmpv::Value {
-        let display_buffer = match self.state.get() {
-            #[cfg(graphics_vulkan)]
-            Some(InternalState::Vulkan(vulkan_state)) => vulkan_state.save_screen_contents(),
-            Some(InternalState::Software(software_state)) => software_state.save_screen_contents(),
-            _ => panic!("Internal state not initialized"),
-        };
-
         rmpv::ext::to_value(Chip8DisplaySnapshot {
-            screen_buffer: display_buffer,
+            screen_buffer: self.screen.lock().unwrap().clone(),
         })
         .unwrap()
     }
 
-    fn load_snapshot(&self, state: rmpv::Value) {
-        let snapshot: Chip8DisplaySnapshot = rmpv::ext::from_value(state).unwrap();
+    fn load_snapshot(&self, state: rmpv::Value) -> Result<(), SnapshotError> {
+        let snapshot: Chip8DisplaySnapshot = rmpv::ext::from_value(state)?;
 
-        match self.state.get() {
-            #[cfg(graphics_vulkan)]
-            Some(InternalState::Vulkan(vulkan_state)) => {
-                vulkan_state.load_screen_contents(snapshot.screen_buffer);
-            }
-            Some(InternalState::Software(software_state)) => {
-                software_state.load_screen_contents(snapshot.screen_buffer);
-            }
-            _ => panic!("Internal state not initialized"),
+        if snapshot.screen_buffer.shape() != (64, 32) {
+            return Err(SnapshotError::SizeMismatch {
+                expected: 64 * 32,
+                found: snapshot.screen_buffer.len(),
+            });
         }
+
+        self.modified.store(true, Ordering::Relaxed);
+        self.generation.fetch_add(1, Ordering::Relaxed);
+        *self.screen.lock().unwrap() = snapshot.screen_buffer;
+
+        Ok(())
+    }
+
+    fn snapshot_generation(&self) -> Option<u64> {
+        Some(self.generation.load(Ordering::Relaxed))
+    }
+
+    fn shutdown(&self) {
+        *self.surface.write().unwrap() = None;
     }
 }
 
@@ -125,113 +118,53 @@ pub struct Chip8DisplayConfig {
 impl FromConfig for Chip8Display {
     type Config = Chip8DisplayConfig;
 
-    fn from_config(component_builder: &mut ComponentBuilder<Self>, config: Self::Config) {
+    fn from_config(
+        component_builder: &mut ComponentBuilder<Self>,
+        config: Self::Config,
+    ) -> Result<(), MachineBuildError> {
         component_builder
             .set_component(Chip8Display {
                 config,
-                state: OnceLock::default(),
+                screen: Mutex::new(DMatrix::from_element(64, 32, Srgba::new(0, 0, 0, 255))),
+                surface: RwLock::default(),
                 modified: AtomicBool::new(false),
+                generation: AtomicU64::new(0),
             })
             .set_schedulable(Ratio::from_integer(60), [], [])
-            .set_display();
-    }
-}
+            .set_display()
+            .set_label("display");
 
-trait Chip8DisplayImplementation {
-    fn draw_sprite(&self, position: Point2<u8>, sprite: &[u8]) -> bool;
-    fn clear_display(&self);
-    fn save_screen_contents(&self) -> DMatrix<Srgba<u8>>;
-    fn load_screen_contents(&self, buffer: DMatrix<Srgba<u8>>);
-    fn get_framebuffer(&self) -> DisplayComponentFramebuffer;
-    fn commit_display(&self);
+        Ok(())
+    }
 }
 
 impl SchedulableComponent for Chip8Display {
-    fn run(&self, _period: u64) {
+    fn run(&self, _period: u64, _context: RunContext) {
         // Only update it once and if the thing is actually updated
         if self.modified.swap(false, Ordering::Relaxed) {
-            match self.state.get() {
-                Some(InternalState::Software(software_state)) => {
-                    software_state.commit_display();
-                }
-                #[cfg(graphics_vulkan)]
-                Some(InternalState::Vulkan(vulkan_state)) => {
-                    vulkan_state.commit_display();
-                }
-                _ => panic!("Internal state not initialized"),
-            }
+            self.commit_display();
         }
     }
 }
 
 impl DisplayComponent for Chip8Display {
     fn set_display_data(&self, initialization_data: DisplayComponentInitializationData) {
-        let _ = self.state.set(match initialization_data {
-            DisplayComponentInitializationData::Software => {
-                let framebuffer = DMatrix::from_element(64, 32, Srgba::new(0, 0, 0, 255));
-                InternalState::Software(SoftwareState {
-                    framebuffer: Arc::new(Mutex::new(framebuffer)),
-                })
-            }
-            #[cfg(graphics_vulkan)]
-            DisplayComponentInitializationData::Vulkan(initialization_data) => {
-                use vulkano::buffer::Buffer;
-                use vulkano::buffer::BufferCreateInfo;
-                use vulkano::buffer::BufferUsage;
-                use vulkano::format::Format;
-                use vulkano::image::Image;
-                use vulkano::image::ImageCreateInfo;
-                use vulkano::image::ImageType;
-                use vulkano::image::ImageUsage;
-                use vulkano::memory::allocator::AllocationCreateInfo;
-                use vulkano::memory::allocator::MemoryTypeFilter;
-
-                let staging_buffer = Buffer::from_iter(
-                    initialization_data.memory_allocator.clone(),
-                    BufferCreateInfo {
-                        usage: BufferUsage::TRANSFER_SRC,
-                        ..Default::default()
-                    },
-                    AllocationCreateInfo {
-                        memory_type_filter: MemoryTypeFilter::HOST_RANDOM_ACCESS,
-                        ..Default::default()
-                    },
-                    vec![Srgba::new(0, 0, 0, 0xff); 64 * 32],
-                )
-                .unwrap();
-
-                let render_image = Image::new(
-                    initialization_data.memory_allocator.clone(),
-                    ImageCreateInfo {
-                        image_type: ImageType::Dim2d,
-                        format: Format::R8G8B8A8_SRGB,
-                        extent: [64, 32, 1],
-                        usage: ImageUsage::TRANSFER_SRC
-                            | ImageUsage::TRANSFER_DST
-                            | ImageUsage::SAMPLED,
-                        ..Default::default()
-                    },
-                    AllocationCreateInfo::default(),
-                )
-                .unwrap();
-
-                InternalState::Vulkan(VulkanState {
-                    queue: initialization_data.queue,
-                    command_buffer_allocator: initialization_data.command_buffer_allocator,
-                    staging_buffer,
-                    render_image,
-                })
-            }
-        });
+        *self.surface.write().unwrap() = Some(initialization_data.create_surface(64, 32));
+        // Keep showing the same thing if the frontend had to start over, like after losing the gpu
+        self.commit_display();
     }
 
     fn get_framebuffer(&self) -> DisplayComponentFramebuffer {
-        match self.state.get() {
-            Some(InternalState::Software(software_state)) => software_state.get_framebuffer(),
-            #[cfg(graphics_vulkan)]
-            Some(InternalState::Vulkan(vulkan_state)) => vulkan_state.get_framebuffer(),
-            _ => panic!("Internal state not initialized"),
-        }
+        self.surface
+            .read()
+            .unwrap()
+            .as_ref()
+            .expect("Display data not set")
+            .framebuffer()
+    }
+
+    fn redraw(&self) {
+        self.commit_display();
     }
 }
 
@@ -2,7 +2,8 @@ use super::misc::memory::standard::{
     StandardMemory, StandardMemoryConfig, StandardMemoryInitialContents,
 };
 use crate::{
-    machine::Machine,
+    component::media::{MediaSlotId, MediaSlotMetadata},
+    machine::{definition::MachineDefinition, Machine, MachineBuildError},
     memory::AddressSpaceId,
     rom::{
         id::RomId,
@@ -14,6 +15,7 @@ use audio::Chip8Audio;
 use display::{Chip8Display, Chip8DisplayConfig};
 use num::rational::Ratio;
 use processor::{Chip8Processor, Chip8ProcessorConfig};
+use serde::Deserialize;
 use std::{borrow::Cow, sync::Arc};
 use timer::Chip8Timer;
 
@@ -23,8 +25,9 @@ pub mod processor;
 pub mod timer;
 
 pub const CHIP8_ADDRESS_SPACE_ID: AddressSpaceId = 0;
+pub const CHIP8_PROGRAM_MEDIA_SLOT: MediaSlotId = MediaSlotId::new("program");
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
 pub enum Chip8Kind {
     Chip8,
     Chip8x,
@@ -149,16 +152,40 @@ const CHIP8_FONT: [[u8; 5]; 16] = [
     ],
 ];
 
-pub fn chip8_machine(user_specified_roms: Vec<RomId>, rom_manager: Arc<RomManager>) -> Machine {
+pub struct Chip8Definition;
+
+impl MachineDefinition for Chip8Definition {
+    fn name(&self) -> &str {
+        "Chip8"
+    }
+
+    fn systems(&self) -> Vec<GameSystem> {
+        vec![GameSystem::Other(OtherSystem::Chip8)]
+    }
+
+    fn construct(
+        &self,
+        _system: GameSystem,
+        user_specified_roms: Vec<RomId>,
+        rom_manager: Arc<RomManager>,
+    ) -> Result<Machine, MachineBuildError> {
+        chip8_machine(user_specified_roms, rom_manager)
+    }
+}
+
+pub fn chip8_machine(
+    user_specified_roms: Vec<RomId>,
+    rom_manager: Arc<RomManager>,
+) -> Result<Machine, MachineBuildError> {
     let machine = Machine::build(GameSystem::Other(OtherSystem::Chip8), rom_manager);
     let machine = machine.insert_bus(CHIP8_ADDRESS_SPACE_ID, 12);
 
-    let (machine, audio_component_id) = machine.default_component::<Chip8Audio>();
-    let (machine, timer_component_id) = machine.default_component::<Chip8Timer>();
+    let (machine, audio_component_id) = machine.default_component::<Chip8Audio>()?;
+    let (machine, timer_component_id) = machine.default_component::<Chip8Timer>()?;
     let (machine, display_component_id) =
         machine.build_component::<Chip8Display>(Chip8DisplayConfig {
             kind: Chip8Kind::Chip8,
-        });
+        })?;
 
     let (machine, _) = machine.build_component::<Chip8Processor>(Chip8ProcessorConfig {
         frequency: Ratio::from_integer(700),
@@ -166,31 +193,47 @@ pub fn chip8_machine(user_specified_roms: Vec<RomId>, rom_manager: Arc<RomManage
         display: display_component_id,
         audio: audio_component_id,
         timer: timer_component_id,
-    });
+    })?;
 
-    let (machine, _) = machine.build_component::<StandardMemory>(StandardMemoryConfig {
-        readable: true,
-        writable: true,
-        max_word_size: 2,
-        assigned_range: 0x000..0x200,
-        assigned_address_space: CHIP8_ADDRESS_SPACE_ID,
-        initial_contents: StandardMemoryInitialContents::Array {
-            value: Cow::Borrowed(bytemuck::cast_slice(&CHIP8_FONT)),
-            offset: 0x000,
+    let (machine, _) = machine.build_labeled_component::<StandardMemory>(
+        "font",
+        StandardMemoryConfig {
+            readable: true,
+            writable: true,
+            max_word_size: 2,
+            assigned_range: 0x000..0x200,
+            assigned_address_space: CHIP8_ADDRESS_SPACE_ID,
+            initial_contents: StandardMemoryInitialContents::Array {
+                value: Cow::Borrowed(bytemuck::cast_slice(&CHIP8_FONT)),
+                offset: 0x000,
+            },
         },
-    });
+    )?;
+
+    let (machine, program_memory_component_id) = machine
+        .build_labeled_component::<StandardMemory>(
+            "program",
+            StandardMemoryConfig {
+                readable: true,
+                writable: true,
+                max_word_size: 2,
+                assigned_range: 0x200..0x1000,
+                assigned_address_space: CHIP8_ADDRESS_SPACE_ID,
+                initial_contents: StandardMemoryInitialContents::Rom {
+                    rom_id: user_specified_roms.first().copied(),
+                    offset: 0x200,
+                },
+            },
+        )?;
 
-    let (machine, _) = machine.build_component::<StandardMemory>(StandardMemoryConfig {
-        readable: true,
-        writable: true,
-        max_word_size: 2,
-        assigned_range: 0x200..0x1000,
-        assigned_address_space: CHIP8_ADDRESS_SPACE_ID,
-        initial_contents: StandardMemoryInitialContents::Rom {
-            rom_id: user_specified_roms[0],
-            offset: 0x200,
+    // Lets programs be hot swapped without tearing down the machine
+    let machine = machine.insert_media_slot::<StandardMemory>(
+        program_memory_component_id,
+        CHIP8_PROGRAM_MEDIA_SLOT,
+        MediaSlotMetadata {
+            requires_reset: true,
         },
-    });
+    )?;
 
     machine.build()
 }
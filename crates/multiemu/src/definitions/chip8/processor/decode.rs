@@ -1,4 +1,6 @@
-use super::instruction::{Chip8InstructionSet, InstructionSetChip8, Register};
+use super::instruction::{
+    Chip8InstructionSet, InstructionSetChip8, InstructionSetChip8X, Register,
+};
 use bitvec::{field::BitField, prelude::Msb0, view::BitView};
 use nalgebra::Point2;
 
@@ -182,6 +184,12 @@ pub(super) fn decode_instruction(
                 0xa1 => Ok(Chip8InstructionSet::Chip8(InstructionSetChip8::Skup {
                     key: Register::try_from(register).unwrap(),
                 })),
+                0xf2 => Ok(Chip8InstructionSet::Chip8X(InstructionSetChip8X::Skpr2 {
+                    key: Register::try_from(register).unwrap(),
+                })),
+                0xf5 => Ok(Chip8InstructionSet::Chip8X(InstructionSetChip8X::Skup2 {
+                    key: Register::try_from(register).unwrap(),
+                })),
                 _ => {
                     unimplemented!()
                 }
@@ -240,4 +248,16 @@ mod tests {
             Chip8InstructionSet::Chip8(InstructionSetChip8::Sys { syscall: 0 })
         )
     }
+
+    #[test]
+    pub fn second_keypad() {
+        assert_eq!(
+            decode_instruction([0xe3, 0xf2]).unwrap(),
+            Chip8InstructionSet::Chip8X(InstructionSetChip8X::Skpr2 { key: Register::V3 })
+        );
+        assert_eq!(
+            decode_instruction([0xea, 0xf5]).unwrap(),
+            Chip8InstructionSet::Chip8X(InstructionSetChip8X::Skup2 { key: Register::VA })
+        );
+    }
 }
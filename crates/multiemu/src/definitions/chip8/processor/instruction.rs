@@ -184,6 +184,15 @@ pub enum InstructionSetChip8 {
     },
 }
 
+/// Added by the VP-590 expansion, which came with a second keypad
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum InstructionSetChip8X {
+    /// Skpr for the second keypad
+    Skpr2 { key: Register },
+    /// Skup for the second keypad
+    Skup2 { key: Register },
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum InstructionSetSuperChip8 {
     Scrd { amount: u8 },
@@ -202,6 +211,7 @@ pub enum InstructionSetXoChip {
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Chip8InstructionSet {
     Chip8(InstructionSetChip8),
+    Chip8X(InstructionSetChip8X),
     SuperChip8(InstructionSetSuperChip8),
     XoChip(InstructionSetXoChip),
 }
@@ -1,9 +1,12 @@
 use super::{
     input::Chip8KeyCode,
-    instruction::{Chip8InstructionSet, InstructionSetChip8},
+    instruction::{Chip8InstructionSet, InstructionSetChip8, InstructionSetChip8X},
     Chip8Processor, ExecutionState, ProcessorState,
 };
-use crate::definitions::chip8::{Chip8Kind, CHIP8_ADDRESS_SPACE_ID, CHIP8_FONT};
+use crate::{
+    definitions::chip8::{audio::Chip8AudioMessage, Chip8Kind, CHIP8_ADDRESS_SPACE_ID, CHIP8_FONT},
+    machine::fault::MachineFault,
+};
 use arrayvec::ArrayVec;
 use bitvec::{
     field::BitField,
@@ -11,26 +14,26 @@ use bitvec::{
     view::BitView,
 };
 use nalgebra::Point2;
-use rand::Rng;
 
 impl Chip8Processor {
     pub(super) fn interpret_instruction(
         &self,
         state: &mut ProcessorState,
         instruction: Chip8InstructionSet,
-    ) {
+    ) -> Result<(), MachineFault> {
+        // The program counter has already moved past the instruction
+        let instruction_address = state.registers.program.wrapping_sub(2) as usize;
+
         match instruction {
             Chip8InstructionSet::Chip8(InstructionSetChip8::Sys { syscall }) => match syscall {
                 0x0e0 => {
                     self.display.clear_display();
                 }
                 0x0ee => {
-                    if let Some(address) = state.stack.pop() {
-                        state.registers.program = address;
-                    } else {
-                        tracing::error!("Stack underflow");
-                        state.registers.program = 0x200;
-                    }
+                    state.registers.program =
+                        state.stack.pop().ok_or(MachineFault::StackUnderflow {
+                            address: instruction_address,
+                        })?;
                 }
                 _ => {
                     tracing::warn!("Unknown syscall: {:#04x}", syscall);
@@ -41,7 +44,12 @@ impl Chip8Processor {
             }
             Chip8InstructionSet::Chip8(InstructionSetChip8::Call { address }) => {
                 let program = state.registers.program;
-                state.stack.push(program);
+                state
+                    .stack
+                    .try_push(program)
+                    .map_err(|_| MachineFault::StackOverflow {
+                        address: instruction_address,
+                    })?;
                 state.registers.program = address;
             }
             Chip8InstructionSet::Chip8(InstructionSetChip8::Ske {
@@ -224,7 +232,7 @@ impl Chip8Processor {
                 immediate,
             }) => {
                 state.registers.work_registers[register as usize] =
-                    rand::rng().random::<u8>() & immediate;
+                    self.rng.random::<u8>() & immediate;
             }
             Chip8InstructionSet::Chip8(InstructionSetChip8::Draw {
                 coordinate_registers,
@@ -233,19 +241,12 @@ impl Chip8Processor {
                 let mut buffer =
                     ArrayVec::<_, 16>::from_iter(std::iter::repeat(0).take(height as usize));
 
-                let mut cursor = 0;
-                for buffer_section in buffer.chunks_mut(2) {
-                    self.memory_translation_table
-                        .get()
-                        .unwrap()
-                        .read(
-                            state.registers.index as usize + cursor,
-                            buffer_section,
-                            CHIP8_ADDRESS_SPACE_ID,
-                        )
-                        .unwrap();
-                    cursor += buffer_section.len();
-                }
+                let address = state.registers.index as usize;
+                self.memory_translation_table
+                    .get()
+                    .unwrap()
+                    .read_block(address, &mut buffer, CHIP8_ADDRESS_SPACE_ID)
+                    .map_err(|_| MachineFault::InvalidMemoryAccess { address })?;
 
                 let actual_coords = Point2::new(
                     state.registers.work_registers[coordinate_registers.x as usize],
@@ -258,7 +259,8 @@ impl Chip8Processor {
             }
             Chip8InstructionSet::Chip8(InstructionSetChip8::Skpr { key }) => {
                 let (input_manager, gamepad_port) = self.input_manager.get().unwrap();
-                let key = Chip8KeyCode(state.registers.work_registers[key as usize]);
+                // The hardware only looks at the low nibble
+                let key = Chip8KeyCode(state.registers.work_registers[key as usize] & 0xf);
 
                 let key_value = input_manager.get_input(*gamepad_port, key.try_into().unwrap());
 
@@ -269,7 +271,7 @@ impl Chip8Processor {
             Chip8InstructionSet::Chip8(InstructionSetChip8::Skup { key }) => {
                 let (input_manager, gamepad_port) = self.input_manager.get().unwrap();
 
-                let key = Chip8KeyCode(state.registers.work_registers[key as usize]);
+                let key = Chip8KeyCode(state.registers.work_registers[key as usize] & 0xf);
 
                 let key_value = input_manager.get_input(*gamepad_port, key.try_into().unwrap());
 
@@ -293,7 +295,7 @@ impl Chip8Processor {
             Chip8InstructionSet::Chip8(InstructionSetChip8::Loads { register }) => {
                 let register_value = state.registers.work_registers[register as usize];
 
-                self.audio.set(register_value);
+                self.audio.post(Chip8AudioMessage::SetTimer(register_value));
             }
             Chip8InstructionSet::Chip8(InstructionSetChip8::Addi { register }) => {
                 let register_value = state.registers.work_registers[register as usize];
@@ -312,39 +314,27 @@ impl Chip8Processor {
 
                 let memory_translation_table = self.memory_translation_table.get().unwrap();
 
-                memory_translation_table
-                    .write(
-                        state.registers.index as usize,
-                        bytemuck::bytes_of(&hundreds),
-                        CHIP8_ADDRESS_SPACE_ID,
-                    )
-                    .unwrap();
-                memory_translation_table
-                    .write(
-                        state.registers.index as usize + 1,
-                        bytemuck::bytes_of(&tens),
-                        CHIP8_ADDRESS_SPACE_ID,
-                    )
-                    .unwrap();
-                memory_translation_table
-                    .write(
-                        state.registers.index as usize + 2,
-                        bytemuck::bytes_of(&ones),
-                        CHIP8_ADDRESS_SPACE_ID,
-                    )
-                    .unwrap();
+                for (offset, digit) in [hundreds, tens, ones].into_iter().enumerate() {
+                    let address = state.registers.index as usize + offset;
+
+                    memory_translation_table
+                        .write(address, bytemuck::bytes_of(&digit), CHIP8_ADDRESS_SPACE_ID)
+                        .map_err(|_| MachineFault::InvalidMemoryAccess { address })?;
+                }
             }
             Chip8InstructionSet::Chip8(InstructionSetChip8::Save { count }) => {
                 let memory_translation_table = self.memory_translation_table.get().unwrap();
 
                 for i in 0..=count {
+                    let address = state.registers.index as usize + i as usize;
+
                     memory_translation_table
                         .write(
-                            state.registers.index as usize + i as usize,
+                            address,
                             &state.registers.work_registers[i as usize..=i as usize],
                             CHIP8_ADDRESS_SPACE_ID,
                         )
-                        .unwrap();
+                        .map_err(|_| MachineFault::InvalidMemoryAccess { address })?;
                 }
 
                 // Only the original chip8 modifies the index register for this operation
@@ -356,13 +346,15 @@ impl Chip8Processor {
                 let memory_translation_table = self.memory_translation_table.get().unwrap();
 
                 for i in 0..=count {
+                    let address = state.registers.index as usize + i as usize;
+
                     memory_translation_table
                         .read(
-                            state.registers.index as usize + i as usize,
+                            address,
                             &mut state.registers.work_registers[i as usize..=i as usize],
                             CHIP8_ADDRESS_SPACE_ID,
                         )
-                        .unwrap();
+                        .map_err(|_| MachineFault::InvalidMemoryAccess { address })?;
                 }
 
                 // Only the original chip8 modifies the index register for this operation
@@ -370,9 +362,34 @@ impl Chip8Processor {
                     state.registers.index = state.registers.index.wrapping_add(count as u16 + 1);
                 }
             }
+            Chip8InstructionSet::Chip8X(instruction) => {
+                // Everything else only has the one keypad
+                let Some(second_keypad_port) = self.second_keypad_port.get() else {
+                    return Err(MachineFault::IllegalInstruction {
+                        address: instruction_address,
+                        opcode: format!("{:?}", instruction),
+                    });
+                };
+                let (input_manager, _) = self.input_manager.get().unwrap();
+
+                let (key, skip_if_pressed) = match instruction {
+                    InstructionSetChip8X::Skpr2 { key } => (key, true),
+                    InstructionSetChip8X::Skup2 { key } => (key, false),
+                };
+                let key = Chip8KeyCode(state.registers.work_registers[key as usize] & 0xf);
+
+                let key_value =
+                    input_manager.get_input(*second_keypad_port, key.try_into().unwrap());
+
+                if key_value.as_digital() == skip_if_pressed {
+                    state.registers.program = state.registers.program.wrapping_add(2);
+                }
+            }
             Chip8InstructionSet::SuperChip8(_) => todo!(),
             Chip8InstructionSet::XoChip(_) => todo!(),
         }
+
+        Ok(())
     }
 }
 
@@ -1,18 +1,28 @@
-use super::{audio::Chip8Audio, display::Chip8Display, timer::Chip8Timer, Chip8Kind};
+use super::{audio::Chip8AudioMessage, display::Chip8Display, timer::Chip8Timer, Chip8Kind};
 use crate::{
     component::{
-        input::{EmulatedGamepadMetadata, InputComponent},
-        schedulable::SchedulableComponent,
-        Component, ComponentId, FromConfig,
+        input::InputComponent,
+        introspectable::{DebugValue, IntrospectableComponent},
+        schedulable::{RunContext, SchedulableComponent},
+        Component, ComponentId, FromConfig, ResetKind, SnapshotError,
     },
     definitions::chip8::CHIP8_ADDRESS_SPACE_ID,
-    input::{manager::InputManager, EmulatedGamepadId},
-    machine::ComponentBuilder,
+    input::{
+        manager::InputManager,
+        registry::{gamepad_type, CHIP8_KEYPAD_GAMEPAD_TYPE},
+        EmulatedGamepadId,
+    },
+    machine::{
+        fault::{FaultReporter, MachineFault},
+        message::MessageSender,
+        rng::MachineRng,
+        ComponentBuilder, MachineBuildError,
+    },
     memory::MemoryTranslationTable,
 };
 use arrayvec::ArrayVec;
 use decode::decode_instruction;
-use input::{default_bindings, present_inputs, Chip8KeyCode, CHIP8_KEYPAD_GAMEPAD_TYPE};
+use input::Chip8KeyCode;
 use instruction::Register;
 use num::rational::Ratio;
 use serde::{Deserialize, Serialize};
@@ -78,8 +88,8 @@ pub struct Chip8Processor {
     config: Chip8ProcessorConfig,
     /// chip8 display component
     display: Arc<Chip8Display>,
-    /// chip8 audio component
-    audio: Arc<Chip8Audio>,
+    /// chip8 audio component, which only ever gets told things so it does not need to be locked
+    audio: MessageSender<Chip8AudioMessage>,
     /// chip8 timer component
     timer: Arc<Chip8Timer>,
     /// parts of the cpu that actually change over execution
@@ -88,6 +98,12 @@ pub struct Chip8Processor {
     memory_translation_table: OnceLock<Arc<MemoryTranslationTable>>,
     /// input manager + port for our keypad
     input_manager: OnceLock<(Arc<InputManager>, EmulatedGamepadId)>,
+    /// port for the second keypad, only a chip8x has one
+    second_keypad_port: OnceLock<EmulatedGamepadId>,
+    /// where we report bad programs
+    fault_reporter: OnceLock<Arc<FaultReporter>>,
+    /// machine randomness, for the rand instruction
+    rng: Arc<MachineRng>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -98,7 +114,7 @@ pub struct Chip8ProcessorSnapshot {
 }
 
 impl Component for Chip8Processor {
-    fn reset(&self) {
+    fn reset(&self, _kind: ResetKind) {
         let mut state = self.state.lock().unwrap();
 
         state.stack.clear();
@@ -117,13 +133,15 @@ impl Component for Chip8Processor {
         .unwrap()
     }
 
-    fn load_snapshot(&self, state: rmpv::Value) {
-        let snapshot: Chip8ProcessorSnapshot = rmpv::ext::from_value(state).unwrap();
+    fn load_snapshot(&self, state: rmpv::Value) -> Result<(), SnapshotError> {
+        let snapshot: Chip8ProcessorSnapshot = rmpv::ext::from_value(state)?;
         let mut state = self.state.lock().unwrap();
 
         state.registers = snapshot.registers;
         state.stack = snapshot.stack;
         state.execution_state = snapshot.execution_state;
+
+        Ok(())
     }
 
     fn set_memory_translation_table(&self, memory_translation_table: Arc<MemoryTranslationTable>) {
@@ -131,16 +149,29 @@ impl Component for Chip8Processor {
             .set(memory_translation_table)
             .unwrap();
     }
+
+    fn set_fault_reporter(&self, fault_reporter: Arc<FaultReporter>) {
+        self.fault_reporter.set(fault_reporter).unwrap();
+    }
 }
 
 impl FromConfig for Chip8Processor {
     type Config = Chip8ProcessorConfig;
 
-    fn from_config(component_builder: &mut ComponentBuilder<Self>, config: Self::Config)
+    fn from_config(
+        component_builder: &mut ComponentBuilder<Self>,
+        config: Self::Config,
+    ) -> Result<(), MachineBuildError>
     where
         Self: Sized,
     {
         let frequency = config.frequency;
+        let timer = config.timer;
+        let keypads = if config.kind == Chip8Kind::Chip8x {
+            vec![CHIP8_KEYPAD_GAMEPAD_TYPE; 2]
+        } else {
+            vec![CHIP8_KEYPAD_GAMEPAD_TYPE]
+        };
 
         component_builder
             .set_component(Self {
@@ -149,33 +180,26 @@ impl FromConfig for Chip8Processor {
                     registers: Chip8ProcessorRegisters::default(),
                     execution_state: ExecutionState::Normal,
                 }),
-                display: component_builder
-                    .machine()
-                    .get_component(config.display)
-                    .expect("Display component not found"),
-                audio: component_builder
-                    .machine()
-                    .get_component(config.audio)
-                    .expect("Audio component not found"),
-                timer: component_builder
-                    .machine()
-                    .get_component(config.timer)
-                    .expect("Timer component not found"),
+                display: component_builder.machine().get_component(config.display)?,
+                audio: component_builder.machine().message_sender(config.audio)?,
+                timer: component_builder.machine().get_component(config.timer)?,
+                rng: component_builder.machine().rng.clone(),
                 config,
                 memory_translation_table: OnceLock::default(),
                 input_manager: OnceLock::default(),
+                second_keypad_port: OnceLock::default(),
+                fault_reporter: OnceLock::default(),
             })
-            .set_schedulable(frequency, [], [])
+            // The timer has to have ticked down before the program reads it each frame
+            .set_schedulable(frequency, [timer], [])
             .set_input(
-                [(
-                    CHIP8_KEYPAD_GAMEPAD_TYPE,
-                    EmulatedGamepadMetadata {
-                        present_inputs: present_inputs(),
-                        default_bindings: default_bindings(),
-                    },
-                )],
-                [CHIP8_KEYPAD_GAMEPAD_TYPE],
-            );
+                [gamepad_type(&CHIP8_KEYPAD_GAMEPAD_TYPE).registration()],
+                keypads,
+            )
+            .set_debug()
+            .set_label("cpu");
+
+        Ok(())
     }
 }
 
@@ -194,28 +218,75 @@ impl InputComponent for Chip8Processor {
                     .expect("Input manager did not allocate our gamepad"),
             ))
             .expect("Input manager set multiple times");
+
+        if let Some(second_keypad_port) = gamepad_ports.get(1) {
+            self.second_keypad_port.set(*second_keypad_port).unwrap();
+        }
+    }
+}
+
+impl IntrospectableComponent for Chip8Processor {
+    fn debug_state(&self) -> Vec<(String, DebugValue)> {
+        let state = self.state.lock().unwrap();
+
+        let mut debug_state: Vec<_> = state
+            .registers
+            .work_registers
+            .iter()
+            .enumerate()
+            .map(|(index, value)| (format!("V{:X}", index), DebugValue::U8(*value)))
+            .collect();
+        debug_state.extend([
+            ("I".to_string(), DebugValue::U16(state.registers.index)),
+            ("PC".to_string(), DebugValue::U16(state.registers.program)),
+            (
+                "Stack Depth".to_string(),
+                DebugValue::U8(state.stack.len() as u8),
+            ),
+            (
+                "Waiting For Key".to_string(),
+                DebugValue::Flag(!matches!(state.execution_state, ExecutionState::Normal)),
+            ),
+        ]);
+
+        debug_state
+    }
+}
+
+impl Chip8Processor {
+    fn report_fault(&self, fault: MachineFault) {
+        self.fault_reporter.get().unwrap().report(fault);
     }
 }
 
 impl SchedulableComponent for Chip8Processor {
-    fn run(&self, period: u64) {
+    fn run(&self, period: u64, _context: RunContext) {
         let mut state = self.state.lock().unwrap();
 
         for _ in 0..period {
             match &state.execution_state {
                 ExecutionState::Normal => {
+                    let program = state.registers.program as usize;
                     let mut instruction = [0; 2];
-                    self.memory_translation_table
+
+                    if self
+                        .memory_translation_table
                         .get()
                         .unwrap()
-                        .read(
-                            state.registers.program as usize,
-                            &mut instruction,
-                            CHIP8_ADDRESS_SPACE_ID,
-                        )
-                        .unwrap();
-
-                    let decompiled_instruction = decode_instruction(instruction).unwrap();
+                        .read(program, &mut instruction, CHIP8_ADDRESS_SPACE_ID)
+                        .is_err()
+                    {
+                        self.report_fault(MachineFault::InvalidMemoryAccess { address: program });
+                        return;
+                    }
+
+                    let Ok(decompiled_instruction) = decode_instruction(instruction) else {
+                        self.report_fault(MachineFault::IllegalInstruction {
+                            address: program,
+                            opcode: format!("{:#06x}", u16::from_be_bytes(instruction)),
+                        });
+                        return;
+                    };
                     state.registers.program = state.registers.program.wrapping_add(2);
 
                     tracing::trace!(
@@ -224,7 +295,12 @@ impl SchedulableComponent for Chip8Processor {
                         state.registers.program
                     );
 
-                    self.interpret_instruction(&mut state, decompiled_instruction);
+                    if let Err(fault) =
+                        self.interpret_instruction(&mut state, decompiled_instruction)
+                    {
+                        self.report_fault(fault);
+                        return;
+                    }
                 }
                 ExecutionState::AwaitingKeyPress { register } => {
                     // FIXME: A allocation every cycle isn't a good idea
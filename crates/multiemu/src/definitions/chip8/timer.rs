@@ -1,8 +1,11 @@
 use std::sync::Mutex;
 
 use crate::{
-    component::{schedulable::SchedulableComponent, Component, FromConfig},
-    machine::ComponentBuilder,
+    component::{
+        schedulable::{RunContext, SchedulableComponent},
+        Component, FromConfig,
+    },
+    machine::{ComponentBuilder, MachineBuildError},
 };
 use num::rational::Ratio;
 
@@ -27,17 +30,23 @@ impl Component for Chip8Timer {}
 impl FromConfig for Chip8Timer {
     type Config = ();
 
-    fn from_config(component_builder: &mut ComponentBuilder<Self>, _config: Self::Config) {
+    fn from_config(
+        component_builder: &mut ComponentBuilder<Self>,
+        _config: Self::Config,
+    ) -> Result<(), MachineBuildError> {
         component_builder
             .set_component(Self {
                 delay_timer: Mutex::new(0),
             })
-            .set_schedulable(Ratio::from_integer(60), [], []);
+            .set_schedulable(Ratio::from_integer(60), [], [])
+            .set_label("timer");
+
+        Ok(())
     }
 }
 
 impl SchedulableComponent for Chip8Timer {
-    fn run(&self, period: u64) {
+    fn run(&self, period: u64, _context: RunContext) {
         let mut delay_timer_guard = self.delay_timer.lock().unwrap();
 
         *delay_timer_guard = delay_timer_guard.saturating_sub(period.try_into().unwrap_or(u8::MAX));
@@ -0,0 +1,359 @@
+use super::standard::StandardMemoryInitialContents;
+use crate::{
+    component::{memory::MemoryComponent, Component, FromConfig, ResetKind, SnapshotError},
+    machine::{rng::MachineRng, ComponentBuilder, MachineBuildError},
+    memory::{AddressSpaceId, ReadMemoryRecord, WriteMemoryRecord, VALID_ACCESS_SIZES},
+    rom::manager::{RomManager, RomRequirement},
+};
+use rangemap::RangeMap;
+use serde::{Deserialize, Serialize};
+use std::{
+    io::Read,
+    ops::Range,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+};
+
+#[derive(Debug)]
+pub struct BankedMemoryConfig {
+    pub readable: bool,
+    pub writable: bool,
+    pub max_word_size: usize,
+    /// The window the active bank shows through, every bank is this big
+    pub assigned_range: Range<usize>,
+    /// Address space this exists on
+    pub assigned_address_space: AddressSpaceId,
+    pub bank_count: usize,
+    /// Address that switches banks when written to, for memory that does not leave it up to a mapper
+    pub bank_register: Option<usize>,
+    /// Offsets count from the start of the first bank, with the banks one after another
+    pub initial_contents: StandardMemoryInitialContents,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BankedMemorySnapshot {
+    pub active_bank: usize,
+    pub memory: Vec<u8>,
+}
+
+/// Memory bigger than the window it is mapped into, only one bank of it visible at a time
+///
+/// Mappers get a hold of this with [crate::machine::MachineBuilder::get_component] and call
+/// [BankedMemory::set_active_bank]
+#[derive(Debug)]
+pub struct BankedMemory {
+    config: BankedMemoryConfig,
+    /// Every bank, one after another
+    buffer: Mutex<Vec<u8>>,
+    active_bank: AtomicUsize,
+    rom_manager: Arc<RomManager>,
+    rng: Arc<MachineRng>,
+}
+
+impl BankedMemory {
+    pub fn bank_count(&self) -> usize {
+        self.config.bank_count
+    }
+
+    pub fn active_bank(&self) -> usize {
+        self.active_bank.load(Ordering::Relaxed)
+    }
+
+    /// Banks past the end wrap around, like the bits a mapper register has no use for
+    pub fn set_active_bank(&self, bank: usize) {
+        self.active_bank
+            .store(bank % self.config.bank_count, Ordering::Relaxed);
+    }
+
+    fn bank_size(&self) -> usize {
+        self.config.assigned_range.len()
+    }
+
+    /// Where `address` in the window lands in the whole buffer
+    fn buffer_offset(&self, address: usize) -> usize {
+        self.active_bank() * self.bank_size() + (address - self.config.assigned_range.start)
+    }
+
+    fn initialize_buffer(&self) {
+        let mut buffer = self.buffer.lock().unwrap();
+
+        match &self.config.initial_contents {
+            StandardMemoryInitialContents::Value { value } => buffer.fill(*value),
+            StandardMemoryInitialContents::Random => self.rng.fill_bytes(&mut buffer),
+            StandardMemoryInitialContents::Array { value, offset } => {
+                let end = (*offset + value.len()).min(buffer.len());
+                buffer[*offset..end].copy_from_slice(&value[..end - *offset]);
+            }
+            StandardMemoryInitialContents::Rom { rom_id, offset } => {
+                // Nothing inserted reads like an empty slot
+                buffer.fill(0);
+
+                let Some(rom_id) = rom_id else {
+                    return;
+                };
+
+                let mut rom_file = self
+                    .rom_manager
+                    .open(*rom_id, RomRequirement::Required)
+                    .unwrap();
+                let mut total_read = *offset;
+
+                while total_read < buffer.len() {
+                    let amount = rom_file
+                        .read(&mut buffer[total_read..])
+                        .expect("Could not read rom");
+
+                    if amount == 0 {
+                        break;
+                    }
+
+                    total_read += amount;
+                }
+            }
+        }
+
+        self.active_bank.store(0, Ordering::Relaxed);
+    }
+
+    /// The access if it strays outside of both the window and the bank register
+    fn out_of_range(&self, affected_range: Range<usize>) -> Option<Range<usize>> {
+        let is_register = self
+            .config
+            .bank_register
+            .is_some_and(|register| affected_range == (register..register + 1));
+
+        if is_register
+            || (self.config.assigned_range.contains(&affected_range.start)
+                && affected_range.end <= self.config.assigned_range.end)
+        {
+            None
+        } else {
+            Some(affected_range)
+        }
+    }
+}
+
+impl Component for BankedMemory {
+    fn reset(&self, kind: ResetKind) {
+        match kind {
+            ResetKind::Soft => self.active_bank.store(0, Ordering::Relaxed),
+            ResetKind::Hard => self.initialize_buffer(),
+        }
+    }
+
+    fn save_snapshot(&self) -> rmpv::Value {
+        let state = BankedMemorySnapshot {
+            active_bank: self.active_bank(),
+            memory: self.buffer.lock().unwrap().clone(),
+        };
+
+        rmpv::ext::to_value(&state).unwrap()
+    }
+
+    fn load_snapshot(&self, state: rmpv::Value) -> Result<(), SnapshotError> {
+        let state = rmpv::ext::from_value::<BankedMemorySnapshot>(state)?;
+        let mut buffer = self.buffer.lock().unwrap();
+
+        if state.memory.len() != buffer.len() {
+            return Err(SnapshotError::SizeMismatch {
+                expected: buffer.len(),
+                found: state.memory.len(),
+            });
+        }
+
+        buffer.copy_from_slice(&state.memory);
+        self.set_active_bank(state.active_bank);
+
+        Ok(())
+    }
+}
+
+impl FromConfig for BankedMemory {
+    type Config = BankedMemoryConfig;
+
+    fn from_config(
+        component_builder: &mut ComponentBuilder<Self>,
+        config: Self::Config,
+    ) -> Result<(), MachineBuildError> {
+        assert!(
+            VALID_ACCESS_SIZES.contains(&config.max_word_size),
+            "Invalid word size"
+        );
+        assert!(
+            !config.assigned_range.is_empty(),
+            "Memory assigned must be non-empty"
+        );
+        assert!(config.bank_count != 0, "There must be at least one bank");
+        assert!(
+            !config
+                .bank_register
+                .is_some_and(|register| config.assigned_range.contains(&register)),
+            "The bank register cannot be inside of the banked window"
+        );
+
+        let assigned_address_space = config.assigned_address_space;
+        let assigned_ranges = std::iter::once(config.assigned_range.clone())
+            .chain(config.bank_register.map(|register| register..register + 1))
+            .map(|range| (assigned_address_space, range))
+            .collect::<Vec<_>>();
+
+        let me = Self {
+            buffer: Mutex::new(vec![0; config.assigned_range.len() * config.bank_count]),
+            active_bank: AtomicUsize::new(0),
+            rom_manager: component_builder.machine().rom_manager.clone(),
+            rng: component_builder.machine().rng.clone(),
+            config,
+        };
+        me.initialize_buffer();
+
+        component_builder
+            .set_component(me)
+            .set_memory(assigned_ranges);
+
+        Ok(())
+    }
+}
+
+impl MemoryComponent for BankedMemory {
+    fn read_memory(
+        &self,
+        address: usize,
+        buffer: &mut [u8],
+        _address_space: AddressSpaceId,
+        errors: &mut RangeMap<usize, ReadMemoryRecord>,
+    ) {
+        debug_assert!(
+            VALID_ACCESS_SIZES.contains(&buffer.len()),
+            "Invalid memory access size {}",
+            buffer.len()
+        );
+
+        let affected_range = address..address + buffer.len();
+
+        if !self.config.readable || buffer.len() > self.config.max_word_size {
+            errors.insert(affected_range.clone(), ReadMemoryRecord::Denied);
+        }
+
+        if let Some(invalid_range) = self.out_of_range(affected_range) {
+            errors.insert(invalid_range, ReadMemoryRecord::Denied);
+        }
+
+        if !errors.is_empty() {
+            return;
+        }
+
+        if self.config.bank_register == Some(address) {
+            buffer[0] = self.active_bank() as u8;
+            return;
+        }
+
+        let offset = self.buffer_offset(address);
+        buffer.copy_from_slice(&self.buffer.lock().unwrap()[offset..offset + buffer.len()]);
+    }
+
+    fn write_memory(
+        &self,
+        address: usize,
+        buffer: &[u8],
+        _address_space: AddressSpaceId,
+        errors: &mut RangeMap<usize, WriteMemoryRecord>,
+    ) {
+        debug_assert!(
+            VALID_ACCESS_SIZES.contains(&buffer.len()),
+            "Invalid memory access size {}",
+            buffer.len()
+        );
+
+        let affected_range = address..address + buffer.len();
+
+        // The bank register still works on memory that is read only, since that is what cartridge roms do
+        if self.config.bank_register == Some(address) && buffer.len() == 1 {
+            self.set_active_bank(buffer[0] as usize);
+            return;
+        }
+
+        if !self.config.writable || buffer.len() > self.config.max_word_size {
+            errors.insert(affected_range.clone(), WriteMemoryRecord::Denied);
+        }
+
+        if let Some(invalid_range) = self.out_of_range(affected_range) {
+            errors.insert(invalid_range, WriteMemoryRecord::Denied);
+        }
+
+        if !errors.is_empty() {
+            return;
+        }
+
+        let offset = self.buffer_offset(address);
+        self.buffer.lock().unwrap()[offset..offset + buffer.len()].copy_from_slice(buffer);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        machine::Machine,
+        rom::{manager::RomManager, system::GameSystem},
+    };
+    use std::borrow::Cow;
+
+    const ADDRESS_SPACE: AddressSpaceId = 0;
+
+    #[test]
+    fn switching_banks() {
+        let rom_manager = Arc::new(RomManager::new(None).unwrap());
+        let (machine, banked_memory) = Machine::build(GameSystem::Unknown, rom_manager)
+            .insert_bus(ADDRESS_SPACE, 16)
+            .build_component::<BankedMemory>(BankedMemoryConfig {
+                readable: true,
+                writable: false,
+                max_word_size: 2,
+                assigned_range: 0x8000..0x8004,
+                assigned_address_space: ADDRESS_SPACE,
+                bank_count: 3,
+                bank_register: Some(0xffff),
+                initial_contents: StandardMemoryInitialContents::Array {
+                    offset: 0,
+                    value: Cow::Borrowed(&[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11]),
+                },
+            })
+            .unwrap();
+        let banked_memory = machine
+            .get_component::<BankedMemory>(banked_memory)
+            .unwrap();
+        let machine = machine.build().unwrap();
+        let mut buffer = [0; 2];
+
+        machine
+            .memory_translation_table
+            .read(0x8002, &mut buffer, ADDRESS_SPACE)
+            .unwrap();
+        assert_eq!(buffer, [2, 3]);
+
+        machine
+            .memory_translation_table
+            .write(0xffff, &[2], ADDRESS_SPACE)
+            .unwrap();
+        machine
+            .memory_translation_table
+            .read(0x8002, &mut buffer, ADDRESS_SPACE)
+            .unwrap();
+        assert_eq!(buffer, [10, 11]);
+
+        // Mappers switch without going through the bus, and past the end wraps around
+        banked_memory.set_active_bank(4);
+        machine
+            .memory_translation_table
+            .read(0x8000, &mut buffer, ADDRESS_SPACE)
+            .unwrap();
+        assert_eq!(buffer, [4, 5]);
+
+        assert!(machine
+            .memory_translation_table
+            .write(0x8000, &[0xff], ADDRESS_SPACE)
+            .is_err());
+    }
+}
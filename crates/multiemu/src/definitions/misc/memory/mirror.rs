@@ -1,6 +1,6 @@
 use crate::{
     component::{memory::MemoryComponent, Component, FromConfig},
-    machine::ComponentBuilder,
+    machine::{ComponentBuilder, MachineBuildError},
     memory::{AddressSpaceId, ReadMemoryRecord, WriteMemoryRecord, VALID_ACCESS_SIZES},
 };
 use rangemap::RangeMap;
@@ -24,7 +24,10 @@ impl Component for MirrorMemory {}
 impl FromConfig for MirrorMemory {
     type Config = MirrorMemoryConfig;
 
-    fn from_config(component_builder: &mut ComponentBuilder<Self>, config: Self::Config) {
+    fn from_config(
+        component_builder: &mut ComponentBuilder<Self>,
+        config: Self::Config,
+    ) -> Result<(), MachineBuildError> {
         let assigned_address_space = config.assigned_address_space;
         let assigned_ranges = config.assigned_ranges.clone();
 
@@ -33,6 +36,8 @@ impl FromConfig for MirrorMemory {
                 .into_iter()
                 .map(|(assignment, _)| (assigned_address_space, assignment)),
         );
+
+        Ok(())
     }
 }
 
@@ -135,6 +140,7 @@ mod test {
                 assigned_address_space: ADDRESS_SPACE,
                 initial_contents: StandardMemoryInitialContents::Value { value: 0xff },
             })
+            .unwrap()
             .0
             .build_component::<MirrorMemory>(MirrorMemoryConfig {
                 readable: true,
@@ -142,8 +148,10 @@ mod test {
                 assigned_ranges: RangeMap::from_iter([(0x10000..0x20000, 0x0000)]),
                 assigned_address_space: ADDRESS_SPACE,
             })
+            .unwrap()
             .0
-            .build();
+            .build()
+            .unwrap();
         let mut buffer = [0; 8];
 
         machine
@@ -166,6 +174,7 @@ mod test {
                 assigned_address_space: ADDRESS_SPACE,
                 initial_contents: StandardMemoryInitialContents::Value { value: 0xff },
             })
+            .unwrap()
             .0
             .build_component::<MirrorMemory>(MirrorMemoryConfig {
                 readable: true,
@@ -173,8 +182,10 @@ mod test {
                 assigned_ranges: RangeMap::from_iter([(0x10000..0x20000, 0x0000)]),
                 assigned_address_space: ADDRESS_SPACE,
             })
+            .unwrap()
             .0
-            .build();
+            .build()
+            .unwrap();
         let buffer = [0; 8];
 
         machine
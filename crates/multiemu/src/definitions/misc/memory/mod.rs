@@ -1,3 +1,7 @@
+pub mod banked;
 pub mod mirror;
+pub mod persistent;
+pub mod register;
 pub mod rom;
+pub mod shared;
 pub mod standard;
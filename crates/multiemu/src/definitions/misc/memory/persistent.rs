@@ -0,0 +1,506 @@
+use crate::{
+    component::{
+        event_driven::EventDrivenComponent, memory::MemoryComponent, Component, ComponentId,
+        FromConfig, ResetKind, SnapshotError,
+    },
+    config::GLOBAL_CONFIG,
+    machine::{ComponentBuilder, MachineBuildError},
+    memory::{AddressSpaceId, ReadMemoryRecord, WriteMemoryRecord, VALID_ACCESS_SIZES},
+    rom::id::RomId,
+    runtime::executor::{Executor, TaskHandle},
+    scheduler::EventQueue,
+};
+use bitvec::vec::BitVec;
+use rangemap::RangeMap;
+use serde::{Deserialize, Serialize};
+use std::{
+    any::type_name,
+    borrow::Cow,
+    fs::{create_dir_all, rename, File},
+    io::{Read, Write},
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{channel, RecvTimeoutError, Sender},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+/// How much emulated time can pass between writes reaching the disk if not configured, on top of when the machine is
+/// torn down
+pub const DEFAULT_FLUSH_INTERVAL: Duration = Duration::from_secs(5);
+/// Writes are tracked in chunks this big, so a flush only has to copy out what changed
+const CHUNK_SIZE: usize = 256;
+/// How often the writer checks if the machine's executor is shutting down while it has nothing to write
+const STOP_POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// How long shutting down waits for the last write
+const FINISH_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug)]
+pub struct PersistentMemoryConfig {
+    // The maximum word size
+    pub max_word_size: usize,
+    // Memory region this buffer will be mapped to
+    pub assigned_range: Range<usize>,
+    /// Address space this exists on
+    pub assigned_address_space: AddressSpaceId,
+    /// Game the save belongs to
+    pub rom_id: RomId,
+    /// Tells saves of the same game apart, for games with more than one kind of persistent memory
+    pub name: Cow<'static, str>,
+    /// Where saves go instead of [crate::config::GlobalConfig::save_directory]
+    pub save_directory: Option<PathBuf>,
+    /// Used instead of [crate::config::GlobalConfig::save_flush_interval]
+    pub flush_interval: Option<Duration>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PersistentMemorySnapshot {
+    pub memory: Vec<u8>,
+}
+
+#[derive(Debug)]
+struct Contents {
+    buffer: Vec<u8>,
+    /// Chunks written to since the last flush
+    dirty_chunks: BitVec,
+}
+
+impl Contents {
+    fn mark_dirty(&mut self, range: Range<usize>) {
+        let chunks = range.start / CHUNK_SIZE..range.end.div_ceil(CHUNK_SIZE);
+        self.dirty_chunks[chunks].fill(true);
+    }
+
+    /// Copies of every run of dirty chunks along with where they go, clearing them
+    fn take_changes(&mut self) -> Vec<(usize, Vec<u8>)> {
+        let mut changes = Vec::new();
+        let mut chunk = 0;
+
+        while let Some(run_start) = self.dirty_chunks[chunk..]
+            .first_one()
+            .map(|run| run + chunk)
+        {
+            let run_end = self.dirty_chunks[run_start..]
+                .first_zero()
+                .map_or(self.dirty_chunks.len(), |run| run + run_start);
+            let range = run_start * CHUNK_SIZE..(run_end * CHUNK_SIZE).min(self.buffer.len());
+
+            changes.push((range.start, self.buffer[range].to_vec()));
+            chunk = run_end;
+        }
+
+        self.dirty_chunks.fill(false);
+        changes
+    }
+}
+
+/// Memory like [super::standard::StandardMemory] that keeps its contents between runs, like battery backed cartridge RAM
+///
+/// Contents are loaded from `<save directory>/<rom id>/<name>.sav` when built. Every flush interval of emulated time
+/// the chunks that changed are handed to a task on [crate::machine::Machine::executor] that does the writing, so a slow
+/// disk never holds up a frame. The last of it is waited on when the machine shuts down
+#[derive(Debug)]
+pub struct PersistentMemory {
+    config: PersistentMemoryConfig,
+    contents: Mutex<Contents>,
+    /// Gone once shut down
+    writer: Mutex<Option<SaveWriter>>,
+    save_path: PathBuf,
+    /// Settled on when built, the config is not ours to lock from inside the machine
+    flush_interval: Duration,
+    id: ComponentId,
+    events: Arc<EventQueue>,
+}
+
+impl PersistentMemory {
+    pub fn save_path(&self) -> &Path {
+        &self.save_path
+    }
+
+    /// Hands what changed since the last time to the writer
+    pub fn flush(&self) {
+        let changes = self.contents.lock().unwrap().take_changes();
+
+        if let Some(writer) = self.writer.lock().unwrap().as_ref() {
+            writer.send(changes);
+        }
+    }
+}
+
+/// Keeps its own copy of the save, patched with the changes it is sent and then written out whole
+#[derive(Debug)]
+struct SaveWriter {
+    sender: Sender<Vec<(usize, Vec<u8>)>>,
+    task: TaskHandle,
+    /// Set while the last write did not make it, so the next flush tries again even if nothing changed
+    failed: Arc<AtomicBool>,
+}
+
+impl SaveWriter {
+    fn spawn(executor: &Executor, save_path: PathBuf, mut contents: Vec<u8>) -> Self {
+        let (sender, receiver) = channel::<Vec<(usize, Vec<u8>)>>();
+        let failed = Arc::new(AtomicBool::new(false));
+        let task_failed = failed.clone();
+
+        let task = executor.spawn("save writer", move |context| loop {
+            let changes = match receiver.recv_timeout(STOP_POLL_INTERVAL) {
+                Ok(changes) => changes,
+                // Only happens if the machine went away without being shut down, so nothing would be flushed anyway
+                Err(RecvTimeoutError::Timeout) if context.should_stop() => return,
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(RecvTimeoutError::Disconnected) => return,
+            };
+
+            // Anything that piled up while the last write was going goes out in one go
+            for changes in std::iter::once(changes).chain(receiver.try_iter()) {
+                for (offset, bytes) in changes {
+                    contents[offset..offset + bytes.len()].copy_from_slice(&bytes);
+                }
+            }
+
+            match write_save(&save_path, &contents) {
+                Ok(()) => task_failed.store(false, Ordering::Release),
+                Err(err) => {
+                    tracing::error!("Could not write save to {}: {}", save_path.display(), err);
+                    task_failed.store(true, Ordering::Release);
+                }
+            }
+        });
+
+        Self {
+            sender,
+            task,
+            failed,
+        }
+    }
+
+    fn send(&self, changes: Vec<(usize, Vec<u8>)>) {
+        if changes.is_empty() && !self.failed.load(Ordering::Acquire) {
+            return;
+        }
+
+        // The thread only stops once this is dropped
+        let _ = self.sender.send(changes);
+    }
+
+    /// Waits for everything sent to be written
+    fn finish(self) {
+        drop(self.sender);
+
+        if !self.task.wait(FINISH_TIMEOUT) {
+            tracing::error!("Save writer did not finish within {:?}", FINISH_TIMEOUT);
+        }
+    }
+}
+
+/// Goes through a temporary file so a crash halfway through never leaves a truncated save behind
+fn write_save(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        create_dir_all(parent)?;
+    }
+
+    let temporary_path = path.with_extension("sav.tmp");
+    let mut file = File::create(&temporary_path)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    rename(temporary_path, path)
+}
+
+impl Component for PersistentMemory {
+    fn reset(&self, _kind: ResetKind) {
+        // The contents survive a reset, it is only the flush wakeup that was cleared
+        self.events.schedule_event_in(self.id, self.flush_interval);
+    }
+
+    fn save_snapshot(&self) -> rmpv::Value {
+        let state = PersistentMemorySnapshot {
+            memory: self.contents.lock().unwrap().buffer.clone(),
+        };
+
+        rmpv::ext::to_value(&state).unwrap()
+    }
+
+    fn load_snapshot(&self, state: rmpv::Value) -> Result<(), SnapshotError> {
+        let state = rmpv::ext::from_value::<PersistentMemorySnapshot>(state)?;
+        let mut contents = self.contents.lock().unwrap();
+
+        if state.memory.len() != contents.buffer.len() {
+            return Err(SnapshotError::SizeMismatch {
+                expected: contents.buffer.len(),
+                found: state.memory.len(),
+            });
+        }
+
+        contents.buffer.copy_from_slice(&state.memory);
+        contents.dirty_chunks.fill(true);
+
+        Ok(())
+    }
+
+    fn shutdown(&self) {
+        self.flush();
+
+        if let Some(writer) = self.writer.lock().unwrap().take() {
+            writer.finish();
+        }
+    }
+}
+
+impl Drop for PersistentMemory {
+    fn drop(&mut self) {
+        // Nothing left to write if the machine was shut down first
+        self.shutdown();
+    }
+}
+
+impl FromConfig for PersistentMemory {
+    type Config = PersistentMemoryConfig;
+
+    fn from_config(
+        component_builder: &mut ComponentBuilder<Self>,
+        config: Self::Config,
+    ) -> Result<(), MachineBuildError> {
+        if !VALID_ACCESS_SIZES.contains(&config.max_word_size) {
+            return Err(MachineBuildError::InvalidWordSize {
+                component: type_name::<Self>(),
+                size: config.max_word_size,
+            });
+        }
+
+        if config.assigned_range.is_empty() {
+            return Err(MachineBuildError::EmptyMemoryRange(type_name::<Self>()));
+        }
+
+        let save_directory = config
+            .save_directory
+            .clone()
+            .unwrap_or_else(|| GLOBAL_CONFIG.read().unwrap().save_directory.clone());
+        let save_path = save_directory
+            .join(config.rom_id.to_string())
+            .join(format!("{}.sav", config.name));
+
+        let mut buffer = Vec::with_capacity(config.assigned_range.len());
+        match File::open(&save_path) {
+            Ok(mut file) => {
+                if let Err(err) = file.read_to_end(&mut buffer) {
+                    tracing::error!("Could not read save {}: {}", save_path.display(), err);
+                    buffer.clear();
+                } else {
+                    tracing::info!("Loaded save {}", save_path.display());
+                }
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+            Err(err) => tracing::error!("Could not open save {}: {}", save_path.display(), err),
+        }
+
+        if !buffer.is_empty() && buffer.len() != config.assigned_range.len() {
+            tracing::warn!(
+                "Save {} is {} bytes but the memory is {} bytes",
+                save_path.display(),
+                buffer.len(),
+                config.assigned_range.len()
+            );
+        }
+        buffer.resize(config.assigned_range.len(), 0);
+
+        let flush_interval = config
+            .flush_interval
+            .unwrap_or_else(|| GLOBAL_CONFIG.read().unwrap().save_flush_interval);
+        let id = component_builder.id();
+        let events = component_builder.machine().events.clone();
+        events.schedule_event_in(id, flush_interval);
+
+        let assigned_range = config.assigned_range.clone();
+        let assigned_address_space = config.assigned_address_space;
+        let writer = SaveWriter::spawn(
+            &component_builder.machine().executor,
+            save_path.clone(),
+            buffer.clone(),
+        );
+
+        component_builder
+            .set_component(Self {
+                config,
+                contents: Mutex::new(Contents {
+                    dirty_chunks: BitVec::repeat(false, buffer.len().div_ceil(CHUNK_SIZE)),
+                    buffer,
+                }),
+                writer: Mutex::new(Some(writer)),
+                save_path,
+                flush_interval,
+                id,
+                events,
+            })
+            .set_memory([(assigned_address_space, assigned_range)])
+            .set_event_driven();
+
+        Ok(())
+    }
+}
+
+impl EventDrivenComponent for PersistentMemory {
+    fn wake(&self, _now: Duration) {
+        // Only copying happens here, the writer does the slow part
+        self.flush();
+
+        self.events.schedule_event_in(self.id, self.flush_interval);
+    }
+}
+
+impl MemoryComponent for PersistentMemory {
+    fn read_memory(
+        &self,
+        address: usize,
+        buffer: &mut [u8],
+        _address_space: AddressSpaceId,
+        errors: &mut RangeMap<usize, ReadMemoryRecord>,
+    ) {
+        debug_assert!(
+            VALID_ACCESS_SIZES.contains(&buffer.len()),
+            "Invalid memory access size {}",
+            buffer.len()
+        );
+
+        let affected_range = address..address + buffer.len();
+
+        if buffer.len() > self.config.max_word_size
+            || affected_range.end > self.config.assigned_range.end
+        {
+            errors.insert(affected_range, ReadMemoryRecord::Denied);
+            return;
+        }
+
+        let offset = address - self.config.assigned_range.start;
+        buffer
+            .copy_from_slice(&self.contents.lock().unwrap().buffer[offset..offset + buffer.len()]);
+    }
+
+    fn write_memory(
+        &self,
+        address: usize,
+        buffer: &[u8],
+        _address_space: AddressSpaceId,
+        errors: &mut RangeMap<usize, WriteMemoryRecord>,
+    ) {
+        debug_assert!(
+            VALID_ACCESS_SIZES.contains(&buffer.len()),
+            "Invalid memory access size {}",
+            buffer.len()
+        );
+
+        let affected_range = address..address + buffer.len();
+
+        if buffer.len() > self.config.max_word_size
+            || affected_range.end > self.config.assigned_range.end
+        {
+            errors.insert(affected_range, WriteMemoryRecord::Denied);
+            return;
+        }
+
+        let offset = address - self.config.assigned_range.start;
+        let mut contents = self.contents.lock().unwrap();
+        contents.buffer[offset..offset + buffer.len()].copy_from_slice(buffer);
+        contents.mark_dirty(offset..offset + buffer.len());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        machine::Machine,
+        rom::{manager::RomManager, system::GameSystem},
+    };
+
+    const ADDRESS_SPACE: AddressSpaceId = 0;
+
+    fn build_machine(save_directory: &Path) -> Machine {
+        let rom_manager = Arc::new(RomManager::new(None).unwrap());
+
+        Machine::build(GameSystem::Unknown, rom_manager)
+            .insert_bus(ADDRESS_SPACE, 16)
+            .build_component::<PersistentMemory>(PersistentMemoryConfig {
+                max_word_size: 2,
+                assigned_range: 0x6000..0x8000,
+                assigned_address_space: ADDRESS_SPACE,
+                rom_id: RomId::new([0xaa; 20]),
+                name: Cow::Borrowed("sram"),
+                save_directory: Some(save_directory.to_path_buf()),
+                flush_interval: None,
+            })
+            .unwrap()
+            .0
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn only_dirty_chunks_are_taken() {
+        let mut contents = Contents {
+            buffer: (0..1000).map(|index| index as u8).collect(),
+            dirty_chunks: BitVec::repeat(false, 1000usize.div_ceil(CHUNK_SIZE)),
+        };
+
+        contents.mark_dirty(10..12);
+        contents.mark_dirty(CHUNK_SIZE - 1..CHUNK_SIZE + 1);
+        contents.mark_dirty(999..1000);
+
+        let changes = contents.take_changes();
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].0, 0);
+        assert_eq!(changes[0].1, contents.buffer[..CHUNK_SIZE * 2]);
+        assert_eq!(changes[1].0, CHUNK_SIZE * 3);
+        assert_eq!(changes[1].1, contents.buffer[CHUNK_SIZE * 3..]);
+
+        assert!(contents.take_changes().is_empty());
+    }
+
+    #[test]
+    fn bad_configs_are_refused() {
+        let rom_manager = Arc::new(RomManager::new(None).unwrap());
+        let result = Machine::build(GameSystem::Unknown, rom_manager)
+            .insert_bus(ADDRESS_SPACE, 16)
+            .build_component::<PersistentMemory>(PersistentMemoryConfig {
+                max_word_size: 3,
+                assigned_range: 0x6000..0x8000,
+                assigned_address_space: ADDRESS_SPACE,
+                rom_id: RomId::new([0xaa; 20]),
+                name: Cow::Borrowed("sram"),
+                save_directory: Some(std::env::temp_dir()),
+                flush_interval: None,
+            });
+
+        assert!(matches!(
+            result,
+            Err(MachineBuildError::InvalidWordSize { size: 3, .. })
+        ));
+    }
+
+    #[test]
+    fn contents_survive_teardown() {
+        let save_directory =
+            std::env::temp_dir().join(format!("multiemu_persistent_{}", std::process::id()));
+
+        let machine = build_machine(&save_directory);
+        machine
+            .memory_translation_table
+            .write(0x6010, &[0x12, 0x34], ADDRESS_SPACE)
+            .unwrap();
+        assert_eq!(machine.executor.running_tasks(), ["save writer"]);
+        drop(machine);
+
+        let machine = build_machine(&save_directory);
+        let mut buffer = [0; 2];
+        machine
+            .memory_translation_table
+            .read(0x6010, &mut buffer, ADDRESS_SPACE)
+            .unwrap();
+        drop(machine);
+        std::fs::remove_dir_all(&save_directory).unwrap();
+
+        assert_eq!(buffer, [0x12, 0x34]);
+    }
+}
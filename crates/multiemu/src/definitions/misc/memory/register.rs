@@ -0,0 +1,309 @@
+use crate::{
+    component::{memory::MemoryComponent, Component, FromConfig},
+    machine::{ComponentBuilder, MachineBuildError},
+    memory::{AddressSpaceId, PreviewMemoryRecord, ReadMemoryRecord, WriteMemoryRecord},
+};
+use rangemap::RangeMap;
+use std::{collections::HashMap, fmt::Debug, ops::Range};
+
+type ReadCallback = Box<dyn Fn(&Register) -> u8 + Send + Sync>;
+type WriteCallback = Box<dyn Fn(&Register, u8) + Send + Sync>;
+
+/// A named run of bits within a register
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegisterField {
+    pub name: &'static str,
+    pub bits: Range<u8>,
+}
+
+impl RegisterField {
+    fn mask(&self) -> u8 {
+        (((1u16 << self.bits.len()) - 1) << self.bits.start) as u8
+    }
+}
+
+/// A single byte wide register, built up like `Register::new("PPUCTRL", 0).field("nmi", 7..8).on_write(...)`
+pub struct Register {
+    pub name: &'static str,
+    /// Where in the block it sits
+    pub offset: usize,
+    pub fields: Vec<RegisterField>,
+    read: Option<ReadCallback>,
+    write: Option<WriteCallback>,
+    /// Reads without side effects, so debuggers can look at it
+    preview: Option<ReadCallback>,
+}
+
+impl Register {
+    pub fn new(name: &'static str, offset: usize) -> Self {
+        Self {
+            name,
+            offset,
+            fields: Vec::new(),
+            read: None,
+            write: None,
+            preview: None,
+        }
+    }
+
+    pub fn field(mut self, name: &'static str, bits: Range<u8>) -> Self {
+        assert!(
+            !bits.is_empty() && bits.end <= 8,
+            "Field {} does not fit in register {}",
+            name,
+            self.name
+        );
+
+        self.fields.push(RegisterField { name, bits });
+        self
+    }
+
+    /// Without this, reads are denied like the register is write only
+    pub fn on_read(mut self, callback: impl Fn(&Register) -> u8 + Send + Sync + 'static) -> Self {
+        self.read = Some(Box::new(callback));
+        self
+    }
+
+    /// Without this, writes are denied like the register is read only
+    pub fn on_write(mut self, callback: impl Fn(&Register, u8) + Send + Sync + 'static) -> Self {
+        self.write = Some(Box::new(callback));
+        self
+    }
+
+    /// Without this, previews are impossible since reading could have side effects
+    pub fn on_preview(
+        mut self,
+        callback: impl Fn(&Register) -> u8 + Send + Sync + 'static,
+    ) -> Self {
+        self.preview = Some(Box::new(callback));
+        self
+    }
+
+    fn named_field(&self, name: &str) -> &RegisterField {
+        self.fields
+            .iter()
+            .find(|field| field.name == name)
+            .unwrap_or_else(|| panic!("Register {} has no field {}", self.name, name))
+    }
+
+    /// Pulls the named field out of a value of this register
+    pub fn get(&self, value: u8, field: &str) -> u8 {
+        let field = self.named_field(field);
+
+        (value & field.mask()) >> field.bits.start
+    }
+
+    /// Puts `field_value` into the named field of `value`, cutting off whatever does not fit
+    pub fn set(&self, value: u8, field: &str, field_value: u8) -> u8 {
+        let field = self.named_field(field);
+
+        (value & !field.mask()) | ((field_value << field.bits.start) & field.mask())
+    }
+}
+
+impl Debug for Register {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Register")
+            .field("name", &self.name)
+            .field("offset", &self.offset)
+            .field("fields", &self.fields)
+            .field("readable", &self.read.is_some())
+            .field("writable", &self.write.is_some())
+            .finish()
+    }
+}
+
+#[derive(Debug)]
+pub struct RegisterBlockConfig {
+    pub assigned_range: Range<usize>,
+    /// Address space this exists on
+    pub assigned_address_space: AddressSpaceId,
+    /// Registers repeat every this many bytes through the range, like the NES PPU's eight do up to 0x4000
+    pub repeat_every: Option<usize>,
+    pub registers: Vec<Register>,
+}
+
+/// Memory mapped registers that hand accesses to callbacks, instead of a component matching on raw addresses
+#[derive(Debug)]
+pub struct RegisterBlock {
+    assigned_range: Range<usize>,
+    repeat_every: Option<usize>,
+    registers: HashMap<usize, Register>,
+}
+
+impl RegisterBlock {
+    fn register(&self, address: usize) -> Option<&Register> {
+        let offset = address - self.assigned_range.start;
+        let offset = match self.repeat_every {
+            Some(repeat_every) => offset % repeat_every,
+            None => offset,
+        };
+
+        self.registers.get(&offset)
+    }
+}
+
+impl Component for RegisterBlock {}
+
+impl FromConfig for RegisterBlock {
+    type Config = RegisterBlockConfig;
+
+    fn from_config(
+        component_builder: &mut ComponentBuilder<Self>,
+        config: Self::Config,
+    ) -> Result<(), MachineBuildError> {
+        let mut registers = HashMap::new();
+
+        for register in config.registers {
+            let name = register.name;
+
+            assert!(
+                registers.insert(register.offset, register).is_none(),
+                "Register {} overlaps another",
+                name
+            );
+        }
+
+        component_builder
+            .set_component(Self {
+                assigned_range: config.assigned_range.clone(),
+                repeat_every: config.repeat_every,
+                registers,
+            })
+            .set_memory([(config.assigned_address_space, config.assigned_range)]);
+
+        Ok(())
+    }
+}
+
+impl MemoryComponent for RegisterBlock {
+    fn read_memory(
+        &self,
+        address: usize,
+        buffer: &mut [u8],
+        _address_space: AddressSpaceId,
+        errors: &mut RangeMap<usize, ReadMemoryRecord>,
+    ) {
+        for (index, byte) in buffer.iter_mut().enumerate() {
+            let address = address + index;
+
+            match self
+                .register(address)
+                .and_then(|register| Some((register, register.read.as_ref()?)))
+            {
+                Some((register, read)) => *byte = read(register),
+                None => errors.insert(address..address + 1, ReadMemoryRecord::Denied),
+            }
+        }
+    }
+
+    fn write_memory(
+        &self,
+        address: usize,
+        buffer: &[u8],
+        _address_space: AddressSpaceId,
+        errors: &mut RangeMap<usize, WriteMemoryRecord>,
+    ) {
+        for (index, byte) in buffer.iter().enumerate() {
+            let address = address + index;
+
+            match self
+                .register(address)
+                .and_then(|register| Some((register, register.write.as_ref()?)))
+            {
+                Some((register, write)) => write(register, *byte),
+                None => errors.insert(address..address + 1, WriteMemoryRecord::Denied),
+            }
+        }
+    }
+
+    fn preview_memory(
+        &self,
+        address: usize,
+        buffer: &mut [u8],
+        _address_space: AddressSpaceId,
+        errors: &mut RangeMap<usize, PreviewMemoryRecord>,
+    ) {
+        for (index, byte) in buffer.iter_mut().enumerate() {
+            let address = address + index;
+
+            match self.register(address) {
+                Some(register) => match &register.preview {
+                    Some(preview) => *byte = preview(register),
+                    None => errors.insert(address..address + 1, PreviewMemoryRecord::Impossible),
+                },
+                None => errors.insert(address..address + 1, PreviewMemoryRecord::Denied),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testing::ComponentHarness;
+    use std::sync::{
+        atomic::{AtomicU8, Ordering},
+        Arc,
+    };
+
+    #[test]
+    fn registers_decode_their_fields() {
+        let control = Arc::new(AtomicU8::new(0));
+        let status = Arc::new(AtomicU8::new(0b1000_0000));
+
+        let harness = ComponentHarness::build()
+            .memory(0, 16, [0])
+            .component::<RegisterBlock>(RegisterBlockConfig {
+                assigned_range: 0x2000..0x4000,
+                assigned_address_space: 0,
+                repeat_every: Some(8),
+                registers: vec![
+                    Register::new("CONTROL", 0)
+                        .field("increment", 2..3)
+                        .field("nmi", 7..8)
+                        .on_write({
+                            let control = control.clone();
+                            move |register, value| {
+                                control.store(register.get(value, "increment"), Ordering::Relaxed)
+                            }
+                        }),
+                    Register::new("STATUS", 2).field("vblank", 7..8).on_read({
+                        let status = status.clone();
+                        move |register| {
+                            // Reading acknowledges the vblank
+                            let value = status.load(Ordering::Relaxed);
+                            status.store(register.set(value, "vblank", 0), Ordering::Relaxed);
+                            value
+                        }
+                    }),
+                ],
+            });
+        let memory_translation_table = harness.memory_translation_table();
+
+        // Through a mirror
+        memory_translation_table
+            .write(0x2008, &[0b1000_0100], 0)
+            .unwrap();
+        assert_eq!(control.load(Ordering::Relaxed), 1);
+
+        let mut buffer = [0];
+        memory_translation_table
+            .read(0x2002, &mut buffer, 0)
+            .unwrap();
+        assert_eq!(buffer, [0b1000_0000]);
+        memory_translation_table
+            .read(0x2002, &mut buffer, 0)
+            .unwrap();
+        assert_eq!(buffer, [0]);
+
+        // Write only, and nothing at all
+        assert!(memory_translation_table
+            .read(0x2000, &mut buffer, 0)
+            .is_err());
+        assert!(memory_translation_table.write(0x2001, &[0], 0).is_err());
+        assert!(memory_translation_table
+            .preview(0x2002, &mut buffer, 0)
+            .is_err());
+    }
+}
@@ -1,6 +1,6 @@
 use crate::{
-    component::{memory::MemoryComponent, Component, FromConfig},
-    machine::ComponentBuilder,
+    component::{memory::MemoryComponent, Component, FromConfig, ResetKind},
+    machine::{ComponentBuilder, MachineBuildError},
     memory::{
         AddressSpaceId, PreviewMemoryRecord, ReadMemoryRecord, WriteMemoryRecord,
         VALID_ACCESS_SIZES,
@@ -30,7 +30,7 @@ pub struct RomMemory {
 }
 
 impl Component for RomMemory {
-    fn reset(&self) {
+    fn reset(&self, _kind: ResetKind) {
         // This is basically a stateless component so there isn't any need to reset
     }
 }
@@ -38,7 +38,10 @@ impl Component for RomMemory {
 impl FromConfig for RomMemory {
     type Config = RomMemoryConfig;
 
-    fn from_config(component_builder: &mut ComponentBuilder<Self>, config: Self::Config) {
+    fn from_config(
+        component_builder: &mut ComponentBuilder<Self>,
+        config: Self::Config,
+    ) -> Result<(), MachineBuildError> {
         let rom_file = component_builder
             .machine()
             .rom_manager
@@ -52,6 +55,8 @@ impl FromConfig for RomMemory {
         component_builder
             .set_component(Self { config, rom })
             .set_memory([(assigned_address_space, assigned_range)]);
+
+        Ok(())
     }
 }
 
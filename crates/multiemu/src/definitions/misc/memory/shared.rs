@@ -0,0 +1,244 @@
+use crate::{
+    component::{memory::MemoryComponent, Component, FromConfig, ResetKind, SnapshotError},
+    machine::{ComponentBuilder, MachineBuildError},
+    memory::{AddressSpaceId, ReadMemoryRecord, WriteMemoryRecord, VALID_ACCESS_SIZES},
+};
+use rangemap::RangeMap;
+use serde::{Deserialize, Serialize};
+use std::{ops::Range, sync::Mutex};
+
+/// Where one view of the memory sits
+#[derive(Debug, Clone)]
+pub struct SharedMemoryMapping {
+    pub address_space: AddressSpaceId,
+    pub assigned_range: Range<usize>,
+    /// Where in the memory the start of the range lands
+    pub offset: usize,
+}
+
+#[derive(Debug)]
+pub struct SharedMemoryConfig {
+    pub readable: bool,
+    pub writable: bool,
+    pub max_word_size: usize,
+    pub size: usize,
+    /// Every place the memory shows up, which can be on as many address spaces as needed
+    pub mappings: Vec<SharedMemoryMapping>,
+    pub initial_value: u8,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SharedMemorySnapshot {
+    pub memory: Vec<u8>,
+}
+
+/// Memory seen from more than one address space at once, like CHR RAM that both the CPU and PPU can get at
+///
+/// There is only one buffer behind every mapping, so a write from one side is seen by the other right away
+#[derive(Debug)]
+pub struct SharedMemory {
+    config: SharedMemoryConfig,
+    buffer: Mutex<Vec<u8>>,
+}
+
+impl Component for SharedMemory {
+    fn reset(&self, kind: ResetKind) {
+        if kind == ResetKind::Hard {
+            self.buffer.lock().unwrap().fill(self.config.initial_value);
+        }
+    }
+
+    fn save_snapshot(&self) -> rmpv::Value {
+        let state = SharedMemorySnapshot {
+            memory: self.buffer.lock().unwrap().clone(),
+        };
+
+        rmpv::ext::to_value(&state).unwrap()
+    }
+
+    fn load_snapshot(&self, state: rmpv::Value) -> Result<(), SnapshotError> {
+        let state = rmpv::ext::from_value::<SharedMemorySnapshot>(state)?;
+
+        if state.memory.len() != self.config.size {
+            return Err(SnapshotError::SizeMismatch {
+                expected: self.config.size,
+                found: state.memory.len(),
+            });
+        }
+
+        *self.buffer.lock().unwrap() = state.memory;
+
+        Ok(())
+    }
+}
+
+impl FromConfig for SharedMemory {
+    type Config = SharedMemoryConfig;
+
+    fn from_config(
+        component_builder: &mut ComponentBuilder<Self>,
+        config: Self::Config,
+    ) -> Result<(), MachineBuildError> {
+        assert!(
+            VALID_ACCESS_SIZES.contains(&config.max_word_size),
+            "Invalid word size"
+        );
+
+        for mapping in &config.mappings {
+            assert!(
+                mapping.offset + mapping.assigned_range.len() <= config.size,
+                "Mapping {:#x?} on address space {} goes past the end of the memory",
+                mapping.assigned_range,
+                mapping.address_space
+            );
+        }
+
+        let assigned_ranges = Vec::from_iter(
+            config
+                .mappings
+                .iter()
+                .map(|mapping| (mapping.address_space, mapping.assigned_range.clone())),
+        );
+
+        component_builder
+            .set_component(Self {
+                buffer: Mutex::new(vec![config.initial_value; config.size]),
+                config,
+            })
+            .set_memory(assigned_ranges);
+
+        Ok(())
+    }
+}
+
+impl MemoryComponent for SharedMemory {
+    fn read_memory(
+        &self,
+        address: usize,
+        buffer: &mut [u8],
+        address_space: AddressSpaceId,
+        errors: &mut RangeMap<usize, ReadMemoryRecord>,
+    ) {
+        debug_assert!(
+            VALID_ACCESS_SIZES.contains(&buffer.len()),
+            "Invalid memory access size {}",
+            buffer.len()
+        );
+
+        let affected_range = address..address + buffer.len();
+
+        let offset = match self.offset(address_space, &affected_range) {
+            Some(offset) if self.config.readable && buffer.len() <= self.config.max_word_size => {
+                offset
+            }
+            _ => {
+                errors.insert(affected_range, ReadMemoryRecord::Denied);
+                return;
+            }
+        };
+
+        buffer.copy_from_slice(&self.buffer.lock().unwrap()[offset..offset + buffer.len()]);
+    }
+
+    fn write_memory(
+        &self,
+        address: usize,
+        buffer: &[u8],
+        address_space: AddressSpaceId,
+        errors: &mut RangeMap<usize, WriteMemoryRecord>,
+    ) {
+        debug_assert!(
+            VALID_ACCESS_SIZES.contains(&buffer.len()),
+            "Invalid memory access size {}",
+            buffer.len()
+        );
+
+        let affected_range = address..address + buffer.len();
+
+        let offset = match self.offset(address_space, &affected_range) {
+            Some(offset) if self.config.writable && buffer.len() <= self.config.max_word_size => {
+                offset
+            }
+            _ => {
+                errors.insert(affected_range, WriteMemoryRecord::Denied);
+                return;
+            }
+        };
+
+        self.buffer.lock().unwrap()[offset..offset + buffer.len()].copy_from_slice(buffer);
+    }
+}
+
+impl SharedMemory {
+    /// Where in the memory an access lands, if a single mapping on its address space covers all of it
+    fn offset(
+        &self,
+        address_space: AddressSpaceId,
+        affected_range: &Range<usize>,
+    ) -> Option<usize> {
+        self.config
+            .mappings
+            .iter()
+            .find(|mapping| {
+                mapping.address_space == address_space
+                    && mapping.assigned_range.start <= affected_range.start
+                    && affected_range.end <= mapping.assigned_range.end
+            })
+            .map(|mapping| mapping.offset + affected_range.start - mapping.assigned_range.start)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testing::ComponentHarness;
+
+    #[test]
+    fn both_sides_see_the_same_memory() {
+        let harness = ComponentHarness::build()
+            .memory(0, 16, [0])
+            .memory(1, 14, [0])
+            .component::<SharedMemory>(SharedMemoryConfig {
+                readable: true,
+                writable: true,
+                max_word_size: 2,
+                size: 0x2000,
+                mappings: vec![
+                    // The CPU only sees the second half through a window
+                    SharedMemoryMapping {
+                        address_space: 0,
+                        assigned_range: 0x6000..0x7000,
+                        offset: 0x1000,
+                    },
+                    SharedMemoryMapping {
+                        address_space: 1,
+                        assigned_range: 0x2000..0x4000,
+                        offset: 0,
+                    },
+                ],
+                initial_value: 0,
+            });
+        let memory_translation_table = harness.memory_translation_table();
+        let mut buffer = [0; 2];
+
+        memory_translation_table
+            .write(0x6010, &[0x12, 0x34], 0)
+            .unwrap();
+        memory_translation_table
+            .read(0x3010, &mut buffer, 1)
+            .unwrap();
+        assert_eq!(buffer, [0x12, 0x34]);
+
+        memory_translation_table.write(0x3ffe, &[0xab], 1).unwrap();
+        memory_translation_table
+            .read(0x6ffe, &mut buffer[..1], 0)
+            .unwrap();
+        assert_eq!(buffer[0], 0xab);
+
+        harness.component().reset(ResetKind::Hard);
+        memory_translation_table
+            .read(0x6010, &mut buffer, 0)
+            .unwrap();
+        assert_eq!(buffer, [0, 0]);
+    }
+}
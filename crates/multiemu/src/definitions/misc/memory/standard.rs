@@ -1,21 +1,28 @@
 use crate::{
-    component::{memory::MemoryComponent, Component, FromConfig},
-    machine::ComponentBuilder,
+    component::{
+        media::{MediaComponent, MediaSlotId, MediaSwapError},
+        memory::MemoryComponent,
+        Component, FromConfig, ResetKind, SnapshotError,
+    },
+    machine::{rng::MachineRng, ComponentBuilder, MachineBuildError},
     memory::{AddressSpaceId, ReadMemoryRecord, WriteMemoryRecord, VALID_ACCESS_SIZES},
     rom::{
         id::RomId,
         manager::{RomManager, RomRequirement},
     },
 };
-use rand::RngCore;
 use rangemap::RangeMap;
 use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
 use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, Bytes};
 use std::{
     borrow::Cow,
     io::{Read, Write},
     ops::Range,
-    sync::{Arc, Mutex},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
 };
 
 const CHUNK_SIZE: usize = 4096;
@@ -30,7 +37,8 @@ pub enum StandardMemoryInitialContents {
         value: Cow<'static, [u8]>,
     },
     Rom {
-        rom_id: RomId,
+        /// [None] starts out like an empty slot
+        rom_id: Option<RomId>,
         offset: usize,
     },
     Random,
@@ -38,9 +46,9 @@ pub enum StandardMemoryInitialContents {
 
 #[derive(Debug)]
 pub struct StandardMemoryConfig {
-    // If the buffer is readable
+    // If the buffer is readable, until changed with StandardMemory::set_readable
     pub readable: bool,
-    // If the buffer is writable
+    // If the buffer is writable, until changed with StandardMemory::set_writable
     pub writable: bool,
     // The maximum word size
     pub max_word_size: usize,
@@ -52,21 +60,44 @@ pub struct StandardMemoryConfig {
     pub initial_contents: StandardMemoryInitialContents,
 }
 
+#[serde_as]
 #[derive(Debug, Serialize, Deserialize)]
 pub struct StandardMemorySnapshot {
+    /// Written as one blob so a changed byte never moves the ones after it, older snapshots wrote a list and still load
+    #[serde_as(as = "Bytes")]
     pub memory: Vec<u8>,
+    /// Missing from older snapshots, which always had what the config says
+    #[serde(default)]
+    pub readable: Option<bool>,
+    #[serde(default)]
+    pub writable: Option<bool>,
 }
 
 #[derive(Debug)]
 pub struct StandardMemory {
     config: StandardMemoryConfig,
     buffer: Vec<Mutex<[u8; CHUNK_SIZE]>>,
+    readable: AtomicBool,
+    writable: AtomicBool,
     rom_manager: Arc<RomManager>,
+    rng: Arc<MachineRng>,
+    /// ROM currently backing this memory, can be swapped out at runtime if we were built with one
+    inserted_rom: Mutex<Option<RomId>>,
+    /// Bumped on every change, see [Component::snapshot_generation]
+    generation: AtomicU64,
 }
 
 impl Component for StandardMemory {
-    fn reset(&self) {
-        self.initialize_buffer();
+    fn reset(&self, kind: ResetKind) {
+        self.set_readable(self.config.readable);
+        self.set_writable(self.config.writable);
+
+        if kind == ResetKind::Hard {
+            // It was readable when inserted, so this is something like the file being deleted since
+            if let Err(err) = self.initialize_buffer() {
+                tracing::error!("Could not reload the inserted ROM: {}", err);
+            }
+        }
     }
 
     fn save_snapshot(&self) -> rmpv::Value {
@@ -76,29 +107,53 @@ impl Component for StandardMemory {
             let chunk_guard = chunk.lock().unwrap();
             memory.write_all(chunk_guard.as_slice()).unwrap();
         }
+        // The last chunk is padded out to 4096 bytes, which is not part of the range
+        memory.truncate(self.config.assigned_range.len());
 
-        let state = StandardMemorySnapshot { memory };
+        let state = StandardMemorySnapshot {
+            memory,
+            readable: Some(self.is_readable()),
+            writable: Some(self.is_writable()),
+        };
 
         rmpv::ext::to_value(&state).unwrap()
     }
 
-    fn load_snapshot(&self, state: rmpv::Value) {
-        let state = rmpv::ext::from_value::<StandardMemorySnapshot>(state).unwrap();
+    fn load_snapshot(&self, state: rmpv::Value) -> Result<(), SnapshotError> {
+        let state = rmpv::ext::from_value::<StandardMemorySnapshot>(state)?;
+
+        if state.memory.len() != self.config.assigned_range.len() {
+            return Err(SnapshotError::SizeMismatch {
+                expected: self.config.assigned_range.len(),
+                found: state.memory.len(),
+            });
+        }
 
-        assert_eq!(state.memory.len(), self.config.assigned_range.len());
+        self.set_readable(state.readable.unwrap_or(self.config.readable));
+        self.set_writable(state.writable.unwrap_or(self.config.writable));
 
-        // This also does size validation
         for (src, dest) in state.memory.chunks(4096).zip(self.buffer.iter()) {
             let mut dest_guard = dest.lock().unwrap();
             dest_guard[..src.len()].copy_from_slice(src);
         }
+
+        self.generation.fetch_add(1, Ordering::Relaxed);
+
+        Ok(())
+    }
+
+    fn snapshot_generation(&self) -> Option<u64> {
+        Some(self.generation.load(Ordering::Relaxed))
     }
 }
 
 impl FromConfig for StandardMemory {
     type Config = StandardMemoryConfig;
 
-    fn from_config(component_builder: &mut ComponentBuilder<Self>, config: Self::Config) {
+    fn from_config(
+        component_builder: &mut ComponentBuilder<Self>,
+        config: Self::Config,
+    ) -> Result<(), MachineBuildError> {
         assert!(
             VALID_ACCESS_SIZES.contains(&config.max_word_size),
             "Invalid word size"
@@ -117,17 +172,53 @@ impl FromConfig for StandardMemory {
         );
         let assigned_range = config.assigned_range.clone();
         let assigned_address_space = config.assigned_address_space;
+        let inserted_rom = match &config.initial_contents {
+            StandardMemoryInitialContents::Rom { rom_id, .. } => *rom_id,
+            _ => None,
+        };
 
         let me = Self {
+            inserted_rom: Mutex::new(inserted_rom),
+            readable: AtomicBool::new(config.readable),
+            writable: AtomicBool::new(config.writable),
             config,
             buffer: buffer.into_iter().collect(),
             rom_manager: component_builder.machine().rom_manager.clone(),
+            rng: component_builder.machine().rng.clone(),
+            generation: AtomicU64::new(0),
         };
-        me.initialize_buffer();
+        me.initialize_buffer()?;
 
         component_builder
             .set_component(me)
             .set_memory([(assigned_address_space, assigned_range)]);
+
+        Ok(())
+    }
+}
+
+impl MediaComponent for StandardMemory {
+    fn swap_media(&self, slot: &MediaSlotId, rom_id: Option<RomId>) -> Result<(), MediaSwapError> {
+        if !matches!(
+            self.config.initial_contents,
+            StandardMemoryInitialContents::Rom { .. }
+        ) {
+            tracing::warn!(
+                "Media slot {} is attached to memory that is not backed by a ROM",
+                slot
+            );
+            return Ok(());
+        }
+
+        let previous_rom = std::mem::replace(&mut *self.inserted_rom.lock().unwrap(), rom_id);
+
+        // The ROM is read in full before anything is written, so the old contents are still there
+        if let Err(err) = self.initialize_buffer() {
+            *self.inserted_rom.lock().unwrap() = previous_rom;
+            return Err(err);
+        }
+
+        Ok(())
     }
 }
 
@@ -145,7 +236,7 @@ impl MemoryComponent for StandardMemory {
             buffer.len()
         );
 
-        if !self.config.readable {
+        if !self.is_readable() {
             errors.insert(address..address + buffer.len(), ReadMemoryRecord::Denied);
         }
 
@@ -223,7 +314,7 @@ impl MemoryComponent for StandardMemory {
             buffer.len()
         );
 
-        if !self.config.writable {
+        if !self.is_writable() {
             errors.insert(address..address + buffer.len(), WriteMemoryRecord::Denied);
         }
 
@@ -254,8 +345,30 @@ impl MemoryComponent for StandardMemory {
 }
 
 impl StandardMemory {
+    /// For mappers that lock and unlock ram, or tests that want to see a program trip over something
+    pub fn set_readable(&self, readable: bool) {
+        self.readable.store(readable, Ordering::Relaxed);
+        self.generation.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// For mappers that write protect banks, or unlock ram that starts out protected
+    pub fn set_writable(&self, writable: bool) {
+        self.writable.store(writable, Ordering::Relaxed);
+        self.generation.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn is_readable(&self) -> bool {
+        self.readable.load(Ordering::Relaxed)
+    }
+
+    pub fn is_writable(&self) -> bool {
+        self.writable.load(Ordering::Relaxed)
+    }
+
     /// Writes unchecked internally
     fn write_internal(&self, address: usize, buffer: &[u8]) {
+        self.generation.fetch_add(1, Ordering::Relaxed);
+
         let requested_range = address - self.config.assigned_range.start
             ..address - self.config.assigned_range.start + buffer.len();
 
@@ -296,8 +409,8 @@ impl StandardMemory {
         }
     }
 
-    fn initialize_buffer(&self) {
-        let internal_buffer_size = self.config.assigned_range.len();
+    fn initialize_buffer(&self) -> Result<(), MediaSwapError> {
+        self.generation.fetch_add(1, Ordering::Relaxed);
 
         // HACK: This overfills the buffer for ease of programming, but its ok because the actual mmu doesn't allow accesses out at runtime
         match &self.config.initial_contents {
@@ -307,40 +420,35 @@ impl StandardMemory {
                     .for_each(|chunk| chunk.lock().unwrap().fill(*value));
             }
             StandardMemoryInitialContents::Random => {
+                // In order, so the contents only depend on the seed
                 self.buffer
-                    .par_iter()
-                    .for_each(|chunk| rand::rng().fill_bytes(chunk.lock().unwrap().as_mut_slice()));
+                    .iter()
+                    .for_each(|chunk| self.rng.fill_bytes(chunk.lock().unwrap().as_mut_slice()));
             }
             StandardMemoryInitialContents::Array { value, offset } => {
                 self.write_internal(*offset, value);
             }
-            StandardMemoryInitialContents::Rom { rom_id, offset } => {
-                let mut rom_file = self
-                    .rom_manager
-                    .open(*rom_id, RomRequirement::Required)
-                    .unwrap();
-
-                let mut total_read = 0;
-                let mut buffer = [0; 4096];
-
-                while total_read < internal_buffer_size {
-                    let remaining_space = internal_buffer_size - total_read;
-                    let amount_to_read = remaining_space.min(buffer.len());
-                    let amount = rom_file
-                        .read(&mut buffer[..amount_to_read])
-                        .expect("Could not read rom");
-
-                    if amount == 0 {
-                        break;
-                    }
-
-                    total_read += amount;
-
-                    let write_size = remaining_space.min(amount);
-                    self.write_internal(*offset + total_read - amount, &buffer[..write_size]);
-                }
+            StandardMemoryInitialContents::Rom { offset, .. } => {
+                // Nothing inserted reads like an empty slot
+                let Some(rom_id) = *self.inserted_rom.lock().unwrap() else {
+                    self.buffer
+                        .par_iter()
+                        .for_each(|chunk| chunk.lock().unwrap().fill(0));
+                    return Ok(());
+                };
+
+                let mut contents = Vec::new();
+                self.rom_manager
+                    .open(rom_id, RomRequirement::Required)
+                    .ok_or(MediaSwapError::MissingRom(rom_id))?
+                    .take(self.config.assigned_range.len() as u64)
+                    .read_to_end(&mut contents)?;
+
+                self.write_internal(*offset, &contents);
             }
         }
+
+        Ok(())
     }
 }
 
@@ -364,8 +472,10 @@ mod test {
                 assigned_address_space: ADDRESS_SPACE,
                 initial_contents: StandardMemoryInitialContents::Value { value: 0xff },
             })
+            .unwrap()
             .0
-            .build();
+            .build()
+            .unwrap();
         let mut buffer = [0; 4];
 
         machine
@@ -387,8 +497,10 @@ mod test {
                     offset: 0,
                 },
             })
+            .unwrap()
             .0
-            .build();
+            .build()
+            .unwrap();
         let mut buffer = [0; 4];
 
         machine
@@ -411,8 +523,10 @@ mod test {
                 assigned_address_space: ADDRESS_SPACE,
                 initial_contents: StandardMemoryInitialContents::Value { value: 0xff },
             })
+            .unwrap()
             .0
-            .build();
+            .build()
+            .unwrap();
         let mut buffer = [0; 8];
 
         machine
@@ -435,8 +549,10 @@ mod test {
                 assigned_address_space: ADDRESS_SPACE,
                 initial_contents: StandardMemoryInitialContents::Value { value: 0xff },
             })
+            .unwrap()
             .0
-            .build();
+            .build()
+            .unwrap();
         let buffer = [0; 8];
 
         machine
@@ -445,6 +561,60 @@ mod test {
             .unwrap();
     }
 
+    #[test]
+    fn write_protection_can_be_toggled() {
+        let rom_manager = Arc::new(RomManager::new(None).unwrap());
+        let machine = Machine::build(GameSystem::Unknown, rom_manager)
+            .insert_bus(ADDRESS_SPACE, 16)
+            .build_component::<StandardMemory>(StandardMemoryConfig {
+                max_word_size: 2,
+                readable: true,
+                writable: true,
+                assigned_range: 0..0x100,
+                assigned_address_space: ADDRESS_SPACE,
+                initial_contents: StandardMemoryInitialContents::Value { value: 0 },
+            })
+            .unwrap()
+            .0
+            .build()
+            .unwrap();
+        let memory = machine.find_component::<StandardMemory>().unwrap();
+        let memory_translation_table = &machine.memory_translation_table;
+
+        memory.set_writable(false);
+        assert!(memory_translation_table
+            .write(0x10, &[0xff], ADDRESS_SPACE)
+            .is_err());
+        let mut buffer = [0xff];
+        memory_translation_table
+            .read(0x10, &mut buffer, ADDRESS_SPACE)
+            .unwrap();
+        assert_eq!(buffer, [0]);
+
+        memory.set_writable(true);
+        memory_translation_table
+            .set_write_protected(ADDRESS_SPACE, 0x20..0x30, true)
+            .unwrap();
+        assert!(memory_translation_table
+            .write(0x1f, &[0xff, 0xff], ADDRESS_SPACE)
+            .is_err());
+        memory_translation_table
+            .write(0x30, &[0xff], ADDRESS_SPACE)
+            .unwrap();
+
+        memory_translation_table
+            .set_write_protected(ADDRESS_SPACE, 0x20..0x30, false)
+            .unwrap();
+        memory_translation_table
+            .write(0x1f, &[0xff, 0xff], ADDRESS_SPACE)
+            .unwrap();
+
+        // Going back to how it was built
+        memory.set_readable(false);
+        memory.reset(ResetKind::Hard);
+        assert!(memory.is_readable());
+    }
+
     #[test]
     fn basic_read_write() {
         let rom_manager = Arc::new(RomManager::new(None).unwrap());
@@ -458,8 +628,10 @@ mod test {
                 assigned_address_space: ADDRESS_SPACE,
                 initial_contents: StandardMemoryInitialContents::Value { value: 0xff },
             })
+            .unwrap()
             .0
-            .build();
+            .build()
+            .unwrap();
         let mut buffer = [0xff; 8];
 
         machine
@@ -487,8 +659,10 @@ mod test {
                 assigned_address_space: ADDRESS_SPACE,
                 initial_contents: StandardMemoryInitialContents::Value { value: 0xff },
             })
+            .unwrap()
             .0
-            .build();
+            .build()
+            .unwrap();
         let mut buffer = [0xff; 1];
 
         for i in 0..0x10000 {
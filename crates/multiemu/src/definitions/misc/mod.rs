@@ -1,2 +1,5 @@
 pub mod memory;
 pub mod processor;
+pub mod tape;
+#[cfg(wasm_plugins)]
+pub mod wasm_plugin;
@@ -1,49 +1,668 @@
-use super::instruction::SingleByteArgument;
-use crate::memory::MemoryTranslationTable;
-use bitvec::field::BitField;
-use bitvec::prelude::Msb0;
-use bitvec::view::BitView;
-use std::ops::Range;
+use super::instruction::{
+    AluOperation, BlockOperation, Condition, IndexRegister, Operand16, Operand8, Port, Register16,
+    Register8, RotateOperation, Z80Instruction,
+};
+use crate::memory::{AddressSpaceId, MemoryTranslationTable};
 
-const INSTRUCTION_IDENTIFIER: Range<usize> = 0..2;
-const SECONDARY_INSTRUCTION_IDENTIFIER: Range<usize> = 5..8;
-const ARGUMENT: Range<usize> = 2..5;
+// Opcodes are split up into xxyyyzzz, with yyy further split into ppq, which is how the Z80 lays out its tables
+
+const CONDITIONS: [Condition; 8] = [
+    Condition::NonZero,
+    Condition::Zero,
+    Condition::NoCarry,
+    Condition::Carry,
+    Condition::ParityOdd,
+    Condition::ParityEven,
+    Condition::Positive,
+    Condition::Negative,
+];
+
+const ALU_OPERATIONS: [AluOperation; 8] = [
+    AluOperation::Add,
+    AluOperation::Adc,
+    AluOperation::Sub,
+    AluOperation::Sbc,
+    AluOperation::And,
+    AluOperation::Xor,
+    AluOperation::Or,
+    AluOperation::Cp,
+];
+
+const ROTATE_OPERATIONS: [RotateOperation; 8] = [
+    RotateOperation::Rlc,
+    RotateOperation::Rrc,
+    RotateOperation::Rl,
+    RotateOperation::Rr,
+    RotateOperation::Sla,
+    RotateOperation::Sra,
+    RotateOperation::Sll,
+    RotateOperation::Srl,
+];
+
+const BLOCK_OPERATIONS: [BlockOperation; 4] = [
+    BlockOperation::Load,
+    BlockOperation::Compare,
+    BlockOperation::Input,
+    BlockOperation::Output,
+];
+
+const INTERRUPT_MODES: [u8; 8] = [0, 0, 1, 2, 0, 0, 1, 2];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodedInstruction {
+    pub instruction: Z80Instruction,
+    pub length: u16,
+    /// T-states taken, for conditional and repeating instructions when they fall through
+    pub cycles: u8,
+    /// Opcode fetches, each of which bumps the refresh register
+    pub fetches: u8,
+}
+
+struct Decoder<'a> {
+    cursor: u16,
+    length: u16,
+    /// What a DD or FD prefix swapped in for HL
+    index: Option<IndexRegister>,
+    address_space: AddressSpaceId,
+    memory_translation_table: &'a MemoryTranslationTable,
+}
 
 pub fn decode_instruction(
-    cursor: usize,
+    cursor: u16,
+    address_space: AddressSpaceId,
     memory_translation_table: &MemoryTranslationTable,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let mut instruction_first_byte = 0;
-    memory_translation_table.read(cursor, std::slice::from_mut(&mut instruction_first_byte));
-    let instruction_first_byte = instruction_first_byte.view_bits::<Msb0>();
-    let instruction_identifier = instruction_first_byte[INSTRUCTION_IDENTIFIER].load::<u8>();
+) -> DecodedInstruction {
+    let mut decoder = Decoder {
+        cursor,
+        length: 0,
+        index: None,
+        address_space,
+        memory_translation_table,
+    };
 
-    match instruction_identifier {
-        0b00 => {
-            todo!()
+    let (instruction, cycles, fetches) = match decoder.next_byte() {
+        0xcb => {
+            let opcode = decoder.next_byte();
+            let (instruction, cycles) = decoder.decode_bit(opcode);
+
+            (instruction, cycles, 2)
+        }
+        0xed => {
+            let opcode = decoder.next_byte();
+            let (instruction, cycles) = decoder.decode_extended(opcode);
+
+            (instruction, cycles, 2)
         }
-        0b01 => {
-            let source_register = instruction_first_byte[ARGUMENT].load::<u8>();
-            let destination_register =
-                instruction_first_byte[SECONDARY_INSTRUCTION_IDENTIFIER].load::<u8>();
+        prefix @ (0xdd | 0xfd) => {
+            decoder.index = Some(if prefix == 0xdd {
+                IndexRegister::Ix
+            } else {
+                IndexRegister::Iy
+            });
+
+            match decoder.next_byte() {
+                // Whatever prefix comes next takes over, this one acts like a NOP
+                0xdd | 0xed | 0xfd => {
+                    decoder.length = 1;
 
-            let source_register = SingleByteArgument::from_id(source_register).unwrap();
-            let destination_register = SingleByteArgument::from_id(destination_register).unwrap();
+                    (Z80Instruction::Nop, 4, 1)
+                }
+                0xcb => {
+                    let (instruction, cycles) = decoder.decode_indexed_bit();
 
-            if source_register == SingleByteArgument::HlIndirect
-                && destination_register == SingleByteArgument::HlIndirect
-            {}
+                    (instruction, cycles, 2)
+                }
+                opcode => {
+                    let (instruction, cycles) = decoder.decode_unprefixed(opcode);
+
+                    (instruction, cycles + indexed_cycles(&instruction), 2)
+                }
+            }
         }
-        0b10 => {
-            todo!()
+        opcode => {
+            let (instruction, cycles) = decoder.decode_unprefixed(opcode);
+
+            (instruction, cycles, 1)
         }
-        0b11 => {
-            todo!()
+    };
+
+    DecodedInstruction {
+        instruction,
+        length: decoder.length,
+        cycles,
+        fetches,
+    }
+}
+
+/// What a DD or FD prefix adds on top of the unprefixed timing
+fn indexed_cycles(instruction: &Z80Instruction) -> u8 {
+    match instruction {
+        Z80Instruction::Ld8 {
+            destination: Operand8::Indexed(..),
+            source: Operand8::Immediate(_),
+        } => 9,
+        Z80Instruction::Ld8 {
+            destination: Operand8::Indexed(..),
+            ..
+        }
+        | Z80Instruction::Ld8 {
+            source: Operand8::Indexed(..),
+            ..
+        }
+        | Z80Instruction::Alu {
+            operand: Operand8::Indexed(..),
+            ..
         }
-        _ => {
-            unreachable!()
+        | Z80Instruction::Inc8(Operand8::Indexed(..))
+        | Z80Instruction::Dec8(Operand8::Indexed(..)) => 12,
+        _ => 4,
+    }
+}
+
+impl Decoder<'_> {
+    fn next_byte(&mut self) -> u8 {
+        let mut byte = [0xff];
+        let _ = self.memory_translation_table.read(
+            self.cursor.wrapping_add(self.length) as usize,
+            &mut byte,
+            self.address_space,
+        );
+        self.length += 1;
+
+        byte[0]
+    }
+
+    fn next_word(&mut self) -> u16 {
+        u16::from_le_bytes([self.next_byte(), self.next_byte()])
+    }
+
+    fn hl(&self) -> Register16 {
+        match self.index {
+            None => Register16::Hl,
+            Some(IndexRegister::Ix) => Register16::Ix,
+            Some(IndexRegister::Iy) => Register16::Iy,
         }
     }
 
-    Ok(())
+    /// `index_halves` is off for instructions that also touch (IX+d), where H and L stay themselves
+    fn operand8(&mut self, id: u8, index_halves: bool) -> Operand8 {
+        let index = self.index.filter(|_| index_halves);
+
+        Operand8::Register(match id {
+            0 => Register8::B,
+            1 => Register8::C,
+            2 => Register8::D,
+            3 => Register8::E,
+            4 => match index {
+                None => Register8::H,
+                Some(IndexRegister::Ix) => Register8::Ixh,
+                Some(IndexRegister::Iy) => Register8::Iyh,
+            },
+            5 => match index {
+                None => Register8::L,
+                Some(IndexRegister::Ix) => Register8::Ixl,
+                Some(IndexRegister::Iy) => Register8::Iyl,
+            },
+            6 => {
+                return match self.index {
+                    None => Operand8::Indirect(Register16::Hl),
+                    Some(index) => Operand8::Indexed(index, self.next_byte() as i8),
+                };
+            }
+            7 => Register8::A,
+            _ => unreachable!(),
+        })
+    }
+
+    /// Plain registers, for where the undocumented indexed bit instructions copy their result
+    fn register8(id: u8) -> Option<Register8> {
+        match id {
+            0 => Some(Register8::B),
+            1 => Some(Register8::C),
+            2 => Some(Register8::D),
+            3 => Some(Register8::E),
+            4 => Some(Register8::H),
+            5 => Some(Register8::L),
+            7 => Some(Register8::A),
+            _ => None,
+        }
+    }
+
+    fn register16(&self, p: u8) -> Register16 {
+        match p {
+            0 => Register16::Bc,
+            1 => Register16::De,
+            2 => self.hl(),
+            3 => Register16::Sp,
+            _ => unreachable!(),
+        }
+    }
+
+    /// Like [Self::register16] but with AF in place of SP, for pushing and popping
+    fn register16_af(&self, p: u8) -> Register16 {
+        match p {
+            3 => Register16::Af,
+            p => self.register16(p),
+        }
+    }
+
+    fn decode_unprefixed(&mut self, opcode: u8) -> (Z80Instruction, u8) {
+        let (x, y, z) = (opcode >> 6, (opcode >> 3) & 0b111, opcode & 0b111);
+        let (p, q) = (y >> 1, y & 1);
+
+        match (x, z) {
+            (0, 0) => match y {
+                0 => (Z80Instruction::Nop, 4),
+                1 => (Z80Instruction::ExAf, 4),
+                2 => (Z80Instruction::Djnz(self.next_byte() as i8), 8),
+                3 => (
+                    Z80Instruction::Jr {
+                        condition: None,
+                        offset: self.next_byte() as i8,
+                    },
+                    12,
+                ),
+                _ => (
+                    Z80Instruction::Jr {
+                        condition: Some(CONDITIONS[y as usize - 4]),
+                        offset: self.next_byte() as i8,
+                    },
+                    7,
+                ),
+            },
+            (0, 1) => {
+                if q == 0 {
+                    (
+                        Z80Instruction::Ld16 {
+                            destination: Operand16::Register(self.register16(p)),
+                            source: Operand16::Immediate(self.next_word()),
+                        },
+                        10,
+                    )
+                } else {
+                    (
+                        Z80Instruction::Add16 {
+                            destination: self.hl(),
+                            source: self.register16(p),
+                        },
+                        11,
+                    )
+                }
+            }
+            (0, 2) => {
+                let accumulator = Operand8::Register(Register8::A);
+
+                let (memory, cycles) = match p {
+                    0 => (Operand8::Indirect(Register16::Bc), 7),
+                    1 => (Operand8::Indirect(Register16::De), 7),
+                    2 => {
+                        let address = Operand16::Absolute(self.next_word());
+                        let hl = Operand16::Register(self.hl());
+
+                        return if q == 0 {
+                            (
+                                Z80Instruction::Ld16 {
+                                    destination: address,
+                                    source: hl,
+                                },
+                                16,
+                            )
+                        } else {
+                            (
+                                Z80Instruction::Ld16 {
+                                    destination: hl,
+                                    source: address,
+                                },
+                                16,
+                            )
+                        };
+                    }
+                    _ => (Operand8::Absolute(self.next_word()), 13),
+                };
+
+                if q == 0 {
+                    (
+                        Z80Instruction::Ld8 {
+                            destination: memory,
+                            source: accumulator,
+                        },
+                        cycles,
+                    )
+                } else {
+                    (
+                        Z80Instruction::Ld8 {
+                            destination: accumulator,
+                            source: memory,
+                        },
+                        cycles,
+                    )
+                }
+            }
+            (0, 3) => {
+                if q == 0 {
+                    (Z80Instruction::Inc16(self.register16(p)), 6)
+                } else {
+                    (Z80Instruction::Dec16(self.register16(p)), 6)
+                }
+            }
+            (0, 4 | 5) => {
+                let operand = self.operand8(y, true);
+                let cycles = if y == 6 { 11 } else { 4 };
+
+                if z == 4 {
+                    (Z80Instruction::Inc8(operand), cycles)
+                } else {
+                    (Z80Instruction::Dec8(operand), cycles)
+                }
+            }
+            (0, 6) => (
+                Z80Instruction::Ld8 {
+                    destination: self.operand8(y, true),
+                    source: Operand8::Immediate(self.next_byte()),
+                },
+                if y == 6 { 10 } else { 7 },
+            ),
+            (0, 7) => (
+                [
+                    Z80Instruction::Rlca,
+                    Z80Instruction::Rrca,
+                    Z80Instruction::Rla,
+                    Z80Instruction::Rra,
+                    Z80Instruction::Daa,
+                    Z80Instruction::Cpl,
+                    Z80Instruction::Scf,
+                    Z80Instruction::Ccf,
+                ][y as usize],
+                4,
+            ),
+            (1, _) => {
+                if y == 6 && z == 6 {
+                    return (Z80Instruction::Halt, 4);
+                }
+
+                let index_halves = y != 6 && z != 6;
+
+                (
+                    Z80Instruction::Ld8 {
+                        destination: self.operand8(y, index_halves),
+                        source: self.operand8(z, index_halves),
+                    },
+                    if index_halves { 4 } else { 7 },
+                )
+            }
+            (2, _) => (
+                Z80Instruction::Alu {
+                    operation: ALU_OPERATIONS[y as usize],
+                    operand: self.operand8(z, true),
+                },
+                if z == 6 { 7 } else { 4 },
+            ),
+            (3, 0) => (Z80Instruction::Ret(Some(CONDITIONS[y as usize])), 5),
+            (3, 1) => {
+                if q == 0 {
+                    return (Z80Instruction::Pop(self.register16_af(p)), 10);
+                }
+
+                match p {
+                    0 => (Z80Instruction::Ret(None), 10),
+                    1 => (Z80Instruction::Exx, 4),
+                    2 => (Z80Instruction::JpIndirect(self.hl()), 4),
+                    _ => (
+                        Z80Instruction::Ld16 {
+                            destination: Operand16::Register(Register16::Sp),
+                            source: Operand16::Register(self.hl()),
+                        },
+                        6,
+                    ),
+                }
+            }
+            (3, 2) => (
+                Z80Instruction::Jp {
+                    condition: Some(CONDITIONS[y as usize]),
+                    address: self.next_word(),
+                },
+                10,
+            ),
+            (3, 3) => match y {
+                0 => (
+                    Z80Instruction::Jp {
+                        condition: None,
+                        address: self.next_word(),
+                    },
+                    10,
+                ),
+                // Prefixes are handled before getting here
+                1 => unreachable!(),
+                2 => (
+                    Z80Instruction::Out {
+                        source: Some(Register8::A),
+                        port: Port::Immediate(self.next_byte()),
+                    },
+                    11,
+                ),
+                3 => (
+                    Z80Instruction::In {
+                        destination: Some(Register8::A),
+                        port: Port::Immediate(self.next_byte()),
+                    },
+                    11,
+                ),
+                4 => (Z80Instruction::ExStack(self.hl()), 19),
+                5 => (Z80Instruction::ExDeHl, 4),
+                6 => (Z80Instruction::Di, 4),
+                _ => (Z80Instruction::Ei, 4),
+            },
+            (3, 4) => (
+                Z80Instruction::Call {
+                    condition: Some(CONDITIONS[y as usize]),
+                    address: self.next_word(),
+                },
+                10,
+            ),
+            (3, 5) => {
+                if q == 0 {
+                    (Z80Instruction::Push(self.register16_af(p)), 11)
+                } else {
+                    // Everything else here is a prefix
+                    (
+                        Z80Instruction::Call {
+                            condition: None,
+                            address: self.next_word(),
+                        },
+                        17,
+                    )
+                }
+            }
+            (3, 6) => (
+                Z80Instruction::Alu {
+                    operation: ALU_OPERATIONS[y as usize],
+                    operand: Operand8::Immediate(self.next_byte()),
+                },
+                7,
+            ),
+            (3, 7) => (Z80Instruction::Rst(y * 8), 11),
+            _ => unreachable!(),
+        }
+    }
+
+    /// CB prefixed
+    fn decode_bit(&mut self, opcode: u8) -> (Z80Instruction, u8) {
+        let (x, y, z) = (opcode >> 6, (opcode >> 3) & 0b111, opcode & 0b111);
+        let operand = self.operand8(z, false);
+        let in_memory = z == 6;
+
+        match x {
+            0 => (
+                Z80Instruction::Rotate {
+                    operation: ROTATE_OPERATIONS[y as usize],
+                    operand,
+                    copy: None,
+                },
+                if in_memory { 15 } else { 8 },
+            ),
+            1 => (
+                Z80Instruction::Bit { bit: y, operand },
+                if in_memory { 12 } else { 8 },
+            ),
+            2 => (
+                Z80Instruction::Res {
+                    bit: y,
+                    operand,
+                    copy: None,
+                },
+                if in_memory { 15 } else { 8 },
+            ),
+            _ => (
+                Z80Instruction::Set {
+                    bit: y,
+                    operand,
+                    copy: None,
+                },
+                if in_memory { 15 } else { 8 },
+            ),
+        }
+    }
+
+    /// DDCB and FDCB prefixed, where the displacement comes before the opcode
+    fn decode_indexed_bit(&mut self) -> (Z80Instruction, u8) {
+        let operand = self.operand8(6, false);
+        let opcode = self.next_byte();
+        let (x, y, z) = (opcode >> 6, (opcode >> 3) & 0b111, opcode & 0b111);
+        let copy = Self::register8(z);
+
+        match x {
+            0 => (
+                Z80Instruction::Rotate {
+                    operation: ROTATE_OPERATIONS[y as usize],
+                    operand,
+                    copy,
+                },
+                23,
+            ),
+            1 => (Z80Instruction::Bit { bit: y, operand }, 20),
+            2 => (
+                Z80Instruction::Res {
+                    bit: y,
+                    operand,
+                    copy,
+                },
+                23,
+            ),
+            _ => (
+                Z80Instruction::Set {
+                    bit: y,
+                    operand,
+                    copy,
+                },
+                23,
+            ),
+        }
+    }
+
+    /// ED prefixed, where anything undefined acts like two NOPs
+    fn decode_extended(&mut self, opcode: u8) -> (Z80Instruction, u8) {
+        let (x, y, z) = (opcode >> 6, (opcode >> 3) & 0b111, opcode & 0b111);
+        let (p, q) = (y >> 1, y & 1);
+
+        match (x, z) {
+            (1, 0) => (
+                Z80Instruction::In {
+                    destination: Self::register8(y),
+                    port: Port::C,
+                },
+                12,
+            ),
+            (1, 1) => (
+                Z80Instruction::Out {
+                    source: Self::register8(y),
+                    port: Port::C,
+                },
+                12,
+            ),
+            (1, 2) => {
+                if q == 0 {
+                    (Z80Instruction::Sbc16(self.register16(p)), 15)
+                } else {
+                    (Z80Instruction::Adc16(self.register16(p)), 15)
+                }
+            }
+            (1, 3) => {
+                let address = Operand16::Absolute(self.next_word());
+                let register = Operand16::Register(self.register16(p));
+
+                if q == 0 {
+                    (
+                        Z80Instruction::Ld16 {
+                            destination: address,
+                            source: register,
+                        },
+                        20,
+                    )
+                } else {
+                    (
+                        Z80Instruction::Ld16 {
+                            destination: register,
+                            source: address,
+                        },
+                        20,
+                    )
+                }
+            }
+            (1, 4) => (Z80Instruction::Neg, 8),
+            (1, 5) => {
+                if y == 1 {
+                    (Z80Instruction::Reti, 14)
+                } else {
+                    (Z80Instruction::Retn, 14)
+                }
+            }
+            (1, 6) => (Z80Instruction::Im(INTERRUPT_MODES[y as usize]), 8),
+            (1, 7) => {
+                let accumulator = Operand8::Register(Register8::A);
+
+                match y {
+                    0 => (
+                        Z80Instruction::Ld8 {
+                            destination: Operand8::I,
+                            source: accumulator,
+                        },
+                        9,
+                    ),
+                    1 => (
+                        Z80Instruction::Ld8 {
+                            destination: Operand8::R,
+                            source: accumulator,
+                        },
+                        9,
+                    ),
+                    2 => (
+                        Z80Instruction::Ld8 {
+                            destination: accumulator,
+                            source: Operand8::I,
+                        },
+                        9,
+                    ),
+                    3 => (
+                        Z80Instruction::Ld8 {
+                            destination: accumulator,
+                            source: Operand8::R,
+                        },
+                        9,
+                    ),
+                    4 => (Z80Instruction::Rrd, 18),
+                    5 => (Z80Instruction::Rld, 18),
+                    _ => (Z80Instruction::Nop, 8),
+                }
+            }
+            (2, 0..=3) if y >= 4 => (
+                Z80Instruction::Block {
+                    operation: BLOCK_OPERATIONS[z as usize],
+                    decrement: y & 1 == 1,
+                    repeat: y >= 6,
+                },
+                16,
+            ),
+            _ => (Z80Instruction::Nop, 8),
+        }
+    }
 }
@@ -1,5 +1,11 @@
-#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub enum Register {
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum IndexRegister {
+    Ix,
+    Iy,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Register8 {
     A,
     B,
     C,
@@ -7,41 +13,197 @@ pub enum Register {
     E,
     H,
     L,
+    /// Halves of the index registers, undocumented but used by plenty of software
+    Ixh,
+    Ixl,
+    Iyh,
+    Iyl,
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub enum SingleByteArgument {
-    Register(Register),
-    HlIndirect,
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Register16 {
+    Af,
+    Bc,
+    De,
+    Hl,
+    Sp,
+    Ix,
+    Iy,
 }
 
-impl SingleByteArgument {
-    pub fn from_id(id: u8) -> Option<Self> {
-        match id {
-            0b000 => Some(SingleByteArgument::Register(Register::B)),
-            0b001 => Some(SingleByteArgument::Register(Register::C)),
-            0b010 => Some(SingleByteArgument::Register(Register::D)),
-            0b011 => Some(SingleByteArgument::Register(Register::E)),
-            0b100 => Some(SingleByteArgument::Register(Register::H)),
-            0b101 => Some(SingleByteArgument::Register(Register::L)),
-            0b110 => Some(SingleByteArgument::HlIndirect),
-            0b111 => Some(SingleByteArgument::Register(Register::A)),
-            _ => None,
-        }
-    }
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Operand8 {
+    Register(Register8),
+    Immediate(u8),
+    /// Memory pointed to by a register pair
+    Indirect(Register16),
+    /// Memory pointed to by an index register plus a displacement
+    Indexed(IndexRegister, i8),
+    Absolute(u16),
+    /// Interrupt vector base
+    I,
+    /// Memory refresh counter
+    R,
 }
 
-pub enum I8080Instruction {
-    Nop,
-    Ld,
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Operand16 {
+    Register(Register16),
+    Immediate(u16),
+    Absolute(u16),
 }
 
-pub enum Lr35902Instruction {}
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Condition {
+    NonZero,
+    Zero,
+    NoCarry,
+    Carry,
+    ParityOdd,
+    ParityEven,
+    Positive,
+    Negative,
+}
 
-pub enum Z80Instruction {}
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum AluOperation {
+    Add,
+    Adc,
+    Sub,
+    Sbc,
+    And,
+    Xor,
+    Or,
+    Cp,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum RotateOperation {
+    Rlc,
+    Rrc,
+    Rl,
+    Rr,
+    Sla,
+    Sra,
+    /// Undocumented, shifts a 1 in
+    Sll,
+    Srl,
+}
 
-pub enum InstructionSet {
-    I8080(I8080Instruction),
-    Lr35902(Lr35902Instruction),
-    Z80(Z80Instruction),
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum BlockOperation {
+    Load,
+    Compare,
+    Input,
+    Output,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Port {
+    /// The accumulator goes out on the upper half of the address bus
+    Immediate(u8),
+    /// BC goes out on the whole address bus
+    C,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Z80Instruction {
+    Nop,
+    Halt,
+    Di,
+    Ei,
+    Ld8 {
+        destination: Operand8,
+        source: Operand8,
+    },
+    Ld16 {
+        destination: Operand16,
+        source: Operand16,
+    },
+    Push(Register16),
+    Pop(Register16),
+    ExAf,
+    Exx,
+    ExDeHl,
+    /// Swaps the top of the stack with HL, IX or IY
+    ExStack(Register16),
+    Alu {
+        operation: AluOperation,
+        operand: Operand8,
+    },
+    Inc8(Operand8),
+    Dec8(Operand8),
+    Inc16(Register16),
+    Dec16(Register16),
+    Add16 {
+        destination: Register16,
+        source: Register16,
+    },
+    Adc16(Register16),
+    Sbc16(Register16),
+    Daa,
+    Cpl,
+    Neg,
+    Ccf,
+    Scf,
+    Rlca,
+    Rrca,
+    Rla,
+    Rra,
+    Rld,
+    Rrd,
+    /// `copy` is where the undocumented indexed forms also put the result
+    Rotate {
+        operation: RotateOperation,
+        operand: Operand8,
+        copy: Option<Register8>,
+    },
+    Bit {
+        bit: u8,
+        operand: Operand8,
+    },
+    Res {
+        bit: u8,
+        operand: Operand8,
+        copy: Option<Register8>,
+    },
+    Set {
+        bit: u8,
+        operand: Operand8,
+        copy: Option<Register8>,
+    },
+    Jp {
+        condition: Option<Condition>,
+        address: u16,
+    },
+    JpIndirect(Register16),
+    Jr {
+        condition: Option<Condition>,
+        offset: i8,
+    },
+    Djnz(i8),
+    Call {
+        condition: Option<Condition>,
+        address: u16,
+    },
+    Ret(Option<Condition>),
+    Retn,
+    Reti,
+    Rst(u8),
+    /// [None] only sets the flags
+    In {
+        destination: Option<Register8>,
+        port: Port,
+    },
+    /// [None] writes out a zero
+    Out {
+        source: Option<Register8>,
+        port: Port,
+    },
+    Block {
+        operation: BlockOperation,
+        decrement: bool,
+        repeat: bool,
+    },
+    Im(u8),
 }
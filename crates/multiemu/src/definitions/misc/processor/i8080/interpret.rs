@@ -0,0 +1,749 @@
+use super::{
+    instruction::{
+        AluOperation, BlockOperation, Condition, IndexRegister, Operand16, Operand8, Port,
+        Register16, Register8, RotateOperation, Z80Instruction,
+    },
+    I8080State, Z80FlagRegister, Z80Registers, I8080,
+};
+
+// NOTE: Like the real thing this ignores every memory error, reading whatever the bus floats to
+
+const SIGN: u8 = Z80FlagRegister::Sign as u8;
+const ZERO: u8 = Z80FlagRegister::Zero as u8;
+const BIT5: u8 = Z80FlagRegister::Bit5 as u8;
+const HALF_CARRY: u8 = Z80FlagRegister::HalfCarry as u8;
+const BIT3: u8 = Z80FlagRegister::Bit3 as u8;
+const PARITY_OVERFLOW: u8 = Z80FlagRegister::ParityOverflow as u8;
+const SUBTRACT: u8 = Z80FlagRegister::Subtract as u8;
+const CARRY: u8 = Z80FlagRegister::Carry as u8;
+
+/// Sign, zero, and the undocumented bits copied out of a result
+fn sign_zero(value: u8) -> u8 {
+    (value & (SIGN | BIT5 | BIT3)) | if value == 0 { ZERO } else { 0 }
+}
+
+fn parity(value: u8) -> u8 {
+    if value.count_ones() % 2 == 0 {
+        PARITY_OVERFLOW
+    } else {
+        0
+    }
+}
+
+fn add(a: u8, value: u8, carry: u8) -> (u8, u8) {
+    let sum = a as u16 + value as u16 + carry as u16;
+    let result = sum as u8;
+
+    let mut flags = sign_zero(result);
+    if (a & 0xf) + (value & 0xf) + carry > 0xf {
+        flags |= HALF_CARRY;
+    }
+    if !(a ^ value) & (a ^ result) & 0x80 != 0 {
+        flags |= PARITY_OVERFLOW;
+    }
+    if sum > 0xff {
+        flags |= CARRY;
+    }
+
+    (result, flags)
+}
+
+fn subtract(a: u8, value: u8, carry: u8) -> (u8, u8) {
+    let difference = a as i16 - value as i16 - carry as i16;
+    let result = difference as u8;
+
+    let mut flags = SUBTRACT | sign_zero(result);
+    if (a & 0xf) < (value & 0xf) + carry {
+        flags |= HALF_CARRY;
+    }
+    if (a ^ value) & (a ^ result) & 0x80 != 0 {
+        flags |= PARITY_OVERFLOW;
+    }
+    if difference < 0 {
+        flags |= CARRY;
+    }
+
+    (result, flags)
+}
+
+fn rotate(operation: RotateOperation, value: u8, carry: u8) -> (u8, u8) {
+    match operation {
+        RotateOperation::Rlc => (value.rotate_left(1), value >> 7),
+        RotateOperation::Rrc => (value.rotate_right(1), value & 1),
+        RotateOperation::Rl => ((value << 1) | carry, value >> 7),
+        RotateOperation::Rr => ((value >> 1) | (carry << 7), value & 1),
+        RotateOperation::Sla => (value << 1, value >> 7),
+        RotateOperation::Sra => ((value >> 1) | (value & 0x80), value & 1),
+        RotateOperation::Sll => ((value << 1) | 1, value >> 7),
+        RotateOperation::Srl => (value >> 1, value & 1),
+    }
+}
+
+impl Z80Registers {
+    /// Bumps the lower 7 bits of the refresh register, the top bit only changes when written
+    pub(super) fn refresh(&mut self, fetches: u8) {
+        self.r = (self.r & 0x80) | (self.r.wrapping_add(fetches) & 0x7f);
+    }
+
+    fn get8(&self, register: Register8) -> u8 {
+        match register {
+            Register8::A => self.a,
+            Register8::B => self.b,
+            Register8::C => self.c,
+            Register8::D => self.d,
+            Register8::E => self.e,
+            Register8::H => self.h,
+            Register8::L => self.l,
+            Register8::Ixh => (self.ix >> 8) as u8,
+            Register8::Ixl => self.ix as u8,
+            Register8::Iyh => (self.iy >> 8) as u8,
+            Register8::Iyl => self.iy as u8,
+        }
+    }
+
+    fn set8(&mut self, register: Register8, value: u8) {
+        match register {
+            Register8::A => self.a = value,
+            Register8::B => self.b = value,
+            Register8::C => self.c = value,
+            Register8::D => self.d = value,
+            Register8::E => self.e = value,
+            Register8::H => self.h = value,
+            Register8::L => self.l = value,
+            Register8::Ixh => self.ix = (self.ix & 0x00ff) | ((value as u16) << 8),
+            Register8::Ixl => self.ix = (self.ix & 0xff00) | value as u16,
+            Register8::Iyh => self.iy = (self.iy & 0x00ff) | ((value as u16) << 8),
+            Register8::Iyl => self.iy = (self.iy & 0xff00) | value as u16,
+        }
+    }
+
+    fn get16(&self, register: Register16) -> u16 {
+        match register {
+            Register16::Af => u16::from_be_bytes([self.a, self.f]),
+            Register16::Bc => u16::from_be_bytes([self.b, self.c]),
+            Register16::De => u16::from_be_bytes([self.d, self.e]),
+            Register16::Hl => u16::from_be_bytes([self.h, self.l]),
+            Register16::Sp => self.sp,
+            Register16::Ix => self.ix,
+            Register16::Iy => self.iy,
+        }
+    }
+
+    fn set16(&mut self, register: Register16, value: u16) {
+        let [high, low] = value.to_be_bytes();
+
+        match register {
+            Register16::Af => (self.a, self.f) = (high, low),
+            Register16::Bc => (self.b, self.c) = (high, low),
+            Register16::De => (self.d, self.e) = (high, low),
+            Register16::Hl => (self.h, self.l) = (high, low),
+            Register16::Sp => self.sp = value,
+            Register16::Ix => self.ix = value,
+            Register16::Iy => self.iy = value,
+        }
+    }
+
+    fn condition(&self, condition: Option<Condition>) -> bool {
+        let flag = |flag: u8| self.f & flag != 0;
+
+        match condition {
+            None => true,
+            Some(Condition::NonZero) => !flag(ZERO),
+            Some(Condition::Zero) => flag(ZERO),
+            Some(Condition::NoCarry) => !flag(CARRY),
+            Some(Condition::Carry) => flag(CARRY),
+            Some(Condition::ParityOdd) => !flag(PARITY_OVERFLOW),
+            Some(Condition::ParityEven) => flag(PARITY_OVERFLOW),
+            Some(Condition::Positive) => !flag(SIGN),
+            Some(Condition::Negative) => flag(SIGN),
+        }
+    }
+
+    /// Where a memory operand points, [None] for everything else
+    fn address(&self, operand: Operand8) -> Option<u16> {
+        match operand {
+            Operand8::Indirect(register) => Some(self.get16(register)),
+            Operand8::Indexed(IndexRegister::Ix, displacement) => {
+                Some(self.ix.wrapping_add_signed(displacement as i16))
+            }
+            Operand8::Indexed(IndexRegister::Iy, displacement) => {
+                Some(self.iy.wrapping_add_signed(displacement as i16))
+            }
+            Operand8::Absolute(address) => Some(address),
+            _ => None,
+        }
+    }
+}
+
+impl I8080 {
+    pub(super) fn read_byte(&self, address: u16) -> u8 {
+        let mut byte = [0xff];
+        let _ = self.memory_translation_table.get().unwrap().read(
+            address as usize,
+            &mut byte,
+            self.config.assigned_address_space,
+        );
+
+        byte[0]
+    }
+
+    fn write_byte(&self, address: u16, value: u8) {
+        let _ = self.memory_translation_table.get().unwrap().write(
+            address as usize,
+            &[value],
+            self.config.assigned_address_space,
+        );
+    }
+
+    pub(super) fn read_word(&self, address: u16) -> u16 {
+        u16::from_le_bytes([
+            self.read_byte(address),
+            self.read_byte(address.wrapping_add(1)),
+        ])
+    }
+
+    fn write_word(&self, address: u16, value: u16) {
+        let [low, high] = value.to_le_bytes();
+
+        self.write_byte(address, low);
+        self.write_byte(address.wrapping_add(1), high);
+    }
+
+    fn input(&self, port: u16) -> u8 {
+        let mut byte = [0xff];
+        let _ = self.memory_translation_table.get().unwrap().read(
+            port as usize,
+            &mut byte,
+            self.config.io_address_space.unwrap(),
+        );
+
+        byte[0]
+    }
+
+    fn output(&self, port: u16, value: u8) {
+        let _ = self.memory_translation_table.get().unwrap().write(
+            port as usize,
+            &[value],
+            self.config.io_address_space.unwrap(),
+        );
+    }
+
+    pub(super) fn push(&self, state: &mut I8080State, value: u16) {
+        state.registers.sp = state.registers.sp.wrapping_sub(2);
+        self.write_word(state.registers.sp, value);
+    }
+
+    fn pop(&self, state: &mut I8080State) -> u16 {
+        let value = self.read_word(state.registers.sp);
+        state.registers.sp = state.registers.sp.wrapping_add(2);
+
+        value
+    }
+
+    fn load(&self, registers: &Z80Registers, operand: Operand8) -> u8 {
+        match operand {
+            Operand8::Register(register) => registers.get8(register),
+            Operand8::Immediate(value) => value,
+            Operand8::I => registers.i,
+            Operand8::R => registers.r,
+            _ => self.read_byte(registers.address(operand).unwrap()),
+        }
+    }
+
+    fn store(&self, registers: &mut Z80Registers, operand: Operand8, value: u8) {
+        match operand {
+            Operand8::Register(register) => registers.set8(register, value),
+            Operand8::Immediate(_) => unreachable!(),
+            Operand8::I => registers.i = value,
+            Operand8::R => registers.r = value,
+            _ => self.write_byte(registers.address(operand).unwrap(), value),
+        }
+    }
+
+    fn port(registers: &Z80Registers, port: Port) -> u16 {
+        match port {
+            Port::Immediate(port) => u16::from_be_bytes([registers.a, port]),
+            Port::C => registers.get16(Register16::Bc),
+        }
+    }
+
+    /// Returns T-states taken on top of what decoding said, for branches taken and block instructions repeating
+    pub(super) fn interpret_instruction(
+        &self,
+        state: &mut I8080State,
+        instruction: Z80Instruction,
+    ) -> u8 {
+        let registers = &mut state.registers;
+
+        match instruction {
+            Z80Instruction::Nop => {}
+            Z80Instruction::Halt => state.halted = true,
+            Z80Instruction::Di => {
+                registers.iff1 = false;
+                registers.iff2 = false;
+            }
+            Z80Instruction::Ei => {
+                registers.iff1 = true;
+                registers.iff2 = true;
+                state.interrupts_deferred = true;
+            }
+            Z80Instruction::Ld8 {
+                destination,
+                source,
+            } => {
+                let value = self.load(registers, source);
+                self.store(registers, destination, value);
+
+                // LD A,I and LD A,R let software see the interrupt enable
+                if matches!(source, Operand8::I | Operand8::R) {
+                    registers.f = (registers.f & CARRY)
+                        | sign_zero(value)
+                        | if registers.iff2 { PARITY_OVERFLOW } else { 0 };
+                }
+            }
+            Z80Instruction::Ld16 {
+                destination,
+                source,
+            } => {
+                let value = match source {
+                    Operand16::Register(register) => registers.get16(register),
+                    Operand16::Immediate(value) => value,
+                    Operand16::Absolute(address) => self.read_word(address),
+                };
+
+                match destination {
+                    Operand16::Register(register) => registers.set16(register, value),
+                    Operand16::Absolute(address) => self.write_word(address, value),
+                    Operand16::Immediate(_) => unreachable!(),
+                }
+            }
+            Z80Instruction::Push(register) => {
+                let value = registers.get16(register);
+                self.push(state, value);
+            }
+            Z80Instruction::Pop(register) => {
+                let value = self.pop(state);
+                state.registers.set16(register, value);
+            }
+            Z80Instruction::ExAf => {
+                let af = registers.get16(Register16::Af);
+                registers.set16(Register16::Af, registers.shadow[0]);
+                registers.shadow[0] = af;
+            }
+            Z80Instruction::Exx => {
+                for (index, register) in [Register16::Bc, Register16::De, Register16::Hl]
+                    .into_iter()
+                    .enumerate()
+                {
+                    let value = registers.get16(register);
+                    registers.set16(register, registers.shadow[index + 1]);
+                    registers.shadow[index + 1] = value;
+                }
+            }
+            Z80Instruction::ExDeHl => {
+                let de = registers.get16(Register16::De);
+                registers.set16(Register16::De, registers.get16(Register16::Hl));
+                registers.set16(Register16::Hl, de);
+            }
+            Z80Instruction::ExStack(register) => {
+                let value = self.read_word(registers.sp);
+                self.write_word(registers.sp, registers.get16(register));
+                registers.set16(register, value);
+            }
+            Z80Instruction::Alu { operation, operand } => {
+                let value = self.load(registers, operand);
+                let a = registers.a;
+                let carry = registers.f & CARRY;
+
+                let (result, flags) = match operation {
+                    AluOperation::Add => add(a, value, 0),
+                    AluOperation::Adc => add(a, value, carry),
+                    AluOperation::Sub => subtract(a, value, 0),
+                    AluOperation::Sbc => subtract(a, value, carry),
+                    AluOperation::And => (
+                        a & value,
+                        sign_zero(a & value) | parity(a & value) | HALF_CARRY,
+                    ),
+                    AluOperation::Xor => (a ^ value, sign_zero(a ^ value) | parity(a ^ value)),
+                    AluOperation::Or => (a | value, sign_zero(a | value) | parity(a | value)),
+                    AluOperation::Cp => {
+                        let (_, flags) = subtract(a, value, 0);
+
+                        // The undocumented bits come from the operand instead of the discarded result
+                        (a, (flags & !(BIT5 | BIT3)) | (value & (BIT5 | BIT3)))
+                    }
+                };
+
+                registers.a = result;
+                registers.f = flags;
+            }
+            Z80Instruction::Inc8(operand) => {
+                let value = self.load(registers, operand);
+                let result = value.wrapping_add(1);
+
+                registers.f = (registers.f & CARRY)
+                    | sign_zero(result)
+                    | if value & 0xf == 0xf { HALF_CARRY } else { 0 }
+                    | if value == 0x7f { PARITY_OVERFLOW } else { 0 };
+                self.store(registers, operand, result);
+            }
+            Z80Instruction::Dec8(operand) => {
+                let value = self.load(registers, operand);
+                let result = value.wrapping_sub(1);
+
+                registers.f = (registers.f & CARRY)
+                    | SUBTRACT
+                    | sign_zero(result)
+                    | if value & 0xf == 0 { HALF_CARRY } else { 0 }
+                    | if value == 0x80 { PARITY_OVERFLOW } else { 0 };
+                self.store(registers, operand, result);
+            }
+            Z80Instruction::Inc16(register) => {
+                registers.set16(register, registers.get16(register).wrapping_add(1));
+            }
+            Z80Instruction::Dec16(register) => {
+                registers.set16(register, registers.get16(register).wrapping_sub(1));
+            }
+            Z80Instruction::Add16 {
+                destination,
+                source,
+            } => {
+                let a = registers.get16(destination);
+                let value = registers.get16(source);
+                let (result, carry) = a.overflowing_add(value);
+
+                registers.f = (registers.f & (SIGN | ZERO | PARITY_OVERFLOW))
+                    | ((result >> 8) as u8 & (BIT5 | BIT3))
+                    | if (a & 0xfff) + (value & 0xfff) > 0xfff {
+                        HALF_CARRY
+                    } else {
+                        0
+                    }
+                    | if carry { CARRY } else { 0 };
+                registers.set16(destination, result);
+            }
+            Z80Instruction::Adc16(register) => {
+                let hl = registers.get16(Register16::Hl);
+                let value = registers.get16(register);
+                let carry = (registers.f & CARRY) as u32;
+                let sum = hl as u32 + value as u32 + carry;
+                let result = sum as u16;
+
+                registers.f = ((result >> 8) as u8 & (SIGN | BIT5 | BIT3))
+                    | if result == 0 { ZERO } else { 0 }
+                    | if (hl as u32 & 0xfff) + (value as u32 & 0xfff) + carry > 0xfff {
+                        HALF_CARRY
+                    } else {
+                        0
+                    }
+                    | if !(hl ^ value) & (hl ^ result) & 0x8000 != 0 {
+                        PARITY_OVERFLOW
+                    } else {
+                        0
+                    }
+                    | if sum > 0xffff { CARRY } else { 0 };
+                registers.set16(Register16::Hl, result);
+            }
+            Z80Instruction::Sbc16(register) => {
+                let hl = registers.get16(Register16::Hl);
+                let value = registers.get16(register);
+                let carry = (registers.f & CARRY) as i32;
+                let difference = hl as i32 - value as i32 - carry;
+                let result = difference as u16;
+
+                registers.f = SUBTRACT
+                    | ((result >> 8) as u8 & (SIGN | BIT5 | BIT3))
+                    | if result == 0 { ZERO } else { 0 }
+                    | if ((hl & 0xfff) as i32) < (value & 0xfff) as i32 + carry {
+                        HALF_CARRY
+                    } else {
+                        0
+                    }
+                    | if (hl ^ value) & (hl ^ result) & 0x8000 != 0 {
+                        PARITY_OVERFLOW
+                    } else {
+                        0
+                    }
+                    | if difference < 0 { CARRY } else { 0 };
+                registers.set16(Register16::Hl, result);
+            }
+            Z80Instruction::Daa => {
+                let a = registers.a;
+                let subtracting = registers.f & SUBTRACT != 0;
+                let mut correction = 0;
+                let mut carry = registers.f & CARRY;
+
+                if registers.f & HALF_CARRY != 0 || a & 0xf > 9 {
+                    correction |= 0x06;
+                }
+                if carry != 0 || a > 0x99 {
+                    correction |= 0x60;
+                    carry = CARRY;
+                }
+
+                let result = if subtracting {
+                    a.wrapping_sub(correction)
+                } else {
+                    a.wrapping_add(correction)
+                };
+                let half_carry = if subtracting {
+                    registers.f & HALF_CARRY != 0 && a & 0xf < 6
+                } else {
+                    a & 0xf > 9
+                };
+
+                registers.a = result;
+                registers.f = sign_zero(result)
+                    | parity(result)
+                    | (registers.f & SUBTRACT)
+                    | if half_carry { HALF_CARRY } else { 0 }
+                    | carry;
+            }
+            Z80Instruction::Cpl => {
+                registers.a = !registers.a;
+                registers.f = (registers.f & (SIGN | ZERO | PARITY_OVERFLOW | CARRY))
+                    | (registers.a & (BIT5 | BIT3))
+                    | HALF_CARRY
+                    | SUBTRACT;
+            }
+            Z80Instruction::Neg => {
+                (registers.a, registers.f) = subtract(0, registers.a, 0);
+            }
+            Z80Instruction::Ccf => {
+                let carry = registers.f & CARRY;
+
+                registers.f = (registers.f & (SIGN | ZERO | PARITY_OVERFLOW))
+                    | (registers.a & (BIT5 | BIT3))
+                    | if carry != 0 { HALF_CARRY } else { CARRY };
+            }
+            Z80Instruction::Scf => {
+                registers.f = (registers.f & (SIGN | ZERO | PARITY_OVERFLOW))
+                    | (registers.a & (BIT5 | BIT3))
+                    | CARRY;
+            }
+            Z80Instruction::Rlca
+            | Z80Instruction::Rrca
+            | Z80Instruction::Rla
+            | Z80Instruction::Rra => {
+                let operation = match instruction {
+                    Z80Instruction::Rlca => RotateOperation::Rlc,
+                    Z80Instruction::Rrca => RotateOperation::Rrc,
+                    Z80Instruction::Rla => RotateOperation::Rl,
+                    _ => RotateOperation::Rr,
+                };
+                let (result, carry) = rotate(operation, registers.a, registers.f & CARRY);
+
+                // Unlike the CB prefixed versions these leave sign, zero and parity alone
+                registers.a = result;
+                registers.f = (registers.f & (SIGN | ZERO | PARITY_OVERFLOW))
+                    | (result & (BIT5 | BIT3))
+                    | carry;
+            }
+            Z80Instruction::Rld | Z80Instruction::Rrd => {
+                let address = registers.get16(Register16::Hl);
+                let value = self.read_byte(address);
+
+                let (memory, a) = if instruction == Z80Instruction::Rld {
+                    (
+                        (value << 4) | (registers.a & 0xf),
+                        (registers.a & 0xf0) | (value >> 4),
+                    )
+                } else {
+                    (
+                        (registers.a << 4) | (value >> 4),
+                        (registers.a & 0xf0) | (value & 0xf),
+                    )
+                };
+
+                self.write_byte(address, memory);
+                registers.a = a;
+                registers.f = (registers.f & CARRY) | sign_zero(a) | parity(a);
+            }
+            Z80Instruction::Rotate {
+                operation,
+                operand,
+                copy,
+            } => {
+                let value = self.load(registers, operand);
+                let (result, carry) = rotate(operation, value, registers.f & CARRY);
+
+                registers.f = sign_zero(result) | parity(result) | carry;
+                self.store(registers, operand, result);
+                if let Some(copy) = copy {
+                    registers.set8(copy, result);
+                }
+            }
+            Z80Instruction::Bit { bit, operand } => {
+                let value = self.load(registers, operand);
+                let set = value & (1 << bit) != 0;
+
+                // Memory operands leak part of an internal address register instead, this is close enough
+                let undocumented = registers
+                    .address(operand)
+                    .map(|address| (address >> 8) as u8)
+                    .unwrap_or(value);
+
+                registers.f = (registers.f & CARRY)
+                    | HALF_CARRY
+                    | (undocumented & (BIT5 | BIT3))
+                    | if set { 0 } else { ZERO | PARITY_OVERFLOW }
+                    | if set && bit == 7 { SIGN } else { 0 };
+            }
+            Z80Instruction::Res { bit, operand, copy }
+            | Z80Instruction::Set { bit, operand, copy } => {
+                let value = self.load(registers, operand);
+                let result = if matches!(instruction, Z80Instruction::Res { .. }) {
+                    value & !(1 << bit)
+                } else {
+                    value | (1 << bit)
+                };
+
+                self.store(registers, operand, result);
+                if let Some(copy) = copy {
+                    registers.set8(copy, result);
+                }
+            }
+            Z80Instruction::Jp { condition, address } => {
+                if registers.condition(condition) {
+                    registers.pc = address;
+                }
+            }
+            Z80Instruction::JpIndirect(register) => {
+                registers.pc = registers.get16(register);
+            }
+            Z80Instruction::Jr { condition, offset } => {
+                if registers.condition(condition) {
+                    registers.pc = registers.pc.wrapping_add_signed(offset as i16);
+
+                    // The unconditional one was already counted as taken
+                    if condition.is_some() {
+                        return 5;
+                    }
+                }
+            }
+            Z80Instruction::Djnz(offset) => {
+                registers.b = registers.b.wrapping_sub(1);
+
+                if registers.b != 0 {
+                    registers.pc = registers.pc.wrapping_add_signed(offset as i16);
+                    return 5;
+                }
+            }
+            Z80Instruction::Call { condition, address } => {
+                if registers.condition(condition) {
+                    let program = registers.pc;
+                    self.push(state, program);
+                    state.registers.pc = address;
+
+                    if condition.is_some() {
+                        return 7;
+                    }
+                }
+            }
+            Z80Instruction::Ret(condition) => {
+                if registers.condition(condition) {
+                    state.registers.pc = self.pop(state);
+
+                    if condition.is_some() {
+                        return 6;
+                    }
+                }
+            }
+            Z80Instruction::Retn | Z80Instruction::Reti => {
+                state.registers.pc = self.pop(state);
+                state.registers.iff1 = state.registers.iff2;
+            }
+            Z80Instruction::Rst(address) => {
+                let program = registers.pc;
+                self.push(state, program);
+                state.registers.pc = address as u16;
+            }
+            Z80Instruction::In { destination, port } => {
+                let value = self.input(Self::port(registers, port));
+
+                // IN A,(n) is the only one that leaves the flags alone
+                if !matches!(port, Port::Immediate(_)) {
+                    registers.f = (registers.f & CARRY) | sign_zero(value) | parity(value);
+                }
+                if let Some(destination) = destination {
+                    registers.set8(destination, value);
+                }
+            }
+            Z80Instruction::Out { source, port } => {
+                let value = source.map(|source| registers.get8(source)).unwrap_or(0);
+                self.output(Self::port(registers, port), value);
+            }
+            Z80Instruction::Block {
+                operation,
+                decrement,
+                repeat,
+            } => {
+                let step = if decrement { 0xffff } else { 1 };
+                let hl = registers.get16(Register16::Hl);
+                registers.set16(Register16::Hl, hl.wrapping_add(step));
+
+                let keep_going = match operation {
+                    BlockOperation::Load => {
+                        let de = registers.get16(Register16::De);
+                        let value = self.read_byte(hl);
+                        self.write_byte(de, value);
+                        registers.set16(Register16::De, de.wrapping_add(step));
+
+                        let bc = registers.get16(Register16::Bc).wrapping_sub(1);
+                        registers.set16(Register16::Bc, bc);
+
+                        let undocumented = value.wrapping_add(registers.a);
+                        registers.f = (registers.f & (SIGN | ZERO | CARRY))
+                            | (undocumented & BIT3)
+                            | ((undocumented << 4) & BIT5)
+                            | if bc != 0 { PARITY_OVERFLOW } else { 0 };
+
+                        bc != 0
+                    }
+                    BlockOperation::Compare => {
+                        let value = self.read_byte(hl);
+                        let (result, flags) = subtract(registers.a, value, 0);
+
+                        let bc = registers.get16(Register16::Bc).wrapping_sub(1);
+                        registers.set16(Register16::Bc, bc);
+
+                        let undocumented = result.wrapping_sub((flags & HALF_CARRY != 0) as u8);
+                        registers.f = (registers.f & CARRY)
+                            | (flags & (SIGN | ZERO | HALF_CARRY | SUBTRACT))
+                            | (undocumented & BIT3)
+                            | ((undocumented << 4) & BIT5)
+                            | if bc != 0 { PARITY_OVERFLOW } else { 0 };
+
+                        bc != 0 && result != 0
+                    }
+                    BlockOperation::Input => {
+                        let value = self.input(registers.get16(Register16::Bc));
+                        self.write_byte(hl, value);
+                        registers.b = registers.b.wrapping_sub(1);
+                        registers.f = (registers.f & CARRY) | SUBTRACT | sign_zero(registers.b);
+
+                        registers.b != 0
+                    }
+                    BlockOperation::Output => {
+                        // B counts down before it goes out on the address bus
+                        let value = self.read_byte(hl);
+                        registers.b = registers.b.wrapping_sub(1);
+                        self.output(registers.get16(Register16::Bc), value);
+                        registers.f = (registers.f & CARRY) | SUBTRACT | sign_zero(registers.b);
+
+                        registers.b != 0
+                    }
+                };
+
+                if repeat && keep_going {
+                    // Runs itself again, letting interrupts in between
+                    registers.pc = registers.pc.wrapping_sub(2);
+                    return 5;
+                }
+            }
+            Z80Instruction::Im(mode) => registers.interrupt_mode = mode,
+        }
+
+        0
+    }
+}
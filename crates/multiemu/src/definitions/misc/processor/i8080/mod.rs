@@ -1,11 +1,30 @@
 use crate::{
-    component::{Component, FromConfig},
-    machine::ComponentBuilder,
+    component::{
+        introspectable::{DebugValue, IntrospectableComponent},
+        schedulable::{RunContext, SchedulableComponent},
+        Component, FromConfig, ResetKind, SnapshotError,
+    },
+    machine::{signal::SignalLine, ComponentBuilder, MachineBuildError},
+    memory::{take_wait_states, AddressSpaceId, MemoryTranslationTable},
 };
+use decode::decode_instruction;
 use enumflags2::bitflags;
+use num::rational::Ratio;
+use serde::{Deserialize, Serialize};
+use std::{
+    any::type_name,
+    sync::{Arc, Mutex, OnceLock},
+};
+
+/// The maskable interrupt input, see [crate::machine::MachineBuilder::signal_driver]
+pub const INTERRUPT_LINE: &str = "INT";
 
 mod decode;
 mod instruction;
+mod interpret;
+
+#[cfg(test)]
+mod test;
 
 #[bitflags]
 #[repr(u8)]
@@ -13,14 +32,19 @@ mod instruction;
 enum Z80FlagRegister {
     Sign = 0b1000_0000,
     Zero = 0b0100_0000,
-    __Unused0 = 0b0010_0000,
+    /// Undocumented, usually a copy of bit 5 of the result
+    Bit5 = 0b0010_0000,
     HalfCarry = 0b0001_0000,
-    __Unused1 = 0b0000_1000,
-    Overflow = 0b0000_0100,
-    Parity = 0b0000_0010,
+    /// Undocumented, usually a copy of bit 3 of the result
+    Bit3 = 0b0000_1000,
+    /// Parity for logical operations, overflow for arithmetic ones
+    ParityOverflow = 0b0000_0100,
+    Subtract = 0b0000_0010,
     Carry = 0b0000_0001,
 }
 
+// Only the Z80 is implemented so far
+#[allow(dead_code)]
 #[bitflags]
 #[repr(u8)]
 #[derive(Copy, Clone, Debug, PartialEq)]
@@ -35,6 +59,7 @@ enum Lr35902FlagRegister {
     __Unused3 = 0b0000_0001,
 }
 
+#[allow(dead_code)]
 #[bitflags]
 #[repr(u8)]
 #[derive(Copy, Clone, Debug, PartialEq)]
@@ -56,42 +81,302 @@ pub enum I8080Kind {
     Lr35902,
 }
 
-#[derive(Debug)]
-pub struct I8080 {
-    config: I8080Config,
+/// What nothing driving the data bus while an interrupt is acknowledged reads as, which is RST 38h in mode 0
+const INTERRUPT_DATA_BUS: u8 = 0xff;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Z80Registers {
+    pub a: u8,
+    pub f: u8,
+    pub b: u8,
+    pub c: u8,
+    pub d: u8,
+    pub e: u8,
+    pub h: u8,
+    pub l: u8,
+    /// AF', BC', DE' and HL', swapped in by EX AF,AF' and EXX
+    pub shadow: [u16; 4],
+    pub ix: u16,
+    pub iy: u16,
+    pub sp: u16,
+    pub pc: u16,
+    pub i: u8,
+    pub r: u8,
+    pub iff1: bool,
+    pub iff2: bool,
+    pub interrupt_mode: u8,
+}
+
+impl Default for Z80Registers {
+    fn default() -> Self {
+        // What a real one tends to come out of reset with
+        Self {
+            a: 0xff,
+            f: 0xff,
+            b: 0,
+            c: 0,
+            d: 0,
+            e: 0,
+            h: 0,
+            l: 0,
+            shadow: [0; 4],
+            ix: 0,
+            iy: 0,
+            sp: 0xffff,
+            pc: 0,
+            i: 0,
+            r: 0,
+            iff1: false,
+            iff2: false,
+            interrupt_mode: 0,
+        }
+    }
 }
 
-impl Component for I8080 {}
+/// Everything that changes as it runs, which is also what goes into a snapshot
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct I8080State {
+    pub registers: Z80Registers,
+    /// Waiting on an interrupt after a HALT
+    pub halted: bool,
+    /// EI holds interrupts off until after the next instruction
+    pub interrupts_deferred: bool,
+    /// T-states the last instruction ran past the end of the period it started in
+    pub overrun: u64,
+}
 
 #[derive(Debug)]
 pub struct I8080Config {
     pub kind: I8080Kind,
+    pub frequency: Ratio<u64>,
+    pub assigned_address_space: AddressSpaceId,
+    /// Where IN and OUT go, the LR35902 has no separate I/O bus
+    pub io_address_space: Option<AddressSpaceId>,
 }
 
 impl I8080Config {
-    pub fn lr35902() -> Self {
+    pub fn lr35902(frequency: Ratio<u64>, assigned_address_space: AddressSpaceId) -> Self {
         Self {
             kind: I8080Kind::Lr35902,
+            frequency,
+            assigned_address_space,
+            io_address_space: None,
         }
     }
 
-    pub fn z80() -> Self {
+    pub fn z80(
+        frequency: Ratio<u64>,
+        assigned_address_space: AddressSpaceId,
+        io_address_space: AddressSpaceId,
+    ) -> Self {
         Self {
             kind: I8080Kind::Z80,
+            frequency,
+            assigned_address_space,
+            io_address_space: Some(io_address_space),
         }
     }
 
-    pub fn i8080() -> Self {
+    pub fn i8080(
+        frequency: Ratio<u64>,
+        assigned_address_space: AddressSpaceId,
+        io_address_space: AddressSpaceId,
+    ) -> Self {
         Self {
             kind: I8080Kind::I8080,
+            frequency,
+            assigned_address_space,
+            io_address_space: Some(io_address_space),
+        }
+    }
+}
+
+/// The 8080 family, of which only the Z80 is implemented so far
+///
+/// Runs in T-states, an instruction that goes past the end of a period takes the difference out of the next one
+#[derive(Debug)]
+pub struct I8080 {
+    config: I8080Config,
+    state: Mutex<I8080State>,
+    /// Held by whatever wants an interrupt, checked between instructions
+    ///
+    /// Interrupts are level triggered, so whatever holds it has to release it again
+    interrupt_line: Arc<SignalLine>,
+    memory_translation_table: OnceLock<Arc<MemoryTranslationTable>>,
+}
+
+impl I8080 {
+    /// Runs one instruction or takes an interrupt, returning how many T-states it took
+    fn step(&self, state: &mut I8080State) -> u8 {
+        if self.interrupt_line.is_asserted() && state.registers.iff1 && !state.interrupts_deferred {
+            return self.accept_interrupt(state);
+        }
+        state.interrupts_deferred = false;
+
+        if state.halted {
+            state.registers.refresh(1);
+            return 4;
+        }
+
+        let program = state.registers.pc;
+        let decoded = decode_instruction(
+            program,
+            self.config.assigned_address_space,
+            self.memory_translation_table.get().unwrap(),
+        );
+
+        tracing::trace!(
+            "Decoded instruction {:?} from {:#06x}",
+            decoded.instruction,
+            program
+        );
+
+        state.registers.pc = program.wrapping_add(decoded.length);
+        state.registers.refresh(decoded.fetches);
+
+        decoded.cycles + self.interpret_instruction(state, decoded.instruction)
+    }
+
+    fn accept_interrupt(&self, state: &mut I8080State) -> u8 {
+        state.halted = false;
+        state.registers.iff1 = false;
+        state.registers.iff2 = false;
+        state.registers.refresh(1);
+
+        let program = state.registers.pc;
+        self.push(state, program);
+
+        match state.registers.interrupt_mode {
+            2 => {
+                let vector = u16::from_be_bytes([state.registers.i, INTERRUPT_DATA_BUS]);
+                state.registers.pc = self.read_word(vector);
+
+                19
+            }
+            // Mode 0 executes whatever is on the data bus, which floats to RST 38h just like mode 1
+            _ => {
+                state.registers.pc = 0x38;
+
+                13
+            }
         }
     }
 }
 
+impl Component for I8080 {
+    fn reset(&self, _kind: ResetKind) {
+        *self.state.lock().unwrap() = I8080State::default();
+    }
+
+    fn save_snapshot(&self) -> rmpv::Value {
+        rmpv::ext::to_value(&*self.state.lock().unwrap()).unwrap()
+    }
+
+    fn load_snapshot(&self, state: rmpv::Value) -> Result<(), SnapshotError> {
+        *self.state.lock().unwrap() = rmpv::ext::from_value(state)?;
+
+        Ok(())
+    }
+
+    fn set_memory_translation_table(&self, memory_translation_table: Arc<MemoryTranslationTable>) {
+        self.memory_translation_table
+            .set(memory_translation_table)
+            .unwrap();
+    }
+}
+
 impl FromConfig for I8080 {
     type Config = I8080Config;
 
-    fn from_config(component_builder: &mut ComponentBuilder<Self>, config: Self::Config) {
-        todo!()
+    fn from_config(
+        component_builder: &mut ComponentBuilder<Self>,
+        config: Self::Config,
+    ) -> Result<(), MachineBuildError> {
+        // Only the Z80 is implemented so far
+        if config.kind != I8080Kind::Z80 {
+            return Err(MachineBuildError::UnimplementedVariant {
+                component: type_name::<Self>(),
+                variant: format!("{:?}", config.kind),
+            });
+        }
+
+        if config.io_address_space.is_none() {
+            return Err(MachineBuildError::MissingAddressSpace {
+                component: type_name::<Self>(),
+                purpose: "I/O",
+            });
+        }
+
+        let frequency = config.frequency;
+        let interrupt_line = component_builder.signal_line(INTERRUPT_LINE);
+
+        component_builder
+            .set_component(Self {
+                config,
+                state: Mutex::default(),
+                interrupt_line,
+                memory_translation_table: OnceLock::default(),
+            })
+            .set_schedulable(frequency, [], [])
+            .set_debug()
+            .set_label("cpu");
+
+        Ok(())
+    }
+}
+
+impl IntrospectableComponent for I8080 {
+    fn debug_state(&self) -> Vec<(String, DebugValue)> {
+        let state = self.state.lock().unwrap();
+        let registers = &state.registers;
+
+        [
+            ("A", DebugValue::U8(registers.a)),
+            ("F", DebugValue::U8(registers.f)),
+            ("B", DebugValue::U8(registers.b)),
+            ("C", DebugValue::U8(registers.c)),
+            ("D", DebugValue::U8(registers.d)),
+            ("E", DebugValue::U8(registers.e)),
+            ("H", DebugValue::U8(registers.h)),
+            ("L", DebugValue::U8(registers.l)),
+            ("AF'", DebugValue::U16(registers.shadow[0])),
+            ("BC'", DebugValue::U16(registers.shadow[1])),
+            ("DE'", DebugValue::U16(registers.shadow[2])),
+            ("HL'", DebugValue::U16(registers.shadow[3])),
+            ("IX", DebugValue::U16(registers.ix)),
+            ("IY", DebugValue::U16(registers.iy)),
+            ("SP", DebugValue::U16(registers.sp)),
+            ("PC", DebugValue::U16(registers.pc)),
+            ("I", DebugValue::U8(registers.i)),
+            ("R", DebugValue::U8(registers.r)),
+            ("IFF1", DebugValue::Flag(registers.iff1)),
+            ("IFF2", DebugValue::Flag(registers.iff2)),
+            ("IM", DebugValue::U8(registers.interrupt_mode)),
+            ("Halted", DebugValue::Flag(state.halted)),
+        ]
+        .into_iter()
+        .map(|(name, value)| (name.to_string(), value))
+        .collect()
+    }
+}
+
+impl SchedulableComponent for I8080 {
+    fn run(&self, period: u64, _context: RunContext) {
+        let mut state = self.state.lock().unwrap();
+        let mut elapsed = state.overrun;
+
+        while elapsed < period {
+            if state.halted && !(self.interrupt_line.is_asserted() && state.registers.iff1) {
+                // Nothing but NOPs until an interrupt comes along, no point going through them one at a time
+                let nops = (period - elapsed).div_ceil(4);
+                state.registers.refresh((nops % 0x80) as u8);
+                elapsed += nops * 4;
+                break;
+            }
+
+            elapsed += self.step(&mut state) as u64 + take_wait_states();
+        }
+
+        state.overrun = elapsed - period;
     }
 }
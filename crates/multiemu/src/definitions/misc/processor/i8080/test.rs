@@ -0,0 +1,236 @@
+use super::{I8080Config, I8080, INTERRUPT_LINE};
+use crate::{
+    machine::{Machine, MachineBuildError},
+    memory::AddressSpaceId,
+    rom::{manager::RomManager, system::GameSystem},
+    testing::ComponentHarness,
+};
+use num::rational::Ratio;
+use std::sync::Arc;
+
+const ADDRESS_SPACE: AddressSpaceId = 0;
+const IO_ADDRESS_SPACE: AddressSpaceId = 1;
+
+fn z80(program: &[(u16, &[u8])]) -> ComponentHarness<I8080> {
+    let mut memory = vec![0; 0x10000];
+    for (address, bytes) in program {
+        memory[*address as usize..*address as usize + bytes.len()].copy_from_slice(bytes);
+    }
+
+    ComponentHarness::build()
+        .memory(ADDRESS_SPACE, 16, memory)
+        .memory(IO_ADDRESS_SPACE, 16, vec![0; 0x10000])
+        .component::<I8080>(I8080Config::z80(
+            Ratio::from_integer(3_500_000),
+            ADDRESS_SPACE,
+            IO_ADDRESS_SPACE,
+        ))
+}
+
+#[test]
+fn arithmetic_and_indexing() {
+    let harness = z80(&[(
+        0x0000,
+        &[
+            0x06, 0x0a, // LD B,10
+            0xaf, // XOR A
+            0x80, // ADD A,B
+            0x10, 0xfd, // DJNZ -3
+            0x32, 0x00, 0x80, // LD (0x8000),A
+            0x3e, 0x15, // LD A,0x15
+            0xc6, 0x27, // ADD A,0x27
+            0x27, // DAA
+            0x32, 0x01, 0x80, // LD (0x8001),A
+            0xdd, 0x21, 0x00, 0x80, // LD IX,0x8000
+            0xdd, 0x36, 0x02, 0x99, // LD (IX+2),0x99
+            0xdd, 0xcb, 0x03, 0xc6, // SET 0,(IX+3)
+            0xdd, 0x7e, 0x02, // LD A,(IX+2)
+            0xed, 0x44, // NEG
+            0xdd, 0x77, 0x04, // LD (IX+4),A
+            0x76, // HALT
+        ],
+    )]);
+
+    harness.run(1000);
+
+    assert_eq!(
+        &harness.memory(ADDRESS_SPACE).contents()[0x8000..0x8005],
+        [55, 0x42, 0x99, 0x01, 0x67]
+    );
+}
+
+#[test]
+fn block_copy_and_calls() {
+    let harness = z80(&[
+        (
+            0x0000,
+            &[
+                0x31, 0x00, 0x90, // LD SP,0x9000
+                0x21, 0x00, 0x02, // LD HL,0x0200
+                0x11, 0x00, 0x80, // LD DE,0x8000
+                0x01, 0x04, 0x00, // LD BC,4
+                0xed, 0xb0, // LDIR
+                0xcd, 0x00, 0x01, // CALL 0x0100
+                0x76, // HALT
+            ],
+        ),
+        (
+            0x0100,
+            &[
+                0x3e, 0x42, // LD A,0x42
+                0x32, 0x10, 0x80, // LD (0x8010),A
+                0xc9, // RET
+            ],
+        ),
+        (0x0200, &[1, 2, 3, 4]),
+    ]);
+
+    harness.run(1000);
+
+    let memory = harness.memory(ADDRESS_SPACE).contents();
+    assert_eq!(&memory[0x8000..0x8005], [1, 2, 3, 4, 0]);
+    assert_eq!(memory[0x8010], 0x42);
+    // The return address is still sitting below the stack pointer
+    assert_eq!(&memory[0x8ffe..0x9000], [0x11, 0x00]);
+}
+
+#[test]
+fn ports_go_out_on_the_io_bus() {
+    let harness = z80(&[(
+        0x0000,
+        &[
+            0x3e, 0x12, // LD A,0x12
+            0xd3, 0x34, // OUT (0x34),A
+            0x01, 0xfe, 0x7f, // LD BC,0x7ffe
+            0xed, 0x78, // IN A,(C)
+            0x32, 0x00, 0x80, // LD (0x8000),A
+            0x76, // HALT
+        ],
+    )]);
+    harness
+        .memory(IO_ADDRESS_SPACE)
+        .set_contents(0x7ffe, &[0xbf]);
+
+    harness.run(1000);
+
+    assert_eq!(harness.memory(IO_ADDRESS_SPACE).contents()[0x1234], 0x12);
+    assert_eq!(harness.memory(ADDRESS_SPACE).contents()[0x8000], 0xbf);
+}
+
+#[test]
+fn interrupts_wake_halt() {
+    let harness = z80(&[
+        (
+            0x0000,
+            &[
+                0x31, 0x00, 0x90, // LD SP,0x9000
+                0xed, 0x56, // IM 1
+                0xfb, // EI
+                0x76, // HALT
+                0x3e, 0x02, // LD A,2
+                0x32, 0x01, 0x80, // LD (0x8001),A
+                0x76, // HALT
+            ],
+        ),
+        (
+            0x0038,
+            &[
+                0x3e, 0x01, // LD A,1
+                0x32, 0x00, 0x80, // LD (0x8000),A
+                0xfb, // EI
+                0xc9, // RET
+            ],
+        ),
+    ]);
+
+    harness.run(1000);
+    assert_eq!(
+        &harness.memory(ADDRESS_SPACE).contents()[0x8000..0x8002],
+        [0, 0]
+    );
+
+    let interrupt = harness.signal_driver(INTERRUPT_LINE);
+    interrupt.assert();
+    harness.run(30);
+    interrupt.release();
+    harness.run(1000);
+
+    assert_eq!(
+        &harness.memory(ADDRESS_SPACE).contents()[0x8000..0x8002],
+        [1, 2]
+    );
+}
+
+#[test]
+fn instructions_take_their_t_states() {
+    let harness = z80(&[(
+        0x0000,
+        &[
+            0x3e, 0x01, // LD A,1 (7)
+            0x32, 0x00, 0x80, // LD (0x8000),A (13)
+            0x32, 0x01, 0x80, // LD (0x8001),A (13)
+        ],
+    )]);
+    let written = || harness.memory(ADDRESS_SPACE).contents()[0x8000..0x8002].to_vec();
+
+    harness.run(7);
+    assert_eq!(written(), [0, 0]);
+
+    // Starts the store, which runs 12 T-states into the next period
+    harness.run(1);
+    assert_eq!(written(), [1, 0]);
+
+    harness.run(12);
+    assert_eq!(written(), [1, 0]);
+
+    harness.run(1);
+    assert_eq!(written(), [1, 1]);
+}
+
+#[test]
+fn slow_memory_holds_instructions_up() {
+    let harness = z80(&[(
+        0x0000,
+        &[
+            0x3e, 0x01, // LD A,1 (7)
+            0x32, 0x00, 0x80, // LD (0x8000),A (13 + 3)
+            0x32, 0x01, 0x80, // LD (0x8001),A
+        ],
+    )]);
+    harness
+        .memory_translation_table()
+        .set_wait_states(ADDRESS_SPACE, 0x8000..0x8002, 3)
+        .unwrap();
+    let written = || harness.memory(ADDRESS_SPACE).contents()[0x8000..0x8002].to_vec();
+
+    harness.run(7);
+    harness.run(1);
+    assert_eq!(written(), [1, 0]);
+
+    // Where the store would have finished without the wait states
+    harness.run(12);
+    harness.run(1);
+    assert_eq!(written(), [1, 0]);
+
+    harness.run(2);
+    assert_eq!(written(), [1, 0]);
+
+    harness.run(1);
+    assert_eq!(written(), [1, 1]);
+}
+
+#[test]
+fn unimplemented_kinds_are_refused() {
+    let rom_manager = Arc::new(RomManager::new(None).unwrap());
+    let result = Machine::build(GameSystem::Unknown, rom_manager)
+        .insert_bus(ADDRESS_SPACE, 16)
+        .build_component::<I8080>(I8080Config::lr35902(
+            Ratio::from_integer(4_194_304),
+            ADDRESS_SPACE,
+        ));
+
+    assert!(matches!(
+        result,
+        Err(MachineBuildError::UnimplementedVariant { .. })
+    ));
+}
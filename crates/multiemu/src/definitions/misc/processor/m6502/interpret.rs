@@ -96,12 +96,9 @@ macro_rules! load_m6502_addressing_modes {
         let mut value: u8 = 0;
 
         let indirection_address = $argument.wrapping_add($register_store.index_registers[0]);
-        let mut actual_address = [0; 2];
-
-        let _ = $memory_translation_table
-            .read(indirection_address as usize, &mut actual_address, $assigned_address_space);
-
-        let actual_address = u16::from_le_bytes(actual_address);
+        let actual_address = $memory_translation_table
+            .read_le_u16(indirection_address as usize, $assigned_address_space)
+            .unwrap_or_default();
 
         let _ = $memory_translation_table
             .read(actual_address as usize, bytemuck::bytes_of_mut(&mut value), $assigned_address_space);
@@ -408,7 +405,7 @@ impl M6502 {
             M6502InstructionSetSpecifier::Pha => {
                 let _ = memory_translation_table.write(
                     state.registers.stack_pointer as usize,
-                    &state.registers.accumulator.to_le_bytes(),
+                    &[state.registers.accumulator],
                     self.config.assigned_address_space,
                 );
 
@@ -422,7 +419,7 @@ impl M6502 {
 
                 let _ = memory_translation_table.write(
                     state.registers.stack_pointer as usize,
-                    &flags.bits().to_be_bytes(),
+                    &[flags.bits()],
                     self.config.assigned_address_space,
                 );
 
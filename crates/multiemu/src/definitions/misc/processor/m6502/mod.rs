@@ -1,8 +1,11 @@
 use std::sync::{Arc, Mutex, OnceLock};
 
 use crate::{
-    component::{schedulable::SchedulableComponent, Component, FromConfig},
-    machine::ComponentBuilder,
+    component::{
+        schedulable::{RunContext, SchedulableComponent},
+        Component, FromConfig,
+    },
+    machine::{ComponentBuilder, MachineBuildError},
     memory::{AddressSpaceId, MemoryTranslationTable},
 };
 use enumflags2::{bitflags, BitFlags};
@@ -98,7 +101,10 @@ impl Component for M6502 {}
 impl FromConfig for M6502 {
     type Config = M6502Config;
 
-    fn from_config(component_builder: &mut ComponentBuilder<Self>, config: Self::Config) {
+    fn from_config(
+        component_builder: &mut ComponentBuilder<Self>,
+        config: Self::Config,
+    ) -> Result<(), MachineBuildError> {
         let frequency = config.frequency;
 
         component_builder
@@ -108,9 +114,11 @@ impl FromConfig for M6502 {
                 memory_translation_table: OnceLock::default(),
             })
             .set_schedulable(frequency, [], []);
+
+        Ok(())
     }
 }
 
 impl SchedulableComponent for M6502 {
-    fn run(&self, period: u64) {}
+    fn run(&self, period: u64, _context: RunContext) {}
 }
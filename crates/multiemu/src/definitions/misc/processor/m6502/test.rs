@@ -2,22 +2,12 @@ use indexmap::IndexMap;
 
 use super::instruction::{AddressingMode, M6502InstructionSet, M6502InstructionSetSpecifier};
 use crate::definitions::misc::processor::m6502::decode::decode_instruction;
-use crate::{
-    definitions::misc::memory::standard::{
-        StandardMemory, StandardMemoryConfig, StandardMemoryInitialContents,
-    },
-    machine::Machine,
-    memory::AddressSpaceId,
-    rom::{manager::RomManager, system::GameSystem},
-};
-use std::{borrow::Cow, collections::HashMap, sync::Arc};
+use crate::{memory::AddressSpaceId, testing::ComponentHarness};
 
 const ADDRESS_SPACE: AddressSpaceId = 0;
 
 #[test]
 fn m6502_instruction_decode() {
-    let rom_manager = Arc::new(RomManager::new(None).unwrap());
-
     let map: IndexMap<_, _> = IndexMap::from_iter([
         (
             [0x00].as_slice(),
@@ -312,23 +302,15 @@ fn m6502_instruction_decode() {
     ]);
 
     for (instruction_binary, (decoded_instruction, decoded_instruction_size)) in map {
-        let machine = Machine::build(GameSystem::Unknown, rom_manager.clone())
-            .build_component::<StandardMemory>(StandardMemoryConfig {
-                max_word_size: 8,
-                readable: true,
-                writable: true,
-                assigned_range: 0..0x4,
-                assigned_address_space: ADDRESS_SPACE,
-                initial_contents: StandardMemoryInitialContents::Array {
-                    value: Cow::Borrowed(instruction_binary),
-                    offset: 0,
-                },
-            })
-            .0
-            .build();
+        // Padded out so reading the longest instruction never runs off the end
+        let mut contents = instruction_binary.to_vec();
+        contents.resize(4, 0);
+        let memory_translation_table = ComponentHarness::build()
+            .memory(ADDRESS_SPACE, 16, contents)
+            .memory_translation_table();
 
         let (decoded_instruction_result, decoded_instruction_result_size) =
-            decode_instruction(0x0, ADDRESS_SPACE, &machine.memory_translation_table).unwrap();
+            decode_instruction(0x0, ADDRESS_SPACE, &memory_translation_table).unwrap();
 
         assert_eq!(
             (decoded_instruction, decoded_instruction_size),
@@ -1,2 +1,2 @@
-//pub mod i8080;
+pub mod i8080;
 pub mod m6502;
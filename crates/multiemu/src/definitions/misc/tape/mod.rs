@@ -0,0 +1,446 @@
+use crate::{
+    component::{
+        media::{MediaComponent, MediaSlotId, MediaSwapError},
+        schedulable::{RunContext, SchedulableComponent},
+        Component, FromConfig, ResetKind, SnapshotError,
+    },
+    machine::{ComponentBuilder, MachineBuildError},
+    rom::{
+        id::RomId,
+        manager::{RomManager, RomRequirement},
+    },
+};
+use num::rational::Ratio;
+use serde::{Deserialize, Serialize};
+use std::{
+    io::Read,
+    sync::{Arc, Mutex, RwLock},
+};
+use thiserror::Error;
+
+mod tap;
+mod tzx;
+
+/// Pulse lengths in both formats are in T-states of a Spectrum, whatever machine the tape is played into
+pub const TAPE_CLOCK: u64 = 3_500_000;
+
+/// How much faster than normal the machine runs while a tape plays with fast loading on
+pub const FAST_LOAD_SPEED: Ratio<u64> = Ratio::new_raw(16, 1);
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum TapeError {
+    #[error("Tape image is truncated")]
+    Truncated,
+    #[error("Tape image has an unsupported block type {0:#04x}")]
+    UnsupportedBlock(u8),
+}
+
+/// A stretch of tape, made up of pulses that each flip the level at their start
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TapeSegment {
+    /// `count` pulses all `length` T-states long
+    Tone { length: u32, count: u32 },
+    /// Pulses of these lengths, one after the other
+    Pulses(Vec<u32>),
+    /// Every bit is two pulses of the length for its value, most significant bit first
+    Data {
+        zero: u32,
+        one: u32,
+        /// Bits of the last byte that are actually on the tape
+        used_bits: u8,
+        data: Vec<u8>,
+    },
+    /// Silence, which leaves the level low
+    Pause { milliseconds: u32 },
+    /// The tape deck stops by itself here, for loaders that need the user to do something first
+    Stop,
+}
+
+impl TapeSegment {
+    fn pulse_count(&self) -> usize {
+        match self {
+            TapeSegment::Tone { count, .. } => *count as usize,
+            TapeSegment::Pulses(pulses) => pulses.len(),
+            TapeSegment::Data {
+                used_bits, data, ..
+            } => match data.len() {
+                0 => 0,
+                length => ((length - 1) * 8 + *used_bits as usize) * 2,
+            },
+            TapeSegment::Pause { milliseconds } => (*milliseconds != 0) as usize,
+            TapeSegment::Stop => 0,
+        }
+    }
+
+    /// How long a pulse is in T-states, and the level it sets if it does not just flip it
+    fn pulse(&self, index: usize) -> (u64, Option<bool>) {
+        match self {
+            TapeSegment::Tone { length, .. } => (*length as u64, None),
+            TapeSegment::Pulses(pulses) => (pulses[index] as u64, None),
+            TapeSegment::Data {
+                zero, one, data, ..
+            } => {
+                let bit = index / 2;
+                let length = if data[bit / 8] & (0x80 >> (bit % 8)) != 0 {
+                    *one
+                } else {
+                    *zero
+                };
+
+                (length as u64, None)
+            }
+            TapeSegment::Pause { milliseconds } => {
+                (*milliseconds as u64 * TAPE_CLOCK / 1000, Some(false))
+            }
+            TapeSegment::Stop => unreachable!(),
+        }
+    }
+}
+
+/// A tape image, flattened into segments
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Tape {
+    pub segments: Vec<TapeSegment>,
+    /// Segment each block of the image starts at, for skipping between them
+    pub block_starts: Vec<usize>,
+}
+
+impl Tape {
+    /// Works out the format from the contents, a .tzx always starts with its signature
+    pub fn parse(bytes: &[u8]) -> Result<Self, TapeError> {
+        if bytes.starts_with(tzx::SIGNATURE) {
+            tzx::parse(bytes)
+        } else {
+            tap::parse(bytes)
+        }
+    }
+
+    /// The block the ROM saves, a pilot tone, two sync pulses and the data
+    fn push_standard_block(&mut self, data: &[u8], pause_milliseconds: u32) {
+        self.block_starts.push(self.segments.len());
+        self.segments.extend([
+            TapeSegment::Tone {
+                length: 2168,
+                // Headers are flagged with a zero and get a longer tone
+                count: if data.first().is_some_and(|flag| *flag < 0x80) {
+                    8063
+                } else {
+                    3223
+                },
+            },
+            TapeSegment::Pulses(vec![667, 735]),
+            TapeSegment::Data {
+                zero: 855,
+                one: 1710,
+                used_bits: 8,
+                data: data.to_vec(),
+            },
+            TapeSegment::Pause {
+                milliseconds: pause_milliseconds,
+            },
+        ]);
+    }
+}
+
+/// What the user can do to the tape deck
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TapeControl {
+    Play,
+    Stop,
+    /// Back to the start of the tape, stopping it
+    Rewind,
+    /// Skip to the start of the next block
+    NextBlock,
+    SetFastLoad(bool),
+}
+
+/// Where the tape is, for showing the user
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TapeStatus {
+    pub block: usize,
+    pub blocks: usize,
+    pub playing: bool,
+    pub fast_load: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TapeDeckSnapshot {
+    pub segment: usize,
+    pub pulse: usize,
+    /// T-states into the current pulse
+    pub elapsed: u64,
+    pub playing: bool,
+    pub level: bool,
+}
+
+/// A cassette player, whose output goes into whatever input line the machine reads tapes from
+#[derive(Debug)]
+pub struct TapeDeck {
+    rom_manager: Arc<RomManager>,
+    /// [None] with nothing inserted
+    tape: RwLock<Option<Tape>>,
+    state: Mutex<TapeDeckSnapshot>,
+    fast_load: Mutex<bool>,
+}
+
+impl TapeDeck {
+    /// Swaps in a tape, rewound and stopped
+    pub fn insert(&self, tape: Option<Tape>) {
+        *self.tape.write().unwrap() = tape;
+        *self.state.lock().unwrap() = TapeDeckSnapshot::default();
+    }
+
+    /// The level the tape is putting out right now
+    pub fn level(&self) -> bool {
+        self.state.lock().unwrap().level
+    }
+
+    pub fn fast_load(&self) -> bool {
+        *self.fast_load.lock().unwrap()
+    }
+
+    /// If the machine should be running at [FAST_LOAD_SPEED]
+    pub fn fast_loading(&self) -> bool {
+        self.fast_load() && self.state.lock().unwrap().playing
+    }
+
+    pub fn status(&self) -> Option<TapeStatus> {
+        let tape = self.tape.read().unwrap();
+        let tape = tape.as_ref()?;
+        let state = self.state.lock().unwrap();
+
+        Some(TapeStatus {
+            block: tape
+                .block_starts
+                .iter()
+                .rposition(|start| *start <= state.segment)
+                .unwrap_or(0),
+            blocks: tape.block_starts.len(),
+            playing: state.playing,
+            fast_load: self.fast_load(),
+        })
+    }
+
+    pub fn control(&self, control: TapeControl) {
+        let tape = self.tape.read().unwrap();
+        let mut state = self.state.lock().unwrap();
+
+        match control {
+            TapeControl::Play => state.playing = tape.is_some(),
+            TapeControl::Stop => state.playing = false,
+            TapeControl::Rewind => *state = TapeDeckSnapshot::default(),
+            TapeControl::NextBlock => {
+                if let Some(tape) = tape.as_ref() {
+                    state.segment = tape
+                        .block_starts
+                        .iter()
+                        .copied()
+                        .find(|start| *start > state.segment)
+                        .unwrap_or(tape.segments.len());
+                    state.pulse = 0;
+                    state.elapsed = 0;
+                }
+            }
+            TapeControl::SetFastLoad(fast_load) => *self.fast_load.lock().unwrap() = fast_load,
+        }
+    }
+}
+
+impl Component for TapeDeck {
+    fn reset(&self, _kind: ResetKind) {
+        // Resetting the machine does nothing to the tape, but the motor does stop
+        self.state.lock().unwrap().playing = false;
+    }
+
+    fn save_snapshot(&self) -> rmpv::Value {
+        rmpv::ext::to_value(&*self.state.lock().unwrap()).unwrap()
+    }
+
+    fn load_snapshot(&self, state: rmpv::Value) -> Result<(), SnapshotError> {
+        *self.state.lock().unwrap() = rmpv::ext::from_value(state)?;
+
+        Ok(())
+    }
+}
+
+impl FromConfig for TapeDeck {
+    type Config = ();
+
+    fn from_config(
+        component_builder: &mut ComponentBuilder<Self>,
+        _config: Self::Config,
+    ) -> Result<(), MachineBuildError> {
+        let rom_manager = component_builder.machine().rom_manager.clone();
+
+        component_builder
+            .set_component(Self {
+                rom_manager,
+                tape: RwLock::default(),
+                state: Mutex::default(),
+                fast_load: Mutex::default(),
+            })
+            .set_schedulable(Ratio::from_integer(TAPE_CLOCK), [], [])
+            .set_label("tape");
+
+        Ok(())
+    }
+}
+
+impl SchedulableComponent for TapeDeck {
+    fn run(&self, period: u64, _context: RunContext) {
+        let tape = self.tape.read().unwrap();
+        let mut state = self.state.lock().unwrap();
+
+        let Some(tape) = tape.as_ref().filter(|_| state.playing) else {
+            return;
+        };
+
+        let mut remaining = period;
+
+        while remaining > 0 {
+            let Some(segment) = tape.segments.get(state.segment) else {
+                // Ran off the end
+                state.playing = false;
+                state.level = false;
+                break;
+            };
+
+            if *segment == TapeSegment::Stop {
+                state.segment += 1;
+                state.playing = false;
+                break;
+            }
+
+            if state.pulse >= segment.pulse_count() {
+                state.segment += 1;
+                state.pulse = 0;
+                state.elapsed = 0;
+                continue;
+            }
+
+            let (length, level) = segment.pulse(state.pulse);
+
+            // The edge at the start of the pulse
+            if state.elapsed == 0 {
+                state.level = level.unwrap_or(!state.level);
+            }
+
+            let left = length.max(1) - state.elapsed;
+            if remaining < left {
+                state.elapsed += remaining;
+                break;
+            }
+
+            remaining -= left;
+            state.pulse += 1;
+            state.elapsed = 0;
+        }
+    }
+}
+
+impl MediaComponent for TapeDeck {
+    fn swap_media(&self, _slot: &MediaSlotId, rom_id: Option<RomId>) -> Result<(), MediaSwapError> {
+        let Some(rom_id) = rom_id else {
+            self.insert(None);
+            return Ok(());
+        };
+
+        let mut bytes = Vec::new();
+        self.rom_manager
+            .open(rom_id, RomRequirement::Required)
+            .ok_or(MediaSwapError::MissingRom(rom_id))?
+            .read_to_end(&mut bytes)?;
+        let tape = Tape::parse(&bytes).map_err(|err| MediaSwapError::InvalidMedia(err.into()))?;
+
+        self.insert(Some(tape));
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testing::ComponentHarness;
+
+    fn deck(tape: Tape) -> ComponentHarness<TapeDeck> {
+        let harness = ComponentHarness::build().component::<TapeDeck>(());
+        harness.component().insert(Some(tape));
+
+        harness
+    }
+
+    #[test]
+    fn pulses_flip_the_level() {
+        let harness = deck(Tape {
+            segments: vec![
+                TapeSegment::Pulses(vec![100, 200]),
+                TapeSegment::Pause { milliseconds: 1 },
+            ],
+            block_starts: vec![0],
+        });
+
+        // Nothing happens until it is played
+        harness.run(50);
+        assert!(!harness.component().level());
+
+        harness.component().control(TapeControl::Play);
+        harness.run(50);
+        assert!(harness.component().level());
+        harness.run(50);
+        assert!(harness.component().level());
+        harness.run(1);
+        assert!(!harness.component().level());
+        harness.run(199);
+        // Into the pause
+        harness.run(1);
+        assert!(!harness.component().level());
+        harness.run(TAPE_CLOCK / 1000);
+        harness.run(1);
+        assert!(!harness.component().status().unwrap().playing);
+    }
+
+    #[test]
+    fn data_is_most_significant_bit_first() {
+        let segment = TapeSegment::Data {
+            zero: 1,
+            one: 2,
+            used_bits: 3,
+            data: vec![0b1000_0000, 0b0100_0000],
+        };
+
+        assert_eq!(segment.pulse_count(), (8 + 3) * 2);
+        assert_eq!(segment.pulse(0), (2, None));
+        assert_eq!(segment.pulse(1), (2, None));
+        assert_eq!(segment.pulse(2), (1, None));
+        assert_eq!(segment.pulse(18), (2, None));
+    }
+
+    #[test]
+    fn stops_and_skips_blocks() {
+        let harness = deck(Tape {
+            segments: vec![
+                TapeSegment::Tone {
+                    length: 10,
+                    count: 10,
+                },
+                TapeSegment::Stop,
+                TapeSegment::Tone {
+                    length: 10,
+                    count: 10,
+                },
+            ],
+            block_starts: vec![0, 2],
+        });
+
+        harness.component().control(TapeControl::Play);
+        harness.run(1000);
+        let status = harness.component().status().unwrap();
+        assert!(!status.playing);
+        assert_eq!(status.block, 1);
+
+        harness.component().control(TapeControl::Rewind);
+        harness.component().control(TapeControl::NextBlock);
+        assert_eq!(harness.component().status().unwrap().block, 1);
+    }
+}
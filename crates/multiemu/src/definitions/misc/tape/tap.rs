@@ -0,0 +1,62 @@
+//! .tap, nothing but the blocks the ROM saves with a length in front of each
+
+use super::{Tape, TapeError};
+
+/// The ROM waits a second between blocks
+const PAUSE_MILLISECONDS: u32 = 1000;
+
+pub fn parse(mut bytes: &[u8]) -> Result<Tape, TapeError> {
+    let mut tape = Tape::default();
+
+    while !bytes.is_empty() {
+        let length = bytes
+            .get(..2)
+            .map(|length| u16::from_le_bytes([length[0], length[1]]) as usize)
+            .ok_or(TapeError::Truncated)?;
+        let data = bytes.get(2..2 + length).ok_or(TapeError::Truncated)?;
+
+        tape.push_standard_block(data, PAUSE_MILLISECONDS);
+        bytes = &bytes[2 + length..];
+    }
+
+    Ok(tape)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::definitions::misc::tape::TapeSegment;
+
+    #[test]
+    fn headers_get_the_long_pilot_tone() {
+        let tap = [[3, 0, 0x00, 0x01, 0x02], [3, 0, 0xff, 0x03, 0x04]].concat();
+
+        let tape = parse(&tap).unwrap();
+
+        assert_eq!(tape.block_starts, [0, 4]);
+        assert_eq!(
+            tape.segments[0],
+            TapeSegment::Tone {
+                length: 2168,
+                count: 8063
+            }
+        );
+        assert_eq!(
+            tape.segments[4],
+            TapeSegment::Tone {
+                length: 2168,
+                count: 3223
+            }
+        );
+        assert_eq!(
+            tape.segments[6],
+            TapeSegment::Data {
+                zero: 855,
+                one: 1710,
+                used_bits: 8,
+                data: vec![0xff, 0x03, 0x04]
+            }
+        );
+        assert_eq!(parse(&[3, 0, 0x00]).unwrap_err(), TapeError::Truncated);
+    }
+}
@@ -0,0 +1,242 @@
+//! .tzx, which can describe custom loaders down to the individual pulse
+
+use super::{Tape, TapeError, TapeSegment};
+
+pub const SIGNATURE: &[u8] = b"ZXTape!\x1a";
+/// The signature followed by the major and minor version
+const HEADER_SIZE: usize = 10;
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, length: usize) -> Result<&'a [u8], TapeError> {
+        if self.bytes.len() < length {
+            return Err(TapeError::Truncated);
+        }
+
+        let (taken, rest) = self.bytes.split_at(length);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn byte(&mut self) -> Result<u8, TapeError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn word(&mut self) -> Result<u16, TapeError> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn triple(&mut self) -> Result<u32, TapeError> {
+        let bytes = self.take(3)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], 0]))
+    }
+
+    fn double_word(&mut self) -> Result<u32, TapeError> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+}
+
+pub fn parse(bytes: &[u8]) -> Result<Tape, TapeError> {
+    let mut reader = Reader {
+        bytes: bytes.get(HEADER_SIZE..).ok_or(TapeError::Truncated)?,
+    };
+    let mut tape = Tape::default();
+    // Where the loop started in the segments and the blocks, and how many times it goes round
+    let mut open_loop = None;
+
+    while !reader.bytes.is_empty() {
+        match reader.byte()? {
+            // Standard speed data
+            0x10 => {
+                let pause = reader.word()?;
+                let length = reader.word()? as usize;
+
+                tape.push_standard_block(reader.take(length)?, pause as u32);
+            }
+            // Turbo speed data, the standard block with every timing changed
+            0x11 => {
+                let pilot = reader.word()? as u32;
+                let sync = [reader.word()? as u32, reader.word()? as u32];
+                let zero = reader.word()? as u32;
+                let one = reader.word()? as u32;
+                let pilot_count = reader.word()? as u32;
+                let used_bits = reader.byte()?;
+                let pause = reader.word()? as u32;
+                let length = reader.triple()? as usize;
+
+                tape.block_starts.push(tape.segments.len());
+                tape.segments.extend([
+                    TapeSegment::Tone {
+                        length: pilot,
+                        count: pilot_count,
+                    },
+                    TapeSegment::Pulses(sync.to_vec()),
+                    TapeSegment::Data {
+                        zero,
+                        one,
+                        used_bits,
+                        data: reader.take(length)?.to_vec(),
+                    },
+                    TapeSegment::Pause {
+                        milliseconds: pause,
+                    },
+                ]);
+            }
+            // Pure tone
+            0x12 => {
+                let length = reader.word()? as u32;
+                let count = reader.word()? as u32;
+
+                tape.block_starts.push(tape.segments.len());
+                tape.segments.push(TapeSegment::Tone { length, count });
+            }
+            // Pulse sequence
+            0x13 => {
+                let count = reader.byte()?;
+                let pulses = (0..count)
+                    .map(|_| reader.word().map(u32::from))
+                    .collect::<Result<_, _>>()?;
+
+                tape.block_starts.push(tape.segments.len());
+                tape.segments.push(TapeSegment::Pulses(pulses));
+            }
+            // Pure data
+            0x14 => {
+                let zero = reader.word()? as u32;
+                let one = reader.word()? as u32;
+                let used_bits = reader.byte()?;
+                let pause = reader.word()? as u32;
+                let length = reader.triple()? as usize;
+
+                tape.block_starts.push(tape.segments.len());
+                tape.segments.extend([
+                    TapeSegment::Data {
+                        zero,
+                        one,
+                        used_bits,
+                        data: reader.take(length)?.to_vec(),
+                    },
+                    TapeSegment::Pause {
+                        milliseconds: pause,
+                    },
+                ]);
+            }
+            // Pause, where zero means stop the tape
+            0x20 => match reader.word()? {
+                0 => tape.segments.push(TapeSegment::Stop),
+                milliseconds => tape.segments.push(TapeSegment::Pause {
+                    milliseconds: milliseconds as u32,
+                }),
+            },
+            // Group start
+            0x21 => {
+                let length = reader.byte()? as usize;
+                reader.take(length)?;
+            }
+            // Group end
+            0x22 => {}
+            // Loop start
+            0x24 => {
+                open_loop = Some((tape.segments.len(), tape.block_starts.len(), reader.word()?))
+            }
+            // Loop end
+            0x25 => {
+                if let Some((segment, block, repetitions)) = open_loop.take() {
+                    let segments = tape.segments[segment..].to_vec();
+                    let block_starts = tape.block_starts[block..].to_vec();
+
+                    for _ in 1..repetitions {
+                        let offset = tape.segments.len() - segment;
+
+                        tape.block_starts
+                            .extend(block_starts.iter().map(|start| start + offset));
+                        tape.segments.extend_from_slice(&segments);
+                    }
+                }
+            }
+            // Stop the tape if in 48K mode, which we always are
+            0x2a => {
+                reader.double_word()?;
+                tape.segments.push(TapeSegment::Stop);
+            }
+            // Text description
+            0x30 => {
+                let length = reader.byte()? as usize;
+                reader.take(length)?;
+            }
+            // Archive info
+            0x32 => {
+                let length = reader.word()? as usize;
+                reader.take(length)?;
+            }
+            // Hardware type
+            0x33 => {
+                let count = reader.byte()? as usize;
+                reader.take(count * 3)?;
+            }
+            // Custom info
+            0x35 => {
+                reader.take(16)?;
+                let length = reader.double_word()? as usize;
+                reader.take(length)?;
+            }
+            // Glue, left behind when two files are stuck together
+            0x5a => {
+                reader.take(9)?;
+            }
+            block => return Err(TapeError::UnsupportedBlock(block)),
+        }
+    }
+
+    Ok(tape)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn tzx(blocks: &[u8]) -> Vec<u8> {
+        [SIGNATURE, &[1, 20], blocks].concat()
+    }
+
+    #[test]
+    fn standard_blocks_and_pauses() {
+        let tape = Tape::parse(&tzx(&[
+            0x10, 0xf4, 0x01, 0x02, 0x00, 0xff, 0xaa, // Standard block with a 500ms pause
+            0x30, 0x02, b'h', b'i', // Text, which is skipped
+            0x20, 0x00, 0x00, // Stop the tape
+        ]))
+        .unwrap();
+
+        assert_eq!(tape.block_starts, [0]);
+        assert_eq!(tape.segments.len(), 5);
+        assert_eq!(tape.segments[3], TapeSegment::Pause { milliseconds: 500 });
+        assert_eq!(tape.segments[4], TapeSegment::Stop);
+    }
+
+    #[test]
+    fn loops_repeat_blocks() {
+        let tape = Tape::parse(&tzx(&[
+            0x24, 0x03, 0x00, // Three times round
+            0x12, 0x10, 0x00, 0x02, 0x00, // Tone
+            0x25,
+        ]))
+        .unwrap();
+
+        assert_eq!(tape.block_starts, [0, 1, 2]);
+        assert!(tape.segments.iter().all(|segment| *segment
+            == TapeSegment::Tone {
+                length: 0x10,
+                count: 2
+            }));
+        assert_eq!(
+            Tape::parse(&tzx(&[0x15])).unwrap_err(),
+            TapeError::UnsupportedBlock(0x15)
+        );
+    }
+}
@@ -0,0 +1,396 @@
+//! Experimental host for community components compiled to WASM
+//!
+//! A plugin is a WASM module that exports `memory` and any of the following, all of which are optional
+//!
+//! - `multiemu_buffer() -> i32`: Pointer to an 8 byte buffer memory accesses are staged through, required for any memory access
+//! - `multiemu_read(address: i64, address_space: i32, size: i32) -> i32`: Fill the buffer, return 0 on success
+//! - `multiemu_write(address: i64, address_space: i32, size: i32) -> i32`: Consume the buffer, return 0 on success
+//! - `multiemu_run(period: i64)`: Run for this many ticks
+//! - `multiemu_reset()`
+//!
+//! Plugins can import the following from the `multiemu` module
+//!
+//! - `log(pointer: i32, length: i32)`: Log a UTF-8 string
+//! - `bus_read(address: i64, address_space: i32, size: i32) -> i32`: Read the machine bus into the buffer, return 0 on success
+//! - `bus_write(address: i64, address_space: i32, size: i32) -> i32`: Write the buffer to the machine bus, return 0 on success
+//!
+//! Plugins must not touch their own address ranges over the bus, those accesses are denied
+
+use crate::{
+    component::{
+        memory::MemoryComponent,
+        schedulable::{RunContext, SchedulableComponent},
+        Component, FromConfig, ResetKind,
+    },
+    machine::{ComponentBuilder, MachineBuildError},
+    memory::{
+        AddressSpaceId, MemoryTranslationTable, PreviewMemoryRecord, ReadMemoryRecord,
+        WriteMemoryRecord, VALID_ACCESS_SIZES,
+    },
+};
+use num::rational::Ratio;
+use rangemap::RangeMap;
+use std::{
+    fmt::Debug,
+    ops::Range,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+};
+use wasmtime::{Caller, Engine, Linker, Memory, Module, Store, TypedFunc};
+
+const ACCESS_OK: u32 = 0;
+const ACCESS_DENIED: u32 = 1;
+
+#[derive(Debug)]
+pub struct WasmPluginConfig {
+    pub path: PathBuf,
+    pub assigned_ranges: Vec<(AddressSpaceId, Range<usize>)>,
+    /// How many times per second the plugin runs, [None] if it has no `multiemu_run`
+    pub timings: Option<Ratio<u64>>,
+}
+
+#[derive(Default)]
+struct PluginState {
+    memory_translation_table: Option<Arc<MemoryTranslationTable>>,
+    buffer_pointer: usize,
+}
+
+pub struct WasmPlugin {
+    path: PathBuf,
+    store: Mutex<Store<PluginState>>,
+    memory: Memory,
+    read: Option<TypedFunc<(u64, u32, u32), u32>>,
+    write: Option<TypedFunc<(u64, u32, u32), u32>>,
+    run: Option<TypedFunc<u64, ()>>,
+    reset: Option<TypedFunc<(), ()>>,
+    /// Set once the plugin traps, a broken plugin should not bring the whole machine down
+    faulted: AtomicBool,
+}
+
+impl Debug for WasmPlugin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WasmPlugin")
+            .field("path", &self.path)
+            .field("faulted", &self.faulted)
+            .finish()
+    }
+}
+
+impl WasmPlugin {
+    fn call<P: wasmtime::WasmParams, R: wasmtime::WasmResults>(
+        &self,
+        function: &TypedFunc<P, R>,
+        params: P,
+    ) -> Option<R> {
+        if self.faulted.load(Ordering::Relaxed) {
+            return None;
+        }
+
+        // Failing to lock means the plugin reached itself through the bus
+        let Ok(mut store) = self.store.try_lock() else {
+            tracing::warn!("Plugin {} tried to access itself", self.path.display());
+            return None;
+        };
+
+        match function.call(&mut *store, params) {
+            Ok(result) => Some(result),
+            Err(err) => {
+                tracing::error!(
+                    "Plugin {} trapped and has been disabled: {}",
+                    self.path.display(),
+                    err
+                );
+                self.faulted.store(true, Ordering::Relaxed);
+                None
+            }
+        }
+    }
+
+    fn buffer_range(&self, store: &Store<PluginState>, size: usize) -> Range<usize> {
+        let buffer_pointer = store.data().buffer_pointer;
+        buffer_pointer..buffer_pointer + size
+    }
+}
+
+impl Component for WasmPlugin {
+    fn reset(&self, _kind: ResetKind) {
+        if let Some(reset) = &self.reset {
+            self.call(reset, ());
+        }
+    }
+
+    fn set_memory_translation_table(&self, memory_translation_table: Arc<MemoryTranslationTable>) {
+        self.store
+            .lock()
+            .unwrap()
+            .data_mut()
+            .memory_translation_table = Some(memory_translation_table);
+    }
+}
+
+impl FromConfig for WasmPlugin {
+    type Config = WasmPluginConfig;
+
+    fn from_config(
+        component_builder: &mut ComponentBuilder<Self>,
+        config: Self::Config,
+    ) -> Result<(), MachineBuildError> {
+        let engine = Engine::default();
+        let module = Module::from_file(&engine, &config.path).expect("Failed to load plugin");
+        let mut linker = Linker::new(&engine);
+
+        linker
+            .func_wrap(
+                "multiemu",
+                "log",
+                |mut caller: Caller<'_, PluginState>, pointer: u32, length: u32| {
+                    let Some(memory) = plugin_memory(&mut caller) else {
+                        return;
+                    };
+                    let pointer = pointer as usize;
+
+                    if let Some(message) =
+                        memory.data(&caller).get(pointer..pointer + length as usize)
+                    {
+                        tracing::info!("Plugin: {}", String::from_utf8_lossy(message));
+                    }
+                },
+            )
+            .unwrap()
+            .func_wrap(
+                "multiemu",
+                "bus_read",
+                |mut caller: Caller<'_, PluginState>,
+                 address: u64,
+                 address_space: u32,
+                 size: u32| {
+                    let Some(memory) = plugin_memory(&mut caller) else {
+                        return ACCESS_DENIED;
+                    };
+                    let (data, state) = memory.data_and_store_mut(&mut caller);
+
+                    match (
+                        &state.memory_translation_table,
+                        staging_buffer(data, state.buffer_pointer, size),
+                    ) {
+                        (Some(memory_translation_table), Some(buffer)) => {
+                            match memory_translation_table.read(
+                                address as usize,
+                                buffer,
+                                address_space as AddressSpaceId,
+                            ) {
+                                Ok(()) => ACCESS_OK,
+                                Err(_) => ACCESS_DENIED,
+                            }
+                        }
+                        _ => ACCESS_DENIED,
+                    }
+                },
+            )
+            .unwrap()
+            .func_wrap(
+                "multiemu",
+                "bus_write",
+                |mut caller: Caller<'_, PluginState>,
+                 address: u64,
+                 address_space: u32,
+                 size: u32| {
+                    let Some(memory) = plugin_memory(&mut caller) else {
+                        return ACCESS_DENIED;
+                    };
+                    let (data, state) = memory.data_and_store_mut(&mut caller);
+
+                    match (
+                        &state.memory_translation_table,
+                        staging_buffer(data, state.buffer_pointer, size),
+                    ) {
+                        (Some(memory_translation_table), Some(buffer)) => {
+                            match memory_translation_table.write(
+                                address as usize,
+                                buffer,
+                                address_space as AddressSpaceId,
+                            ) {
+                                Ok(()) => ACCESS_OK,
+                                Err(_) => ACCESS_DENIED,
+                            }
+                        }
+                        _ => ACCESS_DENIED,
+                    }
+                },
+            )
+            .unwrap();
+
+        let mut store = Store::new(&engine, PluginState::default());
+        let instance = linker
+            .instantiate(&mut store, &module)
+            .expect("Failed to instantiate plugin");
+        let memory = instance
+            .get_memory(&mut store, "memory")
+            .expect("Plugin must export its memory");
+
+        if let Ok(buffer) = instance.get_typed_func::<(), u32>(&mut store, "multiemu_buffer") {
+            store.data_mut().buffer_pointer = buffer
+                .call(&mut store, ())
+                .expect("Plugin failed to report its buffer")
+                as usize;
+        }
+
+        let read = instance.get_typed_func(&mut store, "multiemu_read").ok();
+        let write = instance.get_typed_func(&mut store, "multiemu_write").ok();
+        let run = instance.get_typed_func(&mut store, "multiemu_run").ok();
+        let reset = instance.get_typed_func(&mut store, "multiemu_reset").ok();
+
+        tracing::info!(
+            "Loaded plugin {} (read: {}, write: {}, run: {})",
+            config.path.display(),
+            read.is_some(),
+            write.is_some(),
+            run.is_some()
+        );
+
+        let is_schedulable = run.is_some();
+        let component_builder = component_builder.set_component(Self {
+            path: config.path,
+            store: Mutex::new(store),
+            memory,
+            read,
+            write,
+            run,
+            reset,
+            faulted: AtomicBool::new(false),
+        });
+
+        if !config.assigned_ranges.is_empty() {
+            component_builder.set_memory(config.assigned_ranges);
+        }
+
+        match (config.timings, is_schedulable) {
+            (Some(timings), true) => {
+                component_builder.set_schedulable(timings, [], []);
+            }
+            (Some(_), false) => {
+                tracing::warn!("Plugin was given timings but does not export multiemu_run");
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+}
+
+impl MemoryComponent for WasmPlugin {
+    fn read_memory(
+        &self,
+        address: usize,
+        buffer: &mut [u8],
+        address_space: AddressSpaceId,
+        errors: &mut RangeMap<usize, ReadMemoryRecord>,
+    ) {
+        let affected_range = address..address + buffer.len();
+
+        let Some(read) = &self.read else {
+            errors.insert(affected_range, ReadMemoryRecord::Denied);
+            return;
+        };
+
+        if self.call(
+            read,
+            (address as u64, address_space as u32, buffer.len() as u32),
+        ) != Some(ACCESS_OK)
+        {
+            errors.insert(affected_range, ReadMemoryRecord::Denied);
+            return;
+        }
+
+        let store = self.store.lock().unwrap();
+        let buffer_range = self.buffer_range(&store, buffer.len());
+
+        match self.memory.data(&*store).get(buffer_range) {
+            Some(staged) => buffer.copy_from_slice(staged),
+            None => {
+                errors.insert(affected_range, ReadMemoryRecord::Denied);
+            }
+        }
+    }
+
+    fn write_memory(
+        &self,
+        address: usize,
+        buffer: &[u8],
+        address_space: AddressSpaceId,
+        errors: &mut RangeMap<usize, WriteMemoryRecord>,
+    ) {
+        let affected_range = address..address + buffer.len();
+
+        let Some(write) = &self.write else {
+            errors.insert(affected_range, WriteMemoryRecord::Denied);
+            return;
+        };
+
+        {
+            let Ok(mut store) = self.store.try_lock() else {
+                tracing::warn!("Plugin {} tried to access itself", self.path.display());
+                errors.insert(affected_range, WriteMemoryRecord::Denied);
+                return;
+            };
+            let buffer_range = self.buffer_range(&store, buffer.len());
+
+            match self.memory.data_mut(&mut *store).get_mut(buffer_range) {
+                Some(staged) => staged.copy_from_slice(buffer),
+                None => {
+                    errors.insert(affected_range, WriteMemoryRecord::Denied);
+                    return;
+                }
+            }
+        }
+
+        if self.call(
+            write,
+            (address as u64, address_space as u32, buffer.len() as u32),
+        ) != Some(ACCESS_OK)
+        {
+            errors.insert(affected_range, WriteMemoryRecord::Denied);
+        }
+    }
+
+    // Reading a plugin can do anything, so we can't promise it won't change state
+    fn preview_memory(
+        &self,
+        address: usize,
+        buffer: &mut [u8],
+        _address_space: AddressSpaceId,
+        errors: &mut RangeMap<usize, PreviewMemoryRecord>,
+    ) {
+        errors.insert(
+            address..address + buffer.len(),
+            PreviewMemoryRecord::Impossible,
+        );
+    }
+}
+
+impl SchedulableComponent for WasmPlugin {
+    fn run(&self, period: u64, _context: RunContext) {
+        if let Some(run) = &self.run {
+            self.call(run, period);
+        }
+    }
+}
+
+fn plugin_memory(caller: &mut Caller<'_, PluginState>) -> Option<Memory> {
+    caller
+        .get_export("memory")
+        .and_then(|export| export.into_memory())
+}
+
+fn staging_buffer(data: &mut [u8], buffer_pointer: usize, size: u32) -> Option<&mut [u8]> {
+    let size = size as usize;
+
+    if !VALID_ACCESS_SIZES.contains(&size) {
+        return None;
+    }
+
+    data.get_mut(buffer_pointer..buffer_pointer + size)
+}
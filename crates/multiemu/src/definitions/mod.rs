@@ -1,3 +1,4 @@
 pub mod chip8;
 pub mod misc;
 pub mod nes;
+pub mod spectrum;
@@ -0,0 +1,84 @@
+//! Which iNES mappers the NES core can run, and what the rest are called so users know what a ROM is waiting on
+
+use crate::{machine::definition::UnsupportedRom, rom::header::InesHeader};
+
+/// Mappers with a working implementation
+///
+/// Cartridges are not loaded at all yet, NROM is going to be the first. Until then every NES ROM is reported as
+/// unsupported and refused before the machine is built, which is on purpose since none of them would get past reset
+pub const SUPPORTED_MAPPERS: &[u16] = &[];
+
+/// Boards behind the mapper numbers most of a library uses, going by the NESdev wiki
+const BOARDS: &[(u16, &str)] = &[
+    (0, "NROM"),
+    (1, "MMC1, SxROM"),
+    (2, "UxROM"),
+    (3, "CNROM"),
+    (4, "MMC3, TxROM"),
+    (5, "MMC5, ExROM"),
+    (7, "AxROM"),
+    (9, "MMC2, PxROM"),
+    (10, "MMC4, FxROM"),
+    (11, "Color Dreams"),
+    (13, "CPROM"),
+    (19, "Namco 163"),
+    (21, "VRC4a/VRC4c"),
+    (22, "VRC2a"),
+    (23, "VRC2b/VRC4e"),
+    (24, "VRC6a"),
+    (25, "VRC4b/VRC4d"),
+    (26, "VRC6b"),
+    (34, "BNROM/NINA-001"),
+    (66, "GxROM"),
+    (69, "Sunsoft FME-7"),
+    (71, "Camerica BF9093"),
+    (85, "VRC7"),
+    (206, "Namco 118, DxROM"),
+];
+
+/// Name of the board that uses `mapper`, for the mappers common enough to be worth naming
+pub fn board_name(mapper: u16) -> Option<&'static str> {
+    BOARDS
+        .iter()
+        .find(|(board_mapper, _)| *board_mapper == mapper)
+        .map(|(_, name)| *name)
+}
+
+pub fn is_supported(mapper: u16) -> bool {
+    SUPPORTED_MAPPERS.contains(&mapper)
+}
+
+/// Checks the header against the mappers that are implemented, naming the missing one if it is not
+pub fn check_mapper(header: &InesHeader) -> Result<(), UnsupportedRom> {
+    if is_supported(header.mapper) {
+        return Ok(());
+    }
+
+    let mut feature = format!("iNES mapper {}", header.mapper);
+    if let Some(submapper) = header.submapper.filter(|submapper| *submapper != 0) {
+        feature.push_str(&format!(".{}", submapper));
+    }
+    if let Some(board) = board_name(header.mapper) {
+        feature.push_str(&format!(" ({})", board));
+    }
+
+    Err(UnsupportedRom { feature })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn unsupported_mappers_are_named() {
+        let mut rom = vec![b'N', b'E', b'S', 0x1a, 2, 1, 0x40, 0x00];
+        rom.resize(16, 0);
+        let header = InesHeader::parse(&rom).unwrap();
+
+        assert_eq!(
+            check_mapper(&header).unwrap_err().feature,
+            "iNES mapper 4 (MMC3, TxROM)"
+        );
+        assert_eq!(board_name(1000), None);
+    }
+}
@@ -1,26 +1,78 @@
-use super::misc::memory::{
-    mirror::{MirrorMemory, MirrorMemoryConfig},
-    standard::{StandardMemory, StandardMemoryConfig, StandardMemoryInitialContents},
+use super::misc::memory::standard::{
+    StandardMemory, StandardMemoryConfig, StandardMemoryInitialContents,
 };
 use crate::{
-    machine::Machine,
+    machine::{
+        definition::{MachineDefinition, UnsupportedRom},
+        Machine, MachineBuildError,
+    },
     memory::AddressSpaceId,
     rom::{
+        header::InesHeader,
         id::RomId,
-        manager::RomManager,
+        manager::{RomManager, RomRequirement},
         system::{GameSystem, NintendoSystem},
     },
 };
 use ppu::NesPPU;
-use rangemap::RangeMap;
-use std::sync::Arc;
+use std::{io::Read, sync::Arc};
 
 pub const NES_CPU_ADDRESS_SPACE_ID: AddressSpaceId = 0;
 pub const NES_PPU_ADDRESS_SPACE_ID: AddressSpaceId = 1;
 
-mod ppu;
+pub mod cartidge;
+pub mod ppu;
+
+pub struct NesDefinition;
+
+impl MachineDefinition for NesDefinition {
+    fn name(&self) -> &str {
+        "NES"
+    }
+
+    fn systems(&self) -> Vec<GameSystem> {
+        vec![GameSystem::Nintendo(
+            NintendoSystem::NintendoEntertainmentSystem,
+        )]
+    }
+
+    fn check_rom(
+        &self,
+        _system: GameSystem,
+        rom_id: RomId,
+        rom_manager: &RomManager,
+    ) -> Result<(), UnsupportedRom> {
+        // A missing ROM gets reported when the machine opens it
+        let Some(mut rom) = rom_manager.open(rom_id, RomRequirement::Optional) else {
+            return Ok(());
+        };
+
+        let mut header = [0; 16];
+        let header = rom
+            .read_exact(&mut header)
+            .ok()
+            .and_then(|_| InesHeader::parse(&header))
+            .ok_or_else(|| UnsupportedRom {
+                feature: "ROMs without an iNES header".to_string(),
+            })?;
+
+        cartidge::check_mapper(&header)
+    }
+
+    fn construct(
+        &self,
+        _system: GameSystem,
+        user_specified_roms: Vec<RomId>,
+        rom_manager: Arc<RomManager>,
+    ) -> Result<Machine, MachineBuildError> {
+        nes_machine(user_specified_roms, rom_manager)
+    }
+}
 
-pub fn nes_machine(user_specified_roms: Vec<RomId>, rom_manager: Arc<RomManager>) -> Machine {
+pub fn nes_machine(
+    user_specified_roms: Vec<RomId>,
+    rom_manager: Arc<RomManager>,
+) -> Result<Machine, MachineBuildError> {
     let machine = Machine::build(
         GameSystem::Nintendo(NintendoSystem::NintendoEntertainmentSystem),
         rom_manager,
@@ -30,89 +82,94 @@ pub fn nes_machine(user_specified_roms: Vec<RomId>, rom_manager: Arc<RomManager>
     let machine = machine.insert_bus(NES_PPU_ADDRESS_SPACE_ID, 16);
 
     // Set up the NES workram
-    let (machine, _) = machine.build_component::<StandardMemory>(StandardMemoryConfig {
-        readable: true,
-        writable: true,
-        max_word_size: 2,
-        assigned_range: 0x0000..0x0800,
-        assigned_address_space: NES_CPU_ADDRESS_SPACE_ID,
-        initial_contents: StandardMemoryInitialContents::Random,
-    });
-    let (machine, _) = machine.build_component::<MirrorMemory>(MirrorMemoryConfig {
-        readable: true,
-        writable: true,
-        assigned_ranges: RangeMap::from_iter([
-            (0x0800..0x1000, 0x0000),
-            (0x1000..0x1800, 0x0000),
-            (0x1800..0x2000, 0x0000),
-        ]),
-        assigned_address_space: NES_CPU_ADDRESS_SPACE_ID,
-    });
+    let (machine, workram_id) = machine.build_labeled_component::<StandardMemory>(
+        "wram",
+        StandardMemoryConfig {
+            readable: true,
+            writable: true,
+            max_word_size: 2,
+            assigned_range: 0x0000..0x0800,
+            assigned_address_space: NES_CPU_ADDRESS_SPACE_ID,
+            initial_contents: StandardMemoryInitialContents::Random,
+        },
+    )?;
+    let machine =
+        machine.mirror_memory(workram_id, NES_CPU_ADDRESS_SPACE_ID, 0x0800..0x2000, 0x07ff)?;
 
     // Set up the PPU
-    let (machine, _) = machine.default_component::<NesPPU>();
-    let (machine, _) = machine.build_component::<MirrorMemory>(MirrorMemoryConfig {
-        readable: true,
-        writable: true,
-        // Repeats every 8 bytes, not writing it out manually
-        assigned_ranges: RangeMap::from_iter(
-            (0x2008..0x4000)
-                .step_by(8)
-                .map(|base| (base..base + 8, 0x2000)),
-        ),
-        assigned_address_space: NES_CPU_ADDRESS_SPACE_ID,
-    });
+    let (machine, ppu_id) = machine.default_component::<NesPPU>()?;
+    // Registers repeat every 8 bytes
+    let machine =
+        machine.mirror_memory(ppu_id, NES_CPU_ADDRESS_SPACE_ID, 0x2008..0x4000, 0x2007)?;
     // Set up the PPU address space
     // Pattern tables
-    let (machine, _) = machine.build_component::<StandardMemory>(StandardMemoryConfig {
-        readable: true,
-        writable: true,
-        max_word_size: 2,
-        assigned_range: 0x0000..0x1000,
-        assigned_address_space: NES_PPU_ADDRESS_SPACE_ID,
-        initial_contents: StandardMemoryInitialContents::Random,
-    });
-    let (machine, _) = machine.build_component::<StandardMemory>(StandardMemoryConfig {
-        readable: true,
-        writable: true,
-        max_word_size: 2,
-        assigned_range: 0x1000..0x2000,
-        assigned_address_space: NES_PPU_ADDRESS_SPACE_ID,
-        initial_contents: StandardMemoryInitialContents::Random,
-    });
+    let (machine, _) = machine.build_labeled_component::<StandardMemory>(
+        "pattern_table_0",
+        StandardMemoryConfig {
+            readable: true,
+            writable: true,
+            max_word_size: 2,
+            assigned_range: 0x0000..0x1000,
+            assigned_address_space: NES_PPU_ADDRESS_SPACE_ID,
+            initial_contents: StandardMemoryInitialContents::Random,
+        },
+    )?;
+    let (machine, _) = machine.build_labeled_component::<StandardMemory>(
+        "pattern_table_1",
+        StandardMemoryConfig {
+            readable: true,
+            writable: true,
+            max_word_size: 2,
+            assigned_range: 0x1000..0x2000,
+            assigned_address_space: NES_PPU_ADDRESS_SPACE_ID,
+            initial_contents: StandardMemoryInitialContents::Random,
+        },
+    )?;
     // Name tables
-    let (machine, _) = machine.build_component::<StandardMemory>(StandardMemoryConfig {
-        readable: true,
-        writable: true,
-        max_word_size: 2,
-        assigned_range: 0x2000..0x2400,
-        assigned_address_space: NES_PPU_ADDRESS_SPACE_ID,
-        initial_contents: StandardMemoryInitialContents::Random,
-    });
-    let (machine, _) = machine.build_component::<StandardMemory>(StandardMemoryConfig {
-        readable: true,
-        writable: true,
-        max_word_size: 2,
-        assigned_range: 0x2400..0x2800,
-        assigned_address_space: NES_PPU_ADDRESS_SPACE_ID,
-        initial_contents: StandardMemoryInitialContents::Random,
-    });
-    let (machine, _) = machine.build_component::<StandardMemory>(StandardMemoryConfig {
-        readable: true,
-        writable: true,
-        max_word_size: 2,
-        assigned_range: 0x2800..0x2c00,
-        assigned_address_space: NES_PPU_ADDRESS_SPACE_ID,
-        initial_contents: StandardMemoryInitialContents::Random,
-    });
-    let (machine, _) = machine.build_component::<StandardMemory>(StandardMemoryConfig {
-        readable: true,
-        writable: true,
-        max_word_size: 2,
-        assigned_range: 0x2c00..0x3000,
-        assigned_address_space: NES_PPU_ADDRESS_SPACE_ID,
-        initial_contents: StandardMemoryInitialContents::Random,
-    });
+    let (machine, _) = machine.build_labeled_component::<StandardMemory>(
+        "name_table_0",
+        StandardMemoryConfig {
+            readable: true,
+            writable: true,
+            max_word_size: 2,
+            assigned_range: 0x2000..0x2400,
+            assigned_address_space: NES_PPU_ADDRESS_SPACE_ID,
+            initial_contents: StandardMemoryInitialContents::Random,
+        },
+    )?;
+    let (machine, _) = machine.build_labeled_component::<StandardMemory>(
+        "name_table_1",
+        StandardMemoryConfig {
+            readable: true,
+            writable: true,
+            max_word_size: 2,
+            assigned_range: 0x2400..0x2800,
+            assigned_address_space: NES_PPU_ADDRESS_SPACE_ID,
+            initial_contents: StandardMemoryInitialContents::Random,
+        },
+    )?;
+    let (machine, _) = machine.build_labeled_component::<StandardMemory>(
+        "name_table_2",
+        StandardMemoryConfig {
+            readable: true,
+            writable: true,
+            max_word_size: 2,
+            assigned_range: 0x2800..0x2c00,
+            assigned_address_space: NES_PPU_ADDRESS_SPACE_ID,
+            initial_contents: StandardMemoryInitialContents::Random,
+        },
+    )?;
+    let (machine, _) = machine.build_labeled_component::<StandardMemory>(
+        "name_table_3",
+        StandardMemoryConfig {
+            readable: true,
+            writable: true,
+            max_word_size: 2,
+            assigned_range: 0x2c00..0x3000,
+            assigned_address_space: NES_PPU_ADDRESS_SPACE_ID,
+            initial_contents: StandardMemoryInitialContents::Random,
+        },
+    )?;
 
     machine.build()
 }
@@ -1,7 +1,10 @@
 use crate::{
     component::{memory::MemoryComponent, Component, FromConfig},
-    machine::ComponentBuilder,
-    memory::{AddressSpaceId, MemoryTranslationTable, ReadMemoryRecord, WriteMemoryRecord},
+    machine::{ComponentBuilder, MachineBuildError},
+    memory::{
+        AddressSpaceId, MemoryTranslationTable, PreviewMemoryRecord, ReadMemoryRecord,
+        WriteMemoryRecord,
+    },
 };
 use std::sync::Arc;
 
@@ -30,7 +33,7 @@ struct State {
 }
 
 #[derive(Debug)]
-pub(super) struct NesPPU {}
+pub struct NesPPU {}
 
 impl Component for NesPPU {
     fn set_memory_translation_table(&self, _memory_translation_table: Arc<MemoryTranslationTable>) {
@@ -40,14 +43,20 @@ impl Component for NesPPU {
 impl FromConfig for NesPPU {
     type Config = ();
 
-    fn from_config(component_builder: &mut ComponentBuilder<Self>, config: Self::Config) {
+    fn from_config(
+        component_builder: &mut ComponentBuilder<Self>,
+        config: Self::Config,
+    ) -> Result<(), MachineBuildError> {
         component_builder
             .set_component(Self {})
             // Claim our registers
             .set_memory([
                 (NES_CPU_ADDRESS_SPACE_ID, 0x2000..0x2008),
                 (NES_CPU_ADDRESS_SPACE_ID, 0x4014..0x4015),
-            ]);
+            ])
+            .set_label("ppu");
+
+        Ok(())
     }
 }
 
@@ -97,4 +106,18 @@ impl MemoryComponent for NesPPU {
             }
         }
     }
+
+    // Reading status clears vblank and reading data moves the address along, so none of these can be peeked at
+    fn preview_memory(
+        &self,
+        address: usize,
+        buffer: &mut [u8],
+        _address_space: AddressSpaceId,
+        errors: &mut rangemap::RangeMap<usize, PreviewMemoryRecord>,
+    ) {
+        errors.insert(
+            address..address + buffer.len(),
+            PreviewMemoryRecord::Impossible,
+        );
+    }
 }
@@ -0,0 +1,103 @@
+use crate::{
+    component::{
+        audio::AudioComponent,
+        schedulable::{RunContext, SchedulableComponent},
+        Component, FromConfig, ResetKind, SnapshotError,
+    },
+    machine::{ComponentBuilder, MachineBuildError},
+};
+use num::rational::Ratio;
+use ringbuffer::{AllocRingBuffer, RingBuffer};
+use serde::{Deserialize, Serialize};
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Mutex,
+};
+
+/// A divisor of the processor clock, so every sample lines up with a T-state
+pub const BEEPER_SAMPLE_RATE: u64 = 31250;
+const AMPLITUDE: f32 = 0.25;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BeeperSnapshot {
+    pub level: bool,
+}
+
+/// The speaker hanging off bit 4 of port 0xfe, programs make sound by toggling it fast enough
+#[derive(Debug)]
+pub struct Beeper {
+    level: AtomicBool,
+    /// Whatever plays these drains them, the oldest are dropped if nothing does
+    samples: Mutex<AllocRingBuffer<f32>>,
+}
+
+impl Beeper {
+    pub fn set_level(&self, high: bool) {
+        self.level.store(high, Ordering::Relaxed);
+    }
+}
+
+impl Component for Beeper {
+    fn reset(&self, _kind: ResetKind) {
+        self.level.store(false, Ordering::Relaxed);
+        self.samples.lock().unwrap().clear();
+    }
+
+    fn save_snapshot(&self) -> rmpv::Value {
+        rmpv::ext::to_value(BeeperSnapshot {
+            level: self.level.load(Ordering::Relaxed),
+        })
+        .unwrap()
+    }
+
+    fn load_snapshot(&self, state: rmpv::Value) -> Result<(), SnapshotError> {
+        let snapshot: BeeperSnapshot = rmpv::ext::from_value(state)?;
+
+        self.level.store(snapshot.level, Ordering::Relaxed);
+        self.samples.lock().unwrap().clear();
+
+        Ok(())
+    }
+}
+
+impl FromConfig for Beeper {
+    type Config = ();
+
+    fn from_config(
+        component_builder: &mut ComponentBuilder<Self>,
+        _config: Self::Config,
+    ) -> Result<(), MachineBuildError> {
+        component_builder
+            .set_component(Self {
+                level: AtomicBool::new(false),
+                // A second worth
+                samples: Mutex::new(AllocRingBuffer::new(BEEPER_SAMPLE_RATE as usize)),
+            })
+            .set_schedulable(Ratio::from_integer(BEEPER_SAMPLE_RATE), [], [])
+            .set_audio(BEEPER_SAMPLE_RATE as u32)
+            .set_label("beeper");
+
+        Ok(())
+    }
+}
+
+impl AudioComponent for Beeper {
+    fn drain_samples(&self) -> Vec<f32> {
+        self.samples.lock().unwrap().drain().collect()
+    }
+}
+
+impl SchedulableComponent for Beeper {
+    fn run(&self, period: u64, _context: RunContext) {
+        let sample = if self.level.load(Ordering::Relaxed) {
+            AMPLITUDE
+        } else {
+            -AMPLITUDE
+        };
+
+        self.samples
+            .lock()
+            .unwrap()
+            .extend(std::iter::repeat(sample).take(period as usize));
+    }
+}
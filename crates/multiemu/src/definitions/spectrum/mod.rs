@@ -0,0 +1,201 @@
+use super::misc::{
+    memory::{
+        rom::{RomMemory, RomMemoryConfig},
+        standard::{
+            StandardMemory, StandardMemoryConfig, StandardMemoryInitialContents,
+            StandardMemorySnapshot,
+        },
+    },
+    processor::i8080::{I8080Config, I8080},
+    tape::{Tape, TapeDeck},
+};
+use crate::{
+    component::{
+        media::{MediaSlotId, MediaSlotMetadata},
+        ComponentId,
+    },
+    machine::{
+        definition::{FirmwareRequirement, MachineDefinition},
+        serialization::SavestateError,
+        Machine, MachineBuildError,
+    },
+    memory::AddressSpaceId,
+    rom::{
+        id::RomId,
+        manager::{RomManager, RomRequirement},
+        system::{ComputerSystem, GameSystem},
+    },
+};
+use beeper::Beeper;
+use num::rational::Ratio;
+use snapshot::SpectrumSnapshot;
+use std::{borrow::Cow, io::Read, sync::Arc};
+use ula::{Ula, UlaConfig, UlaSnapshot};
+
+pub mod beeper;
+pub mod snapshot;
+pub mod ula;
+
+pub const SPECTRUM_ADDRESS_SPACE_ID: AddressSpaceId = 0;
+pub const SPECTRUM_IO_ADDRESS_SPACE_ID: AddressSpaceId = 1;
+pub const SPECTRUM_TAPE_MEDIA_SLOT: MediaSlotId = MediaSlotId::new("tape");
+
+/// The 16K Sinclair BASIC ROM every 48K shipped with
+pub const SPECTRUM_48K_ROM: RomId = RomId::new([
+    0x5e, 0xa7, 0xc2, 0xb8, 0x24, 0x67, 0x2e, 0x91, 0x45, 0x25, 0xd1, 0xd5, 0xc4, 0x19, 0xd7, 0x1b,
+    0x84, 0xa4, 0x26, 0xa2,
+]);
+
+pub struct SpectrumDefinition;
+
+impl MachineDefinition for SpectrumDefinition {
+    fn name(&self) -> &str {
+        "ZX Spectrum"
+    }
+
+    fn systems(&self) -> Vec<GameSystem> {
+        vec![GameSystem::Computer(ComputerSystem::ZxSpectrum)]
+    }
+
+    fn firmware(&self, _system: GameSystem) -> Vec<FirmwareRequirement> {
+        vec![FirmwareRequirement {
+            name: Cow::Borrowed("48K ROM"),
+            rom_id: SPECTRUM_48K_ROM,
+            requirement: RomRequirement::Required,
+        }]
+    }
+
+    fn construct(
+        &self,
+        _system: GameSystem,
+        user_specified_roms: Vec<RomId>,
+        rom_manager: Arc<RomManager>,
+    ) -> Result<Machine, MachineBuildError> {
+        spectrum_machine(user_specified_roms, rom_manager)
+    }
+}
+
+/// A 48K, with the first user specified ROM put in the tape deck or loaded as a .sna or .z80 snapshot if there is one
+pub fn spectrum_machine(
+    user_specified_roms: Vec<RomId>,
+    rom_manager: Arc<RomManager>,
+) -> Result<Machine, MachineBuildError> {
+    let frequency = Ratio::from_integer(3_500_000);
+
+    let machine = Machine::build(
+        GameSystem::Computer(ComputerSystem::ZxSpectrum),
+        rom_manager.clone(),
+    );
+    let machine = machine.insert_bus(SPECTRUM_ADDRESS_SPACE_ID, 16);
+    let machine = machine.insert_bus(SPECTRUM_IO_ADDRESS_SPACE_ID, 16);
+
+    let (machine, beeper_component_id) = machine.default_component::<Beeper>()?;
+    let (machine, processor_component_id) = machine.build_component::<I8080>(I8080Config::z80(
+        frequency,
+        SPECTRUM_ADDRESS_SPACE_ID,
+        SPECTRUM_IO_ADDRESS_SPACE_ID,
+    ))?;
+    let (machine, tape_component_id) = machine.default_component::<TapeDeck>()?;
+    let (machine, ula_component_id) = machine.build_component::<Ula>(UlaConfig {
+        processor: processor_component_id,
+        beeper: beeper_component_id,
+        tape: tape_component_id,
+        frequency,
+    })?;
+    let machine = machine.insert_media_slot::<TapeDeck>(
+        tape_component_id,
+        SPECTRUM_TAPE_MEDIA_SLOT,
+        MediaSlotMetadata {
+            requires_reset: false,
+        },
+    )?;
+
+    let (machine, _) = machine.build_labeled_component::<RomMemory>(
+        "rom",
+        RomMemoryConfig {
+            rom: SPECTRUM_48K_ROM,
+            max_word_size: 2,
+            assigned_range: 0x0000..0x4000,
+            assigned_address_space: SPECTRUM_ADDRESS_SPACE_ID,
+        },
+    )?;
+    let (machine, ram_component_id) = machine.build_labeled_component::<StandardMemory>(
+        "ram",
+        StandardMemoryConfig {
+            readable: true,
+            writable: true,
+            max_word_size: 2,
+            assigned_range: 0x4000..0x10000,
+            assigned_address_space: SPECTRUM_ADDRESS_SPACE_ID,
+            initial_contents: StandardMemoryInitialContents::Random,
+        },
+    )?;
+
+    let mut machine = machine.build()?;
+
+    if let Some(rom_id) = user_specified_roms.first() {
+        let mut bytes = Vec::new();
+
+        match rom_manager
+            .open(*rom_id, RomRequirement::Required)
+            .map(|mut file| file.read_to_end(&mut bytes))
+        {
+            // Snapshots are never valid tapes, but a .sna might look like one so it is checked by size first
+            Some(Ok(_)) if !SpectrumSnapshot::is_sna(&bytes) && Tape::parse(&bytes).is_ok() => {
+                machine.swap_media(&SPECTRUM_TAPE_MEDIA_SLOT, Some(*rom_id))?;
+            }
+            Some(Ok(_)) => match SpectrumSnapshot::parse(&bytes) {
+                Ok(snapshot) => {
+                    if let Err(err) = load_spectrum_snapshot(
+                        &mut machine,
+                        snapshot,
+                        processor_component_id,
+                        ula_component_id,
+                        ram_component_id,
+                    ) {
+                        tracing::error!("Could not load snapshot {}: {}", rom_id, err);
+                    }
+                }
+                Err(err) => tracing::error!("Could not load snapshot {}: {}", rom_id, err),
+            },
+            Some(Err(err)) => tracing::error!("Could not read snapshot {}: {}", rom_id, err),
+            None => {}
+        }
+    }
+
+    Ok(machine)
+}
+
+/// Goes through the savestate system, so a loaded snapshot is no different from one of our own states
+fn load_spectrum_snapshot(
+    machine: &mut Machine,
+    snapshot: SpectrumSnapshot,
+    processor: ComponentId,
+    ula: ComponentId,
+    ram: ComponentId,
+) -> Result<(), SavestateError> {
+    let mut state = machine.snapshot();
+
+    state
+        .components
+        .insert(processor, rmpv::ext::to_value(snapshot.processor).unwrap());
+    state.components.insert(
+        ula,
+        rmpv::ext::to_value(UlaSnapshot {
+            border: snapshot.border,
+            ..Default::default()
+        })
+        .unwrap(),
+    );
+    state.components.insert(
+        ram,
+        rmpv::ext::to_value(StandardMemorySnapshot {
+            memory: snapshot.ram,
+            readable: None,
+            writable: None,
+        })
+        .unwrap(),
+    );
+
+    machine.restore_snapshot(state)
+}
@@ -0,0 +1,316 @@
+//! The .sna and .z80 snapshot formats, turned into what our own components save so loading one is just a savestate load
+
+use crate::definitions::misc::processor::i8080::{I8080State, Z80Registers};
+use thiserror::Error;
+
+pub const RAM_SIZE: usize = 0xc000;
+const PAGE_SIZE: usize = 0x4000;
+const SNA_HEADER_SIZE: usize = 27;
+const Z80_HEADER_SIZE: usize = 30;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum SnapshotError {
+    #[error("Snapshot is truncated")]
+    Truncated,
+    #[error("Snapshot is for hardware other than the 48K, mode {0}")]
+    UnsupportedHardware(u8),
+    #[error("Snapshot has a memory page that does not exist on the 48K: {0}")]
+    InvalidPage(u8),
+}
+
+/// A machine as one of the snapshot formats describes it
+#[derive(Debug, Clone)]
+pub struct SpectrumSnapshot {
+    pub processor: I8080State,
+    pub border: u8,
+    /// Everything from 0x4000 up
+    pub ram: Vec<u8>,
+}
+
+impl SpectrumSnapshot {
+    /// Works out the format from the contents, a .sna is always the same size
+    pub fn parse(bytes: &[u8]) -> Result<Self, SnapshotError> {
+        if Self::is_sna(bytes) {
+            Self::parse_sna(bytes)
+        } else {
+            Self::parse_z80(bytes)
+        }
+    }
+
+    pub fn is_sna(bytes: &[u8]) -> bool {
+        bytes.len() == SNA_HEADER_SIZE + RAM_SIZE
+    }
+
+    pub fn parse_sna(bytes: &[u8]) -> Result<Self, SnapshotError> {
+        if bytes.len() < SNA_HEADER_SIZE + RAM_SIZE {
+            return Err(SnapshotError::Truncated);
+        }
+
+        let (header, ram) = bytes.split_at(SNA_HEADER_SIZE);
+        let word = |offset: usize| u16::from_le_bytes([header[offset], header[offset + 1]]);
+        let [f, a] = word(21).to_le_bytes();
+        let [c, b] = word(13).to_le_bytes();
+        let [e, d] = word(11).to_le_bytes();
+        let [l, h] = word(9).to_le_bytes();
+        let iff2 = header[19] & 0b100 != 0;
+        let ram = ram[..RAM_SIZE].to_vec();
+
+        // There is nowhere for the program counter in the header, it was pushed like an interrupt had happened
+        let sp = word(23);
+        let stack = (sp as usize).wrapping_sub(PAGE_SIZE);
+        let pc = match (ram.get(stack), ram.get(stack.wrapping_add(1))) {
+            (Some(low), Some(high)) => u16::from_le_bytes([*low, *high]),
+            _ => return Err(SnapshotError::Truncated),
+        };
+
+        Ok(Self {
+            processor: I8080State {
+                registers: Z80Registers {
+                    a,
+                    f,
+                    b,
+                    c,
+                    d,
+                    e,
+                    h,
+                    l,
+                    shadow: [word(7), word(5), word(3), word(1)],
+                    ix: word(17),
+                    iy: word(15),
+                    sp: sp.wrapping_add(2),
+                    pc,
+                    i: header[0],
+                    r: header[20],
+                    iff1: iff2,
+                    iff2,
+                    interrupt_mode: header[25] & 0b11,
+                },
+                ..Default::default()
+            },
+            border: header[26] & 0b111,
+            ram,
+        })
+    }
+
+    pub fn parse_z80(bytes: &[u8]) -> Result<Self, SnapshotError> {
+        let header = bytes
+            .get(..Z80_HEADER_SIZE)
+            .ok_or(SnapshotError::Truncated)?;
+        let word = |offset: usize| u16::from_le_bytes([header[offset], header[offset + 1]]);
+        // Some old writers put 255 here, which means 1
+        let flags = if header[12] == 0xff { 1 } else { header[12] };
+        let mut pc = word(6);
+        let mut ram = vec![0; RAM_SIZE];
+
+        if pc != 0 {
+            // Version 1, a single block of all the ram
+            let data = &bytes[Z80_HEADER_SIZE..];
+
+            if flags & 0b10_0000 != 0 {
+                let data = data.strip_suffix(&[0x00, 0xed, 0xed, 0x00]).unwrap_or(data);
+                decompress(data, &mut ram)?;
+            } else {
+                ram.copy_from_slice(data.get(..RAM_SIZE).ok_or(SnapshotError::Truncated)?);
+            }
+        } else {
+            // Versions 2 and 3 have an extra header and the ram in separate pages
+            let extra_length = bytes
+                .get(Z80_HEADER_SIZE..Z80_HEADER_SIZE + 2)
+                .map(|length| u16::from_le_bytes([length[0], length[1]]) as usize)
+                .ok_or(SnapshotError::Truncated)?;
+            let extra_header = bytes
+                .get(Z80_HEADER_SIZE + 2..Z80_HEADER_SIZE + 2 + extra_length)
+                .filter(|extra_header| extra_header.len() >= 4)
+                .ok_or(SnapshotError::Truncated)?;
+            pc = u16::from_le_bytes([extra_header[0], extra_header[1]]);
+
+            // 3 is a 48K with an MGT interface in version 3, which we can pretend is a plain 48K
+            let hardware = extra_header[2];
+            if !(hardware == 0 || hardware == 1 || (hardware == 3 && extra_length != 23)) {
+                return Err(SnapshotError::UnsupportedHardware(hardware));
+            }
+
+            let mut blocks = &bytes[Z80_HEADER_SIZE + 2 + extra_length..];
+            while !blocks.is_empty() {
+                let block_header = blocks.get(..3).ok_or(SnapshotError::Truncated)?;
+                let length = u16::from_le_bytes([block_header[0], block_header[1]]);
+                let page = block_header[2];
+                let stored_length = if length == 0xffff {
+                    PAGE_SIZE
+                } else {
+                    length as usize
+                };
+                let offset = match page {
+                    4 => 0x8000,
+                    5 => 0xc000,
+                    8 => 0x4000,
+                    // The ROM, which we bring ourselves
+                    0 => {
+                        blocks = blocks
+                            .get(3 + stored_length..)
+                            .ok_or(SnapshotError::Truncated)?;
+                        continue;
+                    }
+                    _ => return Err(SnapshotError::InvalidPage(page)),
+                } - PAGE_SIZE;
+                let destination = &mut ram[offset..offset + PAGE_SIZE];
+                let data = blocks
+                    .get(3..3 + stored_length)
+                    .ok_or(SnapshotError::Truncated)?;
+
+                // All ones means it was not worth compressing
+                if length == 0xffff {
+                    destination.copy_from_slice(data);
+                } else {
+                    decompress(data, destination)?;
+                }
+                blocks = &blocks[3 + stored_length..];
+            }
+        }
+
+        let [c, b] = word(2).to_le_bytes();
+        let [l, h] = word(4).to_le_bytes();
+        let [e, d] = word(13).to_le_bytes();
+
+        Ok(Self {
+            processor: I8080State {
+                registers: Z80Registers {
+                    a: header[0],
+                    f: header[1],
+                    b,
+                    c,
+                    d,
+                    e,
+                    h,
+                    l,
+                    shadow: [
+                        u16::from_be_bytes([header[21], header[22]]),
+                        word(15),
+                        word(17),
+                        word(19),
+                    ],
+                    ix: word(25),
+                    iy: word(23),
+                    sp: word(8),
+                    pc,
+                    i: header[10],
+                    // The top bit of R is stored separately
+                    r: (header[11] & 0x7f) | ((flags & 1) << 7),
+                    iff1: header[27] != 0,
+                    iff2: header[28] != 0,
+                    interrupt_mode: header[29] & 0b11,
+                },
+                ..Default::default()
+            },
+            border: (flags >> 1) & 0b111,
+            ram,
+        })
+    }
+}
+
+/// Undoes the run length encoding, where ED ED nn bb is nn copies of bb
+fn decompress(mut data: &[u8], destination: &mut [u8]) -> Result<(), SnapshotError> {
+    let mut position = 0;
+
+    while !data.is_empty() && position < destination.len() {
+        let (value, count, consumed) = match data {
+            [0xed, 0xed, count, value, ..] => (*value, *count as usize, 4),
+            [value, ..] => (*value, 1, 1),
+            [] => unreachable!(),
+        };
+
+        let end = (position + count).min(destination.len());
+        destination[position..end].fill(value);
+        position = end;
+        data = &data[consumed..];
+    }
+
+    if position < destination.len() {
+        return Err(SnapshotError::Truncated);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn sna_pops_the_program_counter() {
+        let mut sna = vec![0; SNA_HEADER_SIZE + RAM_SIZE];
+        // SP
+        sna[23..25].copy_from_slice(&0x8000u16.to_le_bytes());
+        // AF
+        sna[21..23].copy_from_slice(&[0x44, 0x12]);
+        // IFF2, IM 1 and a blue border
+        sna[19] = 0b100;
+        sna[25] = 1;
+        sna[26] = 1;
+        sna[SNA_HEADER_SIZE + 0x4000..SNA_HEADER_SIZE + 0x4002].copy_from_slice(&[0x34, 0x12]);
+
+        let snapshot = SpectrumSnapshot::parse(&sna).unwrap();
+
+        assert_eq!(snapshot.processor.registers.pc, 0x1234);
+        assert_eq!(snapshot.processor.registers.sp, 0x8002);
+        assert_eq!(snapshot.processor.registers.a, 0x12);
+        assert_eq!(snapshot.processor.registers.f, 0x44);
+        assert!(snapshot.processor.registers.iff1);
+        assert_eq!(snapshot.processor.registers.interrupt_mode, 1);
+        assert_eq!(snapshot.border, 1);
+    }
+
+    #[test]
+    fn z80_version_1_compressed() {
+        let mut z80 = vec![0; Z80_HEADER_SIZE];
+        // A, PC, R with its top bit in the flags, compressed with a red border
+        z80[0] = 0x56;
+        z80[6..8].copy_from_slice(&0x8000u16.to_le_bytes());
+        z80[11] = 0x01;
+        z80[12] = 0b10_0101;
+        z80.extend([0xed, 0xed, 0x10, 0xaa, 0x01, 0x02]);
+        z80.extend([0xed, 0xed, 0xff, 0x00].repeat(RAM_SIZE / 0xff + 1));
+        z80.extend([0x00, 0xed, 0xed, 0x00]);
+
+        let snapshot = SpectrumSnapshot::parse(&z80).unwrap();
+
+        assert_eq!(snapshot.processor.registers.a, 0x56);
+        assert_eq!(snapshot.processor.registers.pc, 0x8000);
+        assert_eq!(snapshot.processor.registers.r, 0x81);
+        assert_eq!(snapshot.border, 2);
+        assert_eq!(
+            &snapshot.ram[..0x12],
+            &[[0xaa; 0x10].as_slice(), &[1, 2]].concat()
+        );
+        assert!(snapshot.ram[0x12..].iter().all(|byte| *byte == 0));
+    }
+
+    #[test]
+    fn z80_version_2_pages() {
+        let mut z80 = vec![0; Z80_HEADER_SIZE];
+        z80.extend(23u16.to_le_bytes());
+        let mut extra_header = vec![0; 23];
+        extra_header[0..2].copy_from_slice(&0x1234u16.to_le_bytes());
+        z80.extend(extra_header);
+
+        for (page, value) in [(8, 0x11), (4, 0x22), (5, 0x33)] {
+            z80.extend(0xffffu16.to_le_bytes());
+            z80.push(page);
+            z80.extend([value; PAGE_SIZE]);
+        }
+
+        let snapshot = SpectrumSnapshot::parse(&z80).unwrap();
+
+        assert_eq!(snapshot.processor.registers.pc, 0x1234);
+        assert_eq!(snapshot.ram[0x0000], 0x11);
+        assert_eq!(snapshot.ram[0x4000], 0x22);
+        assert_eq!(snapshot.ram[0x8000], 0x33);
+
+        // A 128K snapshot
+        z80[Z80_HEADER_SIZE + 4] = 3;
+        assert_eq!(
+            SpectrumSnapshot::parse(&z80).unwrap_err(),
+            SnapshotError::UnsupportedHardware(3)
+        );
+    }
+}
@@ -0,0 +1,96 @@
+use crate::{
+    component::input::EmulatedGamepadTypeId,
+    input::{keyboard::KeyboardInput, Input},
+};
+use std::collections::{HashMap, HashSet};
+
+pub const SPECTRUM_KEYBOARD_GAMEPAD_TYPE: EmulatedGamepadTypeId =
+    EmulatedGamepadTypeId::new("ZX Spectrum Keyboard");
+
+/// The 40 keys as the ULA sees them, a half row is selected by pulling one of A8 to A15 low
+///
+/// CAPS SHIFT sits on left shift and SYMBOL SHIFT on left control
+pub(super) const KEYBOARD_MATRIX: [[KeyboardInput; 5]; 8] = [
+    [
+        KeyboardInput::ShiftLeft,
+        KeyboardInput::KeyZ,
+        KeyboardInput::KeyX,
+        KeyboardInput::KeyC,
+        KeyboardInput::KeyV,
+    ],
+    [
+        KeyboardInput::KeyA,
+        KeyboardInput::KeyS,
+        KeyboardInput::KeyD,
+        KeyboardInput::KeyF,
+        KeyboardInput::KeyG,
+    ],
+    [
+        KeyboardInput::KeyQ,
+        KeyboardInput::KeyW,
+        KeyboardInput::KeyE,
+        KeyboardInput::KeyR,
+        KeyboardInput::KeyT,
+    ],
+    [
+        KeyboardInput::Digit1,
+        KeyboardInput::Digit2,
+        KeyboardInput::Digit3,
+        KeyboardInput::Digit4,
+        KeyboardInput::Digit5,
+    ],
+    [
+        KeyboardInput::Digit0,
+        KeyboardInput::Digit9,
+        KeyboardInput::Digit8,
+        KeyboardInput::Digit7,
+        KeyboardInput::Digit6,
+    ],
+    [
+        KeyboardInput::KeyP,
+        KeyboardInput::KeyO,
+        KeyboardInput::KeyI,
+        KeyboardInput::KeyU,
+        KeyboardInput::KeyY,
+    ],
+    [
+        KeyboardInput::Enter,
+        KeyboardInput::KeyL,
+        KeyboardInput::KeyK,
+        KeyboardInput::KeyJ,
+        KeyboardInput::KeyH,
+    ],
+    [
+        KeyboardInput::Space,
+        KeyboardInput::ControlLeft,
+        KeyboardInput::KeyM,
+        KeyboardInput::KeyN,
+        KeyboardInput::KeyB,
+    ],
+];
+
+pub(super) fn default_bindings() -> HashMap<Input, Input> {
+    HashMap::from_iter(
+        present_inputs()
+            .into_iter()
+            .map(|input| (input, input))
+            .chain([
+                (
+                    Input::Keyboard(KeyboardInput::ShiftRight),
+                    Input::Keyboard(KeyboardInput::ShiftLeft),
+                ),
+                (
+                    Input::Keyboard(KeyboardInput::ControlRight),
+                    Input::Keyboard(KeyboardInput::ControlLeft),
+                ),
+            ]),
+    )
+}
+
+pub(super) fn present_inputs() -> HashSet<Input> {
+    KEYBOARD_MATRIX
+        .into_iter()
+        .flatten()
+        .map(Input::Keyboard)
+        .collect()
+}
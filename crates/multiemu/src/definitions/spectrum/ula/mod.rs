@@ -0,0 +1,434 @@
+use super::{beeper::Beeper, SPECTRUM_ADDRESS_SPACE_ID, SPECTRUM_IO_ADDRESS_SPACE_ID};
+use crate::{
+    component::{
+        display::DisplayComponent,
+        input::{EmulatedGamepadMetadata, InputComponent},
+        memory::MemoryComponent,
+        schedulable::{RunContext, SchedulableComponent},
+        Component, ComponentId, FromConfig, ResetKind, SnapshotError,
+    },
+    definitions::misc::{processor::i8080::INTERRUPT_LINE, tape::TapeDeck},
+    input::{manager::InputManager, EmulatedGamepadId, Input},
+    machine::{signal::SignalDriver, ComponentBuilder, MachineBuildError},
+    memory::{
+        AddressSpaceId, MemoryTranslationTable, PreviewMemoryRecord, ReadMemoryRecord,
+        WriteMemoryRecord,
+    },
+    runtime::rendering_backend::{
+        DisplayComponentFramebuffer, DisplayComponentInitializationData, RenderSurface,
+    },
+};
+use input::{default_bindings, present_inputs, KEYBOARD_MATRIX, SPECTRUM_KEYBOARD_GAMEPAD_TYPE};
+use nalgebra::DMatrix;
+use num::rational::Ratio;
+use palette::Srgba;
+use rangemap::RangeMap;
+use serde::{Deserialize, Serialize};
+use std::sync::{
+    atomic::{AtomicU8, Ordering},
+    Arc, Mutex, OnceLock, RwLock,
+};
+
+mod input;
+
+/// T-states the ULA takes to do one step, it draws 8 pixels in 4 so this is a quarter of a line
+pub const ULA_STEP_CYCLES: u64 = 32;
+const LINE_STEPS: u32 = 224 / ULA_STEP_CYCLES as u32;
+const FRAME_LINES: u32 = 312;
+const FRAME_STEPS: u32 = LINE_STEPS * FRAME_LINES;
+
+pub const SCREEN_WIDTH: usize = 256 + 2 * BORDER_WIDTH;
+pub const SCREEN_HEIGHT: usize = 192 + 2 * BORDER_HEIGHT;
+const BORDER_WIDTH: usize = 32;
+const BORDER_HEIGHT: usize = 24;
+/// Lines after the interrupt the picture starts on
+const FIRST_PICTURE_LINE: u32 = 64;
+const FIRST_VISIBLE_LINE: u32 = FIRST_PICTURE_LINE - BORDER_HEIGHT as u32;
+
+const BITMAP_ADDRESS: u16 = 0x4000;
+const ATTRIBUTE_ADDRESS: u16 = 0x5800;
+/// FLASH swaps ink and paper this many frames
+const FLASH_FRAMES: u32 = 16;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UlaSnapshot {
+    pub border: u8,
+    /// Steps since the last interrupt
+    pub step: u32,
+    pub frame: u32,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct UlaTiming {
+    /// Steps since the last interrupt
+    step: u32,
+    frame: u32,
+}
+
+#[derive(Debug)]
+pub struct UlaConfig {
+    pub processor: ComponentId,
+    pub beeper: ComponentId,
+    /// Read back on bit 6 of every even port
+    pub tape: ComponentId,
+    /// What the processor runs at, the ULA steps at a fraction of it
+    pub frequency: Ratio<u64>,
+}
+
+/// Sinclair's custom chip, which draws the screen, interrupts the processor every frame and sits on every even port
+#[derive(Debug)]
+pub struct Ula {
+    /// The interrupt line of the processor
+    interrupt: SignalDriver,
+    beeper: Arc<Beeper>,
+    tape: Arc<TapeDeck>,
+    timing: Mutex<UlaTiming>,
+    border: AtomicU8,
+    /// Drawn into a line at a time, handed to the frontend once a frame is done
+    screen: Mutex<DMatrix<Srgba<u8>>>,
+    /// Replaced whenever the frontend hands us new display data
+    surface: RwLock<Option<Box<dyn RenderSurface>>>,
+    memory_translation_table: OnceLock<Arc<MemoryTranslationTable>>,
+    input_manager: OnceLock<(Arc<InputManager>, EmulatedGamepadId)>,
+}
+
+impl Ula {
+    fn draw_line(&self, line: u32, frame: u32) {
+        let Some(y) = line
+            .checked_sub(FIRST_VISIBLE_LINE)
+            .map(|y| y as usize)
+            .filter(|y| *y < SCREEN_HEIGHT)
+        else {
+            return;
+        };
+
+        let mut screen = self.screen.lock().unwrap();
+        let border = color(self.border.load(Ordering::Relaxed), false);
+
+        for x in 0..SCREEN_WIDTH {
+            screen[(x, y)] = border;
+        }
+
+        let Some(picture_line) = line.checked_sub(FIRST_PICTURE_LINE).filter(|y| *y < 192) else {
+            return;
+        };
+
+        let memory_translation_table = self.memory_translation_table.get().unwrap();
+        let picture_line = picture_line as u16;
+        // The thirds, character rows and pixel rows are interleaved in a way that made the hardware cheap
+        let bitmap_address = BITMAP_ADDRESS
+            | ((picture_line & 0xc0) << 5)
+            | ((picture_line & 0x07) << 8)
+            | ((picture_line & 0x38) << 2);
+        let attribute_address = ATTRIBUTE_ADDRESS + (picture_line / 8) * 32;
+        let mut bitmap = [0; 32];
+        let mut attributes = [0; 32];
+
+        for (address, buffer) in [
+            (bitmap_address, &mut bitmap),
+            (attribute_address, &mut attributes),
+        ] {
+            let _ = memory_translation_table.preview_block(
+                address as usize,
+                buffer,
+                SPECTRUM_ADDRESS_SPACE_ID,
+            );
+        }
+
+        let flash_inverted = (frame / FLASH_FRAMES) % 2 == 1;
+
+        for (column, (pixels, attribute)) in bitmap.into_iter().zip(attributes).enumerate() {
+            let bright = attribute & 0b0100_0000 != 0;
+            let mut ink = color(attribute & 0b111, bright);
+            let mut paper = color((attribute >> 3) & 0b111, bright);
+
+            if attribute & 0b1000_0000 != 0 && flash_inverted {
+                std::mem::swap(&mut ink, &mut paper);
+            }
+
+            for bit in 0..8 {
+                screen[(BORDER_WIDTH + column * 8 + bit, y)] = if pixels & (0x80 >> bit) != 0 {
+                    ink
+                } else {
+                    paper
+                };
+            }
+        }
+    }
+
+    fn commit_display(&self) {
+        // Nothing to show it on yet
+        if let Some(surface) = self.surface.read().unwrap().as_ref() {
+            surface.upload(&self.screen.lock().unwrap());
+            surface.commit();
+        }
+    }
+
+    /// Keys in the half rows whose address line is low, active low like the hardware
+    ///
+    /// Only counts as the game polling its inputs if `poll` is set, so previewing the port does not hide lag frames
+    fn read_keyboard(&self, port: u16, poll: bool) -> u8 {
+        let mut value = 0b1111_1111;
+
+        let Some((input_manager, gamepad_id)) = self.input_manager.get() else {
+            return value;
+        };
+
+        for (row, keys) in KEYBOARD_MATRIX.iter().enumerate() {
+            if port & (0x100 << row) != 0 {
+                continue;
+            }
+
+            for (bit, key) in keys.iter().enumerate() {
+                let input = Input::Keyboard(*key);
+                let state = if poll {
+                    input_manager.get_input(*gamepad_id, input)
+                } else {
+                    input_manager.peek_input(*gamepad_id, input)
+                };
+
+                if state.as_digital() {
+                    value &= !(1 << bit);
+                }
+            }
+        }
+
+        value
+    }
+
+    fn read_port(&self, port: u16, poll: bool) -> u8 {
+        if port & 1 == 0 {
+            let ear = if self.tape.level() { 0b0100_0000 } else { 0 };
+
+            (self.read_keyboard(port, poll) & 0b1011_1111) | ear
+        } else {
+            0xff
+        }
+    }
+}
+
+impl Component for Ula {
+    fn reset(&self, _kind: ResetKind) {
+        *self.timing.lock().unwrap() = UlaTiming::default();
+        self.border.store(0, Ordering::Relaxed);
+        self.interrupt.release();
+    }
+
+    fn save_snapshot(&self) -> rmpv::Value {
+        let timing = *self.timing.lock().unwrap();
+
+        rmpv::ext::to_value(UlaSnapshot {
+            border: self.border.load(Ordering::Relaxed),
+            step: timing.step,
+            frame: timing.frame,
+        })
+        .unwrap()
+    }
+
+    fn load_snapshot(&self, state: rmpv::Value) -> Result<(), SnapshotError> {
+        let snapshot: UlaSnapshot = rmpv::ext::from_value(state)?;
+
+        self.border.store(snapshot.border, Ordering::Relaxed);
+        // Raised on the first step of a frame and lowered on the next
+        self.interrupt.set(snapshot.step == 1);
+        *self.timing.lock().unwrap() = UlaTiming {
+            step: snapshot.step,
+            frame: snapshot.frame,
+        };
+
+        Ok(())
+    }
+
+    fn shutdown(&self) {
+        *self.surface.write().unwrap() = None;
+    }
+
+    fn set_memory_translation_table(&self, memory_translation_table: Arc<MemoryTranslationTable>) {
+        self.memory_translation_table
+            .set(memory_translation_table)
+            .unwrap();
+    }
+}
+
+impl FromConfig for Ula {
+    type Config = UlaConfig;
+
+    fn from_config(
+        component_builder: &mut ComponentBuilder<Self>,
+        config: Self::Config,
+    ) -> Result<(), MachineBuildError> {
+        let interrupt = component_builder
+            .machine()
+            .signal_driver(config.processor, INTERRUPT_LINE)?;
+        let beeper = component_builder
+            .machine()
+            .get_component::<Beeper>(config.beeper)?;
+        let tape = component_builder
+            .machine()
+            .get_component::<TapeDeck>(config.tape)?;
+
+        component_builder
+            .set_component(Self {
+                interrupt,
+                beeper,
+                tape,
+                timing: Mutex::default(),
+                border: AtomicU8::new(0),
+                screen: Mutex::new(DMatrix::from_element(
+                    SCREEN_WIDTH,
+                    SCREEN_HEIGHT,
+                    Srgba::new(0, 0, 0, 255),
+                )),
+                surface: RwLock::default(),
+                memory_translation_table: OnceLock::default(),
+                input_manager: OnceLock::default(),
+            })
+            // Ahead of the processor so it sees the interrupt on the step it is raised
+            .set_schedulable(config.frequency / ULA_STEP_CYCLES, [], [config.processor])
+            .set_display()
+            // Only A0 is decoded, so it answers on all of them and lets odd ones float
+            .set_memory([(SPECTRUM_IO_ADDRESS_SPACE_ID, 0x0000..0x10000)])
+            .set_input(
+                [(
+                    SPECTRUM_KEYBOARD_GAMEPAD_TYPE,
+                    EmulatedGamepadMetadata {
+                        present_inputs: present_inputs(),
+                        default_bindings: default_bindings(),
+                    },
+                )],
+                [SPECTRUM_KEYBOARD_GAMEPAD_TYPE],
+            )
+            .set_label("ula");
+
+        Ok(())
+    }
+}
+
+impl SchedulableComponent for Ula {
+    fn run(&self, period: u64, _context: RunContext) {
+        for _ in 0..period {
+            let (step, frame) = {
+                let mut timing = self.timing.lock().unwrap();
+                let current = (timing.step, timing.frame);
+
+                timing.step += 1;
+                if timing.step == FRAME_STEPS {
+                    timing.step = 0;
+                    timing.frame = timing.frame.wrapping_add(1);
+                }
+
+                current
+            };
+
+            // Held for 32 T-states, long enough for any instruction to finish
+            match step {
+                0 => self.interrupt.assert(),
+                1 => self.interrupt.release(),
+                _ => {}
+            }
+
+            if step % LINE_STEPS == 0 {
+                self.draw_line(step / LINE_STEPS, frame);
+            }
+
+            if step == FRAME_STEPS - 1 {
+                self.commit_display();
+            }
+        }
+    }
+}
+
+impl MemoryComponent for Ula {
+    fn read_memory(
+        &self,
+        address: usize,
+        buffer: &mut [u8],
+        _address_space: AddressSpaceId,
+        _errors: &mut RangeMap<usize, ReadMemoryRecord>,
+    ) {
+        for (offset, byte) in buffer.iter_mut().enumerate() {
+            *byte = self.read_port((address + offset) as u16, true);
+        }
+    }
+
+    fn preview_memory(
+        &self,
+        address: usize,
+        buffer: &mut [u8],
+        _address_space: AddressSpaceId,
+        _errors: &mut RangeMap<usize, PreviewMemoryRecord>,
+    ) {
+        for (offset, byte) in buffer.iter_mut().enumerate() {
+            *byte = self.read_port((address + offset) as u16, false);
+        }
+    }
+
+    fn write_memory(
+        &self,
+        address: usize,
+        buffer: &[u8],
+        _address_space: AddressSpaceId,
+        _errors: &mut RangeMap<usize, WriteMemoryRecord>,
+    ) {
+        for (offset, byte) in buffer.iter().enumerate() {
+            if (address + offset) & 1 == 0 {
+                self.border.store(byte & 0b111, Ordering::Relaxed);
+                self.beeper.set_level(byte & 0b1_0000 != 0);
+            }
+        }
+    }
+}
+
+impl InputComponent for Ula {
+    fn set_input_manager(
+        &self,
+        input_manager: Arc<InputManager>,
+        gamepad_ports: &[EmulatedGamepadId],
+    ) {
+        self.input_manager
+            .set((
+                input_manager,
+                gamepad_ports
+                    .first()
+                    .copied()
+                    .expect("Input manager did not allocate our gamepad"),
+            ))
+            .expect("Input manager set multiple times");
+    }
+}
+
+impl DisplayComponent for Ula {
+    fn set_display_data(&self, initialization_data: DisplayComponentInitializationData) {
+        *self.surface.write().unwrap() =
+            Some(initialization_data.create_surface(SCREEN_WIDTH, SCREEN_HEIGHT));
+        // Keep showing the same thing if the frontend had to start over, like after losing the gpu
+        self.commit_display();
+    }
+
+    fn get_framebuffer(&self) -> DisplayComponentFramebuffer {
+        self.surface
+            .read()
+            .unwrap()
+            .as_ref()
+            .expect("Display data not set")
+            .framebuffer()
+    }
+
+    fn redraw(&self) {
+        let frame = self.timing.lock().unwrap().frame;
+
+        for line in 0..FRAME_LINES {
+            self.draw_line(line, frame);
+        }
+
+        self.commit_display();
+    }
+}
+
+/// The eight colours, each at normal or bright intensity
+fn color(index: u8, bright: bool) -> Srgba<u8> {
+    let intensity = if bright { 0xff } else { 0xd7 };
+    let channel = |bit: u8| if index & bit != 0 { intensity } else { 0 };
+
+    Srgba::new(channel(0b010), channel(0b100), channel(0b001), 255)
+}
@@ -0,0 +1,36 @@
+use crate::rom::system::GameSystem;
+use indexmap::IndexMap;
+use std::{collections::HashMap, path::PathBuf};
+
+#[derive(Clone, Debug)]
+pub enum DatabaseImportStatus {
+    Queued,
+    Importing,
+    /// How many entries were imported for each system
+    Imported(HashMap<GameSystem, usize>),
+    Failed(String),
+}
+
+/// Shared with whatever is doing the import so the menu can show how far along it is
+#[derive(Debug, Default)]
+pub struct DatabaseImportProgress {
+    pub files: IndexMap<PathBuf, DatabaseImportStatus>,
+}
+
+impl DatabaseImportProgress {
+    pub fn finished(&self) -> usize {
+        self.files
+            .values()
+            .filter(|status| {
+                matches!(
+                    status,
+                    DatabaseImportStatus::Imported(_) | DatabaseImportStatus::Failed(_)
+                )
+            })
+            .count()
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.finished() != self.files.len()
+    }
+}
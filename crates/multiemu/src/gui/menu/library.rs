@@ -0,0 +1,178 @@
+use super::{MenuState, UiOutput};
+use crate::rom::{
+    id::RomId,
+    info::RomInfo,
+    manager::RomManager,
+    notes::{CompatibilityRating, RomNotes},
+    system::GameSystem,
+};
+use egui::{ComboBox, Grid, ScrollArea, TextEdit, Ui};
+use std::{error::Error, fs::read_dir, path::Path};
+use strum::IntoEnumIterator;
+
+/// A ROM in the roms directory, with whatever the database knows about it
+#[derive(Debug, Clone)]
+pub struct LibraryEntry {
+    pub rom_id: RomId,
+    pub name: Option<String>,
+    pub system: Option<GameSystem>,
+    pub notes: RomNotes,
+}
+
+/// Every ROM in `roms_directory`, ordered by system and name
+pub fn scan_library(
+    rom_manager: &RomManager,
+    roms_directory: &Path,
+) -> Result<Vec<LibraryEntry>, Box<dyn Error>> {
+    let transaction = rom_manager.rom_information.r_transaction()?;
+    let mut library = Vec::new();
+
+    for entry in read_dir(roms_directory)? {
+        let path = entry?.path();
+
+        // Compressed entries carry an extension, so go by the stem
+        let Some(rom_id) = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .and_then(|stem| stem.parse::<RomId>().ok())
+        else {
+            continue;
+        };
+
+        let rom_info = transaction.get().primary::<RomInfo>(rom_id)?;
+        let notes = transaction
+            .get()
+            .primary::<RomNotes>(rom_id)?
+            .unwrap_or_else(|| RomNotes::new(rom_id));
+
+        library.push(LibraryEntry {
+            rom_id,
+            name: rom_info.as_ref().and_then(|rom_info| rom_info.name.clone()),
+            system: rom_info.map(|rom_info| rom_info.system),
+            notes,
+        });
+    }
+    library.sort_by(|a, b| (a.system, &a.name).cmp(&(b.system, &b.name)));
+
+    Ok(library)
+}
+
+impl MenuState {
+    /// ROMs that were imported, where the user can note down how well they run
+    pub(super) fn library(&mut self, ui: &mut Ui) -> Option<UiOutput> {
+        let mut output = None;
+
+        ui.horizontal(|ui| {
+            if ui.button("Refresh").clicked() {
+                output = Some(UiOutput::RefreshLibrary);
+            }
+
+            if ui.button("Export Notes").clicked() {
+                output = Some(UiOutput::ExportRomNotes);
+            }
+        });
+
+        if let Some(library_status) = &self.library_status {
+            ui.label(library_status);
+        }
+
+        let Some(library) = &mut self.library else {
+            // Scanning is left to the frontend, which has the rom manager
+            return Some(UiOutput::RefreshLibrary);
+        };
+
+        if library.is_empty() {
+            ui.label("No ROMs have been imported");
+            return output;
+        }
+
+        if let Some((rom_id, rom_header)) = &self.rom_header {
+            let mut open = true;
+
+            ui.group(|ui| {
+                ui.horizontal(|ui| {
+                    ui.heading(format!("Header of {}", rom_id));
+                    open = !ui.button("Close").clicked();
+                });
+
+                match rom_header {
+                    Ok(fields) => {
+                        Grid::new("rom_header").striped(true).show(ui, |ui| {
+                            for (name, value) in fields {
+                                ui.label(*name);
+                                ui.label(value);
+                                ui.end_row();
+                            }
+                        });
+                    }
+                    Err(err) => {
+                        ui.colored_label(egui::Color32::RED, err);
+                    }
+                }
+            });
+
+            if !open {
+                self.rom_header = None;
+            }
+        }
+
+        ScrollArea::vertical().show(ui, |ui| {
+            Grid::new("library").striped(true).show(ui, |ui| {
+                for entry in library.iter_mut() {
+                    ui.label(
+                        entry
+                            .name
+                            .clone()
+                            .unwrap_or_else(|| entry.rom_id.to_string()),
+                    );
+                    ui.label(
+                        entry
+                            .system
+                            .map(|system| system.to_string())
+                            .unwrap_or_default(),
+                    );
+
+                    let previous_rating = entry.notes.rating;
+                    ComboBox::from_id_salt(("rating", entry.rom_id))
+                        .selected_text(
+                            entry
+                                .notes
+                                .rating
+                                .map(|rating| rating.to_string())
+                                .unwrap_or("Unrated".to_string()),
+                        )
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut entry.notes.rating, None, "Unrated");
+
+                            for rating in CompatibilityRating::iter() {
+                                ui.selectable_value(
+                                    &mut entry.notes.rating,
+                                    Some(rating),
+                                    rating.to_string(),
+                                );
+                            }
+                        });
+
+                    let notes_response =
+                        ui.add(TextEdit::singleline(&mut entry.notes.notes).hint_text("Notes"));
+
+                    // Notes are only saved once done with, instead of on every keystroke
+                    if entry.notes.rating != previous_rating || notes_response.lost_focus() {
+                        output = Some(UiOutput::SetRomNotes {
+                            rom_notes: entry.notes.clone(),
+                        });
+                    }
+
+                    if ui.button("Header").clicked() {
+                        output = Some(UiOutput::InspectRom {
+                            rom_id: entry.rom_id,
+                        });
+                    }
+                    ui.end_row();
+                }
+            });
+        });
+
+        output
+    }
+}
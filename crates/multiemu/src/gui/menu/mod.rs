@@ -1,13 +1,196 @@
-use crate::config::{GraphicsSettings, GLOBAL_CONFIG};
-use egui::{CentralPanel, ComboBox, Context, ScrollArea, SidePanel};
+use crate::{
+    component::{
+        input::EmulatedGamepadTypeId, introspectable::DebugValue, media::MediaSlotId, ComponentId,
+        ResetKind,
+    },
+    config::{GraphicsSettings, StoragePath, GLOBAL_CONFIG},
+    definitions::misc::tape::{TapeControl, TapeStatus},
+    input::{
+        profile::{InputProfile, INPUT_PROFILE_DIRECTORY},
+        EmulatedGamepadId,
+    },
+    machine::serialization::SAVESTATE_SLOTS,
+    memory::{
+        parse_address_range, AddressSpaceId, MemoryAccess, PageAccesses, UnmappedAccess, PAGE_SIZE,
+    },
+    profile::{create_profile, profiles, select_profile, selected_profile, ACTIVE_PROFILE},
+    rom::{
+        compatibility::{CompatibilityReport, CompatibilityStatus},
+        id::RomId,
+        notes::RomNotes,
+        statistics::UsageStatistics,
+    },
+    runtime::{
+        frame_compare::FrameComparisonSummary,
+        frame_presentation::{MotionSmoothing, RefreshRateSync},
+        power_profile::PowerProfile,
+        video_filter::VideoFilter,
+        video_preset::{ScalingMode, VideoPreset, VIDEO_PRESET_DIRECTORY},
+    },
+    scheduler::{Pacing, SchedulerStats, DEFAULT_WATCHDOG_BUDGET},
+    tas::TasSession,
+    update::{UpdateStatus, CURRENT_VERSION},
+};
+use database_import::{DatabaseImportProgress, DatabaseImportStatus};
+use egui::{
+    CentralPanel, ComboBox, Context, DragValue, Grid, ProgressBar, ScrollArea, SidePanel, Slider,
+};
 use file_browser::{FileBrowserSortingMethod, FileBrowserState};
+use itertools::Itertools;
+use library::LibraryEntry;
+use std::collections::HashMap;
 use std::fmt::Display;
-use std::path::PathBuf;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use strum::{EnumIter, IntoEnumIterator};
+pub mod database_import;
 mod file_browser;
+pub mod library;
+mod statistics;
+mod tas_editor;
+
+const MEBIBYTE: usize = 1024 * 1024;
 
 pub enum UiOutput {
-    OpenGame { path: PathBuf },
+    OpenGame {
+        path: PathBuf,
+    },
+    /// Run a ROM in a window of its own, next to whatever is already running
+    OpenInstance {
+        path: PathBuf,
+    },
+    /// Swap the media in a slot of the running machine, ejecting it if there is no path
+    ChangeMedia {
+        slot: MediaSlotId,
+        path: Option<PathBuf>,
+    },
+    /// Start or stop keeping the memory accesses of the running machine, see [MenuState::access_trace]
+    SetAccessTracing(bool),
+    /// Start or stop counting the memory accesses of the running machine per page, see [MenuState::access_heatmap]
+    SetAccessHeatmap(bool),
+    ClearAccessHeatmap,
+    /// Diff the displays of the running machine against the reference frames in a directory from now on, see
+    /// [MenuState::frame_comparison]
+    StartFrameComparison {
+        references: PathBuf,
+    },
+    StopFrameComparison,
+    /// Reset the running machine, also used to recover from a crash
+    ResetMachine(ResetKind),
+    /// Stop the running machine and go back to having nothing loaded
+    QuitGame,
+    /// Save a snapshot of the running machine for later inspection
+    DumpMachineState,
+    /// Write the running machine into a numbered savestate slot
+    SaveState {
+        slot: u8,
+    },
+    /// Put the running machine back how a numbered savestate slot has it
+    LoadState {
+        slot: u8,
+    },
+    /// Write a range of an address space of the running machine to the snapshot directory, without side effects
+    DumpMemory {
+        address_space: AddressSpaceId,
+        range: Range<usize>,
+    },
+    /// Write a file into an address space of the running machine
+    LoadMemory {
+        address_space: AddressSpaceId,
+        address: usize,
+        path: PathBuf,
+    },
+    /// Import rom databases, reporting back through [MenuState::database_import_progress]
+    ImportDatabases {
+        paths: Vec<PathBuf>,
+    },
+    /// Ask GitHub for the latest release, reporting back through [MenuState::update_status]
+    CheckForUpdates,
+    /// Write a backup archive, reporting back through [MenuState::backup_status]
+    ExportBackup {
+        passphrase: Option<String>,
+    },
+    /// Restore a backup archive, reporting back through [MenuState::backup_status]
+    ImportBackup {
+        path: PathBuf,
+        passphrase: Option<String>,
+    },
+    /// Start editing a new movie on the running machine, see [MenuState::tas_session]
+    StartTas,
+    StopTas,
+    /// Run the frame at the cursor of the movie being edited
+    TasAdvance,
+    /// Put the running machine at the start of a frame of the movie being edited
+    TasSeek {
+        frame: usize,
+    },
+    /// Write the movie being edited to the movies directory
+    ExportMovie,
+    /// Start editing a movie from a file on the running machine
+    ImportMovie {
+        path: PathBuf,
+    },
+    /// Make a preset file the look for every game without one of its own
+    ImportVideoPreset {
+        path: PathBuf,
+    },
+    /// Give the running game its own copy of the current look
+    AttachVideoPreset {
+        name: String,
+    },
+    DetachVideoPreset,
+    /// Scan the roms directory into [MenuState::library]
+    RefreshLibrary,
+    /// Store what the user noted about a ROM
+    SetRomNotes {
+        rom_notes: RomNotes,
+    },
+    /// Write every ROM with notes to the notes directory
+    ExportRomNotes,
+    /// Read the header of a ROM in the library into [MenuState::rom_header]
+    InspectRom {
+        rom_id: RomId,
+    },
+    /// Work the tape deck of the running machine
+    TapeControl(TapeControl),
+    /// Work out [MenuState::usage_statistics] from the recorded play sessions
+    RefreshStatistics,
+    /// Write the usage statistics to the statistics directory as json
+    ExportStatistics,
+    /// Forget every recorded play session
+    ClearStatistics,
+}
+
+impl UiOutput {
+    /// What counts as using a feature for the usage statistics
+    pub fn feature(&self) -> Option<&'static str> {
+        match self {
+            UiOutput::ChangeMedia { .. } => Some("Media Swapping"),
+            UiOutput::OpenInstance { .. } => Some("Multiple Machines"),
+            UiOutput::SetAccessTracing(true) => Some("Access Tracing"),
+            UiOutput::SetAccessHeatmap(true) => Some("Access Heatmap"),
+            UiOutput::StartFrameComparison { .. } => Some("Frame Comparison"),
+            UiOutput::DumpMachineState => Some("State Dump"),
+            UiOutput::SaveState { .. } | UiOutput::LoadState { .. } => Some("Savestates"),
+            UiOutput::DumpMemory { .. } | UiOutput::LoadMemory { .. } => Some("Memory Dump"),
+            UiOutput::StartTas | UiOutput::ImportMovie { .. } => Some("TAS Editor"),
+            UiOutput::ImportVideoPreset { .. } | UiOutput::AttachVideoPreset { .. } => {
+                Some("Video Presets")
+            }
+            UiOutput::TapeControl(_) => Some("Tape Deck"),
+            _ => None,
+        }
+    }
+}
+
+/// How the last backup export or import went
+#[derive(Clone, Debug)]
+pub enum BackupStatus {
+    Running,
+    Finished(String),
+    Failed(String),
 }
 
 #[derive(PartialEq, Eq, Clone, Copy, Debug, Default, EnumIter)]
@@ -17,6 +200,9 @@ pub enum MenuItem {
     FileBrowser,
     Options,
     Database,
+    Library,
+    TasEditor,
+    Statistics,
 }
 
 impl Display for MenuItem {
@@ -29,20 +215,152 @@ impl Display for MenuItem {
                 MenuItem::FileBrowser => "File Browser",
                 MenuItem::Options => "Options",
                 MenuItem::Database => "Database",
+                MenuItem::Library => "Library",
+                MenuItem::TasEditor => "TAS Editor",
+                MenuItem::Statistics => "Statistics",
             }
         )
     }
 }
 
+/// What picking something in the file browser does
+#[derive(Default, Clone, Debug)]
+enum FileBrowserPurpose {
+    #[default]
+    OpenGame,
+    OpenInstance,
+    ChangeMedia(MediaSlotId),
+    ImportDatabase,
+    ImportBackup,
+    ImportMovie,
+    ImportVideoPreset,
+    ImportInputProfile,
+    LoadMemory {
+        address_space: AddressSpaceId,
+        address: usize,
+    },
+    StoragePath(StoragePath),
+}
+
 #[derive(Default, Clone, Debug)]
 pub struct MenuState {
     open_menu_item: MenuItem,
     file_browser_state: FileBrowserState,
+    file_browser_purpose: FileBrowserPurpose,
+    /// Databases picked but not yet imported
+    database_files: Vec<PathBuf>,
+    /// Storage paths as they are being edited, before they are applied
+    storage_path_drafts: HashMap<StoragePath, String>,
+    /// If changing a storage path moves the data at the old one
+    migrate_storage_data: bool,
+    /// Why the last storage path change failed
+    storage_path_error: Option<String>,
+    /// Profile that will be used on the next startup
+    next_profile: Option<String>,
+    new_profile_name: String,
+    /// Why the last profile action failed
+    profile_error: Option<String>,
+    /// How the last input profile export or import went
+    input_profile_status: Option<String>,
+    video_preset_name: String,
+    /// How the last video preset action went
+    pub video_preset_status: Option<String>,
+    pub database_import_progress: Arc<Mutex<DatabaseImportProgress>>,
+    /// Read from disk the first time the database view is shown, and again on refresh
+    compatibility_reports: Option<Vec<CompatibilityReport>>,
+    pub backup_status: Arc<Mutex<Option<BackupStatus>>>,
+    /// Encrypts exported backups and decrypts imported ones, if not empty
+    backup_passphrase: String,
+    /// Filled in by the update check running in the background
+    pub update_status: Arc<Mutex<Option<UpdateStatus>>>,
+    /// Imported ROMs, scanned by the frontend when the library view is first shown
+    pub library: Option<Vec<LibraryEntry>>,
+    /// How the last library action went
+    pub library_status: Option<String>,
+    /// The header of the ROM last inspected in the library view, as name and value
+    pub rom_header: Option<(RomId, Result<Vec<(&'static str, String)>, String>)>,
+    /// Read from the rom database by the frontend when the statistics view is first shown
+    pub usage_statistics: Option<UsageStatistics>,
+    /// How the last statistics action went
+    pub statistics_status: Option<String>,
+    /// Movie being edited on the running machine
+    pub tas_session: Option<TasSession>,
+    /// Why the last movie action failed
+    pub tas_error: Option<String>,
+    new_branch_name: String,
+    /// Media slots the running machine exposes
+    pub media_slots: Vec<MediaSlotId>,
+    /// Why the last media change failed
+    pub media_error: Option<String>,
+    /// Why the running machine stopped, if it crashed
+    pub machine_fault: Option<String>,
+    /// Why the last machine could not be put together
+    pub machine_build_error: Option<String>,
+    /// What the renderer last changed on its own, like a video filter too slow to keep
+    pub renderer_notice: Option<String>,
+    /// Background tasks that panicked since the user last dismissed them
+    pub task_panics: Vec<String>,
+    /// If a machine is loaded, the menu only pauses it
+    pub machine_running: bool,
+    /// How many machines are running in windows of their own
+    pub machine_instances: usize,
+    /// If the running machine has an audio device to play on
+    pub audio_output: bool,
+    /// Savestate slot the hotkeys and menu use
+    pub state_slot: u8,
+    /// How the last savestate save or load went
+    pub savestate_status: Option<String>,
+    /// The component the watchdog last caught running for too long
+    pub runaway_component: Option<String>,
+    /// Frames where the running machine never polled input
+    pub lag_frames: Option<u64>,
+    /// Ports of the running machine and what is plugged into them
+    pub emulated_gamepads: Vec<(EmulatedGamepadId, EmulatedGamepadTypeId)>,
+    /// If the running machine should time its components
+    pub profiling: bool,
+    /// Where the time of the last run before the menu opened went, if profiling
+    pub scheduler_stats: Option<SchedulerStats>,
+    /// The most hit addresses nothing is mapped to on the running machine, with the name of the component that hit them
+    pub unmapped_accesses: Vec<(UnmappedAccess, Option<&'static str>)>,
+    /// What every component of the running machine that can be looked inside of had in it, with its type name
+    pub component_debug_state: Vec<(ComponentId, &'static str, Vec<(String, DebugValue)>)>,
+    /// If the running machine is keeping its memory accesses
+    pub access_tracing: bool,
+    memory_dump_address_space: AddressSpaceId,
+    /// Like 0x0000..0x0800, loading goes to the start of it
+    memory_dump_range: String,
+    /// How the last memory dump or load went
+    pub memory_dump_status: Option<String>,
+    /// The latest memory accesses of the running machine, newest first, with the name of the component that made them
+    pub access_trace: Vec<(MemoryAccess, Option<&'static str>)>,
+    /// If the running machine is counting its memory accesses per page
+    pub collecting_access_heatmap: bool,
+    /// Every page the running machine touched while counting
+    pub access_heatmap: Vec<PageAccesses>,
+    /// Directory of reference frames as it is being typed in
+    frame_comparison_directory: String,
+    /// How diffing the running machine against reference frames has gone, if it is being done
+    pub frame_comparison: Option<FrameComparisonSummary>,
+    /// Why the frame comparison could not be started
+    pub frame_comparison_status: Option<String>,
+    /// The tape deck of the running machine, if it has one with a tape in it
+    pub tape: Option<TapeStatus>,
     pub egui_context: egui::Context,
     pub active: bool,
 }
 
 impl MenuState {
+    fn pick_storage_path(&mut self, storage_path: StoragePath, path: PathBuf) {
+        self.storage_path_drafts
+            .insert(storage_path, path.display().to_string());
+        self.file_browser_purpose = FileBrowserPurpose::default();
+        self.open_menu_item = MenuItem::Options;
+    }
+
+    fn backup_passphrase(&self) -> Option<String> {
+        (!self.backup_passphrase.is_empty()).then(|| self.backup_passphrase.clone())
+    }
+
     /// TODO: barely does anything
     pub fn run_menu(&mut self, ctx: &Context) -> Option<UiOutput> {
         let mut output = None;
@@ -65,9 +383,432 @@ impl MenuState {
             ui.with_layout(
                 egui::Layout::top_down_justified(egui::Align::LEFT),
                 |ui| match self.open_menu_item {
-                    MenuItem::Main => if ui.button("Resume").clicked() {},
+                    MenuItem::Main => {
+                        if let Some(machine_build_error) = &self.machine_build_error {
+                            ui.colored_label(
+                                egui::Color32::RED,
+                                format!("Could not start the machine: {}", machine_build_error),
+                            );
+                        }
+
+                        if let Some(renderer_notice) = &self.renderer_notice {
+                            ui.colored_label(egui::Color32::YELLOW, renderer_notice);
+                        }
+
+                        if !self.task_panics.is_empty() {
+                            for task_panic in &self.task_panics {
+                                ui.colored_label(egui::Color32::RED, task_panic);
+                            }
+
+                            if ui.button("Dismiss").clicked() {
+                                self.task_panics.clear();
+                            }
+                        }
+
+                        if let Some(machine_fault) = &self.machine_fault {
+                            ui.colored_label(
+                                egui::Color32::RED,
+                                format!("The emulated machine crashed: {}", machine_fault),
+                            );
+
+                            ui.horizontal(|ui| {
+                                if ui.button("Reset").clicked() {
+                                    output = Some(UiOutput::ResetMachine(ResetKind::Hard));
+                                }
+
+                                if ui.button("Dump State").clicked() {
+                                    output = Some(UiOutput::DumpMachineState);
+                                }
+                            });
+                        }
+
+                        if let Some(runaway_component) = &self.runaway_component {
+                            ui.colored_label(egui::Color32::YELLOW, runaway_component);
+
+                            if ui.button("Dismiss").clicked() {
+                                self.runaway_component = None;
+                            }
+                        }
+
+                        if let Some(UpdateStatus::Available(release)) =
+                            self.update_status.lock().unwrap().as_ref()
+                        {
+                            ui.colored_label(
+                                egui::Color32::LIGHT_GREEN,
+                                format!(
+                                    "{} is out, this is version {}",
+                                    release.name.as_deref().unwrap_or(&release.tag_name),
+                                    CURRENT_VERSION
+                                ),
+                            );
+                            ui.label(&release.html_url);
+
+                            ui.collapsing("Release Notes", |ui| {
+                                ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                                    ui.label(release.body.as_deref().unwrap_or("No release notes"));
+                                });
+                            });
+                        }
+
+                        if !self.machine_running {
+                            ui.label("No game is running, open one from the file browser");
+                        }
+
+                        ui.add_enabled_ui(self.machine_running, |ui| {
+                            ui.horizontal(|ui| {
+                                if ui.button("Resume").clicked() {
+                                    self.active = false;
+                                }
+
+                                if ui.button("Soft Reset").clicked() {
+                                    output = Some(UiOutput::ResetMachine(ResetKind::Soft));
+                                }
+
+                                if ui.button("Hard Reset").clicked() {
+                                    output = Some(UiOutput::ResetMachine(ResetKind::Hard));
+                                }
+
+                                if ui.button("Quit Game").clicked() {
+                                    output = Some(UiOutput::QuitGame);
+                                    self.open_menu_item = MenuItem::FileBrowser;
+                                }
+
+                                if ui.button("Open in New Window").clicked() {
+                                    self.file_browser_purpose = FileBrowserPurpose::OpenInstance;
+                                    self.open_menu_item = MenuItem::FileBrowser;
+                                }
+                            });
+
+                            if self.machine_instances != 0 {
+                                ui.label(format!(
+                                    "{} more running in their own windows",
+                                    self.machine_instances
+                                ));
+                            }
+
+                            ui.horizontal(|ui| {
+                                ComboBox::from_label("Slot")
+                                    .selected_text(self.state_slot.to_string())
+                                    .show_ui(ui, |ui| {
+                                        for slot in 0..SAVESTATE_SLOTS {
+                                            ui.selectable_value(
+                                                &mut self.state_slot,
+                                                slot,
+                                                slot.to_string(),
+                                            );
+                                        }
+                                    });
+
+                                if ui.button("Save State").clicked() {
+                                    output = Some(UiOutput::SaveState {
+                                        slot: self.state_slot,
+                                    });
+                                }
+
+                                if ui.button("Load State").clicked() {
+                                    output = Some(UiOutput::LoadState {
+                                        slot: self.state_slot,
+                                    });
+                                }
+                            });
+                        });
+
+                        if let Some(savestate_status) = &self.savestate_status {
+                            ui.label(savestate_status);
+                        }
+
+                        if let Some(lag_frames) = self.lag_frames {
+                            ui.label(format!("Lag frames: {}", lag_frames));
+
+                            ui.collapsing("Profiler", |ui| {
+                                ui.checkbox(&mut self.profiling, "Time each component");
+
+                                if let Some(scheduler_stats) = &self.scheduler_stats {
+                                    scheduler_stats_grid(ui, scheduler_stats);
+                                }
+                            });
+
+                            ui.collapsing("Unmapped Accesses", |ui| {
+                                if self.unmapped_accesses.is_empty() {
+                                    ui.label("Nothing accessed outside of what the machine maps");
+                                } else {
+                                    unmapped_accesses_grid(ui, &self.unmapped_accesses);
+                                }
+                            });
+
+                            ui.collapsing("Registers", |ui| {
+                                if self.component_debug_state.is_empty() {
+                                    ui.label("Nothing in this machine can be looked inside of");
+                                }
+
+                                for (component_id, component_name, debug_state) in
+                                    &self.component_debug_state
+                                {
+                                    ui.label(format!(
+                                        "{} ({})",
+                                        short_type_name(component_name),
+                                        component_id.0
+                                    ))
+                                    .on_hover_text(*component_name);
+                                    debug_state_grid(ui, *component_id, debug_state);
+                                }
+                            });
+
+                            ui.collapsing("Access Trace", |ui| {
+                                let mut access_tracing = self.access_tracing;
+
+                                if ui
+                                    .checkbox(&mut access_tracing, "Trace Memory Accesses")
+                                    .changed()
+                                {
+                                    output = Some(UiOutput::SetAccessTracing(access_tracing));
+                                }
+
+                                if !self.access_trace.is_empty() {
+                                    access_trace_grid(ui, &self.access_trace);
+                                }
+                            });
+
+                            ui.collapsing("Access Heatmap", |ui| {
+                                ui.horizontal(|ui| {
+                                    let mut collecting = self.collecting_access_heatmap;
+
+                                    if ui.checkbox(&mut collecting, "Count Accesses").changed() {
+                                        output = Some(UiOutput::SetAccessHeatmap(collecting));
+                                    }
+
+                                    if ui.button("Clear").clicked() {
+                                        output = Some(UiOutput::ClearAccessHeatmap);
+                                    }
+                                });
+
+                                if self.access_heatmap.is_empty() {
+                                    ui.label("Nothing counted yet");
+                                } else {
+                                    access_heatmap(ui, &self.access_heatmap);
+                                }
+                            });
+
+                            ui.collapsing("Frame Comparison", |ui| {
+                                ui.horizontal(|ui| {
+                                    ui.label("Reference Frames");
+                                    ui.text_edit_singleline(&mut self.frame_comparison_directory);
+                                });
+
+                                ui.horizontal(|ui| {
+                                    if ui
+                                        .add_enabled(
+                                            !self.frame_comparison_directory.is_empty(),
+                                            egui::Button::new("Start"),
+                                        )
+                                        .clicked()
+                                    {
+                                        output = Some(UiOutput::StartFrameComparison {
+                                            references: PathBuf::from(
+                                                &self.frame_comparison_directory,
+                                            ),
+                                        });
+                                    }
+
+                                    if ui
+                                        .add_enabled(
+                                            self.frame_comparison.is_some(),
+                                            egui::Button::new("Stop"),
+                                        )
+                                        .clicked()
+                                    {
+                                        output = Some(UiOutput::StopFrameComparison);
+                                    }
+                                });
+
+                                if let Some(frame_comparison_status) = &self.frame_comparison_status
+                                {
+                                    ui.label(frame_comparison_status);
+                                }
+
+                                if let Some(summary) = &self.frame_comparison {
+                                    frame_comparison_summary(ui, summary);
+                                }
+                            });
+
+                            ui.collapsing("Input Display", |ui| {
+                                let mut global_config_guard = GLOBAL_CONFIG.write().unwrap();
+                                let input_display = &mut global_config_guard.input_display;
+
+                                for (port, gamepad_type) in &self.emulated_gamepads {
+                                    let mut shown = input_display.ports.contains(port);
+
+                                    if ui
+                                        .checkbox(
+                                            &mut shown,
+                                            format!("Port {}: {}", port + 1, gamepad_type),
+                                        )
+                                        .changed()
+                                    {
+                                        if shown {
+                                            input_display.ports.insert(*port);
+                                        } else {
+                                            input_display.ports.remove(port);
+                                        }
+                                    }
+                                }
+
+                                ui.add(
+                                    Slider::new(&mut input_display.position[0], 0.0..=1.0)
+                                        .text("Horizontal Position"),
+                                );
+                                ui.add(
+                                    Slider::new(&mut input_display.position[1], 0.0..=1.0)
+                                        .text("Vertical Position"),
+                                );
+                                ui.add(
+                                    Slider::new(&mut input_display.scale, 0.5..=4.0).text("Scale"),
+                                );
+                            });
+
+                            ui.collapsing("Memory Dump", |ui| {
+                                ui.horizontal(|ui| {
+                                    ui.label("Address Space");
+                                    ui.add(DragValue::new(&mut self.memory_dump_address_space));
+                                    ui.label("Range");
+                                    ui.text_edit_singleline(&mut self.memory_dump_range);
+                                });
+
+                                let range = parse_address_range(&self.memory_dump_range);
+
+                                ui.horizontal(|ui| {
+                                    if ui
+                                        .add_enabled(range.is_ok(), egui::Button::new("Dump"))
+                                        .clicked()
+                                    {
+                                        output = Some(UiOutput::DumpMemory {
+                                            address_space: self.memory_dump_address_space,
+                                            range: range.as_ref().unwrap().clone(),
+                                        });
+                                    }
+
+                                    if ui
+                                        .add_enabled(range.is_ok(), egui::Button::new("Load"))
+                                        .clicked()
+                                    {
+                                        self.file_browser_purpose =
+                                            FileBrowserPurpose::LoadMemory {
+                                                address_space: self.memory_dump_address_space,
+                                                address: range.as_ref().unwrap().start,
+                                            };
+                                        self.open_menu_item = MenuItem::FileBrowser;
+                                    }
+                                });
+
+                                if let Some(memory_dump_status) = &self.memory_dump_status {
+                                    ui.label(memory_dump_status);
+                                }
+                            });
+
+                            ui.collapsing("Memory", |ui| {
+                                let mut global_config_guard = GLOBAL_CONFIG.write().unwrap();
+                                let memory_budgets = &mut global_config_guard.memory_budgets;
+                                let mut greenzone_budget = memory_budgets.tas_greenzone / MEBIBYTE;
+
+                                if ui
+                                    .add(
+                                        Slider::new(&mut greenzone_budget, 16..=4096)
+                                            .text("TAS Greenzone Budget (MiB)"),
+                                    )
+                                    .changed()
+                                {
+                                    memory_budgets.tas_greenzone = greenzone_budget * MEBIBYTE;
+
+                                    if let Some(tas_session) = &mut self.tas_session {
+                                        tas_session
+                                            .set_greenzone_budget(memory_budgets.tas_greenzone);
+                                    }
+                                }
+
+                                match &self.tas_session {
+                                    Some(tas_session) => ui.label(format!(
+                                        "TAS greenzone: {:.1} MiB in {} states",
+                                        tas_session.greenzone_size() as f32 / MEBIBYTE as f32,
+                                        tas_session.greenzone_len()
+                                    )),
+                                    None => ui.label("TAS greenzone: not in use"),
+                                };
+
+                                let mut rewind_budget = memory_budgets.rewind / MEBIBYTE;
+
+                                if ui
+                                    .add(
+                                        Slider::new(&mut rewind_budget, 0..=1024)
+                                            .text("Rewind Budget (MiB, 0 turns it off)"),
+                                    )
+                                    .changed()
+                                {
+                                    memory_budgets.rewind = rewind_budget * MEBIBYTE;
+                                }
+                            });
+                        }
+
+                        if !self.media_slots.is_empty() {
+                            ui.collapsing("Change Disc", |ui| {
+                                for slot in self.media_slots.iter() {
+                                    ui.horizontal(|ui| {
+                                        ui.label(slot.as_ref());
+
+                                        if ui.button("Insert").clicked() {
+                                            self.file_browser_purpose =
+                                                FileBrowserPurpose::ChangeMedia(slot.clone());
+                                            self.open_menu_item = MenuItem::FileBrowser;
+                                        }
+
+                                        if ui.button("Eject").clicked() {
+                                            output = Some(UiOutput::ChangeMedia {
+                                                slot: slot.clone(),
+                                                path: None,
+                                            });
+                                        }
+                                    });
+                                }
+
+                                if let Some(media_error) = &self.media_error {
+                                    ui.colored_label(egui::Color32::RED, media_error);
+                                }
+                            });
+                        }
+
+                        if let Some(tape) = self.tape {
+                            ui.collapsing("Tape", |ui| {
+                                ui.label(format!(
+                                    "Block {} of {}, {}",
+                                    (tape.block + 1).min(tape.blocks),
+                                    tape.blocks,
+                                    if tape.playing { "playing" } else { "stopped" }
+                                ));
+
+                                ui.horizontal(|ui| {
+                                    for (label, control) in [
+                                        ("Play", TapeControl::Play),
+                                        ("Stop", TapeControl::Stop),
+                                        ("Rewind", TapeControl::Rewind),
+                                        ("Next Block", TapeControl::NextBlock),
+                                    ] {
+                                        if ui.button(label).clicked() {
+                                            output = Some(UiOutput::TapeControl(control));
+                                        }
+                                    }
+                                });
+
+                                let mut fast_load = tape.fast_load;
+                                if ui.checkbox(&mut fast_load, "Fast load").changed() {
+                                    output = Some(UiOutput::TapeControl(TapeControl::SetFastLoad(
+                                        fast_load,
+                                    )));
+                                }
+                            });
+                        }
+                    }
                     MenuItem::FileBrowser => {
                         let mut new_dir = None;
+                        let mut picked_storage_path = None;
 
                         ui.horizontal(|ui| {
                             // Iter over the path segments
@@ -91,6 +832,19 @@ impl MenuState {
                                 self.file_browser_state.refresh_directory();
                             }
 
+                            if let FileBrowserPurpose::StoragePath(storage_path) =
+                                self.file_browser_purpose
+                            {
+                                if storage_path.is_directory()
+                                    && ui.button("Use This Directory").clicked()
+                                {
+                                    picked_storage_path = Some((
+                                        storage_path,
+                                        self.file_browser_state.directory().to_path_buf(),
+                                    ));
+                                }
+                            }
+
                             let mut selected_sorting = self.file_browser_state.get_sorting_method();
                             egui::ComboBox::from_label("Sorting")
                                 .selected_text(format!("{:?}", selected_sorting))
@@ -119,14 +873,92 @@ impl MenuState {
                                     }
 
                                     if file_entry.is_file() {
-                                        output = Some(UiOutput::OpenGame {
-                                            path: file_entry.to_path_buf(),
-                                        });
+                                        let path = file_entry.to_path_buf();
+
+                                        match std::mem::take(&mut self.file_browser_purpose) {
+                                            FileBrowserPurpose::OpenGame => {
+                                                output = Some(UiOutput::OpenGame { path });
+                                            }
+                                            FileBrowserPurpose::OpenInstance => {
+                                                output = Some(UiOutput::OpenInstance { path });
+                                                self.open_menu_item = MenuItem::Main;
+                                            }
+                                            FileBrowserPurpose::ChangeMedia(slot) => {
+                                                output = Some(UiOutput::ChangeMedia {
+                                                    slot,
+                                                    path: Some(path),
+                                                });
+                                            }
+                                            FileBrowserPurpose::ImportDatabase => {
+                                                self.database_files.push(path);
+                                                self.open_menu_item = MenuItem::Database;
+                                            }
+                                            FileBrowserPurpose::ImportBackup => {
+                                                output = Some(UiOutput::ImportBackup {
+                                                    path,
+                                                    passphrase: self.backup_passphrase(),
+                                                });
+                                                self.open_menu_item = MenuItem::Options;
+                                            }
+                                            FileBrowserPurpose::ImportMovie => {
+                                                output = Some(UiOutput::ImportMovie { path });
+                                                self.open_menu_item = MenuItem::TasEditor;
+                                            }
+                                            FileBrowserPurpose::ImportVideoPreset => {
+                                                output = Some(UiOutput::ImportVideoPreset { path });
+                                                self.open_menu_item = MenuItem::Options;
+                                            }
+                                            // Mappings are looked up as inputs come in, so this is all it takes
+                                            FileBrowserPurpose::ImportInputProfile => {
+                                                self.input_profile_status =
+                                                    Some(match InputProfile::load(&path) {
+                                                        Ok(profile) => {
+                                                            let status = format!(
+                                                                "Imported the {} mapping for {}",
+                                                                profile.gamepad_type,
+                                                                profile.system
+                                                            );
+                                                            profile.apply_to_config(
+                                                                &mut GLOBAL_CONFIG.write().unwrap(),
+                                                            );
+
+                                                            status
+                                                        }
+                                                        Err(err) => err.to_string(),
+                                                    });
+                                                self.open_menu_item = MenuItem::Options;
+                                            }
+                                            FileBrowserPurpose::LoadMemory {
+                                                address_space,
+                                                address,
+                                            } => {
+                                                output = Some(UiOutput::LoadMemory {
+                                                    address_space,
+                                                    address,
+                                                    path,
+                                                });
+                                                self.open_menu_item = MenuItem::Main;
+                                            }
+                                            // Directories are picked with the button up top
+                                            FileBrowserPurpose::StoragePath(storage_path)
+                                                if storage_path.is_directory() =>
+                                            {
+                                                self.file_browser_purpose =
+                                                    FileBrowserPurpose::StoragePath(storage_path);
+                                            }
+                                            FileBrowserPurpose::StoragePath(storage_path) => {
+                                                picked_storage_path = Some((storage_path, path));
+                                            }
+                                        }
                                     }
                                 }
                             }
                         });
 
+                        if let Some((storage_path, path)) = picked_storage_path {
+                            self.pick_storage_path(storage_path, path);
+                        }
+
                         if let Some(new_dir) = new_dir {
                             tracing::trace!("Changing directory to {:?}", new_dir);
                             self.file_browser_state.change_directory(new_dir);
@@ -153,9 +985,566 @@ impl MenuState {
                                 }
                             });
 
+                        ComboBox::from_label("Video Filter")
+                            .selected_text(global_config_guard.video_filter.to_string())
+                            .show_ui(ui, |ui| {
+                                for filter in VideoFilter::iter() {
+                                    // Whatever the renderer said about the old one is moot now
+                                    if ui
+                                        .selectable_value(
+                                            &mut global_config_guard.video_filter,
+                                            filter,
+                                            filter.to_string(),
+                                        )
+                                        .changed()
+                                    {
+                                        self.renderer_notice = None;
+                                    }
+                                }
+                            });
+
+                        if global_config_guard.video_filter == VideoFilter::CrtLite {
+                            let parameters = &mut global_config_guard.video_filter_parameters;
+
+                            ui.add(
+                                Slider::new(&mut parameters.scanline_strength, 0.0..=1.0)
+                                    .text("Scanline Strength"),
+                            );
+                            ui.add(
+                                Slider::new(&mut parameters.mask_strength, 0.0..=1.0)
+                                    .text("Mask Strength"),
+                            );
+                        }
+
+                        ComboBox::from_label("Scaling Mode")
+                            .selected_text(global_config_guard.scaling_mode.to_string())
+                            .show_ui(ui, |ui| {
+                                for scaling_mode in ScalingMode::iter() {
+                                    ui.selectable_value(
+                                        &mut global_config_guard.scaling_mode,
+                                        scaling_mode,
+                                        scaling_mode.to_string(),
+                                    );
+                                }
+                            });
+
+                        ComboBox::from_label("Motion Smoothing")
+                            .selected_text(global_config_guard.motion_smoothing.to_string())
+                            .show_ui(ui, |ui| {
+                                for motion_smoothing in MotionSmoothing::iter() {
+                                    ui.selectable_value(
+                                        &mut global_config_guard.motion_smoothing,
+                                        motion_smoothing,
+                                        motion_smoothing.to_string(),
+                                    );
+                                }
+                            });
+
+                        ComboBox::from_label("Refresh Rate Sync")
+                            .selected_text(global_config_guard.refresh_rate_sync.to_string())
+                            .show_ui(ui, |ui| {
+                                for refresh_rate_sync in RefreshRateSync::iter() {
+                                    ui.selectable_value(
+                                        &mut global_config_guard.refresh_rate_sync,
+                                        refresh_rate_sync,
+                                        refresh_rate_sync.to_string(),
+                                    );
+                                }
+                            });
+
                         ui.checkbox(&mut global_config_guard.vsync, "VSync");
+
+                        let mut max_catch_up = global_config_guard.max_catch_up.as_millis() as u64;
+                        if ui
+                            .add(Slider::new(&mut max_catch_up, 0..=1000).text("Max Catch Up (ms)"))
+                            .changed()
+                        {
+                            global_config_guard.max_catch_up = Duration::from_millis(max_catch_up);
+                        }
+
+                        ui.add(
+                            Slider::new(&mut global_config_guard.paste_key_frames, 1..=30)
+                                .text("Pasted Key Length (frames)"),
+                        );
+
+                        let mut save_flush_interval =
+                            global_config_guard.save_flush_interval.as_secs();
+                        if ui
+                            .add(
+                                Slider::new(&mut save_flush_interval, 1..=120)
+                                    .text("Save Flush Interval (s)"),
+                            )
+                            .changed()
+                        {
+                            global_config_guard.save_flush_interval =
+                                Duration::from_secs(save_flush_interval);
+                        }
+
+                        let mut watchdog = global_config_guard.watchdog_budget.is_some();
+                        if ui.checkbox(&mut watchdog, "Watchdog").changed() {
+                            global_config_guard.watchdog_budget =
+                                watchdog.then_some(DEFAULT_WATCHDOG_BUDGET);
+                        }
+
+                        if let Some(watchdog_budget) = &mut global_config_guard.watchdog_budget {
+                            let mut watchdog_budget_millis = watchdog_budget.as_millis() as u64;
+                            if ui
+                                .add(
+                                    Slider::new(&mut watchdog_budget_millis, 100..=10000)
+                                        .text("Watchdog Budget (ms)"),
+                                )
+                                .changed()
+                            {
+                                *watchdog_budget = Duration::from_millis(watchdog_budget_millis);
+                            }
+                        }
+
+                        ComboBox::from_label("Pacing")
+                            .selected_text(global_config_guard.pacing.to_string())
+                            .show_ui(ui, |ui| {
+                                for pacing in Pacing::iter() {
+                                    ui.selectable_value(
+                                        &mut global_config_guard.pacing,
+                                        pacing,
+                                        pacing.to_string(),
+                                    );
+                                }
+                            });
+
+                        if global_config_guard.pacing == Pacing::Audio
+                            && self.machine_running
+                            && !self.audio_output
+                        {
+                            ui.label(
+                                "No audio device is open, so this paces by the host clock instead",
+                            );
+                        }
+
+                        ComboBox::from_label("Power Profile")
+                            .selected_text(global_config_guard.power_profile.to_string())
+                            .show_ui(ui, |ui| {
+                                for power_profile in PowerProfile::iter() {
+                                    ui.selectable_value(
+                                        &mut global_config_guard.power_profile,
+                                        power_profile,
+                                        power_profile.to_string(),
+                                    );
+                                }
+                            });
+
+                        ui.collapsing("Storage", |ui| {
+                            for storage_path in StoragePath::iter() {
+                                let current_path =
+                                    global_config_guard.storage_path(storage_path).to_path_buf();
+                                let draft = self
+                                    .storage_path_drafts
+                                    .entry(storage_path)
+                                    .or_insert_with(|| current_path.display().to_string());
+
+                                ui.horizontal(|ui| {
+                                    ui.label(storage_path.to_string());
+                                    ui.text_edit_singleline(draft);
+
+                                    if ui.button("Browse").clicked() {
+                                        self.file_browser_purpose =
+                                            FileBrowserPurpose::StoragePath(storage_path);
+                                        self.open_menu_item = MenuItem::FileBrowser;
+                                    }
+                                });
+
+                                let draft_path = Path::new(draft.as_str());
+
+                                if draft_path == current_path {
+                                    continue;
+                                }
+
+                                match global_config_guard
+                                    .validate_storage_path(storage_path, draft_path)
+                                {
+                                    Ok(()) => {
+                                        if ui.button(format!("Apply {}", storage_path)).clicked() {
+                                            match global_config_guard.change_storage_path(
+                                                storage_path,
+                                                draft_path.to_path_buf(),
+                                                self.migrate_storage_data,
+                                            ) {
+                                                Ok(()) => {
+                                                    self.storage_path_error = None;
+
+                                                    // Data was moved, so the old config would point at nothing
+                                                    if self.migrate_storage_data {
+                                                        if let Err(err) = global_config_guard.save()
+                                                        {
+                                                            self.storage_path_error =
+                                                                Some(err.to_string());
+                                                        }
+                                                    }
+                                                }
+                                                Err(err) => {
+                                                    self.storage_path_error = Some(err.to_string());
+                                                }
+                                            }
+                                        }
+                                    }
+                                    Err(err) => {
+                                        ui.colored_label(egui::Color32::RED, err.to_string());
+                                    }
+                                }
+                            }
+
+                            ui.checkbox(&mut self.migrate_storage_data, "Move existing data");
+                            ui.label("A new database location takes effect after a restart");
+
+                            if let Some(storage_path_error) = &self.storage_path_error {
+                                ui.colored_label(egui::Color32::RED, storage_path_error);
+                            }
+                        });
+
+                        ui.collapsing("Profile", |ui| {
+                            let next_profile = self.next_profile.get_or_insert_with(|| {
+                                selected_profile().unwrap_or_else(|| ACTIVE_PROFILE.clone())
+                            });
+
+                            ui.label(format!("Using the profile {}", ACTIVE_PROFILE.as_str()));
+
+                            ComboBox::from_label("Profile On Next Start")
+                                .selected_text(next_profile.as_str())
+                                .show_ui(ui, |ui| {
+                                    for profile in profiles() {
+                                        if ui
+                                            .selectable_label(*next_profile == profile, &profile)
+                                            .clicked()
+                                        {
+                                            match select_profile(&profile) {
+                                                Ok(()) => {
+                                                    *next_profile = profile;
+                                                    self.profile_error = None;
+                                                }
+                                                Err(err) => {
+                                                    self.profile_error = Some(err.to_string());
+                                                }
+                                            }
+                                        }
+                                    }
+                                });
+
+                            if *next_profile != *ACTIVE_PROFILE {
+                                ui.label("Restart to switch profiles");
+                            }
+
+                            ui.horizontal(|ui| {
+                                ui.text_edit_singleline(&mut self.new_profile_name);
+
+                                if ui.button("Create Profile").clicked() {
+                                    match create_profile(&self.new_profile_name) {
+                                        Ok(()) => {
+                                            self.new_profile_name.clear();
+                                            self.profile_error = None;
+                                        }
+                                        Err(err) => {
+                                            self.profile_error = Some(err.to_string());
+                                        }
+                                    }
+                                }
+                            });
+
+                            if let Some(profile_error) = &self.profile_error {
+                                ui.colored_label(egui::Color32::RED, profile_error);
+                            }
+                        });
+
+                        ui.collapsing("Controls", |ui| {
+                            for (system, gamepad_types) in &global_config_guard.gamepad_configs {
+                                for gamepad_type in gamepad_types.keys() {
+                                    ui.horizontal(|ui| {
+                                        ui.label(format!("{}: {}", system, gamepad_type));
+
+                                        if ui.button("Export Profile").clicked() {
+                                            let path = InputProfile::path(*system, gamepad_type);
+
+                                            self.input_profile_status = InputProfile::from_config(
+                                                &global_config_guard,
+                                                *system,
+                                                gamepad_type.clone(),
+                                            )
+                                            .map(|profile| match profile.save(&path) {
+                                                Ok(()) => format!("Exported to {}", path.display()),
+                                                Err(err) => err.to_string(),
+                                            });
+                                        }
+                                    });
+                                }
+                            }
+
+                            if ui.button("Import Profile").clicked() {
+                                if INPUT_PROFILE_DIRECTORY.is_dir() {
+                                    self.file_browser_state
+                                        .change_directory(INPUT_PROFILE_DIRECTORY.clone());
+                                }
+
+                                self.file_browser_purpose = FileBrowserPurpose::ImportInputProfile;
+                                self.open_menu_item = MenuItem::FileBrowser;
+                            }
+
+                            if let Some(input_profile_status) = &self.input_profile_status {
+                                ui.label(input_profile_status);
+                            }
+                        });
+
+                        ui.collapsing("Video Preset", |ui| {
+                            ui.horizontal(|ui| {
+                                ui.text_edit_singleline(&mut self.video_preset_name);
+
+                                if ui
+                                    .add_enabled(
+                                        !self.video_preset_name.is_empty(),
+                                        egui::Button::new("Export Preset"),
+                                    )
+                                    .clicked()
+                                {
+                                    let path = VideoPreset::path(&self.video_preset_name);
+
+                                    self.video_preset_status = Some(
+                                        match VideoPreset::from_config(
+                                            &global_config_guard,
+                                            self.video_preset_name.clone(),
+                                        )
+                                        .save(&path)
+                                        {
+                                            Ok(()) => format!("Exported to {}", path.display()),
+                                            Err(err) => err.to_string(),
+                                        },
+                                    );
+                                }
+
+                                if ui.button("Import Preset").clicked() {
+                                    if VIDEO_PRESET_DIRECTORY.is_dir() {
+                                        self.file_browser_state
+                                            .change_directory(VIDEO_PRESET_DIRECTORY.clone());
+                                    }
+
+                                    self.file_browser_purpose =
+                                        FileBrowserPurpose::ImportVideoPreset;
+                                    self.open_menu_item = MenuItem::FileBrowser;
+                                }
+                            });
+
+                            // Attaching keeps a copy, so tweaking the options afterwards does not touch the game
+                            ui.horizontal(|ui| {
+                                if ui.button("Attach to Running Game").clicked() {
+                                    output = Some(UiOutput::AttachVideoPreset {
+                                        name: self.video_preset_name.clone(),
+                                    });
+                                }
+
+                                if ui.button("Detach from Running Game").clicked() {
+                                    output = Some(UiOutput::DetachVideoPreset);
+                                }
+                            });
+
+                            if let Some(video_preset_status) = &self.video_preset_status {
+                                ui.label(video_preset_status);
+                            }
+                        });
+
+                        ui.collapsing("Updates", |ui| {
+                            ui.checkbox(&mut global_config_guard.network_access, "Network Access");
+
+                            let update_status = self.update_status.lock().unwrap().clone();
+
+                            if ui
+                                .add_enabled(
+                                    global_config_guard.network_access
+                                        && !matches!(update_status, Some(UpdateStatus::Checking)),
+                                    egui::Button::new("Check for Updates"),
+                                )
+                                .clicked()
+                            {
+                                output = Some(UiOutput::CheckForUpdates);
+                            }
+
+                            match update_status {
+                                Some(UpdateStatus::Checking) => {
+                                    ui.spinner();
+                                }
+                                Some(UpdateStatus::UpToDate) => {
+                                    ui.label(format!("Version {} is the latest", CURRENT_VERSION));
+                                }
+                                Some(UpdateStatus::Available(release)) => {
+                                    ui.label(format!(
+                                        "{} is available, see the main menu",
+                                        release.tag_name
+                                    ));
+                                }
+                                Some(UpdateStatus::Failed(err)) => {
+                                    ui.colored_label(egui::Color32::RED, err);
+                                }
+                                None => {}
+                            }
+                        });
+
+                        ui.collapsing("Backup", |ui| {
+                            let backup_status = self.backup_status.lock().unwrap().clone();
+                            let backup_running =
+                                matches!(backup_status, Some(BackupStatus::Running));
+
+                            ui.horizontal(|ui| {
+                                if ui
+                                    .add_enabled(
+                                        !backup_running,
+                                        egui::Button::new("Export Backup"),
+                                    )
+                                    .clicked()
+                                {
+                                    output = Some(UiOutput::ExportBackup {
+                                        passphrase: self.backup_passphrase(),
+                                    });
+                                }
+
+                                if ui
+                                    .add_enabled(
+                                        !backup_running,
+                                        egui::Button::new("Import Backup"),
+                                    )
+                                    .clicked()
+                                {
+                                    self.file_browser_purpose = FileBrowserPurpose::ImportBackup;
+                                    self.open_menu_item = MenuItem::FileBrowser;
+                                }
+                            });
+
+                            ui.horizontal(|ui| {
+                                ui.label("Passphrase");
+                                ui.add(
+                                    egui::TextEdit::singleline(&mut self.backup_passphrase)
+                                        .password(true),
+                                );
+                            });
+
+                            ui.label("Roms are not backed up, only the database entries for them");
+                            ui.label("Leaving the passphrase empty makes a backup anyone can read");
+
+                            match backup_status {
+                                Some(BackupStatus::Running) => {
+                                    ui.spinner();
+                                }
+                                Some(BackupStatus::Finished(message)) => {
+                                    ui.label(message);
+                                }
+                                Some(BackupStatus::Failed(err)) => {
+                                    ui.colored_label(egui::Color32::RED, err);
+                                }
+                                None => {}
+                            }
+                        });
+                    }
+                    MenuItem::Database => {
+                        let database_import_progress =
+                            self.database_import_progress.lock().unwrap();
+                        let import_running = database_import_progress.is_running();
+
+                        // Progress comes from another thread so keep checking on it
+                        if import_running {
+                            ui.ctx().request_repaint();
+                        }
+
+                        ui.horizontal(|ui| {
+                            if ui.button("Add File").clicked() {
+                                self.file_browser_purpose = FileBrowserPurpose::ImportDatabase;
+                                self.open_menu_item = MenuItem::FileBrowser;
+                            }
+
+                            if ui.button("Clear").clicked() {
+                                self.database_files.clear();
+                            }
+
+                            if ui
+                                .add_enabled(
+                                    !self.database_files.is_empty() && !import_running,
+                                    egui::Button::new("Import"),
+                                )
+                                .clicked()
+                            {
+                                output = Some(UiOutput::ImportDatabases {
+                                    paths: std::mem::take(&mut self.database_files),
+                                });
+                            }
+                        });
+
+                        for path in self.database_files.iter() {
+                            ui.label(path.display().to_string());
+                        }
+
+                        if !database_import_progress.files.is_empty() {
+                            ui.separator();
+
+                            let finished = database_import_progress.finished();
+                            let total = database_import_progress.files.len();
+
+                            ui.add(
+                                ProgressBar::new(finished as f32 / total as f32)
+                                    .text(format!("{}/{} databases imported", finished, total)),
+                            );
+
+                            for (path, status) in database_import_progress.files.iter() {
+                                ui.collapsing(path.display().to_string(), |ui| match status {
+                                    DatabaseImportStatus::Queued => {
+                                        ui.label("Waiting");
+                                    }
+                                    DatabaseImportStatus::Importing => {
+                                        ui.horizontal(|ui| {
+                                            ui.spinner();
+                                            ui.label("Importing");
+                                        });
+                                    }
+                                    DatabaseImportStatus::Imported(imported_entries) => {
+                                        for (system, count) in imported_entries {
+                                            ui.label(format!("{}: {} entries", system, count));
+                                        }
+                                    }
+                                    DatabaseImportStatus::Failed(err) => {
+                                        ui.colored_label(egui::Color32::RED, err);
+                                    }
+                                });
+                            }
+                        }
+
+                        ui.separator();
+                        ui.horizontal(|ui| {
+                            ui.heading("Compatibility");
+
+                            if ui.button("Refresh").clicked() {
+                                self.compatibility_reports = None;
+                            }
+                        });
+
+                        let compatibility_reports = self
+                            .compatibility_reports
+                            .get_or_insert_with(CompatibilityReport::load_all);
+
+                        if compatibility_reports.is_empty() {
+                            ui.label("No reports yet, run multiemu rom test-all to make one");
+                        }
+
+                        for report in compatibility_reports.iter() {
+                            compatibility_report(ui, report);
+                        }
+                    }
+                    MenuItem::Library => {
+                        if let Some(library_output) = self.library(ui) {
+                            output = Some(library_output);
+                        }
+                    }
+                    MenuItem::TasEditor => {
+                        if let Some(tas_output) = self.tas_editor(ui) {
+                            output = Some(tas_output);
+                        }
+                    }
+                    MenuItem::Statistics => {
+                        if let Some(statistics_output) = self.statistics(ui) {
+                            output = Some(statistics_output);
+                        }
                     }
-                    MenuItem::Database => {}
                 },
             );
         });
@@ -163,3 +1552,269 @@ impl MenuState {
         output
     }
 }
+
+fn compatibility_report(ui: &mut egui::Ui, report: &CompatibilityReport) {
+    let summary = report.summary();
+
+    ui.collapsing(
+        format!("{} ({} frames per ROM)", report.system, report.frames),
+        |ui| {
+            ui.add(
+                ProgressBar::new(summary.working as f32 / report.results.len().max(1) as f32).text(
+                    format!("{}/{} working", summary.working, report.results.len()),
+                ),
+            );
+            ui.label(format!(
+                "{} blank screen, {} faulted, {} crashed, {} unsupported",
+                summary.blank_screen, summary.faulted, summary.crashed, summary.unsupported
+            ));
+            for (feature, count) in report.missing_features() {
+                ui.label(format!("{} ROMs need {}", count, feature));
+            }
+
+            ScrollArea::vertical()
+                .id_salt(report.system.to_string())
+                .max_height(300.0)
+                .show(ui, |ui| {
+                    Grid::new(("compatibility", report.system.to_string()))
+                        .striped(true)
+                        .show(ui, |ui| {
+                            for result in report.results.iter() {
+                                let color = match result.status {
+                                    CompatibilityStatus::Working => egui::Color32::GREEN,
+                                    CompatibilityStatus::BlankScreen => egui::Color32::YELLOW,
+                                    CompatibilityStatus::Faulted { .. }
+                                    | CompatibilityStatus::Crashed { .. } => egui::Color32::RED,
+                                    CompatibilityStatus::Unsupported { .. } => egui::Color32::GRAY,
+                                };
+
+                                ui.label(
+                                    result
+                                        .name
+                                        .clone()
+                                        .unwrap_or_else(|| result.rom_id.to_string()),
+                                );
+                                ui.colored_label(color, result.status.to_string());
+                                ui.end_row();
+                            }
+                        });
+                });
+        },
+    );
+}
+
+fn debug_state_grid(
+    ui: &mut egui::Ui,
+    component_id: ComponentId,
+    debug_state: &[(String, DebugValue)],
+) {
+    Grid::new(("debug_state", component_id.0))
+        .striped(true)
+        .show(ui, |ui| {
+            for (name, value) in debug_state {
+                ui.label(name);
+                ui.monospace(value.to_string());
+                ui.end_row();
+            }
+        });
+}
+
+/// Most hit first, so whatever the machine definition is missing the most is at the top
+fn unmapped_accesses_grid(
+    ui: &mut egui::Ui,
+    unmapped_accesses: &[(UnmappedAccess, Option<&'static str>)],
+) {
+    Grid::new("unmapped_accesses").striped(true).show(ui, |ui| {
+        ui.label("Address");
+        ui.label("Reads");
+        ui.label("Writes");
+        ui.label("Component");
+        ui.end_row();
+
+        for (unmapped_access, component_name) in unmapped_accesses {
+            ui.label(format!(
+                "{}:{:#06x}",
+                unmapped_access.address_space, unmapped_access.address
+            ));
+            ui.label(unmapped_access.reads.to_string());
+            ui.label(unmapped_access.writes.to_string());
+            match (unmapped_access.component, component_name) {
+                (Some(component_id), Some(component_name)) => ui
+                    .label(format!(
+                        "{} ({})",
+                        short_type_name(component_name),
+                        component_id.0
+                    ))
+                    .on_hover_text(*component_name),
+                _ => ui.label("Unknown"),
+            };
+            ui.end_row();
+        }
+    });
+}
+
+fn access_trace_grid(ui: &mut egui::Ui, access_trace: &[(MemoryAccess, Option<&'static str>)]) {
+    Grid::new("access_trace").striped(true).show(ui, |ui| {
+        ui.label("Access");
+        ui.label("Address");
+        ui.label("Value");
+        ui.label("Component");
+        ui.end_row();
+
+        for (access, component_name) in access_trace {
+            ui.label(format!(
+                "{:?}{}",
+                access.kind,
+                if access.failed { " (failed)" } else { "" }
+            ));
+            ui.label(format!("{}:{:#06x}", access.address_space, access.address));
+            ui.label(format!(
+                "{:#0width$x}",
+                access.value,
+                width = access.size as usize * 2 + 2
+            ));
+            match (access.component, component_name) {
+                (Some(component_id), Some(component_name)) => ui
+                    .label(format!(
+                        "{} ({})",
+                        short_type_name(component_name),
+                        component_id.0
+                    ))
+                    .on_hover_text(*component_name),
+                _ => ui.label("Unknown"),
+            };
+            ui.end_row();
+        }
+    });
+}
+
+/// Pages drawn per row of the heatmap
+const HEATMAP_ROW_PAGES: usize = 32;
+/// Past this the bus is too big to draw page by page, so only the start of it is shown
+const HEATMAP_MAX_PAGES: usize = 4096;
+
+/// A square per page from the start of each address space to the last page touched, brighter the more it was hit
+fn frame_comparison_summary(ui: &mut egui::Ui, summary: &FrameComparisonSummary) {
+    ui.label(format!(
+        "{} frames compared, {} differed",
+        summary.compared_frames, summary.differing_frames
+    ));
+
+    if let Some(difference) = &summary.first_difference {
+        ui.label(format!(
+            "First differed on frame {}, display {}",
+            difference.frame, difference.display
+        ));
+    }
+
+    Grid::new("frame_comparison").striped(true).show(ui, |ui| {
+        ui.label("Frame");
+        ui.label("Display");
+        ui.label("Differing Pixels");
+        ui.end_row();
+
+        for comparison in &summary.latest {
+            ui.label(comparison.frame.to_string());
+            ui.label(comparison.display.to_string());
+            ui.label(format!(
+                "{} / {}",
+                comparison.differing_pixels, comparison.total_pixels
+            ));
+            ui.end_row();
+        }
+    });
+}
+
+fn access_heatmap(ui: &mut egui::Ui, access_heatmap: &[PageAccesses]) {
+    let hottest = access_heatmap
+        .iter()
+        .map(|page| page.reads + page.writes)
+        .max()
+        .unwrap_or_default();
+
+    for (address_space, pages) in &access_heatmap.iter().chunk_by(|page| page.address_space) {
+        let pages: HashMap<_, _> = pages.map(|page| (page.address / PAGE_SIZE, page)).collect();
+        let last_page = pages.keys().max().copied().unwrap_or_default();
+        let shown_pages = (last_page + 1).min(HEATMAP_MAX_PAGES);
+
+        ui.label(format!("Address space {}", address_space));
+        if shown_pages <= last_page {
+            ui.label(format!(
+                "Only the first {} pages are shown",
+                HEATMAP_MAX_PAGES
+            ));
+        }
+
+        Grid::new(("access_heatmap", address_space))
+            .spacing([1.0, 1.0])
+            .show(ui, |ui| {
+                for page_index in 0..shown_pages {
+                    let (rect, response) =
+                        ui.allocate_exact_size(egui::vec2(8.0, 8.0), egui::Sense::hover());
+
+                    let color = match pages.get(&page_index) {
+                        Some(page) => {
+                            // Logarithmic so a few hot pages do not wash everything else out
+                            let heat = ((page.reads + page.writes) as f32).ln_1p()
+                                / (hottest as f32).ln_1p();
+
+                            response.on_hover_text(format!(
+                                "{:#06x}..{:#06x}\nReads: {}\nWrites: {}",
+                                page.address,
+                                page.address + PAGE_SIZE,
+                                page.reads,
+                                page.writes
+                            ));
+
+                            egui::Color32::from_rgb(
+                                (64.0 + heat * 191.0) as u8,
+                                (heat * 160.0) as u8,
+                                32,
+                            )
+                        }
+                        None => egui::Color32::from_gray(32),
+                    };
+                    ui.painter().rect_filled(rect, 0.0, color);
+
+                    if (page_index + 1) % HEATMAP_ROW_PAGES == 0 {
+                        ui.end_row();
+                    }
+                }
+            });
+    }
+}
+
+/// Type names come with their whole module path
+fn short_type_name(name: &str) -> &str {
+    name.split('<')
+        .next()
+        .and_then(|name| name.rsplit("::").next())
+        .unwrap_or(name)
+}
+
+/// Components of the last run, slowest first, so whatever keeps the machine from keeping up is at the top
+fn scheduler_stats_grid(ui: &mut egui::Ui, scheduler_stats: &SchedulerStats) {
+    ui.label(format!(
+        "Last run took {:?} over {} ticks",
+        scheduler_stats.run_time, scheduler_stats.ticks_run
+    ));
+
+    Grid::new("scheduler_stats").striped(true).show(ui, |ui| {
+        for component_stats in scheduler_stats.components.iter() {
+            ui.label(format!(
+                "{} ({})",
+                short_type_name(component_stats.name),
+                component_stats.component_id.0
+            ))
+            .on_hover_text(component_stats.name);
+            ui.label(format!("{:?}", component_stats.run_time));
+            ui.label(format!(
+                "{:.1}%",
+                component_stats.run_time.as_secs_f32()
+                    / scheduler_stats.run_time.as_secs_f32().max(f32::EPSILON)
+                    * 100.0
+            ));
+            ui.end_row();
+        }
+    });
+}
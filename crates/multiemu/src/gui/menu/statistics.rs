@@ -0,0 +1,109 @@
+use super::{MenuState, UiOutput};
+use crate::{config::GLOBAL_CONFIG, rom::statistics::UsageStatistics};
+use egui::{Grid, ScrollArea, Ui};
+use std::time::Duration;
+
+/// Past this many the page gets too long to be useful
+const GAMES_SHOWN: usize = 20;
+
+impl MenuState {
+    /// What the user has been playing, worked out from play sessions recorded on this computer only
+    pub(super) fn statistics(&mut self, ui: &mut Ui) -> Option<UiOutput> {
+        let mut output = None;
+
+        let mut usage_statistics = GLOBAL_CONFIG.read().unwrap().usage_statistics;
+
+        if ui
+            .checkbox(&mut usage_statistics, "Keep usage statistics")
+            .changed()
+        {
+            GLOBAL_CONFIG.write().unwrap().usage_statistics = usage_statistics;
+        }
+        ui.label("Play sessions are only stored locally and are never sent anywhere");
+
+        ui.horizontal(|ui| {
+            if ui.button("Refresh").clicked() {
+                output = Some(UiOutput::RefreshStatistics);
+            }
+
+            if ui.button("Export JSON").clicked() {
+                output = Some(UiOutput::ExportStatistics);
+            }
+
+            if ui.button("Forget Everything").clicked() {
+                output = Some(UiOutput::ClearStatistics);
+            }
+        });
+
+        if let Some(statistics_status) = &self.statistics_status {
+            ui.label(statistics_status);
+        }
+
+        let Some(statistics) = &self.usage_statistics else {
+            // Reading the sessions is left to the frontend, which has the rom manager
+            return Some(UiOutput::RefreshStatistics);
+        };
+
+        if statistics.sessions == 0 {
+            ui.label("Nothing has been played yet");
+            return output;
+        }
+
+        ScrollArea::vertical().show(ui, |ui| {
+            usage_statistics_overview(ui, statistics);
+        });
+
+        output
+    }
+}
+
+fn usage_statistics_overview(ui: &mut Ui, statistics: &UsageStatistics) {
+    ui.label(format!(
+        "{} games played over {} sessions, {} in total",
+        statistics.games.len(),
+        statistics.sessions,
+        format_play_time(statistics.play_time)
+    ));
+
+    ui.heading("Systems");
+    Grid::new("statistics_systems")
+        .striped(true)
+        .show(ui, |ui| {
+            for system in &statistics.systems {
+                ui.label(system.system.to_string());
+                ui.label(format!("{} games", system.games));
+                ui.label(format_play_time(system.play_time));
+                ui.end_row();
+            }
+        });
+
+    ui.heading("Games");
+    Grid::new("statistics_games").striped(true).show(ui, |ui| {
+        for game in statistics.games.iter().take(GAMES_SHOWN) {
+            ui.label(game.name.clone().unwrap_or_else(|| game.rom_id.to_string()));
+            ui.label(game.system.to_string());
+            ui.label(format!("{} sessions", game.sessions));
+            ui.label(format_play_time(game.play_time));
+            ui.end_row();
+        }
+    });
+
+    if !statistics.features.is_empty() {
+        ui.heading("Features");
+        Grid::new("statistics_features")
+            .striped(true)
+            .show(ui, |ui| {
+                for (feature, uses) in &statistics.features {
+                    ui.label(feature);
+                    ui.label(format!("{} times", uses));
+                    ui.end_row();
+                }
+            });
+    }
+}
+
+fn format_play_time(duration: Duration) -> String {
+    let minutes = duration.as_secs() / 60;
+
+    format!("{}h {:02}m", minutes / 60, minutes % 60)
+}
@@ -0,0 +1,182 @@
+use super::{FileBrowserPurpose, MenuItem, MenuState, UiOutput};
+use crate::input::Input;
+use egui::{Button, Color32, Label, RichText, ScrollArea, SelectableLabel, Ui};
+
+const ROW_HEIGHT: f32 = 18.0;
+const FRAME_COLUMN_WIDTH: f32 = 64.0;
+const INPUT_COLUMN_WIDTH: f32 = 56.0;
+
+impl MenuState {
+    /// Piano roll of the movie inputs, with a row per frame and a column per input
+    pub(super) fn tas_editor(&mut self, ui: &mut Ui) -> Option<UiOutput> {
+        let mut output = None;
+
+        if let Some(tas_error) = &self.tas_error {
+            ui.colored_label(Color32::RED, tas_error);
+        }
+
+        let Some(session) = &mut self.tas_session else {
+            ui.horizontal(|ui| {
+                if ui.button("Start Movie").clicked() {
+                    output = Some(UiOutput::StartTas);
+                }
+
+                if ui.button("Import Movie").clicked() {
+                    self.file_browser_purpose = FileBrowserPurpose::ImportMovie;
+                    self.open_menu_item = MenuItem::FileBrowser;
+                }
+            });
+
+            return output;
+        };
+
+        ui.horizontal(|ui| {
+            if ui.button("Stop Movie").clicked() {
+                output = Some(UiOutput::StopTas);
+            }
+
+            if ui.button("Export Movie").clicked() {
+                output = Some(UiOutput::ExportMovie);
+            }
+
+            ui.separator();
+
+            ui.label(format!(
+                "Frame {} of {}, {} lag frames",
+                session.current_frame(),
+                session.movie.frames.len(),
+                session.lag_frame_count()
+            ));
+
+            if ui.button("Advance").clicked() {
+                output = Some(UiOutput::TasAdvance);
+            }
+        });
+
+        ui.collapsing("Branches", |ui| {
+            ui.horizontal(|ui| {
+                ui.text_edit_singleline(&mut self.new_branch_name);
+
+                if ui
+                    .add_enabled(!self.new_branch_name.is_empty(), Button::new("Save Branch"))
+                    .clicked()
+                {
+                    session.save_branch(std::mem::take(&mut self.new_branch_name));
+                }
+            });
+
+            let mut loaded_branch = None;
+            let mut removed_branch = None;
+
+            for name in session.branches() {
+                ui.horizontal(|ui| {
+                    ui.label(name);
+
+                    if ui.button("Load").clicked() {
+                        loaded_branch = Some(name.to_string());
+                    }
+
+                    if ui.button("Delete").clicked() {
+                        removed_branch = Some(name.to_string());
+                    }
+                });
+            }
+
+            if let Some(name) = loaded_branch {
+                if session.load_branch(&name) {
+                    output = Some(UiOutput::TasSeek {
+                        frame: session.current_frame(),
+                    });
+                }
+            }
+
+            if let Some(name) = removed_branch {
+                session.remove_branch(&name);
+            }
+        });
+
+        ui.separator();
+
+        ui.horizontal(|ui| {
+            ui.add_sized([FRAME_COLUMN_WIDTH, ROW_HEIGHT], Label::new("Frame"));
+
+            for (port, input) in session.columns() {
+                ui.add_sized(
+                    [INPUT_COLUMN_WIDTH, ROW_HEIGHT],
+                    Label::new(input_label(*input)),
+                )
+                .on_hover_text(format!("Port {}: {:?}", port, input));
+            }
+        });
+
+        let mut toggled = None;
+        let mut seek = None;
+
+        // One row past the end so the next frame can be filled in before it is run
+        let rows = session.movie.frames.len() + 1;
+
+        ScrollArea::vertical().show_rows(ui, ROW_HEIGHT, rows, |ui, row_range| {
+            for frame in row_range {
+                ui.horizontal(|ui| {
+                    // States cached in the greenzone are quick to seek to
+                    let frame_text = if frame == session.current_frame() {
+                        RichText::new(format!("▶ {}", frame)).strong()
+                    } else if session.is_lag_frame(frame) {
+                        RichText::new(frame.to_string()).color(Color32::LIGHT_RED)
+                    } else if session.is_greenzone(frame) {
+                        RichText::new(frame.to_string()).color(Color32::GREEN)
+                    } else {
+                        RichText::new(frame.to_string())
+                    };
+
+                    let frame_button =
+                        ui.add_sized([FRAME_COLUMN_WIDTH, ROW_HEIGHT], Button::new(frame_text));
+                    let frame_button = if session.is_lag_frame(frame) {
+                        frame_button.on_hover_text("Lag frame, input was never polled")
+                    } else {
+                        frame_button
+                    };
+
+                    if frame_button.clicked() {
+                        seek = Some(frame);
+                    }
+
+                    for (port, input) in session.columns() {
+                        let held = session.is_held(frame, *port, *input);
+
+                        if ui
+                            .add_sized(
+                                [INPUT_COLUMN_WIDTH, ROW_HEIGHT],
+                                SelectableLabel::new(held, if held { "●" } else { "·" }),
+                            )
+                            .clicked()
+                        {
+                            toggled = Some((frame, *port, *input, !held));
+                        }
+                    }
+                });
+            }
+        });
+
+        if let Some((frame, port, input, held)) = toggled {
+            if session.set_held(frame, port, input, held) {
+                output = Some(UiOutput::TasSeek {
+                    frame: session.current_frame(),
+                });
+            }
+        }
+
+        if let Some(frame) = seek {
+            output = Some(UiOutput::TasSeek { frame });
+        }
+
+        output
+    }
+}
+
+fn input_label(input: Input) -> String {
+    match input {
+        Input::Gamepad(gamepad_input) => format!("{:?}", gamepad_input),
+        Input::Keyboard(keyboard_input) => format!("{:?}", keyboard_input),
+    }
+}
@@ -1,3 +1,4 @@
+use crate::host_cpu::multiversion;
 use arrayvec::ArrayVec;
 use egui::FullOutput;
 use egui::TextureId;
@@ -84,7 +85,9 @@ impl SoftwareEguiRenderer {
             self.textures.remove(&remove_texture_id);
         }
 
-        render_buffer.fill(Srgba::new(0, 0, 0, 0xff));
+        for mut column in render_buffer.column_iter_mut() {
+            fill_pixels(column.as_mut_slice(), Srgba::new(0, 0, 0, 0xff));
+        }
 
         let render_buffer_dimensions =
             Vector2::new(render_buffer.nrows(), render_buffer.ncols()).cast::<f32>();
@@ -152,43 +155,15 @@ impl SoftwareEguiRenderer {
                                 .enumerate()
                                 .map(|(y, row)| (y + min.y, row))
                                 .for_each(|(y, mut row)| {
-                                    for x in min.x..=max.x {
-                                        let pixel_center =
-                                            Point2::new(x as f32 + 0.5, y as f32 + 0.5);
-
-                                        if is_point_in_triangle(pixel_center, points, &edges) {
-                                            // Interpolate colors based on barycentric coordinates
-                                            let barycentric =
-                                                barycentric_coordinates(pixel_center, points, &edges);
-
-                                            let interpolated_color = v0.color.into_linear()
-                                                * barycentric.x
-                                                + v1.color.into_linear() * barycentric.y
-                                                + v2.color.into_linear() * barycentric.z;
-
-                                            let interpolated_uv = v0.uv.coords * barycentric.x
-                                                + v1.uv.coords * barycentric.y
-                                                + v2.uv.coords * barycentric.z;
-
-                                            let pixel_coords = Point2::new(
-                                                (texture.nrows() as f32 * interpolated_uv.x)
-                                                    as usize,
-                                                (texture.ncols() as f32 * interpolated_uv.y)
-                                                    as usize,
-                                            );
-
-                                            // Inaccuraries that lead outside the texture we will read off with black
-                                            let pixel = texture
-                                                .get((pixel_coords.x, pixel_coords.y))
-                                                .copied()
-                                                .unwrap_or(Srgba::new(0, 0, 0, 0xff));
-
-                                            row[x - min.x] = Srgba::from_linear(
-                                                (interpolated_color * pixel.into_linear())
-                                                    .over(row[x - min.x].into_linear()),
-                                            );
-                                        }
-                                    }
+                                    shade_row(
+                                        row.as_mut_slice(),
+                                        min.x,
+                                        y,
+                                        points,
+                                        &edges,
+                                        &[*v0, *v1, *v2],
+                                        texture,
+                                    );
                                 });
                         }
                     }
@@ -201,6 +176,59 @@ impl SoftwareEguiRenderer {
     }
 }
 
+multiversion! {
+    fn fill_pixels(pixels: &mut [Srgba<u8>], color: Srgba<u8>) {
+        pixels.fill(color);
+    }
+}
+
+multiversion! {
+    /// Shades the part of one line of the framebuffer the triangle covers, `row` starting at `first_x`
+    fn shade_row(
+        row: &mut [Srgba<u8>],
+        first_x: usize,
+        y: usize,
+        points: Matrix2x3<f32>,
+        edges: &Matrix2x3<f32>,
+        vertexes: &[EguiVertex; 3],
+        texture: &DMatrix<Srgba<u8>>,
+    ) {
+        let [v0, v1, v2] = vertexes;
+
+        for (x, destination) in (first_x..).zip(row.iter_mut()) {
+            let pixel_center = Point2::new(x as f32 + 0.5, y as f32 + 0.5);
+
+            if is_point_in_triangle(pixel_center, points, edges) {
+                // Interpolate colors based on barycentric coordinates
+                let barycentric = barycentric_coordinates(pixel_center, points, edges);
+
+                let interpolated_color = v0.color.into_linear() * barycentric.x
+                    + v1.color.into_linear() * barycentric.y
+                    + v2.color.into_linear() * barycentric.z;
+
+                let interpolated_uv = v0.uv.coords * barycentric.x
+                    + v1.uv.coords * barycentric.y
+                    + v2.uv.coords * barycentric.z;
+
+                let pixel_coords = Point2::new(
+                    (texture.nrows() as f32 * interpolated_uv.x) as usize,
+                    (texture.ncols() as f32 * interpolated_uv.y) as usize,
+                );
+
+                // Inaccuraries that lead outside the texture we will read off with black
+                let pixel = texture
+                    .get((pixel_coords.x, pixel_coords.y))
+                    .copied()
+                    .unwrap_or(Srgba::new(0, 0, 0, 0xff));
+
+                *destination = Srgba::from_linear(
+                    (interpolated_color * pixel.into_linear()).over(destination.into_linear()),
+                );
+            }
+        }
+    }
+}
+
 #[inline]
 fn triangle_area(v: Matrix2x3<f32>) -> f32 {
     let edges = Matrix2x3::from_columns(&[
@@ -255,3 +283,147 @@ fn is_point_in_triangle(point: Point2<f32>, v: Matrix2x3<f32>, edges: &Matrix2x3
 
     b.into_iter().all(|&val| val >= 0.0) || b.into_iter().all(|&val| val <= 0.0)
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use egui::{Color32, Pos2, Rect, Stroke, Vec2};
+    use image::RgbaImage;
+    use std::path::{Path, PathBuf};
+
+    const SCENE_SIZE: Vector2<usize> = Vector2::new(160, 120);
+    /// How far a channel can be off before the pixel counts as different, so rounding changes do not fail anything
+    const CHANNEL_TOLERANCE: u8 = 2;
+    /// How many pixels can differ before the whole image does, for the odd pixel on a triangle edge
+    const PIXEL_TOLERANCE: usize = 8;
+
+    /// Set to write out what was rendered as the new golden images instead of comparing against them
+    const UPDATE_GOLDEN_VARIABLE: &str = "MULTIEMU_UPDATE_GOLDEN";
+
+    fn golden_path(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/golden")
+            .join(format!("{}.png", name))
+    }
+
+    fn render_scene(mut scene: impl FnMut(&egui::Context)) -> DMatrix<Srgba<u8>> {
+        let context = egui::Context::default();
+        let mut renderer = SoftwareEguiRenderer::default();
+        let mut render_buffer =
+            DMatrix::from_element(SCENE_SIZE.x, SCENE_SIZE.y, Srgba::new(0, 0, 0, 0xff));
+        let raw_input = egui::RawInput {
+            screen_rect: Some(Rect::from_min_size(
+                Pos2::ZERO,
+                Vec2::new(SCENE_SIZE.x as f32, SCENE_SIZE.y as f32),
+            )),
+            ..Default::default()
+        };
+
+        // The first pass only has layout to go off of, and the font texture has to make it to the renderer either way
+        for _ in 0..2 {
+            let full_output = context.run(raw_input.clone(), &mut scene);
+            renderer.render(&context, render_buffer.as_view_mut(), full_output);
+        }
+
+        render_buffer
+    }
+
+    fn read_image(path: &Path) -> DMatrix<Srgba<u8>> {
+        let image = image::open(path)
+            .unwrap_or_else(|error| panic!("Could not open {}: {}", path.display(), error))
+            .into_rgba8();
+
+        DMatrix::from_fn(image.width() as usize, image.height() as usize, |x, y| {
+            Srgba::from(image.get_pixel(x as u32, y as u32).0)
+        })
+    }
+
+    fn write_image(path: &Path, rendered: &DMatrix<Srgba<u8>>) {
+        let image = RgbaImage::from_fn(rendered.nrows() as u32, rendered.ncols() as u32, |x, y| {
+            image::Rgba(rendered[(x as usize, y as usize)].into())
+        });
+
+        image.save(path).unwrap();
+    }
+
+    fn assert_matches_golden(name: &str, rendered: &DMatrix<Srgba<u8>>) {
+        let path = golden_path(name);
+
+        if std::env::var_os(UPDATE_GOLDEN_VARIABLE).is_some() {
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            write_image(&path, rendered);
+            return;
+        }
+
+        let golden = read_image(&path);
+        assert_eq!(
+            golden.shape(),
+            rendered.shape(),
+            "{} was rendered at a different size than its golden image",
+            name
+        );
+
+        let differing_pixels = golden
+            .iter()
+            .zip(rendered.iter())
+            .filter(|(golden, rendered)| {
+                <[u8; 4]>::from(**golden)
+                    .into_iter()
+                    .zip(<[u8; 4]>::from(**rendered))
+                    .any(|(golden, rendered)| golden.abs_diff(rendered) > CHANNEL_TOLERANCE)
+            })
+            .count();
+
+        if differing_pixels > PIXEL_TOLERANCE {
+            let actual_path = std::env::temp_dir().join(format!("{}.actual.png", name));
+            write_image(&actual_path, rendered);
+
+            panic!(
+                "{} differs from its golden image in {} pixels, what was rendered is at {}",
+                name,
+                differing_pixels,
+                actual_path.display()
+            );
+        }
+    }
+
+    #[test]
+    fn shapes_match_golden() {
+        let rendered = render_scene(|context| {
+            let painter = context.layer_painter(egui::LayerId::background());
+
+            painter.rect_filled(
+                Rect::from_min_size(Pos2::new(8.0, 8.0), Vec2::new(64.0, 40.0)),
+                6.0,
+                Color32::from_rgb(0xd0, 0x40, 0x30),
+            );
+            painter.circle(
+                Pos2::new(116.0, 36.0),
+                24.0,
+                Color32::from_rgba_unmultiplied(0x30, 0x90, 0xe0, 0xa0),
+                Stroke::new(2.0, Color32::WHITE),
+            );
+            painter.line_segment(
+                [Pos2::new(8.0, 112.0), Pos2::new(152.0, 64.0)],
+                Stroke::new(3.0, Color32::from_rgb(0x40, 0xc0, 0x50)),
+            );
+        });
+
+        assert_matches_golden("shapes", &rendered);
+    }
+
+    #[test]
+    fn widgets_match_golden() {
+        let rendered = render_scene(|context| {
+            egui::CentralPanel::default().show(context, |ui| {
+                ui.heading("Multiemu");
+                ui.label("Golden image");
+                let _ = ui.button("Button");
+                ui.checkbox(&mut true, "Checkbox");
+                ui.add(egui::Slider::new(&mut 42, 0..=100));
+            });
+        });
+
+        assert_matches_golden("widgets", &rendered);
+    }
+}
@@ -0,0 +1,167 @@
+//! What the host processor can do beyond the baseline we are compiled for
+//!
+//! Hot loops are written once and compiled again for each feature worth having with `multiversion!`, which picks the
+//! best copy the host can run, so the baseline target never has to be raised
+
+use std::{fmt::Display, sync::LazyLock};
+
+static CPU_FEATURES: LazyLock<CpuFeatures> = LazyLock::new(CpuFeatures::detect);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CpuFeatures {
+    pub sse2: bool,
+    pub avx2: bool,
+    pub neon: bool,
+}
+
+impl CpuFeatures {
+    #[allow(unreachable_code)]
+    fn detect() -> Self {
+        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+        return Self {
+            sse2: std::arch::is_x86_feature_detected!("sse2"),
+            avx2: std::arch::is_x86_feature_detected!("avx2"),
+            neon: false,
+        };
+
+        #[cfg(target_arch = "aarch64")]
+        return Self {
+            sse2: false,
+            avx2: false,
+            neon: std::arch::is_aarch64_feature_detected!("neon"),
+        };
+
+        // Everything else gets the baseline
+        Self::default()
+    }
+}
+
+impl Display for CpuFeatures {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let features: Vec<_> = [
+            ("SSE2", self.sse2),
+            ("AVX2", self.avx2),
+            ("NEON", self.neon),
+        ]
+        .into_iter()
+        .filter_map(|(name, present)| present.then_some(name))
+        .collect();
+
+        if features.is_empty() {
+            write!(f, "nothing past the baseline")
+        } else {
+            write!(f, "{}", features.join(", "))
+        }
+    }
+}
+
+/// Checked once, the first time anything asks
+pub fn cpu_features() -> CpuFeatures {
+    *CPU_FEATURES
+}
+
+/// Compiles a function again for every feature level worth having on this architecture, calling the best one the host
+/// supports
+///
+/// The body is inlined into each copy, so write it as plain loops the compiler can vectorize. Generic functions are not
+/// supported. The copies themselves live in a module with the same name as the function, so tests can call each one
+macro_rules! multiversion {
+    (
+        $(#[$meta:meta])*
+        $visibility:vis fn $name:ident($($argument:ident: $argument_type:ty),* $(,)?) $(-> $return_type:ty)?
+        $body:block
+    ) => {
+        $(#[$meta])*
+        $visibility fn $name($($argument: $argument_type),*) $(-> $return_type)? {
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            {
+                let features = $crate::host_cpu::cpu_features();
+
+                // The features were checked for at runtime
+                if features.avx2 {
+                    return unsafe { $name::avx2($($argument),*) };
+                }
+
+                if features.sse2 {
+                    return unsafe { $name::sse2($($argument),*) };
+                }
+            }
+
+            #[cfg(target_arch = "aarch64")]
+            {
+                // The feature was checked for at runtime
+                if $crate::host_cpu::cpu_features().neon {
+                    return unsafe { $name::neon($($argument),*) };
+                }
+            }
+
+            $name::baseline($($argument),*)
+        }
+
+        mod $name {
+            #[allow(unused_imports)]
+            use super::*;
+
+            #[inline(always)]
+            pub(super) fn baseline($($argument: $argument_type),*) $(-> $return_type)? $body
+
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            #[target_feature(enable = "avx2")]
+            pub(super) unsafe fn avx2($($argument: $argument_type),*) $(-> $return_type)? {
+                baseline($($argument),*)
+            }
+
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            #[target_feature(enable = "sse2")]
+            pub(super) unsafe fn sse2($($argument: $argument_type),*) $(-> $return_type)? {
+                baseline($($argument),*)
+            }
+
+            #[cfg(target_arch = "aarch64")]
+            #[target_feature(enable = "neon")]
+            pub(super) unsafe fn neon($($argument: $argument_type),*) $(-> $return_type)? {
+                baseline($($argument),*)
+            }
+        }
+    };
+}
+pub(crate) use multiversion;
+
+#[cfg(test)]
+mod test {
+    use super::cpu_features;
+
+    multiversion! {
+        fn sum(values: &[u32]) -> u32 {
+            values.iter().sum()
+        }
+    }
+
+    #[test]
+    fn every_version_agrees() {
+        let values = Vec::from_iter(0..1000);
+        let expected = sum::baseline(&values);
+
+        assert_eq!(expected, values.iter().sum::<u32>());
+        assert_eq!(sum(&values), expected);
+
+        // Only the copies this host can run are checked
+        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+        {
+            let features = cpu_features();
+
+            if features.avx2 {
+                assert_eq!(unsafe { sum::avx2(&values) }, expected);
+            }
+
+            if features.sse2 {
+                assert_eq!(unsafe { sum::sse2(&values) }, expected);
+            }
+        }
+
+        #[cfg(target_arch = "aarch64")]
+        if cpu_features().neon {
+            assert_eq!(unsafe { sum::neon(&values) }, expected);
+        }
+    }
+}
@@ -7,9 +7,34 @@ use strum::EnumIter;
 #[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq, Hash, EnumIter)]
 pub enum Hotkey {
     ToggleMenu,
+    /// Toggle running at double speed
     FastForward,
+    /// Toggle running at four times the speed
+    UltraFastForward,
+    /// Toggle running at half speed
+    SlowMotion,
     LoadSnapshot,
     SaveSnapshot,
+    /// Switch between running normally and only running a frame at a time
+    ToggleFrameAdvance,
+    /// Run a single frame while frame advancing
+    FrameAdvance,
+    /// Switch to the next power profile
+    CyclePowerProfile,
+    /// Start or stop the tape deck
+    TapePlay,
+    /// Toggle running flat out while the tape plays
+    TapeFastLoad,
+    /// Type whatever is on the clipboard into the emulated keyboard
+    PasteClipboard,
+    /// Step back through recent play for as long as it is held
+    Rewind,
+    /// Close the running machine and go back to the menu
+    QuitGame,
+    /// Press the reset button of the running machine
+    SoftReset,
+    /// Power cycle the running machine
+    HardReset,
 }
 
 pub static DEFAULT_HOTKEYS: LazyLock<IndexMap<BTreeSet<Input>, Hotkey>> = LazyLock::new(|| {
@@ -62,6 +87,168 @@ pub static DEFAULT_HOTKEYS: LazyLock<IndexMap<BTreeSet<Input>, Hotkey>> = LazyLo
             [Input::Keyboard(KeyboardInput::F4)].into(),
             Hotkey::LoadSnapshot,
         ),
+        (
+            [
+                Input::Gamepad(GamepadInput::Mode),
+                Input::Gamepad(GamepadInput::FPadDown),
+            ]
+            .into(),
+            Hotkey::ToggleFrameAdvance,
+        ),
+        (
+            [Input::Keyboard(KeyboardInput::F5)].into(),
+            Hotkey::ToggleFrameAdvance,
+        ),
+        (
+            [
+                Input::Gamepad(GamepadInput::Mode),
+                Input::Gamepad(GamepadInput::FPadRight),
+            ]
+            .into(),
+            Hotkey::FrameAdvance,
+        ),
+        (
+            [Input::Keyboard(KeyboardInput::F6)].into(),
+            Hotkey::FrameAdvance,
+        ),
+        (
+            [
+                Input::Gamepad(GamepadInput::Mode),
+                Input::Gamepad(GamepadInput::RightTrigger),
+            ]
+            .into(),
+            Hotkey::UltraFastForward,
+        ),
+        (
+            [Input::Keyboard(KeyboardInput::F7)].into(),
+            Hotkey::UltraFastForward,
+        ),
+        (
+            [
+                Input::Gamepad(GamepadInput::Mode),
+                Input::Gamepad(GamepadInput::LeftTrigger),
+            ]
+            .into(),
+            Hotkey::SlowMotion,
+        ),
+        (
+            [Input::Keyboard(KeyboardInput::F8)].into(),
+            Hotkey::SlowMotion,
+        ),
+        (
+            [
+                Input::Gamepad(GamepadInput::Mode),
+                Input::Gamepad(GamepadInput::LeftThumb),
+            ]
+            .into(),
+            Hotkey::CyclePowerProfile,
+        ),
+        (
+            [Input::Keyboard(KeyboardInput::F9)].into(),
+            Hotkey::CyclePowerProfile,
+        ),
+        (
+            [
+                Input::Gamepad(GamepadInput::Mode),
+                Input::Gamepad(GamepadInput::RightThumb),
+            ]
+            .into(),
+            Hotkey::TapePlay,
+        ),
+        (
+            [Input::Keyboard(KeyboardInput::F10)].into(),
+            Hotkey::TapePlay,
+        ),
+        (
+            [Input::Keyboard(KeyboardInput::F11)].into(),
+            Hotkey::TapeFastLoad,
+        ),
+        (
+            [Input::Keyboard(KeyboardInput::F12)].into(),
+            Hotkey::PasteClipboard,
+        ),
+        (
+            [
+                Input::Gamepad(GamepadInput::Mode),
+                Input::Gamepad(GamepadInput::LeftSecondaryTrigger),
+            ]
+            .into(),
+            Hotkey::Rewind,
+        ),
+        (
+            [Input::Keyboard(KeyboardInput::Backspace)].into(),
+            Hotkey::Rewind,
+        ),
+        // Two keys so it is hard to hit by accident
+        (
+            [
+                Input::Keyboard(KeyboardInput::ControlLeft),
+                Input::Keyboard(KeyboardInput::KeyQ),
+            ]
+            .into(),
+            Hotkey::QuitGame,
+        ),
+        (
+            [
+                Input::Keyboard(KeyboardInput::ControlLeft),
+                Input::Keyboard(KeyboardInput::KeyR),
+            ]
+            .into(),
+            Hotkey::SoftReset,
+        ),
+        (
+            [
+                Input::Keyboard(KeyboardInput::ControlLeft),
+                Input::Keyboard(KeyboardInput::ShiftLeft),
+                Input::Keyboard(KeyboardInput::KeyR),
+            ]
+            .into(),
+            Hotkey::HardReset,
+        ),
     ]
     .into()
 });
+
+/// Finds the hotkey that pressing `pressed` completed, preferring the combination with the most inputs
+pub fn triggered_hotkey(
+    hotkeys: &IndexMap<BTreeSet<Input>, Hotkey>,
+    held: &BTreeSet<Input>,
+    pressed: Input,
+) -> Option<Hotkey> {
+    hotkeys
+        .iter()
+        .filter(|(combination, _)| combination.contains(&pressed) && combination.is_subset(held))
+        .max_by_key(|(combination, _)| combination.len())
+        .map(|(_, hotkey)| *hotkey)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn hotkey_triggering() {
+        let held = BTreeSet::from([
+            Input::Gamepad(GamepadInput::Mode),
+            Input::Gamepad(GamepadInput::FPadRight),
+        ]);
+
+        assert_eq!(
+            triggered_hotkey(
+                &DEFAULT_HOTKEYS,
+                &held,
+                Input::Gamepad(GamepadInput::FPadRight)
+            ),
+            Some(Hotkey::FrameAdvance)
+        );
+        // Every input of a combination has to be held
+        assert_eq!(
+            triggered_hotkey(
+                &DEFAULT_HOTKEYS,
+                &BTreeSet::from([Input::Gamepad(GamepadInput::FPadRight)]),
+                Input::Gamepad(GamepadInput::FPadRight)
+            ),
+            None
+        );
+    }
+}
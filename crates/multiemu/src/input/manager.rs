@@ -4,9 +4,15 @@ use crate::{
     rom::system::GameSystem,
 };
 
-use super::{EmulatedGamepadId, GamepadId, Input, InputState};
+use super::{paste::Paste, EmulatedGamepadId, GamepadId, Input, InputState};
 use dashmap::DashMap;
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+};
 
 #[derive(Debug)]
 /// Stores what each gamepad is cached to be at right now
@@ -20,10 +26,24 @@ pub struct InputManager {
     pub gamepad_types: HashMap<EmulatedGamepadTypeId, EmulatedGamepadMetadata>,
     emulated_gamepads: DashMap<EmulatedGamepadId, EmulatedGamepadState>,
     real_to_emulated_gamepad_mappings: DashMap<GamepadId, EmulatedGamepadId>,
+    /// How many times the emulated software read its inputs this frame
+    polls: AtomicU64,
+    /// Frames where the emulated software never read its inputs
+    lag_frames: AtomicU64,
+    /// Text still being typed in
+    paste: Mutex<Option<Paste>>,
 }
 
 impl InputManager {
+    /// Meant for components, every call counts as the emulated software polling its inputs
     pub fn get_input(&self, port: EmulatedGamepadId, input: Input) -> InputState {
+        self.polls.fetch_add(1, Ordering::Relaxed);
+
+        self.peek_input(port, input)
+    }
+
+    /// Meant for the frontend, reads an input without it counting as a poll
+    pub fn peek_input(&self, port: EmulatedGamepadId, input: Input) -> InputState {
         self.emulated_gamepads
             .get(&port)
             .unwrap()
@@ -37,37 +57,143 @@ impl InputManager {
         let global_config = GLOBAL_CONFIG.read().unwrap();
 
         // Find out which real controller is hooked up to which emulated one
-        if let Some(mut emulated_gamepad_state) = self
+        let Some(port) = self
             .real_to_emulated_gamepad_mappings
             .get(&id)
-            .and_then(|entry| self.emulated_gamepads.get_mut(entry.key()))
-        {
-            let metadata = self
-                .gamepad_types
-                .get(&emulated_gamepad_state.kind)
-                .unwrap();
-
-            // Translate the input according to the global config
-            let Some(translated_input) = global_config
-                .gamepad_configs
-                .get(&system)
-                .and_then(|emulated_gamepad_infos| {
-                    emulated_gamepad_infos.get(&emulated_gamepad_state.kind)
-                })
-                .and_then(|gamepad_specific_mappings| gamepad_specific_mappings.get(&input))
-            else {
-                tracing::warn!("Unbound input {:?}", input);
-                return;
-            };
-
-            if metadata.present_inputs.contains(translated_input) {
-                emulated_gamepad_state
-                    .state
-                    .insert(*translated_input, state);
-            } else {
-                tracing::warn!("We have a bound from {:?} to {:?}, but emulated gamepad doesn't support this input", input, translated_input);
+            .map(|entry| *entry.value())
+        else {
+            return;
+        };
+
+        let split_bindings = global_config
+            .keyboard_splits
+            .get(&system)
+            .filter(|_| matches!(input, Input::Keyboard(_)));
+
+        // Translate the input according to the global config
+        let translated = match split_bindings {
+            // A keyboard shared between players goes to whoever the key belongs to
+            Some(split_bindings) => {
+                split_bindings
+                    .iter()
+                    .enumerate()
+                    .find_map(|(player, bindings)| {
+                        Some((
+                            EmulatedGamepadId::try_from(player).ok()?,
+                            *bindings.get(&input)?,
+                        ))
+                    })
+            }
+            None => self
+                .emulated_gamepads
+                .get(&port)
+                .and_then(|emulated_gamepad_state| {
+                    global_config
+                        .gamepad_configs
+                        .get(&system)?
+                        .get(&emulated_gamepad_state.kind)?
+                        .get(&input)
+                        .map(|translated_input| (port, *translated_input))
+                }),
+        };
+
+        let Some((port, translated_input)) = translated else {
+            tracing::warn!("Unbound input {:?}", input);
+            return;
+        };
+
+        let Some(mut emulated_gamepad_state) = self.emulated_gamepads.get_mut(&port) else {
+            return;
+        };
+
+        let metadata = self
+            .gamepad_types
+            .get(&emulated_gamepad_state.kind)
+            .unwrap();
+
+        if metadata.present_inputs.contains(&translated_input) {
+            emulated_gamepad_state.state.insert(translated_input, state);
+        } else {
+            tracing::warn!("We have a bound from {:?} to {:?}, but emulated gamepad doesn't support this input", input, translated_input);
+        }
+    }
+
+    /// Sets an emulated input directly, skipping the bindings, for when inputs come from something like a movie
+    pub fn set_emulated_input(&self, port: EmulatedGamepadId, input: Input, state: InputState) {
+        if let Some(mut emulated_gamepad_state) = self.emulated_gamepads.get_mut(&port) {
+            emulated_gamepad_state.state.insert(input, state);
+        }
+    }
+
+    /// Every emulated gamepad and what kind it is
+    pub fn emulated_gamepads(&self) -> Vec<(EmulatedGamepadId, EmulatedGamepadTypeId)> {
+        let mut emulated_gamepads: Vec<_> = self
+            .emulated_gamepads
+            .iter()
+            .map(|entry| (*entry.key(), entry.value().kind.clone()))
+            .collect();
+        emulated_gamepads.sort();
+
+        emulated_gamepads
+    }
+
+    /// Marks the end of an emulated frame, returning if it was a lag frame where nothing polled input
+    pub fn end_frame(&self) -> bool {
+        let lagged = self.polls.swap(0, Ordering::Relaxed) == 0;
+
+        if lagged {
+            self.lag_frames.fetch_add(1, Ordering::Relaxed);
+        }
+
+        let mut paste_guard = self.paste.lock().unwrap();
+        if let Some(paste) = paste_guard.as_mut() {
+            for (key, pressed) in paste.step() {
+                self.insert_input(
+                    paste.system,
+                    paste.gamepad_id,
+                    Input::Keyboard(key),
+                    InputState::Digital(pressed),
+                );
+            }
+
+            if paste.is_finished() {
+                *paste_guard = None;
             }
         }
+
+        lagged
+    }
+
+    /// Types `text` in as if it came from the real keyboard `gamepad_id`, holding each key for `key_frames` frames
+    ///
+    /// Goes after whatever is still being typed
+    pub fn paste_text(
+        &self,
+        system: GameSystem,
+        gamepad_id: GamepadId,
+        text: &str,
+        key_frames: u32,
+    ) {
+        let mut paste_guard = self.paste.lock().unwrap();
+
+        match paste_guard.as_mut() {
+            Some(paste) => paste.push_text(text),
+            None => *paste_guard = Some(Paste::new(system, gamepad_id, text, key_frames)),
+        }
+    }
+
+    pub fn is_pasting(&self) -> bool {
+        self.paste.lock().unwrap().is_some()
+    }
+
+    pub fn lag_frames(&self) -> u64 {
+        self.lag_frames.load(Ordering::Relaxed)
+    }
+
+    /// Starts counting lag frames from zero, forgetting any polls from the frame in progress
+    pub fn reset_lag_frames(&self) {
+        self.polls.store(0, Ordering::Relaxed);
+        self.lag_frames.store(0, Ordering::Relaxed);
     }
 
     pub fn set_real_to_emulated_mapping(&self, gamepad_id: GamepadId, index: EmulatedGamepadId) {
@@ -97,3 +223,28 @@ impl InputManager {
         self.gamepad_types.insert(kind, metadata);
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::input::keyboard::KeyboardInput;
+
+    #[test]
+    fn unpolled_frames_lag() {
+        let mut input_manager = InputManager::default();
+        input_manager.register_emulated_gamepad(0, EmulatedGamepadTypeId::new("Test"));
+        let input = Input::Keyboard(KeyboardInput::KeyA);
+
+        input_manager.get_input(0, input);
+        assert!(!input_manager.end_frame());
+
+        // The frontend looking at inputs is not the emulated software polling them
+        input_manager.peek_input(0, input);
+        assert!(input_manager.end_frame());
+        assert!(input_manager.end_frame());
+        assert_eq!(input_manager.lag_frames(), 2);
+
+        input_manager.reset_lag_frames();
+        assert_eq!(input_manager.lag_frames(), 0);
+    }
+}
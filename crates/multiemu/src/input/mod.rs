@@ -7,6 +7,9 @@ pub mod gamepad;
 pub mod hotkey;
 pub mod keyboard;
 pub mod manager;
+pub mod paste;
+pub mod profile;
+pub mod registry;
 
 #[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum Input {
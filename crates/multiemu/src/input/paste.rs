@@ -0,0 +1,194 @@
+//! Typing text into an emulated keyboard as if someone was pressing the keys
+
+use super::{keyboard::KeyboardInput, GamepadId};
+use crate::rom::system::GameSystem;
+use std::collections::VecDeque;
+
+/// Frames each pasted key is held for, and then let go for, unless configured otherwise
+pub const DEFAULT_PASTE_KEY_FRAMES: u32 = 3;
+
+/// The keys a US layout keyboard needs held down to type `character`
+pub fn keys_for_char(character: char) -> Option<Vec<KeyboardInput>> {
+    let (key, shifted) = match character {
+        'a'..='z' | 'A'..='Z' => (
+            LETTERS[(character.to_ascii_lowercase() as u8 - b'a') as usize],
+            character.is_ascii_uppercase(),
+        ),
+        '0'..='9' => (DIGITS[(character as u8 - b'0') as usize], false),
+        ' ' => (KeyboardInput::Space, false),
+        '\n' => (KeyboardInput::Enter, false),
+        '\t' => (KeyboardInput::Tab, false),
+        '-' => (KeyboardInput::Minus, false),
+        '=' => (KeyboardInput::Equal, false),
+        '[' => (KeyboardInput::BracketLeft, false),
+        ']' => (KeyboardInput::BracketRight, false),
+        '\\' => (KeyboardInput::Backslash, false),
+        ';' => (KeyboardInput::Semicolon, false),
+        '\'' => (KeyboardInput::Quote, false),
+        ',' => (KeyboardInput::Comma, false),
+        '.' => (KeyboardInput::Period, false),
+        '/' => (KeyboardInput::Slash, false),
+        '`' => (KeyboardInput::Backquote, false),
+        '!' => (KeyboardInput::Digit1, true),
+        '@' => (KeyboardInput::Digit2, true),
+        '#' => (KeyboardInput::Digit3, true),
+        '$' => (KeyboardInput::Digit4, true),
+        '%' => (KeyboardInput::Digit5, true),
+        '^' => (KeyboardInput::Digit6, true),
+        '&' => (KeyboardInput::Digit7, true),
+        '*' => (KeyboardInput::Digit8, true),
+        '(' => (KeyboardInput::Digit9, true),
+        ')' => (KeyboardInput::Digit0, true),
+        '_' => (KeyboardInput::Minus, true),
+        '+' => (KeyboardInput::Equal, true),
+        '{' => (KeyboardInput::BracketLeft, true),
+        '}' => (KeyboardInput::BracketRight, true),
+        '|' => (KeyboardInput::Backslash, true),
+        ':' => (KeyboardInput::Semicolon, true),
+        '"' => (KeyboardInput::Quote, true),
+        '<' => (KeyboardInput::Comma, true),
+        '>' => (KeyboardInput::Period, true),
+        '?' => (KeyboardInput::Slash, true),
+        '~' => (KeyboardInput::Backquote, true),
+        _ => return None,
+    };
+
+    Some(if shifted {
+        vec![KeyboardInput::ShiftLeft, key]
+    } else {
+        vec![key]
+    })
+}
+
+const LETTERS: [KeyboardInput; 26] = [
+    KeyboardInput::KeyA,
+    KeyboardInput::KeyB,
+    KeyboardInput::KeyC,
+    KeyboardInput::KeyD,
+    KeyboardInput::KeyE,
+    KeyboardInput::KeyF,
+    KeyboardInput::KeyG,
+    KeyboardInput::KeyH,
+    KeyboardInput::KeyI,
+    KeyboardInput::KeyJ,
+    KeyboardInput::KeyK,
+    KeyboardInput::KeyL,
+    KeyboardInput::KeyM,
+    KeyboardInput::KeyN,
+    KeyboardInput::KeyO,
+    KeyboardInput::KeyP,
+    KeyboardInput::KeyQ,
+    KeyboardInput::KeyR,
+    KeyboardInput::KeyS,
+    KeyboardInput::KeyT,
+    KeyboardInput::KeyU,
+    KeyboardInput::KeyV,
+    KeyboardInput::KeyW,
+    KeyboardInput::KeyX,
+    KeyboardInput::KeyY,
+    KeyboardInput::KeyZ,
+];
+
+const DIGITS: [KeyboardInput; 10] = [
+    KeyboardInput::Digit0,
+    KeyboardInput::Digit1,
+    KeyboardInput::Digit2,
+    KeyboardInput::Digit3,
+    KeyboardInput::Digit4,
+    KeyboardInput::Digit5,
+    KeyboardInput::Digit6,
+    KeyboardInput::Digit7,
+    KeyboardInput::Digit8,
+    KeyboardInput::Digit9,
+];
+
+/// Text being typed in, stepped once per emulated frame so it types at the same rate at any speed
+#[derive(Debug)]
+pub struct Paste {
+    /// Who the keys look like they come from, so they go through the bindings like real ones
+    pub system: GameSystem,
+    pub gamepad_id: GamepadId,
+    /// Key combinations left to type, one per character
+    chords: VecDeque<Vec<KeyboardInput>>,
+    held: Option<Vec<KeyboardInput>>,
+    key_frames: u32,
+    /// Frames before the next press or release
+    frames_left: u32,
+}
+
+impl Paste {
+    /// Characters that cannot be typed are left out
+    pub fn new(system: GameSystem, gamepad_id: GamepadId, text: &str, key_frames: u32) -> Self {
+        Self {
+            system,
+            gamepad_id,
+            chords: text.chars().filter_map(keys_for_char).collect(),
+            held: None,
+            key_frames: key_frames.max(1),
+            frames_left: 0,
+        }
+    }
+
+    pub fn push_text(&mut self, text: &str) {
+        self.chords.extend(text.chars().filter_map(keys_for_char));
+    }
+
+    /// Keys pressed or released this frame
+    pub fn step(&mut self) -> Vec<(KeyboardInput, bool)> {
+        if self.frames_left > 0 {
+            self.frames_left -= 1;
+            return Vec::new();
+        }
+
+        self.frames_left = self.key_frames - 1;
+
+        match self.held.take() {
+            Some(chord) => chord.into_iter().map(|key| (key, false)).collect(),
+            None => match self.chords.pop_front() {
+                Some(chord) => {
+                    let pressed = chord.iter().map(|key| (*key, true)).collect();
+                    self.held = Some(chord);
+
+                    pressed
+                }
+                None => Vec::new(),
+            },
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.held.is_none() && self.chords.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn keys_are_held_then_released() {
+        let mut paste = Paste::new(GameSystem::Unknown, 0, "A\r1", 2);
+
+        assert_eq!(
+            paste.step(),
+            [
+                (KeyboardInput::ShiftLeft, true),
+                (KeyboardInput::KeyA, true)
+            ]
+        );
+        assert!(paste.step().is_empty());
+        assert_eq!(
+            paste.step(),
+            [
+                (KeyboardInput::ShiftLeft, false),
+                (KeyboardInput::KeyA, false)
+            ]
+        );
+        assert!(paste.step().is_empty());
+        // The carriage return is skipped
+        assert_eq!(paste.step(), [(KeyboardInput::Digit1, true)]);
+        paste.step();
+        assert_eq!(paste.step(), [(KeyboardInput::Digit1, false)]);
+        assert!(paste.is_finished());
+    }
+}
@@ -0,0 +1,83 @@
+use super::Input;
+use crate::{
+    component::input::EmulatedGamepadTypeId,
+    config::{GlobalConfig, STORAGE_DIRECTORY},
+    rom::system::GameSystem,
+};
+use indexmap::IndexMap;
+use ron::ser::PrettyConfig;
+use serde::{Deserialize, Serialize};
+use std::{
+    error::Error,
+    fs::{create_dir_all, File},
+    path::{Path, PathBuf},
+    sync::LazyLock,
+};
+
+pub const INPUT_PROFILE_EXTENSION: &str = "ron";
+
+/// Where the controls menu exports to and starts looking for profiles to import
+pub static INPUT_PROFILE_DIRECTORY: LazyLock<PathBuf> =
+    LazyLock::new(|| STORAGE_DIRECTORY.join("input_profiles"));
+
+/// A controller mapping for a single emulated gamepad type that can be handed to someone else
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct InputProfile {
+    pub system: GameSystem,
+    pub gamepad_type: EmulatedGamepadTypeId,
+    pub bindings: IndexMap<Input, Input>,
+}
+
+impl InputProfile {
+    /// Grabs the mapping the user currently has configured
+    pub fn from_config(
+        global_config: &GlobalConfig,
+        system: GameSystem,
+        gamepad_type: EmulatedGamepadTypeId,
+    ) -> Option<Self> {
+        let bindings = global_config
+            .gamepad_configs
+            .get(&system)?
+            .get(&gamepad_type)?
+            .clone();
+
+        Some(Self {
+            system,
+            gamepad_type,
+            bindings,
+        })
+    }
+
+    /// Replaces the mapping for this profiles system and gamepad type
+    pub fn apply_to_config(self, global_config: &mut GlobalConfig) {
+        global_config
+            .gamepad_configs
+            .entry(self.system)
+            .or_default()
+            .insert(self.gamepad_type, self.bindings);
+    }
+
+    /// Where exporting the mapping for this system and gamepad type from the menu puts it
+    pub fn path(system: GameSystem, gamepad_type: &EmulatedGamepadTypeId) -> PathBuf {
+        INPUT_PROFILE_DIRECTORY
+            .join(format!("{} - {}", system, gamepad_type))
+            .with_extension(INPUT_PROFILE_EXTENSION)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), Box<dyn Error>> {
+        let path = path.as_ref();
+        create_dir_all(path.parent().unwrap_or(Path::new("")))?;
+
+        let profile_file = File::create(path)?;
+        ron::ser::to_writer_pretty(profile_file, self, PrettyConfig::default())?;
+
+        Ok(())
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Box<dyn Error>> {
+        let profile_file = File::open(path)?;
+        let profile = ron::de::from_reader(profile_file)?;
+
+        Ok(profile)
+    }
+}
@@ -0,0 +1,506 @@
+//! Gamepad types more than one component or system hands out, defined once here so they all agree on what the pad has
+//! and how it is bound by default
+
+use super::{gamepad::GamepadInput, keyboard::KeyboardInput, Input};
+use crate::component::input::{EmulatedGamepadMetadata, EmulatedGamepadTypeId};
+use std::{collections::HashMap, sync::LazyLock};
+
+pub const NES_GAMEPAD_TYPE: EmulatedGamepadTypeId = EmulatedGamepadTypeId::new("NES Controller");
+pub const SNES_GAMEPAD_TYPE: EmulatedGamepadTypeId = EmulatedGamepadTypeId::new("SNES Controller");
+pub const GENESIS_3_BUTTON_GAMEPAD_TYPE: EmulatedGamepadTypeId =
+    EmulatedGamepadTypeId::new("Genesis 3 Button Controller");
+pub const GENESIS_6_BUTTON_GAMEPAD_TYPE: EmulatedGamepadTypeId =
+    EmulatedGamepadTypeId::new("Genesis 6 Button Controller");
+pub const CHIP8_KEYPAD_GAMEPAD_TYPE: EmulatedGamepadTypeId =
+    EmulatedGamepadTypeId::new("Chip8 Keypad");
+
+/// Where a button goes on a drawing of the pad, for the remapping ui
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ButtonArtwork {
+    pub input: Input,
+    /// What is printed on the real button
+    pub label: &'static str,
+    /// From the top left of the pad, 0.0 to 1.0 on both axes
+    pub position: [f32; 2],
+}
+
+#[derive(Debug, Clone)]
+pub struct GamepadTypeDefinition {
+    pub id: EmulatedGamepadTypeId,
+    /// Every input the pad has, laid out like the real thing
+    pub artwork: Vec<ButtonArtwork>,
+    pub default_bindings: HashMap<Input, Input>,
+    /// Keyboard only bindings for each side of a keyboard shared between two players, see [KEYBOARD_CLUSTERS]
+    pub split_bindings: [HashMap<Input, Input>; 2],
+}
+
+impl GamepadTypeDefinition {
+    /// What [crate::machine::ComponentBuilder::set_input] takes for this type
+    pub fn registration(&self) -> (EmulatedGamepadTypeId, EmulatedGamepadMetadata) {
+        (
+            self.id.clone(),
+            EmulatedGamepadMetadata {
+                present_inputs: self.artwork.iter().map(|button| button.input).collect(),
+                default_bindings: self.default_bindings.clone(),
+            },
+        )
+    }
+}
+
+pub static GAMEPAD_TYPES: LazyLock<HashMap<EmulatedGamepadTypeId, GamepadTypeDefinition>> =
+    LazyLock::new(|| {
+        [
+            nes_gamepad(),
+            snes_gamepad(),
+            genesis_3_button_gamepad(),
+            genesis_6_button_gamepad(),
+            chip8_keypad(),
+        ]
+        .into_iter()
+        .map(|definition| (definition.id.clone(), definition))
+        .collect()
+    });
+
+/// Panics for types that were never registered here, since asking for one is always a typo
+pub fn gamepad_type(id: &EmulatedGamepadTypeId) -> &'static GamepadTypeDefinition {
+    GAMEPAD_TYPES
+        .get(id)
+        .unwrap_or_else(|| panic!("{} is not a known gamepad type", id))
+}
+
+/// One side of a keyboard shared between two players
+#[derive(Debug)]
+pub struct KeyboardCluster {
+    /// Up, down, left and right
+    pub dpad: [KeyboardInput; 4],
+    /// Handed out to every other button in the order the pad draws them
+    pub buttons: [KeyboardInput; 8],
+    pub select: KeyboardInput,
+    pub start: KeyboardInput,
+    /// For pads that are a grid of keys instead, row by row
+    pub keypad: [[KeyboardInput; 4]; 4],
+}
+
+/// The first player gets the WASD side, the second gets the numpad
+pub static KEYBOARD_CLUSTERS: [KeyboardCluster; 2] = [
+    KeyboardCluster {
+        dpad: [
+            KeyboardInput::KeyW,
+            KeyboardInput::KeyS,
+            KeyboardInput::KeyA,
+            KeyboardInput::KeyD,
+        ],
+        buttons: [
+            KeyboardInput::KeyF,
+            KeyboardInput::KeyG,
+            KeyboardInput::KeyR,
+            KeyboardInput::KeyT,
+            KeyboardInput::KeyV,
+            KeyboardInput::KeyB,
+            KeyboardInput::KeyQ,
+            KeyboardInput::KeyE,
+        ],
+        select: KeyboardInput::Digit1,
+        start: KeyboardInput::Digit2,
+        keypad: [
+            [
+                KeyboardInput::Digit1,
+                KeyboardInput::Digit2,
+                KeyboardInput::Digit3,
+                KeyboardInput::Digit4,
+            ],
+            [
+                KeyboardInput::KeyQ,
+                KeyboardInput::KeyW,
+                KeyboardInput::KeyE,
+                KeyboardInput::KeyR,
+            ],
+            [
+                KeyboardInput::KeyA,
+                KeyboardInput::KeyS,
+                KeyboardInput::KeyD,
+                KeyboardInput::KeyF,
+            ],
+            [
+                KeyboardInput::KeyZ,
+                KeyboardInput::KeyX,
+                KeyboardInput::KeyC,
+                KeyboardInput::KeyV,
+            ],
+        ],
+    },
+    KeyboardCluster {
+        dpad: [
+            KeyboardInput::Numpad8,
+            KeyboardInput::Numpad5,
+            KeyboardInput::Numpad4,
+            KeyboardInput::Numpad6,
+        ],
+        buttons: [
+            KeyboardInput::Numpad1,
+            KeyboardInput::Numpad2,
+            KeyboardInput::Numpad7,
+            KeyboardInput::Numpad9,
+            KeyboardInput::Numpad3,
+            KeyboardInput::Numpad0,
+            KeyboardInput::NumpadDivide,
+            KeyboardInput::NumpadMultiply,
+        ],
+        select: KeyboardInput::NumpadSubtract,
+        start: KeyboardInput::NumpadEnter,
+        keypad: [
+            [
+                KeyboardInput::Numpad7,
+                KeyboardInput::Numpad8,
+                KeyboardInput::Numpad9,
+                KeyboardInput::NumpadDivide,
+            ],
+            [
+                KeyboardInput::Numpad4,
+                KeyboardInput::Numpad5,
+                KeyboardInput::Numpad6,
+                KeyboardInput::NumpadMultiply,
+            ],
+            [
+                KeyboardInput::Numpad1,
+                KeyboardInput::Numpad2,
+                KeyboardInput::Numpad3,
+                KeyboardInput::NumpadSubtract,
+            ],
+            [
+                KeyboardInput::Numpad0,
+                KeyboardInput::NumpadDecimal,
+                KeyboardInput::NumpadEnter,
+                KeyboardInput::NumpadAdd,
+            ],
+        ],
+    },
+];
+
+/// Keyboard bindings for when there is more than one player, one per player, or [None] if the keyboard can't be split
+/// for this mix of pads
+pub fn split_keyboard_bindings<'a>(
+    gamepad_types: impl IntoIterator<Item = &'a EmulatedGamepadTypeId>,
+) -> Option<Vec<HashMap<Input, Input>>> {
+    let split_bindings: Vec<_> = gamepad_types
+        .into_iter()
+        .take(KEYBOARD_CLUSTERS.len())
+        .enumerate()
+        .map(|(player, id)| Some(GAMEPAD_TYPES.get(id)?.split_bindings[player].clone()))
+        .collect::<Option<_>>()?;
+
+    (split_bindings.len() > 1).then_some(split_bindings)
+}
+
+fn button(input: GamepadInput, label: &'static str, x: f32, y: f32) -> ButtonArtwork {
+    ButtonArtwork {
+        input: Input::Gamepad(input),
+        label,
+        position: [x, y],
+    }
+}
+
+fn dpad(x: f32, y: f32) -> [ButtonArtwork; 4] {
+    [
+        button(GamepadInput::DPadUp, "Up", x, y - 0.15),
+        button(GamepadInput::DPadDown, "Down", x, y + 0.15),
+        button(GamepadInput::DPadLeft, "Left", x - 0.08, y),
+        button(GamepadInput::DPadRight, "Right", x + 0.08, y),
+    ]
+}
+
+/// Host gamepads are bound straight through, and the keyboard to whatever is listed
+fn default_bindings(
+    artwork: &[ButtonArtwork],
+    keyboard: impl IntoIterator<Item = (KeyboardInput, GamepadInput)>,
+) -> HashMap<Input, Input> {
+    artwork
+        .iter()
+        .map(|button| (button.input, button.input))
+        .chain([
+            (
+                Input::Keyboard(KeyboardInput::ArrowUp),
+                Input::Gamepad(GamepadInput::DPadUp),
+            ),
+            (
+                Input::Keyboard(KeyboardInput::ArrowDown),
+                Input::Gamepad(GamepadInput::DPadDown),
+            ),
+            (
+                Input::Keyboard(KeyboardInput::ArrowLeft),
+                Input::Gamepad(GamepadInput::DPadLeft),
+            ),
+            (
+                Input::Keyboard(KeyboardInput::ArrowRight),
+                Input::Gamepad(GamepadInput::DPadRight),
+            ),
+        ])
+        .chain(
+            keyboard
+                .into_iter()
+                .map(|(key, input)| (Input::Keyboard(key), Input::Gamepad(input))),
+        )
+        .collect()
+}
+
+/// Puts a pad on one side of the keyboard, running out of keys leaves the rest unbound
+fn cluster_bindings(artwork: &[ButtonArtwork], cluster: &KeyboardCluster) -> HashMap<Input, Input> {
+    let mut buttons = cluster.buttons.iter().copied();
+
+    artwork
+        .iter()
+        .filter_map(|button| {
+            let Input::Gamepad(input) = button.input else {
+                return None;
+            };
+
+            let key = match input {
+                GamepadInput::DPadUp => cluster.dpad[0],
+                GamepadInput::DPadDown => cluster.dpad[1],
+                GamepadInput::DPadLeft => cluster.dpad[2],
+                GamepadInput::DPadRight => cluster.dpad[3],
+                GamepadInput::Select => cluster.select,
+                GamepadInput::Start => cluster.start,
+                _ => buttons.next()?,
+            };
+
+            Some((Input::Keyboard(key), button.input))
+        })
+        .collect()
+}
+
+fn nes_gamepad() -> GamepadTypeDefinition {
+    let artwork = Vec::from_iter(dpad(0.15, 0.5).into_iter().chain([
+        button(GamepadInput::Select, "SELECT", 0.4, 0.6),
+        button(GamepadInput::Start, "START", 0.55, 0.6),
+        button(GamepadInput::FPadDown, "B", 0.75, 0.6),
+        button(GamepadInput::FPadRight, "A", 0.88, 0.6),
+    ]));
+
+    GamepadTypeDefinition {
+        id: NES_GAMEPAD_TYPE,
+        default_bindings: default_bindings(
+            &artwork,
+            [
+                (KeyboardInput::KeyX, GamepadInput::FPadRight),
+                (KeyboardInput::KeyZ, GamepadInput::FPadDown),
+                (KeyboardInput::ShiftRight, GamepadInput::Select),
+                (KeyboardInput::Enter, GamepadInput::Start),
+            ],
+        ),
+        split_bindings: KEYBOARD_CLUSTERS
+            .each_ref()
+            .map(|cluster| cluster_bindings(&artwork, cluster)),
+        artwork,
+    }
+}
+
+fn snes_gamepad() -> GamepadTypeDefinition {
+    let artwork = Vec::from_iter(dpad(0.2, 0.5).into_iter().chain([
+        button(GamepadInput::LeftTrigger, "L", 0.2, 0.05),
+        button(GamepadInput::RightTrigger, "R", 0.8, 0.05),
+        button(GamepadInput::Select, "SELECT", 0.42, 0.55),
+        button(GamepadInput::Start, "START", 0.55, 0.55),
+        button(GamepadInput::FPadUp, "X", 0.8, 0.35),
+        button(GamepadInput::FPadLeft, "Y", 0.72, 0.5),
+        button(GamepadInput::FPadRight, "A", 0.88, 0.5),
+        button(GamepadInput::FPadDown, "B", 0.8, 0.65),
+    ]));
+
+    GamepadTypeDefinition {
+        id: SNES_GAMEPAD_TYPE,
+        default_bindings: default_bindings(
+            &artwork,
+            [
+                (KeyboardInput::KeyX, GamepadInput::FPadRight),
+                (KeyboardInput::KeyZ, GamepadInput::FPadDown),
+                (KeyboardInput::KeyS, GamepadInput::FPadUp),
+                (KeyboardInput::KeyA, GamepadInput::FPadLeft),
+                (KeyboardInput::KeyQ, GamepadInput::LeftTrigger),
+                (KeyboardInput::KeyW, GamepadInput::RightTrigger),
+                (KeyboardInput::ShiftRight, GamepadInput::Select),
+                (KeyboardInput::Enter, GamepadInput::Start),
+            ],
+        ),
+        split_bindings: KEYBOARD_CLUSTERS
+            .each_ref()
+            .map(|cluster| cluster_bindings(&artwork, cluster)),
+        artwork,
+    }
+}
+
+fn genesis_3_button_gamepad() -> GamepadTypeDefinition {
+    let artwork = Vec::from_iter(dpad(0.2, 0.5).into_iter().chain([
+        button(GamepadInput::Start, "START", 0.5, 0.35),
+        button(GamepadInput::FPadLeft, "A", 0.68, 0.6),
+        button(GamepadInput::FPadDown, "B", 0.78, 0.55),
+        button(GamepadInput::FPadRight, "C", 0.88, 0.5),
+    ]));
+
+    GamepadTypeDefinition {
+        id: GENESIS_3_BUTTON_GAMEPAD_TYPE,
+        default_bindings: default_bindings(
+            &artwork,
+            [
+                (KeyboardInput::KeyA, GamepadInput::FPadLeft),
+                (KeyboardInput::KeyS, GamepadInput::FPadDown),
+                (KeyboardInput::KeyD, GamepadInput::FPadRight),
+                (KeyboardInput::Enter, GamepadInput::Start),
+            ],
+        ),
+        split_bindings: KEYBOARD_CLUSTERS
+            .each_ref()
+            .map(|cluster| cluster_bindings(&artwork, cluster)),
+        artwork,
+    }
+}
+
+/// Same as the 3 button pad, with a top row and a mode button to fall back to acting like one
+fn genesis_6_button_gamepad() -> GamepadTypeDefinition {
+    let mut definition = genesis_3_button_gamepad();
+    definition.id = GENESIS_6_BUTTON_GAMEPAD_TYPE;
+
+    let extra_buttons = [
+        button(GamepadInput::Mode, "MODE", 0.85, 0.1),
+        button(GamepadInput::LeftTrigger, "X", 0.68, 0.4),
+        button(GamepadInput::FPadUp, "Y", 0.78, 0.35),
+        button(GamepadInput::RightTrigger, "Z", 0.88, 0.3),
+    ];
+    definition.default_bindings.extend(
+        extra_buttons
+            .iter()
+            .map(|button| (button.input, button.input))
+            .chain(
+                [
+                    (KeyboardInput::KeyQ, GamepadInput::LeftTrigger),
+                    (KeyboardInput::KeyW, GamepadInput::FPadUp),
+                    (KeyboardInput::KeyE, GamepadInput::RightTrigger),
+                    (KeyboardInput::Tab, GamepadInput::Mode),
+                ]
+                .map(|(key, input)| (Input::Keyboard(key), Input::Gamepad(input))),
+            ),
+    );
+    definition.artwork.extend(extra_buttons);
+    definition.split_bindings = KEYBOARD_CLUSTERS
+        .each_ref()
+        .map(|cluster| cluster_bindings(&definition.artwork, cluster));
+
+    definition
+}
+
+/// Interpreting the numbers on the keypad as "numpad", laid out like the COSMAC VIP had it
+fn chip8_keypad() -> GamepadTypeDefinition {
+    static LAYOUT: [[(KeyboardInput, &str, KeyboardInput); 4]; 4] = [
+        [
+            (KeyboardInput::Numpad1, "1", KeyboardInput::Digit1),
+            (KeyboardInput::Numpad2, "2", KeyboardInput::Digit2),
+            (KeyboardInput::Numpad3, "3", KeyboardInput::Digit3),
+            (KeyboardInput::KeyC, "C", KeyboardInput::Digit4),
+        ],
+        [
+            (KeyboardInput::Numpad4, "4", KeyboardInput::KeyQ),
+            (KeyboardInput::Numpad5, "5", KeyboardInput::KeyW),
+            (KeyboardInput::Numpad6, "6", KeyboardInput::KeyE),
+            (KeyboardInput::KeyD, "D", KeyboardInput::KeyR),
+        ],
+        [
+            (KeyboardInput::Numpad7, "7", KeyboardInput::KeyA),
+            (KeyboardInput::Numpad8, "8", KeyboardInput::KeyS),
+            (KeyboardInput::Numpad9, "9", KeyboardInput::KeyD),
+            (KeyboardInput::KeyE, "E", KeyboardInput::KeyF),
+        ],
+        [
+            (KeyboardInput::KeyA, "A", KeyboardInput::KeyZ),
+            (KeyboardInput::Numpad0, "0", KeyboardInput::KeyX),
+            (KeyboardInput::KeyB, "B", KeyboardInput::KeyC),
+            (KeyboardInput::KeyF, "F", KeyboardInput::KeyV),
+        ],
+    ];
+
+    let keys = LAYOUT.iter().enumerate().flat_map(|(row, keys)| {
+        keys.iter()
+            .enumerate()
+            .map(move |(column, key)| (row, column, *key))
+    });
+
+    GamepadTypeDefinition {
+        id: CHIP8_KEYPAD_GAMEPAD_TYPE,
+        artwork: keys
+            .clone()
+            .map(|(row, column, (input, label, _))| ButtonArtwork {
+                input: Input::Keyboard(input),
+                label,
+                position: [0.125 + column as f32 * 0.25, 0.125 + row as f32 * 0.25],
+            })
+            .collect(),
+        // The left side of a qwerty keyboard has the same shape
+        default_bindings: keys
+            .clone()
+            .map(|(_, _, (input, _, key))| (Input::Keyboard(key), Input::Keyboard(input)))
+            .collect(),
+        split_bindings: KEYBOARD_CLUSTERS.each_ref().map(|cluster| {
+            keys.clone()
+                .map(|(row, column, (input, _, _))| {
+                    (
+                        Input::Keyboard(cluster.keypad[row][column]),
+                        Input::Keyboard(input),
+                    )
+                })
+                .collect()
+        }),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn bindings_only_target_present_inputs() {
+        for definition in GAMEPAD_TYPES.values() {
+            let (_, metadata) = definition.registration();
+
+            assert_eq!(
+                metadata.present_inputs.len(),
+                definition.artwork.len(),
+                "{} has a button drawn twice",
+                definition.id
+            );
+
+            for input in metadata
+                .default_bindings
+                .values()
+                .chain(definition.split_bindings.iter().flat_map(HashMap::values))
+            {
+                assert!(
+                    metadata.present_inputs.contains(input),
+                    "{} binds to {:?} which it does not have",
+                    definition.id,
+                    input
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn split_sides_do_not_overlap() {
+        for definition in GAMEPAD_TYPES.values() {
+            let [first, second] = &definition.split_bindings;
+
+            assert!(!first.is_empty(), "{} has nothing bound", definition.id);
+            assert!(
+                first.keys().all(|key| !second.contains_key(key)),
+                "{} has a key on both sides of the keyboard",
+                definition.id
+            );
+        }
+
+        assert!(split_keyboard_bindings([&CHIP8_KEYPAD_GAMEPAD_TYPE]).is_none());
+        assert_eq!(
+            split_keyboard_bindings([&NES_GAMEPAD_TYPE, &NES_GAMEPAD_TYPE])
+                .unwrap()
+                .len(),
+            2
+        );
+    }
+}
@@ -0,0 +1,31 @@
+//! A multisystem hardware emulator
+//!
+//! New systems are put together out of components with [machine::MachineBuilder], see `examples/counter_machine.rs` for a
+//! complete toy system
+//!
+//! Front ends and tools that only need to run machines should depend on `multiemu-core` instead, which is the part of
+//! this crate that is kept stable
+
+// Backups are zip archives, and zip is desktop only
+#[cfg(platform_desktop)]
+pub mod backup;
+// Cli tools are designed only to operate on desktop
+#[cfg(platform_desktop)]
+pub mod cli;
+pub mod component;
+pub mod config;
+pub mod definitions;
+pub mod gui;
+pub mod host_cpu;
+pub mod input;
+pub mod machine;
+pub mod memory;
+pub mod processor;
+pub mod profile;
+pub mod rom;
+pub mod runtime;
+pub mod scheduler;
+pub mod tas;
+#[cfg(test)]
+pub mod testing;
+pub mod update;
@@ -1,26 +1,65 @@
 use super::ComponentTable;
 use crate::component::ComponentId;
+use std::collections::HashMap;
 
-/// [ComponentId]s are sequential so we can make this optimization
 #[derive(Debug)]
-pub struct ComponentStore(pub(super) Vec<ComponentTable>);
+pub struct ComponentStore {
+    /// [ComponentId]s are sequential so we can make this optimization
+    pub(super) tables: Vec<ComponentTable>,
+    labels: HashMap<String, ComponentId>,
+}
 
 impl ComponentStore {
     pub fn new() -> Self {
-        Self(Vec::default())
+        Self {
+            tables: Vec::default(),
+            labels: HashMap::default(),
+        }
     }
 
     pub fn get(&self, component_id: ComponentId) -> Option<&ComponentTable> {
-        self.0.get(component_id.0 as usize)
+        self.tables.get(component_id.0 as usize)
+    }
+
+    pub(super) fn get_mut(&mut self, component_id: ComponentId) -> Option<&mut ComponentTable> {
+        self.tables.get_mut(component_id.0 as usize)
+    }
+
+    /// See [ComponentTable::label]
+    pub fn by_label(&self, label: &str) -> Option<ComponentId> {
+        self.labels.get(label).copied()
+    }
+
+    /// `label` if nothing has it yet, otherwise the first of `label#2`, `label#3` and on that is free
+    pub(super) fn free_label(&self, label: &str) -> String {
+        if !self.labels.contains_key(label) {
+            return label.to_string();
+        }
+
+        (2..)
+            .map(|number| format!("{}#{}", label, number))
+            .find(|numbered| !self.labels.contains_key(numbered))
+            .unwrap()
+    }
+
+    /// The label is assumed to be free
+    pub(super) fn insert(&mut self, table: ComponentTable) {
+        let component_id = ComponentId(self.tables.len().try_into().expect("Too many components"));
+
+        self.labels.insert(table.label.clone(), component_id);
+        self.tables.push(table);
     }
 
     pub fn iter<'a>(&'a self) -> impl Iterator<Item = (ComponentId, &'a ComponentTable)> + use<'a> {
-        self.0.iter().enumerate().map(|(index, component_table)| {
-            (
-                ComponentId(index.try_into().expect("Too many components")),
-                component_table,
-            )
-        })
+        self.tables
+            .iter()
+            .enumerate()
+            .map(|(index, component_table)| {
+                (
+                    ComponentId(index.try_into().expect("Too many components")),
+                    component_table,
+                )
+            })
     }
 
     pub fn ids<'a>(&'a self) -> impl Iterator<Item = ComponentId> + use<'a> {
@@ -0,0 +1,508 @@
+//! Machines described in RON instead of Rust, for variants of a system that only differ in which components go where
+//!
+//! Descriptions in [MACHINES_DIRECTORY] are registered over the built in definitions on startup. Components refer to
+//! each other by name and are built in the order they are listed, so they can only use ones listed before them
+//!
+//! ```ron
+//! (
+//!     name: "Chip-48",
+//!     system: Other(Chip8),
+//!     busses: { 0: 12 },
+//!     components: [
+//!         ("audio", Chip8Audio),
+//!         ("timer", Chip8Timer),
+//!         ("display", Chip8Display(kind: Chip48)),
+//!         ("processor", Chip8Processor(
+//!             frequency: 1000,
+//!             kind: Chip48,
+//!             display: "display",
+//!             audio: "audio",
+//!             timer: "timer",
+//!         )),
+//!         ("program", StandardMemory(
+//!             address_space: 0,
+//!             range: (start: 0x200, end: 0x1000),
+//!             contents: UserRom(offset: 0x200),
+//!         )),
+//!     ],
+//!     media_slots: [(component: "program", slot: "program", requires_reset: true)],
+//! )
+//! ```
+
+use super::{definition::MachineDefinition, Machine, MachineBuildError, MachineBuilder};
+use crate::{
+    component::{
+        media::{MediaSlotId, MediaSlotMetadata},
+        ComponentId,
+    },
+    definitions::{
+        chip8::{
+            audio::Chip8Audio,
+            display::{Chip8Display, Chip8DisplayConfig},
+            processor::{Chip8Processor, Chip8ProcessorConfig},
+            timer::Chip8Timer,
+            Chip8Kind,
+        },
+        misc::{
+            memory::{
+                rom::{RomMemory, RomMemoryConfig},
+                standard::{StandardMemory, StandardMemoryConfig, StandardMemoryInitialContents},
+            },
+            processor::i8080::{I8080Config, I8080},
+            tape::TapeDeck,
+        },
+        nes::ppu::NesPPU,
+        spectrum::{
+            beeper::Beeper,
+            ula::{Ula, UlaConfig},
+        },
+    },
+    memory::AddressSpaceId,
+    profile::PROFILE_DIRECTORY,
+    rom::{id::RomId, manager::RomManager, system::GameSystem},
+};
+use num::rational::Ratio;
+use serde::Deserialize;
+use serde_with::{serde_as, DisplayFromStr};
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap},
+    error::Error,
+    fs::{read_dir, File},
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::{Arc, LazyLock},
+};
+
+pub static MACHINES_DIRECTORY: LazyLock<PathBuf> =
+    LazyLock::new(|| PROFILE_DIRECTORY.join("machines"));
+
+/// What a memory component starts out holding
+#[serde_as]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub enum MemoryContents {
+    Value(u8),
+    Random,
+    Bytes {
+        offset: usize,
+        bytes: Vec<u8>,
+    },
+    /// One of the ROMs the user asked to run, the first one by default
+    UserRom {
+        #[serde(default)]
+        index: usize,
+        offset: usize,
+    },
+    Rom {
+        #[serde_as(as = "DisplayFromStr")]
+        id: RomId,
+        offset: usize,
+    },
+}
+
+/// Every component that can be put in a description, with other components referred to by name
+#[serde_as]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub enum ComponentDescription {
+    StandardMemory {
+        address_space: AddressSpaceId,
+        range: Range<usize>,
+        #[serde(default = "enabled")]
+        readable: bool,
+        #[serde(default = "enabled")]
+        writable: bool,
+        #[serde(default = "default_word_size")]
+        max_word_size: usize,
+        contents: MemoryContents,
+    },
+    RomMemory {
+        #[serde_as(as = "DisplayFromStr")]
+        rom: RomId,
+        address_space: AddressSpaceId,
+        range: Range<usize>,
+        #[serde(default = "default_rom_word_size")]
+        max_word_size: u8,
+    },
+    Chip8Audio,
+    Chip8Timer,
+    Chip8Display {
+        kind: Chip8Kind,
+    },
+    Chip8Processor {
+        /// In hertz
+        frequency: u64,
+        kind: Chip8Kind,
+        display: String,
+        audio: String,
+        timer: String,
+    },
+    NesPpu,
+    Z80 {
+        /// In hertz
+        frequency: u64,
+        address_space: AddressSpaceId,
+        io_address_space: AddressSpaceId,
+    },
+    Beeper,
+    TapeDeck,
+    Ula {
+        /// What the processor runs at in hertz
+        frequency: u64,
+        processor: String,
+        beeper: String,
+        tape: String,
+    },
+}
+
+fn enabled() -> bool {
+    true
+}
+
+fn default_word_size() -> usize {
+    2
+}
+
+fn default_rom_word_size() -> u8 {
+    2
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct MirrorDescription {
+    pub component: String,
+    pub address_space: AddressSpaceId,
+    pub range: Range<usize>,
+    pub mask: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct WaitStateDescription {
+    pub address_space: AddressSpaceId,
+    pub range: Range<usize>,
+    pub wait_states: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct MediaSlotDescription {
+    pub component: String,
+    pub slot: MediaSlotId,
+    pub requires_reset: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct MachineDescription {
+    /// Shown in logs and menus
+    pub name: String,
+    pub system: GameSystem,
+    /// How many bits wide each address space is
+    pub busses: BTreeMap<AddressSpaceId, u8>,
+    pub components: Vec<(String, ComponentDescription)>,
+    #[serde(default)]
+    pub mirrors: Vec<MirrorDescription>,
+    #[serde(default)]
+    pub wait_states: Vec<WaitStateDescription>,
+    #[serde(default)]
+    pub media_slots: Vec<MediaSlotDescription>,
+}
+
+impl MachineDescription {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Box<dyn Error>> {
+        Ok(ron::de::from_reader(File::open(path)?)?)
+    }
+
+    /// Every description in `directory`, skipping any that cannot be read
+    pub fn load_all(directory: impl AsRef<Path>) -> Vec<Self> {
+        let Ok(entries) = read_dir(directory) else {
+            return Vec::default();
+        };
+
+        entries
+            .flatten()
+            .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "ron"))
+            .filter_map(|entry| match Self::load(entry.path()) {
+                Ok(description) => Some(description),
+                Err(err) => {
+                    tracing::warn!(
+                        "Could not read machine description {}: {}",
+                        entry.path().display(),
+                        err
+                    );
+                    None
+                }
+            })
+            .collect()
+    }
+
+    pub fn build(
+        &self,
+        user_specified_roms: &[RomId],
+        rom_manager: Arc<RomManager>,
+    ) -> Result<Machine, MachineBuildError> {
+        let mut machine = Machine::build(self.system, rom_manager);
+        for (address_space, width) in self.busses.iter() {
+            machine = machine.insert_bus(*address_space, *width);
+        }
+
+        // Names double as labels, so the builder already refuses ones used twice
+        let mut names = HashMap::new();
+        for (name, component) in self.components.iter() {
+            let id;
+            (machine, id) = build_component(machine, name, component, &names, user_specified_roms)?;
+            names.insert(name.as_str(), id);
+        }
+
+        for mirror in self.mirrors.iter() {
+            machine = machine.mirror_memory(
+                resolve(&names, &mirror.component)?,
+                mirror.address_space,
+                mirror.range.clone(),
+                mirror.mask,
+            )?;
+        }
+
+        for wait_states in self.wait_states.iter() {
+            machine = machine.wait_states(
+                wait_states.address_space,
+                wait_states.range.clone(),
+                wait_states.wait_states,
+            )?;
+        }
+
+        for media_slot in self.media_slots.iter() {
+            let id = resolve(&names, &media_slot.component)?;
+            let metadata = MediaSlotMetadata {
+                requires_reset: media_slot.requires_reset,
+            };
+
+            // The slot has to know the concrete type, which only the description does
+            machine = match self
+                .components
+                .iter()
+                .find(|(name, _)| *name == media_slot.component)
+                .map(|(_, component)| component)
+            {
+                Some(ComponentDescription::StandardMemory { .. }) => machine
+                    .insert_media_slot::<StandardMemory>(id, media_slot.slot.clone(), metadata)?,
+                Some(ComponentDescription::TapeDeck) => {
+                    machine.insert_media_slot::<TapeDeck>(id, media_slot.slot.clone(), metadata)?
+                }
+                _ => {
+                    return Err(MachineBuildError::WrongComponentType {
+                        component: media_slot.component.clone(),
+                        expected: "media component",
+                    })
+                }
+            };
+        }
+
+        machine.build()
+    }
+}
+
+fn resolve(
+    names: &HashMap<&str, ComponentId>,
+    name: &str,
+) -> Result<ComponentId, MachineBuildError> {
+    names
+        .get(name)
+        .copied()
+        .ok_or_else(|| MachineBuildError::UnknownComponentName(name.to_string()))
+}
+
+fn build_component(
+    machine: MachineBuilder,
+    label: &str,
+    component: &ComponentDescription,
+    names: &HashMap<&str, ComponentId>,
+    user_specified_roms: &[RomId],
+) -> Result<(MachineBuilder, ComponentId), MachineBuildError> {
+    match component.clone() {
+        ComponentDescription::StandardMemory {
+            address_space,
+            range,
+            readable,
+            writable,
+            max_word_size,
+            contents,
+        } => machine.build_labeled_component::<StandardMemory>(
+            label,
+            StandardMemoryConfig {
+                readable,
+                writable,
+                max_word_size,
+                assigned_range: range,
+                assigned_address_space: address_space,
+                initial_contents: match contents {
+                    MemoryContents::Value(value) => StandardMemoryInitialContents::Value { value },
+                    MemoryContents::Random => StandardMemoryInitialContents::Random,
+                    MemoryContents::Bytes { offset, bytes } => {
+                        StandardMemoryInitialContents::Array {
+                            offset,
+                            value: Cow::Owned(bytes),
+                        }
+                    }
+                    MemoryContents::UserRom { index, offset } => {
+                        StandardMemoryInitialContents::Rom {
+                            rom_id: user_specified_roms.get(index).copied(),
+                            offset,
+                        }
+                    }
+                    MemoryContents::Rom { id, offset } => StandardMemoryInitialContents::Rom {
+                        rom_id: Some(id),
+                        offset,
+                    },
+                },
+            },
+        ),
+        ComponentDescription::RomMemory {
+            rom,
+            address_space,
+            range,
+            max_word_size,
+        } => machine.build_labeled_component::<RomMemory>(
+            label,
+            RomMemoryConfig {
+                rom,
+                max_word_size,
+                assigned_range: range,
+                assigned_address_space: address_space,
+            },
+        ),
+        ComponentDescription::Chip8Audio => {
+            machine.build_labeled_component::<Chip8Audio>(label, ())
+        }
+        ComponentDescription::Chip8Timer => {
+            machine.build_labeled_component::<Chip8Timer>(label, ())
+        }
+        ComponentDescription::Chip8Display { kind } => {
+            machine.build_labeled_component::<Chip8Display>(label, Chip8DisplayConfig { kind })
+        }
+        ComponentDescription::Chip8Processor {
+            frequency,
+            kind,
+            display,
+            audio,
+            timer,
+        } => machine.build_labeled_component::<Chip8Processor>(
+            label,
+            Chip8ProcessorConfig {
+                frequency: Ratio::from_integer(frequency),
+                kind,
+                display: resolve(names, &display)?,
+                audio: resolve(names, &audio)?,
+                timer: resolve(names, &timer)?,
+            },
+        ),
+        ComponentDescription::NesPpu => machine.build_labeled_component::<NesPPU>(label, ()),
+        ComponentDescription::Z80 {
+            frequency,
+            address_space,
+            io_address_space,
+        } => machine.build_labeled_component::<I8080>(
+            label,
+            I8080Config::z80(
+                Ratio::from_integer(frequency),
+                address_space,
+                io_address_space,
+            ),
+        ),
+        ComponentDescription::Beeper => machine.build_labeled_component::<Beeper>(label, ()),
+        ComponentDescription::TapeDeck => machine.build_labeled_component::<TapeDeck>(label, ()),
+        ComponentDescription::Ula {
+            frequency,
+            processor,
+            beeper,
+            tape,
+        } => machine.build_labeled_component::<Ula>(
+            label,
+            UlaConfig {
+                processor: resolve(names, &processor)?,
+                beeper: resolve(names, &beeper)?,
+                tape: resolve(names, &tape)?,
+                frequency: Ratio::from_integer(frequency),
+            },
+        ),
+    }
+}
+
+/// Lets a description be registered like any other definition
+pub struct DeclarativeDefinition(pub MachineDescription);
+
+impl MachineDefinition for DeclarativeDefinition {
+    fn name(&self) -> &str {
+        &self.0.name
+    }
+
+    fn systems(&self) -> Vec<GameSystem> {
+        vec![self.0.system]
+    }
+
+    fn construct(
+        &self,
+        _system: GameSystem,
+        user_specified_roms: Vec<RomId>,
+        rom_manager: Arc<RomManager>,
+    ) -> Result<Machine, MachineBuildError> {
+        self.0.build(&user_specified_roms, rom_manager)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rom::system::OtherSystem;
+
+    const CHIP48: &str = r#"(
+        name: "Chip-48",
+        system: Other(Chip8),
+        busses: { 0: 12 },
+        components: [
+            ("audio", Chip8Audio),
+            ("timer", Chip8Timer),
+            ("display", Chip8Display(kind: Chip48)),
+            ("processor", Chip8Processor(
+                frequency: 1000,
+                kind: Chip48,
+                display: "display",
+                audio: "audio",
+                timer: "timer",
+            )),
+            ("font", StandardMemory(
+                address_space: 0,
+                range: (start: 0x000, end: 0x200),
+                contents: Bytes(offset: 0, bytes: [0xf0, 0x90, 0x90, 0x90, 0xf0]),
+            )),
+            ("program", StandardMemory(
+                address_space: 0,
+                range: (start: 0x200, end: 0x1000),
+                contents: UserRom(offset: 0x200),
+            )),
+        ],
+        media_slots: [(component: "program", slot: "program", requires_reset: true)],
+    )"#;
+
+    #[test]
+    fn descriptions_build_machines() {
+        let description: MachineDescription = ron::from_str(CHIP48).unwrap();
+        assert_eq!(description.system, GameSystem::Other(OtherSystem::Chip8));
+
+        let rom_manager = Arc::new(RomManager::new(None).unwrap());
+        let machine = description.build(&[], rom_manager.clone()).unwrap();
+        let mut buffer = [0; 2];
+        machine
+            .memory_translation_table
+            .read(0x000, &mut buffer, 0)
+            .unwrap();
+        assert_eq!(buffer, [0xf0, 0x90]);
+        assert_eq!(machine.media_slots().count(), 1);
+
+        // The processor can not use a display listed after it
+        let mut description = description;
+        description.components.swap(2, 3);
+        assert_eq!(
+            description.build(&[], rom_manager).err(),
+            Some(MachineBuildError::UnknownComponentName(
+                "display".to_string()
+            ))
+        );
+    }
+}
@@ -0,0 +1,174 @@
+use super::{
+    declarative::{DeclarativeDefinition, MachineDescription, MACHINES_DIRECTORY},
+    Machine, MachineBuildError,
+};
+use crate::{
+    definitions::{chip8::Chip8Definition, nes::NesDefinition, spectrum::SpectrumDefinition},
+    rom::{
+        id::RomId,
+        manager::{RomManager, RomRequirement},
+        system::GameSystem,
+    },
+};
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    path::Path,
+    sync::{Arc, LazyLock, RwLock},
+};
+use thiserror::Error;
+
+pub static MACHINE_DEFINITIONS: LazyLock<RwLock<MachineDefinitionRegistry>> = LazyLock::new(|| {
+    let mut registry = MachineDefinitionRegistry::with_builtin_definitions();
+    registry.load_declarative(MACHINES_DIRECTORY.as_path());
+    RwLock::new(registry)
+});
+
+/// A rom a system needs before it can boot that the user has to provide, like a bios
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FirmwareRequirement {
+    pub name: Cow<'static, str>,
+    pub rom_id: RomId,
+    pub requirement: RomRequirement,
+}
+
+/// Where missing features are tracked, pointed to whenever a ROM turns out to need one
+pub const COMPATIBILITY_TRACKER_URL: &str = "https://github.com/fullbrightkay/multiemu/issues";
+
+/// A ROM needing something the core does not have yet, found before ever running it
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("Needs {feature}, which is not implemented yet")]
+pub struct UnsupportedRom {
+    /// What is missing, kept the same for every ROM waiting on it so reports can count them together
+    pub feature: String,
+}
+
+/// Everything the frontend knows about a core, kept object safe so cores can eventually live outside this crate
+pub trait MachineDefinition: Send + Sync {
+    /// Human readable name for logs and menus
+    fn name(&self) -> &str;
+
+    /// Systems this definition can construct machines for
+    fn systems(&self) -> Vec<GameSystem>;
+
+    fn firmware(&self, _system: GameSystem) -> Vec<FirmwareRequirement> {
+        Vec::new()
+    }
+
+    /// Looks at a ROM for anything the core is known not to handle, like an unimplemented mapper
+    fn check_rom(
+        &self,
+        _system: GameSystem,
+        _rom_id: RomId,
+        _rom_manager: &RomManager,
+    ) -> Result<(), UnsupportedRom> {
+        Ok(())
+    }
+
+    fn construct(
+        &self,
+        system: GameSystem,
+        user_specified_roms: Vec<RomId>,
+        rom_manager: Arc<RomManager>,
+    ) -> Result<Machine, MachineBuildError>;
+}
+
+/// Looks up which definition handles a system
+#[derive(Default)]
+pub struct MachineDefinitionRegistry {
+    definitions: HashMap<GameSystem, Arc<dyn MachineDefinition>>,
+}
+
+impl MachineDefinitionRegistry {
+    pub fn with_builtin_definitions() -> Self {
+        let mut registry = Self::default();
+
+        registry.register(Arc::new(Chip8Definition));
+        registry.register(Arc::new(NesDefinition));
+        registry.register(Arc::new(SpectrumDefinition));
+
+        registry
+    }
+
+    /// Later registrations win, so an external core can replace a built in one
+    pub fn register(&mut self, definition: Arc<dyn MachineDefinition>) {
+        for system in definition.systems() {
+            if let Some(previous_definition) = self.definitions.insert(system, definition.clone()) {
+                tracing::warn!(
+                    "{} replaced {} as the definition for {}",
+                    definition.name(),
+                    previous_definition.name(),
+                    system
+                );
+            }
+        }
+    }
+
+    /// Registers every RON machine description in `directory`
+    pub fn load_declarative(&mut self, directory: impl AsRef<Path>) {
+        for description in MachineDescription::load_all(directory) {
+            self.register(Arc::new(DeclarativeDefinition(description)));
+        }
+    }
+
+    /// Every system some definition handles
+    pub fn systems(&self) -> impl Iterator<Item = GameSystem> + '_ {
+        self.definitions.keys().copied()
+    }
+
+    pub fn get(&self, system: GameSystem) -> Option<Arc<dyn MachineDefinition>> {
+        self.definitions.get(&system).cloned()
+    }
+
+    /// Firmware for a system that the rom manager does not know the location of
+    pub fn missing_firmware(
+        &self,
+        system: GameSystem,
+        rom_manager: &RomManager,
+    ) -> Vec<FirmwareRequirement> {
+        self.get(system)
+            .map(|definition| definition.firmware(system))
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|firmware| !rom_manager.rom_paths.contains_key(&firmware.rom_id))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rom::system::OtherSystem;
+
+    struct ExternalChip8Definition;
+
+    impl MachineDefinition for ExternalChip8Definition {
+        fn name(&self) -> &str {
+            "External Chip8"
+        }
+
+        fn systems(&self) -> Vec<GameSystem> {
+            vec![GameSystem::Other(OtherSystem::Chip8)]
+        }
+
+        fn construct(
+            &self,
+            _system: GameSystem,
+            _user_specified_roms: Vec<RomId>,
+            _rom_manager: Arc<RomManager>,
+        ) -> Result<Machine, MachineBuildError> {
+            unreachable!()
+        }
+    }
+
+    #[test]
+    fn external_definitions_replace_builtin() {
+        let mut registry = MachineDefinitionRegistry::with_builtin_definitions();
+        let system = GameSystem::Other(OtherSystem::Chip8);
+
+        assert_eq!(registry.get(system).unwrap().name(), "Chip8");
+        registry.register(Arc::new(ExternalChip8Definition));
+        assert_eq!(registry.get(system).unwrap().name(), "External Chip8");
+        assert!(registry.get(GameSystem::Unknown).is_none());
+    }
+}
@@ -0,0 +1,81 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Mutex,
+};
+use thiserror::Error;
+
+/// Something a component ran into that the emulated machine can't continue past, usually the fault of a bad ROM
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum MachineFault {
+    #[error("illegal opcode {opcode} at {address:#06x}")]
+    IllegalInstruction { address: usize, opcode: String },
+    #[error("invalid memory access at {address:#06x}")]
+    InvalidMemoryAccess { address: usize },
+    #[error("stack overflow at {address:#06x}")]
+    StackOverflow { address: usize },
+    #[error("stack underflow at {address:#06x}")]
+    StackUnderflow { address: usize },
+}
+
+/// Shared between every component of a machine so any of them can stop it
+///
+/// Only the first fault is kept, whatever happens after it is usually fallout
+#[derive(Debug, Default)]
+pub struct FaultReporter {
+    faulted: AtomicBool,
+    fault: Mutex<Option<MachineFault>>,
+}
+
+impl FaultReporter {
+    pub fn report(&self, fault: MachineFault) {
+        let mut fault_guard = self.fault.lock().unwrap();
+
+        if fault_guard.is_none() {
+            tracing::error!("The emulated machine crashed: {}", fault);
+
+            *fault_guard = Some(fault);
+            self.faulted.store(true, Ordering::Release);
+        }
+    }
+
+    /// Cheap enough to check every scheduler iteration
+    #[inline]
+    pub fn is_faulted(&self) -> bool {
+        self.faulted.load(Ordering::Acquire)
+    }
+
+    pub fn fault(&self) -> Option<MachineFault> {
+        self.fault.lock().unwrap().clone()
+    }
+
+    pub fn clear(&self) {
+        *self.fault.lock().unwrap() = None;
+        self.faulted.store(false, Ordering::Release);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn first_fault_is_kept() {
+        let fault_reporter = FaultReporter::default();
+
+        fault_reporter.report(MachineFault::IllegalInstruction {
+            address: 0x200,
+            opcode: "0xffff".to_string(),
+        });
+        fault_reporter.report(MachineFault::StackUnderflow { address: 0x202 });
+
+        assert!(fault_reporter.is_faulted());
+        assert_eq!(
+            fault_reporter.fault().unwrap().to_string(),
+            "illegal opcode 0xffff at 0x0200"
+        );
+
+        fault_reporter.clear();
+        assert!(!fault_reporter.is_faulted());
+        assert_eq!(fault_reporter.fault(), None);
+    }
+}
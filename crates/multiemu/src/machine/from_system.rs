@@ -1,11 +1,8 @@
-use super::Machine;
-use crate::{
-    definitions::{chip8::chip8_machine, nes::nes_machine},
-    rom::{
-        id::RomId,
-        manager::RomManager,
-        system::{GameSystem, NintendoSystem, OtherSystem},
-    },
+use super::{definition::MACHINE_DEFINITIONS, Machine, MachineBuildError};
+use crate::rom::{
+    id::RomId,
+    manager::{RomManager, RomRequirement},
+    system::GameSystem,
 };
 use std::sync::Arc;
 
@@ -14,25 +11,48 @@ impl Machine {
         user_specified_roms: Vec<RomId>,
         rom_manager: Arc<RomManager>,
         system: GameSystem,
-    ) -> Machine {
-        match system {
-            GameSystem::Nintendo(NintendoSystem::GameBoy) => todo!(),
-            GameSystem::Nintendo(NintendoSystem::GameBoyColor) => todo!(),
-            GameSystem::Nintendo(NintendoSystem::GameBoyAdvance) => todo!(),
-            GameSystem::Nintendo(NintendoSystem::NintendoEntertainmentSystem) => {
-                nes_machine(user_specified_roms, rom_manager)
-            }
-            GameSystem::Nintendo(NintendoSystem::SuperNintendoEntertainmentSystem) => todo!(),
-            GameSystem::Sega(sega_system) => todo!(),
-            GameSystem::Sony(sony_system) => todo!(),
-            GameSystem::Atari(atari_system) => todo!(),
-            GameSystem::Other(OtherSystem::Chip8) => {
-                chip8_machine(user_specified_roms, rom_manager)
+    ) -> Result<Machine, MachineBuildError> {
+        let (definition, missing_firmware) = {
+            let machine_definitions = MACHINE_DEFINITIONS.read().unwrap();
+
+            (
+                machine_definitions
+                    .get(system)
+                    .ok_or(MachineBuildError::UnsupportedSystem(system))?,
+                machine_definitions.missing_firmware(system, &rom_manager),
+            )
+        };
+
+        for firmware in missing_firmware {
+            if firmware.requirement == RomRequirement::Required {
+                tracing::error!(
+                    "Missing firmware {} ({}) for {}, it will probably not boot",
+                    firmware.name,
+                    firmware.rom_id,
+                    system
+                );
+            } else {
+                tracing::warn!(
+                    "Missing firmware {} ({}) for {}",
+                    firmware.name,
+                    firmware.rom_id,
+                    system
+                );
             }
-            GameSystem::Unknown => todo!(),
-            _ => {
-                unimplemented!("This system is not supported by this emulator");
+        }
+
+        for rom_id in user_specified_roms.iter().copied() {
+            if let Err(err) = definition.check_rom(system, rom_id, &rom_manager) {
+                tracing::error!("{} can not run {}: {}", definition.name(), rom_id, err);
+                return Err(err.into());
             }
         }
+
+        tracing::info!("Constructing {} machine with {}", system, definition.name());
+
+        let mut machine = definition.construct(system, user_specified_roms.clone(), rom_manager)?;
+        machine.user_specified_roms = user_specified_roms;
+
+        Ok(machine)
     }
 }
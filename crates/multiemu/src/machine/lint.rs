@@ -0,0 +1,232 @@
+use super::Machine;
+use crate::{component::ComponentId, memory::AddressSpaceId};
+use itertools::Itertools;
+use num::rational::Ratio;
+use petgraph::{algo::has_path_connecting, graphmap::DiGraphMap};
+use rangemap::RangeSet;
+use std::ops::Range;
+use thiserror::Error;
+
+/// A likely mistake in how a machine was put together, see [Machine::lint]
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum LintIssue {
+    #[error(
+        "{first} and {second} are both assigned {range:#06x?} on address space {address_space}"
+    )]
+    OverlappingMemory {
+        address_space: AddressSpaceId,
+        range: Range<usize>,
+        first: String,
+        second: String,
+    },
+    #[error("{component} is not schedulable, event driven, memory, display, input or media, so nothing can reach it")]
+    UnreachableComponent { component: String },
+    #[error("{first} and {second} run at the same frequency without saying which goes first")]
+    MissingRunOrdering { first: String, second: String },
+    #[error("{component} is schedulable with a frequency of zero, so it never runs")]
+    ZeroFrequency { component: String },
+    #[error("{range:#06x?} on address space {address_space} is not claimed by any component")]
+    UnclaimedAddressSpace {
+        address_space: AddressSpaceId,
+        range: Range<usize>,
+    },
+}
+
+impl Machine {
+    /// Looks over how the machine was put together for things that are usually mistakes
+    ///
+    /// None of these stop a machine from running, so they are left for core authors to check on
+    pub fn lint(&self) -> Vec<LintIssue> {
+        let mut issues = Vec::default();
+
+        self.lint_memory(&mut issues);
+        self.lint_components(&mut issues);
+        self.lint_run_ordering(&mut issues);
+
+        issues
+    }
+
+    /// Names are only unique together with the id, there are usually a few of the same memory type
+    fn component_name(&self, component_id: ComponentId) -> String {
+        let name = self
+            .component_store
+            .get(component_id)
+            .map(|table| table.name)
+            .unwrap_or_default();
+
+        format!("{} ({})", name, component_id.0)
+    }
+
+    fn lint_memory(&self, issues: &mut Vec<LintIssue>) {
+        let memory_components: Vec<_> = self
+            .component_store
+            .iter()
+            .filter_map(|(component_id, table)| {
+                table
+                    .as_memory
+                    .as_ref()
+                    .map(|memory_info| (component_id, memory_info.claimed_ranges()))
+            })
+            .collect();
+
+        for ((first, first_ranges), (second, second_ranges)) in
+            memory_components.iter().tuple_combinations()
+        {
+            for (address_space, first_ranges) in first_ranges.iter().sorted_by_key(|(id, _)| **id) {
+                let Some(second_ranges) = second_ranges.get(address_space) else {
+                    continue;
+                };
+
+                for first_range in first_ranges.iter() {
+                    for second_range in second_ranges.overlapping(first_range) {
+                        issues.push(LintIssue::OverlappingMemory {
+                            address_space: *address_space,
+                            range: first_range.start.max(second_range.start)
+                                ..first_range.end.min(second_range.end),
+                            first: self.component_name(*first),
+                            second: self.component_name(*second),
+                        });
+                    }
+                }
+            }
+        }
+
+        for (address_space, width) in self
+            .memory_translation_table
+            .busses()
+            .sorted_by_key(|(id, _)| *id)
+        {
+            let claimed: RangeSet<_> = memory_components
+                .iter()
+                .filter_map(|(_, assigned_ranges)| assigned_ranges.get(&address_space))
+                .flat_map(|ranges| ranges.iter().cloned())
+                .collect();
+            let bus_end = 1usize.checked_shl(width.into()).unwrap_or(usize::MAX);
+
+            for range in claimed.gaps(&(0..bus_end)) {
+                issues.push(LintIssue::UnclaimedAddressSpace {
+                    address_space,
+                    range,
+                });
+            }
+        }
+    }
+
+    fn lint_components(&self, issues: &mut Vec<LintIssue>) {
+        for (component_id, table) in self.component_store.iter() {
+            if table.as_schedulable.is_none()
+                && table.as_event_driven.is_none()
+                && table.as_display.is_none()
+                && table.as_input.is_none()
+                && table.as_memory.is_none()
+                && table.as_media.is_none()
+            {
+                issues.push(LintIssue::UnreachableComponent {
+                    component: self.component_name(component_id),
+                });
+            }
+
+            if table
+                .as_schedulable
+                .as_ref()
+                .is_some_and(|schedulable_info| schedulable_info.timings == Ratio::from_integer(0))
+            {
+                issues.push(LintIssue::ZeroFrequency {
+                    component: self.component_name(component_id),
+                });
+            }
+        }
+    }
+
+    /// Components running at the same frequency always share a time slice, so which one sees the other's changes
+    /// first is down to the order they happened to be built in
+    fn lint_run_ordering(&self, issues: &mut Vec<LintIssue>) {
+        let mut graph = DiGraphMap::<ComponentId, ()>::new();
+        let mut schedulables = Vec::default();
+
+        for (component_id, table) in self.component_store.iter() {
+            let Some(schedulable_info) = &table.as_schedulable else {
+                continue;
+            };
+            graph.add_node(component_id);
+            schedulables.push((component_id, schedulable_info.timings));
+
+            for run_after in schedulable_info.run_after.iter() {
+                graph.add_edge(*run_after, component_id, ());
+            }
+
+            for run_before in schedulable_info.run_before.iter() {
+                graph.add_edge(component_id, *run_before, ());
+            }
+        }
+
+        for ((first, first_timings), (second, second_timings)) in
+            schedulables.iter().tuple_combinations()
+        {
+            if first_timings != second_timings || *first_timings == Ratio::from_integer(0) {
+                continue;
+            }
+
+            if !has_path_connecting(&graph, *first, *second, None)
+                && !has_path_connecting(&graph, *second, *first, None)
+            {
+                issues.push(LintIssue::MissingRunOrdering {
+                    first: self.component_name(*first),
+                    second: self.component_name(*second),
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        definitions::misc::memory::standard::{
+            StandardMemory, StandardMemoryConfig, StandardMemoryInitialContents,
+        },
+        rom::{manager::RomManager, system::GameSystem},
+    };
+    use std::sync::Arc;
+
+    #[test]
+    fn lint_finds_overlaps_and_gaps() {
+        let rom_manager = Arc::new(RomManager::new(None).unwrap());
+        let machine = Machine::build(GameSystem::Unknown, rom_manager)
+            .insert_bus(0, 8)
+            .allow_overlapping_memory();
+        let (machine, _) = machine
+            .build_component::<StandardMemory>(StandardMemoryConfig {
+                readable: true,
+                writable: true,
+                max_word_size: 1,
+                assigned_range: 0x00..0x80,
+                assigned_address_space: 0,
+                initial_contents: StandardMemoryInitialContents::Value { value: 0 },
+            })
+            .unwrap();
+        let (machine, _) = machine
+            .build_component::<StandardMemory>(StandardMemoryConfig {
+                readable: true,
+                writable: true,
+                max_word_size: 1,
+                assigned_range: 0x40..0xc0,
+                assigned_address_space: 0,
+                initial_contents: StandardMemoryInitialContents::Value { value: 0 },
+            })
+            .unwrap();
+        let machine = machine.build().unwrap();
+
+        let issues = machine.lint();
+        assert_eq!(issues.len(), 2);
+        assert!(matches!(
+            &issues[0],
+            LintIssue::OverlappingMemory { range, .. } if *range == (0x40..0x80)
+        ));
+        assert!(matches!(
+            &issues[1],
+            LintIssue::UnclaimedAddressSpace { range, .. } if *range == (0xc0..0x100)
+        ));
+    }
+}
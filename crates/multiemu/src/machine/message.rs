@@ -0,0 +1,119 @@
+//! Queues for components to hand each other work without holding onto each other, so neither has to lock the other
+//! while running and the scheduler is free to run them apart
+
+use std::sync::{Arc, Mutex};
+
+/// Messages posted to a component, which it picks up the next time it runs
+///
+/// Set up with [super::ComponentBuilder::mailbox]. Messages are not part of snapshots, so consumers should handle
+/// them every run and drop whatever is left on reset
+#[derive(Debug)]
+pub struct Mailbox<M> {
+    messages: Mutex<Vec<M>>,
+}
+
+impl<M> Default for Mailbox<M> {
+    fn default() -> Self {
+        Self {
+            messages: Mutex::default(),
+        }
+    }
+}
+
+impl<M> Mailbox<M> {
+    /// Everything posted since the last call, oldest first
+    pub fn receive(&self) -> Vec<M> {
+        std::mem::take(&mut self.messages.lock().unwrap())
+    }
+
+    pub fn clear(&self) {
+        self.messages.lock().unwrap().clear();
+    }
+}
+
+/// The posting end of a [Mailbox], handed out by [super::MachineBuilder::message_sender]
+#[derive(Debug)]
+pub struct MessageSender<M>(pub(super) Arc<Mailbox<M>>);
+
+impl<M> Clone for MessageSender<M> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<M> MessageSender<M> {
+    pub fn post(&self, message: M) {
+        self.0.messages.lock().unwrap().push(message);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        component::{
+            schedulable::{RunContext, SchedulableComponent},
+            Component, FromConfig,
+        },
+        machine::{ComponentBuilder, Machine, MachineBuildError},
+        rom::{manager::RomManager, system::GameSystem},
+    };
+    use num::rational::Ratio;
+    use std::{
+        sync::atomic::{AtomicU32, Ordering},
+        time::Duration,
+    };
+
+    /// Adds up whatever it is sent
+    #[derive(Debug)]
+    struct Adder {
+        mailbox: Arc<Mailbox<u32>>,
+        total: Arc<AtomicU32>,
+    }
+
+    impl Component for Adder {}
+
+    impl FromConfig for Adder {
+        type Config = Arc<AtomicU32>;
+
+        fn from_config(
+            component_builder: &mut ComponentBuilder<Self>,
+            total: Self::Config,
+        ) -> Result<(), MachineBuildError> {
+            let mailbox = component_builder.mailbox();
+
+            component_builder
+                .set_component(Self { mailbox, total })
+                .set_schedulable(Ratio::from_integer(60), [], []);
+
+            Ok(())
+        }
+    }
+
+    impl SchedulableComponent for Adder {
+        fn run(&self, _period: u64, _context: RunContext) {
+            for message in self.mailbox.receive() {
+                self.total.fetch_add(message, Ordering::Relaxed);
+            }
+        }
+    }
+
+    #[test]
+    fn messages_are_handled_when_the_consumer_runs() {
+        let total = Arc::new(AtomicU32::default());
+        let rom_manager = Arc::new(RomManager::new(None).unwrap());
+        let (machine_builder, adder_id) = Machine::build(GameSystem::Unknown, rom_manager)
+            .build_component::<Adder>(total.clone())
+            .unwrap();
+        let message_sender = machine_builder.message_sender::<u32>(adder_id).unwrap();
+        assert!(machine_builder.message_sender::<u8>(adder_id).is_err());
+        let mut machine = machine_builder.build().unwrap();
+
+        message_sender.post(1);
+        message_sender.post(2);
+        assert_eq!(total.load(Ordering::Relaxed), 0);
+
+        machine.run_frame(Duration::from_secs(1) / 60);
+        assert_eq!(total.load(Ordering::Relaxed), 3);
+    }
+}
@@ -1,29 +1,54 @@
 use crate::{
     component::{
+        audio::{AudioChunk, AudioComponent},
         display::DisplayComponent,
+        event_driven::EventDrivenComponent,
         input::{EmulatedGamepadMetadata, EmulatedGamepadTypeId, InputComponent},
+        introspectable::IntrospectableComponent,
+        media::{MediaComponent, MediaSlotId, MediaSlotMetadata, MediaSwapError},
         memory::MemoryComponent,
         schedulable::SchedulableComponent,
-        Component, ComponentId, FromConfig,
+        Component, ComponentId, FromConfig, ResetKind,
     },
     input::manager::InputManager,
-    memory::{AddressSpaceId, MemoryTranslationTable},
-    rom::{manager::RomManager, system::GameSystem},
-    scheduler::Scheduler,
+    memory::{AddressSpaceId, MemoryTranslationTable, RemapError},
+    rom::{id::RomId, manager::RomManager, system::GameSystem},
+    runtime::{
+        av_dump::AvDumper,
+        capture::{CaptureKind, CaptureRule, Capturer},
+        executor::Executor,
+        frame_compare::{FrameComparer, FrameComparisonSummary},
+    },
+    scheduler::{EventQueue, FrequencyChanges, Scheduler, StepGranularity},
 };
 use component_store::ComponentStore;
+use fault::{FaultReporter, MachineFault};
+use message::{Mailbox, MessageSender};
 use num::rational::Ratio;
-use rangemap::RangeSet;
+use rangemap::{RangeMap, RangeSet};
+use rng::MachineRng;
+use signal::{SignalDriver, SignalLine};
 use std::{
+    any::{type_name, Any},
     collections::{HashMap, HashSet},
     ops::Range,
+    path::{Path, PathBuf},
     sync::Arc,
     time::Duration,
 };
+use thiserror::Error;
 
 pub mod component_store;
+pub mod declarative;
+pub mod definition;
+pub mod fault;
 pub mod from_system;
+pub mod lint;
+pub mod message;
+pub mod rewind;
+pub mod rng;
 pub mod serialization;
+pub mod signal;
 
 #[derive(Debug)]
 pub struct SchedulableComponentInfo {
@@ -33,11 +58,23 @@ pub struct SchedulableComponentInfo {
     pub run_before: HashSet<ComponentId>,
 }
 
+#[derive(Debug)]
+pub struct EventDrivenComponentInfo {
+    pub component: Arc<dyn EventDrivenComponent>,
+}
+
 #[derive(Debug)]
 pub struct DisplayComponentInfo {
     pub component: Arc<dyn DisplayComponent>,
 }
 
+#[derive(Debug)]
+pub struct AudioComponentInfo {
+    pub component: Arc<dyn AudioComponent>,
+    /// Samples per second
+    pub sample_rate: u32,
+}
+
 #[derive(Debug)]
 pub struct InputComponentInfo {
     pub component: Arc<dyn InputComponent>,
@@ -45,19 +82,66 @@ pub struct InputComponentInfo {
     pub registered_gamepads: Vec<EmulatedGamepadTypeId>,
 }
 
+#[derive(Debug)]
+pub struct DebugComponentInfo {
+    pub component: Arc<dyn IntrospectableComponent>,
+}
+
 #[derive(Debug)]
 pub struct MemoryComponentInfo {
     pub component: Arc<dyn MemoryComponent>,
     pub assigned_ranges: HashMap<AddressSpaceId, RangeSet<usize>>,
+    /// Where the component shows up again, with the mask addresses there go through, see [MachineBuilder::mirror_memory]
+    pub mirrored_ranges: HashMap<AddressSpaceId, RangeMap<usize, usize>>,
+}
+
+impl MemoryComponentInfo {
+    /// Everything the component takes up on each address space, mirrors included
+    pub fn claimed_ranges(&self) -> HashMap<AddressSpaceId, RangeSet<usize>> {
+        let mut claimed_ranges = self.assigned_ranges.clone();
+
+        for (address_space_id, mirrored_ranges) in &self.mirrored_ranges {
+            claimed_ranges
+                .entry(*address_space_id)
+                .or_default()
+                .extend(mirrored_ranges.iter().map(|(range, _)| range.clone()));
+        }
+
+        claimed_ranges
+    }
+}
+
+#[derive(Debug)]
+pub struct MediaComponentInfo {
+    pub component: Arc<dyn MediaComponent>,
+    pub slots: HashMap<MediaSlotId, MediaSlotMetadata>,
 }
 
 #[derive(Debug)]
 pub struct ComponentTable {
+    /// Type name of the component, for showing to the user
+    pub name: &'static str,
+    /// Unique within the machine and stays the same no matter what order it was built in, see [Machine::component_by_name]
+    pub label: String,
     pub component: Arc<dyn Component>,
     pub as_schedulable: Option<SchedulableComponentInfo>,
+    pub as_event_driven: Option<EventDrivenComponentInfo>,
     pub as_display: Option<DisplayComponentInfo>,
+    pub as_audio: Option<AudioComponentInfo>,
     pub as_input: Option<InputComponentInfo>,
     pub as_memory: Option<MemoryComponentInfo>,
+    pub as_media: Option<MediaComponentInfo>,
+    pub as_debug: Option<DebugComponentInfo>,
+    /// The [Mailbox] other components post to, if it has one
+    pub mailbox: Option<Arc<dyn Any + Send + Sync>>,
+    /// Inputs other components can drive, by name
+    pub signal_lines: HashMap<&'static str, Arc<SignalLine>>,
+}
+
+impl ComponentTable {
+    pub fn signal_driver(&self, name: &str) -> Option<SignalDriver> {
+        self.signal_lines.get(name).map(SignalLine::driver)
+    }
 }
 
 pub struct Machine {
@@ -67,9 +151,56 @@ pub struct Machine {
     pub input_manager: Arc<InputManager>,
     pub system: GameSystem,
     pub scheduler: Scheduler,
+    pub fault_reporter: Arc<FaultReporter>,
+    pub rng: Arc<MachineRng>,
+    /// ROMs the user asked to run, empty for machines put together by hand
+    pub user_specified_roms: Vec<RomId>,
+    media_slots: HashMap<MediaSlotId, ComponentId>,
+    /// See [Machine::frame_audio]
+    frame_audio: Vec<AudioChunk>,
+    av_dumper: Option<AvDumper>,
+    capturer: Option<Capturer>,
+    frame_comparer: Option<FrameComparer>,
+    /// Where components run work that should not hold up the emulation, like writing saves
+    ///
+    /// Last so the components are gone, and done with their tasks, before it waits on them
+    pub executor: Arc<Executor>,
 }
 
 impl Machine {
+    /// Starts putting together a machine, components that depend on others have to be built after them
+    ///
+    /// ```
+    /// use multiemu::{
+    ///     definitions::misc::memory::standard::{
+    ///         StandardMemory, StandardMemoryConfig, StandardMemoryInitialContents,
+    ///     },
+    ///     machine::Machine,
+    ///     rom::{manager::RomManager, system::GameSystem},
+    /// };
+    /// use std::sync::Arc;
+    ///
+    /// let rom_manager = Arc::new(RomManager::new(None).unwrap());
+    /// let (machine, _) = Machine::build(GameSystem::Unknown, rom_manager)
+    ///     .insert_bus(0, 16)
+    ///     .build_component::<StandardMemory>(StandardMemoryConfig {
+    ///         readable: true,
+    ///         writable: true,
+    ///         max_word_size: 2,
+    ///         assigned_range: 0..0x1000,
+    ///         assigned_address_space: 0,
+    ///         initial_contents: StandardMemoryInitialContents::Value { value: 0xaa },
+    ///     })
+    ///     .unwrap();
+    /// let machine = machine.build().unwrap();
+    ///
+    /// let mut buffer = [0; 2];
+    /// machine
+    ///     .memory_translation_table
+    ///     .read(0x10, &mut buffer, 0)
+    ///     .unwrap();
+    /// assert_eq!(buffer, [0xaa; 2]);
+    /// ```
     pub fn build(game_system: GameSystem, rom_manager: Arc<RomManager>) -> MachineBuilder {
         MachineBuilder {
             current_component_index: ComponentId(0),
@@ -78,6 +209,11 @@ impl Machine {
             input_manager: InputManager::default(),
             system: game_system,
             memory_translation_table: MemoryTranslationTable::default(),
+            rng: Arc::default(),
+            frequency_changes: Arc::default(),
+            events: Arc::default(),
+            executor: Arc::default(),
+            allow_overlapping_memory: false,
         }
     }
 
@@ -87,8 +223,435 @@ impl Machine {
             .filter_map(|table| table.as_display.as_ref())
     }
 
+    pub fn audio_components(&self) -> impl Iterator<Item = &AudioComponentInfo> {
+        self.component_store
+            .components()
+            .filter_map(|table| table.as_audio.as_ref())
+    }
+
+    /// Components a debugger can look inside of, with their type names
+    pub fn debug_components(
+        &self,
+    ) -> impl Iterator<Item = (ComponentId, &'static str, &DebugComponentInfo)> {
+        self.component_store
+            .iter()
+            .filter_map(|(id, table)| Some((id, table.name, table.as_debug.as_ref()?)))
+    }
+
+    /// Does nothing once the machine has faulted, until it is reset
     pub fn run(&mut self) {
-        self.scheduler.run(&self.component_store);
+        // A run that does nothing made no sound either
+        self.frame_audio.clear();
+
+        if self.fault_reporter.is_faulted() {
+            return;
+        }
+
+        let ticks_run = match &self.av_dumper {
+            // Dumped frames all have to cover the same emulated time, no matter how fast the host is
+            Some(av_dumper)
+                if self.scheduler.step_granularity().is_none() && !self.scheduler.is_paused() =>
+            {
+                let frame_duration = av_dumper
+                    .next_frame_end()
+                    .saturating_sub(self.scheduler.emulated_time());
+
+                self.scheduler
+                    .run_for(&self.component_store, &self.fault_reporter, frame_duration)
+            }
+            _ => self
+                .scheduler
+                .run(&self.component_store, &self.fault_reporter),
+        };
+
+        // Runs that did nothing, like while paused, are not frames
+        if ticks_run != 0 {
+            self.input_manager.end_frame();
+            self.drain_audio();
+            self.dump_av_frame();
+            self.capture_frame();
+            self.compare_frame();
+        }
+    }
+
+    /// Runs one frame of `frame_duration` emulated time, even while paused or stepping, returning if it was a lag frame
+    pub fn run_frame(&mut self, frame_duration: Duration) -> bool {
+        self.frame_audio.clear();
+
+        if self.fault_reporter.is_faulted() {
+            return false;
+        }
+
+        self.scheduler
+            .run_for(&self.component_store, &self.fault_reporter, frame_duration);
+        let lagged = self.input_manager.end_frame();
+        self.drain_audio();
+        self.dump_av_frame();
+        self.capture_frame();
+        self.compare_frame();
+
+        lagged
+    }
+
+    /// How much emulated time a frame covers right now
+    pub fn frame_duration(&self) -> Duration {
+        self.av_dumper
+            .as_ref()
+            .map(AvDumper::frame_duration)
+            .unwrap_or_else(|| self.scheduler.allotted_time())
+    }
+
+    /// Writes every frame run from now on to `directory`, see [crate::runtime::av_dump]
+    pub fn start_av_dump(
+        &mut self,
+        directory: impl Into<PathBuf>,
+        frame_duration: Duration,
+    ) -> std::io::Result<()> {
+        self.av_dumper = Some(AvDumper::new(
+            directory,
+            frame_duration,
+            self.scheduler.emulated_time(),
+        )?);
+
+        Ok(())
+    }
+
+    /// What every audio component made over the last frame run, in the same order as [Machine::audio_components]
+    ///
+    /// Only good until the next run, so whatever plays it has to pick it up after every one
+    pub fn frame_audio(&self) -> &[AudioChunk] {
+        &self.frame_audio
+    }
+
+    fn drain_audio(&mut self) {
+        self.frame_audio = self
+            .audio_components()
+            .map(|component_info| AudioChunk {
+                sample_rate: component_info.sample_rate,
+                samples: component_info.component.drain_samples(),
+            })
+            .collect();
+    }
+
+    fn dump_av_frame(&mut self) {
+        let Some(av_dumper) = &mut self.av_dumper else {
+            return;
+        };
+
+        let framebuffers = self
+            .component_store
+            .components()
+            .filter_map(|table| table.as_display.as_ref())
+            .map(|component_info| component_info.component.get_framebuffer());
+
+        if let Err(err) = av_dumper.dump_frame(
+            framebuffers,
+            &self.frame_audio,
+            self.scheduler.elapsed_ticks(),
+            self.scheduler.emulated_time(),
+        ) {
+            tracing::error!("Stopping the AV dump: {}", err);
+            self.av_dumper = None;
+        }
+    }
+
+    /// Checks `rules` at the end of every frame run from now on, capturing to `directory`, see [crate::runtime::capture]
+    pub fn start_capturing(
+        &mut self,
+        directory: impl Into<PathBuf>,
+        rules: Vec<CaptureRule>,
+    ) -> std::io::Result<()> {
+        let capturer = Capturer::new(directory, rules)?;
+        self.memory_translation_table
+            .set_write_watches(capturer.watched_writes());
+        self.capturer = Some(capturer);
+
+        Ok(())
+    }
+
+    pub fn stop_capturing(&mut self) {
+        self.memory_translation_table.set_write_watches([]);
+        self.capturer = None;
+    }
+
+    fn capture_frame(&mut self) {
+        // Taken out so the machine can be snapshotted while it is in use
+        let Some(mut capturer) = self.capturer.take() else {
+            return;
+        };
+
+        let write_hits = self.memory_translation_table.take_write_watch_hits();
+        let captures = capturer.end_frame(&write_hits);
+        for kind in captures.iter().copied() {
+            let result = match kind {
+                CaptureKind::Screenshot => capturer.save_screenshots(
+                    self.display_components()
+                        .map(|component_info| component_info.component.get_framebuffer()),
+                ),
+                CaptureKind::State => capturer.save_state(&self.snapshot()),
+            };
+
+            if let Err(err) = result {
+                tracing::error!("Could not capture {:?}: {}", kind, err);
+            }
+        }
+
+        // Rules that only fire once may be gone now
+        if !captures.is_empty() {
+            self.memory_translation_table
+                .set_write_watches(capturer.watched_writes());
+        }
+        self.capturer = Some(capturer);
+    }
+
+    /// Diffs the displays against the reference frames in `reference_directory` at the end of every frame run from
+    /// now on, writing heatmaps to `heatmap_directory`, see [crate::runtime::frame_compare]
+    pub fn start_comparing_frames(
+        &mut self,
+        reference_directory: impl AsRef<Path>,
+        heatmap_directory: impl Into<PathBuf>,
+    ) -> std::io::Result<()> {
+        self.frame_comparer = Some(FrameComparer::new(reference_directory, heatmap_directory)?);
+
+        Ok(())
+    }
+
+    pub fn stop_comparing_frames(&mut self) {
+        self.frame_comparer = None;
+    }
+
+    /// [None] when frames are not being compared
+    pub fn frame_comparison_summary(&self) -> Option<&FrameComparisonSummary> {
+        self.frame_comparer.as_ref().map(FrameComparer::summary)
+    }
+
+    fn compare_frame(&mut self) {
+        let Some(frame_comparer) = &mut self.frame_comparer else {
+            return;
+        };
+
+        let framebuffers = self
+            .component_store
+            .components()
+            .filter_map(|table| table.as_display.as_ref())
+            .map(|component_info| component_info.component.get_framebuffer());
+
+        if let Err(err) = frame_comparer.end_frame(framebuffers) {
+            tracing::error!("Stopping the frame comparison: {}", err);
+            self.frame_comparer = None;
+        }
+    }
+
+    /// Freezes the machine, [Machine::run] does nothing until [Machine::resume] is called
+    pub fn pause(&mut self) {
+        self.scheduler.pause();
+    }
+
+    pub fn resume(&mut self) {
+        self.scheduler.resume();
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.scheduler.is_paused()
+    }
+
+    /// See [Scheduler::set_step_granularity]
+    pub fn set_step_granularity(&mut self, step_granularity: Option<StepGranularity>) {
+        self.scheduler.set_step_granularity(step_granularity);
+    }
+
+    pub fn step_granularity(&self) -> Option<StepGranularity> {
+        self.scheduler.step_granularity()
+    }
+
+    pub fn step(&mut self) {
+        self.scheduler.step();
+    }
+
+    /// See [Scheduler::set_speed]
+    pub fn set_speed(&mut self, speed: Ratio<u64>) {
+        self.scheduler.set_speed(speed);
+    }
+
+    pub fn speed(&self) -> Ratio<u64> {
+        self.scheduler.speed()
+    }
+
+    /// See [Scheduler::set_fixed_timestep]
+    pub fn set_fixed_timestep(&mut self, ticks: Option<u64>) {
+        self.scheduler.set_fixed_timestep(ticks);
+    }
+
+    /// See [Scheduler::set_component_frequency]
+    pub fn set_component_frequency(&mut self, component_id: ComponentId, frequency: Ratio<u64>) {
+        self.scheduler
+            .set_component_frequency(&self.component_store, component_id, frequency);
+    }
+
+    /// Resets every component and starts the schedule over
+    ///
+    /// Components go in the order they were built, so anything a component was handed while being built has already
+    /// been reset by the time it is
+    pub fn reset(&mut self, kind: ResetKind) {
+        tracing::info!("{:?} resetting the machine", kind);
+
+        // Components schedule whatever they need again while resetting
+        self.scheduler.events().clear();
+
+        for component_table in self.component_store.components() {
+            component_table.component.reset(kind);
+        }
+
+        self.scheduler.restart(&self.component_store);
+        self.fault_reporter.clear();
+        self.input_manager.reset_lag_frames();
+        self.memory_translation_table.clear_unmapped_accesses();
+    }
+
+    /// Has every component write out save RAM and drop what it holds outside the machine, like display surfaces
+    ///
+    /// The machine should not be run again afterwards
+    pub fn shutdown(&self) {
+        for component_table in self.component_store.components() {
+            component_table.component.shutdown();
+        }
+    }
+
+    pub fn fault(&self) -> Option<MachineFault> {
+        self.fault_reporter.fault()
+    }
+
+    pub fn media_slots(&self) -> impl Iterator<Item = (&MediaSlotId, &MediaSlotMetadata)> {
+        self.component_store
+            .components()
+            .filter_map(|table| table.as_media.as_ref())
+            .flat_map(|media_component_info| media_component_info.slots.iter())
+    }
+
+    /// Inserts a ROM into the named slot, or ejects the current one with [None]
+    ///
+    /// The ROM must already be known to the [RomManager]
+    pub fn swap_media(
+        &mut self,
+        slot: &MediaSlotId,
+        rom_id: Option<RomId>,
+    ) -> Result<(), MediaSwapError> {
+        let media_component_info = self.media_component_info(slot)?;
+
+        tracing::info!("Swapping media in slot {} to {:?}", slot, rom_id);
+        media_component_info.component.swap_media(slot, rom_id)?;
+
+        if media_component_info.slots[slot].requires_reset {
+            self.reset(ResetKind::Hard);
+        }
+
+        Ok(())
+    }
+
+    /// The component with this label, which unlike its [ComponentId] does not change when the machine gains components
+    pub fn component_by_name(&self, label: &str) -> Option<(ComponentId, &ComponentTable)> {
+        let component_id = self.component_store.by_label(label)?;
+
+        Some((component_id, self.component_store.get(component_id)?))
+    }
+
+    /// The first component of this type, for the frontend to poke at peripherals like tape decks it knows about
+    pub fn find_component<C: Component>(&self) -> Option<Arc<C>> {
+        self.component_store
+            .components()
+            .find_map(|table| table.component.clone().into_any_arc().downcast::<C>().ok())
+    }
+
+    fn media_component_info(
+        &self,
+        slot: &MediaSlotId,
+    ) -> Result<&MediaComponentInfo, MediaSwapError> {
+        self.media_slots
+            .get(slot)
+            .and_then(|component_id| self.component_store.get(*component_id))
+            .and_then(|table| table.as_media.as_ref())
+            .ok_or_else(|| MediaSwapError::UnknownSlot(slot.clone()))
+    }
+}
+
+/// A mistake in how a machine definition wired its components together
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum MachineBuildError {
+    #[error("Component {0:?} does not exist, components can only use ones built before them")]
+    MissingComponent(ComponentId),
+    #[error("{component} is not a {expected}")]
+    WrongComponentType {
+        component: String,
+        expected: &'static str,
+    },
+    #[error("{0} is not a memory component")]
+    NotMemoryComponent(String),
+    #[error("{component} does not take {message} messages")]
+    NoMailbox {
+        component: String,
+        message: &'static str,
+    },
+    #[error("{component} has no signal line called {name}")]
+    NoSignalLine { component: String, name: String },
+    #[error("{0} did not initialize itself")]
+    UninitializedComponent(&'static str),
+    #[error("Address space {0} is used without being inserted first")]
+    UndefinedAddressSpace(AddressSpaceId),
+    #[error(
+        "{first} and {second} are both assigned {range:#06x?} on address space {address_space}"
+    )]
+    OverlappingMemory {
+        address_space: AddressSpaceId,
+        range: Range<usize>,
+        first: String,
+        second: String,
+    },
+    #[error("Media slot {0} is registered twice")]
+    DuplicateMediaSlot(MediaSlotId),
+    #[error("No component is called {0}")]
+    UnknownComponentName(String),
+    #[error("More than one component is called {0}")]
+    DuplicateComponentName(String),
+    #[error("The rng seed has to be set before any component is built")]
+    LateRngSeed,
+    #[error("Too many components")]
+    TooManyComponents,
+    #[error("Too many gamepads")]
+    TooManyGamepads,
+    #[error("{component} cannot do {size} byte accesses")]
+    InvalidWordSize {
+        component: &'static str,
+        size: usize,
+    },
+    #[error("{0} was not assigned any memory")]
+    EmptyMemoryRange(&'static str),
+    #[error("{component} cannot be a {variant} yet")]
+    UnimplementedVariant {
+        component: &'static str,
+        variant: String,
+    },
+    #[error("{component} needs a {purpose} address space")]
+    MissingAddressSpace {
+        component: &'static str,
+        purpose: &'static str,
+    },
+    #[error("{0} is not supported by this emulator")]
+    UnsupportedSystem(GameSystem),
+    #[error(
+        "{0}, see {url} for what is being worked on",
+        url = definition::COMPATIBILITY_TRACKER_URL
+    )]
+    UnsupportedRom(#[from] definition::UnsupportedRom),
+    #[error(transparent)]
+    Remap(#[from] RemapError),
+    /// Only the message is kept, so these stay comparable
+    #[error("{0}")]
+    MediaSwap(String),
+}
+
+impl From<MediaSwapError> for MachineBuildError {
+    fn from(err: MediaSwapError) -> Self {
+        Self::MediaSwap(err.to_string())
     }
 }
 
@@ -99,19 +662,46 @@ pub struct MachineBuilder {
     input_manager: InputManager,
     pub rom_manager: Arc<RomManager>,
     pub system: GameSystem,
+    /// Components that need randomness should take this while being built
+    pub rng: Arc<MachineRng>,
+    /// Components that change how often they run, like a processor with a double speed mode, should take this while being built
+    pub frequency_changes: Arc<FrequencyChanges>,
+    /// Event driven components should take this while being built to schedule their wakeups
+    pub events: Arc<EventQueue>,
+    /// Components with work to do off the emulation thread should spawn it here while being built
+    pub executor: Arc<Executor>,
+    allow_overlapping_memory: bool,
 }
 
 impl MachineBuilder {
     pub fn build_component<C: FromConfig>(
+        self,
+        config: C::Config,
+    ) -> Result<(MachineBuilder, ComponentId), MachineBuildError> {
+        self.insert_component::<C>(None, config)
+    }
+
+    /// Like [MachineBuilder::build_component], but with a label picked by the machine instead of the component, which
+    /// has to be unique, see [Machine::component_by_name]
+    pub fn build_labeled_component<C: FromConfig>(
+        self,
+        label: impl Into<String>,
+        config: C::Config,
+    ) -> Result<(MachineBuilder, ComponentId), MachineBuildError> {
+        self.insert_component::<C>(Some(label.into()), config)
+    }
+
+    fn insert_component<C: FromConfig>(
         mut self,
+        label: Option<String>,
         config: C::Config,
-    ) -> (MachineBuilder, ComponentId) {
+    ) -> Result<(MachineBuilder, ComponentId), MachineBuildError> {
         let id = self.current_component_index;
         self.current_component_index = ComponentId(
             self.current_component_index
                 .0
                 .checked_add(1)
-                .expect("Too many components"),
+                .ok_or(MachineBuildError::TooManyComponents)?,
         );
 
         let mut component_builder = ComponentBuilder {
@@ -119,16 +709,25 @@ impl MachineBuilder {
             machine: self,
             component: None,
             as_schedulable: None,
+            as_event_driven: None,
             as_display: None,
+            as_audio: None,
             as_input: None,
             as_memory: None,
+            as_media: None,
+            as_debug: None,
+            mailbox: None,
+            signal_lines: HashMap::default(),
+            label: None,
         };
-        C::from_config(&mut component_builder, config);
+        C::from_config(&mut component_builder, config)?;
 
-        (component_builder.build(), id)
+        Ok((component_builder.build(label)?, id))
     }
 
-    pub fn default_component<C: FromConfig>(self) -> (MachineBuilder, ComponentId)
+    pub fn default_component<C: FromConfig>(
+        self,
+    ) -> Result<(MachineBuilder, ComponentId), MachineBuildError>
     where
         C::Config: Default,
     {
@@ -136,22 +735,200 @@ impl MachineBuilder {
         self.build_component::<C>(config)
     }
 
+    /// Seeds the machine randomness, this has to come before any component is built
+    pub fn rng_seed(mut self, seed: u64) -> Result<MachineBuilder, MachineBuildError> {
+        if self.component_store.components().next().is_some() {
+            return Err(MachineBuildError::LateRngSeed);
+        }
+
+        self.rng = Arc::new(MachineRng::new(seed));
+        Ok(self)
+    }
+
     pub fn insert_bus(mut self, id: AddressSpaceId, width: u8) -> MachineBuilder {
         self.memory_translation_table.insert_bus(id, width);
         self
     }
 
-    pub fn get_component<C: Component>(&self, id: ComponentId) -> Option<Arc<C>> {
-        self.component_store
-            .get(id)?
+    /// See [MemoryTranslationTable::set_wait_states], the bus has to be inserted first
+    pub fn wait_states(
+        self,
+        address_space: AddressSpaceId,
+        range: Range<usize>,
+        wait_states: u32,
+    ) -> Result<MachineBuilder, MachineBuildError> {
+        self.memory_translation_table
+            .set_wait_states(address_space, range, wait_states)?;
+        Ok(self)
+    }
+
+    /// Lets memory components cover ones built before them instead of that being an error, the later one wins
+    ///
+    /// [Machine::lint] still points the overlaps out
+    pub fn allow_overlapping_memory(mut self) -> MachineBuilder {
+        self.allow_overlapping_memory = true;
+        self
+    }
+
+    /// Mirrors an already built memory component over `range`, with addresses there put through `mask` first
+    ///
+    /// Unlike [crate::definitions::misc::memory::mirror::MirrorMemory] this is resolved in the same lookup as any
+    /// other access
+    pub fn mirror_memory(
+        mut self,
+        id: ComponentId,
+        address_space: AddressSpaceId,
+        range: Range<usize>,
+        mask: usize,
+    ) -> Result<MachineBuilder, MachineBuildError> {
+        let component_table = self
+            .component_store
+            .get_mut(id)
+            .ok_or(MachineBuildError::MissingComponent(id))?;
+        let name = component_name(id, component_table);
+
+        component_table
+            .as_memory
+            .as_mut()
+            .ok_or(MachineBuildError::NotMemoryComponent(name))?
+            .mirrored_ranges
+            .entry(address_space)
+            .or_default()
+            .insert(range, mask);
+
+        Ok(self)
+    }
+
+    /// Exposes a slot on an already built component that media can be swapped in and out of
+    pub fn insert_media_slot<C: MediaComponent>(
+        mut self,
+        id: ComponentId,
+        slot: MediaSlotId,
+        metadata: MediaSlotMetadata,
+    ) -> Result<MachineBuilder, MachineBuildError> {
+        let component = self.get_component::<C>(id)?;
+
+        self.component_store.tables[id.0 as usize]
+            .as_media
+            .get_or_insert_with(|| MediaComponentInfo {
+                component,
+                slots: HashMap::default(),
+            })
+            .slots
+            .insert(slot, metadata);
+
+        Ok(self)
+    }
+
+    /// Lets a component post `M`s to an already built one, if it took a [Mailbox] for them
+    pub fn message_sender<M: Send + 'static>(
+        &self,
+        id: ComponentId,
+    ) -> Result<MessageSender<M>, MachineBuildError> {
+        let component_table = self.component_table(id)?;
+
+        component_table
+            .mailbox
+            .clone()
+            .and_then(|mailbox| mailbox.downcast::<Mailbox<M>>().ok())
+            .map(MessageSender)
+            .ok_or_else(|| MachineBuildError::NoMailbox {
+                component: component_name(id, component_table),
+                message: type_name::<M>(),
+            })
+    }
+
+    /// Lets a component hold an input of an already built one, if it took a [SignalLine] by that name
+    pub fn signal_driver(
+        &self,
+        id: ComponentId,
+        name: &str,
+    ) -> Result<SignalDriver, MachineBuildError> {
+        let component_table = self.component_table(id)?;
+
+        component_table
+            .signal_driver(name)
+            .ok_or_else(|| MachineBuildError::NoSignalLine {
+                component: component_name(id, component_table),
+                name: name.to_string(),
+            })
+    }
+
+    pub fn get_component<C: Component>(
+        &self,
+        id: ComponentId,
+    ) -> Result<Arc<C>, MachineBuildError> {
+        let component_table = self.component_table(id)?;
+
+        component_table
             .component
             .clone()
             .into_any_arc()
             .downcast::<C>()
-            .ok()
+            .map_err(|_| MachineBuildError::WrongComponentType {
+                component: component_name(id, component_table),
+                expected: type_name::<C>(),
+            })
+    }
+
+    fn component_table(&self, id: ComponentId) -> Result<&ComponentTable, MachineBuildError> {
+        self.component_store
+            .get(id)
+            .ok_or(MachineBuildError::MissingComponent(id))
+    }
+
+    /// Every memory component has to sit on busses that were inserted, and unless overlaps were allowed none can
+    /// cover another
+    fn check_memory(&self) -> Result<(), MachineBuildError> {
+        let mut claimed: HashMap<AddressSpaceId, RangeMap<usize, ComponentId>> = HashMap::default();
+
+        for (component_id, component_table, memory_component_info) in self
+            .component_store
+            .iter()
+            .filter_map(|(component_id, component_table)| {
+                Some((
+                    component_id,
+                    component_table,
+                    component_table.as_memory.as_ref()?,
+                ))
+            })
+        {
+            for (address_space_id, ranges) in memory_component_info.claimed_ranges() {
+                if !self
+                    .memory_translation_table
+                    .busses()
+                    .any(|(id, _)| id == address_space_id)
+                {
+                    return Err(MachineBuildError::UndefinedAddressSpace(address_space_id));
+                }
+
+                if self.allow_overlapping_memory {
+                    continue;
+                }
+
+                let claimed = claimed.entry(address_space_id).or_default();
+                for range in ranges.iter() {
+                    if let Some((claimed_range, other_id)) = claimed.overlapping(range).next() {
+                        return Err(MachineBuildError::OverlappingMemory {
+                            address_space: address_space_id,
+                            range: claimed_range.start.max(range.start)
+                                ..claimed_range.end.min(range.end),
+                            first: component_name(*other_id, self.component_table(*other_id)?),
+                            second: component_name(component_id, component_table),
+                        });
+                    }
+
+                    claimed.insert(range.clone(), component_id);
+                }
+            }
+        }
+
+        Ok(())
     }
 
-    pub fn build(mut self) -> Machine {
+    pub fn build(mut self) -> Result<Machine, MachineBuildError> {
+        self.check_memory()?;
+
         for (address_space_id, assigned_ranges, component_id) in self
             .component_store
             .iter()
@@ -175,6 +952,25 @@ impl MachineBuilder {
             );
         }
 
+        for (component_id, memory_component_info) in
+            self.component_store
+                .iter()
+                .filter_map(|(component_id, component_table)| {
+                    Some((component_id, component_table.as_memory.as_ref()?))
+                })
+        {
+            for (address_space_id, mirrored_ranges) in &memory_component_info.mirrored_ranges {
+                for (range, mask) in mirrored_ranges.iter() {
+                    self.memory_translation_table.insert_mirror(
+                        *address_space_id,
+                        component_id,
+                        range.clone(),
+                        *mask,
+                    );
+                }
+            }
+        }
+
         // Setup emulated gamepad types
         for (emulated_gamepad_type_id, emulated_gamepad_metadata) in self
             .component_store
@@ -214,7 +1010,9 @@ impl MachineBuilder {
             })
             .enumerate()
         {
-            let emulated_gamepad_id = raw_gamepad_id.try_into().expect("Too many gamepads!");
+            let emulated_gamepad_id = raw_gamepad_id
+                .try_into()
+                .map_err(|_| MachineBuildError::TooManyGamepads)?;
             emulated_gamepad_ids
                 .entry(component_id)
                 .or_default()
@@ -223,6 +1021,31 @@ impl MachineBuilder {
                 .register_emulated_gamepad(emulated_gamepad_id, gamepad_type_id.clone());
         }
 
+        let mut media_slots = HashMap::default();
+
+        // Make sure media slot names are unique across the machine
+        for (component_id, slot) in
+            self.component_store
+                .iter()
+                .flat_map(|(component_id, component_table)| {
+                    component_table
+                        .as_media
+                        .iter()
+                        .flat_map(|media_component_info| media_component_info.slots.keys())
+                        .map(move |slot| (component_id, slot.clone()))
+                })
+        {
+            tracing::debug!(
+                "Registering media slot {} for component {:?}",
+                slot,
+                component_id
+            );
+
+            if media_slots.insert(slot.clone(), component_id).is_some() {
+                return Err(MachineBuildError::DuplicateMediaSlot(slot));
+            }
+        }
+
         let component_store = Arc::new(self.component_store);
 
         self.memory_translation_table
@@ -230,21 +1053,31 @@ impl MachineBuilder {
         let memory_translation_table = Arc::new(self.memory_translation_table);
 
         let machine = Machine {
-            scheduler: Scheduler::new(&component_store),
+            scheduler: Scheduler::new(&component_store, self.frequency_changes, self.events),
             rom_manager: self.rom_manager,
             memory_translation_table,
             component_store,
             input_manager: Arc::new(self.input_manager),
             system: self.system,
+            fault_reporter: Arc::default(),
+            rng: self.rng,
+            user_specified_roms: Vec::default(),
+            media_slots,
+            frame_audio: Vec::default(),
+            av_dumper: None,
+            capturer: None,
+            frame_comparer: None,
+            executor: self.executor,
         };
 
-        // Set the memory translation tables for everything
+        // Set the memory translation tables and fault reporter for everything
         for component in machine
             .component_store
             .components()
             .map(|component_table| &component_table.component)
         {
             component.set_memory_translation_table(machine.memory_translation_table.clone());
+            component.set_fault_reporter(machine.fault_reporter.clone());
         }
 
         // Set up input for only input components
@@ -260,17 +1093,29 @@ impl MachineBuilder {
                 .set_input_manager(machine.input_manager.clone(), &gamepad_ids);
         }
 
-        machine
+        Ok(machine)
     }
 }
 
+/// Names are only unique together with the id, there are usually a few of the same memory type
+fn component_name(id: ComponentId, component_table: &ComponentTable) -> String {
+    format!("{} ({})", component_table.name, id.0)
+}
+
 pub struct ComponentBuilder<C: Component> {
     id: ComponentId,
     component: Option<Arc<C>>,
     as_schedulable: Option<SchedulableComponentInfo>,
+    as_event_driven: Option<EventDrivenComponentInfo>,
     as_display: Option<DisplayComponentInfo>,
+    as_audio: Option<AudioComponentInfo>,
     as_input: Option<InputComponentInfo>,
     as_memory: Option<MemoryComponentInfo>,
+    as_media: Option<MediaComponentInfo>,
+    as_debug: Option<DebugComponentInfo>,
+    mailbox: Option<Arc<dyn Any + Send + Sync>>,
+    signal_lines: HashMap<&'static str, Arc<SignalLine>>,
+    label: Option<String>,
     machine: MachineBuilder,
 }
 
@@ -302,6 +1147,19 @@ impl<C: Component> ComponentBuilder<C> {
         self
     }
 
+    /// Has the component woken up through [MachineBuilder::events] instead of running on a schedule
+    pub fn set_event_driven(&mut self) -> &mut Self
+    where
+        C: EventDrivenComponent,
+    {
+        self.as_event_driven = self
+            .component
+            .clone()
+            .map(|c| EventDrivenComponentInfo { component: c });
+
+        self
+    }
+
     pub fn set_display(&mut self) -> &mut Self
     where
         C: DisplayComponent,
@@ -314,6 +1172,31 @@ impl<C: Component> ComponentBuilder<C> {
         self
     }
 
+    pub fn set_audio(&mut self, sample_rate: u32) -> &mut Self
+    where
+        C: AudioComponent,
+    {
+        self.as_audio = self.component.clone().map(|c| AudioComponentInfo {
+            component: c,
+            sample_rate,
+        });
+
+        self
+    }
+
+    /// Lets debuggers see inside the component, see [IntrospectableComponent]
+    pub fn set_debug(&mut self) -> &mut Self
+    where
+        C: IntrospectableComponent,
+    {
+        self.as_debug = self
+            .component
+            .clone()
+            .map(|c| DebugComponentInfo { component: c });
+
+        self
+    }
+
     pub fn set_memory(
         &mut self,
         ranges: impl IntoIterator<Item = (AddressSpaceId, Range<usize>)>,
@@ -333,6 +1216,7 @@ impl<C: Component> ComponentBuilder<C> {
         self.as_memory = self.component.clone().map(|c| MemoryComponentInfo {
             component: c,
             assigned_ranges,
+            mirrored_ranges: HashMap::default(),
         });
 
         self
@@ -357,25 +1241,209 @@ impl<C: Component> ComponentBuilder<C> {
         self
     }
 
+    /// Sets up somewhere for other components to post work to this one, see [MachineBuilder::message_sender]
+    ///
+    /// There is only one per component, so use an enum for more than one kind of message
+    pub fn mailbox<M: Send + 'static>(&mut self) -> Arc<Mailbox<M>> {
+        assert!(self.mailbox.is_none(), "Component already has a mailbox");
+
+        let mailbox = Arc::new(Mailbox::default());
+        self.mailbox = Some(mailbox.clone());
+
+        mailbox
+    }
+
+    /// Sets up an input other components can hold, see [MachineBuilder::signal_driver]
+    pub fn signal_line(&mut self, name: &'static str) -> Arc<SignalLine> {
+        let signal_line = Arc::new(SignalLine::default());
+
+        assert!(
+            self.signal_lines
+                .insert(name, signal_line.clone())
+                .is_none(),
+            "Signal line {} registered twice",
+            name
+        );
+
+        signal_line
+    }
+
     pub fn id(&self) -> ComponentId {
         self.id
     }
 
+    /// What the component goes by unless the machine gives it a label of its own, like "cpu". Taken labels get numbered,
+    /// and components without one go by their type name
+    pub fn set_label(&mut self, label: impl Into<String>) -> &mut Self {
+        self.label = Some(label.into());
+
+        self
+    }
+
     pub fn machine(&self) -> &MachineBuilder {
         &self.machine
     }
 
-    fn build(mut self) -> MachineBuilder {
-        assert!(self.machine.component_store.0.len() == self.id.0 as usize);
+    fn build(mut self, label: Option<String>) -> Result<MachineBuilder, MachineBuildError> {
+        assert!(self.machine.component_store.tables.len() == self.id.0 as usize);
+
+        let label = match label {
+            Some(label) if self.machine.component_store.by_label(&label).is_some() => {
+                return Err(MachineBuildError::DuplicateComponentName(label));
+            }
+            Some(label) => label,
+            None => self.machine.component_store.free_label(
+                &self
+                    .label
+                    .take()
+                    .unwrap_or_else(|| type_label(type_name::<C>())),
+            ),
+        };
 
-        self.machine.component_store.0.push(ComponentTable {
-            component: self.component.expect("Component did not initialize itself"),
+        self.machine.component_store.insert(ComponentTable {
+            name: type_name::<C>(),
+            label,
+            component: self
+                .component
+                .ok_or(MachineBuildError::UninitializedComponent(type_name::<C>()))?,
             as_schedulable: self.as_schedulable,
+            as_event_driven: self.as_event_driven,
             as_display: self.as_display,
+            as_audio: self.as_audio,
             as_input: self.as_input,
             as_memory: self.as_memory,
+            as_media: self.as_media,
+            as_debug: self.as_debug,
+            mailbox: self.mailbox,
+            signal_lines: self.signal_lines,
         });
 
-        self.machine
+        Ok(self.machine)
+    }
+}
+
+/// Label for a component that did not pick one, `Chip8Display` becomes `chip8_display`
+fn type_label(type_name: &str) -> String {
+    let type_name = type_name.split('<').next().unwrap_or_default();
+    let type_name = type_name.rsplit("::").next().unwrap_or_default();
+    let mut label = String::with_capacity(type_name.len() + 4);
+    let mut previous = None;
+
+    for character in type_name.chars() {
+        if character.is_uppercase()
+            && previous
+                .is_some_and(|previous: char| previous.is_lowercase() || previous.is_numeric())
+        {
+            label.push('_');
+        }
+
+        label.extend(character.to_lowercase());
+        previous = Some(character);
+    }
+
+    label
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::definitions::misc::memory::standard::{
+        StandardMemory, StandardMemoryConfig, StandardMemoryInitialContents,
+    };
+
+    fn memory(address_space: AddressSpaceId, assigned_range: Range<usize>) -> StandardMemoryConfig {
+        StandardMemoryConfig {
+            readable: true,
+            writable: true,
+            max_word_size: 1,
+            assigned_range,
+            assigned_address_space: address_space,
+            initial_contents: StandardMemoryInitialContents::Value { value: 0 },
+        }
+    }
+
+    #[test]
+    fn bad_wiring_is_an_error() {
+        let rom_manager = Arc::new(RomManager::new(None).unwrap());
+
+        let (machine, _) = Machine::build(GameSystem::Unknown, rom_manager.clone())
+            .insert_bus(0, 8)
+            .build_component::<StandardMemory>(memory(0, 0x00..0x80))
+            .unwrap();
+        let (machine, _) = machine
+            .build_component::<StandardMemory>(memory(0, 0x40..0xc0))
+            .unwrap();
+        assert!(matches!(
+            machine.build(),
+            Err(MachineBuildError::OverlappingMemory { range, .. }) if range == (0x40..0x80)
+        ));
+
+        let (machine, _) = Machine::build(GameSystem::Unknown, rom_manager.clone())
+            .build_component::<StandardMemory>(memory(1, 0x00..0x80))
+            .unwrap();
+        assert_eq!(
+            machine.build().err(),
+            Some(MachineBuildError::UndefinedAddressSpace(1))
+        );
+
+        let (machine, memory_id) = Machine::build(GameSystem::Unknown, rom_manager)
+            .insert_bus(0, 8)
+            .build_component::<StandardMemory>(memory(0, 0x00..0x80))
+            .unwrap();
+        assert_eq!(
+            machine
+                .get_component::<StandardMemory>(ComponentId(1))
+                .err(),
+            Some(MachineBuildError::MissingComponent(ComponentId(1)))
+        );
+        assert!(matches!(
+            machine.signal_driver(memory_id, "IRQ"),
+            Err(MachineBuildError::NoSignalLine { .. })
+        ));
+    }
+
+    #[test]
+    fn labels() {
+        assert_eq!(type_label(type_name::<StandardMemory>()), "standard_memory");
+        assert_eq!(type_label("crate::I8080"), "i8080");
+        assert_eq!(type_label("crate::NesPPU"), "nes_ppu");
+
+        let rom_manager = Arc::new(RomManager::new(None).unwrap());
+        let (machine, _) = Machine::build(GameSystem::Unknown, rom_manager.clone())
+            .insert_bus(0, 8)
+            .build_component::<StandardMemory>(memory(0, 0x00..0x40))
+            .unwrap();
+        let (machine, second_id) = machine
+            .build_component::<StandardMemory>(memory(0, 0x40..0x80))
+            .unwrap();
+        let (machine, wram_id) = machine
+            .build_labeled_component::<StandardMemory>("wram", memory(0, 0x80..0xc0))
+            .unwrap();
+        let machine = machine.build().unwrap();
+        assert_eq!(
+            machine
+                .component_by_name("standard_memory#2")
+                .map(|(component_id, _)| component_id),
+            Some(second_id)
+        );
+        assert_eq!(
+            machine
+                .component_by_name("wram")
+                .map(|(component_id, _)| component_id),
+            Some(wram_id)
+        );
+
+        let (machine, _) = Machine::build(GameSystem::Unknown, rom_manager)
+            .insert_bus(0, 8)
+            .build_labeled_component::<StandardMemory>("wram", memory(0, 0x00..0x40))
+            .unwrap();
+        assert_eq!(
+            machine
+                .build_labeled_component::<StandardMemory>("wram", memory(0, 0x40..0x80))
+                .err(),
+            Some(MachineBuildError::DuplicateComponentName(
+                "wram".to_string()
+            ))
+        );
     }
 }
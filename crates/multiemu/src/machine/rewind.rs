@@ -0,0 +1,515 @@
+//! Keeps the last stretch of play around so it can be stepped back through, see [RewindBuffer]
+
+use super::{
+    serialization::{MachineState, SavestateError},
+    Machine,
+};
+use crate::host_cpu::multiversion;
+use std::collections::VecDeque;
+
+/// Memory the rewind buffer can take up before the oldest steps are dropped, until told otherwise
+pub const DEFAULT_REWIND_BUDGET: usize = 64 * 1024 * 1024;
+/// Runs of the machine between each step the rewind buffer captures
+pub const DEFAULT_REWIND_INTERVAL: u32 = 2;
+
+/// Unchanged bytes in a row it takes to end a literal, shorter runs are cheaper to copy than to skip
+const MIN_SKIP: usize = 8;
+
+/// The newest step, kept whole so the older ones can be rebuilt from it
+#[derive(Debug)]
+struct Capture {
+    scheduler: Vec<u8>,
+    rng_seed: Option<u64>,
+    /// Encoded snapshot of every component in id order, and the generation it was taken at
+    components: Vec<(Option<u64>, Vec<u8>)>,
+}
+
+impl Capture {
+    fn size(&self) -> usize {
+        size_of::<Self>()
+            + self.scheduler.len()
+            + self
+                .components
+                .iter()
+                .map(|(_, bytes)| size_of::<(Option<u64>, Vec<u8>)>() + bytes.len())
+                .sum::<usize>()
+    }
+}
+
+/// Turns a [Capture] back into the one taken before it
+#[derive(Debug)]
+struct Delta {
+    scheduler: Patch,
+    rng_seed: Option<u64>,
+    /// [None] for every component that did not change between the two
+    components: Vec<Option<Patch>>,
+}
+
+impl Delta {
+    fn size(&self) -> usize {
+        size_of::<Self>()
+            + self.scheduler.data.len()
+            + self
+                .components
+                .iter()
+                .map(|patch| {
+                    size_of::<Option<Patch>>() + patch.as_ref().map_or(0, |patch| patch.data.len())
+                })
+                .sum::<usize>()
+    }
+}
+
+/// What has to be flipped to turn one encoding into another, with the unchanged stretches left out
+#[derive(Debug)]
+struct Patch {
+    /// Length of what this turns things into
+    len: usize,
+    /// Pairs of how much to skip and a literal to xor in, each length a LEB128 varint
+    data: Vec<u8>,
+}
+
+impl Patch {
+    fn new(from: &[u8], to: &[u8]) -> Self {
+        let length = from.len().max(to.len());
+        let byte = |index: usize| {
+            from.get(index).copied().unwrap_or_default()
+                ^ to.get(index).copied().unwrap_or_default()
+        };
+        let mut data = Vec::new();
+        let mut index = 0;
+
+        while index < length {
+            let skip_start = index;
+            // Most of a snapshot stays the same between steps, so get through the bulk of it quickly
+            index += matching_prefix(
+                from.get(index..).unwrap_or_default(),
+                to.get(index..).unwrap_or_default(),
+            );
+            while index < length && byte(index) == 0 {
+                index += 1;
+            }
+
+            let literal_start = index;
+            let mut unchanged = 0;
+            while index < length && unchanged < MIN_SKIP {
+                if byte(index) == 0 {
+                    unchanged += 1;
+                } else {
+                    unchanged = 0;
+                }
+
+                index += 1;
+            }
+            // Leave the unchanged tail for the next skip
+            index -= unchanged;
+
+            // Whatever is past the end is filled in when applied anyway
+            if literal_start == index {
+                break;
+            }
+
+            write_varint(&mut data, literal_start - skip_start);
+            write_varint(&mut data, index - literal_start);
+            data.extend((literal_start..index).map(byte));
+        }
+
+        Self {
+            len: to.len(),
+            data,
+        }
+    }
+
+    fn apply(&self, bytes: &mut Vec<u8>) {
+        bytes.resize(bytes.len().max(self.len), 0);
+
+        let mut data = self.data.as_slice();
+        let mut index = 0;
+
+        while !data.is_empty() {
+            index += read_varint(&mut data);
+            let literal = read_varint(&mut data);
+
+            xor_into(&mut bytes[index..index + literal], &data[..literal]);
+
+            data = &data[literal..];
+            index += literal;
+        }
+
+        bytes.truncate(self.len);
+    }
+}
+
+multiversion! {
+    fn xor_into(bytes: &mut [u8], patch: &[u8]) {
+        for (byte, patch) in bytes.iter_mut().zip(patch) {
+            *byte ^= patch;
+        }
+    }
+}
+
+multiversion! {
+    /// How many bytes at the start of both are the same, only as far as the shorter one goes
+    fn matching_prefix(from: &[u8], to: &[u8]) -> usize {
+        let length = from.len().min(to.len());
+        let (from, to) = (&from[..length], &to[..length]);
+        let mut matching = 0;
+
+        // Whole chunks are checked without branching on every byte, which is what lets them vectorize
+        for (from, to) in from.chunks_exact(64).zip(to.chunks_exact(64)) {
+            let difference = from
+                .iter()
+                .zip(to)
+                .fold(0, |difference, (from, to)| difference | (from ^ to));
+
+            if difference != 0 {
+                break;
+            }
+
+            matching += 64;
+        }
+
+        matching
+            + from[matching..]
+                .iter()
+                .zip(&to[matching..])
+                .take_while(|(from, to)| from == to)
+                .count()
+    }
+}
+
+fn write_varint(data: &mut Vec<u8>, mut value: usize) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+
+        if value == 0 {
+            data.push(byte);
+            return;
+        }
+
+        data.push(byte | 0x80);
+    }
+}
+
+fn read_varint(data: &mut &[u8]) -> usize {
+    let mut value = 0;
+    let mut shift = 0;
+
+    loop {
+        let byte = data[0];
+        *data = &data[1..];
+        value |= ((byte & 0x7f) as usize) << shift;
+        shift += 7;
+
+        if byte & 0x80 == 0 {
+            return value;
+        }
+    }
+}
+
+/// Snapshots the machine every few runs into a ring of deltas, so play can be stepped back through
+///
+/// Components that report a [crate::component::Component::snapshot_generation] are only snapshotted again when it
+/// changes, and only what changed between two steps is kept, so big memories that barely change stay cheap
+#[derive(Debug)]
+pub struct RewindBuffer {
+    latest: Option<Capture>,
+    /// Oldest first
+    history: VecDeque<Delta>,
+    size: usize,
+    budget: usize,
+    interval: u32,
+    runs_since_capture: u32,
+}
+
+impl RewindBuffer {
+    /// A budget of 0 turns rewinding off
+    pub fn new(budget: usize, interval: u32) -> Self {
+        Self {
+            latest: None,
+            history: VecDeque::default(),
+            size: 0,
+            budget,
+            interval: interval.max(1),
+            runs_since_capture: 0,
+        }
+    }
+
+    /// Drops the oldest steps until it fits
+    pub fn set_budget(&mut self, budget: usize) {
+        self.budget = budget;
+
+        if budget == 0 {
+            self.clear();
+        } else {
+            self.evict();
+        }
+    }
+
+    /// Roughly how much memory the steps take up
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// How many steps back can be taken, counting the newest one
+    pub fn len(&self) -> usize {
+        self.history.len() + usize::from(self.latest.is_some())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.latest.is_none()
+    }
+
+    pub fn clear(&mut self) {
+        self.latest = None;
+        self.history.clear();
+        self.size = 0;
+        self.runs_since_capture = 0;
+    }
+
+    /// Call after every run of the machine, capturing a step every interval
+    pub fn ran(&mut self, machine: &Machine) -> Result<(), SavestateError> {
+        if self.budget == 0 {
+            return Ok(());
+        }
+
+        self.runs_since_capture += 1;
+
+        if self.runs_since_capture < self.interval && self.latest.is_some() {
+            return Ok(());
+        }
+
+        self.capture(machine)
+    }
+
+    /// Captures a step right now, this reseeds the machine rng like [Machine::snapshot] does
+    pub fn capture(&mut self, machine: &Machine) -> Result<(), SavestateError> {
+        self.runs_since_capture = 0;
+
+        let mut previous = self.latest.take();
+        // Before anything is moved out of it
+        let previous_size = previous.as_ref().map_or(0, Capture::size);
+        let capture = match take_capture(machine, previous.as_mut()) {
+            Ok(capture) => capture,
+            Err(err) => {
+                // The history only means anything relative to the newest step
+                self.clear();
+                return Err(err);
+            }
+        };
+
+        self.size -= previous_size;
+
+        if let Some(previous) = previous {
+            let delta = Delta {
+                scheduler: Patch::new(&capture.scheduler, &previous.scheduler),
+                rng_seed: previous.rng_seed,
+                components: capture
+                    .components
+                    .iter()
+                    .zip(previous.components)
+                    .map(|((_, bytes), (_, previous_bytes))| {
+                        // Left empty when it was moved over unchanged, encodings are never empty
+                        if previous_bytes.is_empty() || *bytes == previous_bytes {
+                            None
+                        } else {
+                            Some(Patch::new(bytes, &previous_bytes))
+                        }
+                    })
+                    .collect(),
+            };
+
+            self.size += delta.size();
+            self.history.push_back(delta);
+        }
+
+        self.size += capture.size();
+        self.latest = Some(capture);
+        self.evict();
+
+        Ok(())
+    }
+
+    /// Puts the machine back one step, returning false once there is nothing further back to go to
+    pub fn rewind(&mut self, machine: &mut Machine) -> Result<bool, SavestateError> {
+        let Some(latest) = &mut self.latest else {
+            return Ok(false);
+        };
+
+        // If the machine has not moved on since the newest step we are already there
+        if self.runs_since_capture == 0 {
+            let Some(delta) = self.history.pop_back() else {
+                return Ok(false);
+            };
+
+            self.size -= delta.size() + latest.size();
+
+            delta.scheduler.apply(&mut latest.scheduler);
+            latest.rng_seed = delta.rng_seed;
+            for ((_, bytes), patch) in latest.components.iter_mut().zip(delta.components) {
+                if let Some(patch) = patch {
+                    patch.apply(bytes);
+                }
+            }
+
+            self.size += latest.size();
+        }
+
+        self.runs_since_capture = 0;
+
+        let mut state = MachineState {
+            scheduler: rmp_serde::from_slice(&latest.scheduler)?,
+            components: Default::default(),
+            rng_seed: latest.rng_seed,
+            component_versions: Default::default(),
+            component_labels: Default::default(),
+        };
+
+        for ((component_id, table), (_, bytes)) in
+            machine.component_store.iter().zip(&latest.components)
+        {
+            state
+                .components
+                .insert(component_id, rmp_serde::from_slice(bytes)?);
+            state
+                .component_versions
+                .insert(component_id, table.component.snapshot_version());
+        }
+
+        machine.restore_snapshot(state)?;
+
+        // Loading counts as a change, but what was loaded is exactly what we hold
+        for ((_, table), (generation, _)) in
+            machine.component_store.iter().zip(&mut latest.components)
+        {
+            *generation = table.component.snapshot_generation();
+        }
+
+        for component_info in machine.display_components() {
+            component_info.component.redraw();
+        }
+
+        Ok(true)
+    }
+
+    fn evict(&mut self) {
+        while self.size > self.budget {
+            let Some(delta) = self.history.pop_front() else {
+                break;
+            };
+
+            self.size -= delta.size();
+        }
+    }
+}
+
+/// Snapshots every component, moving over the encodings of the ones whose generation says they did not change
+fn take_capture(
+    machine: &Machine,
+    mut previous: Option<&mut Capture>,
+) -> Result<Capture, SavestateError> {
+    let mut components = Vec::new();
+
+    for (component_id, table) in machine.component_store.iter() {
+        let generation = table.component.snapshot_generation();
+        let unchanged = previous
+            .as_deref_mut()
+            .and_then(|previous| previous.components.get_mut(component_id.0 as usize))
+            .filter(|previous| generation.is_some() && previous.0 == generation);
+
+        let bytes = match unchanged {
+            Some((_, bytes)) => std::mem::take(bytes),
+            None => rmp_serde::to_vec(&table.component.save_snapshot())?,
+        };
+
+        components.push((generation, bytes));
+    }
+
+    Ok(Capture {
+        scheduler: rmp_serde::to_vec(&machine.scheduler.state())?,
+        rng_seed: Some(machine.rng.checkpoint()),
+        components,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        definitions::misc::memory::standard::{
+            StandardMemory, StandardMemoryConfig, StandardMemoryInitialContents,
+        },
+        rom::{manager::RomManager, system::GameSystem},
+    };
+    use std::sync::Arc;
+
+    #[test]
+    fn patches_round_trip() {
+        let older: Vec<u8> = (0..300).map(|index| (index * 7) as u8).collect();
+        let mut newer = older.clone();
+        newer[3] ^= 0xff;
+        newer[200..210].fill(0);
+        newer.extend([1, 2, 3]);
+
+        for (from, to) in [(&newer, &older), (&older, &newer)] {
+            let patch = Patch::new(from, to);
+            let mut bytes = from.clone();
+            patch.apply(&mut bytes);
+            assert_eq!(&bytes, to);
+        }
+
+        // Nothing changed, nothing kept
+        assert!(Patch::new(&older, &older).data.is_empty());
+    }
+
+    #[test]
+    fn matching_prefixes() {
+        let from: Vec<u8> = (0..1000).map(|index| index as u8).collect();
+        let mut to = from.clone();
+        assert_eq!(matching_prefix(&from, &to), 1000);
+        assert_eq!(matching_prefix(&from, &to[..300]), 300);
+
+        to[130] = 0xff;
+        assert_eq!(matching_prefix(&from, &to), 130);
+    }
+
+    #[test]
+    fn rewinding_memory() {
+        let rom_manager = Arc::new(RomManager::new(None).unwrap());
+        let (machine, _) = Machine::build(GameSystem::Unknown, rom_manager)
+            .insert_bus(0, 16)
+            .build_component::<StandardMemory>(StandardMemoryConfig {
+                readable: true,
+                writable: true,
+                max_word_size: 2,
+                assigned_range: 0..0x1000,
+                assigned_address_space: 0,
+                initial_contents: StandardMemoryInitialContents::Value { value: 0xaa },
+            })
+            .unwrap();
+        let mut machine = machine.build().unwrap();
+        let mut rewind_buffer = RewindBuffer::new(DEFAULT_REWIND_BUDGET, 1);
+
+        rewind_buffer.capture(&machine).unwrap();
+        for value in [0x11, 0x22] {
+            machine
+                .memory_translation_table
+                .write(0x800, &[value], 0)
+                .unwrap();
+            rewind_buffer.ran(&machine).unwrap();
+        }
+        assert_eq!(rewind_buffer.len(), 3);
+
+        let mut buffer = [0; 1];
+        for expected in [0x11, 0xaa] {
+            assert!(rewind_buffer.rewind(&mut machine).unwrap());
+            machine
+                .memory_translation_table
+                .read(0x800, &mut buffer, 0)
+                .unwrap();
+            assert_eq!(buffer, [expected]);
+        }
+
+        assert!(!rewind_buffer.rewind(&mut machine).unwrap());
+    }
+}
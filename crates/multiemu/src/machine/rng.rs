@@ -0,0 +1,120 @@
+use rand::{
+    distr::{Distribution, StandardUniform},
+    rngs::StdRng,
+    Rng, RngCore, SeedableRng,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+
+/// A point where the machine randomness was seeded again, like when a snapshot was taken
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RngReseed {
+    /// How many values had been drawn before this happened
+    pub draws: u64,
+    pub seed: u64,
+}
+
+#[derive(Debug)]
+struct MachineRngState {
+    rng: StdRng,
+    draws: u64,
+    reseeds: Vec<RngReseed>,
+}
+
+/// The only source of randomness components should use, so the same seed and inputs always lead to the same machine state
+///
+/// The starting seed and every reseed are kept so a recording can reproduce them
+#[derive(Debug)]
+pub struct MachineRng {
+    seed: u64,
+    state: Mutex<MachineRngState>,
+}
+
+impl MachineRng {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            state: Mutex::new(MachineRngState {
+                rng: StdRng::seed_from_u64(seed),
+                draws: 0,
+                reseeds: Vec::default(),
+            }),
+        }
+    }
+
+    /// The seed the machine started with
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Every reseed since the machine started, in order
+    pub fn reseeds(&self) -> Vec<RngReseed> {
+        self.state.lock().unwrap().reseeds.clone()
+    }
+
+    pub fn reseed(&self, seed: u64) {
+        let mut state = self.state.lock().unwrap();
+
+        let reseed = RngReseed {
+            draws: state.draws,
+            seed,
+        };
+        tracing::debug!("Machine rng reseeded with {:?}", reseed);
+
+        state.rng = StdRng::seed_from_u64(seed);
+        state.reseeds.push(reseed);
+    }
+
+    /// Reseeds with a seed drawn from ourselves, returning it so the current state can be restored later
+    pub fn checkpoint(&self) -> u64 {
+        let seed = self.random();
+        self.reseed(seed);
+
+        seed
+    }
+
+    pub fn random<T>(&self) -> T
+    where
+        StandardUniform: Distribution<T>,
+    {
+        let mut state = self.state.lock().unwrap();
+        state.draws += 1;
+
+        state.rng.random()
+    }
+
+    pub fn fill_bytes(&self, buffer: &mut [u8]) {
+        let mut state = self.state.lock().unwrap();
+        state.draws += 1;
+
+        state.rng.fill_bytes(buffer);
+    }
+}
+
+impl Default for MachineRng {
+    /// Seeded from the host, for when reproducing a run does not matter
+    fn default() -> Self {
+        Self::new(rand::random())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn checkpoints_reproduce() {
+        let rng = MachineRng::new(0x1234);
+        let _: u8 = rng.random();
+
+        let seed = rng.checkpoint();
+        let expected: [u64; 4] = std::array::from_fn(|_| rng.random());
+
+        let restored = MachineRng::new(0x5678);
+        restored.reseed(seed);
+        let restored: [u64; 4] = std::array::from_fn(|_| restored.random());
+
+        assert_eq!(expected, restored);
+        assert_eq!(rng.reseeds(), [RngReseed { draws: 2, seed }]);
+    }
+}
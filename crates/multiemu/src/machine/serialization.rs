@@ -1,47 +1,539 @@
 use super::Machine;
-use crate::{component::ComponentId, scheduler::Scheduler};
+use crate::{
+    component::{ComponentId, SnapshotError},
+    config::GLOBAL_CONFIG,
+    rom::{id::RomId, system::GameSystem},
+    scheduler::SchedulerState,
+};
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, fs::File, path::Path};
+use sha1::{Digest, Sha1};
+use std::{
+    collections::HashMap,
+    fs::{create_dir_all, read_dir, File},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+use thiserror::Error;
 
-#[derive(Serialize, Deserialize)]
+/// Extension savestates are written with, see [Savestate]
+pub const SAVESTATE_EXTENSION: &str = "state";
+/// Numbered savestates the hotkeys and menu cycle through, see [Machine::save_state]
+pub const SAVESTATE_SLOTS: u8 = 10;
+/// Bumped whenever [Savestate] itself changes shape, see [Savestate::migrate]
+///
+/// 0 is everything from before the format was versioned
+pub const SAVESTATE_FORMAT_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct MachineState {
-    pub scheduler: Scheduler,
+    pub scheduler: SchedulerState,
     pub components: HashMap<ComponentId, rmpv::Value>,
+    /// Seed the machine randomness was reseeded with when this was taken
+    #[serde(default)]
+    pub rng_seed: Option<u64>,
+    /// [crate::component::Component::snapshot_version] of every component, missing ones are version 0
+    #[serde(default)]
+    pub component_versions: HashMap<ComponentId, u32>,
+    /// [super::ComponentTable::label] of every component, which is what restoring goes by so the ids can change in
+    /// between. States from before there were labels go by id
+    #[serde(default)]
+    pub component_labels: HashMap<ComponentId, String>,
+}
+
+// TODO: Replace this with a system that does less copying
+
+impl MachineState {
+    /// Roughly how much memory this takes up, for keeping caches of states within a budget
+    pub fn estimated_size(&self) -> usize {
+        size_of::<Self>()
+            + self
+                .components
+                .values()
+                .map(|value| size_of::<(ComponentId, rmpv::Value)>() + value_size(value))
+                .sum::<usize>()
+    }
+}
+
+/// Heap memory a value holds onto
+fn value_size(value: &rmpv::Value) -> usize {
+    match value {
+        rmpv::Value::String(string) => string.as_bytes().len(),
+        rmpv::Value::Binary(bytes) => bytes.len(),
+        rmpv::Value::Ext(_, bytes) => bytes.len(),
+        rmpv::Value::Array(values) => values
+            .iter()
+            .map(|value| size_of::<rmpv::Value>() + value_size(value))
+            .sum(),
+        rmpv::Value::Map(entries) => entries
+            .iter()
+            .map(|(key, value)| 2 * size_of::<rmpv::Value>() + value_size(key) + value_size(value))
+            .sum(),
+        _ => 0,
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum SavestateError {
+    #[error("Could not access the savestate: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Could not write the savestate: {0}")]
+    Encode(#[from] rmp_serde::encode::Error),
+    #[error("Could not read the savestate: {0}")]
+    Decode(#[from] rmp_serde::decode::Error),
+    #[error("Savestate was made with a different machine, it has components {0:?}")]
+    ComponentMismatch(Vec<String>),
+    #[error("Savestate has state for a component {0:?} that this machine does not have")]
+    UnknownComponent(ComponentId),
+    #[error("Savestate has state for a component called {0} that this machine does not have")]
+    UnknownComponentLabel(String),
+    #[error("Savestate is format version {0}, this version of the emulator only reads up to {SAVESTATE_FORMAT_VERSION}")]
+    NewerFormat(u32),
+    #[error("{component} could not load its part of the savestate: {source}")]
+    Component {
+        component: String,
+        source: SnapshotError,
+    },
+    #[error("Savestates are kept per ROM, and this machine is not running one")]
+    NoRom,
+    #[error("Nothing is saved in slot {0}")]
+    EmptySlot(u8),
+    #[error("{failure}, and putting the machine back how it was failed too: {rollback}")]
+    RollbackFailed {
+        failure: Box<SavestateError>,
+        rollback: Box<SavestateError>,
+    },
 }
 
-// TODO: Replace this with a system that does less copying and supports versioning
-// TODO: Replace this with a system that uses a stable id system, component ids are not stable
+/// A [MachineState] on disk, along with enough about where it came from to tell states apart
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Savestate {
+    /// See [SAVESTATE_FORMAT_VERSION]
+    #[serde(default)]
+    pub format_version: u32,
+    pub system: GameSystem,
+    pub user_specified_roms: Vec<RomId>,
+    /// Seconds since the unix epoch
+    pub created: u64,
+    /// Version of the emulator that made it, states are not promised to load across versions
+    pub emulator_version: String,
+    /// Names of every component in the machine, ordered by id since that is what the state is keyed by
+    pub components: Vec<String>,
+    /// [component_hash] of [Savestate::components], to tell if a state fits a machine without comparing every name
+    #[serde(default)]
+    pub component_hash: u64,
+    pub state: MachineState,
+}
+
+/// Stable across versions and platforms, unlike the std hasher
+pub fn component_hash(components: &[String]) -> u64 {
+    let mut hasher = Sha1::new();
+
+    for component in components {
+        hasher.update(component.as_bytes());
+        hasher.update([0]);
+    }
+
+    u64::from_le_bytes(hasher.finalize()[..8].try_into().unwrap())
+}
+
+impl Savestate {
+    /// Where the savestate called `name` for a ROM lives
+    pub fn path(rom_id: RomId, name: &str) -> PathBuf {
+        Self::directory(rom_id).join(format!("{}.{}", name, SAVESTATE_EXTENSION))
+    }
+
+    /// Brings a savestate written by an older format up to [SAVESTATE_FORMAT_VERSION]
+    pub fn migrate(mut self) -> Result<Self, SavestateError> {
+        if self.format_version > SAVESTATE_FORMAT_VERSION {
+            return Err(SavestateError::NewerFormat(self.format_version));
+        }
+
+        if self.format_version == 0 {
+            self.component_hash = component_hash(&self.components);
+            self.format_version = 1;
+        }
+
+        Ok(self)
+    }
+
+    /// Name of the savestate a numbered slot is kept in
+    pub fn slot_name(slot: u8) -> String {
+        format!("slot_{}", slot)
+    }
+
+    fn directory(rom_id: RomId) -> PathBuf {
+        GLOBAL_CONFIG
+            .read()
+            .unwrap()
+            .snapshot_directory
+            .join(rom_id.to_string())
+    }
+
+    /// Every savestate a ROM has and its name, sorted oldest first. States that fail to load are skipped
+    pub fn list(rom_id: RomId) -> Result<Vec<(String, Savestate)>, SavestateError> {
+        let directory = Self::directory(rom_id);
+
+        if !directory.is_dir() {
+            return Ok(Vec::new());
+        }
+
+        let mut savestates = Vec::new();
+
+        for entry in read_dir(directory)? {
+            let path = entry?.path();
+
+            if path
+                .extension()
+                .is_none_or(|extension| extension != SAVESTATE_EXTENSION)
+            {
+                continue;
+            }
+
+            let Some(name) = path
+                .file_stem()
+                .map(|name| name.to_string_lossy().to_string())
+            else {
+                continue;
+            };
+
+            match Self::load(&path) {
+                Ok(savestate) => savestates.push((name, savestate)),
+                Err(err) => tracing::warn!("Skipping savestate {}: {}", path.display(), err),
+            }
+        }
+        savestates
+            .sort_by(|(a_name, a), (b_name, b)| a.created.cmp(&b.created).then(a_name.cmp(b_name)));
+
+        Ok(savestates)
+    }
+
+    /// Reads a savestate, migrating it to the current format
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SavestateError> {
+        let mut file = File::open(path)?;
+        let savestate: Savestate = rmp_serde::decode::from_read(&mut file)?;
+
+        savestate.migrate()
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), SavestateError> {
+        let path = path.as_ref();
+
+        if let Some(parent) = path.parent() {
+            create_dir_all(parent)?;
+        }
+
+        let mut file = File::create(path)?;
+        rmp_serde::encode::write_named(&mut file, self)?;
+
+        Ok(())
+    }
+}
 
 impl Machine {
+    /// Names of every component, ordered by id
+    fn component_names(&self) -> Vec<String> {
+        self.component_store
+            .iter()
+            .map(|(_, table)| table.name.to_string())
+            .collect()
+    }
+
+    /// A [Machine::snapshot] ready to be written to disk
+    pub fn savestate(&self) -> Savestate {
+        let components = self.component_names();
+
+        Savestate {
+            format_version: SAVESTATE_FORMAT_VERSION,
+            system: self.system,
+            user_specified_roms: self.user_specified_roms.clone(),
+            created: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            emulator_version: env!("CARGO_PKG_VERSION").to_string(),
+            component_hash: component_hash(&components),
+            components,
+            state: self.snapshot(),
+        }
+    }
+
+    /// Writes the whole machine into a numbered slot for the ROM it is running, returning where it went
+    pub fn save_state(&self, slot: u8) -> Result<PathBuf, SavestateError> {
+        let rom_id = self
+            .user_specified_roms
+            .first()
+            .ok_or(SavestateError::NoRom)?;
+        let path = Savestate::path(*rom_id, &Savestate::slot_name(slot));
+
+        self.savestate().save(&path)?;
+
+        Ok(path)
+    }
+
+    /// Puts the machine back how [Machine::save_state] left it
+    pub fn load_state(&mut self, slot: u8) -> Result<(), SavestateError> {
+        let rom_id = self
+            .user_specified_roms
+            .first()
+            .ok_or(SavestateError::NoRom)?;
+        let path = Savestate::path(*rom_id, &Savestate::slot_name(slot));
+
+        if !path.is_file() {
+            return Err(SavestateError::EmptySlot(slot));
+        }
+
+        self.restore_savestate(Savestate::load(path)?)
+    }
+
+    /// Restores a savestate, refusing ones made with a machine built out of different components
+    ///
+    /// If a component cannot take its part the machine is put back how it was before
+    pub fn restore_savestate(&mut self, savestate: Savestate) -> Result<(), SavestateError> {
+        let savestate = savestate.migrate()?;
+        let components = self.component_names();
+
+        if savestate.component_hash != component_hash(&components)
+            || savestate.components != components
+        {
+            return Err(SavestateError::ComponentMismatch(savestate.components));
+        }
+
+        let previous_state = self.snapshot();
+        if let Err(err) = self.restore_snapshot(savestate.state) {
+            if let Err(rollback) = self.restore_snapshot(previous_state) {
+                return Err(SavestateError::RollbackFailed {
+                    failure: Box::new(err),
+                    rollback: Box::new(rollback),
+                });
+            }
+
+            return Err(err);
+        }
+
+        Ok(())
+    }
+
+    /// Captures the machine in memory, this reseeds the machine rng so restoring it continues the same way
+    pub fn snapshot(&self) -> MachineState {
+        MachineState {
+            scheduler: self.scheduler.state(),
+            components: self
+                .component_store
+                .iter()
+                .map(|(component_id, table)| (component_id, table.component.save_snapshot()))
+                .collect(),
+            rng_seed: Some(self.rng.checkpoint()),
+            component_versions: self
+                .component_store
+                .iter()
+                .map(|(component_id, table)| (component_id, table.component.snapshot_version()))
+                .collect(),
+            component_labels: self
+                .component_store
+                .iter()
+                .map(|(component_id, table)| (component_id, table.label.clone()))
+                .collect(),
+        }
+    }
+
     pub fn save_snapshot(&self, path: impl AsRef<Path>) {
         let mut file = File::create(path).unwrap();
 
-        rmp_serde::encode::write_named(
-            &mut file,
-            &MachineState {
-                scheduler: self.scheduler.clone(),
-                components: self
-                    .component_store
-                    .iter()
-                    .map(|(component_id, table)| (component_id, table.component.save_snapshot()))
-                    .collect(),
-            },
-        )
-        .unwrap();
+        rmp_serde::encode::write_named(&mut file, &self.snapshot()).unwrap();
     }
 
-    pub fn load_snapshot(&mut self, path: impl AsRef<Path>) {
-        let mut file = File::create(path).unwrap();
-        let state: MachineState = rmp_serde::decode::from_read(&mut file).unwrap();
+    pub fn load_snapshot(&mut self, path: impl AsRef<Path>) -> Result<(), SavestateError> {
+        let mut file = File::open(path)?;
+        let state: MachineState = rmp_serde::decode::from_read(&mut file)?;
+
+        self.restore_snapshot(state)
+    }
 
-        self.scheduler = state.scheduler;
+    /// Loads every component, migrating the ones saved with an older [crate::component::Component::snapshot_version]
+    /// first so nothing is touched if one cannot be migrated
+    pub fn restore_snapshot(&mut self, state: MachineState) -> Result<(), SavestateError> {
+        let mut components = Vec::with_capacity(state.components.len());
 
-        for (component_id, component_state) in state.components {
-            self.component_store
+        for (saved_component_id, component_state) in state.components {
+            let component_id = match state.component_labels.get(&saved_component_id) {
+                Some(label) => self
+                    .component_store
+                    .by_label(label)
+                    .ok_or_else(|| SavestateError::UnknownComponentLabel(label.clone()))?,
+                None => saved_component_id,
+            };
+            let table = self
+                .component_store
                 .get(component_id)
-                .expect("Missing component from manifest!")
+                .ok_or(SavestateError::UnknownComponent(component_id))?;
+            let version = state
+                .component_versions
+                .get(&saved_component_id)
+                .copied()
+                .unwrap_or_default();
+
+            let component_state = if version == table.component.snapshot_version() {
+                component_state
+            } else {
+                table
+                    .component
+                    .migrate_snapshot(version, component_state)
+                    .map_err(|source| SavestateError::Component {
+                        component: table.name.to_string(),
+                        source,
+                    })?
+            };
+
+            components.push((component_id, component_state));
+        }
+
+        self.scheduler
+            .restore_state(&self.component_store, state.scheduler);
+
+        if let Some(rng_seed) = state.rng_seed {
+            self.rng.reseed(rng_seed);
+        }
+
+        for (component_id, component_state) in components {
+            let table = self.component_store.get(component_id).unwrap();
+
+            table
                 .component
-                .load_snapshot(component_state);
+                .load_snapshot(component_state)
+                .map_err(|source| SavestateError::Component {
+                    component: table.name.to_string(),
+                    source,
+                })?;
         }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        definitions::misc::memory::standard::{
+            StandardMemory, StandardMemoryConfig, StandardMemoryInitialContents,
+            StandardMemorySnapshot,
+        },
+        rom::manager::RomManager,
+    };
+    use std::{ops::Range, sync::Arc};
+
+    fn machine() -> Machine {
+        let rom_manager = Arc::new(RomManager::new(None).unwrap());
+        let (machine, _) = Machine::build(GameSystem::Unknown, rom_manager)
+            .insert_bus(0, 16)
+            .build_component::<StandardMemory>(StandardMemoryConfig {
+                readable: true,
+                writable: true,
+                max_word_size: 2,
+                assigned_range: 0..0x100,
+                assigned_address_space: 0,
+                initial_contents: StandardMemoryInitialContents::Value { value: 0xaa },
+            })
+            .unwrap();
+
+        machine.build().unwrap()
+    }
+
+    #[test]
+    fn old_savestates_are_migrated() {
+        let mut savestate = machine().savestate();
+        savestate.format_version = 0;
+        savestate.component_hash = 0;
+
+        let savestate = savestate.migrate().unwrap();
+        assert_eq!(savestate.format_version, SAVESTATE_FORMAT_VERSION);
+        assert_eq!(
+            savestate.component_hash,
+            component_hash(&savestate.components)
+        );
+
+        let mut newer_savestate = savestate;
+        newer_savestate.format_version = SAVESTATE_FORMAT_VERSION + 1;
+        assert!(matches!(
+            newer_savestate.migrate(),
+            Err(SavestateError::NewerFormat(_))
+        ));
+    }
+
+    #[test]
+    fn bad_component_state_is_rolled_back() {
+        let mut machine = machine();
+        let mut savestate = machine.savestate();
+        savestate.state.components.insert(
+            ComponentId(0),
+            rmpv::ext::to_value(StandardMemorySnapshot {
+                memory: vec![0x55; 0x10],
+                readable: None,
+                writable: None,
+            })
+            .unwrap(),
+        );
+
+        assert!(matches!(
+            machine.restore_savestate(savestate),
+            Err(SavestateError::Component {
+                source: SnapshotError::SizeMismatch { .. },
+                ..
+            })
+        ));
+
+        let mut buffer = [0; 2];
+        machine
+            .memory_translation_table
+            .read(0, &mut buffer, 0)
+            .unwrap();
+        assert_eq!(buffer, [0xaa; 2]);
+    }
+
+    #[test]
+    fn snapshots_follow_labels() {
+        fn memory(range: Range<usize>, value: u8) -> StandardMemoryConfig {
+            StandardMemoryConfig {
+                readable: true,
+                writable: true,
+                max_word_size: 1,
+                assigned_range: range,
+                assigned_address_space: 0,
+                initial_contents: StandardMemoryInitialContents::Value { value },
+            }
+        }
+
+        let rom_manager = Arc::new(RomManager::new(None).unwrap());
+        let (machine, _) = Machine::build(GameSystem::Unknown, rom_manager.clone())
+            .insert_bus(0, 16)
+            .build_labeled_component::<StandardMemory>("low", memory(0..0x100, 0xaa))
+            .unwrap();
+        let (machine, _) = machine
+            .build_labeled_component::<StandardMemory>("high", memory(0x100..0x200, 0xbb))
+            .unwrap();
+        let state = machine.build().unwrap().snapshot();
+
+        // Same components, built the other way around
+        let (machine, _) = Machine::build(GameSystem::Unknown, rom_manager)
+            .insert_bus(0, 16)
+            .build_labeled_component::<StandardMemory>("high", memory(0x100..0x200, 0))
+            .unwrap();
+        let (machine, _) = machine
+            .build_labeled_component::<StandardMemory>("low", memory(0..0x100, 0))
+            .unwrap();
+        let mut machine = machine.build().unwrap();
+        machine.restore_snapshot(state).unwrap();
+
+        let mut buffer = [0; 1];
+        machine
+            .memory_translation_table
+            .read(0, &mut buffer, 0)
+            .unwrap();
+        assert_eq!(buffer, [0xaa]);
+        machine
+            .memory_translation_table
+            .read(0x100, &mut buffer, 0)
+            .unwrap();
+        assert_eq!(buffer, [0xbb]);
     }
 }
@@ -0,0 +1,154 @@
+//! Wires between components, like the interrupt lines a video chip pulls to get the attention of a processor
+//!
+//! The component with the input takes a [SignalLine] while being built, and anything built after it can get a
+//! [SignalDriver] for it with [super::MachineBuilder::signal_driver]
+
+use std::{
+    fmt::Debug,
+    sync::{
+        atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering},
+        Arc, OnceLock,
+    },
+};
+
+/// Commonly found input names, processors are free to use whatever their datasheet calls them
+pub const NMI: &str = "NMI";
+pub const IRQ: &str = "IRQ";
+pub const RESET: &str = "RESET";
+
+/// A line any number of components can hold, it stays asserted as long as any of them do
+///
+/// Lines are not part of snapshots, whatever drives them sets them again when loading its own
+#[derive(Default)]
+pub struct SignalLine {
+    /// A bit per driver holding the line
+    holders: AtomicU64,
+    drivers: AtomicU32,
+    /// Set when the line goes from released to held, for edge triggered inputs
+    edge: AtomicBool,
+    callback: OnceLock<Box<dyn Fn(bool) + Send + Sync>>,
+}
+
+impl Debug for SignalLine {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SignalLine")
+            .field("holders", &self.holders)
+            .field("drivers", &self.drivers)
+            .field("edge", &self.edge)
+            .finish_non_exhaustive()
+    }
+}
+
+impl SignalLine {
+    /// Level triggered inputs just poll this
+    pub fn is_asserted(&self) -> bool {
+        self.holders.load(Ordering::Acquire) != 0
+    }
+
+    /// If the line went from released to held since the last call, for inputs like NMI that only care about the edge
+    pub fn take_edge(&self) -> bool {
+        self.edge.swap(false, Ordering::AcqRel)
+    }
+
+    /// Called with the new level whenever it changes, on the thread of whatever changed it
+    ///
+    /// Only one callback can be set, and it should do as little as possible since the driving component is waiting
+    pub fn on_change(&self, callback: impl Fn(bool) + Send + Sync + 'static) {
+        if self.callback.set(Box::new(callback)).is_err() {
+            panic!("Signal line already has a callback");
+        }
+    }
+
+    pub(super) fn driver(self: &Arc<Self>) -> SignalDriver {
+        let index = self.drivers.fetch_add(1, Ordering::Relaxed);
+        assert!(index < u64::BITS, "Too many drivers on one signal line");
+
+        SignalDriver {
+            line: self.clone(),
+            bit: 1 << index,
+        }
+    }
+
+    fn set(&self, bit: u64, asserted: bool) {
+        let previous = if asserted {
+            self.holders.fetch_or(bit, Ordering::AcqRel)
+        } else {
+            self.holders.fetch_and(!bit, Ordering::AcqRel)
+        };
+        let current = if asserted {
+            previous | bit
+        } else {
+            previous & !bit
+        };
+
+        match (previous != 0, current != 0) {
+            (false, true) => {
+                self.edge.store(true, Ordering::Release);
+                if let Some(callback) = self.callback.get() {
+                    callback(true);
+                }
+            }
+            (true, false) => {
+                if let Some(callback) = self.callback.get() {
+                    callback(false);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// One component holding or releasing a [SignalLine]
+#[derive(Debug)]
+pub struct SignalDriver {
+    line: Arc<SignalLine>,
+    bit: u64,
+}
+
+impl SignalDriver {
+    pub fn set(&self, asserted: bool) {
+        self.line.set(self.bit, asserted);
+    }
+
+    pub fn assert(&self) {
+        self.set(true);
+    }
+
+    pub fn release(&self) {
+        self.set(false);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[test]
+    fn line_is_held_while_any_driver_holds_it() {
+        let line = Arc::new(SignalLine::default());
+        let changes = Arc::new(Mutex::new(Vec::new()));
+        line.on_change({
+            let changes = changes.clone();
+            move |asserted| changes.lock().unwrap().push(asserted)
+        });
+
+        let first = line.driver();
+        let second = line.driver();
+        assert!(!line.is_asserted());
+
+        first.assert();
+        second.assert();
+        first.release();
+        assert!(line.is_asserted());
+        assert!(line.take_edge());
+        assert!(!line.take_edge());
+
+        second.release();
+        assert!(!line.is_asserted());
+        // Releasing a line nobody holds does nothing
+        second.release();
+
+        assert_eq!(*changes.lock().unwrap(), [true, false]);
+    }
+}
@@ -1,37 +1,26 @@
-//! A multisystem hardware emulator
-
-use config::{GraphicsSettings, GLOBAL_CONFIG};
-use rom::manager::RomManager;
-use runtime::{
-    launch::Runtime,
-    platform::{PlatformRuntime, SoftwareRenderingRuntime},
+use multiemu::{
+    config::{GraphicsSettings, GLOBAL_CONFIG, STORAGE_DIRECTORY},
+    host_cpu::cpu_features,
+    profile::ACTIVE_PROFILE,
+    rom::manager::RomManager,
+    runtime::{
+        launch::Runtime,
+        platform::{PlatformRuntime, SoftwareRenderingRuntime},
+    },
 };
 use std::sync::Arc;
 
-// Cli tools are designed only to operate on desktop
-#[cfg(platform_desktop)]
-mod cli;
-mod component;
-mod config;
-mod definitions;
-mod gui;
-mod input;
-mod machine;
-mod memory;
-mod processor;
-mod rom;
-mod runtime;
-mod scheduler;
-
 fn main() {
     tracing_subscriber::fmt::init();
     tracing::info!("MultiEMU v{}", env!("CARGO_PKG_VERSION"));
+    tracing::info!("Host processor has {}", cpu_features());
 
     #[cfg(platform_desktop)]
     {
         use clap::Parser;
-        use cli::handle_cli;
-        use cli::Cli;
+        use multiemu::cli::handle_cli;
+        use multiemu::cli::rom::run::rom_run;
+        use multiemu::cli::Cli;
 
         let cli = Cli::parse();
 
@@ -39,8 +28,20 @@ fn main() {
             handle_cli(action).unwrap();
             return;
         }
+
+        // Same as rom run, so file associations can just hand us the path
+        if let Some(rom) = cli.rom {
+            rom_run(vec![rom], None, None).unwrap();
+            return;
+        }
     }
 
+    tracing::info!(
+        "Storing data in {} with the profile {}",
+        STORAGE_DIRECTORY.display(),
+        ACTIVE_PROFILE.as_str()
+    );
+
     let global_config_guard = GLOBAL_CONFIG.try_read().unwrap();
     let rom_manager = Arc::new(RomManager::new(Some(&global_config_guard.database_file)).unwrap());
     let graphics_setting = global_config_guard.graphics_setting;
@@ -52,7 +53,7 @@ fn main() {
         }
         #[cfg(graphics_vulkan)]
         GraphicsSettings::Vulkan => {
-            use runtime::platform::desktop::renderer::vulkan::VulkanRenderingRuntime;
+            use multiemu::runtime::platform::desktop::renderer::vulkan::VulkanRenderingRuntime;
 
             PlatformRuntime::<VulkanRenderingRuntime>::launch_gui(rom_manager);
         }
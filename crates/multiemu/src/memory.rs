@@ -1,12 +1,36 @@
-use crate::{component::ComponentId, machine::component_store::ComponentStore};
+use crate::{
+    component::{memory::MemoryComponent, ComponentId},
+    machine::component_store::ComponentStore,
+};
 use arrayvec::ArrayVec;
 use bitvec::{field::BitField, order::Lsb0, view::BitView};
-use rangemap::RangeMap;
-use std::{collections::HashMap, ops::Range, sync::Arc};
+use itertools::Either;
+use rangemap::{RangeMap, RangeSet};
+use ringbuffer::{AllocRingBuffer, RingBuffer};
+use std::{
+    cell::Cell,
+    collections::{HashMap, HashSet},
+    fmt::Display,
+    fs::File,
+    io::{BufWriter, Read, Write},
+    num::ParseIntError,
+    ops::Range,
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, RwLock,
+    },
+};
 use thiserror::Error;
 
 pub const VALID_ACCESS_SIZES: &[usize] = &[1, 2, 4, 8];
 
+/// Granularity of the page table accesses are looked up in before falling back to the full bus population
+pub const PAGE_SIZE: usize = 256;
+
+/// Busses wider than this would need too big of a page table, so they always take the slow path
+const MAX_PAGED_BUS_WIDTH: u8 = 24;
+
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum ReadMemoryOperationErrorFailureType {
     Denied,
@@ -38,6 +62,52 @@ pub enum PreviewMemoryOperationErrorFailureType {
 #[error("Preview operation failed (this really shouldn't be thrown): {0:#?}")]
 pub struct PreviewMemoryOperationError(RangeMap<usize, PreviewMemoryOperationErrorFailureType>);
 
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum RemapError {
+    #[error("Address space {0} does not exist")]
+    UnknownAddressSpace(AddressSpaceId),
+    #[error("Component {0:?} is not a memory component")]
+    NotMemoryComponent(ComponentId),
+    #[error("Range {0:#x?} does not fit on the bus")]
+    OutOfBus(Range<usize>),
+}
+
+#[derive(Error, Debug)]
+pub enum MemoryDumpError {
+    #[error("Address space {0} does not exist")]
+    UnknownAddressSpace(AddressSpaceId),
+    #[error("Range {0:#x?} does not fit on the bus")]
+    OutOfBus(Range<usize>),
+    #[error("{0}")]
+    Io(#[from] std::io::Error),
+    #[error("{0}")]
+    Write(#[from] WriteMemoryOperationError),
+}
+
+/// Takes decimal, or hex if it starts with 0x
+pub fn parse_address(address: &str) -> Result<usize, ParseIntError> {
+    let address = address.trim();
+
+    match address
+        .strip_prefix("0x")
+        .or_else(|| address.strip_prefix("0X"))
+    {
+        Some(hex) => usize::from_str_radix(hex, 16),
+        None => address.parse(),
+    }
+}
+
+/// Takes `start..end`, with each end like [parse_address] takes it
+pub fn parse_address_range(
+    range: &str,
+) -> Result<Range<usize>, Box<dyn std::error::Error + Send + Sync>> {
+    let (start, end) = range
+        .split_once("..")
+        .ok_or("Expected a range like 0x0000..0x0800")?;
+
+    Ok(parse_address(start)?..parse_address(end)?)
+}
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum ReadMemoryRecord {
     /// Memory could not be read
@@ -81,23 +151,344 @@ const MAX_ACCESS_SIZE: u8 = const {
 
 pub type AddressSpaceId = u8;
 
+thread_local! {
+    /// Component running on this thread right now, so whatever it accesses can be traced back to it
+    static ACCESSING_COMPONENT: Cell<Option<ComponentId>> = const { Cell::new(None) };
+    /// Wait states the accesses made on this thread added up to since they were last taken
+    static WAIT_STATES: Cell<u64> = const { Cell::new(0) };
+}
+
+/// Marks what `component_id` does until told otherwise as its own, see [UnmappedAccess::component]
+///
+/// Wait states start counting from zero again, so nothing left over from another component is handed to this one
+pub fn set_accessing_component(component_id: Option<ComponentId>) {
+    ACCESSING_COMPONENT.set(component_id);
+    WAIT_STATES.set(0);
+}
+
+/// Extra cycles the accesses made on this thread cost since the last call, see
+/// [MemoryTranslationTable::set_wait_states]
+///
+/// Processors that care about timing add this to their own cycle count after each instruction
+pub fn take_wait_states() -> u64 {
+    WAIT_STATES.replace(0)
+}
+
+/// Accesses to an address no component is assigned, which usually means a piece of the machine is missing
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnmappedAccess {
+    pub address_space: AddressSpaceId,
+    pub address: usize,
+    /// Which component made the accesses, if it was run by the scheduler
+    pub component: Option<ComponentId>,
+    pub reads: u64,
+    pub writes: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemoryAccessKind {
+    Read,
+    Write,
+}
+
+/// A single read or write as the memory access trace saw it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryAccess {
+    pub kind: MemoryAccessKind,
+    pub address_space: AddressSpaceId,
+    pub address: usize,
+    pub size: u8,
+    /// Which component made the access, if it was run by the scheduler
+    pub component: Option<ComponentId>,
+    /// The bytes read or written, little endian
+    pub value: u64,
+    /// If something along the way denied the access
+    pub failed: bool,
+}
+
+impl Display for MemoryAccess {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {}:{:#06x} {:#0width$x}",
+            match self.kind {
+                MemoryAccessKind::Read => "R",
+                MemoryAccessKind::Write => "W",
+            },
+            self.address_space,
+            self.address,
+            self.value,
+            width = self.size as usize * 2 + 2
+        )?;
+
+        if let Some(component) = self.component {
+            write!(f, " by {}", component.0)?;
+        }
+
+        if self.failed {
+            write!(f, " failed")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// How often a page of an address space was hit while the heatmap was on
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PageAccesses {
+    pub address_space: AddressSpaceId,
+    /// First address of the page, pages being [PAGE_SIZE] long
+    pub address: usize,
+    pub reads: u64,
+    pub writes: u64,
+}
+
+/// Where traced memory accesses go
 #[derive(Debug)]
+pub enum AccessTraceSink {
+    /// Only the latest accesses, for looking at from the menu
+    RingBuffer(AllocRingBuffer<MemoryAccess>),
+    /// Every access, a line each
+    File(BufWriter<File>),
+}
+
+impl AccessTraceSink {
+    pub fn ring_buffer(capacity: usize) -> Self {
+        Self::RingBuffer(AllocRingBuffer::new(capacity))
+    }
+
+    pub fn file(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        Ok(Self::File(BufWriter::new(File::create(path)?)))
+    }
+}
+
+/// What an assignment on the bus hands accesses to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Mapping {
+    component_id: ComponentId,
+    /// Taken off bus addresses before the component sees them, which is what makes mirrors land on the original
+    offset: usize,
+}
+
+/// A component assignment that covers at least one page entirely
+#[derive(Debug)]
+struct MappedRegion {
+    assigned_range: Range<usize>,
+    offset: usize,
+    component: Arc<dyn MemoryComponent>,
+}
+
+#[derive(Debug, Clone)]
 pub struct BusInfo {
-    population: RangeMap<usize, ComponentId>,
+    /// Source of truth for what is mapped where
+    population: RangeMap<usize, Mapping>,
+    /// Flattened from [BusInfo::population] whenever it or the component store changes, with [None] for pages that
+    /// are not covered by a single component
+    pages: Vec<Option<Arc<MappedRegion>>>,
+    /// Writes here fail whatever is mapped, see [MemoryTranslationTable::set_write_protected]
+    write_protected: RangeSet<usize>,
+    /// Extra cycles an access here costs, see [MemoryTranslationTable::set_wait_states]
+    wait_states: RangeMap<usize, u32>,
     width: u8,
 }
 
+impl BusInfo {
+    /// The region the whole access lands in, if the page table knows it without looking any further
+    #[inline]
+    fn page(&self, accessing_range: &Range<usize>) -> Option<&MappedRegion> {
+        let region = self
+            .pages
+            .get(accessing_range.start / PAGE_SIZE)?
+            .as_deref()?;
+
+        // Accesses straddling the end of a region need to be split up by the slow path
+        (accessing_range.end <= region.assigned_range.end).then_some(region)
+    }
+
+    /// An access is one bus transaction, so it costs whatever the slowest region it touches does
+    #[inline]
+    fn charge_wait_states(&self, accessing_range: &Range<usize>) {
+        if let Some(wait_states) = self
+            .wait_states
+            .overlapping(accessing_range)
+            .map(|(_, wait_states)| *wait_states)
+            .max()
+        {
+            WAIT_STATES.set(WAIT_STATES.get() + wait_states as u64);
+        }
+    }
+
+    /// One past the last address the bus can hold
+    fn end(&self) -> usize {
+        1usize.checked_shl(self.width as u32).unwrap_or(usize::MAX)
+    }
+
+    /// Where the assignment or gap between assignments `address` sits in ends
+    fn boundary(&self, address: usize) -> usize {
+        let end = self.end();
+
+        match self.population.get_key_value(&address) {
+            Some((assigned_range, _)) => assigned_range.end,
+            None => self
+                .population
+                .gaps(&(address..end))
+                .next()
+                .map_or(end, |gap| gap.end),
+        }
+        .min(end)
+    }
+
+    fn rebuild_pages(&mut self, component_store: &ComponentStore) {
+        self.pages.clear();
+
+        if self.width > MAX_PAGED_BUS_WIDTH {
+            return;
+        }
+
+        let bus_size: usize = 1 << self.width;
+        self.pages.resize(bus_size.div_ceil(PAGE_SIZE), None);
+        self.rebuild_page_range(component_store, 0..bus_size);
+    }
+
+    /// Refills only the pages `range` touches, for when a small part of the bus changed
+    fn rebuild_page_range(&mut self, component_store: &ComponentStore, range: Range<usize>) {
+        let bus_size = 1 << self.width;
+        let mut regions: HashMap<Range<usize>, Arc<MappedRegion>> = HashMap::new();
+
+        for page in range.start / PAGE_SIZE..range.end.div_ceil(PAGE_SIZE).min(self.pages.len()) {
+            let page_start = page * PAGE_SIZE;
+            let page_range = page_start..(page_start + PAGE_SIZE).min(bus_size);
+            let mut overlapping = self.population.overlapping(&page_range);
+
+            let region = match (overlapping.next(), overlapping.next()) {
+                (Some((assigned_range, mapping)), None)
+                    if assigned_range.start <= page_range.start
+                        && assigned_range.end >= page_range.end =>
+                {
+                    let component = component_store
+                        .get(mapping.component_id)
+                        .and_then(|table| table.as_memory.as_ref())
+                        .map(|info| info.component.clone())
+                        .expect("Memory mapped component is not a memory component");
+
+                    Some(
+                        regions
+                            .entry(assigned_range.clone())
+                            .or_insert_with(|| {
+                                Arc::new(MappedRegion {
+                                    assigned_range: assigned_range.clone(),
+                                    offset: mapping.offset,
+                                    component,
+                                })
+                            })
+                            .clone(),
+                    )
+                }
+                _ => None,
+            };
+
+            self.pages[page] = region;
+        }
+    }
+}
+
+/// Splits a mirrored range into the blocks a mask maps contiguously, along with what to take off their addresses
+///
+/// Everything below the lowest bit the mask clears passes through untouched, so that is how big each block is
+fn mirror_blocks(range: Range<usize>, mask: usize) -> impl Iterator<Item = (Range<usize>, usize)> {
+    let block_size = 1usize
+        .checked_shl((!mask).trailing_zeros())
+        .unwrap_or(usize::MAX);
+    let mut start = range.start;
+
+    std::iter::from_fn(move || {
+        if start >= range.end {
+            return None;
+        }
+
+        let end = (start / block_size + 1)
+            .saturating_mul(block_size)
+            .min(range.end);
+        let block = (start..end, start - (start & mask));
+        start = end;
+
+        Some(block)
+    })
+}
+
 #[derive(Default, Debug)]
 pub struct MemoryTranslationTable {
-    busses: HashMap<AddressSpaceId, BusInfo>,
+    /// Indexed by [AddressSpaceId], which are few enough that this beats hashing on every access
+    ///
+    /// Accesses take their own reference to the bus, so a component remapping it in the middle of one does not deadlock
+    busses: Vec<Option<RwLock<Arc<BusInfo>>>>,
     component_store: Option<Arc<ComponentStore>>,
+    /// Reads and writes keyed by where they landed and who made them
+    unmapped_accesses: Mutex<HashMap<(AddressSpaceId, usize, Option<ComponentId>), (u64, u64)>>,
+    /// Checked on every access so the sink lock is only taken while tracing
+    tracing: AtomicBool,
+    access_trace: Mutex<Option<AccessTraceSink>>,
+    /// Same idea as [Self::tracing], for the heatmap
+    collecting_heatmap: AtomicBool,
+    /// Reads and writes keyed by address space and page index
+    heatmap: Mutex<HashMap<(AddressSpaceId, usize), (u64, u64)>>,
+    /// Same idea as [Self::tracing], for the write watches
+    watching_writes: AtomicBool,
+    write_watches: Mutex<WriteWatches>,
+}
+
+#[derive(Default, Debug)]
+struct WriteWatches {
+    watched: HashSet<(AddressSpaceId, usize)>,
+    /// Watched addresses written since the last time they were taken, in the order they were written
+    hits: Vec<(AddressSpaceId, usize)>,
+}
+
+/// Words read and written as a single access of their size, so the component sees what a real bus would
+///
+/// Addresses do not wrap, cores that need a word to wrap around a page have to read it a byte at a time
+macro_rules! typed_accessors {
+    ($($read:ident, $write:ident, $type:ty, $from_bytes:ident, $to_bytes:ident;)*) => {
+        $(
+            #[doc = concat!("Reads a `", stringify!($type), "` with [Self::read], using [", stringify!($type), "::", stringify!($from_bytes), "]")]
+            pub fn $read(
+                &self,
+                address: usize,
+                address_space: AddressSpaceId,
+            ) -> Result<$type, ReadMemoryOperationError> {
+                let mut buffer = [0; std::mem::size_of::<$type>()];
+                self.read(address, &mut buffer, address_space)?;
+
+                Ok(<$type>::$from_bytes(buffer))
+            }
+
+            #[doc = concat!("Writes a `", stringify!($type), "` with [Self::write], using [", stringify!($type), "::", stringify!($to_bytes), "]")]
+            pub fn $write(
+                &self,
+                address: usize,
+                value: $type,
+                address_space: AddressSpaceId,
+            ) -> Result<(), WriteMemoryOperationError> {
+                self.write(address, &value.$to_bytes(), address_space)
+            }
+        )*
+    };
 }
 
 impl MemoryTranslationTable {
     pub fn insert_bus(&mut self, id: AddressSpaceId, width: u8) {
-        self.busses.entry(id).or_insert_with(|| BusInfo {
-            population: RangeMap::default(),
-            width,
+        if self.busses.len() <= id as usize {
+            self.busses.resize_with(id as usize + 1, || None);
+        }
+
+        self.busses[id as usize].get_or_insert_with(|| {
+            RwLock::new(Arc::new(BusInfo {
+                population: RangeMap::default(),
+                pages: Vec::default(),
+                write_protected: RangeSet::default(),
+                wait_states: RangeMap::default(),
+                width,
+            }))
         });
     }
 
@@ -107,20 +498,480 @@ impl MemoryTranslationTable {
         component_id: ComponentId,
         ranges: impl IntoIterator<Item = Range<usize>>,
     ) {
-        self.busses
-            .get_mut(&id)
-            .expect("Bus must be initialized before inserting component")
+        let bus_info = Arc::make_mut(
+            self.busses
+                .get_mut(id as usize)
+                .and_then(Option::as_mut)
+                .expect("Bus must be initialized before inserting component")
+                .get_mut()
+                .unwrap(),
+        );
+
+        bus_info.population.extend(ranges.into_iter().map(|range| {
+            (
+                range,
+                Mapping {
+                    component_id,
+                    offset: 0,
+                },
+            )
+        }));
+
+        if let Some(component_store) = &self.component_store {
+            bus_info.rebuild_pages(component_store);
+        }
+    }
+
+    /// Shows `component_id` again over `range`, with every address put through `mask` before the component sees it
+    ///
+    /// The mask is resolved into plain offsets here, so a mirrored access costs the same as any other. For example
+    /// the NES work ram at 0x0000..0x0800 repeats up to 0x2000 with a mask of 0x07ff
+    pub fn insert_mirror(
+        &mut self,
+        id: AddressSpaceId,
+        component_id: ComponentId,
+        range: Range<usize>,
+        mask: usize,
+    ) {
+        let bus_info = Arc::make_mut(
+            self.busses
+                .get_mut(id as usize)
+                .and_then(Option::as_mut)
+                .expect("Bus must be initialized before inserting component")
+                .get_mut()
+                .unwrap(),
+        );
+
+        bus_info
             .population
-            .extend(ranges.into_iter().map(|range| (range, component_id)));
+            .extend(mirror_blocks(range, mask).map(|(block, offset)| {
+                (
+                    block,
+                    Mapping {
+                        component_id,
+                        offset,
+                    },
+                )
+            }));
+
+        if let Some(component_store) = &self.component_store {
+            bus_info.rebuild_pages(component_store);
+        }
     }
 
     pub fn set_component_store(&mut self, component_store: Arc<ComponentStore>) {
+        for bus_info in self.busses.iter_mut().flatten() {
+            Arc::make_mut(bus_info.get_mut().unwrap()).rebuild_pages(&component_store);
+        }
+
         self.component_store = Some(component_store);
     }
 
+    /// Every bus and how many bits wide its addresses are
+    pub fn busses(&self) -> impl Iterator<Item = (AddressSpaceId, u8)> + '_ {
+        self.busses.iter().enumerate().filter_map(|(id, bus_info)| {
+            Some((
+                id as AddressSpaceId,
+                bus_info.as_ref()?.read().unwrap().width,
+            ))
+        })
+    }
+
+    #[inline]
+    fn bus_info(&self, address_space: AddressSpaceId) -> Arc<BusInfo> {
+        self.busses
+            .get(address_space as usize)
+            .and_then(Option::as_ref)
+            .expect("Non existant address space")
+            .read()
+            .unwrap()
+            .clone()
+    }
+
+    /// Hands `range` over to `component_id` while the machine runs, for things like cartridge mappers switching banks
+    ///
+    /// The component is handed addresses in `range` as they are, so it has to be ready for them. Accesses already
+    /// underway finish with the old mapping
+    pub fn remap(
+        &self,
+        address_space: AddressSpaceId,
+        range: Range<usize>,
+        component_id: ComponentId,
+    ) -> Result<(), RemapError> {
+        let component_store = self
+            .component_store
+            .as_ref()
+            .expect("Machine must be built before remapping");
+
+        if component_store
+            .get(component_id)
+            .and_then(|table| table.as_memory.as_ref())
+            .is_none()
+        {
+            return Err(RemapError::NotMemoryComponent(component_id));
+        }
+
+        let mut bus_info_guard = self
+            .busses
+            .get(address_space as usize)
+            .and_then(Option::as_ref)
+            .ok_or(RemapError::UnknownAddressSpace(address_space))?
+            .write()
+            .unwrap();
+
+        if range.end > bus_info_guard.end() {
+            return Err(RemapError::OutOfBus(range));
+        }
+
+        if range.is_empty() {
+            return Ok(());
+        }
+
+        let mut bus_info = BusInfo::clone(&bus_info_guard);
+        bus_info.population.insert(
+            range.clone(),
+            Mapping {
+                component_id,
+                offset: 0,
+            },
+        );
+
+        // Neighbouring assignments to the same component merge with the range, so their pages are stale too
+        let stale = bus_info
+            .population
+            .overlapping(&(range.start.saturating_sub(1)..range.end + 1))
+            .fold(range, |stale, (assigned_range, _)| {
+                stale.start.min(assigned_range.start)..stale.end.max(assigned_range.end)
+            });
+        bus_info.rebuild_page_range(component_store, stale);
+
+        *bus_info_guard = Arc::new(bus_info);
+
+        Ok(())
+    }
+
+    /// Makes every write to `range` fail no matter what is mapped there, or lets them through again
+    ///
+    /// This goes by the address the bus is written with, so mirrors of the range need protecting too
+    pub fn set_write_protected(
+        &self,
+        address_space: AddressSpaceId,
+        range: Range<usize>,
+        write_protected: bool,
+    ) -> Result<(), RemapError> {
+        let mut bus_info_guard = self
+            .busses
+            .get(address_space as usize)
+            .and_then(Option::as_ref)
+            .ok_or(RemapError::UnknownAddressSpace(address_space))?
+            .write()
+            .unwrap();
+
+        if range.end > bus_info_guard.end() {
+            return Err(RemapError::OutOfBus(range));
+        }
+
+        if range.is_empty() {
+            return Ok(());
+        }
+
+        let mut bus_info = BusInfo::clone(&bus_info_guard);
+
+        if write_protected {
+            bus_info.write_protected.insert(range);
+        } else {
+            bus_info.write_protected.remove(range);
+        }
+
+        *bus_info_guard = Arc::new(bus_info);
+
+        Ok(())
+    }
+
+    /// Makes every access to `range` cost `wait_states` extra cycles, handed to processors through [take_wait_states]
+    ///
+    /// Like [Self::set_write_protected] this goes by bus address, and can be changed while running for machines where
+    /// the program sets up its own wait states. Zero takes them off again
+    pub fn set_wait_states(
+        &self,
+        address_space: AddressSpaceId,
+        range: Range<usize>,
+        wait_states: u32,
+    ) -> Result<(), RemapError> {
+        let mut bus_info_guard = self
+            .busses
+            .get(address_space as usize)
+            .and_then(Option::as_ref)
+            .ok_or(RemapError::UnknownAddressSpace(address_space))?
+            .write()
+            .unwrap();
+
+        if range.end > bus_info_guard.end() {
+            return Err(RemapError::OutOfBus(range));
+        }
+
+        if range.is_empty() {
+            return Ok(());
+        }
+
+        let mut bus_info = BusInfo::clone(&bus_info_guard);
+
+        if wait_states == 0 {
+            bus_info.wait_states.remove(range);
+        } else {
+            bus_info.wait_states.insert(range, wait_states);
+        }
+
+        *bus_info_guard = Arc::new(bus_info);
+
+        Ok(())
+    }
+
+    /// Components that `accessing_range` touches, the ranges they are assigned, and what to take off addresses before
+    /// handing them over, through the page table if possible
+    #[inline]
+    fn mapped_components<'a>(
+        &'a self,
+        bus_info: &'a BusInfo,
+        accessing_range: Range<usize>,
+    ) -> impl Iterator<Item = (&'a Range<usize>, usize, &'a Arc<dyn MemoryComponent>)> + 'a {
+        if let Some(region) = bus_info.page(&accessing_range) {
+            return Either::Left(std::iter::once((
+                &region.assigned_range,
+                region.offset,
+                &region.component,
+            )));
+        }
+
+        let component_store = self.component_store.as_ref().unwrap();
+
+        Either::Right(bus_info.population.overlapping(accessing_range).map(
+            move |(component_assignment_range, mapping)| {
+                let component = component_store
+                    .get(mapping.component_id)
+                    .and_then(|table| table.as_memory.as_ref().map(|info| &info.component))
+                    .unwrap();
+
+                (component_assignment_range, mapping.offset, component)
+            },
+        ))
+    }
+
+    /// Every address accessed with nothing there since the last clear, most accessed first
+    pub fn unmapped_accesses(&self) -> Vec<UnmappedAccess> {
+        let mut unmapped_accesses: Vec<_> = self
+            .unmapped_accesses
+            .lock()
+            .unwrap()
+            .iter()
+            .map(
+                |((address_space, address, component), (reads, writes))| UnmappedAccess {
+                    address_space: *address_space,
+                    address: *address,
+                    component: *component,
+                    reads: *reads,
+                    writes: *writes,
+                },
+            )
+            .collect();
+        unmapped_accesses.sort_by(|a, b| {
+            (b.reads + b.writes)
+                .cmp(&(a.reads + a.writes))
+                .then(a.address_space.cmp(&b.address_space))
+                .then(a.address.cmp(&b.address))
+        });
+
+        unmapped_accesses
+    }
+
+    pub fn clear_unmapped_accesses(&self) {
+        self.unmapped_accesses.lock().unwrap().clear();
+    }
+
+    /// Only called once an access is known to have missed somewhere, so mapped accesses never take the lock
+    #[cold]
+    fn record_unmapped(
+        &self,
+        bus_info: &BusInfo,
+        address_space: AddressSpaceId,
+        accessing_range: Range<usize>,
+        write: bool,
+    ) {
+        let component = ACCESSING_COMPONENT.get();
+        let mut unmapped_accesses = self.unmapped_accesses.lock().unwrap();
+
+        for gap in bus_info.population.gaps(&accessing_range) {
+            let (reads, writes) = unmapped_accesses
+                .entry((address_space, gap.start, component))
+                .or_default();
+
+            if write {
+                *writes += 1;
+            } else {
+                *reads += 1;
+            }
+        }
+    }
+
+    /// Starts sending every read and write to `sink`, or stops tracing with [None]
+    pub fn set_access_trace(&self, sink: Option<AccessTraceSink>) {
+        let mut access_trace = self.access_trace.lock().unwrap();
+
+        if let Some(AccessTraceSink::File(mut file)) = access_trace.take() {
+            if let Err(err) = file.flush() {
+                tracing::error!("Could not finish writing memory access trace: {}", err);
+            }
+        }
+
+        self.tracing.store(sink.is_some(), Ordering::Relaxed);
+        *access_trace = sink;
+    }
+
+    pub fn is_tracing(&self) -> bool {
+        self.tracing.load(Ordering::Relaxed)
+    }
+
+    /// What the ring buffer being traced to holds, oldest first
+    pub fn traced_accesses(&self) -> Vec<MemoryAccess> {
+        match self.access_trace.lock().unwrap().as_ref() {
+            Some(AccessTraceSink::RingBuffer(accesses)) => accesses.to_vec(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Starts or stops counting accesses per page, what was counted so far is kept either way
+    pub fn set_heatmap(&self, collecting: bool) {
+        self.collecting_heatmap.store(collecting, Ordering::Relaxed);
+    }
+
+    pub fn is_collecting_heatmap(&self) -> bool {
+        self.collecting_heatmap.load(Ordering::Relaxed)
+    }
+
+    /// Every page accessed while collecting, ordered by address space then address
+    pub fn access_heatmap(&self) -> Vec<PageAccesses> {
+        let mut heatmap: Vec<_> = self
+            .heatmap
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|((address_space, page), (reads, writes))| PageAccesses {
+                address_space: *address_space,
+                address: page * PAGE_SIZE,
+                reads: *reads,
+                writes: *writes,
+            })
+            .collect();
+        heatmap.sort_by_key(|page| (page.address_space, page.address));
+
+        heatmap
+    }
+
+    pub fn clear_access_heatmap(&self) {
+        self.heatmap.lock().unwrap().clear();
+    }
+
+    /// Remembers writes that touch any of `addresses` until they are taken with [Self::take_write_watch_hits]
+    ///
+    /// Replaces whatever was watched before, watching nothing turns it off
+    pub fn set_write_watches(&self, addresses: impl IntoIterator<Item = (AddressSpaceId, usize)>) {
+        let mut write_watches = self.write_watches.lock().unwrap();
+
+        write_watches.watched = addresses.into_iter().collect();
+        write_watches.hits.clear();
+        self.watching_writes
+            .store(!write_watches.watched.is_empty(), Ordering::Relaxed);
+    }
+
+    /// Watched addresses written since the last call, a write of several bytes counting for each it touched
+    pub fn take_write_watch_hits(&self) -> Vec<(AddressSpaceId, usize)> {
+        std::mem::take(&mut self.write_watches.lock().unwrap().hits)
+    }
+
+    #[cold]
+    fn check_write_watches(
+        &self,
+        bus_info: &BusInfo,
+        address: usize,
+        size: usize,
+        address_space: AddressSpaceId,
+    ) {
+        let width = bus_info.width as usize;
+        let mut write_watches = self.write_watches.lock().unwrap();
+        let write_watches = &mut *write_watches;
+
+        for offset in 0..size {
+            let address =
+                address.wrapping_add(offset).view_bits::<Lsb0>()[..width].load_le::<usize>();
+
+            if write_watches.watched.contains(&(address_space, address)) {
+                write_watches.hits.push((address_space, address));
+            }
+        }
+    }
+
+    #[cold]
+    fn record_heat(
+        &self,
+        bus_info: &BusInfo,
+        address: usize,
+        address_space: AddressSpaceId,
+        write: bool,
+    ) {
+        let width = bus_info.width as usize;
+        let address = address.view_bits::<Lsb0>()[..width].load_le::<usize>();
+        let mut heatmap = self.heatmap.lock().unwrap();
+        let (reads, writes) = heatmap
+            .entry((address_space, address / PAGE_SIZE))
+            .or_default();
+
+        if write {
+            *writes += 1;
+        } else {
+            *reads += 1;
+        }
+    }
+
+    #[cold]
+    fn trace(
+        &self,
+        bus_info: &BusInfo,
+        kind: MemoryAccessKind,
+        address: usize,
+        buffer: &[u8],
+        address_space: AddressSpaceId,
+        failed: bool,
+    ) {
+        let width = bus_info.width as usize;
+        let mut value = [0; 8];
+        value[..buffer.len()].copy_from_slice(buffer);
+
+        let access = MemoryAccess {
+            kind,
+            address_space,
+            address: address.view_bits::<Lsb0>()[..width].load_le::<usize>(),
+            size: buffer.len() as u8,
+            component: ACCESSING_COMPONENT.get(),
+            value: u64::from_le_bytes(value),
+            failed,
+        };
+
+        match self.access_trace.lock().unwrap().as_mut() {
+            Some(AccessTraceSink::RingBuffer(accesses)) => accesses.push(access),
+            Some(AccessTraceSink::File(file)) => {
+                if let Err(err) = writeln!(file, "{}", access) {
+                    tracing::error!("Could not write memory access trace: {}", err);
+                }
+            }
+            None => {}
+        }
+    }
+
     pub fn address_spaces(&self) -> u8 {
         self.busses
-            .len()
+            .iter()
+            .flatten()
+            .count()
             .try_into()
             .expect("Too many address spaces!")
     }
@@ -134,6 +985,36 @@ impl MemoryTranslationTable {
         address: usize,
         buffer: &mut [u8],
         address_space: AddressSpaceId,
+    ) -> Result<(), ReadMemoryOperationError> {
+        // Taken once so the bookkeeping below does not go back to the lock for it
+        let bus_info = self.bus_info(address_space);
+        let result = self.read_untraced(&bus_info, address, buffer, address_space);
+
+        if self.collecting_heatmap.load(Ordering::Relaxed) {
+            self.record_heat(&bus_info, address, address_space, false);
+        }
+
+        if self.tracing.load(Ordering::Relaxed) {
+            self.trace(
+                &bus_info,
+                MemoryAccessKind::Read,
+                address,
+                buffer,
+                address_space,
+                result.is_err(),
+            );
+        }
+
+        result
+    }
+
+    #[inline]
+    fn read_untraced(
+        &self,
+        bus_info: &BusInfo,
+        address: usize,
+        buffer: &mut [u8],
+        address_space: AddressSpaceId,
     ) -> Result<(), ReadMemoryOperationError> {
         debug_assert!(
             VALID_ACCESS_SIZES.contains(&buffer.len()),
@@ -141,14 +1022,13 @@ impl MemoryTranslationTable {
             buffer.len()
         );
 
-        let bus_info = self
-            .busses
-            .get(&address_space)
-            .expect("Non existant address space");
-
         // Cut off address
         let address = address.view_bits::<Lsb0>()[..bus_info.width as usize].load_le::<usize>();
 
+        if !bus_info.wait_states.is_empty() {
+            bus_info.charge_wait_states(&(address..address + buffer.len()));
+        }
+
         let mut needed_accesses =
             ArrayVec::<_, { MAX_ACCESS_SIZE as usize }>::from_iter([(address, 0..buffer.len())]);
 
@@ -156,24 +1036,20 @@ impl MemoryTranslationTable {
             let accessing_range =
                 (buffer_subrange.start + address)..(buffer_subrange.end + address);
 
-            for (component_assignment_range, component_id) in
-                bus_info.population.overlapping(accessing_range.clone())
+            let mut mapped = 0;
+
+            for (component_assignment_range, offset, component) in
+                self.mapped_components(bus_info, accessing_range.clone())
             {
                 let mut errors = RangeMap::default();
-                let component = self
-                    .component_store
-                    .as_ref()
-                    .unwrap()
-                    .get(*component_id)
-                    .and_then(|table| table.as_memory.as_ref().map(|info| &info.component))
-                    .unwrap();
 
                 let overlap_start = accessing_range.start.max(component_assignment_range.start);
                 let overlap_end = accessing_range.end.min(component_assignment_range.end);
                 let overlap = overlap_start..overlap_end;
+                mapped += overlap.len();
 
                 component.read_memory(
-                    overlap.start,
+                    overlap.start - offset,
                     &mut buffer[buffer_subrange.clone()],
                     address_space,
                     &mut errors,
@@ -182,6 +1058,9 @@ impl MemoryTranslationTable {
                 let mut detected_errors = RangeMap::default();
 
                 for (range, error) in errors {
+                    // Back to where it is on the bus
+                    let range = range.start + offset..range.end + offset;
+
                     match error {
                         ReadMemoryRecord::Denied => {
                             detected_errors
@@ -207,6 +1086,10 @@ impl MemoryTranslationTable {
                     return Err(ReadMemoryOperationError(detected_errors));
                 }
             }
+
+            if mapped < accessing_range.len() {
+                self.record_unmapped(bus_info, address_space, accessing_range, false);
+            }
         }
 
         Ok(())
@@ -221,6 +1104,40 @@ impl MemoryTranslationTable {
         address: usize,
         buffer: &[u8],
         address_space: AddressSpaceId,
+    ) -> Result<(), WriteMemoryOperationError> {
+        // Taken once so the bookkeeping below does not go back to the lock for it
+        let bus_info = self.bus_info(address_space);
+        let result = self.write_untraced(&bus_info, address, buffer, address_space);
+
+        if self.collecting_heatmap.load(Ordering::Relaxed) {
+            self.record_heat(&bus_info, address, address_space, true);
+        }
+
+        if self.watching_writes.load(Ordering::Relaxed) && result.is_ok() {
+            self.check_write_watches(&bus_info, address, buffer.len(), address_space);
+        }
+
+        if self.tracing.load(Ordering::Relaxed) {
+            self.trace(
+                &bus_info,
+                MemoryAccessKind::Write,
+                address,
+                buffer,
+                address_space,
+                result.is_err(),
+            );
+        }
+
+        result
+    }
+
+    #[inline]
+    fn write_untraced(
+        &self,
+        bus_info: &BusInfo,
+        address: usize,
+        buffer: &[u8],
+        address_space: AddressSpaceId,
     ) -> Result<(), WriteMemoryOperationError> {
         debug_assert!(
             VALID_ACCESS_SIZES.contains(&buffer.len()),
@@ -228,13 +1145,30 @@ impl MemoryTranslationTable {
             buffer.len()
         );
 
-        let bus_info = self
-            .busses
-            .get(&address_space)
-            .expect("Non existant address space");
-
         let address = address.view_bits::<Lsb0>()[..bus_info.width as usize].load_le::<usize>();
 
+        if !bus_info.wait_states.is_empty() {
+            bus_info.charge_wait_states(&(address..address + buffer.len()));
+        }
+
+        if !bus_info.write_protected.is_empty() {
+            let accessing_range = address..address + buffer.len();
+            let protected =
+                RangeMap::from_iter(bus_info.write_protected.overlapping(&accessing_range).map(
+                    |range| {
+                        (
+                            range.start.max(accessing_range.start)
+                                ..range.end.min(accessing_range.end),
+                            WriteMemoryOperationErrorFailureType::Denied,
+                        )
+                    },
+                ));
+
+            if !protected.is_empty() {
+                return Err(WriteMemoryOperationError(protected));
+            }
+        }
+
         let mut needed_accesses =
             ArrayVec::<_, { MAX_ACCESS_SIZE as usize }>::from_iter([(address, 0..buffer.len())]);
 
@@ -242,24 +1176,20 @@ impl MemoryTranslationTable {
             let accessing_range =
                 (buffer_subrange.start + address)..(buffer_subrange.end + address);
 
-            for (component_assignment_range, component_id) in
-                bus_info.population.overlapping(accessing_range.clone())
+            let mut mapped = 0;
+
+            for (component_assignment_range, offset, component) in
+                self.mapped_components(bus_info, accessing_range.clone())
             {
                 let mut errors = RangeMap::default();
-                let component = self
-                    .component_store
-                    .as_ref()
-                    .unwrap()
-                    .get(*component_id)
-                    .and_then(|table| table.as_memory.as_ref().map(|info| &info.component))
-                    .unwrap();
 
                 let overlap_start = accessing_range.start.max(component_assignment_range.start);
                 let overlap_end = accessing_range.end.min(component_assignment_range.end);
                 let overlap = overlap_start..overlap_end;
+                mapped += overlap.len();
 
                 component.write_memory(
-                    overlap.start,
+                    overlap.start - offset,
                     &buffer[buffer_subrange.clone()],
                     address_space,
                     &mut errors,
@@ -268,6 +1198,9 @@ impl MemoryTranslationTable {
                 let mut detected_errors = RangeMap::default();
 
                 for (range, error) in errors {
+                    // Back to where it is on the bus
+                    let range = range.start + offset..range.end + offset;
+
                     match error {
                         WriteMemoryRecord::Denied => {
                             detected_errors
@@ -293,6 +1226,194 @@ impl MemoryTranslationTable {
                     return Err(WriteMemoryOperationError(detected_errors));
                 }
             }
+
+            if mapped < accessing_range.len() {
+                self.record_unmapped(bus_info, address_space, accessing_range, true);
+            }
+        }
+
+        Ok(())
+    }
+
+    typed_accessors! {
+        read_le_u16, write_le_u16, u16, from_le_bytes, to_le_bytes;
+        read_le_u32, write_le_u32, u32, from_le_bytes, to_le_bytes;
+        read_be_u16, write_be_u16, u16, from_be_bytes, to_be_bytes;
+        read_be_u32, write_be_u32, u32, from_be_bytes, to_be_bytes;
+    }
+
+    /// Like [Self::read] but for buffers of any length, split up so no single access crosses from one component to another
+    ///
+    /// Stops at the first piece that fails, leaving the rest of the buffer untouched
+    pub fn read_block(
+        &self,
+        address: usize,
+        buffer: &mut [u8],
+        address_space: AddressSpaceId,
+    ) -> Result<(), ReadMemoryOperationError> {
+        for (address, buffer_subrange) in self.block_accesses(address, buffer.len(), address_space)
+        {
+            self.read(address, &mut buffer[buffer_subrange], address_space)?;
+        }
+
+        Ok(())
+    }
+
+    /// Like [Self::write] but for buffers of any length, see [Self::read_block]
+    pub fn write_block(
+        &self,
+        address: usize,
+        buffer: &[u8],
+        address_space: AddressSpaceId,
+    ) -> Result<(), WriteMemoryOperationError> {
+        for (address, buffer_subrange) in self.block_accesses(address, buffer.len(), address_space)
+        {
+            self.write(address, &buffer[buffer_subrange], address_space)?;
+        }
+
+        Ok(())
+    }
+
+    /// Like [Self::preview] but for buffers of any length, see [Self::read_block]
+    pub fn preview_block(
+        &self,
+        address: usize,
+        buffer: &mut [u8],
+        address_space: AddressSpaceId,
+    ) -> Result<(), PreviewMemoryOperationError> {
+        for (address, buffer_subrange) in self.block_accesses(address, buffer.len(), address_space)
+        {
+            self.preview(address, &mut buffer[buffer_subrange], address_space)?;
+        }
+
+        Ok(())
+    }
+
+    /// Breaks a transfer up into the biggest valid accesses that each stay within one component, wrapping around the
+    /// end of the bus like single accesses do
+    fn block_accesses(
+        &self,
+        address: usize,
+        length: usize,
+        address_space: AddressSpaceId,
+    ) -> impl Iterator<Item = (usize, Range<usize>)> + '_ {
+        let bus_info = self.bus_info(address_space);
+        let mut offset = 0;
+
+        std::iter::from_fn(move || {
+            if offset >= length {
+                return None;
+            }
+
+            let address = address.wrapping_add(offset).view_bits::<Lsb0>()
+                [..bus_info.width as usize]
+                .load_le::<usize>();
+            let span = (length - offset).min(bus_info.boundary(address) - address);
+            let size = VALID_ACCESS_SIZES
+                .iter()
+                .copied()
+                .filter(|size| *size <= span)
+                .max()
+                .unwrap();
+
+            let access = (address, offset..offset + size);
+            offset += size;
+
+            Some(access)
+        })
+    }
+
+    /// Previews every byte of `range`, with [None] wherever nothing is mapped or the component could not say without
+    /// changing something
+    pub fn preview_range(
+        &self,
+        range: Range<usize>,
+        address_space: AddressSpaceId,
+    ) -> Vec<Option<u8>> {
+        let bus_info = self.bus_info(address_space);
+        let mut contents = vec![None; range.len()];
+        let mut buffer = [0; MAX_ACCESS_SIZE as usize];
+
+        for (address, buffer_subrange) in
+            self.block_accesses(range.start, range.len(), address_space)
+        {
+            // Accesses never straddle a gap, so this is the whole access being unmapped
+            if !bus_info.population.contains_key(&address) {
+                continue;
+            }
+
+            let buffer = &mut buffer[..buffer_subrange.len()];
+            let failures = match self.preview(address, buffer, address_space) {
+                Ok(()) => RangeMap::default(),
+                Err(PreviewMemoryOperationError(failures)) => failures,
+            };
+
+            for (offset, byte) in buffer.iter().enumerate() {
+                if !failures.contains_key(&(address + offset)) {
+                    contents[buffer_subrange.start + offset] = Some(*byte);
+                }
+            }
+        }
+
+        contents
+    }
+
+    /// The whole bus as [Self::preview_range] sees it, which is a lot of memory for anything wider than 16 bits
+    pub fn dump_address_space(&self, address_space: AddressSpaceId) -> Vec<Option<u8>> {
+        let end = self.bus_info(address_space).end();
+
+        self.preview_range(0..end, address_space)
+    }
+
+    /// Writes what [Self::preview_range] sees to `writer`, with zeros wherever it saw nothing, and says how many of
+    /// those there were
+    pub fn dump_range(
+        &self,
+        range: Range<usize>,
+        address_space: AddressSpaceId,
+        mut writer: impl Write,
+    ) -> Result<usize, MemoryDumpError> {
+        self.check_dump_range(&range, address_space)?;
+
+        let contents = self.preview_range(range, address_space);
+        let missing = contents.iter().filter(|byte| byte.is_none()).count();
+        writer.write_all(&Vec::from_iter(
+            contents.into_iter().map(|byte| byte.unwrap_or(0)),
+        ))?;
+
+        Ok(missing)
+    }
+
+    /// Writes everything in `reader` to the bus starting at `address`
+    ///
+    /// Unlike dumping this takes the normal write path, so registers in the way will see it
+    pub fn restore_range(
+        &self,
+        address: usize,
+        address_space: AddressSpaceId,
+        mut reader: impl Read,
+    ) -> Result<usize, MemoryDumpError> {
+        let mut contents = Vec::new();
+        reader.read_to_end(&mut contents)?;
+        self.check_dump_range(&(address..address + contents.len()), address_space)?;
+
+        self.write_block(address, &contents, address_space)?;
+
+        Ok(contents.len())
+    }
+
+    /// Ranges for dumping come from the user, so they get an error instead of a panic
+    fn check_dump_range(
+        &self,
+        range: &Range<usize>,
+        address_space: AddressSpaceId,
+    ) -> Result<(), MemoryDumpError> {
+        if !self.busses().any(|(id, _)| id == address_space) {
+            return Err(MemoryDumpError::UnknownAddressSpace(address_space));
+        }
+
+        if range.start > range.end || range.end > self.bus_info(address_space).end() {
+            return Err(MemoryDumpError::OutOfBus(range.clone()));
         }
 
         Ok(())
@@ -311,10 +1432,7 @@ impl MemoryTranslationTable {
             buffer.len()
         );
 
-        let bus_info = self
-            .busses
-            .get(&address_space)
-            .expect("Non existant address space");
+        let bus_info = self.bus_info(address_space);
 
         let address = address.view_bits::<Lsb0>()[..bus_info.width as usize].load_le::<usize>();
 
@@ -325,24 +1443,17 @@ impl MemoryTranslationTable {
             let accessing_range =
                 (buffer_subrange.start + address)..(buffer_subrange.end + address);
 
-            for (component_assignment_range, component_id) in
-                bus_info.population.overlapping(accessing_range.clone())
+            for (component_assignment_range, offset, component) in
+                self.mapped_components(&bus_info, accessing_range.clone())
             {
                 let mut errors = RangeMap::default();
-                let component = self
-                    .component_store
-                    .as_ref()
-                    .unwrap()
-                    .get(*component_id)
-                    .and_then(|table| table.as_memory.as_ref().map(|info| &info.component))
-                    .unwrap();
 
                 let overlap_start = accessing_range.start.max(component_assignment_range.start);
                 let overlap_end = accessing_range.end.min(component_assignment_range.end);
                 let overlap = overlap_start..overlap_end;
 
                 component.preview_memory(
-                    overlap.start,
+                    overlap.start - offset,
                     &mut buffer[buffer_subrange.clone()],
                     address_space,
                     &mut errors,
@@ -351,6 +1462,9 @@ impl MemoryTranslationTable {
                 let mut detected_errors = RangeMap::default();
 
                 for (range, error) in errors {
+                    // Back to where it is on the bus
+                    let range = range.start + offset..range.end + offset;
+
                     match error {
                         PreviewMemoryRecord::Denied => {
                             detected_errors
@@ -385,3 +1499,537 @@ impl MemoryTranslationTable {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        definitions::misc::memory::{
+            register::{Register, RegisterBlock, RegisterBlockConfig},
+            standard::{StandardMemory, StandardMemoryConfig, StandardMemoryInitialContents},
+        },
+        machine::Machine,
+        rom::{manager::RomManager, system::GameSystem},
+        testing::ComponentHarness,
+    };
+    use std::sync::atomic::AtomicU8;
+
+    #[test]
+    fn page_table_falls_back_on_shared_pages() {
+        let rom_manager = Arc::new(RomManager::new(None).unwrap());
+        let machine = Machine::build(GameSystem::Unknown, rom_manager);
+        let (machine, _) = machine
+            .insert_bus(0, 16)
+            .build_component::<StandardMemory>(StandardMemoryConfig {
+                readable: true,
+                writable: true,
+                max_word_size: 2,
+                assigned_range: 0x000..0x180,
+                assigned_address_space: 0,
+                initial_contents: StandardMemoryInitialContents::Value { value: 0x11 },
+            })
+            .unwrap();
+        let (machine, _) = machine
+            .build_component::<StandardMemory>(StandardMemoryConfig {
+                readable: true,
+                writable: true,
+                max_word_size: 2,
+                assigned_range: 0x180..0x200,
+                assigned_address_space: 0,
+                initial_contents: StandardMemoryInitialContents::Value { value: 0x22 },
+            })
+            .unwrap();
+        let machine = machine.build().unwrap();
+        let memory_translation_table = &machine.memory_translation_table;
+        let bus_info = memory_translation_table.bus_info(0);
+
+        assert!(bus_info.page(&(0x0fe..0x100)).is_some());
+        // Shared between both memories
+        assert!(bus_info.page(&(0x17f..0x180)).is_none());
+        assert!(bus_info.page(&(0x200..0x201)).is_none());
+
+        memory_translation_table
+            .write(0x0fe, &[0x33, 0x44], 0)
+            .unwrap();
+        let mut buffer = [0; 2];
+        memory_translation_table
+            .read(0x0fe, &mut buffer, 0)
+            .unwrap();
+        assert_eq!(buffer, [0x33, 0x44]);
+
+        let mut buffer = [0];
+        memory_translation_table
+            .read(0x17f, &mut buffer, 0)
+            .unwrap();
+        assert_eq!(buffer, [0x11]);
+        memory_translation_table
+            .read(0x180, &mut buffer, 0)
+            .unwrap();
+        assert_eq!(buffer, [0x22]);
+    }
+
+    #[test]
+    fn mirrors_resolve_in_one_lookup() {
+        let rom_manager = Arc::new(RomManager::new(None).unwrap());
+        let (machine, workram_id) = Machine::build(GameSystem::Unknown, rom_manager)
+            .insert_bus(0, 16)
+            .build_component::<StandardMemory>(StandardMemoryConfig {
+                readable: true,
+                writable: true,
+                max_word_size: 2,
+                assigned_range: 0x0000..0x0800,
+                assigned_address_space: 0,
+                initial_contents: StandardMemoryInitialContents::Value { value: 0 },
+            })
+            .unwrap();
+        let machine = machine
+            .mirror_memory(workram_id, 0, 0x0800..0x2000, 0x07ff)
+            .unwrap()
+            .build()
+            .unwrap();
+        let memory_translation_table = &machine.memory_translation_table;
+        let bus_info = memory_translation_table.bus_info(0);
+
+        assert_eq!(bus_info.page(&(0x1801..0x1803)).unwrap().offset, 0x1800);
+
+        memory_translation_table
+            .write(0x1801, &[0x12, 0x34], 0)
+            .unwrap();
+        let mut buffer = [0; 2];
+        memory_translation_table
+            .read(0x0001, &mut buffer, 0)
+            .unwrap();
+        assert_eq!(buffer, [0x12, 0x34]);
+        memory_translation_table
+            .read(0x0801, &mut buffer, 0)
+            .unwrap();
+        assert_eq!(buffer, [0x12, 0x34]);
+
+        assert_eq!(
+            Vec::from_iter(mirror_blocks(0x2008..0x2018, 0x2007)),
+            [(0x2008..0x2010, 0x8), (0x2010..0x2018, 0x10)]
+        );
+    }
+
+    #[test]
+    fn blocks_are_split_between_components() {
+        let rom_manager = Arc::new(RomManager::new(None).unwrap());
+        let (machine, _) = Machine::build(GameSystem::Unknown, rom_manager)
+            .insert_bus(0, 8)
+            .build_component::<StandardMemory>(StandardMemoryConfig {
+                readable: true,
+                writable: true,
+                max_word_size: 8,
+                assigned_range: 0x00..0x13,
+                assigned_address_space: 0,
+                initial_contents: StandardMemoryInitialContents::Value { value: 0 },
+            })
+            .unwrap();
+        let (machine, _) = machine
+            .build_component::<StandardMemory>(StandardMemoryConfig {
+                readable: true,
+                writable: true,
+                max_word_size: 8,
+                assigned_range: 0x13..0x100,
+                assigned_address_space: 0,
+                initial_contents: StandardMemoryInitialContents::Value { value: 0 },
+            })
+            .unwrap();
+        let machine = machine.build().unwrap();
+        let memory_translation_table = &machine.memory_translation_table;
+        let block: Vec<u8> = (0..0x25).collect();
+
+        memory_translation_table
+            .write_block(0x05, &block, 0)
+            .unwrap();
+        let mut buffer = vec![0; block.len()];
+        memory_translation_table
+            .read_block(0x05, &mut buffer, 0)
+            .unwrap();
+        assert_eq!(buffer, block);
+
+        // Comes back around from the end of the bus
+        memory_translation_table
+            .write_block(0xfe, &[0xaa; 3], 0)
+            .unwrap();
+        let mut buffer = [0; 3];
+        memory_translation_table
+            .preview_block(0xfe, &mut buffer, 0)
+            .unwrap();
+        assert_eq!(buffer, [0xaa; 3]);
+        assert!(memory_translation_table.unmapped_accesses().is_empty());
+    }
+
+    #[test]
+    fn remapping_invalidates_pages() {
+        let rom_manager = Arc::new(RomManager::new(None).unwrap());
+        let (machine, low) = Machine::build(GameSystem::Unknown, rom_manager)
+            .insert_bus(0, 16)
+            .allow_overlapping_memory()
+            .build_component::<StandardMemory>(StandardMemoryConfig {
+                readable: true,
+                writable: true,
+                max_word_size: 2,
+                assigned_range: 0x000..0x400,
+                assigned_address_space: 0,
+                initial_contents: StandardMemoryInitialContents::Value { value: 0x11 },
+            })
+            .unwrap();
+        // Covers the top half of the first memory
+        let (machine, high) = machine
+            .build_component::<StandardMemory>(StandardMemoryConfig {
+                readable: true,
+                writable: true,
+                max_word_size: 2,
+                assigned_range: 0x200..0x400,
+                assigned_address_space: 0,
+                initial_contents: StandardMemoryInitialContents::Value { value: 0x22 },
+            })
+            .unwrap();
+        let machine = machine.build().unwrap();
+        let memory_translation_table = &machine.memory_translation_table;
+        let mut buffer = [0];
+
+        memory_translation_table
+            .read(0x300, &mut buffer, 0)
+            .unwrap();
+        assert_eq!(buffer, [0x22]);
+
+        memory_translation_table
+            .remap(0, 0x200..0x400, low)
+            .unwrap();
+        memory_translation_table
+            .read(0x300, &mut buffer, 0)
+            .unwrap();
+        assert_eq!(buffer, [0x11]);
+        // Merged with the rest of the first memory
+        assert_eq!(
+            memory_translation_table
+                .bus_info(0)
+                .page(&(0x1ff..0x201))
+                .map(|region| region.assigned_range.clone()),
+            Some(0x000..0x400)
+        );
+
+        memory_translation_table
+            .remap(0, 0x300..0x400, high)
+            .unwrap();
+        memory_translation_table
+            .read(0x300, &mut buffer, 0)
+            .unwrap();
+        assert_eq!(buffer, [0x22]);
+        assert_eq!(
+            memory_translation_table.remap(0, 0xff00..0x10001, high),
+            Err(RemapError::OutOfBus(0xff00..0x10001))
+        );
+    }
+
+    #[test]
+    fn typed_accessors_respect_endianness() {
+        let memory_translation_table = ComponentHarness::build()
+            .memory(0, 16, vec![0; 0x100])
+            .memory_translation_table();
+        let mut buffer = [0; 4];
+
+        memory_translation_table
+            .write_le_u32(0x10, 0x12345678, 0)
+            .unwrap();
+        memory_translation_table.read(0x10, &mut buffer, 0).unwrap();
+        assert_eq!(buffer, [0x78, 0x56, 0x34, 0x12]);
+        assert_eq!(
+            memory_translation_table.read_be_u32(0x10, 0).unwrap(),
+            0x78563412
+        );
+        assert_eq!(
+            memory_translation_table.read_le_u16(0x12, 0).unwrap(),
+            0x1234
+        );
+
+        memory_translation_table
+            .write_be_u16(0x20, 0xabcd, 0)
+            .unwrap();
+        assert_eq!(
+            memory_translation_table.read_le_u16(0x20, 0).unwrap(),
+            0xcdab
+        );
+    }
+
+    #[test]
+    fn dumps_restore_to_the_same_contents() {
+        let memory_translation_table = ComponentHarness::build()
+            .memory(0, 16, vec![0; 0x100])
+            .memory(1, 8, vec![0; 0x100])
+            .memory_translation_table();
+        let contents = Vec::from_iter(0..0x40u8);
+
+        assert_eq!(parse_address_range("0x10..80").unwrap(), 0x10..80);
+        assert!(parse_address_range("0x10").is_err());
+
+        memory_translation_table
+            .restore_range(0x10, 0, contents.as_slice())
+            .unwrap();
+        let mut dump = Vec::new();
+        memory_translation_table
+            .dump_range(0x10..0x50, 0, &mut dump)
+            .unwrap();
+        assert_eq!(dump, contents);
+
+        assert!(matches!(
+            memory_translation_table.dump_range(0xf0..0x110, 1, &mut dump),
+            Err(MemoryDumpError::OutOfBus(_))
+        ));
+        assert!(matches!(
+            memory_translation_table.dump_range(0..1, 2, &mut dump),
+            Err(MemoryDumpError::UnknownAddressSpace(2))
+        ));
+    }
+
+    #[test]
+    fn dumping_does_not_touch_registers() {
+        let reads = Arc::new(AtomicU8::new(0));
+        let harness = ComponentHarness::build()
+            .memory(0, 8, [1, 2, 3, 4])
+            .component::<RegisterBlock>(RegisterBlockConfig {
+                assigned_range: 0x10..0x14,
+                assigned_address_space: 0,
+                repeat_every: None,
+                registers: vec![
+                    Register::new("STATUS", 0).on_read({
+                        let reads = reads.clone();
+                        move |_| reads.fetch_add(1, Ordering::Relaxed)
+                    }),
+                    Register::new("CONTROL", 1).on_preview(|_| 0xaa),
+                ],
+            });
+
+        let contents = harness.memory_translation_table().dump_address_space(0);
+
+        assert_eq!(contents.len(), 0x100);
+        assert_eq!(&contents[..4], &[Some(1), Some(2), Some(3), Some(4)]);
+        assert!(contents[4..0x10].iter().all(Option::is_none));
+        assert_eq!(&contents[0x10..0x14], &[None, Some(0xaa), None, None]);
+        assert!(contents[0x14..].iter().all(Option::is_none));
+        assert_eq!(reads.load(Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    fn accesses_are_traced() {
+        let rom_manager = Arc::new(RomManager::new(None).unwrap());
+        let (machine, _) = Machine::build(GameSystem::Unknown, rom_manager)
+            .insert_bus(0, 8)
+            .build_component::<StandardMemory>(StandardMemoryConfig {
+                readable: true,
+                writable: true,
+                max_word_size: 2,
+                assigned_range: 0x00..0x10,
+                assigned_address_space: 0,
+                initial_contents: StandardMemoryInitialContents::Value { value: 0 },
+            })
+            .unwrap();
+        let machine = machine.build().unwrap();
+        let memory_translation_table = &machine.memory_translation_table;
+
+        // Nothing is kept before tracing starts
+        memory_translation_table.write(0x00, &[0xff], 0).unwrap();
+        memory_translation_table.set_access_trace(Some(AccessTraceSink::ring_buffer(2)));
+
+        memory_translation_table.write(0x00, &[0x11], 0).unwrap();
+        memory_translation_table
+            .write(0x102, &[0x34, 0x12], 0)
+            .unwrap();
+        let mut buffer = [0; 2];
+        memory_translation_table.read(0x02, &mut buffer, 0).unwrap();
+
+        let accesses = memory_translation_table.traced_accesses();
+        assert_eq!(
+            accesses,
+            [
+                MemoryAccess {
+                    kind: MemoryAccessKind::Write,
+                    address_space: 0,
+                    address: 0x02,
+                    size: 2,
+                    component: None,
+                    value: 0x1234,
+                    failed: false,
+                },
+                MemoryAccess {
+                    kind: MemoryAccessKind::Read,
+                    address_space: 0,
+                    address: 0x02,
+                    size: 2,
+                    component: None,
+                    value: 0x1234,
+                    failed: false,
+                }
+            ]
+        );
+        assert_eq!(accesses[0].to_string(), "W 0:0x0002 0x1234");
+
+        memory_translation_table.set_access_trace(None);
+        memory_translation_table.write(0x00, &[0x22], 0).unwrap();
+        assert!(memory_translation_table.traced_accesses().is_empty());
+    }
+
+    #[test]
+    fn heatmap_counts_per_page() {
+        let rom_manager = Arc::new(RomManager::new(None).unwrap());
+        let (machine, _) = Machine::build(GameSystem::Unknown, rom_manager)
+            .insert_bus(0, 16)
+            .build_component::<StandardMemory>(StandardMemoryConfig {
+                readable: true,
+                writable: true,
+                max_word_size: 2,
+                assigned_range: 0x0000..0x0400,
+                assigned_address_space: 0,
+                initial_contents: StandardMemoryInitialContents::Value { value: 0 },
+            })
+            .unwrap();
+        let machine = machine.build().unwrap();
+        let memory_translation_table = &machine.memory_translation_table;
+
+        // Nothing is counted before collecting starts
+        memory_translation_table.read(0x0000, &mut [0], 0).unwrap();
+        memory_translation_table.set_heatmap(true);
+
+        memory_translation_table.read(0x0010, &mut [0], 0).unwrap();
+        memory_translation_table.read(0x00ff, &mut [0], 0).unwrap();
+        memory_translation_table.write(0x0300, &[0, 0], 0).unwrap();
+        // Wraps around to the first page
+        memory_translation_table.write(0x10020, &[0], 0).unwrap();
+
+        memory_translation_table.set_heatmap(false);
+        memory_translation_table.read(0x0300, &mut [0], 0).unwrap();
+
+        assert_eq!(
+            memory_translation_table.access_heatmap(),
+            [
+                PageAccesses {
+                    address_space: 0,
+                    address: 0x0000,
+                    reads: 2,
+                    writes: 1,
+                },
+                PageAccesses {
+                    address_space: 0,
+                    address: 0x0300,
+                    reads: 0,
+                    writes: 1,
+                },
+            ]
+        );
+
+        memory_translation_table.clear_access_heatmap();
+        assert!(memory_translation_table.access_heatmap().is_empty());
+    }
+
+    #[test]
+    fn watched_writes_are_remembered() {
+        let rom_manager = Arc::new(RomManager::new(None).unwrap());
+        let (machine, _) = Machine::build(GameSystem::Unknown, rom_manager)
+            .insert_bus(0, 8)
+            .build_component::<StandardMemory>(StandardMemoryConfig {
+                readable: true,
+                writable: true,
+                max_word_size: 2,
+                assigned_range: 0x00..0x10,
+                assigned_address_space: 0,
+                initial_contents: StandardMemoryInitialContents::Value { value: 0 },
+            })
+            .unwrap();
+        let machine = machine.build().unwrap();
+        let memory_translation_table = &machine.memory_translation_table;
+
+        memory_translation_table.set_write_watches([(0, 0x05)]);
+        memory_translation_table.write(0x04, &[0, 0], 0).unwrap();
+        memory_translation_table.read(0x05, &mut [0], 0).unwrap();
+        memory_translation_table.write(0x06, &[0], 0).unwrap();
+        memory_translation_table.write(0x105, &[0], 0).unwrap();
+
+        assert_eq!(
+            memory_translation_table.take_write_watch_hits(),
+            [(0, 0x05), (0, 0x05)]
+        );
+        assert!(memory_translation_table.take_write_watch_hits().is_empty());
+
+        memory_translation_table.set_write_watches([]);
+        memory_translation_table.write(0x05, &[0], 0).unwrap();
+        assert!(memory_translation_table.take_write_watch_hits().is_empty());
+    }
+
+    #[test]
+    fn slow_regions_cost_wait_states() {
+        let memory_translation_table = ComponentHarness::build()
+            .memory(0, 16, vec![0; 0x100])
+            .memory_translation_table();
+
+        memory_translation_table
+            .set_wait_states(0, 0x80..0x100, 2)
+            .unwrap();
+        memory_translation_table
+            .set_wait_states(0, 0xc0..0x100, 3)
+            .unwrap();
+        set_accessing_component(None);
+
+        memory_translation_table.read(0x10, &mut [0], 0).unwrap();
+        assert_eq!(take_wait_states(), 0);
+
+        memory_translation_table.read(0x80, &mut [0], 0).unwrap();
+        memory_translation_table.write(0x90, &[0], 0).unwrap();
+        assert_eq!(take_wait_states(), 4);
+        assert_eq!(take_wait_states(), 0);
+
+        // Straddling two regions costs the slower one
+        memory_translation_table.read(0xbf, &mut [0; 2], 0).unwrap();
+        assert_eq!(take_wait_states(), 3);
+
+        memory_translation_table
+            .set_wait_states(0, 0x00..0x100, 0)
+            .unwrap();
+        memory_translation_table.read(0xc0, &mut [0], 0).unwrap();
+        assert_eq!(take_wait_states(), 0);
+    }
+
+    #[test]
+    fn unmapped_accesses_are_counted() {
+        let rom_manager = Arc::new(RomManager::new(None).unwrap());
+        let (machine, _) = Machine::build(GameSystem::Unknown, rom_manager)
+            .insert_bus(0, 8)
+            .build_component::<StandardMemory>(StandardMemoryConfig {
+                readable: true,
+                writable: true,
+                max_word_size: 2,
+                assigned_range: 0x00..0x80,
+                assigned_address_space: 0,
+                initial_contents: StandardMemoryInitialContents::Value { value: 0 },
+            })
+            .unwrap();
+        let machine = machine.build().unwrap();
+        let memory_translation_table = &machine.memory_translation_table;
+
+        memory_translation_table.read(0x80, &mut [0; 2], 0).unwrap();
+        memory_translation_table.read(0x80, &mut [0], 0).unwrap();
+        memory_translation_table.write(0x90, &[0], 0).unwrap();
+        memory_translation_table.read(0x10, &mut [0], 0).unwrap();
+
+        assert_eq!(
+            memory_translation_table.unmapped_accesses(),
+            [
+                UnmappedAccess {
+                    address_space: 0,
+                    address: 0x80,
+                    component: None,
+                    reads: 2,
+                    writes: 0,
+                },
+                UnmappedAccess {
+                    address_space: 0,
+                    address: 0x90,
+                    component: None,
+                    reads: 0,
+                    writes: 1,
+                },
+            ]
+        );
+    }
+}
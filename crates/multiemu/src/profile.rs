@@ -0,0 +1,154 @@
+//! Profiles let several people or setups share an install without sharing bindings, saves or libraries
+//!
+//! The default profile lives directly in [STORAGE_DIRECTORY] so installs from before profiles existed keep their data,
+//! every other profile gets its own directory under `profiles/<name>`
+
+use crate::config::STORAGE_DIRECTORY;
+use std::{
+    fs::{create_dir_all, read_dir, read_to_string, write},
+    path::PathBuf,
+    sync::LazyLock,
+};
+use thiserror::Error;
+
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// Remembers which profile was selected so it gets picked again on startup
+static SELECTED_PROFILE_LOCATION: LazyLock<PathBuf> =
+    LazyLock::new(|| STORAGE_DIRECTORY.join("selected_profile"));
+
+/// The profile this process is using, switching requires a restart
+pub static ACTIVE_PROFILE: LazyLock<String> = LazyLock::new(|| {
+    profile_from_arguments()
+        .filter(|name| match validate_profile_name(name) {
+            Ok(()) => true,
+            Err(err) => {
+                tracing::warn!("Ignoring requested profile {}: {}", name, err);
+                false
+            }
+        })
+        .or_else(selected_profile)
+        .unwrap_or_else(|| DEFAULT_PROFILE.to_string())
+});
+
+/// Where everything belonging to [ACTIVE_PROFILE] is stored
+pub static PROFILE_DIRECTORY: LazyLock<PathBuf> =
+    LazyLock::new(|| profile_directory(&ACTIVE_PROFILE));
+
+#[derive(Error, Debug)]
+pub enum ProfileError {
+    #[error("Profile names can only contain letters, numbers, spaces, dashes and underscores")]
+    InvalidName,
+    #[error("Profile {0} already exists")]
+    AlreadyExists(String),
+    #[error("Profile {0} does not exist")]
+    DoesNotExist(String),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+pub fn profile_directory(name: &str) -> PathBuf {
+    if name == DEFAULT_PROFILE {
+        STORAGE_DIRECTORY.clone()
+    } else {
+        STORAGE_DIRECTORY.join("profiles").join(name)
+    }
+}
+
+/// Every profile that exists, starting with the default one
+pub fn profiles() -> Vec<String> {
+    let mut profiles: Vec<_> = read_dir(STORAGE_DIRECTORY.join("profiles"))
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter(|name| validate_profile_name(name).is_ok() && name != DEFAULT_PROFILE)
+        .collect();
+    profiles.sort();
+    profiles.insert(0, DEFAULT_PROFILE.to_string());
+
+    profiles
+}
+
+pub fn create_profile(name: &str) -> Result<(), ProfileError> {
+    validate_profile_name(name)?;
+
+    let profile_directory = profile_directory(name);
+    if name == DEFAULT_PROFILE || profile_directory.exists() {
+        return Err(ProfileError::AlreadyExists(name.to_string()));
+    }
+
+    create_dir_all(profile_directory)?;
+    tracing::info!("Created profile {}", name);
+
+    Ok(())
+}
+
+/// Makes a profile the one picked on the next startup
+pub fn select_profile(name: &str) -> Result<(), ProfileError> {
+    validate_profile_name(name)?;
+
+    if !profiles().iter().any(|profile| profile == name) {
+        return Err(ProfileError::DoesNotExist(name.to_string()));
+    }
+
+    create_dir_all(STORAGE_DIRECTORY.as_path())?;
+    write(SELECTED_PROFILE_LOCATION.as_path(), name)?;
+
+    Ok(())
+}
+
+/// The profile picked on startup when none is passed on the command line
+pub fn selected_profile() -> Option<String> {
+    read_to_string(SELECTED_PROFILE_LOCATION.as_path())
+        .ok()
+        .map(|name| name.trim().to_string())
+        .filter(|name| validate_profile_name(name).is_ok() && profile_directory(name).is_dir())
+}
+
+pub fn validate_profile_name(name: &str) -> Result<(), ProfileError> {
+    // Names become directory names, so keep them boring
+    if name.trim().is_empty()
+        || name.trim() != name
+        || !name
+            .chars()
+            .all(|character| character.is_alphanumeric() || matches!(character, ' ' | '-' | '_'))
+    {
+        return Err(ProfileError::InvalidName);
+    }
+
+    Ok(())
+}
+
+/// This is touched before clap parses anything, so look for the flag ourselves
+fn profile_from_arguments() -> Option<String> {
+    let mut arguments = std::env::args().skip(1);
+
+    while let Some(argument) = arguments.next() {
+        if argument == "--profile" {
+            return arguments.next();
+        }
+
+        if let Some(name) = argument.strip_prefix("--profile=") {
+            return Some(name.to_string());
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn profile_name_validation() {
+        assert!(validate_profile_name("Kay").is_ok());
+        assert!(validate_profile_name("speedrun practice_2").is_ok());
+        assert!(validate_profile_name("").is_err());
+        assert!(validate_profile_name(" padded ").is_err());
+        assert!(validate_profile_name("../escape").is_err());
+        assert!(validate_profile_name("nested/profile").is_err());
+    }
+}
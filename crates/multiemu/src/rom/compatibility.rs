@@ -0,0 +1,373 @@
+//! Runs ROMs headlessly for a while to see which ones get anywhere, see `multiemu rom test-all`
+//!
+//! One report per system is kept in [COMPATIBILITY_DIRECTORY], so running the whole library again after changing a
+//! core shows what broke
+
+use super::{id::RomId, manager::RomManager, system::GameSystem};
+use crate::{
+    machine::definition::MachineDefinition,
+    profile::PROFILE_DIRECTORY,
+    runtime::rendering_backend::{DisplayComponentFramebuffer, DisplayComponentInitializationData},
+};
+use data_encoding::HEXLOWER;
+use ron::ser::PrettyConfig;
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DisplayFromStr};
+use sha1::{Digest, Sha1};
+use std::{
+    error::Error,
+    fmt::Display,
+    fs::{create_dir_all, read_dir, File},
+    panic::{catch_unwind, AssertUnwindSafe},
+    path::{Path, PathBuf},
+    sync::{Arc, LazyLock},
+};
+
+pub static COMPATIBILITY_DIRECTORY: LazyLock<PathBuf> =
+    LazyLock::new(|| PROFILE_DIRECTORY.join("compatibility"));
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum CompatibilityStatus {
+    /// Ran the whole time and put something on screen
+    Working,
+    /// Ran the whole time without ever showing more than a single color
+    BlankScreen,
+    /// The emulated machine crashed, usually down to a missing feature in the core
+    Faulted { frame: u32, fault: String },
+    /// The emulator itself panicked, or the machine definition was wired wrong
+    Crashed { message: String },
+    /// Never run, the core is known to be missing something the ROM needs
+    Unsupported { feature: String },
+}
+
+impl Display for CompatibilityStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CompatibilityStatus::Working => write!(f, "Working"),
+            CompatibilityStatus::BlankScreen => write!(f, "Blank screen"),
+            CompatibilityStatus::Faulted { frame, fault } => {
+                write!(f, "Faulted on frame {}: {}", frame, fault)
+            }
+            CompatibilityStatus::Crashed { message } => write!(f, "Crashed: {}", message),
+            CompatibilityStatus::Unsupported { feature } => write!(f, "Needs {}", feature),
+        }
+    }
+}
+
+#[serde_as]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct CompatibilityResult {
+    #[serde_as(as = "DisplayFromStr")]
+    pub rom_id: RomId,
+    pub name: Option<String>,
+    pub status: CompatibilityStatus,
+    /// What every display showed on the last frame run, to notice a ROM looking different after a core changed
+    pub frame_hash: Option<String>,
+}
+
+/// How many ROMs of a report ended up with each status
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CompatibilitySummary {
+    pub working: usize,
+    pub blank_screen: usize,
+    pub faulted: usize,
+    pub crashed: usize,
+    pub unsupported: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct CompatibilityReport {
+    pub system: GameSystem,
+    /// How many frames each ROM was run for
+    pub frames: u32,
+    pub results: Vec<CompatibilityResult>,
+}
+
+impl CompatibilityReport {
+    /// Where the report for `system` is kept by default
+    pub fn path(system: GameSystem) -> PathBuf {
+        COMPATIBILITY_DIRECTORY.join(format!("{}.ron", system))
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Box<dyn Error>> {
+        Ok(ron::de::from_reader(File::open(path)?)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), Box<dyn Error>> {
+        let path = path.as_ref();
+
+        if let Some(parent) = path.parent() {
+            create_dir_all(parent)?;
+        }
+        ron::ser::to_writer_pretty(File::create(path)?, self, PrettyConfig::default())?;
+
+        Ok(())
+    }
+
+    /// Every report in [COMPATIBILITY_DIRECTORY], skipping any that cannot be read
+    pub fn load_all() -> Vec<Self> {
+        let Ok(entries) = read_dir(COMPATIBILITY_DIRECTORY.as_path()) else {
+            return Vec::default();
+        };
+
+        let mut reports: Vec<_> = entries
+            .flatten()
+            .filter_map(|entry| match Self::load(entry.path()) {
+                Ok(report) => Some(report),
+                Err(err) => {
+                    tracing::warn!(
+                        "Could not read compatibility report {}: {}",
+                        entry.path().display(),
+                        err
+                    );
+                    None
+                }
+            })
+            .collect();
+        reports.sort_by_key(|report| report.system);
+
+        reports
+    }
+
+    pub fn summary(&self) -> CompatibilitySummary {
+        let mut summary = CompatibilitySummary::default();
+
+        for result in self.results.iter() {
+            match result.status {
+                CompatibilityStatus::Working => summary.working += 1,
+                CompatibilityStatus::BlankScreen => summary.blank_screen += 1,
+                CompatibilityStatus::Faulted { .. } => summary.faulted += 1,
+                CompatibilityStatus::Crashed { .. } => summary.crashed += 1,
+                CompatibilityStatus::Unsupported { .. } => summary.unsupported += 1,
+            }
+        }
+
+        summary
+    }
+
+    /// Features the core is missing with how many ROMs wait on each, most wanted first
+    pub fn missing_features(&self) -> Vec<(&str, usize)> {
+        let mut missing_features: Vec<(&str, usize)> = Vec::new();
+
+        for result in self.results.iter() {
+            if let CompatibilityStatus::Unsupported { feature } = &result.status {
+                match missing_features
+                    .iter_mut()
+                    .find(|(missing_feature, _)| missing_feature == feature)
+                {
+                    Some((_, count)) => *count += 1,
+                    None => missing_features.push((feature, 1)),
+                }
+            }
+        }
+        missing_features.sort_by(|(a_feature, a_count), (b_feature, b_count)| {
+            b_count.cmp(a_count).then(a_feature.cmp(b_feature))
+        });
+
+        missing_features
+    }
+}
+
+/// Builds a machine around `rom_id` and runs it for `frames` frames, returning how it went and the hash of the last frame
+pub fn test_rom(
+    definition: &dyn MachineDefinition,
+    system: GameSystem,
+    rom_id: RomId,
+    rom_manager: Arc<RomManager>,
+    frames: u32,
+) -> (CompatibilityStatus, Option<String>) {
+    if let Err(unsupported) = definition.check_rom(system, rom_id, &rom_manager) {
+        return (
+            CompatibilityStatus::Unsupported {
+                feature: unsupported.feature,
+            },
+            None,
+        );
+    }
+
+    let outcome = catch_unwind(AssertUnwindSafe(|| {
+        let mut machine = match definition.construct(system, vec![rom_id], rom_manager) {
+            Ok(machine) => machine,
+            Err(err) => {
+                return (
+                    CompatibilityStatus::Crashed {
+                        message: format!("Could not build the machine: {}", err),
+                    },
+                    None,
+                );
+            }
+        };
+
+        for component_info in machine.display_components() {
+            component_info
+                .component
+                .set_display_data(DisplayComponentInitializationData::Software);
+        }
+
+        // Taken once so every frame covers the same emulated time
+        let frame_duration = machine.frame_duration();
+        let mut drew_something = false;
+
+        for frame in 0..frames {
+            machine.run_frame(frame_duration);
+
+            if let Some(fault) = machine.fault() {
+                return (
+                    CompatibilityStatus::Faulted {
+                        frame,
+                        fault: fault.to_string(),
+                    },
+                    frame_hash(
+                        machine
+                            .display_components()
+                            .map(|component_info| component_info.component.get_framebuffer()),
+                    ),
+                );
+            }
+
+            if !drew_something {
+                drew_something = machine.display_components().any(|component_info| {
+                    shows_something(component_info.component.get_framebuffer())
+                });
+            }
+        }
+
+        let status = if drew_something {
+            CompatibilityStatus::Working
+        } else {
+            CompatibilityStatus::BlankScreen
+        };
+
+        (
+            status,
+            frame_hash(
+                machine
+                    .display_components()
+                    .map(|component_info| component_info.component.get_framebuffer()),
+            ),
+        )
+    }));
+
+    outcome.unwrap_or_else(|payload| {
+        let message = payload
+            .downcast_ref::<&str>()
+            .copied()
+            .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+            .unwrap_or("Unknown panic payload")
+            .to_string();
+
+        (CompatibilityStatus::Crashed { message }, None)
+    })
+}
+
+/// If there is more than one color anywhere on screen
+fn shows_something(framebuffer: DisplayComponentFramebuffer) -> bool {
+    match framebuffer {
+        DisplayComponentFramebuffer::Software(framebuffer) => {
+            let framebuffer = framebuffer.lock().unwrap();
+            let mut pixels = framebuffer.iter();
+
+            pixels
+                .next()
+                .is_some_and(|first| pixels.any(|pixel| pixel != first))
+        }
+        // Displays were all handed software initialization data
+        #[cfg(graphics_vulkan)]
+        DisplayComponentFramebuffer::Vulkan(_) => unreachable!(),
+    }
+}
+
+/// [None] for machines without a display
+fn frame_hash(
+    framebuffers: impl IntoIterator<Item = DisplayComponentFramebuffer>,
+) -> Option<String> {
+    let mut hasher = Sha1::new();
+    let mut any_display = false;
+
+    for framebuffer in framebuffers {
+        match framebuffer {
+            DisplayComponentFramebuffer::Software(framebuffer) => {
+                hasher.update(bytemuck::cast_slice(framebuffer.lock().unwrap().as_slice()));
+            }
+            #[cfg(graphics_vulkan)]
+            DisplayComponentFramebuffer::Vulkan(_) => unreachable!(),
+        }
+        any_display = true;
+    }
+
+    any_display.then(|| HEXLOWER.encode(&hasher.finalize()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{definitions::chip8::Chip8Definition, rom::system::OtherSystem};
+    use std::io::Write;
+
+    fn test_program(name: &str, program: &[u8]) -> (CompatibilityStatus, Option<String>) {
+        let rom_manager = Arc::new(RomManager::new(None).unwrap());
+        let path = std::env::temp_dir().join(format!(
+            "multiemu_compatibility_{}_{}",
+            name,
+            std::process::id()
+        ));
+        File::create(&path).unwrap().write_all(program).unwrap();
+        let rom_id = RomId::from_read(&mut File::open(&path).unwrap());
+        rom_manager.rom_paths.insert(rom_id, path.clone());
+
+        let outcome = test_rom(
+            &Chip8Definition,
+            GameSystem::Other(OtherSystem::Chip8),
+            rom_id,
+            rom_manager,
+            10,
+        );
+        std::fs::remove_file(path).unwrap();
+
+        outcome
+    }
+
+    #[test]
+    fn missing_features_are_counted() {
+        let result = |feature: &str| CompatibilityResult {
+            rom_id: RomId::from_read(&mut feature.as_bytes()),
+            name: None,
+            status: CompatibilityStatus::Unsupported {
+                feature: feature.to_string(),
+            },
+            frame_hash: None,
+        };
+        let report = CompatibilityReport {
+            system: GameSystem::Other(OtherSystem::Chip8),
+            frames: 0,
+            results: vec![result("mapper 1"), result("mapper 4"), result("mapper 4")],
+        };
+
+        assert_eq!(
+            report.missing_features(),
+            [("mapper 4", 2), ("mapper 1", 1)]
+        );
+        assert_eq!(report.summary().unsupported, 3);
+    }
+
+    #[test]
+    fn programs_are_sorted_by_how_far_they_get() {
+        // Draws the 0 glyph then spins
+        let (status, frame_hash) = test_program(
+            "working",
+            &[0x00, 0xe0, 0x60, 0x00, 0xf0, 0x29, 0xd0, 0x05, 0x12, 0x08],
+        );
+        assert_eq!(status, CompatibilityStatus::Working);
+        assert!(frame_hash.is_some());
+
+        let (status, _) = test_program("blank", &[0x12, 0x00]);
+        assert_eq!(status, CompatibilityStatus::BlankScreen);
+
+        // Returns without ever having been called
+        let (status, _) = test_program("faulted", &[0x00, 0xee]);
+        assert!(matches!(
+            status,
+            CompatibilityStatus::Faulted { frame: 0, .. }
+        ));
+    }
+}
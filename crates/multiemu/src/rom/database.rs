@@ -0,0 +1,130 @@
+//! Importing external rom databases into the one the [RomManager] uses
+
+use super::{id::RomId, info::RomInfo, manager::RomManager, system::GameSystem};
+use serde::Deserialize;
+use serde_with::{serde_as, DisplayFromStr};
+use std::{collections::HashMap, error::Error, fs::File, io::BufReader, path::Path};
+
+#[allow(dead_code)]
+#[derive(Debug, Deserialize)]
+struct Datafile {
+    header: Header,
+    #[serde(alias = "game")]
+    machine: Vec<Machine>,
+}
+
+#[allow(dead_code)]
+#[serde_as]
+#[derive(Debug, Deserialize)]
+struct Header {
+    #[serde_as(as = "DisplayFromStr")]
+    name: GameSystem,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Deserialize)]
+struct Machine {
+    #[serde(rename = "@name")]
+    name: String,
+    description: String,
+    rom: Rom,
+}
+
+#[allow(dead_code)]
+#[serde_as]
+#[derive(Debug, Deserialize)]
+struct Rom {
+    #[serde(rename = "@name")]
+    name: Option<String>,
+    #[serde_as(as = "DisplayFromStr")]
+    #[serde(rename = "@sha1")]
+    id: RomId,
+    status: Option<String>,
+    #[serde(rename = "@url")]
+    url: Option<String>,
+    #[serde(rename = "@region")]
+    region: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DatabaseFormat {
+    /// A database written by us
+    Native,
+    /// XML datfiles like the ones No-Intro distributes
+    NoIntro,
+}
+
+impl DatabaseFormat {
+    /// Datfiles are recognizable by extension, anything else is assumed to be native
+    pub fn guess(path: impl AsRef<Path>) -> Self {
+        match path
+            .as_ref()
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(|extension| extension.to_ascii_lowercase())
+            .as_deref()
+        {
+            Some("dat" | "xml") => Self::NoIntro,
+            _ => Self::Native,
+        }
+    }
+}
+
+/// How many entries were imported for each system
+pub type ImportedEntries = HashMap<GameSystem, usize>;
+
+pub fn import_database(
+    rom_manager: &RomManager,
+    path: impl AsRef<Path>,
+) -> Result<ImportedEntries, Box<dyn Error + Send + Sync>> {
+    let path = path.as_ref();
+
+    match DatabaseFormat::guess(path) {
+        DatabaseFormat::Native => rom_manager.load_database(path),
+        DatabaseFormat::NoIntro => import_nointro_database(rom_manager, path),
+    }
+}
+
+pub fn import_nointro_database(
+    rom_manager: &RomManager,
+    path: impl AsRef<Path>,
+) -> Result<ImportedEntries, Box<dyn Error + Send + Sync>> {
+    let file = BufReader::new(File::open(path)?);
+
+    // Parse XML based data file
+    let data_file: Datafile = quick_xml::de::from_reader(file)?;
+    let system = data_file.header.name;
+    let entry_count = data_file.machine.len();
+
+    let database_transaction = rom_manager.rom_information.rw_transaction()?;
+    for entry in data_file.machine {
+        database_transaction.upsert(RomInfo {
+            name: Some(entry.name),
+            id: entry.rom.id,
+            system,
+            region: None,
+        })?;
+    }
+    database_transaction.commit()?;
+
+    Ok(HashMap::from([(system, entry_count)]))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn database_format_guessing() {
+        assert_eq!(
+            DatabaseFormat::guess("Nintendo - Nintendo Entertainment System.dat"),
+            DatabaseFormat::NoIntro
+        );
+        assert_eq!(
+            DatabaseFormat::guess("database.XML"),
+            DatabaseFormat::NoIntro
+        );
+        assert_eq!(DatabaseFormat::guess("database.db"), DatabaseFormat::Native);
+        assert_eq!(DatabaseFormat::guess("database"), DatabaseFormat::Native);
+    }
+}
@@ -0,0 +1,536 @@
+//! What ROMs say about themselves in their headers, for showing to the user and for machines to set themselves up by
+//!
+//! Checksums are worked out from the rest of the ROM, so a mismatch usually means a bad dump or a hack
+
+use super::system::{GameSystem, NintendoSystem, SegaSystem};
+use std::{fmt::Display, io::Read};
+
+const INES_MAGIC: &[u8] = b"NES\x1a";
+const GAME_BOY_HEADER: usize = 0x100;
+const GAME_BOY_LOGO: &[u8] = &[0xce, 0xed, 0x66, 0x66, 0xcc, 0x0d, 0x00, 0x0b];
+const MASTER_SYSTEM_MAGIC: &[u8] = b"TMR SEGA";
+/// Where the Master System header can be, the last one being where the BIOS looks first
+const MASTER_SYSTEM_HEADERS: [usize; 3] = [0x1ff0, 0x3ff0, 0x7ff0];
+const GENESIS_HEADER: usize = 0x100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NesMirroring {
+    Horizontal,
+    Vertical,
+    /// The cartridge brings its own nametable memory
+    FourScreen,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InesHeader {
+    /// If the header uses the NES 2.0 extensions
+    pub nes2: bool,
+    pub prg_rom_size: usize,
+    pub chr_rom_size: usize,
+    pub mapper: u16,
+    /// Only NES 2.0 headers have one
+    pub submapper: Option<u8>,
+    pub mirroring: NesMirroring,
+    /// If the cartridge keeps its RAM when turned off
+    pub battery: bool,
+    /// 512 bytes between the header and PRG ROM meant to be loaded to 0x7000
+    pub trainer: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameBoyColorSupport {
+    None,
+    /// Runs on both, with colors on the Color
+    Enhanced,
+    Only,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameBoyHeader {
+    pub title: String,
+    pub color: GameBoyColorSupport,
+    pub super_game_boy: bool,
+    /// Which mapper and extras the cartridge has, see [GameBoyHeader::cartridge_type_name]
+    pub cartridge_type: u8,
+    pub rom_size: usize,
+    pub ram_size: usize,
+    pub japanese: bool,
+    pub version: u8,
+    /// Stored then worked out, the boot ROM refuses to start the cartridge if these differ
+    pub header_checksum: (u8, u8),
+    /// Stored then worked out, nothing checks this on real hardware
+    pub global_checksum: (u16, u16),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MasterSystemHeader {
+    /// Where in the ROM the header was found
+    pub offset: usize,
+    pub product_code: u32,
+    pub version: u8,
+    pub region: u8,
+    /// How much of the ROM the checksum covers
+    pub checksum_size: Option<usize>,
+    /// Stored then worked out, export BIOSes refuse to start the cartridge if these differ
+    pub checksum: (u16, Option<u16>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GenesisHeader {
+    pub console: String,
+    pub copyright: String,
+    pub domestic_title: String,
+    pub overseas_title: String,
+    pub serial: String,
+    pub rom_range: (u32, u32),
+    pub regions: String,
+    /// Stored then worked out, some games check this themselves and lock up if it differs
+    pub checksum: (u16, u16),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RomHeader {
+    Ines(InesHeader),
+    GameBoy(GameBoyHeader),
+    MasterSystem(MasterSystemHeader),
+    Genesis(GenesisHeader),
+}
+
+impl RomHeader {
+    /// Tries every header format there is a parser for, going by the magic each has
+    pub fn parse(rom: &[u8]) -> Option<Self> {
+        InesHeader::parse(rom)
+            .map(Self::Ines)
+            .or_else(|| GameBoyHeader::parse(rom).map(Self::GameBoy))
+            .or_else(|| GenesisHeader::parse(rom).map(Self::Genesis))
+            .or_else(|| MasterSystemHeader::parse(rom).map(Self::MasterSystem))
+    }
+
+    /// Checksums cover the whole ROM, so all of it gets read
+    pub fn read(mut rom: impl Read) -> std::io::Result<Option<Self>> {
+        let mut bytes = Vec::new();
+        rom.read_to_end(&mut bytes)?;
+
+        Ok(Self::parse(&bytes))
+    }
+
+    pub fn system(&self) -> GameSystem {
+        match self {
+            RomHeader::Ines(_) => GameSystem::Nintendo(NintendoSystem::NintendoEntertainmentSystem),
+            RomHeader::GameBoy(header) => match header.color {
+                GameBoyColorSupport::None => GameSystem::Nintendo(NintendoSystem::GameBoy),
+                _ => GameSystem::Nintendo(NintendoSystem::GameBoyColor),
+            },
+            RomHeader::MasterSystem(header) => match header.region {
+                5..=7 => GameSystem::Sega(SegaSystem::GameGear),
+                _ => GameSystem::Sega(SegaSystem::MasterSystem),
+            },
+            RomHeader::Genesis(_) => GameSystem::Sega(SegaSystem::Genesis),
+        }
+    }
+
+    /// Everything in the header as name and value, in the order it is in the header
+    pub fn fields(&self) -> Vec<(&'static str, String)> {
+        match self {
+            RomHeader::Ines(header) => {
+                let mut fields = vec![
+                    (
+                        "Format",
+                        if header.nes2 { "NES 2.0" } else { "iNES" }.to_string(),
+                    ),
+                    ("PRG ROM", size(header.prg_rom_size)),
+                    ("CHR ROM", size(header.chr_rom_size)),
+                    ("Mapper", header.mapper.to_string()),
+                ];
+                if let Some(submapper) = header.submapper {
+                    fields.push(("Submapper", submapper.to_string()));
+                }
+                fields.extend([
+                    ("Mirroring", format!("{:?}", header.mirroring)),
+                    ("Battery", header.battery.to_string()),
+                    ("Trainer", header.trainer.to_string()),
+                ]);
+
+                fields
+            }
+            RomHeader::GameBoy(header) => vec![
+                ("Title", header.title.clone()),
+                ("Color", format!("{:?}", header.color)),
+                ("Super Game Boy", header.super_game_boy.to_string()),
+                (
+                    "Cartridge Type",
+                    format!(
+                        "{:#04x} ({})",
+                        header.cartridge_type,
+                        header.cartridge_type_name()
+                    ),
+                ),
+                ("ROM", size(header.rom_size)),
+                ("RAM", size(header.ram_size)),
+                ("Japanese", header.japanese.to_string()),
+                ("Version", header.version.to_string()),
+                (
+                    "Header Checksum",
+                    Checksum(header.header_checksum).to_string(),
+                ),
+                (
+                    "Global Checksum",
+                    Checksum(header.global_checksum).to_string(),
+                ),
+            ],
+            RomHeader::MasterSystem(header) => vec![
+                ("Header Offset", format!("{:#06x}", header.offset)),
+                ("Product Code", header.product_code.to_string()),
+                ("Version", header.version.to_string()),
+                (
+                    "Region",
+                    match header.region {
+                        3 => "SMS Japan",
+                        4 => "SMS Export",
+                        5 => "GG Japan",
+                        6 => "GG Export",
+                        7 => "GG International",
+                        _ => "Unknown",
+                    }
+                    .to_string(),
+                ),
+                (
+                    "Checksum",
+                    match header.checksum {
+                        (stored, Some(computed)) => Checksum((stored, computed)).to_string(),
+                        (stored, None) => format!("{:#06x} (size code unknown)", stored),
+                    },
+                ),
+            ],
+            RomHeader::Genesis(header) => vec![
+                ("Console", header.console.clone()),
+                ("Copyright", header.copyright.clone()),
+                ("Domestic Title", header.domestic_title.clone()),
+                ("Overseas Title", header.overseas_title.clone()),
+                ("Serial", header.serial.clone()),
+                (
+                    "ROM Range",
+                    format!("{:#08x}..={:#08x}", header.rom_range.0, header.rom_range.1),
+                ),
+                ("Regions", header.regions.clone()),
+                ("Checksum", Checksum(header.checksum).to_string()),
+            ],
+        }
+    }
+}
+
+impl InesHeader {
+    pub fn parse(rom: &[u8]) -> Option<Self> {
+        let header = rom.get(..16)?;
+        if &header[..4] != INES_MAGIC {
+            return None;
+        }
+
+        let nes2 = header[7] & 0b1100 == 0b1000;
+        let mut mapper = ((header[6] >> 4) | (header[7] & 0xf0)) as u16;
+        let mut prg_rom_size = header[4] as usize * 0x4000;
+        let mut chr_rom_size = header[5] as usize * 0x2000;
+        let mut submapper = None;
+
+        if nes2 {
+            mapper |= ((header[8] & 0x0f) as u16) << 8;
+            submapper = Some(header[8] >> 4);
+            // Exponent notation for sizes that do not fit is left out, nothing that big exists
+            prg_rom_size += ((header[9] & 0x0f) as usize) << 8 << 14;
+            chr_rom_size += ((header[9] >> 4) as usize) << 8 << 13;
+        }
+
+        Some(Self {
+            nes2,
+            prg_rom_size,
+            chr_rom_size,
+            mapper,
+            submapper,
+            mirroring: if header[6] & 0b1000 != 0 {
+                NesMirroring::FourScreen
+            } else if header[6] & 0b1 != 0 {
+                NesMirroring::Vertical
+            } else {
+                NesMirroring::Horizontal
+            },
+            battery: header[6] & 0b10 != 0,
+            trainer: header[6] & 0b100 != 0,
+        })
+    }
+}
+
+impl GameBoyHeader {
+    pub fn parse(rom: &[u8]) -> Option<Self> {
+        let header = rom.get(GAME_BOY_HEADER..GAME_BOY_HEADER + 0x50)?;
+        if &header[0x04..0x0c] != GAME_BOY_LOGO {
+            return None;
+        }
+
+        let color = match header[0x43] {
+            0x80 => GameBoyColorSupport::Enhanced,
+            0xc0 => GameBoyColorSupport::Only,
+            _ => GameBoyColorSupport::None,
+        };
+        // The last bytes of the title were taken over for the manufacturer code and color flag later on
+        let title_end = if color == GameBoyColorSupport::None {
+            0x44
+        } else {
+            0x43
+        };
+        let header_checksum = header[0x34..0x4d]
+            .iter()
+            .fold(0u8, |sum, byte| sum.wrapping_sub(*byte).wrapping_sub(1));
+        let global_checksum = rom
+            .iter()
+            .enumerate()
+            .filter(|(address, _)| !(0x14e..0x150).contains(address))
+            .fold(0u16, |sum, (_, byte)| sum.wrapping_add(*byte as u16));
+
+        Some(Self {
+            title: text(&header[0x34..title_end]),
+            color,
+            super_game_boy: header[0x46] == 0x03,
+            cartridge_type: header[0x47],
+            rom_size: 0x8000usize.checked_shl(header[0x48] as u32).unwrap_or(0),
+            ram_size: match header[0x49] {
+                0x02 => 0x2000,
+                0x03 => 0x8000,
+                0x04 => 0x20000,
+                0x05 => 0x10000,
+                _ => 0,
+            },
+            japanese: header[0x4a] == 0x00,
+            version: header[0x4c],
+            header_checksum: (header[0x4d], header_checksum),
+            global_checksum: (
+                u16::from_be_bytes([header[0x4e], header[0x4f]]),
+                global_checksum,
+            ),
+        })
+    }
+
+    pub fn cartridge_type_name(&self) -> &'static str {
+        match self.cartridge_type {
+            0x00 => "ROM only",
+            0x01 => "MBC1",
+            0x02 => "MBC1 + RAM",
+            0x03 => "MBC1 + RAM + battery",
+            0x05 => "MBC2",
+            0x06 => "MBC2 + battery",
+            0x0f => "MBC3 + timer + battery",
+            0x10 => "MBC3 + timer + RAM + battery",
+            0x11 => "MBC3",
+            0x12 => "MBC3 + RAM",
+            0x13 => "MBC3 + RAM + battery",
+            0x19 => "MBC5",
+            0x1a => "MBC5 + RAM",
+            0x1b => "MBC5 + RAM + battery",
+            0x1c => "MBC5 + rumble",
+            0x1d => "MBC5 + rumble + RAM",
+            0x1e => "MBC5 + rumble + RAM + battery",
+            0x20 => "MBC6",
+            0x22 => "MBC7 + sensor + rumble + RAM + battery",
+            0xfc => "Pocket Camera",
+            0xfe => "HuC3",
+            0xff => "HuC1 + RAM + battery",
+            _ => "Unknown",
+        }
+    }
+}
+
+impl MasterSystemHeader {
+    pub fn parse(rom: &[u8]) -> Option<Self> {
+        let offset = MASTER_SYSTEM_HEADERS.into_iter().rev().find(|offset| {
+            rom.get(*offset..*offset + MASTER_SYSTEM_MAGIC.len()) == Some(MASTER_SYSTEM_MAGIC)
+        })?;
+        let header = rom.get(offset..offset + 0x10)?;
+
+        // Binary coded decimal, with the top nibble of the next byte adding hundreds of thousands
+        let bcd = |byte: u8| (byte >> 4) as u32 * 10 + (byte & 0x0f) as u32;
+        let product_code =
+            bcd(header[0x0c]) + bcd(header[0x0d]) * 100 + (header[0x0e] >> 4) as u32 * 10000;
+        let checksum_size = match header[0x0f] & 0x0f {
+            0x0a => Some(0x2000),
+            0x0b => Some(0x4000),
+            0x0c => Some(0x8000),
+            0x0d => Some(0xc000),
+            0x0e => Some(0x10000),
+            0x0f => Some(0x20000),
+            0x00 => Some(0x40000),
+            0x01 => Some(0x80000),
+            0x02 => Some(0x100000),
+            _ => None,
+        };
+        let checksum = checksum_size
+            .filter(|checksum_size| *checksum_size <= rom.len())
+            .map(|checksum_size| {
+                // The header itself is left out, which ends the range early for ROMs that fit under it
+                let end = if checksum_size <= 0x8000 {
+                    checksum_size - 0x10
+                } else {
+                    checksum_size
+                };
+
+                rom[..end]
+                    .iter()
+                    .enumerate()
+                    .filter(|(address, _)| !(0x7ff0..0x8000).contains(address))
+                    .fold(0u16, |sum, (_, byte)| sum.wrapping_add(*byte as u16))
+            });
+
+        Some(Self {
+            offset,
+            product_code,
+            version: header[0x0e] & 0x0f,
+            region: header[0x0f] >> 4,
+            checksum_size,
+            checksum: (u16::from_le_bytes([header[0x0a], header[0x0b]]), checksum),
+        })
+    }
+}
+
+impl GenesisHeader {
+    pub fn parse(rom: &[u8]) -> Option<Self> {
+        let header = rom.get(GENESIS_HEADER..GENESIS_HEADER + 0x100)?;
+        if !header.starts_with(b"SEGA") {
+            return None;
+        }
+
+        let word = |offset: usize| u16::from_be_bytes([header[offset], header[offset + 1]]);
+        let long =
+            |offset: usize| u32::from_be_bytes(header[offset..offset + 4].try_into().unwrap());
+        let checksum = rom
+            .get(0x200..)
+            .unwrap_or_default()
+            .chunks(2)
+            .fold(0u16, |sum, word| {
+                sum.wrapping_add(u16::from_be_bytes([
+                    word[0],
+                    word.get(1).copied().unwrap_or(0),
+                ]))
+            });
+
+        Some(Self {
+            console: text(&header[0x00..0x10]),
+            copyright: text(&header[0x10..0x20]),
+            domestic_title: text(&header[0x20..0x50]),
+            overseas_title: text(&header[0x50..0x80]),
+            serial: text(&header[0x80..0x8e]),
+            rom_range: (long(0xa0), long(0xa4)),
+            regions: text(&header[0xf0..0xf3]),
+            checksum: (word(0x8e), checksum),
+        })
+    }
+}
+
+/// Header text is padded with spaces or zeros, and old enough to not always be ASCII
+fn text(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes)
+        .trim_end_matches(['\0', ' '])
+        .to_string()
+}
+
+fn size(bytes: usize) -> String {
+    if bytes >= 1024 && bytes % 1024 == 0 {
+        format!("{} KiB", bytes / 1024)
+    } else {
+        format!("{} bytes", bytes)
+    }
+}
+
+/// Stored and worked out
+struct Checksum<T>((T, T));
+
+impl<T: PartialEq + std::fmt::LowerHex> Display for Checksum<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (stored, computed) = &self.0;
+        let width = std::mem::size_of::<T>() * 2 + 2;
+
+        if stored == computed {
+            write!(f, "{:#0width$x} (ok)", stored, width = width)
+        } else {
+            write!(
+                f,
+                "{:#0width$x}, should be {:#0width$x}",
+                stored,
+                computed,
+                width = width
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn ines_header() {
+        let mut rom = vec![0; 16 + 0x8000 + 0x2000];
+        rom[..16].copy_from_slice(&[
+            b'N',
+            b'E',
+            b'S',
+            0x1a,
+            2,
+            1,
+            0b0001_0011,
+            0b0000_1000,
+            0x21,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+        ]);
+
+        let header = RomHeader::parse(&rom).unwrap();
+        assert_eq!(
+            header,
+            RomHeader::Ines(InesHeader {
+                nes2: true,
+                prg_rom_size: 0x8000,
+                chr_rom_size: 0x2000,
+                mapper: 0x101,
+                submapper: Some(2),
+                mirroring: NesMirroring::Vertical,
+                battery: true,
+                trainer: false,
+            })
+        );
+        assert_eq!(
+            header.system(),
+            GameSystem::Nintendo(NintendoSystem::NintendoEntertainmentSystem)
+        );
+    }
+
+    #[test]
+    fn game_boy_checksums_are_checked() {
+        let mut rom = vec![0; 0x8000];
+        rom[0x104..0x10c].copy_from_slice(GAME_BOY_LOGO);
+        rom[0x134..0x139].copy_from_slice(b"TETRA");
+        rom[0x147] = 0x01;
+
+        let mut header = GameBoyHeader::parse(&rom).unwrap();
+        assert_eq!(header.title, "TETRA");
+        assert_eq!(header.cartridge_type_name(), "MBC1");
+        assert_eq!(header.rom_size, 0x8000);
+        assert_ne!(header.header_checksum.0, header.header_checksum.1);
+
+        rom[0x14d] = header.header_checksum.1;
+        header = GameBoyHeader::parse(&rom).unwrap();
+        assert_eq!(header.header_checksum.0, header.header_checksum.1);
+        // The header checksum byte counts towards the global checksum, but the global checksum itself does not
+        let [high, low] = header.global_checksum.1.to_be_bytes();
+        rom[0x14e] = high;
+        rom[0x14f] = low;
+        header = GameBoyHeader::parse(&rom).unwrap();
+        assert_eq!(header.global_checksum.0, header.global_checksum.1);
+        assert!(RomHeader::GameBoy(header).fields().contains(&(
+            "Global Checksum",
+            format!("{:#06x} (ok)", u16::from_be_bytes([high, low]))
+        )));
+    }
+}
@@ -1,4 +1,8 @@
-use super::{id::RomId, info::RomInfo};
+#[cfg(platform_desktop)]
+use super::storage;
+use super::{
+    id::RomId, info::RomInfo, notes::RomNotes, statistics::PlaySession, system::GameSystem,
+};
 use dashmap::DashMap;
 use std::{
     collections::HashMap,
@@ -12,6 +16,8 @@ use std::{
 static DATABASE_MODELS: LazyLock<native_db::Models> = LazyLock::new(|| {
     let mut models = native_db::Models::new();
     models.define::<RomInfo>().unwrap();
+    models.define::<RomNotes>().unwrap();
+    models.define::<PlaySession>().unwrap();
     models
 });
 
@@ -44,10 +50,11 @@ impl RomManager {
         })
     }
 
+    /// Merges another native database into ours, returning how many entries each system got
     pub fn load_database(
         &self,
         path: impl AsRef<Path>,
-    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+    ) -> Result<HashMap<GameSystem, usize>, Box<dyn Error + Send + Sync>> {
         let path = path.as_ref();
 
         if !path.is_file() {
@@ -56,6 +63,7 @@ impl RomManager {
 
         let database = native_db::Builder::new().open(&DATABASE_MODELS, path)?;
         let external_database_transaction = database.r_transaction()?;
+        let mut imported_entries = HashMap::new();
 
         for item in (external_database_transaction
             .scan()
@@ -63,12 +71,14 @@ impl RomManager {
             .all()?)
         .flatten()
         {
+            *imported_entries.entry(item.system).or_default() += 1;
+
             let internal_database_transaction = self.rom_information.rw_transaction()?;
             internal_database_transaction.upsert(item)?;
             internal_database_transaction.commit()?;
         }
 
-        Ok(())
+        Ok(imported_entries)
     }
 
     pub fn load_roms(&mut self, path: impl AsRef<Path>) -> Result<(), Box<dyn Error>> {
@@ -83,9 +93,18 @@ impl RomManager {
                 continue;
             }
 
+            #[cfg(platform_desktop)]
+            if path
+                .file_name()
+                .is_some_and(|name| name == storage::COMPRESSED_ROM_INDEX)
+            {
+                continue;
+            }
+
+            // Compressed entries carry an extension, so go by the stem
             let path_name: RomId = path
                 .canonicalize()?
-                .file_name()
+                .file_stem()
                 .unwrap()
                 .to_str()
                 .unwrap()
@@ -114,10 +133,26 @@ impl RomManager {
                 continue;
             }
 
-            let expected_hash = path.file_name().unwrap().to_str().unwrap().parse()?;
+            #[cfg(platform_desktop)]
+            if path
+                .file_name()
+                .is_some_and(|name| name == storage::COMPRESSED_ROM_INDEX)
+            {
+                continue;
+            }
 
-            let mut file = File::open(&path)?;
-            let hash = RomId::from_read(&mut file);
+            let expected_hash = path.file_stem().unwrap().to_str().unwrap().parse()?;
+
+            #[cfg(platform_desktop)]
+            let hash = if storage::is_compressed(&path) {
+                RomId::from_read(
+                    &mut storage::read_compressed(&path).map_err(|e| e as Box<dyn Error>)?,
+                )
+            } else {
+                RomId::from_read(&mut File::open(&path)?)
+            };
+            #[cfg(not(platform_desktop))]
+            let hash = RomId::from_read(&mut File::open(&path)?);
 
             if hash != expected_hash {
                 incorrect_roms.insert(hash, path);
@@ -130,8 +165,21 @@ impl RomManager {
     }
 
     /// Components should use this function to load roms for themselves
+    ///
+    /// ROMs kept in the compressed store are transparently unpacked into a cache first
     pub fn open(&self, id: RomId, requirement: RomRequirement) -> Option<File> {
         if let Some(path) = self.rom_paths.get(&id) {
+            #[cfg(platform_desktop)]
+            if storage::is_compressed(path.value()) {
+                return match storage::decompress_to_cache(id, path.value()) {
+                    Ok(cache_path) => File::open(cache_path).ok(),
+                    Err(err) => {
+                        tracing::error!("Failed to unpack compressed ROM {}: {}", id, err);
+                        None
+                    }
+                };
+            }
+
             return File::open(path.value()).ok();
         }
 
@@ -1,7 +1,17 @@
+pub mod compatibility;
+// Datfiles are parsed with quick-xml, which is desktop only
+#[cfg(platform_desktop)]
+pub mod database;
 pub mod graphics;
+pub mod header;
 pub mod id;
 pub mod info;
 pub mod manager;
+pub mod notes;
 pub mod region;
 pub mod specification;
+pub mod statistics;
+// Compressed storage leans on ruzstd, which is only pulled in on desktop
+#[cfg(platform_desktop)]
+pub mod storage;
 pub mod system;
@@ -0,0 +1,145 @@
+//! What users wrote down about how well each game runs, kept in the rom database next to [RomInfo]
+
+use super::{id::RomId, info::RomInfo, manager::RomManager, system::GameSystem};
+use native_db::{native_db, ToKey};
+use native_model::{native_model, Model};
+use ron::ser::PrettyConfig;
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DisplayFromStr};
+use std::{error::Error, fs::File, path::Path};
+use strum::{Display, EnumIter};
+
+/// How far a game gets, from the user playing it instead of [super::compatibility::CompatibilityStatus] guessing
+#[derive(
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    EnumIter,
+    Display,
+)]
+pub enum CompatibilityRating {
+    /// Plays start to finish without any issues
+    Perfect,
+    /// Plays start to finish with issues that do not get in the way
+    Playable,
+    /// Gets into the game but cannot be finished
+    Ingame,
+    /// Does not get into the game
+    Broken,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[native_model(id = 2, version = 1)]
+#[native_db]
+pub struct RomNotes {
+    #[primary_key]
+    pub id: RomId,
+    pub rating: Option<CompatibilityRating>,
+    pub notes: String,
+}
+
+impl RomNotes {
+    pub fn new(id: RomId) -> Self {
+        Self {
+            id,
+            rating: None,
+            notes: String::default(),
+        }
+    }
+
+    /// Nothing was noted, so there is no need to keep it around
+    pub fn is_empty(&self) -> bool {
+        self.rating.is_none() && self.notes.is_empty()
+    }
+}
+
+/// [RomNotes] with enough about the ROM to make sense of it away from the database it came out of
+#[serde_as]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ExportedRomNotes {
+    #[serde_as(as = "DisplayFromStr")]
+    pub rom_id: RomId,
+    pub name: Option<String>,
+    pub system: Option<GameSystem>,
+    pub rating: Option<CompatibilityRating>,
+    pub notes: String,
+}
+
+impl RomManager {
+    pub fn rom_notes(&self, id: RomId) -> Result<Option<RomNotes>, Box<dyn Error>> {
+        Ok(self.rom_information.r_transaction()?.get().primary(id)?)
+    }
+
+    /// Replaces whatever was noted about the ROM before, forgetting it entirely if `rom_notes` is empty
+    pub fn set_rom_notes(&self, rom_notes: RomNotes) -> Result<(), Box<dyn Error>> {
+        let transaction = self.rom_information.rw_transaction()?;
+
+        if rom_notes.is_empty() {
+            if let Some(existing) = transaction.get().primary::<RomNotes>(rom_notes.id)? {
+                transaction.remove(existing)?;
+            }
+        } else {
+            transaction.upsert(rom_notes)?;
+        }
+
+        transaction.commit()?;
+
+        Ok(())
+    }
+
+    /// Writes every ROM that has notes to `path`, ordered by system and name
+    pub fn export_rom_notes(&self, path: impl AsRef<Path>) -> Result<usize, Box<dyn Error>> {
+        let transaction = self.rom_information.r_transaction()?;
+        let mut exported = Vec::new();
+
+        for rom_notes in transaction.scan().primary::<RomNotes>()?.all()?.flatten() {
+            let rom_info = transaction.get().primary::<RomInfo>(rom_notes.id)?;
+
+            exported.push(ExportedRomNotes {
+                rom_id: rom_notes.id,
+                name: rom_info.as_ref().and_then(|rom_info| rom_info.name.clone()),
+                system: rom_info.map(|rom_info| rom_info.system),
+                rating: rom_notes.rating,
+                notes: rom_notes.notes,
+            });
+        }
+        exported.sort_by(|a, b| (a.system, &a.name).cmp(&(b.system, &b.name)));
+
+        ron::ser::to_writer_pretty(File::create(path)?, &exported, PrettyConfig::default())?;
+
+        Ok(exported.len())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn empty_notes_are_forgotten() {
+        let rom_manager = RomManager::new(None).unwrap();
+        let id = RomId::new([0x11; 20]);
+
+        rom_manager
+            .set_rom_notes(RomNotes {
+                id,
+                rating: Some(CompatibilityRating::Ingame),
+                notes: "Softlocks after the first boss".to_string(),
+            })
+            .unwrap();
+        assert_eq!(
+            rom_manager.rom_notes(id).unwrap().unwrap().rating,
+            Some(CompatibilityRating::Ingame)
+        );
+
+        rom_manager.set_rom_notes(RomNotes::new(id)).unwrap();
+        assert_eq!(rom_manager.rom_notes(id).unwrap(), None);
+    }
+}
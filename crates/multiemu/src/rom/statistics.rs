@@ -0,0 +1,255 @@
+//! Play sessions kept in the rom database next to [RomInfo], so users can see what they have been playing
+//!
+//! Only recorded if [crate::config::GlobalConfig::usage_statistics] is on, and nothing here ever leaves the computer
+//! unless the user exports it themselves
+
+use super::{id::RomId, info::RomInfo, manager::RomManager, system::GameSystem};
+use native_db::{native_db, ToKey};
+use native_model::{native_model, Model};
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DisplayFromStr, DurationSecondsWithFrac};
+use std::{
+    collections::{BTreeMap, HashMap},
+    error::Error,
+    fs::File,
+    path::Path,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// One stretch of playing a game, from opening it until something else replaced it or the emulator closed
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[native_model(id = 3, version = 1)]
+#[native_db]
+pub struct PlaySession {
+    /// Milliseconds since the unix epoch, only one game runs at a time so this is unique enough
+    #[primary_key]
+    pub started: u64,
+    pub rom_id: RomId,
+    pub system: GameSystem,
+    pub duration: Duration,
+    /// How many times each feature was used
+    pub features: BTreeMap<String, u64>,
+}
+
+impl PlaySession {
+    pub fn start(rom_id: RomId, system: GameSystem) -> Self {
+        Self {
+            started: now_millis(),
+            rom_id,
+            system,
+            duration: Duration::ZERO,
+            features: BTreeMap::default(),
+        }
+    }
+
+    pub fn record_feature(&mut self, feature: impl Into<String>) {
+        *self.features.entry(feature.into()).or_default() += 1;
+    }
+
+    /// Stops the clock, after which it is ready for [RomManager::record_play_session]
+    pub fn finish(mut self) -> Self {
+        self.duration = Duration::from_millis(now_millis().saturating_sub(self.started));
+        self
+    }
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+#[serde_as]
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct GameStatistics {
+    #[serde_as(as = "DisplayFromStr")]
+    pub rom_id: RomId,
+    pub name: Option<String>,
+    #[serde_as(as = "DisplayFromStr")]
+    pub system: GameSystem,
+    pub sessions: usize,
+    #[serde_as(as = "DurationSecondsWithFrac<f64>")]
+    pub play_time: Duration,
+}
+
+#[serde_as]
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct SystemStatistics {
+    #[serde_as(as = "DisplayFromStr")]
+    pub system: GameSystem,
+    pub games: usize,
+    #[serde_as(as = "DurationSecondsWithFrac<f64>")]
+    pub play_time: Duration,
+}
+
+/// Everything worked out from the recorded [PlaySession]s
+#[serde_as]
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, Default)]
+pub struct UsageStatistics {
+    pub sessions: usize,
+    #[serde_as(as = "DurationSecondsWithFrac<f64>")]
+    pub play_time: Duration,
+    /// Most played first
+    pub games: Vec<GameStatistics>,
+    /// Most played first
+    pub systems: Vec<SystemStatistics>,
+    /// Most used first
+    pub features: Vec<(String, u64)>,
+}
+
+impl UsageStatistics {
+    pub fn from_sessions(
+        sessions: impl IntoIterator<Item = PlaySession>,
+        name: impl Fn(RomId) -> Option<String>,
+    ) -> Self {
+        let mut statistics = Self::default();
+        let mut games: HashMap<RomId, GameStatistics> = HashMap::default();
+        let mut features: HashMap<String, u64> = HashMap::default();
+
+        for session in sessions {
+            statistics.sessions += 1;
+            statistics.play_time += session.duration;
+
+            let game = games
+                .entry(session.rom_id)
+                .or_insert_with(|| GameStatistics {
+                    rom_id: session.rom_id,
+                    name: name(session.rom_id),
+                    system: session.system,
+                    sessions: 0,
+                    play_time: Duration::ZERO,
+                });
+            game.sessions += 1;
+            game.play_time += session.duration;
+
+            for (feature, uses) in session.features {
+                *features.entry(feature).or_default() += uses;
+            }
+        }
+
+        let mut systems: HashMap<GameSystem, SystemStatistics> = HashMap::default();
+
+        for game in games.values() {
+            let system = systems
+                .entry(game.system)
+                .or_insert_with(|| SystemStatistics {
+                    system: game.system,
+                    games: 0,
+                    play_time: Duration::ZERO,
+                });
+            system.games += 1;
+            system.play_time += game.play_time;
+        }
+
+        statistics.games = games.into_values().collect();
+        statistics.games.sort_by(|a, b| {
+            b.play_time
+                .cmp(&a.play_time)
+                .then_with(|| a.name.cmp(&b.name))
+        });
+        statistics.systems = systems.into_values().collect();
+        statistics
+            .systems
+            .sort_by(|a, b| b.play_time.cmp(&a.play_time).then(a.system.cmp(&b.system)));
+        statistics.features = features.into_iter().collect();
+        statistics
+            .features
+            .sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+        statistics
+    }
+}
+
+impl RomManager {
+    pub fn record_play_session(&self, play_session: PlaySession) -> Result<(), Box<dyn Error>> {
+        let transaction = self.rom_information.rw_transaction()?;
+        transaction.upsert(play_session)?;
+        transaction.commit()?;
+
+        Ok(())
+    }
+
+    pub fn usage_statistics(&self) -> Result<UsageStatistics, Box<dyn Error>> {
+        let transaction = self.rom_information.r_transaction()?;
+        let sessions: Vec<PlaySession> = transaction
+            .scan()
+            .primary::<PlaySession>()?
+            .all()?
+            .flatten()
+            .collect();
+
+        Ok(UsageStatistics::from_sessions(sessions, |rom_id| {
+            transaction
+                .get()
+                .primary::<RomInfo>(rom_id)
+                .ok()
+                .flatten()
+                .and_then(|rom_info| rom_info.name)
+        }))
+    }
+
+    /// Writes [RomManager::usage_statistics] to `path` as json
+    pub fn export_usage_statistics(&self, path: impl AsRef<Path>) -> Result<(), Box<dyn Error>> {
+        serde_json::to_writer_pretty(File::create(path)?, &self.usage_statistics()?)?;
+
+        Ok(())
+    }
+
+    /// Forgets every recorded session
+    pub fn clear_play_sessions(&self) -> Result<(), Box<dyn Error>> {
+        let transaction = self.rom_information.rw_transaction()?;
+        let sessions: Vec<PlaySession> = transaction
+            .scan()
+            .primary::<PlaySession>()?
+            .all()?
+            .flatten()
+            .collect();
+
+        for session in sessions {
+            transaction.remove(session)?;
+        }
+
+        transaction.commit()?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rom::system::NintendoSystem;
+
+    #[test]
+    fn sessions_add_up() {
+        let rom_manager = RomManager::new(None).unwrap();
+        let first = RomId::new([0x11; 20]);
+        let second = RomId::new([0x22; 20]);
+        let system = GameSystem::Nintendo(NintendoSystem::NintendoEntertainmentSystem);
+
+        for (started, rom_id, minutes, feature) in [
+            (1, first, 30, "Fast Forward"),
+            (2, first, 45, "Fast Forward"),
+            (3, second, 10, "TAS Editor"),
+        ] {
+            let mut session = PlaySession::start(rom_id, system);
+            session.started = started;
+            session.duration = Duration::from_secs(minutes * 60);
+            session.record_feature(feature);
+
+            rom_manager.record_play_session(session).unwrap();
+        }
+
+        let statistics = rom_manager.usage_statistics().unwrap();
+        assert_eq!(statistics.sessions, 3);
+        assert_eq!(statistics.play_time, Duration::from_secs(85 * 60));
+        assert_eq!(statistics.games[0].rom_id, first);
+        assert_eq!(statistics.games[0].sessions, 2);
+        assert_eq!(statistics.systems[0].games, 2);
+        assert_eq!(statistics.features[0], ("Fast Forward".to_string(), 2));
+
+        rom_manager.clear_play_sessions().unwrap();
+        assert_eq!(rom_manager.usage_statistics().unwrap().sessions, 0);
+    }
+}
@@ -0,0 +1,211 @@
+use super::id::RomId;
+use ruzstd::{
+    decoding::StreamingDecoder,
+    encoding::{compress_to_vec, CompressionLevel},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io::{BufReader, Read},
+    path::{Path, PathBuf},
+    sync::{LazyLock, Mutex},
+};
+use strum::{Display, EnumIter};
+
+/// Extension given to ROMs stored in the compressed format
+pub const COMPRESSED_ROM_EXTENSION: &str = "zst";
+
+/// Lives next to the compressed ROMs and says what each of them unpacks to
+pub const COMPRESSED_ROM_INDEX: &str = "compressed_index.ron";
+
+/// Where compressed ROMs are unpacked to so they can be opened (and mmaped) like normal files
+pub static ROM_CACHE_DIRECTORY: LazyLock<PathBuf> =
+    LazyLock::new(|| std::env::temp_dir().join("multiemu").join("rom_cache"));
+
+/// Imports run in parallel, so the index has to be read and written back in one go
+static INDEX_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, EnumIter, Display, PartialEq, Eq, Default)]
+pub enum RomStoragePolicy {
+    /// ROMs are copied into the store as is
+    #[default]
+    Plain,
+    /// ROMs are stored as one zstd frame per file, listed in the [COMPRESSED_ROM_INDEX]
+    Compressed,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompressedRomEntry {
+    pub size: u64,
+    pub compressed_size: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct CompressedRomIndex(pub BTreeMap<RomId, CompressedRomEntry>);
+
+impl CompressedRomIndex {
+    pub fn load(
+        roms_directory: impl AsRef<Path>,
+    ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let path = roms_directory.as_ref().join(COMPRESSED_ROM_INDEX);
+
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        Ok(ron::de::from_reader(File::open(path)?)?)
+    }
+
+    pub fn save(
+        &self,
+        roms_directory: impl AsRef<Path>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let path = roms_directory.as_ref().join(COMPRESSED_ROM_INDEX);
+        let temporary_path = path.with_extension("partial");
+
+        ron::ser::to_writer_pretty(
+            File::create(&temporary_path)?,
+            self,
+            ron::ser::PrettyConfig::default(),
+        )?;
+        fs::rename(temporary_path, path)?;
+
+        Ok(())
+    }
+}
+
+pub fn compressed_path(rom_id: RomId, roms_directory: impl AsRef<Path>) -> PathBuf {
+    roms_directory
+        .as_ref()
+        .join(rom_id.to_string())
+        .with_extension(COMPRESSED_ROM_EXTENSION)
+}
+
+/// Compresses the ROM into the store and records it in the index
+pub fn store_compressed(
+    rom_id: RomId,
+    rom: &mut impl Read,
+    roms_directory: impl AsRef<Path>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let roms_directory = roms_directory.as_ref();
+    let destination = compressed_path(rom_id, roms_directory);
+    let temporary_destination = destination.with_extension("partial");
+
+    let mut contents = Vec::new();
+    rom.read_to_end(&mut contents)?;
+    let compressed = compress_to_vec(contents.as_slice(), CompressionLevel::Fastest);
+
+    fs::write(&temporary_destination, &compressed)?;
+    fs::rename(temporary_destination, destination)?;
+
+    let _guard = INDEX_LOCK.lock().unwrap();
+    let mut index = CompressedRomIndex::load(roms_directory)?;
+    index.0.insert(
+        rom_id,
+        CompressedRomEntry {
+            size: contents.len() as u64,
+            compressed_size: compressed.len() as u64,
+        },
+    );
+    index.save(roms_directory)?;
+
+    Ok(())
+}
+
+/// Drops the compressed copy of a ROM along with its index entry, if there was one
+pub fn remove_compressed(
+    rom_id: RomId,
+    roms_directory: impl AsRef<Path>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let roms_directory = roms_directory.as_ref();
+    let _ = fs::remove_file(compressed_path(rom_id, roms_directory));
+
+    let _guard = INDEX_LOCK.lock().unwrap();
+    let mut index = CompressedRomIndex::load(roms_directory)?;
+    if index.0.remove(&rom_id).is_some() {
+        index.save(roms_directory)?;
+    }
+
+    Ok(())
+}
+
+pub fn is_compressed(path: impl AsRef<Path>) -> bool {
+    path.as_ref()
+        .extension()
+        .is_some_and(|extension| extension == COMPRESSED_ROM_EXTENSION)
+}
+
+/// Streams the ROM out of a compressed store entry
+pub fn read_compressed(
+    path: impl AsRef<Path>,
+) -> Result<impl Read, Box<dyn std::error::Error + Send + Sync>> {
+    Ok(StreamingDecoder::new(BufReader::new(File::open(path)?))?)
+}
+
+/// Unpacks a compressed ROM into the cache, reusing a previous extraction if it is still intact
+pub fn decompress_to_cache(
+    rom_id: RomId,
+    path: impl AsRef<Path>,
+) -> Result<PathBuf, Box<dyn std::error::Error + Send + Sync>> {
+    fs::create_dir_all(ROM_CACHE_DIRECTORY.as_path())?;
+    let cache_path = ROM_CACHE_DIRECTORY.join(rom_id.to_string());
+
+    if let Ok(mut cached_file) = File::open(&cache_path) {
+        if RomId::from_read(&mut cached_file) == rom_id {
+            return Ok(cache_path);
+        }
+
+        tracing::warn!(
+            "Cached copy of ROM {} is corrupted, unpacking again",
+            rom_id
+        );
+    }
+
+    let temporary_cache_path = cache_path.with_extension("partial");
+    let mut rom = read_compressed(path)?;
+    std::io::copy(&mut rom, &mut File::create(&temporary_cache_path)?)?;
+    fs::rename(temporary_cache_path, &cache_path)?;
+
+    Ok(cache_path)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn compressed_roundtrip() {
+        let rom: Vec<u8> = (0..0x4000).map(|index| (index % 7) as u8).collect();
+        let rom_id = RomId::from_read(&mut rom.as_slice());
+
+        let directory =
+            std::env::temp_dir().join(format!("multiemu_storage_test_{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let store_path = compressed_path(rom_id, &directory);
+
+        store_compressed(rom_id, &mut rom.as_slice(), &directory).unwrap();
+        assert!(is_compressed(&store_path));
+        assert!(fs::metadata(&store_path).unwrap().len() < rom.len() as u64);
+        assert_eq!(
+            CompressedRomIndex::load(&directory).unwrap().0[&rom_id].size,
+            rom.len() as u64
+        );
+
+        let mut contents = Vec::new();
+        read_compressed(&store_path)
+            .unwrap()
+            .read_to_end(&mut contents)
+            .unwrap();
+        assert_eq!(contents, rom);
+
+        let cache_path = decompress_to_cache(rom_id, &store_path).unwrap();
+        assert_eq!(fs::read(cache_path).unwrap(), rom);
+
+        remove_compressed(rom_id, &directory).unwrap();
+        assert!(!store_path.exists());
+        assert!(CompressedRomIndex::load(&directory).unwrap().0.is_empty());
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -0,0 +1,202 @@
+//! Lenient deserializing for [GameSystem]
+//!
+//! A config written by a newer version can name a system, or a whole category of them, that this one does not
+//! know about. Those come out as [GameSystem::Unknown] instead of failing the entire file
+//!
+//! Formats like RON cannot buffer an enum without losing its variant names, so this walks the enum by hand instead of
+//! trying the derived implementation and falling back
+
+use super::{
+    AtariSystem, ComputerSystem, GameSystem, NintendoSystem, OtherSystem, SegaSystem, SonySystem,
+};
+use serde::{
+    de::{
+        value::{StrDeserializer, U32Deserializer},
+        DeserializeOwned, DeserializeSeed, EnumAccess, Error, IgnoredAny, VariantAccess, Visitor,
+    },
+    Deserialize, Deserializer,
+};
+use std::marker::PhantomData;
+
+/// Must line up with the variants of [GameSystem], in order
+#[derive(Deserialize)]
+enum Category {
+    Nintendo,
+    Sega,
+    Sony,
+    Atari,
+    Other,
+    Unknown,
+    Arcade,
+    #[serde(alias = "HomeComputer")]
+    Computer,
+}
+
+const CATEGORIES: &[&str] = &[
+    "Nintendo", "Sega", "Sony", "Atari", "Other", "Unknown", "Arcade", "Computer",
+];
+
+/// A variant as it was written, by name in human readable formats and by index otherwise
+enum Identifier {
+    Name(String),
+    Index(u32),
+}
+
+impl Identifier {
+    /// Turns this into a variant of a fieldless enum, going through its derived implementation so aliases still apply
+    fn resolve<T: DeserializeOwned>(&self) -> Option<T> {
+        match self {
+            Identifier::Name(name) => {
+                T::deserialize(StrDeserializer::<serde::de::value::Error>::new(name)).ok()
+            }
+            Identifier::Index(index) => {
+                T::deserialize(U32Deserializer::<serde::de::value::Error>::new(*index)).ok()
+            }
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Identifier {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct IdentifierVisitor;
+
+        impl Visitor<'_> for IdentifierVisitor {
+            type Value = Identifier;
+
+            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                formatter.write_str("a variant name or index")
+            }
+
+            fn visit_u64<E: Error>(self, v: u64) -> Result<Self::Value, E> {
+                // Too big to be any variant, so treat it like any other one that is not known
+                Ok(Identifier::Index(v.try_into().unwrap_or(u32::MAX)))
+            }
+
+            fn visit_str<E: Error>(self, v: &str) -> Result<Self::Value, E> {
+                Ok(Identifier::Name(v.to_string()))
+            }
+
+            fn visit_bytes<E: Error>(self, v: &[u8]) -> Result<Self::Value, E> {
+                Ok(Identifier::Name(String::from_utf8_lossy(v).into_owned()))
+            }
+        }
+
+        deserializer.deserialize_identifier(IdentifierVisitor)
+    }
+}
+
+/// A fieldless enum nested in a [GameSystem], [None] if the variant is not one this version knows
+struct Lenient<T>(PhantomData<T>);
+
+impl<'de, T: DeserializeOwned> DeserializeSeed<'de> for Lenient<T> {
+    type Value = Option<T>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        // Any name works here since the variant list only shows up in errors
+        deserializer.deserialize_enum("System", &[], self)
+    }
+}
+
+impl<'de, T: DeserializeOwned> Visitor<'de> for Lenient<T> {
+    type Value = Option<T>;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("a system")
+    }
+
+    fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<Self::Value, A::Error> {
+        let (identifier, variant) = data.variant::<Identifier>()?;
+        variant.unit_variant()?;
+
+        Ok(identifier.resolve())
+    }
+}
+
+struct GameSystemVisitor;
+
+impl<'de> Visitor<'de> for GameSystemVisitor {
+    type Value = GameSystem;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("a game system")
+    }
+
+    fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<Self::Value, A::Error> {
+        let (identifier, variant) = data.variant::<Identifier>()?;
+
+        fn nested<'de, T: DeserializeOwned, V: VariantAccess<'de>>(
+            variant: V,
+            wrap: fn(T) -> GameSystem,
+        ) -> Result<GameSystem, V::Error> {
+            Ok(variant
+                .newtype_variant_seed(Lenient::<T>(PhantomData))?
+                .map(wrap)
+                .unwrap_or_default())
+        }
+
+        match identifier.resolve::<Category>() {
+            Some(Category::Nintendo) => nested::<NintendoSystem, _>(variant, GameSystem::Nintendo),
+            Some(Category::Sega) => nested::<SegaSystem, _>(variant, GameSystem::Sega),
+            Some(Category::Sony) => nested::<SonySystem, _>(variant, GameSystem::Sony),
+            Some(Category::Atari) => nested::<AtariSystem, _>(variant, GameSystem::Atari),
+            Some(Category::Other) => nested::<OtherSystem, _>(variant, GameSystem::Other),
+            Some(Category::Computer) => nested::<ComputerSystem, _>(variant, GameSystem::Computer),
+            Some(Category::Arcade) => variant.newtype_variant().map(GameSystem::Arcade),
+            Some(Category::Unknown) => variant.unit_variant().map(|()| GameSystem::Unknown),
+            // Every category so far wraps a more specific system
+            None => variant
+                .newtype_variant::<IgnoredAny>()
+                .map(|_| GameSystem::Unknown),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for GameSystem {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_enum("GameSystem", CATEGORIES, GameSystemVisitor)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rom::system::ArcadeMachine;
+
+    #[test]
+    fn round_trip() {
+        let systems = GameSystem::iter()
+            .chain([
+                GameSystem::Unknown,
+                GameSystem::Arcade("pacman".parse::<ArcadeMachine>().unwrap()),
+            ])
+            .collect::<Vec<_>>();
+
+        let ron = ron::to_string(&systems).unwrap();
+        assert_eq!(ron::from_str::<Vec<GameSystem>>(&ron).unwrap(), systems);
+
+        let msgpack = rmp_serde::to_vec(&systems).unwrap();
+        assert_eq!(
+            rmp_serde::from_slice::<Vec<GameSystem>>(&msgpack).unwrap(),
+            systems
+        );
+    }
+
+    #[test]
+    fn unrecognized_systems_are_unknown() {
+        let systems: Vec<GameSystem> = ron::from_str(
+            "[Nintendo(Switch), Bandai(WonderSwan), Computer(C64), Nintendo(GameBoy), Unknown]",
+        )
+        .unwrap();
+
+        assert_eq!(
+            systems,
+            [
+                GameSystem::Unknown,
+                GameSystem::Unknown,
+                GameSystem::Computer(ComputerSystem::Commodore64),
+                GameSystem::Nintendo(NintendoSystem::GameBoy),
+                GameSystem::Unknown,
+            ]
+        );
+    }
+}
@@ -1,4 +1,4 @@
-use super::{AtariSystem, GameSystem, NintendoSystem, OtherSystem, SegaSystem};
+use super::{AtariSystem, ComputerSystem, GameSystem, NintendoSystem, OtherSystem, SegaSystem};
 use std::{
     collections::HashMap,
     fs::File,
@@ -65,6 +65,22 @@ static MAGIC_TABLE: LazyLock<HashMap<GameSystem, Vec<MagicTableEntry>>> = LazyLo
             },
         ]);
 
+    table
+        .entry(GameSystem::Computer(ComputerSystem::Commodore64))
+        .or_default()
+        .extend([MagicTableEntry {
+            bytes: b"C64 CARTRIDGE   ",
+            offset: 0x00,
+        }]);
+
+    table
+        .entry(GameSystem::Computer(ComputerSystem::ZxSpectrum))
+        .or_default()
+        .extend([MagicTableEntry {
+            bytes: b"ZXTape!\x1a",
+            offset: 0x00,
+        }]);
+
     table
 });
 
@@ -127,6 +143,11 @@ fn guess_by_extension(rom: &Path) -> Option<GameSystem> {
             "a26" => Some(GameSystem::Atari(AtariSystem::Atari2600)),
             "a52" => Some(GameSystem::Atari(AtariSystem::Atari5200)),
             "a78" => Some(GameSystem::Atari(AtariSystem::Atari7800)),
+            "d64" | "t64" | "prg" | "crt" => {
+                Some(GameSystem::Computer(ComputerSystem::Commodore64))
+            }
+            "tzx" | "z80" | "sna" => Some(GameSystem::Computer(ComputerSystem::ZxSpectrum)),
+            "mx1" | "mx2" => Some(GameSystem::Computer(ComputerSystem::Msx)),
             _ => None,
         } {
             tracing::info!(
@@ -1,12 +1,16 @@
+use arrayvec::ArrayString;
 use serde::{Deserialize, Serialize};
 use std::{fmt::Display, io::Read, path::Path, str::FromStr};
 use strum::{EnumIter, IntoEnumIterator};
 
+mod deserialize;
 mod guess;
 
-#[derive(
-    Serialize, Deserialize, Default, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash,
-)]
+/// Deserializing is hand written so systems from a newer version come out as [GameSystem::Unknown] instead of failing
+/// the whole config, see [deserialize]
+///
+/// New variants go on the end, the rom database stores these by index
+#[derive(Serialize, Default, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum GameSystem {
     Nintendo(NintendoSystem),
     Sega(SegaSystem),
@@ -15,6 +19,8 @@ pub enum GameSystem {
     Other(OtherSystem),
     #[default]
     Unknown,
+    Arcade(ArcadeMachine),
+    Computer(ComputerSystem),
 }
 
 impl GameSystem {
@@ -25,6 +31,7 @@ impl GameSystem {
             .chain(SonySystem::iter().map(GameSystem::Sony))
             .chain(AtariSystem::iter().map(GameSystem::Atari))
             .chain(OtherSystem::iter().map(GameSystem::Other))
+            .chain(ComputerSystem::iter().map(GameSystem::Computer))
     }
 
     pub fn guess(rom_path: impl AsRef<Path>) -> Option<Self> {
@@ -32,6 +39,38 @@ impl GameSystem {
     }
 }
 
+/// The longest machine name MAME allows
+pub const ARCADE_MACHINE_NAME_LENGTH: usize = 16;
+
+/// An arcade board, by its MAME machine name like `pacman` or `sf2`
+///
+/// There are far too many of these to list, so they are left out of [GameSystem::iter]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ArcadeMachine(ArrayString<ARCADE_MACHINE_NAME_LENGTH>);
+
+impl ArcadeMachine {
+    pub fn name(&self) -> &str {
+        &self.0
+    }
+}
+
+impl FromStr for ArcadeMachine {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.trim().to_lowercase();
+
+        // MAME names are lowercase alphanumerics and underscores
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return Err(format!("Invalid arcade machine name: {}", s));
+        }
+
+        ArrayString::from(&name)
+            .map(Self)
+            .map_err(|_| format!("Arcade machine name too long: {}", s))
+    }
+}
+
 #[derive(
     Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, EnumIter,
 )]
@@ -87,15 +126,35 @@ pub enum AtariSystem {
     Jaguar,
 }
 
+#[derive(
+    Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, EnumIter,
+)]
+pub enum ComputerSystem {
+    #[serde(alias = "C64")]
+    Commodore64,
+    #[serde(alias = "Spectrum")]
+    ZxSpectrum,
+    #[serde(alias = "MSX")]
+    Msx,
+}
+
 impl FromStr for GameSystem {
     type Err = String;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let s = strip_brackets_and_parens(s).trim().to_lowercase();
 
+        if let Some(name) = s.strip_prefix("arcade - ") {
+            return name.parse().map(GameSystem::Arcade);
+        }
+
         GameSystem::iter()
-            // find variations on a system
-            .find(|system| s == system.to_string().to_lowercase())
+            // find variations on a system, with or without the company in front
+            .find(|system| {
+                let name = system.to_string().to_lowercase();
+
+                s == name || name.split_once(" - ").is_some_and(|(_, name)| s == name)
+            })
             .ok_or(format!("Unknown system: {}", s))
     }
 }
@@ -139,6 +198,12 @@ impl Display for GameSystem {
             GameSystem::Atari(AtariSystem::Lynx) => write!(f, "Atari - Lynx"),
             GameSystem::Atari(AtariSystem::Jaguar) => write!(f, "Atari - Jaguar"),
             GameSystem::Unknown => write!(f, "Unknown"),
+            GameSystem::Arcade(machine) => write!(f, "Arcade - {}", machine.name()),
+            GameSystem::Computer(ComputerSystem::Commodore64) => {
+                write!(f, "Commodore - Commodore 64")
+            }
+            GameSystem::Computer(ComputerSystem::ZxSpectrum) => write!(f, "Sinclair - ZX Spectrum"),
+            GameSystem::Computer(ComputerSystem::Msx) => write!(f, "Microsoft - MSX"),
         }
     }
 }
@@ -0,0 +1,396 @@
+use crate::{
+    component::audio::AudioChunk,
+    scheduler::{AudioClock, Scheduler},
+};
+use ringbuffer::{AllocRingBuffer, RingBuffer};
+use std::{sync::Arc, time::Duration};
+
+/// How many adjustment periods without an underrun before we try to lower latency again
+const STABLE_PERIODS_BEFORE_SHRINK: u32 = 64;
+/// How many samples [AdaptiveAudioBuffer::flush] fades over each way, short enough to not be heard as a fade
+const FADE_SAMPLES: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AudioDrift {
+    /// The machine is not producing samples as fast as the host consumes them
+    Starved,
+    Balanced,
+    /// The machine is producing samples faster than the host consumes them
+    Flooded,
+}
+
+/// Sample queue between the machine and the host audio device that sizes itself to how well the host keeps up
+///
+/// The emulation thread calls [AdaptiveAudioBuffer::push_samples] and [AdaptiveAudioBuffer::correct_drift] after every
+/// run, the host audio callback calls [AdaptiveAudioBuffer::fill], and [AdaptiveAudioBuffer::flush] is called whenever
+/// the machine jumps somewhere else, like loading a state, rewinding or seeking through a movie
+#[derive(Debug)]
+pub struct AdaptiveAudioBuffer {
+    samples: AllocRingBuffer<f32>,
+    /// How many samples we try to keep queued
+    target_latency: usize,
+    minimum_latency: usize,
+    maximum_latency: usize,
+    underruns: u32,
+    overruns: u32,
+    stable_periods: u32,
+    /// Samples left to fade in after a [AdaptiveAudioBuffer::flush]
+    fade_in: usize,
+    /// Told how much audio [AdaptiveAudioBuffer::fill] played and at how many samples per second
+    clock: Option<(Arc<AudioClock>, u32)>,
+}
+
+impl AdaptiveAudioBuffer {
+    pub fn new(minimum_latency: usize, maximum_latency: usize) -> Self {
+        assert!(
+            minimum_latency != 0 && minimum_latency <= maximum_latency,
+            "Invalid latency bounds"
+        );
+
+        Self {
+            samples: AllocRingBuffer::new(minimum_latency * 2),
+            target_latency: minimum_latency,
+            minimum_latency,
+            maximum_latency,
+            underruns: 0,
+            overruns: 0,
+            stable_periods: 0,
+            fade_in: 0,
+            clock: None,
+        }
+    }
+
+    /// Lets the scheduler pace itself by what the host plays, see [crate::scheduler::Pacing::Audio]
+    pub fn set_clock(&mut self, clock: Arc<AudioClock>, sample_rate: u32) {
+        if let Some((previous_clock, _)) = self.clock.take() {
+            previous_clock.set_active(false);
+        }
+
+        clock.set_active(true);
+        self.clock = Some((clock, sample_rate));
+    }
+
+    pub fn target_latency(&self) -> usize {
+        self.target_latency
+    }
+
+    pub fn queued_samples(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn push_samples(&mut self, samples: &[f32]) {
+        for sample in samples {
+            // The ring buffer drops the oldest sample when full, which is an audible skip
+            if self.samples.is_full() {
+                self.overruns += 1;
+            }
+
+            let gain = if self.fade_in != 0 {
+                self.fade_in -= 1;
+                (FADE_SAMPLES - self.fade_in) as f32 / FADE_SAMPLES as f32
+            } else {
+                1.0
+            };
+
+            self.samples.push(*sample * gain);
+        }
+    }
+
+    /// Throws away what is queued, since it was made by a machine state that no longer exists
+    ///
+    /// Going straight from one waveform to another pops, so the start of what was queued fades out and whatever gets
+    /// pushed next fades in
+    pub fn flush(&mut self) {
+        let fade_out: Vec<_> = self.samples.drain().take(FADE_SAMPLES).collect();
+        self.samples.clear();
+
+        for (index, sample) in fade_out.iter().enumerate() {
+            self.samples
+                .push(sample * (FADE_SAMPLES - index) as f32 / (FADE_SAMPLES + 1) as f32);
+        }
+
+        self.fade_in = FADE_SAMPLES;
+    }
+
+    /// Fills the host buffer, padding with silence if we ran dry
+    pub fn fill(&mut self, output: &mut [f32]) {
+        let mut ran_dry = false;
+
+        for output_sample in output.iter_mut() {
+            *output_sample = match self.samples.dequeue() {
+                Some(sample) => sample,
+                None => {
+                    ran_dry = true;
+                    0.0
+                }
+            };
+        }
+
+        if ran_dry {
+            self.underruns += 1;
+        }
+
+        // Silence takes as long to play as anything else
+        if let Some((clock, sample_rate)) = &self.clock {
+            clock.played(Duration::from_secs(output.len() as u64) / *sample_rate);
+        }
+    }
+
+    /// Resizes the buffer based on what happened since the last call and reports which way the machine is drifting
+    pub fn adjust(&mut self) -> AudioDrift {
+        if self.underruns != 0 {
+            tracing::debug!(
+                "{} audio underruns, raising latency from {} samples",
+                self.underruns,
+                self.target_latency
+            );
+
+            self.stable_periods = 0;
+            self.resize((self.target_latency * 2).min(self.maximum_latency));
+        } else {
+            self.stable_periods += 1;
+
+            if self.stable_periods >= STABLE_PERIODS_BEFORE_SHRINK {
+                self.stable_periods = 0;
+                self.resize((self.target_latency * 3 / 4).max(self.minimum_latency));
+            }
+        }
+
+        let drift = if self.underruns != 0 || self.samples.len() < self.target_latency / 2 {
+            AudioDrift::Starved
+        } else if self.overruns != 0 || self.samples.len() > self.target_latency * 3 / 2 {
+            AudioDrift::Flooded
+        } else {
+            AudioDrift::Balanced
+        };
+
+        self.underruns = 0;
+        self.overruns = 0;
+
+        drift
+    }
+
+    /// Feeds the drift back into the scheduler so the machine speeds up or slows down to match the host
+    pub fn correct_drift(&mut self, scheduler: &mut Scheduler) {
+        match self.adjust() {
+            AudioDrift::Starved => scheduler.too_fast(),
+            AudioDrift::Balanced => {}
+            AudioDrift::Flooded => scheduler.too_slow(),
+        }
+    }
+
+    fn resize(&mut self, target_latency: usize) {
+        if target_latency == self.target_latency {
+            return;
+        }
+
+        // Leave headroom so the machine can run ahead of the host a bit without dropping samples
+        let mut samples = AllocRingBuffer::new(target_latency * 2);
+        samples.extend(self.samples.drain());
+
+        self.samples = samples;
+        self.target_latency = target_latency;
+    }
+}
+
+/// Mixes what every audio component made down to one stream at the host sample rate
+#[derive(Debug)]
+pub struct AudioMixer {
+    output_rate: u32,
+    resamplers: Vec<Resampler>,
+}
+
+impl AudioMixer {
+    pub fn new(output_rate: u32) -> Self {
+        Self {
+            output_rate,
+            resamplers: Vec::default(),
+        }
+    }
+
+    /// Takes the chunks from [crate::machine::Machine::frame_audio], which have to come in the same order every time
+    pub fn mix(&mut self, chunks: &[AudioChunk]) -> Vec<f32> {
+        // Machines do not gain components while running, but a component can change its rate
+        if self.resamplers.len() != chunks.len()
+            || self
+                .resamplers
+                .iter()
+                .zip(chunks)
+                .any(|(resampler, chunk)| resampler.input_rate != chunk.sample_rate)
+        {
+            self.resamplers = chunks
+                .iter()
+                .map(|chunk| Resampler::new(chunk.sample_rate, self.output_rate))
+                .collect();
+        }
+
+        let mut mixed = Vec::new();
+        let mut resampled = Vec::new();
+
+        for (resampler, chunk) in self.resamplers.iter_mut().zip(chunks) {
+            resampled.clear();
+            resampler.resample(&chunk.samples, &mut resampled);
+
+            if mixed.len() < resampled.len() {
+                mixed.resize(resampled.len(), 0.0);
+            }
+
+            for (mixed_sample, sample) in mixed.iter_mut().zip(&resampled) {
+                *mixed_sample += sample;
+            }
+        }
+
+        mixed
+    }
+}
+
+/// Linear interpolation, which is plenty for square waves and beepers
+#[derive(Debug)]
+struct Resampler {
+    input_rate: u32,
+    /// Input samples per output sample
+    step: f64,
+    /// Where the next output sample falls, counted from the last sample of the previous chunk
+    position: f64,
+    previous: f32,
+}
+
+impl Resampler {
+    fn new(input_rate: u32, output_rate: u32) -> Self {
+        Self {
+            input_rate,
+            step: input_rate as f64 / output_rate as f64,
+            position: 0.0,
+            previous: 0.0,
+        }
+    }
+
+    fn resample(&mut self, samples: &[f32], output: &mut Vec<f32>) {
+        let previous = self.previous;
+        // Index 0 is the last sample of the previous chunk so there is always something to interpolate from
+        let sample = |index: usize| {
+            if index == 0 {
+                previous
+            } else {
+                samples[index - 1]
+            }
+        };
+
+        while self.position < samples.len() as f64 {
+            let index = self.position as usize;
+            let fraction = (self.position - index as f64) as f32;
+
+            output.push(sample(index) + (sample(index + 1) - sample(index)) * fraction);
+            self.position += self.step;
+        }
+
+        self.position -= samples.len() as f64;
+        if let Some(last) = samples.last() {
+            self.previous = *last;
+        }
+    }
+}
+
+impl Drop for AdaptiveAudioBuffer {
+    fn drop(&mut self) {
+        // Nothing is feeding the clock anymore
+        if let Some((clock, _)) = &self.clock {
+            clock.set_active(false);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn underruns_raise_latency() {
+        let mut buffer = AdaptiveAudioBuffer::new(256, 4096);
+        let mut output = [0.0; 512];
+
+        buffer.push_samples(&[0.5; 128]);
+        buffer.fill(&mut output);
+
+        assert_eq!(output[..128], [0.5; 128]);
+        assert_eq!(output[128..], [0.0; 384]);
+        assert_eq!(buffer.adjust(), AudioDrift::Starved);
+        assert_eq!(buffer.target_latency(), 512);
+    }
+
+    #[test]
+    fn stable_playback_lowers_latency() {
+        let mut buffer = AdaptiveAudioBuffer::new(256, 4096);
+        let mut output = [0.0; 256];
+
+        // Force it up first
+        buffer.fill(&mut output);
+        buffer.adjust();
+        assert_eq!(buffer.target_latency(), 512);
+
+        // Keep the queue sitting right at the target
+        buffer.push_samples(&[0.5; 512]);
+        for _ in 0..STABLE_PERIODS_BEFORE_SHRINK {
+            buffer.push_samples(&[0.5; 256]);
+            buffer.fill(&mut output);
+            assert_eq!(buffer.adjust(), AudioDrift::Balanced);
+        }
+
+        assert_eq!(buffer.target_latency(), 384);
+    }
+
+    #[test]
+    fn flushing_fades() {
+        let mut buffer = AdaptiveAudioBuffer::new(256, 4096);
+        let mut output = [0.0; FADE_SAMPLES * 2];
+
+        buffer.push_samples(&[0.5; 256]);
+        buffer.flush();
+        assert_eq!(buffer.queued_samples(), FADE_SAMPLES);
+
+        buffer.push_samples(&[0.5; FADE_SAMPLES]);
+        buffer.fill(&mut output);
+
+        // Down to nearly nothing, then back up
+        assert!(output[0] < 0.5 && output[FADE_SAMPLES - 1] < 0.01);
+        assert!(output[FADE_SAMPLES] < 0.01 && output[FADE_SAMPLES * 2 - 1] == 0.5);
+        assert!(output[FADE_SAMPLES..]
+            .windows(2)
+            .all(|pair| pair[0] <= pair[1]));
+    }
+
+    #[test]
+    fn mixing_resamples() {
+        let mut mixer = AudioMixer::new(200);
+        let chunks = [
+            AudioChunk {
+                sample_rate: 100,
+                samples: vec![1.0; 50],
+            },
+            AudioChunk {
+                sample_rate: 200,
+                samples: vec![0.25; 100],
+            },
+        ];
+
+        // Twice the rate, with the start still coming off the silence before it
+        let mixed = mixer.mix(&chunks);
+        assert_eq!(mixed.len(), 100);
+        assert_eq!(mixed[0], 0.0);
+        assert_eq!(mixed[1], 0.75);
+        assert!(mixed[2..].iter().all(|sample| *sample == 1.25));
+
+        // Nothing is lost or doubled between frames
+        let total: usize = (0..10).map(|_| mixer.mix(&chunks).len()).sum();
+        assert_eq!(total, 1000);
+    }
+
+    #[test]
+    fn overproduction_is_flooded() {
+        let mut buffer = AdaptiveAudioBuffer::new(256, 4096);
+
+        buffer.push_samples(&[0.5; 1024]);
+        assert_eq!(buffer.adjust(), AudioDrift::Flooded);
+        assert_eq!(buffer.queued_samples(), 512);
+    }
+}
@@ -0,0 +1,206 @@
+//! Writes out every frame the machine runs, paced by emulated time instead of the host so nothing gets dropped
+//!
+//! A dump directory holds
+//!
+//! - `display_<n>.rgba` for every display component, raw 8 bit RGBA frames back to back
+//! - `frames.csv` with a line per frame and display, giving the emulated tick and time the frame ended on and its size
+//! - `audio_<n>.f32` for every audio component, raw mono 32 bit float samples back to back
+//! - `audio.csv` with a line per frame and audio component, giving the emulated tick and time the chunk ended on, its
+//!   sample rate and how many samples it had
+//!
+//! As long as the size of a display stays the same it can be piped straight into an encoder, like
+//!
+//! ```sh
+//! ffmpeg -f rawvideo -pixel_format rgba -video_size 64x32 -framerate 60 -i display_0.rgba \
+//!     -f f32le -ar 44100 -ac 1 -i audio_0.f32 \
+//!     -vf scale=iw*8:ih*8:flags=neighbor output.mkv
+//! ```
+
+use super::rendering_backend::DisplayComponentFramebuffer;
+use crate::component::audio::AudioChunk;
+use std::{
+    fs::{create_dir_all, File},
+    io::{BufWriter, Write},
+    path::PathBuf,
+    time::Duration,
+};
+
+#[derive(Debug)]
+pub struct AvDumper {
+    directory: PathBuf,
+    frame_duration: Duration,
+    /// Emulated time the dump started at
+    start: Duration,
+    frame: u32,
+    displays: Vec<BufWriter<File>>,
+    frame_log: BufWriter<File>,
+    audio: Vec<BufWriter<File>>,
+    audio_log: BufWriter<File>,
+}
+
+impl AvDumper {
+    pub fn new(
+        directory: impl Into<PathBuf>,
+        frame_duration: Duration,
+        start: Duration,
+    ) -> std::io::Result<Self> {
+        let directory = directory.into();
+        create_dir_all(&directory)?;
+
+        let mut frame_log = BufWriter::new(File::create(directory.join("frames.csv"))?);
+        writeln!(frame_log, "frame,display,tick,nanoseconds,width,height")?;
+
+        let mut audio_log = BufWriter::new(File::create(directory.join("audio.csv"))?);
+        writeln!(
+            audio_log,
+            "frame,audio,tick,nanoseconds,sample_rate,samples"
+        )?;
+
+        tracing::info!("Dumping frames to {}", directory.display());
+
+        Ok(Self {
+            directory,
+            frame_duration,
+            start,
+            frame: 0,
+            displays: Vec::default(),
+            frame_log,
+            audio: Vec::default(),
+            audio_log,
+        })
+    }
+
+    /// Emulated time the frame being run should end on, kept on a fixed cadence so rounding never adds up
+    pub fn next_frame_end(&self) -> Duration {
+        self.start + self.frame_duration * (self.frame + 1)
+    }
+
+    pub fn frame_duration(&self) -> Duration {
+        self.frame_duration
+    }
+
+    /// Writes out what every display shows right now and the sound made over the frame
+    pub fn dump_frame(
+        &mut self,
+        framebuffers: impl IntoIterator<Item = DisplayComponentFramebuffer>,
+        audio: &[AudioChunk],
+        tick: u64,
+        emulated_time: Duration,
+    ) -> std::io::Result<()> {
+        for (display, framebuffer) in framebuffers.into_iter().enumerate() {
+            let framebuffer = match framebuffer {
+                DisplayComponentFramebuffer::Software(framebuffer) => framebuffer,
+                #[cfg(graphics_vulkan)]
+                DisplayComponentFramebuffer::Vulkan(_) => {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::Unsupported,
+                        "Only software framebuffers can be dumped",
+                    ));
+                }
+            };
+            let framebuffer = framebuffer.lock().unwrap();
+
+            if display == self.displays.len() {
+                self.displays.push(BufWriter::new(File::create(
+                    self.directory.join(format!("display_{}.rgba", display)),
+                )?));
+            }
+
+            // Rows are x, so the column major storage is already in raster order
+            self.displays[display].write_all(bytemuck::cast_slice(framebuffer.as_slice()))?;
+
+            writeln!(
+                self.frame_log,
+                "{},{},{},{},{},{}",
+                self.frame,
+                display,
+                tick,
+                emulated_time.as_nanos(),
+                framebuffer.nrows(),
+                framebuffer.ncols()
+            )?;
+        }
+
+        for (index, chunk) in audio.iter().enumerate() {
+            if index == self.audio.len() {
+                self.audio.push(BufWriter::new(File::create(
+                    self.directory.join(format!("audio_{}.f32", index)),
+                )?));
+            }
+
+            for sample in &chunk.samples {
+                self.audio[index].write_all(&sample.to_le_bytes())?;
+            }
+
+            writeln!(
+                self.audio_log,
+                "{},{},{},{},{},{}",
+                self.frame,
+                index,
+                tick,
+                emulated_time.as_nanos(),
+                chunk.sample_rate,
+                chunk.samples.len()
+            )?;
+        }
+
+        self.frame += 1;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use nalgebra::DMatrix;
+    use palette::Srgba;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn frames_are_raster_order() {
+        let directory =
+            std::env::temp_dir().join(format!("multiemu_av_dump_{}", std::process::id()));
+        let mut framebuffer = DMatrix::from_element(2, 1, Srgba::new(0, 0, 0, 0xff));
+        framebuffer[(1, 0)] = Srgba::new(0xff, 0xff, 0xff, 0xff);
+        let framebuffer = Arc::new(Mutex::new(framebuffer));
+
+        let mut av_dumper =
+            AvDumper::new(&directory, Duration::from_millis(10), Duration::ZERO).unwrap();
+        for frame in 1..=2 {
+            av_dumper
+                .dump_frame(
+                    [DisplayComponentFramebuffer::Software(framebuffer.clone())],
+                    &[AudioChunk {
+                        sample_rate: 100,
+                        samples: vec![0.5; frame as usize],
+                    }],
+                    frame * 100,
+                    Duration::from_millis(frame * 10),
+                )
+                .unwrap();
+        }
+        assert_eq!(av_dumper.next_frame_end(), Duration::from_millis(30));
+        drop(av_dumper);
+
+        let video = std::fs::read(directory.join("display_0.rgba"));
+        let frame_log = std::fs::read_to_string(directory.join("frames.csv"));
+        let audio = std::fs::read(directory.join("audio_0.f32"));
+        let audio_log = std::fs::read_to_string(directory.join("audio.csv"));
+        std::fs::remove_dir_all(&directory).unwrap();
+
+        assert_eq!(
+            video.unwrap(),
+            [0, 0, 0, 0xff, 0xff, 0xff, 0xff, 0xff].repeat(2)
+        );
+        assert_eq!(
+            frame_log.unwrap().lines().skip(1).collect::<Vec<_>>(),
+            ["0,0,100,10000000,2,1", "1,0,200,20000000,2,1"]
+        );
+        assert_eq!(audio.unwrap(), 0.5f32.to_le_bytes().repeat(3));
+        assert_eq!(
+            audio_log.unwrap().lines().skip(1).collect::<Vec<_>>(),
+            ["0,0,100,10000000,100,1", "1,0,200,20000000,100,2"]
+        );
+    }
+}
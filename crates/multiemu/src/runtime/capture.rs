@@ -0,0 +1,225 @@
+//! Screenshots and states taken by the machine itself when something happens in it, for bugs too hard to time by hand
+//!
+//! Rules are checked as each frame ends, so a write in the middle of a frame is captured with the rest of that frame
+//! done. Interrupts are not something the machine knows about yet, so they cannot trigger a capture
+//!
+//! A capture directory holds `frame_<n>_display_<d>.webp` for screenshots and `frame_<n>.state` for states, which
+//! load like any other machine snapshot
+
+use super::{frame_compare::framebuffer_image, rendering_backend::DisplayComponentFramebuffer};
+use crate::{
+    machine::serialization::MachineState,
+    memory::{parse_address, AddressSpaceId},
+};
+use image::ImageFormat;
+use std::{
+    error::Error,
+    fs::{create_dir_all, File},
+    io::BufWriter,
+    path::PathBuf,
+    str::FromStr,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaptureTrigger {
+    /// Anything writing to this address, including multi byte writes that only cover it partly
+    Write {
+        address_space: AddressSpaceId,
+        address: usize,
+    },
+    /// Counted from when capturing started, the first frame being 0
+    Frame(u64),
+}
+
+impl FromStr for CaptureTrigger {
+    type Err = Box<dyn Error + Send + Sync>;
+
+    /// Like `write:0:0x0200` or `frame:120`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split(':').collect::<Vec<_>>().as_slice() {
+            ["write", address_space, address] => Ok(Self::Write {
+                address_space: address_space.parse()?,
+                address: parse_address(address)?,
+            }),
+            ["frame", frame] => Ok(Self::Frame(frame.parse()?)),
+            _ => Err(format!(
+                "Could not parse \"{}\", expected write:<address space>:<address> or frame:<frame>",
+                s
+            )
+            .into()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaptureKind {
+    /// What every display shows
+    Screenshot,
+    /// The whole machine state
+    State,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CaptureRule {
+    pub trigger: CaptureTrigger,
+    pub kind: CaptureKind,
+    /// Stop checking the rule after it fires the first time
+    pub once: bool,
+}
+
+#[derive(Debug)]
+pub struct Capturer {
+    directory: PathBuf,
+    /// Rules left to check, those that only fire once are removed when they do
+    rules: Vec<CaptureRule>,
+    frame: u64,
+}
+
+impl Capturer {
+    pub fn new(directory: impl Into<PathBuf>, rules: Vec<CaptureRule>) -> std::io::Result<Self> {
+        let directory = directory.into();
+        create_dir_all(&directory)?;
+
+        tracing::info!(
+            "Checking {} capture rules, capturing to {}",
+            rules.len(),
+            directory.display()
+        );
+
+        Ok(Self {
+            directory,
+            rules,
+            frame: 0,
+        })
+    }
+
+    /// Addresses the memory translation table has to watch for the write rules
+    pub fn watched_writes(&self) -> impl Iterator<Item = (AddressSpaceId, usize)> + '_ {
+        self.rules.iter().filter_map(|rule| match rule.trigger {
+            CaptureTrigger::Write {
+                address_space,
+                address,
+            } => Some((address_space, address)),
+            CaptureTrigger::Frame(_) => None,
+        })
+    }
+
+    /// Moves on to the next frame, returning what has to be captured for the one that just ended
+    ///
+    /// Each kind is only given once, no matter how many rules fired
+    pub fn end_frame(&mut self, write_hits: &[(AddressSpaceId, usize)]) -> Vec<CaptureKind> {
+        let frame = self.frame;
+        let mut captures = Vec::new();
+
+        self.rules.retain(|rule| {
+            let fired = match rule.trigger {
+                CaptureTrigger::Write {
+                    address_space,
+                    address,
+                } => write_hits.contains(&(address_space, address)),
+                CaptureTrigger::Frame(trigger_frame) => trigger_frame == frame,
+            };
+
+            if fired {
+                tracing::info!("Capture rule {:?} fired on frame {}", rule.trigger, frame);
+
+                if !captures.contains(&rule.kind) {
+                    captures.push(rule.kind);
+                }
+            }
+
+            !(fired && rule.once)
+        });
+
+        self.frame += 1;
+        captures
+    }
+
+    /// The frame [Capturer::end_frame] was last called for
+    fn captured_frame(&self) -> u64 {
+        self.frame.saturating_sub(1)
+    }
+
+    pub fn save_screenshots(
+        &self,
+        framebuffers: impl IntoIterator<Item = DisplayComponentFramebuffer>,
+    ) -> Result<(), Box<dyn Error>> {
+        for (display, framebuffer) in framebuffers.into_iter().enumerate() {
+            let framebuffer = match framebuffer {
+                DisplayComponentFramebuffer::Software(framebuffer) => framebuffer,
+                #[cfg(graphics_vulkan)]
+                DisplayComponentFramebuffer::Vulkan(_) => {
+                    return Err("Only software framebuffers can be captured".into());
+                }
+            };
+            let image = framebuffer_image(&framebuffer.lock().unwrap());
+            let path = self.directory.join(format!(
+                "frame_{}_display_{}.webp",
+                self.captured_frame(),
+                display
+            ));
+
+            image.save_with_format(&path, ImageFormat::WebP)?;
+            tracing::info!("Captured a screenshot to {}", path.display());
+        }
+
+        Ok(())
+    }
+
+    pub fn save_state(&self, state: &MachineState) -> Result<(), Box<dyn Error>> {
+        let path = self
+            .directory
+            .join(format!("frame_{}.state", self.captured_frame()));
+
+        rmp_serde::encode::write_named(&mut BufWriter::new(File::create(&path)?), state)?;
+        tracing::info!("Captured the machine state to {}", path.display());
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn rules_fire_on_their_frame() {
+        let directory =
+            std::env::temp_dir().join(format!("multiemu_capture_{}", std::process::id()));
+        let mut capturer = Capturer::new(
+            &directory,
+            vec![
+                CaptureRule {
+                    trigger: "write:0:0x10".parse().unwrap(),
+                    kind: CaptureKind::State,
+                    once: true,
+                },
+                CaptureRule {
+                    trigger: "frame:1".parse().unwrap(),
+                    kind: CaptureKind::Screenshot,
+                    once: false,
+                },
+                CaptureRule {
+                    trigger: "frame:1".parse().unwrap(),
+                    kind: CaptureKind::State,
+                    once: false,
+                },
+            ],
+        )
+        .unwrap();
+        assert_eq!(capturer.watched_writes().collect::<Vec<_>>(), [(0, 0x10)]);
+
+        assert!(capturer.end_frame(&[(0, 0x11)]).is_empty());
+        assert_eq!(
+            capturer.end_frame(&[(0, 0x10)]),
+            [CaptureKind::State, CaptureKind::Screenshot]
+        );
+        // The write rule only fires once
+        assert!(capturer.end_frame(&[(0, 0x10)]).is_empty());
+        assert_eq!(capturer.watched_writes().count(), 0);
+
+        assert!("read:0:0x10".parse::<CaptureTrigger>().is_err());
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+}
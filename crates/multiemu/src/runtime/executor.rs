@@ -0,0 +1,297 @@
+use std::{
+    any::Any,
+    fmt::Display,
+    panic::{catch_unwind, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Condvar, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+/// How long [Executor::shutdown] waits on tasks before leaving the rest behind
+pub const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Handed to every task so long running loops know when to wind down
+#[derive(Clone, Debug)]
+pub struct TaskContext {
+    shutdown: Arc<AtomicBool>,
+}
+
+impl TaskContext {
+    /// Tasks should check this regularly and return once it is set
+    pub fn should_stop(&self) -> bool {
+        self.shutdown.load(Ordering::Relaxed)
+    }
+}
+
+/// A task that panicked, kept until the frontend picks it up with [Executor::take_panics] to show the user
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TaskPanic {
+    pub task: String,
+    pub message: String,
+}
+
+impl Display for TaskPanic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Task {} panicked: {}", self.task, self.message)
+    }
+}
+
+/// Set once a task returns or panics
+#[derive(Debug, Default)]
+struct Completion {
+    finished: Mutex<bool>,
+    condvar: Condvar,
+}
+
+impl Completion {
+    fn finish(&self) {
+        *self.finished.lock().unwrap() = true;
+        self.condvar.notify_all();
+    }
+
+    fn wait(&self, timeout: Duration) -> bool {
+        let (finished, _) = self
+            .condvar
+            .wait_timeout_while(self.finished.lock().unwrap(), timeout, |finished| {
+                !*finished
+            })
+            .unwrap();
+
+        *finished
+    }
+}
+
+/// Lets whoever spawned a task wait on just that one, the executor still owns the thread
+#[derive(Clone, Debug)]
+pub struct TaskHandle {
+    name: String,
+    completion: Arc<Completion>,
+}
+
+impl TaskHandle {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn is_finished(&self) -> bool {
+        *self.completion.finished.lock().unwrap()
+    }
+
+    /// Returns false if the task was still running once `timeout` was up
+    pub fn wait(&self, timeout: Duration) -> bool {
+        self.completion.wait(timeout)
+    }
+}
+
+struct Task {
+    name: String,
+    join_handle: JoinHandle<()>,
+    completion: Arc<Completion>,
+}
+
+/// Owns every background thread the frontend spawns so they can all be shut down together
+///
+/// Tasks that panic are logged with their name and held onto for [Executor::take_panics], instead of silently taking
+/// their thread with them
+#[derive(Default)]
+pub struct Executor {
+    shutdown: Arc<AtomicBool>,
+    tasks: Mutex<Vec<Task>>,
+    panics: Arc<Mutex<Vec<TaskPanic>>>,
+}
+
+impl Executor {
+    /// The task runs on its own thread, the returned handle can be ignored if nobody needs to wait on it
+    pub fn spawn(
+        &self,
+        name: impl Into<String>,
+        task: impl FnOnce(TaskContext) + Send + 'static,
+    ) -> TaskHandle {
+        let name = name.into();
+        let completion = Arc::new(Completion::default());
+        let handle = TaskHandle {
+            name: name.clone(),
+            completion: completion.clone(),
+        };
+
+        if self.shutdown.load(Ordering::Relaxed) {
+            tracing::warn!("Refusing to spawn task {} while shutting down", name);
+            // Nothing will ever run, so nobody should end up waiting on it
+            completion.finish();
+            return handle;
+        }
+
+        let context = TaskContext {
+            shutdown: self.shutdown.clone(),
+        };
+        let task_name = name.clone();
+        let task_completion = completion.clone();
+        let panics = self.panics.clone();
+
+        let join_handle = thread::Builder::new()
+            .name(name.clone())
+            .spawn(move || {
+                tracing::debug!("Task {} started", task_name);
+
+                match catch_unwind(AssertUnwindSafe(|| task(context))) {
+                    Ok(()) => tracing::debug!("Task {} finished", task_name),
+                    Err(payload) => panics
+                        .lock()
+                        .unwrap()
+                        .push(report_panic(&task_name, payload.as_ref())),
+                }
+
+                task_completion.finish();
+            })
+            .expect("Failed to spawn thread");
+
+        let mut tasks = self.tasks.lock().unwrap();
+        // Forget about anything that already finished so this doesn't grow forever
+        tasks.retain(|task| !task.join_handle.is_finished());
+        tasks.push(Task {
+            name,
+            join_handle,
+            completion,
+        });
+
+        handle
+    }
+
+    /// Names of the tasks that are still running
+    pub fn running_tasks(&self) -> Vec<String> {
+        self.tasks
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|task| !task.join_handle.is_finished())
+            .map(|task| task.name.clone())
+            .collect()
+    }
+
+    /// Every task that panicked since the last call, oldest first
+    pub fn take_panics(&self) -> Vec<TaskPanic> {
+        std::mem::take(&mut *self.panics.lock().unwrap())
+    }
+
+    /// Signals every task to stop and waits up to [SHUTDOWN_TIMEOUT] for them, new tasks will not be spawned afterwards
+    pub fn shutdown(&self) {
+        self.shutdown_within(SHUTDOWN_TIMEOUT);
+    }
+
+    /// Like [Executor::shutdown], but tasks still running after `timeout` are left behind instead of hanging the exit
+    pub fn shutdown_within(&self, timeout: Duration) {
+        self.shutdown.store(true, Ordering::Relaxed);
+
+        let tasks = std::mem::take(&mut *self.tasks.lock().unwrap());
+        let deadline = Instant::now() + timeout;
+
+        for task in tasks {
+            tracing::debug!("Waiting for task {} to stop", task.name);
+
+            if task
+                .completion
+                .wait(deadline.saturating_duration_since(Instant::now()))
+            {
+                // Panics were already reported from inside the thread
+                let _ = task.join_handle.join();
+            } else {
+                tracing::error!("Task {} did not stop in time, leaving it behind", task.name);
+            }
+        }
+    }
+}
+
+impl Drop for Executor {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+fn report_panic(task_name: &str, payload: &(dyn Any + Send)) -> TaskPanic {
+    let message = payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("Unknown panic payload");
+
+    let task_panic = TaskPanic {
+        task: task_name.to_string(),
+        message: message.to_string(),
+    };
+    tracing::error!("{}", task_panic);
+
+    task_panic
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::atomic::AtomicU32;
+
+    #[test]
+    fn shutdown_stops_looping_tasks() {
+        let executor = Executor::default();
+        let iterations = Arc::new(AtomicU32::new(0));
+
+        let task_iterations = iterations.clone();
+        executor.spawn("looping", move |context| {
+            while !context.should_stop() {
+                task_iterations.fetch_add(1, Ordering::Relaxed);
+                thread::yield_now();
+            }
+        });
+
+        assert_eq!(executor.running_tasks(), ["looping"]);
+        executor.shutdown();
+        assert!(executor.running_tasks().is_empty());
+
+        // Nothing new can start once we are shutting down
+        executor.spawn("late", |_| unreachable!());
+        assert!(executor.running_tasks().is_empty());
+    }
+
+    #[test]
+    fn panicking_tasks_do_not_poison_shutdown() {
+        let executor = Executor::default();
+
+        executor.spawn("panicking", |_| panic!("Task went wrong"));
+        executor.shutdown();
+    }
+
+    #[test]
+    fn panics_are_handed_out_once() {
+        let executor = Executor::default();
+
+        let task = executor.spawn("panicking", |_| panic!("Task went wrong"));
+        assert!(task.wait(Duration::from_secs(5)));
+
+        assert_eq!(
+            executor.take_panics(),
+            [TaskPanic {
+                task: "panicking".to_string(),
+                message: "Task went wrong".to_string(),
+            }]
+        );
+        assert!(executor.take_panics().is_empty());
+    }
+
+    #[test]
+    fn stuck_tasks_do_not_hang_shutdown() {
+        let executor = Executor::default();
+
+        let quick = executor.spawn("quick", |_| {});
+        assert!(quick.wait(Duration::from_secs(5)));
+
+        // Never checks if it should stop
+        let stuck = executor.spawn("stuck", |_| loop {
+            thread::sleep(Duration::from_secs(1));
+        });
+        assert!(!stuck.wait(Duration::from_millis(10)));
+
+        executor.shutdown_within(Duration::from_millis(50));
+        assert!(!stuck.is_finished());
+    }
+}
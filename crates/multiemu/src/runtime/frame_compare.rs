@@ -0,0 +1,265 @@
+//! Compares what the machine draws against reference frames, for checking a display component against another
+//! emulator or a hardware capture
+//!
+//! References are named like screenshots from [crate::runtime::capture], `frame_<n>_display_<d>.png` or `.webp`, with
+//! frames counted from when comparing started. Frames without a reference are skipped, so a handful of them spread out
+//! over a run works just as well as every single one
+//!
+//! Every frame that differs gets a heatmap written as `frame_<n>_display_<d>_diff.webp`, where matching pixels are the
+//! reference dimmed down and differing ones are red, brighter the further off they are
+
+use super::rendering_backend::DisplayComponentFramebuffer;
+use image::{ImageFormat, Rgba, RgbaImage};
+use nalgebra::DMatrix;
+use palette::Srgba;
+use std::{
+    collections::HashMap,
+    error::Error,
+    fs::{create_dir_all, read_dir},
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameComparison {
+    pub frame: u64,
+    pub display: usize,
+    /// Every pixel counts as differing if the sizes do not match
+    pub differing_pixels: usize,
+    pub total_pixels: usize,
+}
+
+/// How the comparison has gone so far
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FrameComparisonSummary {
+    /// Frames that had a reference for at least one display
+    pub compared_frames: u64,
+    pub differing_frames: u64,
+    pub first_difference: Option<FrameComparison>,
+    /// One for each display that had a reference on the last frame compared
+    pub latest: Vec<FrameComparison>,
+}
+
+#[derive(Debug)]
+pub struct FrameComparer {
+    references: HashMap<(u64, usize), PathBuf>,
+    heatmap_directory: PathBuf,
+    frame: u64,
+    summary: FrameComparisonSummary,
+}
+
+impl FrameComparer {
+    pub fn new(
+        reference_directory: impl AsRef<Path>,
+        heatmap_directory: impl Into<PathBuf>,
+    ) -> std::io::Result<Self> {
+        let heatmap_directory = heatmap_directory.into();
+        create_dir_all(&heatmap_directory)?;
+
+        let mut references = HashMap::default();
+        for entry in read_dir(reference_directory.as_ref())? {
+            let path = entry?.path();
+
+            if let Some(key) = path
+                .file_name()
+                .and_then(|file_name| file_name.to_str())
+                .and_then(parse_reference_name)
+            {
+                references.insert(key, path);
+            }
+        }
+
+        tracing::info!(
+            "Comparing against {} reference frames from {}",
+            references.len(),
+            reference_directory.as_ref().display()
+        );
+
+        Ok(Self {
+            references,
+            heatmap_directory,
+            frame: 0,
+            summary: FrameComparisonSummary::default(),
+        })
+    }
+
+    pub fn summary(&self) -> &FrameComparisonSummary {
+        &self.summary
+    }
+
+    /// Compares what the displays show against the references for the frame that just ended, then moves on
+    pub fn end_frame(
+        &mut self,
+        framebuffers: impl IntoIterator<Item = DisplayComponentFramebuffer>,
+    ) -> Result<(), Box<dyn Error>> {
+        let frame = self.frame;
+        self.frame += 1;
+
+        let mut comparisons = Vec::new();
+        for (display_index, framebuffer) in framebuffers.into_iter().enumerate() {
+            let Some(reference_path) = self.references.get(&(frame, display_index)) else {
+                continue;
+            };
+
+            let framebuffer = match framebuffer {
+                DisplayComponentFramebuffer::Software(framebuffer) => framebuffer,
+                #[cfg(graphics_vulkan)]
+                DisplayComponentFramebuffer::Vulkan(_) => {
+                    return Err("Only software framebuffers can be compared".into());
+                }
+            };
+            let image = framebuffer_image(&framebuffer.lock().unwrap());
+            let reference = image::open(reference_path)?.into_rgba8();
+            let total_pixels = image.width() as usize * image.height() as usize;
+
+            let comparison = match diff_heatmap(&reference, &image) {
+                Some((differing_pixels, heatmap)) => {
+                    if differing_pixels != 0 {
+                        heatmap.save_with_format(
+                            self.heatmap_directory.join(format!(
+                                "frame_{}_display_{}_diff.webp",
+                                frame, display_index
+                            )),
+                            ImageFormat::WebP,
+                        )?;
+                    }
+
+                    FrameComparison {
+                        frame,
+                        display: display_index,
+                        differing_pixels,
+                        total_pixels,
+                    }
+                }
+                None => {
+                    tracing::warn!(
+                        "Display {} is {}x{} on frame {} but the reference is {}x{}",
+                        display_index,
+                        image.width(),
+                        image.height(),
+                        frame,
+                        reference.width(),
+                        reference.height()
+                    );
+
+                    FrameComparison {
+                        frame,
+                        display: display_index,
+                        differing_pixels: total_pixels,
+                        total_pixels,
+                    }
+                }
+            };
+
+            comparisons.push(comparison);
+        }
+
+        if comparisons.is_empty() {
+            return Ok(());
+        }
+
+        self.summary.compared_frames += 1;
+        if let Some(difference) = comparisons
+            .iter()
+            .find(|comparison| comparison.differing_pixels != 0)
+        {
+            tracing::debug!(
+                "Frame {} differs from its reference in {} pixels",
+                frame,
+                difference.differing_pixels
+            );
+
+            self.summary.differing_frames += 1;
+            self.summary.first_difference.get_or_insert(*difference);
+        }
+        self.summary.latest = comparisons;
+
+        Ok(())
+    }
+}
+
+/// Rows of the framebuffer are x, like everywhere else
+pub(crate) fn framebuffer_image(framebuffer: &DMatrix<Srgba<u8>>) -> RgbaImage {
+    RgbaImage::from_fn(
+        framebuffer.nrows() as u32,
+        framebuffer.ncols() as u32,
+        |x, y| Rgba(framebuffer[(x as usize, y as usize)].into()),
+    )
+}
+
+/// How many pixels differ along with the heatmap of them, or [None] if the sizes do not match
+pub fn diff_heatmap(reference: &RgbaImage, image: &RgbaImage) -> Option<(usize, RgbaImage)> {
+    if reference.dimensions() != image.dimensions() {
+        return None;
+    }
+
+    let mut differing_pixels = 0;
+    let heatmap = RgbaImage::from_fn(image.width(), image.height(), |x, y| {
+        let expected = reference.get_pixel(x, y);
+        let actual = image.get_pixel(x, y);
+
+        let difference = expected
+            .0
+            .iter()
+            .zip(actual.0)
+            .map(|(expected, actual)| expected.abs_diff(actual))
+            .max()
+            .unwrap_or(0);
+
+        if difference == 0 {
+            // Dimmed so the differences stand out but there is still something to place them by
+            let [red, green, blue, _] = expected.0;
+            Rgba([red / 4, green / 4, blue / 4, 0xff])
+        } else {
+            differing_pixels += 1;
+            Rgba([0x80 + difference / 2, 0, 0, 0xff])
+        }
+    });
+
+    Some((differing_pixels, heatmap))
+}
+
+/// The frame and display out of `frame_<n>_display_<d>.<png or webp>`
+fn parse_reference_name(file_name: &str) -> Option<(u64, usize)> {
+    let (stem, extension) = file_name.rsplit_once('.')?;
+
+    if !matches!(extension, "png" | "webp") {
+        return None;
+    }
+
+    let (frame, display) = stem.strip_prefix("frame_")?.split_once("_display_")?;
+
+    Some((frame.parse().ok()?, display.parse().ok()?))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn reference_names() {
+        assert_eq!(
+            parse_reference_name("frame_12_display_0.png"),
+            Some((12, 0))
+        );
+        assert_eq!(parse_reference_name("frame_3_display_1.webp"), Some((3, 1)));
+        assert_eq!(parse_reference_name("frame_3_display_1_diff.webp"), None);
+        assert_eq!(parse_reference_name("frame_3.state"), None);
+    }
+
+    #[test]
+    fn heatmap_marks_differences() {
+        let reference = RgbaImage::from_pixel(4, 2, Rgba([0x40, 0x40, 0x40, 0xff]));
+        let mut image = reference.clone();
+        image.put_pixel(1, 1, Rgba([0xff, 0x40, 0x40, 0xff]));
+
+        let (differing_pixels, heatmap) = diff_heatmap(&reference, &image).unwrap();
+        assert_eq!(differing_pixels, 1);
+        assert_eq!(heatmap.get_pixel(0, 0), &Rgba([0x10, 0x10, 0x10, 0xff]));
+        assert_eq!(
+            heatmap.get_pixel(1, 1),
+            &Rgba([0x80 + 0xbf / 2, 0, 0, 0xff])
+        );
+
+        assert!(diff_heatmap(&reference, &RgbaImage::new(2, 2)).is_none());
+    }
+}
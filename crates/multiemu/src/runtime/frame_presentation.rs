@@ -0,0 +1,224 @@
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use strum::{Display, EnumIter};
+
+/// Rate emulated frames are assumed to come out at until display components can report their own
+pub const DEFAULT_CONTENT_FRAME_RATE: f32 = 60.0;
+
+/// What to do on displays that refresh faster than the emulated machine produces frames
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, EnumIter, Display, PartialEq, Eq, Default)]
+pub enum MotionSmoothing {
+    /// Present whenever the host asks
+    #[default]
+    Off,
+    /// Show every emulated frame for a whole number of host refreshes
+    FrameDuplication,
+    /// Show every emulated frame once followed by black refreshes, reducing sample and hold blur
+    BlackFrameInsertion,
+}
+
+/// How the scheduler lines emulated frames up with host refreshes, only takes effect with vsync on
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, EnumIter, Display, PartialEq, Eq, Default)]
+pub enum RefreshRateSync {
+    /// Let the scheduler pace itself off of frame timings
+    Off,
+    /// Nudge emulation speed so frames line up with host refreshes when the rates are close enough
+    #[default]
+    MicroAdjust,
+    /// Always run one emulated frame per host frame, no matter how far off the speed ends up
+    VsyncSlaved,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PresentKind {
+    /// Run the machine and draw what it made
+    Fresh,
+    /// Draw the last frame again without running the machine
+    Duplicate,
+    Black,
+}
+
+/// Decides what each host refresh should show
+#[derive(Debug, Clone)]
+pub struct FramePresentationPacer {
+    host_refresh_rate: Option<f32>,
+    content_frame_rate: f32,
+    presents_per_frame: u32,
+    phase: u32,
+    /// How long drawing the last fresh frame took
+    last_draw_time: Duration,
+    /// Fresh frames skipped in a row
+    skipped_frames: u32,
+}
+
+impl Default for FramePresentationPacer {
+    fn default() -> Self {
+        Self {
+            host_refresh_rate: None,
+            content_frame_rate: DEFAULT_CONTENT_FRAME_RATE,
+            presents_per_frame: 1,
+            phase: 0,
+            last_draw_time: Duration::ZERO,
+            skipped_frames: 0,
+        }
+    }
+}
+
+impl FramePresentationPacer {
+    /// Recomputes how many host refreshes each emulated frame spans, call when the window changes monitors
+    pub fn set_refresh_rate(&mut self, host_refresh_rate: Option<f32>, content_frame_rate: f32) {
+        // A hair of tolerance so 119.88hz still counts as two 60hz frames
+        let presents_per_frame = host_refresh_rate
+            .map(|host_refresh_rate| (host_refresh_rate / content_frame_rate + 0.01).floor() as u32)
+            .unwrap_or(1)
+            .max(1);
+
+        if presents_per_frame != self.presents_per_frame {
+            tracing::info!(
+                "Host refresh rate is {:?}hz, presenting each frame {} times",
+                host_refresh_rate,
+                presents_per_frame
+            );
+        }
+
+        self.host_refresh_rate = host_refresh_rate;
+        self.content_frame_rate = content_frame_rate;
+        self.presents_per_frame = presents_per_frame;
+        self.phase = 0;
+    }
+
+    /// How much emulated time each machine run should cover to stay in step with the host, or [None] to let the scheduler pace itself
+    pub fn scheduler_allotted_time(
+        &self,
+        refresh_rate_sync: RefreshRateSync,
+        motion_smoothing: MotionSmoothing,
+        speed_tolerance: f32,
+    ) -> Option<Duration> {
+        let host_refresh_rate = self.host_refresh_rate?;
+        let drift =
+            host_refresh_rate / (self.content_frame_rate * self.presents_per_frame as f32) - 1.0;
+
+        match refresh_rate_sync {
+            RefreshRateSync::Off => return None,
+            RefreshRateSync::MicroAdjust if drift.abs() > speed_tolerance => return None,
+            _ => {}
+        }
+
+        // With motion smoothing on the machine only runs on fresh presents
+        let runs_per_frame = if motion_smoothing == MotionSmoothing::Off {
+            self.presents_per_frame
+        } else {
+            1
+        };
+
+        Some(Duration::from_secs_f32(
+            1.0 / (self.content_frame_rate * runs_per_frame as f32),
+        ))
+    }
+
+    pub fn finished_drawing(&mut self, draw_time: Duration) {
+        self.last_draw_time = draw_time;
+    }
+
+    /// If drawing the next fresh frame should be skipped since the last one took longer than a frame, up to `frame_skip` in a row
+    pub fn skip_fresh_frame(&mut self, frame_skip: u32) -> bool {
+        let frame_time = Duration::from_secs_f32(1.0 / self.content_frame_rate);
+
+        if self.last_draw_time > frame_time && self.skipped_frames < frame_skip {
+            self.skipped_frames += 1;
+            return true;
+        }
+
+        self.skipped_frames = 0;
+        false
+    }
+
+    pub fn next_present(&mut self, motion_smoothing: MotionSmoothing) -> PresentKind {
+        if motion_smoothing == MotionSmoothing::Off {
+            return PresentKind::Fresh;
+        }
+
+        let phase = self.phase;
+        self.phase = (self.phase + 1) % self.presents_per_frame;
+
+        match (phase, motion_smoothing) {
+            (0, _) => PresentKind::Fresh,
+            (_, MotionSmoothing::BlackFrameInsertion) => PresentKind::Black,
+            _ => PresentKind::Duplicate,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn black_frame_insertion_at_double_refresh() {
+        let mut pacer = FramePresentationPacer::default();
+        pacer.set_refresh_rate(Some(119.88), DEFAULT_CONTENT_FRAME_RATE);
+
+        for _ in 0..4 {
+            assert_eq!(
+                pacer.next_present(MotionSmoothing::BlackFrameInsertion),
+                PresentKind::Fresh
+            );
+            assert_eq!(
+                pacer.next_present(MotionSmoothing::BlackFrameInsertion),
+                PresentKind::Black
+            );
+        }
+    }
+
+    #[test]
+    fn micro_adjust_only_near_the_content_rate() {
+        let mut pacer = FramePresentationPacer::default();
+
+        pacer.set_refresh_rate(Some(59.94), 60.0988);
+        assert_eq!(
+            pacer.scheduler_allotted_time(RefreshRateSync::MicroAdjust, MotionSmoothing::Off, 0.01),
+            Some(Duration::from_secs_f32(1.0 / 60.0988))
+        );
+
+        pacer.set_refresh_rate(Some(144.0), 60.0988);
+        assert_eq!(
+            pacer.scheduler_allotted_time(RefreshRateSync::MicroAdjust, MotionSmoothing::Off, 0.01),
+            None
+        );
+        assert_eq!(
+            pacer.scheduler_allotted_time(
+                RefreshRateSync::VsyncSlaved,
+                MotionSmoothing::FrameDuplication,
+                0.01
+            ),
+            Some(Duration::from_secs_f32(1.0 / 60.0988))
+        );
+    }
+
+    #[test]
+    fn frame_skip_is_bounded() {
+        let mut pacer = FramePresentationPacer::default();
+        pacer.finished_drawing(Duration::from_millis(40));
+
+        assert!(pacer.skip_fresh_frame(2));
+        assert!(pacer.skip_fresh_frame(2));
+        assert!(!pacer.skip_fresh_frame(2));
+        assert!(!pacer.skip_fresh_frame(0));
+
+        pacer.finished_drawing(Duration::from_millis(5));
+        assert!(!pacer.skip_fresh_frame(2));
+    }
+
+    #[test]
+    fn slow_displays_always_present_fresh() {
+        let mut pacer = FramePresentationPacer::default();
+        pacer.set_refresh_rate(Some(50.0), DEFAULT_CONTENT_FRAME_RATE);
+
+        for _ in 0..4 {
+            assert_eq!(
+                pacer.next_present(MotionSmoothing::FrameDuplication),
+                PresentKind::Fresh
+            );
+        }
+    }
+}
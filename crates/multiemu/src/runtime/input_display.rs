@@ -0,0 +1,198 @@
+//! Drawing what the emulated gamepads are pressing on top of the game, for streams and tutorials
+
+use crate::input::{
+    manager::InputManager,
+    registry::{ButtonArtwork, GAMEPAD_TYPES},
+    EmulatedGamepadId,
+};
+use nalgebra::DMatrixViewMut;
+use palette::Srgba;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+/// Size of a pad drawn at a scale of 1.0, in pixels
+const PAD_SIZE: [f32; 2] = [160.0, 72.0];
+const BUTTON_SIZE: f32 = 10.0;
+const PRESSED_COLOR: Srgba<u8> = Srgba::new(0xff, 0xd0, 0x40, 0xff);
+const RELEASED_COLOR: Srgba<u8> = Srgba::new(0x60, 0x60, 0x60, 0xff);
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct InputDisplayConfig {
+    /// Ports that get drawn, none by default
+    pub ports: BTreeSet<EmulatedGamepadId>,
+    /// Top left of the first pad, from 0.0 to 1.0 of the window on both axes
+    pub position: [f32; 2],
+    pub scale: f32,
+}
+
+impl Default for InputDisplayConfig {
+    fn default() -> Self {
+        Self {
+            ports: BTreeSet::default(),
+            position: [0.02, 0.75],
+            scale: 1.0,
+        }
+    }
+}
+
+/// One emulated gamepad as it was when captured
+#[derive(Debug, Clone)]
+pub struct InputDisplayPad {
+    pub port: EmulatedGamepadId,
+    /// Each button of the pad, lit if pressed
+    pub buttons: Vec<(ButtonArtwork, bool)>,
+}
+
+/// What the overlay draws this frame
+#[derive(Debug, Clone)]
+pub struct InputDisplay {
+    pub pads: Vec<InputDisplayPad>,
+    pub position: [f32; 2],
+    pub scale: f32,
+}
+
+impl InputDisplay {
+    /// Pads whose type has no artwork in [GAMEPAD_TYPES] are left out, returns nothing if no pad is left
+    pub fn capture(input_manager: &InputManager, config: &InputDisplayConfig) -> Option<Self> {
+        let pads = Vec::from_iter(
+            input_manager
+                .emulated_gamepads()
+                .into_iter()
+                .filter(|(port, _)| config.ports.contains(port))
+                .filter_map(|(port, gamepad_type)| {
+                    let definition = GAMEPAD_TYPES.get(&gamepad_type)?;
+
+                    Some(InputDisplayPad {
+                        port,
+                        buttons: definition
+                            .artwork
+                            .iter()
+                            .map(|button| {
+                                (
+                                    *button,
+                                    input_manager.peek_input(port, button.input).as_digital(),
+                                )
+                            })
+                            .collect(),
+                    })
+                }),
+        );
+
+        (!pads.is_empty()).then_some(Self {
+            pads,
+            position: config.position,
+            scale: config.scale,
+        })
+    }
+
+    /// Draws over whatever is already on `surface`, which is indexed by x then y
+    pub fn draw(&self, mut surface: DMatrixViewMut<Srgba<u8>>) {
+        let surface_size = [surface.nrows() as f32, surface.ncols() as f32];
+        let pad_size = PAD_SIZE.map(|dimension| dimension * self.scale);
+        let button_size = BUTTON_SIZE * self.scale;
+
+        for (index, pad) in self.pads.iter().enumerate() {
+            let pad_start = [
+                self.position[0] * surface_size[0],
+                self.position[1] * surface_size[1] + index as f32 * pad_size[1],
+            ];
+
+            // Darken behind the pad so it can be made out on any game
+            fill_rect(&mut surface, pad_start, pad_size, |pixel| {
+                Srgba::new(pixel.red / 3, pixel.green / 3, pixel.blue / 3, pixel.alpha)
+            });
+
+            for (button, pressed) in &pad.buttons {
+                let center = [
+                    pad_start[0] + button.position[0] * pad_size[0],
+                    pad_start[1] + button.position[1] * pad_size[1],
+                ];
+                let color = if *pressed {
+                    PRESSED_COLOR
+                } else {
+                    RELEASED_COLOR
+                };
+
+                fill_rect(
+                    &mut surface,
+                    [center[0] - button_size / 2.0, center[1] - button_size / 2.0],
+                    [button_size, button_size],
+                    |_| color,
+                );
+            }
+        }
+    }
+}
+
+/// Clipped to the surface, so pads partly off screen are fine
+fn fill_rect(
+    surface: &mut DMatrixViewMut<Srgba<u8>>,
+    start: [f32; 2],
+    size: [f32; 2],
+    shade: impl Fn(Srgba<u8>) -> Srgba<u8>,
+) {
+    let start_x = start[0].max(0.0) as usize;
+    let start_y = start[1].max(0.0) as usize;
+    let end_x = ((start[0] + size[0]).max(0.0) as usize).min(surface.nrows());
+    let end_y = ((start[1] + size[1]).max(0.0) as usize).min(surface.ncols());
+
+    for x in start_x..end_x {
+        for y in start_y..end_y {
+            surface[(x, y)] = shade(surface[(x, y)]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::input::{
+        gamepad::GamepadInput,
+        registry::{gamepad_type, NES_GAMEPAD_TYPE},
+        Input, InputState,
+    };
+    use nalgebra::DMatrix;
+
+    #[test]
+    fn pressed_buttons_are_lit() {
+        let mut input_manager = InputManager::default();
+        let (gamepad_type_id, metadata) = gamepad_type(&NES_GAMEPAD_TYPE).registration();
+        input_manager.register_emulated_gamepad_type(gamepad_type_id.clone(), metadata);
+        input_manager.register_emulated_gamepad(0, gamepad_type_id.clone());
+        input_manager.register_emulated_gamepad(1, gamepad_type_id);
+
+        let a = Input::Gamepad(GamepadInput::FPadRight);
+        input_manager.set_emulated_input(0, a, InputState::Digital(true));
+
+        let mut config = InputDisplayConfig::default();
+        assert!(InputDisplay::capture(&input_manager, &config).is_none());
+
+        config.ports.insert(0);
+        config.position = [0.0, 0.0];
+        let input_display = InputDisplay::capture(&input_manager, &config).unwrap();
+        assert_eq!(input_display.pads.len(), 1);
+
+        let mut surface = DMatrix::from_element(320, 240, Srgba::new(0x90, 0x90, 0x90, 0xff));
+        input_display.draw(surface.as_view_mut());
+
+        for (button, pressed) in &input_display.pads[0].buttons {
+            let pixel = surface[(
+                (button.position[0] * PAD_SIZE[0]) as usize,
+                (button.position[1] * PAD_SIZE[1]) as usize,
+            )];
+
+            assert_eq!(*pressed, button.input == a);
+            assert_eq!(
+                pixel,
+                if *pressed {
+                    PRESSED_COLOR
+                } else {
+                    RELEASED_COLOR
+                }
+            );
+        }
+
+        // Nothing past the pad gets touched
+        assert_eq!(surface[(300, 200)], Srgba::new(0x90, 0x90, 0x90, 0xff));
+    }
+}
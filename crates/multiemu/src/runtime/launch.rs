@@ -1,5 +1,5 @@
 use crate::rom::{id::RomId, manager::RomManager, system::GameSystem};
-use std::sync::Arc;
+use std::{path::PathBuf, sync::Arc};
 
 pub trait Runtime {
     fn launch_gui(rom_manager: Arc<RomManager>);
@@ -7,5 +7,6 @@ pub trait Runtime {
         user_specified_roms: Vec<RomId>,
         forced_game_system: Option<GameSystem>,
         rom_manager: Arc<RomManager>,
+        av_dump_directory: Option<PathBuf>,
     );
 }
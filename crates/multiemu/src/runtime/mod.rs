@@ -1,4 +1,14 @@
+pub mod audio;
+pub mod av_dump;
+pub mod capture;
+pub mod executor;
+pub mod frame_compare;
+pub mod frame_presentation;
+pub mod input_display;
 pub mod launch;
 pub mod platform;
+pub mod power_profile;
 pub mod rendering_backend;
 pub mod timing_tracker;
+pub mod video_filter;
+pub mod video_preset;
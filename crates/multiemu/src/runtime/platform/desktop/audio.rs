@@ -0,0 +1,112 @@
+//! Plays what the machine makes through the default output device
+
+use crate::{runtime::audio::AdaptiveAudioBuffer, scheduler::AudioClock};
+use cpal::{
+    traits::{DeviceTrait, HostTrait, StreamTrait},
+    BuildStreamError, DefaultStreamConfigError, FromSample, PlayStreamError, SampleFormat,
+    SizedSample, Stream, StreamConfig,
+};
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use thiserror::Error;
+
+/// Least amount of audio kept queued, lower is snappier but the host has to keep up
+const MINIMUM_LATENCY: Duration = Duration::from_millis(40);
+/// Most the buffer grows to when the host keeps running dry
+const MAXIMUM_LATENCY: Duration = Duration::from_millis(250);
+
+#[derive(Debug, Error)]
+pub enum HostAudioError {
+    #[error("No audio output device")]
+    NoDevice,
+    #[error("Could not query the output device: {0}")]
+    DefaultConfig(#[from] DefaultStreamConfigError),
+    #[error("Could not open an output stream: {0}")]
+    BuildStream(#[from] BuildStreamError),
+    #[error("Could not start the output stream: {0}")]
+    PlayStream(#[from] PlayStreamError),
+    #[error("Output device wants {0} samples, which we do not produce")]
+    UnsupportedSampleFormat(SampleFormat),
+}
+
+/// Stops playing when dropped, which has to happen on the thread that opened it
+pub struct HostAudio {
+    _stream: Stream,
+    buffer: Arc<Mutex<AdaptiveAudioBuffer>>,
+    sample_rate: u32,
+}
+
+impl HostAudio {
+    /// Whatever gets played is reported to `clock`, see [crate::scheduler::Pacing::Audio]
+    pub fn open(clock: Arc<AudioClock>) -> Result<Self, HostAudioError> {
+        let device = cpal::default_host()
+            .default_output_device()
+            .ok_or(HostAudioError::NoDevice)?;
+        let supported_config = device.default_output_config()?;
+        let sample_format = supported_config.sample_format();
+        let config: StreamConfig = supported_config.into();
+        let sample_rate = config.sample_rate.0;
+
+        let latency = |latency: Duration| (sample_rate as f32 * latency.as_secs_f32()) as usize;
+        let mut buffer =
+            AdaptiveAudioBuffer::new(latency(MINIMUM_LATENCY), latency(MAXIMUM_LATENCY));
+        buffer.set_clock(clock, sample_rate);
+        let buffer = Arc::new(Mutex::new(buffer));
+
+        let stream = match sample_format {
+            SampleFormat::F32 => build_stream::<f32>(&device, &config, buffer.clone())?,
+            SampleFormat::I16 => build_stream::<i16>(&device, &config, buffer.clone())?,
+            SampleFormat::U16 => build_stream::<u16>(&device, &config, buffer.clone())?,
+            sample_format => return Err(HostAudioError::UnsupportedSampleFormat(sample_format)),
+        };
+        stream.play()?;
+
+        tracing::info!(
+            "Playing audio at {} samples per second on {} channels",
+            sample_rate,
+            config.channels
+        );
+
+        Ok(Self {
+            _stream: stream,
+            buffer,
+            sample_rate,
+        })
+    }
+
+    /// Shared with the output callback, so keep it locked as briefly as possible
+    pub fn buffer(&self) -> &Arc<Mutex<AdaptiveAudioBuffer>> {
+        &self.buffer
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+}
+
+fn build_stream<T: SizedSample + FromSample<f32>>(
+    device: &cpal::Device,
+    config: &StreamConfig,
+    buffer: Arc<Mutex<AdaptiveAudioBuffer>>,
+) -> Result<Stream, BuildStreamError> {
+    let channels = config.channels as usize;
+    // Kept around so the callback only allocates when the host asks for more than ever before
+    let mut mono = Vec::new();
+
+    device.build_output_stream(
+        config,
+        move |output: &mut [T], _| {
+            mono.resize(output.len() / channels, 0.0);
+            buffer.lock().unwrap().fill(&mut mono);
+
+            // Everything we make is mono, so every channel gets the same thing
+            for (frame, sample) in output.chunks_exact_mut(channels).zip(&mono) {
+                frame.fill(T::from_sample(*sample));
+            }
+        },
+        |err| tracing::error!("Audio output failed: {}", err),
+        None,
+    )
+}
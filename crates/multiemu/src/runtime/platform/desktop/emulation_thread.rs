@@ -0,0 +1,478 @@
+//! Runs the machine on its own thread so how fast it emulates does not depend on how often the compositor lets us redraw
+
+use super::audio::HostAudio;
+use crate::{
+    input::manager::InputManager,
+    machine::{
+        fault::{FaultReporter, MachineFault},
+        rewind::{RewindBuffer, DEFAULT_REWIND_INTERVAL},
+        Machine,
+    },
+    rom::system::GameSystem,
+    runtime::{
+        audio::{AdaptiveAudioBuffer, AudioMixer},
+        executor::{Executor, TaskHandle},
+        rendering_backend::DisplayComponentFramebuffer,
+        timing_tracker::TimingTracker,
+    },
+    scheduler::{Pacing, RunawayComponent, SchedulerStats, Watchdog, NORMAL_SPEED},
+};
+use num::rational::Ratio;
+use std::{
+    sync::{
+        mpsc::{channel, sync_channel, Receiver, Sender, SyncSender, TryRecvError, TrySendError},
+        Arc, Mutex, MutexGuard, TryLockError,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Runs taking longer than this most likely had the host sleep through them, which says nothing about how fast we are
+const SUSPEND_THRESHOLD: Duration = Duration::from_secs(1);
+
+/// How long dropping an [EmulationThread] waits for the run in progress, a component that never returns would hang us
+const STOP_TIMEOUT: Duration = Duration::from_secs(2);
+
+enum EmulationCommand {
+    Pause,
+    Resume,
+    /// See [crate::scheduler::Scheduler::lock_allotted_time]
+    LockAllottedTime(Option<Duration>),
+    SetSpeed(Ratio<u64>),
+    /// See [crate::scheduler::Scheduler::set_max_catch_up]
+    SetMaxCatchUp(Duration),
+    /// See [crate::scheduler::Scheduler::set_pacing]
+    SetPacing(Pacing),
+    /// See [crate::scheduler::Scheduler::set_profiling]
+    SetProfiling(bool),
+    /// Step back through the [RewindBuffer] instead of running
+    SetRewinding(bool),
+    /// See [RewindBuffer::set_budget]
+    SetRewindBudget(usize),
+    Stop,
+}
+
+/// Handed to the renderer every time the machine finishes a run
+pub struct CompletedFrame {
+    pub framebuffers: Vec<DisplayComponentFramebuffer>,
+    /// Where the time of the run went, if profiling
+    pub scheduler_stats: Option<SchedulerStats>,
+}
+
+/// Owns the task a [Machine] runs on, stopping it when dropped
+pub struct EmulationThread {
+    machine: Arc<Mutex<Machine>>,
+    /// Kept out here so input does not have to wait for a run to finish
+    pub input_manager: Arc<InputManager>,
+    pub system: GameSystem,
+    fault_reporter: Arc<FaultReporter>,
+    watchdog: Arc<Watchdog>,
+    command_sender: Sender<EmulationCommand>,
+    frame_receiver: Receiver<CompletedFrame>,
+    last_frame: Option<CompletedFrame>,
+    paused: bool,
+    allotted_time: Option<Duration>,
+    speed: Ratio<u64>,
+    max_catch_up: Duration,
+    pacing: Pacing,
+    profiling: bool,
+    rewinding: bool,
+    rewind_budget: usize,
+    /// [None] if there is no output device to play on
+    host_audio: Option<HostAudio>,
+    task: TaskHandle,
+}
+
+impl EmulationThread {
+    /// The machine starts running right away, so it should be completely set up before being handed over
+    pub fn spawn(machine: Machine, executor: &Executor) -> Self {
+        let input_manager = machine.input_manager.clone();
+        let system = machine.system;
+        let fault_reporter = machine.fault_reporter.clone();
+        let watchdog = machine.scheduler.watchdog().clone();
+        let speed = machine.speed();
+        let max_catch_up = machine.scheduler.max_catch_up();
+        let pacing = machine.scheduler.pacing();
+        let host_audio = match HostAudio::open(machine.scheduler.audio_clock().clone()) {
+            Ok(host_audio) => Some(host_audio),
+            Err(err) => {
+                tracing::warn!("Running without audio: {}", err);
+                None
+            }
+        };
+        let audio_output = host_audio.as_ref().map(|host_audio| {
+            (
+                host_audio.buffer().clone(),
+                AudioMixer::new(host_audio.sample_rate()),
+            )
+        });
+        let machine = Arc::new(Mutex::new(machine));
+        let (command_sender, command_receiver) = channel();
+        // The framebuffers are shared, so a frame the renderer has not picked up yet already shows the newest contents
+        let (frame_sender, frame_receiver) = sync_channel(1);
+
+        let thread_machine = machine.clone();
+        let task = executor.spawn("emulation", move |_| {
+            emulation_loop(thread_machine, command_receiver, frame_sender, audio_output)
+        });
+
+        Self {
+            machine,
+            input_manager,
+            system,
+            fault_reporter,
+            watchdog,
+            command_sender,
+            frame_receiver,
+            last_frame: None,
+            paused: false,
+            allotted_time: None,
+            speed,
+            max_catch_up,
+            pacing,
+            profiling: false,
+            rewinding: false,
+            rewind_budget: 0,
+            host_audio,
+            task,
+        }
+    }
+
+    /// Waits for the current run to finish, so keep this short
+    pub fn machine(&self) -> MutexGuard<'_, Machine> {
+        self.machine.lock().unwrap()
+    }
+
+    pub fn pause(&mut self) {
+        if !self.paused {
+            self.paused = true;
+            let _ = self.command_sender.send(EmulationCommand::Pause);
+        }
+    }
+
+    pub fn resume(&mut self) {
+        if self.paused {
+            self.paused = false;
+            let _ = self.command_sender.send(EmulationCommand::Resume);
+        }
+    }
+
+    pub fn lock_allotted_time(&mut self, allotted_time: Option<Duration>) {
+        if self.allotted_time != allotted_time {
+            self.allotted_time = allotted_time;
+            let _ = self
+                .command_sender
+                .send(EmulationCommand::LockAllottedTime(allotted_time));
+        }
+    }
+
+    /// See [crate::scheduler::Scheduler::set_speed]
+    pub fn set_speed(&mut self, speed: Ratio<u64>) {
+        if self.speed != speed {
+            self.speed = speed;
+            let _ = self.command_sender.send(EmulationCommand::SetSpeed(speed));
+        }
+    }
+
+    /// Switches to `speed`, or back to normal if we are already running at it
+    pub fn toggle_speed(&mut self, speed: Ratio<u64>) {
+        if self.speed == speed {
+            self.set_speed(NORMAL_SPEED);
+        } else {
+            self.set_speed(speed);
+        }
+    }
+
+    pub fn speed(&self) -> Ratio<u64> {
+        self.speed
+    }
+
+    /// See [crate::scheduler::Scheduler::set_max_catch_up]
+    pub fn set_max_catch_up(&mut self, max_catch_up: Duration) {
+        if self.max_catch_up != max_catch_up {
+            self.max_catch_up = max_catch_up;
+            let _ = self
+                .command_sender
+                .send(EmulationCommand::SetMaxCatchUp(max_catch_up));
+        }
+    }
+
+    /// See [crate::scheduler::Scheduler::set_pacing]
+    pub fn set_pacing(&mut self, pacing: Pacing) {
+        if self.pacing != pacing {
+            if pacing == Pacing::Audio && self.host_audio.is_none() {
+                tracing::warn!("Nothing is playing audio, pacing by the host clock instead");
+            }
+
+            self.pacing = pacing;
+            let _ = self
+                .command_sender
+                .send(EmulationCommand::SetPacing(pacing));
+        }
+    }
+
+    /// Has every frame from now on come with [CompletedFrame::scheduler_stats]
+    pub fn set_profiling(&mut self, profiling: bool) {
+        if self.profiling != profiling {
+            self.profiling = profiling;
+            let _ = self
+                .command_sender
+                .send(EmulationCommand::SetProfiling(profiling));
+        }
+    }
+
+    /// Steps the machine back through recent play instead of running it until turned off again
+    pub fn set_rewinding(&mut self, rewinding: bool) {
+        if self.rewinding != rewinding {
+            self.rewinding = rewinding;
+            let _ = self
+                .command_sender
+                .send(EmulationCommand::SetRewinding(rewinding));
+        }
+    }
+
+    /// Rewinding stays off until this is given something other than 0
+    pub fn set_rewind_budget(&mut self, rewind_budget: usize) {
+        if self.rewind_budget != rewind_budget {
+            self.rewind_budget = rewind_budget;
+            let _ = self
+                .command_sender
+                .send(EmulationCommand::SetRewindBudget(rewind_budget));
+        }
+    }
+
+    /// If there is somewhere for the machine's audio to go, otherwise [Pacing::Audio] falls back to the host clock
+    pub fn has_audio_output(&self) -> bool {
+        self.host_audio.is_some()
+    }
+
+    /// Fades out and drops the audio queued up, for after the machine jumps somewhere else like loading a state
+    pub fn flush_audio(&self) {
+        if let Some(host_audio) = &self.host_audio {
+            host_audio.buffer().lock().unwrap().flush();
+        }
+    }
+
+    /// Picks up whatever the machine finished since last time, returning if there was anything
+    pub fn receive_frame(&mut self) -> bool {
+        let mut received = false;
+
+        while let Ok(frame) = self.frame_receiver.try_recv() {
+            self.last_frame = Some(frame);
+            received = true;
+        }
+
+        received
+    }
+
+    /// The most recently received frame
+    pub fn frame(&self) -> Option<&CompletedFrame> {
+        self.last_frame.as_ref()
+    }
+
+    /// Does not wait for the machine, unlike going through [EmulationThread::machine]
+    pub fn fault(&self) -> Option<MachineFault> {
+        self.fault_reporter.fault()
+    }
+
+    /// Like [EmulationThread::machine], but gives up instead of waiting on a run that has not finished
+    pub fn try_machine(&self) -> Option<MutexGuard<'_, Machine>> {
+        self.machine.try_lock().ok()
+    }
+
+    /// Goes straight to the watchdog, since a component that never returns would have us wait on the machine forever
+    pub fn set_watchdog_budget(&self, budget: Option<Duration>) {
+        self.watchdog.set_budget(budget);
+    }
+
+    /// See [Watchdog::take_runaway]
+    pub fn take_runaway(&self) -> Option<RunawayComponent> {
+        self.watchdog.take_runaway()
+    }
+}
+
+impl Drop for EmulationThread {
+    fn drop(&mut self) {
+        let _ = self.command_sender.send(EmulationCommand::Stop);
+
+        tracing::debug!("Waiting for the emulation thread to stop");
+        if !self.task.wait(STOP_TIMEOUT) {
+            tracing::error!(
+                "Emulation thread did not stop within {:?}, leaving it behind",
+                STOP_TIMEOUT
+            );
+        }
+
+        match self.machine.try_lock() {
+            Ok(machine) => machine.shutdown(),
+            // Save RAM is worth trying to write out even if the thread panicked with the machine locked
+            Err(TryLockError::Poisoned(err)) => err.into_inner().shutdown(),
+            Err(TryLockError::WouldBlock) => {
+                tracing::error!(
+                    "The machine is still running, its save RAM could not be written out"
+                )
+            }
+        }
+    }
+}
+
+fn emulation_loop(
+    machine: Arc<Mutex<Machine>>,
+    command_receiver: Receiver<EmulationCommand>,
+    frame_sender: SyncSender<CompletedFrame>,
+    mut audio_output: Option<(Arc<Mutex<AdaptiveAudioBuffer>>, AudioMixer)>,
+) {
+    let mut timing_tracker = TimingTracker::default();
+    let mut paused = false;
+    let mut rewinding = false;
+    let mut rewind_buffer = RewindBuffer::new(0, DEFAULT_REWIND_INTERVAL);
+
+    loop {
+        // Nothing can happen while paused, so sleep until we are told otherwise
+        let command = if paused {
+            match command_receiver.recv() {
+                Ok(command) => Some(command),
+                Err(_) => return,
+            }
+        } else {
+            match command_receiver.try_recv() {
+                Ok(command) => Some(command),
+                Err(TryRecvError::Empty) => None,
+                Err(TryRecvError::Disconnected) => return,
+            }
+        };
+
+        match command {
+            Some(EmulationCommand::Pause) => {
+                machine.lock().unwrap().pause();
+                paused = true;
+                continue;
+            }
+            Some(EmulationCommand::Resume) => {
+                machine.lock().unwrap().resume();
+                paused = false;
+                // Time spent paused says nothing about how fast we run
+                timing_tracker.reset_frame_timings();
+                continue;
+            }
+            Some(EmulationCommand::LockAllottedTime(allotted_time)) => {
+                machine
+                    .lock()
+                    .unwrap()
+                    .scheduler
+                    .lock_allotted_time(allotted_time);
+                continue;
+            }
+            Some(EmulationCommand::SetSpeed(speed)) => {
+                machine.lock().unwrap().set_speed(speed);
+                continue;
+            }
+            Some(EmulationCommand::SetMaxCatchUp(max_catch_up)) => {
+                machine
+                    .lock()
+                    .unwrap()
+                    .scheduler
+                    .set_max_catch_up(max_catch_up);
+                continue;
+            }
+            Some(EmulationCommand::SetPacing(pacing)) => {
+                machine.lock().unwrap().scheduler.set_pacing(pacing);
+                continue;
+            }
+            Some(EmulationCommand::SetProfiling(profiling)) => {
+                machine.lock().unwrap().scheduler.set_profiling(profiling);
+                continue;
+            }
+            Some(EmulationCommand::SetRewinding(value)) => {
+                // What is queued up does not belong to where we are going
+                if rewinding != value {
+                    if let Some((audio_buffer, _)) = &audio_output {
+                        audio_buffer.lock().unwrap().flush();
+                    }
+                }
+
+                rewinding = value;
+                continue;
+            }
+            Some(EmulationCommand::SetRewindBudget(budget)) => {
+                rewind_buffer.set_budget(budget);
+                continue;
+            }
+            Some(EmulationCommand::Stop) => return,
+            None => {}
+        }
+
+        let run_start = Instant::now();
+
+        let (frame, allotted_time) = {
+            let mut machine = machine.lock().unwrap();
+
+            if rewinding {
+                // Sits on the oldest step once there is nothing further back
+                if let Err(err) = rewind_buffer.rewind(&mut machine) {
+                    tracing::error!("Could not rewind: {}", err);
+                    rewind_buffer.clear();
+                }
+            } else {
+                timing_tracker.frame_rendering_starting();
+                machine.run();
+                timing_tracker.frame_rendering_ending();
+
+                // Machines making sound are kept in step with how fast the host plays it instead of our timings
+                let drift_corrected = match &mut audio_output {
+                    Some((audio_buffer, audio_mixer))
+                        if machine.audio_components().next().is_some() =>
+                    {
+                        let samples = audio_mixer.mix(machine.frame_audio());
+                        let mut audio_buffer = audio_buffer.lock().unwrap();
+
+                        audio_buffer.push_samples(&samples);
+                        audio_buffer.correct_drift(&mut machine.scheduler);
+                        true
+                    }
+                    _ => false,
+                };
+
+                let time_taken = run_start.elapsed();
+                let average_timings = timing_tracker.average_frame_timings();
+
+                if time_taken > SUSPEND_THRESHOLD {
+                    tracing::info!(
+                        "Run took {:?}, assuming the host was suspended in the middle of it",
+                        time_taken
+                    );
+                    timing_tracker.reset_frame_timings();
+                } else if !drift_corrected && time_taken > average_timings {
+                    machine.scheduler.too_slow();
+                } else if !drift_corrected && time_taken < average_timings {
+                    machine.scheduler.too_fast();
+                }
+
+                if let Err(err) = rewind_buffer.ran(&machine) {
+                    tracing::error!("Could not capture a rewind step: {}", err);
+                }
+            }
+
+            (
+                CompletedFrame {
+                    framebuffers: machine
+                        .display_components()
+                        .map(|component_info| component_info.component.get_framebuffer())
+                        .collect(),
+                    scheduler_stats: machine.scheduler.stats().cloned(),
+                },
+                machine.frame_duration(),
+            )
+        };
+
+        match frame_sender.try_send(frame) {
+            Ok(()) | Err(TrySendError::Full(_)) => {}
+            Err(TrySendError::Disconnected(_)) => return,
+        }
+
+        // Don't let emulated time get ahead of real time
+        if let Some(remaining_time) = allotted_time.checked_sub(run_start.elapsed()) {
+            thread::sleep(remaining_time);
+        }
+    }
+}
@@ -0,0 +1,137 @@
+//! Machines running alongside the main one, each in a window of its own, for things like linking two handhelds
+//! together or watching two movies side by side
+//!
+//! The menu and hotkeys stay with the main window, these only draw and take the keyboard while focused
+
+use super::{emulation_thread::EmulationThread, winit::setup_window};
+use crate::{
+    config::GLOBAL_CONFIG,
+    input::{GamepadId, InputState},
+    machine::Machine,
+    runtime::{executor::Executor, rendering_backend::RenderingBackendState},
+};
+use std::sync::Arc;
+use winit::{
+    event::WindowEvent,
+    event_loop::ActiveEventLoop,
+    keyboard::PhysicalKey,
+    window::{Window, WindowId},
+};
+
+pub struct MachineInstance<RS: RenderingBackendState> {
+    window: Arc<Window>,
+    runtime_state: RS,
+    /// Only empty while being dropped, since the thread has to stop before the renderer lets go
+    emulation_thread: Option<EmulationThread>,
+    /// Set while the window is hidden, emulation stays paused until then
+    occluded: bool,
+    /// So the title is only changed once
+    faulted: bool,
+}
+
+impl<RS: RenderingBackendState<DisplayApiHandle = Arc<Window>>> MachineInstance<RS> {
+    /// The machine should be completely set up, like for [EmulationThread::spawn]
+    pub fn open(event_loop: &ActiveEventLoop, machine: Machine, executor: &Executor) -> Self {
+        let window = setup_window(event_loop, &format!("MultiEMU - {}", machine.system));
+
+        let mut runtime_state = RS::new(window.clone());
+        runtime_state.initialize_machine(&machine);
+        runtime_state.set_video_preset(
+            &GLOBAL_CONFIG
+                .read()
+                .unwrap()
+                .video_preset_for(machine.user_specified_roms[0]),
+        );
+
+        Self {
+            window,
+            runtime_state,
+            emulation_thread: Some(EmulationThread::spawn(machine, executor)),
+            occluded: false,
+            faulted: false,
+        }
+    }
+
+    pub fn window_id(&self) -> WindowId {
+        self.window.id()
+    }
+
+    /// Returns false once the window wants to be closed, `paused` being if the main window has the menu up
+    pub fn window_event(
+        &mut self,
+        event: WindowEvent,
+        paused: bool,
+        keyboard_gamepad_id: GamepadId,
+    ) -> bool {
+        let emulation_thread = self.emulation_thread.as_mut().unwrap();
+
+        match event {
+            WindowEvent::CloseRequested => {
+                return false;
+            }
+            WindowEvent::Resized(_) => {
+                self.runtime_state.surface_resized();
+            }
+            WindowEvent::Occluded(occluded) => {
+                self.occluded = occluded;
+
+                if occluded {
+                    emulation_thread.pause();
+                } else {
+                    self.window.request_redraw();
+                }
+            }
+            WindowEvent::KeyboardInput {
+                device_id: _,
+                event,
+                is_synthetic: false,
+            } if !paused => {
+                if let PhysicalKey::Code(key_code) = event.physical_key {
+                    emulation_thread.input_manager.insert_input(
+                        emulation_thread.system,
+                        keyboard_gamepad_id,
+                        key_code.try_into().unwrap(),
+                        InputState::Digital(event.state.is_pressed()),
+                    );
+                }
+            }
+            WindowEvent::RedrawRequested => {
+                if paused || self.occluded {
+                    emulation_thread.pause();
+                } else {
+                    emulation_thread.resume();
+                }
+
+                // The menu belongs to the main window, so the title is the only place left to say it
+                if emulation_thread.receive_frame() && !self.faulted {
+                    if let Some(fault) = emulation_thread.fault() {
+                        self.faulted = true;
+                        self.window.set_title(&format!("MultiEMU - {}", fault));
+                    }
+                }
+
+                if let Some(frame) = emulation_thread.frame() {
+                    self.runtime_state.redraw(&frame.framebuffers);
+                }
+
+                if self.runtime_state.display_data_lost() {
+                    self.runtime_state
+                        .initialize_machine(&emulation_thread.machine());
+                }
+
+                self.window.request_redraw();
+            }
+            _ => {}
+        }
+
+        true
+    }
+}
+
+impl<RS: RenderingBackendState> Drop for MachineInstance<RS> {
+    fn drop(&mut self) {
+        // Stops the emulation thread, like closing the main machine does
+        self.emulation_thread = None;
+        self.runtime_state.release_machine();
+    }
+}
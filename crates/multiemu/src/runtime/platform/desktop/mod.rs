@@ -1,14 +1,28 @@
 use crate::{
+    config::GLOBAL_CONFIG,
     gui::menu::MenuState,
-    rom::{id::RomId, manager::RomManager, system::GameSystem},
+    input::Input,
+    rom::{id::RomId, manager::RomManager, statistics::PlaySession, system::GameSystem},
     runtime::{
-        launch::Runtime, rendering_backend::RenderingBackendState, timing_tracker::TimingTracker,
+        executor::Executor, frame_presentation::FramePresentationPacer, launch::Runtime,
+        rendering_backend::RenderingBackendState,
     },
 };
-use ::winit::{event_loop::EventLoop, window::Window};
-use std::sync::Arc;
-use winit::{MachineContext, WindowingContext};
+use ::winit::{
+    event_loop::EventLoop,
+    window::{Window, WindowId},
+};
+use instance::MachineInstance;
+use std::{
+    collections::{BTreeSet, HashMap},
+    path::PathBuf,
+    sync::Arc,
+};
+use winit::{check_for_updates, MachineContext, WindowingContext};
 
+mod audio;
+mod emulation_thread;
+mod instance;
 pub mod renderer;
 mod winit;
 
@@ -16,8 +30,20 @@ pub struct PlatformRuntime<RS: RenderingBackendState> {
     menu: MenuState,
     windowing_context: Option<WindowingContext<RS>>,
     machine_context: Option<MachineContext>,
+    /// Machines running next to the main one in windows of their own, see [instance]
+    machine_instances: HashMap<WindowId, MachineInstance<RS>>,
     rom_manager: Arc<RomManager>,
-    timing_tracker: TimingTracker,
+    frame_presentation_pacer: FramePresentationPacer,
+    /// Background work that has to be stopped before we exit
+    executor: Executor,
+    /// Inputs currently held down, for detecting hotkeys
+    held_inputs: BTreeSet<Input>,
+    /// Set while the window is hidden or the app is suspended, emulation stays paused until then
+    suspended: bool,
+    /// Set while we sped the machine up for a tape, so we know to slow it back down
+    fast_loading: bool,
+    /// What is being played right now, if usage statistics are kept
+    play_session: Option<PlaySession>,
 }
 
 impl<RS: RenderingBackendState<DisplayApiHandle = Arc<Window>>> Runtime for PlatformRuntime<RS> {
@@ -26,10 +52,20 @@ impl<RS: RenderingBackendState<DisplayApiHandle = Arc<Window>>> Runtime for Plat
             menu: MenuState::default(),
             windowing_context: None,
             machine_context: None,
+            machine_instances: HashMap::default(),
             rom_manager,
-            timing_tracker: TimingTracker::default(),
+            frame_presentation_pacer: FramePresentationPacer::default(),
+            executor: Executor::default(),
+            held_inputs: BTreeSet::default(),
+            suspended: false,
+            fast_loading: false,
+            play_session: None,
         };
 
+        if GLOBAL_CONFIG.read().unwrap().network_access {
+            check_for_updates(&me.executor, &me.menu.update_status);
+        }
+
         let event_loop = EventLoop::new().unwrap();
         event_loop.run_app(&mut me).unwrap();
     }
@@ -38,6 +74,7 @@ impl<RS: RenderingBackendState<DisplayApiHandle = Arc<Window>>> Runtime for Plat
         user_specified_roms: Vec<RomId>,
         forced_system: Option<GameSystem>,
         rom_manager: Arc<RomManager>,
+        av_dump_directory: Option<PathBuf>,
     ) {
         let mut me = Self {
             menu: MenuState::default(),
@@ -45,9 +82,16 @@ impl<RS: RenderingBackendState<DisplayApiHandle = Arc<Window>>> Runtime for Plat
             machine_context: Some(MachineContext::Pending {
                 user_specified_roms,
                 forced_system,
+                av_dump_directory,
             }),
+            machine_instances: HashMap::default(),
             rom_manager,
-            timing_tracker: TimingTracker::default(),
+            frame_presentation_pacer: FramePresentationPacer::default(),
+            executor: Executor::default(),
+            held_inputs: BTreeSet::default(),
+            suspended: false,
+            fast_loading: false,
+            play_session: None,
         };
 
         let event_loop = EventLoop::new().unwrap();
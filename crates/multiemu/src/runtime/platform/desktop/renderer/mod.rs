@@ -1,2 +1,3 @@
 pub mod software;
+#[cfg(graphics_vulkan)]
 pub mod vulkan;
@@ -1,21 +1,41 @@
 use crate::{
     component::display::DisplayComponent,
     gui::software_rasterizer::SoftwareEguiRenderer,
+    host_cpu::multiversion,
     machine::Machine,
-    runtime::rendering_backend::{
-        DisplayComponentFramebuffer, DisplayComponentInitializationData, RenderingBackendState,
+    runtime::{
+        input_display::InputDisplay,
+        rendering_backend::{
+            DisplayComponentFramebuffer, DisplayComponentInitializationData, RenderingBackendState,
+        },
+        video_filter::VideoFilter,
+        video_preset::{ScalingMode, VideoPreset},
     },
 };
 use nalgebra::{DMatrixViewMut, Vector2};
 use palette::Srgba;
+use ringbuffer::{AllocRingBuffer, RingBuffer};
 use softbuffer::{Context, Surface};
-use std::{num::NonZero, sync::Arc};
+use std::{
+    num::NonZero,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 use winit::window::Window;
 
+/// Filters averaging more than this per frame get turned off, there is no point in a pretty picture if the game stutters
+const VIDEO_FILTER_TIME_BUDGET: Duration = Duration::from_millis(4);
+
 pub struct SoftwareRenderingRuntime {
     surface: Surface<Arc<Window>, Arc<Window>>,
     display_api_handle: Arc<Window>,
     egui_renderer: SoftwareEguiRenderer,
+    video_filter: VideoFilter,
+    video_filter_timings: AllocRingBuffer<Duration>,
+    scaling_mode: ScalingMode,
+    input_display: Option<InputDisplay>,
+    /// Waiting to be picked up by [RenderingBackendState::take_notice]
+    notice: Option<String>,
 }
 
 impl RenderingBackendState for SoftwareRenderingRuntime {
@@ -39,6 +59,11 @@ impl RenderingBackendState for SoftwareRenderingRuntime {
             surface,
             display_api_handle,
             egui_renderer: SoftwareEguiRenderer::default(),
+            video_filter: VideoFilter::default(),
+            video_filter_timings: AllocRingBuffer::new(32),
+            scaling_mode: ScalingMode::default(),
+            input_display: None,
+            notice: None,
         }
     }
 
@@ -54,81 +79,95 @@ impl RenderingBackendState for SoftwareRenderingRuntime {
             .unwrap();
     }
 
-    fn redraw(&mut self, machine: &Machine) {
+    fn redraw(&mut self, framebuffers: &[DisplayComponentFramebuffer]) {
         let window_dimensions = self.display_api_handle.inner_size();
         let window_dimensions =
             Vector2::new(window_dimensions.width, window_dimensions.height).cast::<usize>();
 
         // HACK: This only works with a single component
-        let component_info = machine.display_components().next().unwrap();
-        let DisplayComponentFramebuffer::Software(display_component_framebuffer) =
-            component_info.component.get_framebuffer()
+        let DisplayComponentFramebuffer::Software(display_component_framebuffer) = &framebuffers[0]
         else {
             unreachable!()
         };
         let display_component_framebuffer = display_component_framebuffer.lock().unwrap();
 
+        let filter_start = Instant::now();
+        let filtered_framebuffer = self.video_filter.apply(&display_component_framebuffer);
+
+        if filtered_framebuffer.is_some() {
+            self.track_video_filter_cost(filter_start.elapsed());
+        }
+
+        let display_component_framebuffer = filtered_framebuffer
+            .as_ref()
+            .unwrap_or(&display_component_framebuffer);
+
         // Skip rendering if impossible window size
         if window_dimensions.min() == 0 {
             return;
         }
 
         let mut surface_buffer = self.surface.buffer_mut().unwrap();
-        let mut surface_buffer_view = DMatrixViewMut::from_slice(
-            bytemuck::cast_slice_mut(surface_buffer.as_mut()),
-            window_dimensions.x as usize,
-            window_dimensions.y as usize,
-        );
+        let surface_pixels = bytemuck::cast_slice_mut::<_, Srgba<u8>>(surface_buffer.as_mut());
 
         // Clear the surface buffer
-        surface_buffer_view.fill(Srgba::<u8>::new(0, 0, 0, 0xff));
+        surface_pixels.fill(Srgba::new(0, 0, 0, 0xff));
 
         let component_display_buffer_size = Vector2::new(
             display_component_framebuffer.nrows(),
             display_component_framebuffer.ncols(),
-        )
-        .cast::<u16>();
+        );
+
+        let (viewport_offset, viewport_size) = self
+            .scaling_mode
+            .viewport(component_display_buffer_size, window_dimensions);
 
-        let scaling = window_dimensions
+        let scaling = viewport_size
             .cast::<f32>()
             .component_div(&component_display_buffer_size.cast::<f32>());
 
-        // Iterate over each pixel in the display component buffer
-        for x in 0..display_component_framebuffer.nrows() {
-            for y in 0..display_component_framebuffer.ncols() {
-                let source_pixel = display_component_framebuffer[(x, y)];
-
-                let dest_start = Vector2::new(x, y)
-                    .cast::<f32>()
-                    .component_mul(&scaling)
-                    .map(f32::round)
-                    .try_cast::<usize>()
-                    .unwrap()
-                    .zip_map(&window_dimensions, |dest_dim, window_dim| {
-                        dest_dim.min(window_dim)
-                    });
-
-                let dest_end = Vector2::new(x, y)
-                    .cast::<f32>()
-                    .add_scalar(1.0)
-                    .component_mul(&scaling)
-                    .map(f32::round)
-                    .try_cast::<usize>()
-                    .unwrap()
-                    .zip_map(&window_dimensions, |dest_dim, window_dim| {
-                        dest_dim.min(window_dim)
-                    });
-
-                // Fill the destination pixels with the source pixel
-                let mut destination_pixels = surface_buffer_view.view_mut(
-                    (dest_start.x, dest_start.y),
-                    (dest_end.x - dest_start.x, dest_end.y - dest_start.y),
-                );
-
-                destination_pixels.fill(source_pixel);
+        // Which source pixel lands on each column and line of the viewport
+        let columns = nearest_sources(component_display_buffer_size.x, scaling.x, viewport_size.x);
+        let lines = nearest_sources(component_display_buffer_size.y, scaling.y, viewport_size.y);
+
+        let source_pixels = display_component_framebuffer.as_slice();
+        let mut previous_line: Option<(usize, usize)> = None;
+
+        for (line, &source_line) in lines.iter().enumerate() {
+            let destination_start =
+                (viewport_offset.y + line) * window_dimensions.x + viewport_offset.x;
+
+            match previous_line {
+                // Lines scaled up are the same as the one above, so just copy that
+                Some((previous_start, previous_source_line))
+                    if previous_source_line == source_line =>
+                {
+                    surface_pixels.copy_within(
+                        previous_start..previous_start + columns.len(),
+                        destination_start,
+                    );
+                }
+                _ => {
+                    let source_start = source_line * component_display_buffer_size.x;
+
+                    scale_line(
+                        &source_pixels
+                            [source_start..source_start + component_display_buffer_size.x],
+                        &mut surface_pixels[destination_start..destination_start + columns.len()],
+                        &columns,
+                    );
+                    previous_line = Some((destination_start, source_line));
+                }
             }
         }
 
+        let surface_buffer_view =
+            DMatrixViewMut::from_slice(surface_pixels, window_dimensions.x, window_dimensions.y);
+
+        if let Some(input_display) = &self.input_display {
+            input_display.draw(surface_buffer_view);
+        }
+
         surface_buffer.present().unwrap();
     }
 
@@ -149,6 +188,34 @@ impl RenderingBackendState for SoftwareRenderingRuntime {
         surface_buffer.present().unwrap();
     }
 
+    fn present_black_frame(&mut self) {
+        let window_dimensions = self.display_api_handle.inner_size();
+
+        // Skip rendering if impossible window size
+        if window_dimensions.width == 0 || window_dimensions.height == 0 {
+            return;
+        }
+
+        let mut surface_buffer = self.surface.buffer_mut().unwrap();
+        bytemuck::cast_slice_mut::<_, Srgba<u8>>(surface_buffer.as_mut())
+            .fill(Srgba::new(0, 0, 0, 0xff));
+
+        surface_buffer.present().unwrap();
+    }
+
+    fn set_video_preset(&mut self, video_preset: &VideoPreset) {
+        self.set_video_filter(video_preset.video_filter);
+        self.scaling_mode = video_preset.scaling_mode;
+    }
+
+    fn set_input_display(&mut self, input_display: Option<InputDisplay>) {
+        self.input_display = input_display;
+    }
+
+    fn take_notice(&mut self) -> Option<String> {
+        self.notice.take()
+    }
+
     fn initialize_machine(&mut self, machine: &Machine) {
         for component_info in machine.display_components() {
             component_info
@@ -157,3 +224,66 @@ impl RenderingBackendState for SoftwareRenderingRuntime {
         }
     }
 }
+
+impl SoftwareRenderingRuntime {
+    fn set_video_filter(&mut self, video_filter: VideoFilter) {
+        tracing::info!("Using video filter {}", video_filter);
+
+        self.video_filter = video_filter;
+        self.video_filter_timings.clear();
+    }
+
+    fn track_video_filter_cost(&mut self, time_taken: Duration) {
+        self.video_filter_timings.push(time_taken);
+
+        // Give it a full window of samples before judging it
+        if !self.video_filter_timings.is_full() {
+            return;
+        }
+
+        let average_time_taken = self
+            .video_filter_timings
+            .iter()
+            .sum::<Duration>()
+            .checked_div(self.video_filter_timings.len() as u32)
+            .unwrap_or_default();
+
+        if average_time_taken > VIDEO_FILTER_TIME_BUDGET {
+            tracing::warn!(
+                "Video filter {} is taking {:?} per frame on average, disabling it",
+                self.video_filter,
+                average_time_taken
+            );
+
+            self.notice = Some(format!(
+                "Turned off the {} video filter, it took {:.1?} per frame where {:?} was allowed",
+                self.video_filter, average_time_taken, VIDEO_FILTER_TIME_BUDGET
+            ));
+            self.set_video_filter(VideoFilter::None);
+        }
+    }
+}
+
+/// The source pixel for every destination pixel along one axis, spans rounded the same way the viewport is
+fn nearest_sources(source_length: usize, scaling: f32, destination_length: usize) -> Vec<usize> {
+    let mut sources = Vec::with_capacity(destination_length);
+
+    for source in 0..source_length {
+        let start = (source as f32 * scaling).round() as usize;
+        let end = ((source + 1) as f32 * scaling).round() as usize;
+
+        sources.extend(std::iter::repeat_n(source, end.saturating_sub(start)));
+    }
+
+    sources.truncate(destination_length);
+    sources
+}
+
+multiversion! {
+    /// Nearest neighbor scales a single line
+    fn scale_line(source: &[Srgba<u8>], destination: &mut [Srgba<u8>], columns: &[usize]) {
+        for (destination, &column) in destination.iter_mut().zip(columns) {
+            *destination = source[column];
+        }
+    }
+}
@@ -0,0 +1,206 @@
+use crate::runtime::video_filter::{VideoFilter, VideoFilterParameters};
+use std::sync::Arc;
+use vulkano::{
+    buffer::BufferContents,
+    command_buffer::{allocator::CommandBufferAllocator, AutoCommandBufferBuilder},
+    descriptor_set::{
+        allocator::StandardDescriptorSetAllocator, PersistentDescriptorSet, WriteDescriptorSet,
+    },
+    device::Device,
+    format::Format,
+    image::{
+        sampler::{Sampler, SamplerCreateInfo},
+        view::ImageView,
+        Image, ImageCreateInfo, ImageType, ImageUsage,
+    },
+    memory::allocator::{AllocationCreateInfo, StandardMemoryAllocator},
+    pipeline::{
+        compute::ComputePipelineCreateInfo, layout::PipelineDescriptorSetLayoutCreateInfo,
+        ComputePipeline, Pipeline, PipelineBindPoint, PipelineLayout,
+        PipelineShaderStageCreateInfo,
+    },
+    shader::{spirv::bytes_to_words, ShaderModule, ShaderModuleCreateInfo},
+};
+
+/// Must match the workgroup size declared in the shaders
+const WORKGROUP_SIZE: u32 = 8;
+
+#[derive(BufferContents)]
+#[repr(C)]
+struct VideoFilterPushConstants {
+    scanline_strength: f32,
+    mask_strength: f32,
+}
+
+/// Resources that only need to be remade when the component hands us a different image
+struct VideoFilterTarget {
+    source_image: Arc<Image>,
+    destination_image: Arc<Image>,
+    descriptor_set: Arc<PersistentDescriptorSet>,
+}
+
+/// A [VideoFilter] running as a compute shader over the component image before it gets blitted to the swapchain
+pub struct VulkanVideoFilter {
+    scale: u32,
+    pipeline: Arc<ComputePipeline>,
+    sampler: Arc<Sampler>,
+    memory_allocator: Arc<StandardMemoryAllocator>,
+    descriptor_set_allocator: StandardDescriptorSetAllocator,
+    target: Option<VideoFilterTarget>,
+}
+
+impl VulkanVideoFilter {
+    /// Returns [None] if the filter has no compute implementation
+    pub fn new(
+        device: Arc<Device>,
+        memory_allocator: Arc<StandardMemoryAllocator>,
+        video_filter: VideoFilter,
+    ) -> Option<Self> {
+        let shader_code: &[u8] = match video_filter {
+            VideoFilter::Xbr2x => include_bytes!(concat!(env!("OUT_DIR"), "/xbr2x.spv")),
+            VideoFilter::CrtLite => include_bytes!(concat!(env!("OUT_DIR"), "/crt_lite.spv")),
+            _ => return None,
+        };
+
+        let shader_module = unsafe {
+            ShaderModule::new(
+                device.clone(),
+                ShaderModuleCreateInfo::new(&bytes_to_words(shader_code).unwrap()),
+            )
+        }
+        .unwrap();
+        let stage = PipelineShaderStageCreateInfo::new(shader_module.entry_point("main").unwrap());
+        let layout = PipelineLayout::new(
+            device.clone(),
+            PipelineDescriptorSetLayoutCreateInfo::from_stages([&stage])
+                .into_pipeline_layout_create_info(device.clone())
+                .unwrap(),
+        )
+        .unwrap();
+        let pipeline = ComputePipeline::new(
+            device.clone(),
+            None,
+            ComputePipelineCreateInfo::stage_layout(stage, layout),
+        )
+        .unwrap();
+
+        Some(Self {
+            scale: video_filter.scale() as u32,
+            pipeline,
+            sampler: Sampler::new(device.clone(), SamplerCreateInfo::default()).unwrap(),
+            memory_allocator,
+            descriptor_set_allocator: StandardDescriptorSetAllocator::new(
+                device,
+                Default::default(),
+            ),
+            target: None,
+        })
+    }
+
+    /// Records the filter pass and returns the image to present instead of the source image
+    pub fn record<L, A: CommandBufferAllocator>(
+        &mut self,
+        command_buffer: &mut AutoCommandBufferBuilder<L, A>,
+        source_image: Arc<Image>,
+        parameters: VideoFilterParameters,
+    ) -> Arc<Image> {
+        if !self
+            .target
+            .as_ref()
+            .is_some_and(|target| Arc::ptr_eq(&target.source_image, &source_image))
+        {
+            self.target = Some(self.create_target(source_image));
+        }
+        let target = self.target.as_ref().unwrap();
+
+        let [width, height, _] = target.destination_image.extent();
+
+        command_buffer
+            .bind_pipeline_compute(self.pipeline.clone())
+            .unwrap()
+            .bind_descriptor_sets(
+                PipelineBindPoint::Compute,
+                self.pipeline.layout().clone(),
+                0,
+                target.descriptor_set.clone(),
+            )
+            .unwrap();
+
+        // Filters without parameters get their push constants optimized out
+        if !self.pipeline.layout().push_constant_ranges().is_empty() {
+            command_buffer
+                .push_constants(
+                    self.pipeline.layout().clone(),
+                    0,
+                    VideoFilterPushConstants {
+                        scanline_strength: parameters.scanline_strength,
+                        mask_strength: parameters.mask_strength,
+                    },
+                )
+                .unwrap();
+        }
+
+        command_buffer
+            .dispatch([
+                width.div_ceil(WORKGROUP_SIZE),
+                height.div_ceil(WORKGROUP_SIZE),
+                1,
+            ])
+            .unwrap();
+
+        target.destination_image.clone()
+    }
+
+    /// Drops the target, which holds onto the image it was last recorded with
+    pub fn release_source(&mut self) {
+        self.target = None;
+    }
+
+    fn create_target(&self, source_image: Arc<Image>) -> VideoFilterTarget {
+        let [width, height, _] = source_image.extent();
+
+        tracing::debug!(
+            "Creating {}x{} video filter target",
+            width * self.scale,
+            height * self.scale
+        );
+
+        let destination_image = Image::new(
+            self.memory_allocator.clone(),
+            ImageCreateInfo {
+                image_type: ImageType::Dim2d,
+                // sRGB formats usually can't be storage images, the blit takes care of encoding
+                format: Format::R8G8B8A8_UNORM,
+                extent: [width * self.scale, height * self.scale, 1],
+                usage: ImageUsage::STORAGE | ImageUsage::TRANSFER_SRC,
+                ..Default::default()
+            },
+            AllocationCreateInfo::default(),
+        )
+        .unwrap();
+
+        let descriptor_set = PersistentDescriptorSet::new(
+            &self.descriptor_set_allocator,
+            self.pipeline.layout().set_layouts()[0].clone(),
+            [
+                WriteDescriptorSet::image_view(
+                    0,
+                    ImageView::new_default(source_image.clone()).unwrap(),
+                ),
+                WriteDescriptorSet::sampler(1, self.sampler.clone()),
+                WriteDescriptorSet::image_view(
+                    2,
+                    ImageView::new_default(destination_image.clone()).unwrap(),
+                ),
+            ],
+            [],
+        )
+        .unwrap();
+
+        VideoFilterTarget {
+            source_image,
+            destination_image,
+            descriptor_set,
+        }
+    }
+}
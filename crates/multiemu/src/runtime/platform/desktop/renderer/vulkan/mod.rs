@@ -1,37 +1,69 @@
+use super::software::SoftwareRenderingRuntime;
 use crate::{
     component::display::DisplayComponent,
     config::GLOBAL_CONFIG,
     machine::Machine,
-    runtime::rendering_backend::{
-        DisplayComponentFramebuffer, DisplayComponentInitializationData, RenderingBackendState,
+    runtime::{
+        input_display::InputDisplay,
+        rendering_backend::{
+            DisplayComponentFramebuffer, DisplayComponentInitializationData, RenderingBackendState,
+        },
+        video_filter::{VideoFilter, VideoFilterParameters},
+        video_preset::{ScalingMode, VideoPreset},
     },
 };
+use filter::VulkanVideoFilter;
 use nalgebra::Vector2;
-use std::sync::Arc;
+use std::{error::Error, sync::Arc};
 use vulkano::{
     command_buffer::{
         allocator::StandardCommandBufferAllocator, AutoCommandBufferBuilder, BlitImageInfo,
-        CommandBufferUsage,
+        ClearColorImageInfo, CommandBufferUsage, PrimaryCommandBufferAbstract,
     },
     device::{
-        physical::PhysicalDeviceType, Device, DeviceCreateInfo, DeviceExtensions, Queue,
-        QueueCreateInfo, QueueFlags,
+        physical::PhysicalDeviceType, Device, DeviceCreateInfo, DeviceExtensions, DeviceOwned,
+        Queue, QueueCreateInfo, QueueFlags,
     },
+    format::ClearColorValue,
     image::{sampler::Filter, view::ImageView, Image, ImageLayout, ImageUsage},
     instance::{Instance, InstanceCreateFlags, InstanceCreateInfo},
     memory::allocator::StandardMemoryAllocator,
     render_pass::{Framebuffer, FramebufferCreateInfo, RenderPass},
     single_pass_renderpass,
     swapchain::{
-        acquire_next_image, PresentMode, Surface, Swapchain, SwapchainCreateInfo,
-        SwapchainPresentInfo,
+        acquire_next_image, PresentMode, Surface, Swapchain, SwapchainAcquireFuture,
+        SwapchainCreateInfo, SwapchainPresentInfo,
     },
     sync::GpuFuture,
     Validated, VulkanError, VulkanLibrary,
 };
 use winit::window::Window;
 
+mod filter;
+pub mod surface;
+
+/// How many times the device gets built again after being lost before we give up on vulkan
+const MAX_DEVICE_RECOVERIES: u32 = 3;
+
 pub struct VulkanRenderingRuntime {
+    backend: Backend,
+    display_api_handle: Arc<Window>,
+    video_preset: VideoPreset,
+    /// Set when display components are holding resources of a device we no longer have
+    display_data_lost: bool,
+    device_recoveries: u32,
+    /// Waiting to be picked up by [RenderingBackendState::take_notice]
+    notice: Option<String>,
+}
+
+enum Backend {
+    Vulkan(VulkanContext),
+    /// Vulkan could not be brought back, so we draw on the cpu like the software backend does
+    Software(SoftwareRenderingRuntime),
+}
+
+/// Everything that has to be made again from scratch when the device is lost
+struct VulkanContext {
     instance: Arc<Instance>,
     surface: Arc<Surface>,
     device: Arc<Device>,
@@ -46,18 +78,229 @@ pub struct VulkanRenderingRuntime {
     swapchain_images: Vec<Arc<Image>>,
     recreate_swapchain: bool,
     display_api_handle: Arc<Window>,
+    video_filter: Option<VulkanVideoFilter>,
 }
 
 impl RenderingBackendState for VulkanRenderingRuntime {
     type DisplayApiHandle = Arc<Window>;
 
     fn new(display_api_handle: Self::DisplayApiHandle) -> Self {
+        let backend = match VulkanContext::new(display_api_handle.clone()) {
+            Ok(context) => Backend::Vulkan(context),
+            Err(err) => {
+                tracing::error!(
+                    "Could not set up vulkan, falling back to software rendering: {}",
+                    err
+                );
+
+                Backend::Software(SoftwareRenderingRuntime::new(display_api_handle.clone()))
+            }
+        };
+
+        Self {
+            backend,
+            display_api_handle,
+            video_preset: VideoPreset::default(),
+            display_data_lost: false,
+            device_recoveries: 0,
+            notice: None,
+        }
+    }
+
+    fn release_machine(&mut self) {
+        if let Backend::Vulkan(context) = &mut self.backend {
+            // Dropping the future of the last frame waits for the gpu to be done with the images it drew from
+            context.previous_frame_future =
+                Some(vulkano::sync::now(context.device.clone()).boxed());
+
+            if let Some(video_filter) = &mut context.video_filter {
+                video_filter.release_source();
+            }
+        }
+    }
+
+    fn surface_resized(&mut self) {
+        match &mut self.backend {
+            Backend::Vulkan(context) => context.recreate_swapchain = true,
+            Backend::Software(software) => software.surface_resized(),
+        }
+    }
+
+    fn redraw(&mut self, framebuffers: &[DisplayComponentFramebuffer]) {
+        let result = match &mut self.backend {
+            Backend::Vulkan(context) => {
+                // HACK: This only works with a single component
+                let DisplayComponentFramebuffer::Vulkan(component_framebuffer) =
+                    framebuffers[0].clone()
+                else {
+                    tracing::trace!("Skipping a frame that is not on the gpu");
+                    return;
+                };
+
+                // Frames drawn before the device was lost still point at the old one
+                if component_framebuffer.device() != &context.device {
+                    tracing::trace!("Skipping a frame drawn on a lost device");
+                    return;
+                }
+
+                context.redraw(
+                    component_framebuffer,
+                    self.video_preset.video_filter_parameters,
+                    self.video_preset.scaling_mode,
+                )
+            }
+            Backend::Software(software) => {
+                if !framebuffers.iter().all(|framebuffer| {
+                    matches!(framebuffer, DisplayComponentFramebuffer::Software(_))
+                }) {
+                    tracing::trace!("Skipping a frame drawn before falling back to software");
+                    return;
+                }
+
+                software.redraw(framebuffers);
+                Ok(())
+            }
+        };
+
+        if let Err(err) = result {
+            self.recover(err);
+        }
+    }
+
+    fn redraw_menu(&mut self, egui_context: &egui::Context, full_output: egui::FullOutput) {
+        if let Backend::Software(software) = &mut self.backend {
+            software.redraw_menu(egui_context, full_output);
+        }
+    }
+
+    fn present_black_frame(&mut self) {
+        let result = match &mut self.backend {
+            Backend::Vulkan(context) => context.present_black_frame(),
+            Backend::Software(software) => {
+                software.present_black_frame();
+                Ok(())
+            }
+        };
+
+        if let Err(err) = result {
+            self.recover(err);
+        }
+    }
+
+    fn set_video_preset(&mut self, video_preset: &VideoPreset) {
+        self.video_preset = video_preset.clone();
+
+        match &mut self.backend {
+            Backend::Vulkan(context) => {
+                context.set_video_filter(video_preset.video_filter);
+
+                if context.video_filter.is_none() && video_preset.video_filter != VideoFilter::None
+                {
+                    self.notice = Some(format!(
+                        "The {} video filter only runs on the software renderer, drawing unfiltered",
+                        video_preset.video_filter
+                    ));
+                }
+            }
+            Backend::Software(software) => software.set_video_preset(video_preset),
+        }
+    }
+
+    /// TODO: Only drawn once fallen back to software, the gpu path has nothing to draw it with yet
+    fn set_input_display(&mut self, input_display: Option<InputDisplay>) {
+        if let Backend::Software(software) = &mut self.backend {
+            software.set_input_display(input_display);
+        }
+    }
+
+    fn initialize_machine(&mut self, machine: &Machine) {
+        self.display_data_lost = false;
+
+        let context = match &mut self.backend {
+            Backend::Vulkan(context) => context,
+            Backend::Software(software) => {
+                software.initialize_machine(machine);
+                return;
+            }
+        };
+
+        for (component_info, queue) in machine
+            .display_components()
+            .zip(context.queues_for_components.iter().cycle().cloned())
+        {
+            component_info
+                .component
+                .set_display_data(DisplayComponentInitializationData::Vulkan(
+                    VulkanDisplayComponentInitializationData {
+                        device: context.device.clone(),
+                        queue,
+                        memory_allocator: context.memory_allocator.clone(),
+                        command_buffer_allocator: context.command_buffer_allocator.clone(),
+                    },
+                ))
+        }
+    }
+
+    fn display_data_lost(&mut self) -> bool {
+        std::mem::take(&mut self.display_data_lost)
+    }
+
+    fn take_notice(&mut self) -> Option<String> {
+        match &mut self.backend {
+            Backend::Vulkan(_) => self.notice.take(),
+            Backend::Software(software) => self.notice.take().or_else(|| software.take_notice()),
+        }
+    }
+}
+
+impl VulkanRenderingRuntime {
+    /// Gets drawing going again after `error`, starting over on a new device or on the cpu if it has to
+    fn recover(&mut self, error: VulkanError) {
+        let Backend::Vulkan(context) = &mut self.backend else {
+            return;
+        };
+
+        if error == VulkanError::SurfaceLost {
+            tracing::warn!("Lost the window surface, creating it again");
+
+            match context.recreate_surface() {
+                Ok(()) => return,
+                Err(err) => tracing::error!("Could not create the window surface again: {}", err),
+            }
+        } else {
+            tracing::error!("Vulkan failed with {}, creating the device again", error);
+        }
+
+        // Whatever happens next, display components are holding onto the old device
+        self.display_data_lost = true;
+
+        while self.device_recoveries < MAX_DEVICE_RECOVERIES {
+            self.device_recoveries += 1;
+
+            match VulkanContext::new(self.display_api_handle.clone()) {
+                Ok(mut context) => {
+                    context.set_video_filter(self.video_preset.video_filter);
+                    self.backend = Backend::Vulkan(context);
+                    return;
+                }
+                Err(err) => tracing::error!("Could not set up vulkan again: {}", err),
+            }
+        }
+
+        tracing::error!("Giving up on vulkan, falling back to software rendering");
+
+        let mut software = SoftwareRenderingRuntime::new(self.display_api_handle.clone());
+        software.set_video_preset(&self.video_preset);
+        self.backend = Backend::Software(software);
+    }
+}
+
+impl VulkanContext {
+    fn new(display_api_handle: Arc<Window>) -> Result<Self, Box<dyn Error>> {
         let window_dimensions = display_api_handle.inner_size();
         let window_dimensions = Vector2::new(window_dimensions.width, window_dimensions.height);
 
-        let global_config_guard = GLOBAL_CONFIG.read().unwrap();
-
-        let library = VulkanLibrary::new().unwrap();
+        let library = VulkanLibrary::new()?;
 
         tracing::info!("Found vulkan {} implementation", library.api_version());
 
@@ -69,16 +312,14 @@ impl RenderingBackendState for VulkanRenderingRuntime {
                 enabled_extensions: required_extensions,
                 ..Default::default()
             },
-        )
-        .unwrap();
-        let surface = Surface::from_window(instance.clone(), display_api_handle.clone()).unwrap();
+        )?;
+        let surface = Surface::from_window(instance.clone(), display_api_handle.clone())?;
         let device_extensions = DeviceExtensions {
             khr_swapchain: true,
             ..DeviceExtensions::empty()
         };
         let (physical_device, queue_family_index) = instance
-            .enumerate_physical_devices()
-            .unwrap()
+            .enumerate_physical_devices()?
             .filter(|p| p.supported_extensions().contains(&device_extensions))
             .filter_map(|p| {
                 p.queue_family_properties()
@@ -98,7 +339,7 @@ impl RenderingBackendState for VulkanRenderingRuntime {
                 PhysicalDeviceType::Other => 4,
                 _ => 5,
             })
-            .unwrap();
+            .ok_or("No device can draw to the window")?;
 
         tracing::info!(
             "Using device: {} (type: {:?})",
@@ -116,8 +357,7 @@ impl RenderingBackendState for VulkanRenderingRuntime {
                 }],
                 ..Default::default()
             },
-        )
-        .unwrap();
+        )?;
         let queues: Vec<_> = queues.collect();
 
         tracing::info!("Using {} queue(s)", queues.len());
@@ -132,12 +372,10 @@ impl RenderingBackendState for VulkanRenderingRuntime {
         let (swapchain, swapchain_images) = {
             let surface_capabilities = device
                 .physical_device()
-                .surface_capabilities(&surface, Default::default())
-                .unwrap();
+                .surface_capabilities(&surface, Default::default())?;
             let image_format = device
                 .physical_device()
-                .surface_formats(&surface, Default::default())
-                .unwrap()[0]
+                .surface_formats(&surface, Default::default())?[0]
                 .0;
 
             Swapchain::new(
@@ -153,15 +391,10 @@ impl RenderingBackendState for VulkanRenderingRuntime {
                         .into_iter()
                         .next()
                         .unwrap(),
-                    present_mode: if global_config_guard.vsync {
-                        PresentMode::Fifo
-                    } else {
-                        PresentMode::Immediate
-                    },
+                    present_mode: present_mode(),
                     ..Default::default()
                 },
-            )
-            .unwrap()
+            )?
         };
         let memory_allocator = Arc::new(StandardMemoryAllocator::new_default(device.clone()));
         let command_buffer_allocator = Arc::new(StandardCommandBufferAllocator::new(
@@ -183,28 +416,11 @@ impl RenderingBackendState for VulkanRenderingRuntime {
                 color: [color],
                 depth_stencil: {}
             }
-        )
-        .unwrap();
+        )?;
 
-        let framebuffers: Vec<Arc<Framebuffer>> = swapchain_images
-            .iter()
-            .map(|image| {
-                let view = ImageView::new_default(image.clone()).unwrap();
+        let framebuffers = create_framebuffers(&render_pass, &swapchain_images);
 
-                Framebuffer::new(
-                    render_pass.clone(),
-                    FramebufferCreateInfo {
-                        attachments: vec![view.clone()],
-                        ..Default::default()
-                    },
-                )
-                .unwrap()
-            })
-            .collect();
-
-        drop(global_config_guard);
-
-        Self {
+        Ok(Self {
             previous_frame_future: Some(vulkano::sync::now(device.clone()).boxed()),
             instance,
             surface,
@@ -219,26 +435,144 @@ impl RenderingBackendState for VulkanRenderingRuntime {
             swapchain_images,
             recreate_swapchain: false,
             display_api_handle,
+            video_filter: None,
+        })
+    }
+
+    fn redraw(
+        &mut self,
+        component_framebuffer: Arc<Image>,
+        video_filter_parameters: VideoFilterParameters,
+        scaling_mode: ScalingMode,
+    ) -> Result<(), VulkanError> {
+        let Some((image_index, acquire_future)) = self.acquire_swapchain_image()? else {
+            return Ok(());
+        };
+
+        let swapchain_image = self.swapchain_images[image_index as usize].clone();
+
+        let mut command_buffer = AutoCommandBufferBuilder::primary(
+            &self.command_buffer_allocator,
+            self.gui_queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+
+        let component_framebuffer = match &mut self.video_filter {
+            Some(video_filter) => video_filter.record(
+                &mut command_buffer,
+                component_framebuffer,
+                video_filter_parameters,
+            ),
+            None => component_framebuffer,
+        };
+
+        let source_extent = component_framebuffer.extent();
+        let destination_extent = swapchain_image.extent();
+        let (viewport_offset, viewport_size) = scaling_mode.viewport(
+            Vector2::new(source_extent[0], source_extent[1]).cast(),
+            Vector2::new(destination_extent[0], destination_extent[1]).cast(),
+        );
+        let viewport_offset = viewport_offset.cast::<u32>();
+        let viewport_end = viewport_offset + viewport_size.cast::<u32>();
+
+        // Whatever the picture does not cover would be left over from the last frame
+        if scaling_mode != ScalingMode::Stretch {
+            command_buffer
+                .clear_color_image(ClearColorImageInfo {
+                    clear_value: ClearColorValue::Float([0.0, 0.0, 0.0, 1.0]),
+                    ..ClearColorImageInfo::image(swapchain_image.clone())
+                })
+                .unwrap();
         }
+
+        let mut blit_image_info =
+            BlitImageInfo::images(component_framebuffer, swapchain_image.clone());
+        blit_image_info.regions[0].dst_offsets = [
+            [viewport_offset.x, viewport_offset.y, 0],
+            [viewport_end.x, viewport_end.y, 1],
+        ];
+
+        command_buffer
+            .blit_image(BlitImageInfo {
+                src_image_layout: ImageLayout::TransferSrcOptimal,
+                dst_image_layout: ImageLayout::TransferDstOptimal,
+                filter: Filter::Nearest,
+                ..blit_image_info
+            })
+            .unwrap();
+
+        self.present(command_buffer.build().unwrap(), image_index, acquire_future)
     }
 
-    fn surface_resized(&mut self) {
-        self.recreate_swapchain = true;
+    fn present_black_frame(&mut self) -> Result<(), VulkanError> {
+        let Some((image_index, acquire_future)) = self.acquire_swapchain_image()? else {
+            return Ok(());
+        };
+
+        let mut command_buffer = AutoCommandBufferBuilder::primary(
+            &self.command_buffer_allocator,
+            self.gui_queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+
+        command_buffer
+            .clear_color_image(ClearColorImageInfo {
+                clear_value: ClearColorValue::Float([0.0, 0.0, 0.0, 1.0]),
+                ..ClearColorImageInfo::image(self.swapchain_images[image_index as usize].clone())
+            })
+            .unwrap();
+
+        self.present(command_buffer.build().unwrap(), image_index, acquire_future)
+    }
+
+    fn set_video_filter(&mut self, video_filter: VideoFilter) {
+        self.video_filter = VulkanVideoFilter::new(
+            self.device.clone(),
+            self.memory_allocator.clone(),
+            video_filter,
+        );
+
+        if self.video_filter.is_none() && video_filter != VideoFilter::None {
+            tracing::warn!(
+                "Video filter {} has no vulkan implementation, drawing unfiltered",
+                video_filter
+            );
+        }
     }
 
-    fn redraw(&mut self, machine: &Machine) {
+    /// Makes a new surface and swapchain on the same device, for when the old surface was lost
+    fn recreate_surface(&mut self) -> Result<(), Box<dyn Error>> {
         let window_dimensions = self.display_api_handle.inner_size();
         let window_dimensions = Vector2::new(window_dimensions.width, window_dimensions.height);
 
-        let global_config_guard = GLOBAL_CONFIG.read().unwrap();
-        // HACK: This only works with a single component
-        let component_info = machine.display_components().next().unwrap();
+        let surface = Surface::from_window(self.instance.clone(), self.display_api_handle.clone())?;
+        let (swapchain, swapchain_images) = Swapchain::new(
+            self.device.clone(),
+            surface.clone(),
+            SwapchainCreateInfo {
+                image_extent: window_dimensions.into(),
+                present_mode: present_mode(),
+                ..self.swapchain.create_info()
+            },
+        )?;
 
-        let DisplayComponentFramebuffer::Vulkan(component_framebuffer) =
-            component_info.component.get_framebuffer()
-        else {
-            unreachable!()
-        };
+        self.framebuffers = create_framebuffers(&self.render_pass, &swapchain_images);
+        self.surface = surface;
+        self.swapchain = swapchain;
+        self.swapchain_images = swapchain_images;
+        self.recreate_swapchain = false;
+
+        Ok(())
+    }
+
+    /// Recreates the swapchain if needed and grabs the next image, returning [None] if there is nothing to draw on
+    fn acquire_swapchain_image(
+        &mut self,
+    ) -> Result<Option<(u32, SwapchainAcquireFuture)>, VulkanError> {
+        let window_dimensions = self.display_api_handle.inner_size();
+        let window_dimensions = Vector2::new(window_dimensions.width, window_dimensions.height);
 
         self.previous_frame_future
             .as_mut()
@@ -247,7 +581,7 @@ impl RenderingBackendState for VulkanRenderingRuntime {
 
         // Skip rendering if impossible window size
         if window_dimensions.min() == 0 {
-            return;
+            return Ok(None);
         }
 
         if self.recreate_swapchain {
@@ -257,61 +591,38 @@ impl RenderingBackendState for VulkanRenderingRuntime {
                 .swapchain
                 .recreate(SwapchainCreateInfo {
                     image_extent: window_dimensions.into(),
-                    present_mode: if global_config_guard.vsync {
-                        PresentMode::Fifo
-                    } else {
-                        PresentMode::Immediate
-                    },
+                    present_mode: present_mode(),
                     ..self.swapchain.create_info()
                 })
-                .expect("Failed to recreate swapchain");
-
-            let new_framebuffers = new_images
-                .iter()
-                .map(|image| {
-                    let view = ImageView::new_default(image.clone()).unwrap();
-                    Framebuffer::new(
-                        self.render_pass.clone(),
-                        FramebufferCreateInfo {
-                            attachments: vec![view],
-                            ..Default::default()
-                        },
-                    )
-                    .unwrap()
-                })
-                .collect::<Vec<_>>();
+                .map_err(Validated::unwrap)?;
 
+            self.framebuffers = create_framebuffers(&self.render_pass, &new_images);
             self.swapchain = new_swapchain;
             self.swapchain_images = new_images;
-            self.framebuffers = new_framebuffers;
             self.recreate_swapchain = false;
         }
 
-        let (image_index, recreate_swapchain, acquire_future) = {
-            acquire_next_image(self.swapchain.clone(), None).expect("Failed to acquire next image")
-        };
-        self.recreate_swapchain |= recreate_swapchain;
-
-        let swapchain_image = self.swapchain_images[image_index as usize].clone();
-
-        let mut command_buffer = AutoCommandBufferBuilder::primary(
-            &self.command_buffer_allocator,
-            self.gui_queue.queue_family_index(),
-            CommandBufferUsage::OneTimeSubmit,
-        )
-        .unwrap();
+        match acquire_next_image(self.swapchain.clone(), None).map_err(Validated::unwrap) {
+            Ok((image_index, recreate_swapchain, acquire_future)) => {
+                self.recreate_swapchain |= recreate_swapchain;
 
-        command_buffer
-            .blit_image(BlitImageInfo {
-                src_image_layout: ImageLayout::TransferSrcOptimal,
-                dst_image_layout: ImageLayout::TransferDstOptimal,
-                filter: Filter::Nearest,
-                ..BlitImageInfo::images(component_framebuffer, swapchain_image.clone())
-            })
-            .unwrap();
+                Ok(Some((image_index, acquire_future)))
+            }
+            Err(VulkanError::OutOfDate) => {
+                self.recreate_swapchain = true;
 
-        let command_buffer = command_buffer.build().unwrap();
+                Ok(None)
+            }
+            Err(err) => Err(err),
+        }
+    }
 
+    fn present(
+        &mut self,
+        command_buffer: Arc<impl PrimaryCommandBufferAbstract + 'static>,
+        image_index: u32,
+        acquire_future: SwapchainAcquireFuture,
+    ) -> Result<(), VulkanError> {
         // Swap that swapchain very painfully
         match self
             .previous_frame_future
@@ -329,36 +640,53 @@ impl RenderingBackendState for VulkanRenderingRuntime {
         {
             Ok(previous_frame_future) => {
                 self.previous_frame_future = Some(Box::new(previous_frame_future));
+
+                Ok(())
             }
-            Err(VulkanError::OutOfDate) => {
-                self.recreate_swapchain = true;
+            Err(err) => {
                 self.previous_frame_future = Some(vulkano::sync::now(self.device.clone()).boxed());
+
+                if err == VulkanError::OutOfDate {
+                    self.recreate_swapchain = true;
+
+                    return Ok(());
+                }
+
+                Err(err)
             }
-            Err(_) => panic!("Failed to present swapchain image"),
         }
     }
+}
 
-    fn redraw_menu(&mut self, _egui_context: &egui::Context, _full_output: egui::FullOutput) {}
-
-    fn initialize_machine(&mut self, machine: &Machine) {
-        for (component_info, queue) in machine
-            .display_components()
-            .zip(self.queues_for_components.iter().cycle().cloned())
-        {
-            component_info
-                .component
-                .set_display_data(DisplayComponentInitializationData::Vulkan(
-                    VulkanDisplayComponentInitializationData {
-                        device: self.device.clone(),
-                        queue,
-                        memory_allocator: self.memory_allocator.clone(),
-                        command_buffer_allocator: self.command_buffer_allocator.clone(),
-                    },
-                ))
-        }
+fn present_mode() -> PresentMode {
+    if GLOBAL_CONFIG.read().unwrap().vsync() {
+        PresentMode::Fifo
+    } else {
+        PresentMode::Immediate
     }
 }
 
+fn create_framebuffers(
+    render_pass: &Arc<RenderPass>,
+    swapchain_images: &[Arc<Image>],
+) -> Vec<Arc<Framebuffer>> {
+    swapchain_images
+        .iter()
+        .map(|image| {
+            let view = ImageView::new_default(image.clone()).unwrap();
+
+            Framebuffer::new(
+                render_pass.clone(),
+                FramebufferCreateInfo {
+                    attachments: vec![view],
+                    ..Default::default()
+                },
+            )
+            .unwrap()
+        })
+        .collect()
+}
+
 pub struct VulkanDisplayComponentInitializationData {
     pub device: Arc<Device>,
     pub queue: Arc<Queue>,
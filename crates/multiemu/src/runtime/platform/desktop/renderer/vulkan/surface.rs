@@ -0,0 +1,114 @@
+use super::VulkanDisplayComponentInitializationData;
+use crate::runtime::rendering_backend::{DisplayComponentFramebuffer, RenderSurface};
+use nalgebra::DMatrix;
+use palette::Srgba;
+use std::{error::Error, sync::Arc};
+use vulkano::{
+    buffer::{Buffer, BufferCreateInfo, BufferUsage, Subbuffer},
+    command_buffer::{
+        allocator::StandardCommandBufferAllocator, AutoCommandBufferBuilder, CommandBufferUsage,
+        CopyBufferToImageInfo, PrimaryCommandBufferAbstract,
+    },
+    device::Queue,
+    format::Format,
+    image::{Image, ImageCreateInfo, ImageType, ImageUsage},
+    memory::allocator::{AllocationCreateInfo, MemoryTypeFilter},
+    sync::GpuFuture,
+};
+
+/// Uploads go into a host visible staging buffer, committing copies that into the image the renderer samples
+#[derive(Debug)]
+pub struct VulkanRenderSurface {
+    staging_buffer: Subbuffer<[Srgba<u8>]>,
+    render_image: Arc<Image>,
+    queue: Arc<Queue>,
+    command_buffer_allocator: Arc<StandardCommandBufferAllocator>,
+}
+
+impl VulkanRenderSurface {
+    pub fn new(
+        initialization_data: &VulkanDisplayComponentInitializationData,
+        width: usize,
+        height: usize,
+    ) -> Self {
+        let staging_buffer = Buffer::from_iter(
+            initialization_data.memory_allocator.clone(),
+            BufferCreateInfo {
+                usage: BufferUsage::TRANSFER_SRC,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                memory_type_filter: MemoryTypeFilter::HOST_RANDOM_ACCESS,
+                ..Default::default()
+            },
+            vec![Srgba::new(0, 0, 0, 0xff); width * height],
+        )
+        .unwrap();
+
+        let render_image = Image::new(
+            initialization_data.memory_allocator.clone(),
+            ImageCreateInfo {
+                image_type: ImageType::Dim2d,
+                format: Format::R8G8B8A8_SRGB,
+                extent: [width as u32, height as u32, 1],
+                usage: ImageUsage::TRANSFER_SRC | ImageUsage::TRANSFER_DST | ImageUsage::SAMPLED,
+                ..Default::default()
+            },
+            AllocationCreateInfo::default(),
+        )
+        .unwrap();
+
+        Self {
+            staging_buffer,
+            render_image,
+            queue: initialization_data.queue.clone(),
+            command_buffer_allocator: initialization_data.command_buffer_allocator.clone(),
+        }
+    }
+}
+
+impl RenderSurface for VulkanRenderSurface {
+    fn upload(&self, frame: &DMatrix<Srgba<u8>>) {
+        self.staging_buffer
+            .write()
+            .unwrap()
+            .copy_from_slice(frame.as_slice());
+    }
+
+    fn commit(&self) {
+        let mut command_buffer = AutoCommandBufferBuilder::primary(
+            &self.command_buffer_allocator,
+            self.queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+
+        command_buffer
+            .copy_buffer_to_image(CopyBufferToImageInfo::buffer_image(
+                self.staging_buffer.clone(),
+                self.render_image.clone(),
+            ))
+            .unwrap();
+
+        // The device can be lost at any time, the frontend hands us a new one when that happens
+        if let Err(err) = submit(&self.queue, command_buffer.build().unwrap()) {
+            tracing::warn!("Could not update the display image: {}", err);
+        }
+    }
+
+    fn framebuffer(&self) -> DisplayComponentFramebuffer {
+        DisplayComponentFramebuffer::Vulkan(self.render_image.clone())
+    }
+}
+
+fn submit(
+    queue: &Arc<Queue>,
+    command_buffer: Arc<impl PrimaryCommandBufferAbstract + 'static>,
+) -> Result<(), Box<dyn Error>> {
+    command_buffer
+        .execute(queue.clone())?
+        .then_signal_fence_and_flush()?
+        .wait(None)?;
+
+    Ok(())
+}
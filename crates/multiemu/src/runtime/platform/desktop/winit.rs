@@ -1,19 +1,49 @@
-use super::PlatformRuntime;
+use super::{emulation_thread::EmulationThread, instance::MachineInstance, PlatformRuntime};
 use crate::{
-    config::GLOBAL_CONFIG,
-    definitions::chip8::chip8_machine,
-    gui::menu::UiOutput,
-    input::{GamepadId, InputState},
+    backup::{export_backup, import_backup},
+    component::ResetKind,
+    config::{GLOBAL_CONFIG, STORAGE_DIRECTORY},
+    definitions::misc::tape::{TapeControl, TapeDeck, FAST_LOAD_SPEED},
+    gui::menu::{
+        database_import::DatabaseImportStatus, library::scan_library, BackupStatus, MenuState,
+        UiOutput,
+    },
+    input::{
+        hotkey::{triggered_hotkey, Hotkey},
+        GamepadId, Input, InputState,
+    },
     machine::Machine,
+    memory::{AccessTraceSink, MemoryDumpError},
     rom::{
+        database::import_database,
+        header::RomHeader,
         id::RomId,
         info::RomInfo,
-        system::{GameSystem, OtherSystem},
+        manager::{RomManager, RomRequirement},
+        statistics::PlaySession,
+        system::GameSystem,
+    },
+    runtime::{
+        executor::Executor,
+        frame_presentation::{PresentKind, DEFAULT_CONTENT_FRAME_RATE},
+        input_display::InputDisplay,
+        rendering_backend::RenderingBackendState,
+        video_preset::VideoPreset,
     },
-    runtime::rendering_backend::RenderingBackendState,
+    scheduler::{StepGranularity, NORMAL_SPEED},
+    tas::{
+        movie::{Movie, MovieError},
+        TasSession,
+    },
+    update::{check_for_update, UpdateStatus},
+};
+use num::rational::Ratio;
+use std::{
+    fs::{create_dir_all, File},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
-use indexmap::IndexMap;
-use std::{fs::File, sync::Arc, time::{Duration, Instant}};
 use winit::{
     application::ApplicationHandler,
     event::WindowEvent,
@@ -25,15 +55,22 @@ use winit::{
 // FIXME: Duplicated hack code is present here
 
 const KEYBOARD_GAMEPAD_ID: GamepadId = 0;
+/// Past this many the menu gets too long to be useful
+const UNMAPPED_ACCESSES_SHOWN: usize = 16;
+/// How many accesses the menu keeps while tracing, a frame of most machines is far more than this
+const ACCESS_TRACE_CAPACITY: usize = 4096;
+const ACCESS_TRACE_SHOWN: usize = 64;
 
 pub enum MachineContext {
     /// Machine is waiting for graphics context to be ready
     Pending {
         user_specified_roms: Vec<RomId>,
         forced_system: Option<GameSystem>,
+        /// Where to dump every frame, see [crate::runtime::av_dump]
+        av_dump_directory: Option<PathBuf>,
     },
-    /// Machine is currently running
-    Running(Machine),
+    /// Machine is currently running on its own thread
+    Running(EmulationThread),
 }
 
 pub struct WindowingContext<RS: RenderingBackendState> {
@@ -46,12 +83,12 @@ impl<RS: RenderingBackendState<DisplayApiHandle = Arc<Window>>> ApplicationHandl
     for PlatformRuntime<RS>
 {
     fn resumed(&mut self, event_loop: &ActiveEventLoop) {
-        // HACK: This will cause frequent crashes on mobile platforms
         if self.windowing_context.is_some() {
-            panic!("Window already created");
+            tracing::debug!("Resumed while the window is still around, nothing to do");
+            return;
         }
 
-        let window = setup_window(event_loop);
+        let window = setup_window(event_loop, "MultiEMU");
         let egui_winit_context = egui_winit::State::new(
             self.menu.egui_context.clone(),
             egui::ViewportId::ROOT,
@@ -62,56 +99,118 @@ impl<RS: RenderingBackendState<DisplayApiHandle = Arc<Window>>> ApplicationHandl
         );
 
         let mut runtime_state = RS::new(window.clone());
+        self.frame_presentation_pacer
+            .set_refresh_rate(monitor_refresh_rate(&window), DEFAULT_CONTENT_FRAME_RATE);
 
         match self.machine_context.take() {
             Some(MachineContext::Pending {
                 user_specified_roms,
                 forced_system,
+                av_dump_directory,
             }) => {
-                let system = forced_system
-                    .or_else(|| {
-                        self.rom_manager
-                            .rom_information
-                            .r_transaction()
-                            .unwrap()
-                            .get()
-                            .primary::<RomInfo>(user_specified_roms[0])
-                            .unwrap()
-                            .map(|info| info.system)
-                    })
-                    .expect("Could not figure out system");
-
-                let machine =
-                    Machine::from_system(user_specified_roms, self.rom_manager.clone(), system);
-                runtime_state.initialize_machine(&machine);
+                let system = forced_system.or_else(|| {
+                    self.rom_manager
+                        .rom_information
+                        .r_transaction()
+                        .unwrap()
+                        .get()
+                        .primary::<RomInfo>(user_specified_roms[0])
+                        .unwrap()
+                        .map(|info| info.system)
+                });
 
-                // HACK: Wire the keyboard to port 0
-                machine
-                    .input_manager
-                    .set_real_to_emulated_mapping(KEYBOARD_GAMEPAD_ID, 0);
-
-                // Make sure the system being run has a default mapping
-                let mut global_config_guard = GLOBAL_CONFIG.write().unwrap();
-
-                for (gamepad_type, metadata) in machine.input_manager.gamepad_types.iter() {
-                    global_config_guard
-                        .gamepad_configs
-                        .entry(machine.system)
-                        .or_default()
-                        .entry(gamepad_type.clone())
-                        .or_insert_with(|| IndexMap::from_iter(metadata.default_bindings.clone()));
-                }
+                let game_rom_id = user_specified_roms[0];
+                let machine = match system {
+                    Some(system) => {
+                        Machine::from_system(user_specified_roms, self.rom_manager.clone(), system)
+                            .map_err(|err| {
+                                tracing::error!("Could not build a {} machine: {}", system, err);
+                                err.to_string()
+                            })
+                    }
+                    // Not in the database and not forced, so there is nothing to go on
+                    None => {
+                        tracing::error!("Could not figure out what system {} is for", game_rom_id);
+                        Err(format!(
+                            "Could not figure out what system {} is for, try importing it first",
+                            game_rom_id
+                        ))
+                    }
+                };
+
+                match machine {
+                    Err(err) => {
+                        self.menu.machine_build_error = Some(err);
+                    }
+                    Ok(mut machine) => {
+                        runtime_state.initialize_machine(&machine);
+
+                        if let Some(av_dump_directory) = av_dump_directory {
+                            if let Err(err) = machine.start_av_dump(
+                                &av_dump_directory,
+                                Duration::from_secs(1).div_f32(DEFAULT_CONTENT_FRAME_RATE),
+                            ) {
+                                tracing::error!(
+                                    "Could not start dumping to {}: {}",
+                                    av_dump_directory.display(),
+                                    err
+                                );
+                            }
+                        }
+
+                        // HACK: Wire the keyboard to port 0
+                        machine
+                            .input_manager
+                            .set_real_to_emulated_mapping(KEYBOARD_GAMEPAD_ID, 0);
+
+                        // Make sure the system being run has a default mapping
+                        let mut global_config_guard = GLOBAL_CONFIG.write().unwrap();
 
-                self.menu.active = false;
+                        global_config_guard
+                            .seed_gamepad_configs(machine.system, &machine.input_manager);
+                        runtime_state
+                            .set_video_preset(&global_config_guard.video_preset_for(game_rom_id));
 
-                self.machine_context = Some(MachineContext::Running(machine));
+                        if global_config_guard.usage_statistics {
+                            self.play_session =
+                                Some(PlaySession::start(game_rom_id, machine.system));
+                        }
+
+                        self.menu.active = false;
+                        self.menu.machine_fault = None;
+                        self.menu.machine_build_error = None;
+                        self.menu.media_error = None;
+                        self.menu.runaway_component = None;
+                        self.menu.media_slots = machine
+                            .media_slots()
+                            .map(|(slot, _)| slot.clone())
+                            .collect();
+
+                        self.machine_context = Some(MachineContext::Running(
+                            EmulationThread::spawn(machine, &self.executor),
+                        ));
+                    }
+                }
             }
-            Some(MachineContext::Running(_)) => {
-                panic!("Window resume while machine is running");
+            // Coming back from a suspend, the displays are still drawing with what the old window had
+            Some(MachineContext::Running(emulation_thread)) => {
+                let machine = emulation_thread.machine();
+                runtime_state.initialize_machine(&machine);
+                runtime_state.set_video_preset(
+                    &GLOBAL_CONFIG
+                        .read()
+                        .unwrap()
+                        .video_preset_for(machine.user_specified_roms[0]),
+                );
+                drop(machine);
+
+                self.machine_context = Some(MachineContext::Running(emulation_thread));
             }
             None => {}
         }
 
+        // Emulation picks back up on the next redraw, unless the menu is open
+        self.suspended = false;
         self.windowing_context = Some(WindowingContext {
             window,
             egui_winit_context,
@@ -119,17 +218,60 @@ impl<RS: RenderingBackendState<DisplayApiHandle = Arc<Window>>> ApplicationHandl
         })
     }
 
+    fn suspended(&mut self, _event_loop: &ActiveEventLoop) {
+        tracing::info!("Suspending");
+
+        self.suspend();
+        // The surface and anything on the gpu may not survive, so they are made again on resume
+        self.windowing_context = None;
+        // Nothing would bring their windows back, so they are closed for good
+        self.machine_instances.clear();
+    }
+
+    fn exiting(&mut self, _event_loop: &ActiveEventLoop) {
+        finish_play_session(&mut self.play_session, &self.rom_manager);
+        // Stops the emulation threads
+        self.machine_context = None;
+        self.machine_instances.clear();
+
+        let running_tasks = self.executor.running_tasks();
+
+        if !running_tasks.is_empty() {
+            tracing::info!("Waiting on background tasks {:?}", running_tasks);
+        }
+
+        self.executor.shutdown();
+    }
+
     fn window_event(
         &mut self,
         event_loop: &ActiveEventLoop,
-        _window_id: WindowId,
+        window_id: WindowId,
         event: WindowEvent,
     ) {
+        if let Some(machine_instance) = self.machine_instances.get_mut(&window_id) {
+            if !machine_instance.window_event(event, self.menu.active, KEYBOARD_GAMEPAD_ID) {
+                tracing::info!("Closing a machine running in its own window");
+                self.machine_instances.remove(&window_id);
+            }
+
+            return;
+        }
+
         // This helps the user not stare at a black screen
         if !matches!(self.machine_context, Some(MachineContext::Running { .. })) {
             self.menu.active = true;
         }
 
+        // Hidden windows stop getting redraws, so emulation has to be paused here instead of on the next one
+        if let WindowEvent::Occluded(occluded) = event {
+            if occluded {
+                self.suspend();
+            } else {
+                self.suspended = false;
+            }
+        }
+
         let window_context = self
             .windowing_context
             .as_mut()
@@ -156,6 +298,16 @@ impl<RS: RenderingBackendState<DisplayApiHandle = Arc<Window>>> ApplicationHandl
         }
 
         match event {
+            WindowEvent::Occluded(false) => {
+                window_context.window.request_redraw();
+            }
+            // The window may have landed on a monitor with a different refresh rate
+            WindowEvent::Moved(_) => {
+                self.frame_presentation_pacer.set_refresh_rate(
+                    monitor_refresh_rate(&window_context.window),
+                    DEFAULT_CONTENT_FRAME_RATE,
+                );
+            }
             WindowEvent::CloseRequested => {
                 tracing::info!("Window close requested");
 
@@ -179,13 +331,192 @@ impl<RS: RenderingBackendState<DisplayApiHandle = Arc<Window>>> ApplicationHandl
 
                 if let PhysicalKey::Code(key_code) = event.physical_key {
                     let state = event.state.is_pressed();
+                    let input: Input = key_code.try_into().unwrap();
+
+                    if !state {
+                        self.held_inputs.remove(&input);
+
+                        // Rewinding only lasts as long as its hotkey is held
+                        if let Some(MachineContext::Running(emulation_thread)) =
+                            &mut self.machine_context
+                        {
+                            if GLOBAL_CONFIG.read().unwrap().hotkeys.iter().any(
+                                |(combination, hotkey)| {
+                                    *hotkey == Hotkey::Rewind && combination.contains(&input)
+                                },
+                            ) {
+                                emulation_thread.set_rewinding(false);
+                            }
+                        }
+                    // Key repeats should not trigger hotkeys again
+                    } else if self.held_inputs.insert(input) {
+                        let mut quit_game = false;
+                        let hotkey = triggered_hotkey(
+                            &GLOBAL_CONFIG.read().unwrap().hotkeys,
+                            &self.held_inputs,
+                            input,
+                        );
+
+                        if let (Some(hotkey), Some(MachineContext::Running(emulation_thread))) =
+                            (hotkey, &mut self.machine_context)
+                        {
+                            tracing::debug!("Hotkey {:?} triggered", hotkey);
+
+                            if let Some(play_session) = &mut self.play_session {
+                                play_session.record_feature(format!("{:?} Hotkey", hotkey));
+                            }
+
+                            match hotkey {
+                                Hotkey::ToggleMenu => {
+                                    self.menu.active = !self.menu.active;
+                                }
+                                // Movies need to stay frame stepping
+                                Hotkey::ToggleFrameAdvance if self.menu.tas_session.is_some() => {}
+                                Hotkey::ToggleFrameAdvance => {
+                                    let mut machine = emulation_thread.machine();
+                                    let step_granularity = match machine.step_granularity() {
+                                        Some(_) => None,
+                                        None => Some(StepGranularity::Frame),
+                                    };
+
+                                    machine.set_step_granularity(step_granularity);
+                                }
+                                Hotkey::FrameAdvance => {
+                                    if let Some(tas_session) = &mut self.menu.tas_session {
+                                        tas_session.advance(&mut emulation_thread.machine());
+                                    } else {
+                                        emulation_thread.machine().step();
+                                    }
+                                }
+                                Hotkey::FastForward => {
+                                    emulation_thread.toggle_speed(Ratio::from_integer(2));
+                                }
+                                Hotkey::UltraFastForward => {
+                                    emulation_thread.toggle_speed(Ratio::from_integer(4));
+                                }
+                                Hotkey::SlowMotion => {
+                                    emulation_thread.toggle_speed(Ratio::new(1, 2));
+                                }
+                                Hotkey::CyclePowerProfile => {
+                                    // Components may read the config while the machine is locked, so never
+                                    // take the machine lock while holding GLOBAL_CONFIG
+                                    let rom_id = emulation_thread.machine().user_specified_roms[0];
+                                    let mut global_config_guard = GLOBAL_CONFIG.write().unwrap();
+                                    global_config_guard.power_profile =
+                                        global_config_guard.power_profile.next();
+                                    tracing::info!(
+                                        "Switched to the {} power profile",
+                                        global_config_guard.power_profile
+                                    );
+
+                                    window_context.runtime_state.set_video_preset(
+                                        &global_config_guard.video_preset_for(rom_id),
+                                    );
+                                }
+                                Hotkey::TapePlay => {
+                                    if let Some(tape_deck) =
+                                        emulation_thread.machine().find_component::<TapeDeck>()
+                                    {
+                                        let playing =
+                                            tape_deck.status().is_some_and(|status| status.playing);
+
+                                        tape_deck.control(if playing {
+                                            TapeControl::Stop
+                                        } else {
+                                            TapeControl::Play
+                                        });
+                                    }
+                                }
+                                Hotkey::TapeFastLoad => {
+                                    if let Some(tape_deck) =
+                                        emulation_thread.machine().find_component::<TapeDeck>()
+                                    {
+                                        tape_deck.control(TapeControl::SetFastLoad(
+                                            !tape_deck.fast_load(),
+                                        ));
+                                    }
+                                }
+                                // Only computers have keyboards to type into
+                                Hotkey::PasteClipboard
+                                    if matches!(
+                                        emulation_thread.system,
+                                        GameSystem::Computer(_)
+                                    ) =>
+                                {
+                                    if let Some(text) =
+                                        window_context.egui_winit_context.clipboard_text()
+                                    {
+                                        emulation_thread.input_manager.paste_text(
+                                            emulation_thread.system,
+                                            KEYBOARD_GAMEPAD_ID,
+                                            &text,
+                                            GLOBAL_CONFIG.read().unwrap().paste_key_frames,
+                                        );
+                                    }
+                                }
+                                Hotkey::PasteClipboard => {}
+                                Hotkey::SaveSnapshot => {
+                                    self.menu.savestate_status = Some(save_state(
+                                        &emulation_thread.machine(),
+                                        self.menu.state_slot,
+                                    ));
+                                }
+                                Hotkey::LoadSnapshot => {
+                                    self.menu.savestate_status = Some(load_state(
+                                        &mut emulation_thread.machine(),
+                                        self.menu.state_slot,
+                                    ));
+                                    emulation_thread.flush_audio();
+                                }
+                                // Going back would desync the movie, the greenzone is for that
+                                Hotkey::Rewind if self.menu.tas_session.is_some() => {}
+                                Hotkey::Rewind => {
+                                    emulation_thread.set_rewinding(true);
+                                }
+                                Hotkey::QuitGame => {
+                                    quit_game = true;
+                                }
+                                Hotkey::SoftReset | Hotkey::HardReset => {
+                                    let mut machine = emulation_thread.machine();
+
+                                    // A reset in the middle of a movie would not be recorded
+                                    if let Some(tas_session) = self.menu.tas_session.take() {
+                                        tas_session.stop(&mut machine);
+                                    }
+
+                                    machine.reset(if hotkey == Hotkey::SoftReset {
+                                        ResetKind::Soft
+                                    } else {
+                                        ResetKind::Hard
+                                    });
+                                    self.menu.machine_fault = None;
+                                    self.menu.runaway_component = None;
+                                }
+                            }
+
+                            window_context.window.request_redraw();
+                        }
+
+                        if quit_game {
+                            close_machine(
+                                &mut self.machine_context,
+                                &mut self.menu,
+                                &mut self.play_session,
+                                &self.rom_manager,
+                                &mut window_context.runtime_state,
+                            );
+                            self.fast_loading = false;
+                        }
+                    }
 
                     if !self.menu.active {
-                        if let Some(MachineContext::Running(machine)) = &mut self.machine_context {
-                            machine.input_manager.insert_input(
-                                machine.system,
+                        if let Some(MachineContext::Running(emulation_thread)) =
+                            &self.machine_context
+                        {
+                            emulation_thread.input_manager.insert_input(
+                                emulation_thread.system,
                                 KEYBOARD_GAMEPAD_ID,
-                                key_code.try_into().unwrap(),
+                                input,
                                 InputState::Digital(state),
                             );
                         }
@@ -193,7 +524,135 @@ impl<RS: RenderingBackendState<DisplayApiHandle = Arc<Window>>> ApplicationHandl
                 }
             }
             WindowEvent::RedrawRequested => {
+                // Otherwise a background task dying would only ever show up in the log
+                let task_panics = self.executor.take_panics();
+                if !task_panics.is_empty() {
+                    self.menu
+                        .task_panics
+                        .extend(task_panics.iter().map(ToString::to_string));
+                    self.menu.active = true;
+                }
+
+                // Emulated time should not pass while the user is in the menu
+                if let Some(MachineContext::Running(emulation_thread)) = &mut self.machine_context {
+                    if self.menu.active || self.suspended {
+                        emulation_thread.pause();
+                    } else {
+                        emulation_thread.resume();
+                    }
+
+                    emulation_thread.set_profiling(self.menu.profiling);
+
+                    // Tapes take minutes at the real speed, so they can be sped through
+                    let fast_loading = emulation_thread
+                        .try_machine()
+                        .and_then(|machine| machine.find_component::<TapeDeck>())
+                        .is_some_and(|tape_deck| tape_deck.fast_loading());
+
+                    if fast_loading != self.fast_loading {
+                        self.fast_loading = fast_loading;
+                        emulation_thread.set_speed(if fast_loading {
+                            FAST_LOAD_SPEED
+                        } else {
+                            NORMAL_SPEED
+                        });
+                    }
+                }
+
                 if self.menu.active {
+                    self.menu.machine_running =
+                        matches!(self.machine_context, Some(MachineContext::Running(_)));
+                    self.menu.machine_instances = self.machine_instances.len();
+                    self.menu.lag_frames = match &self.machine_context {
+                        Some(MachineContext::Running(emulation_thread)) => {
+                            Some(emulation_thread.input_manager.lag_frames())
+                        }
+                        _ => None,
+                    };
+                    self.menu.emulated_gamepads = match &self.machine_context {
+                        Some(MachineContext::Running(emulation_thread)) => {
+                            emulation_thread.input_manager.emulated_gamepads()
+                        }
+                        _ => Vec::default(),
+                    };
+                    self.menu.scheduler_stats = match &self.machine_context {
+                        Some(MachineContext::Running(emulation_thread)) => emulation_thread
+                            .frame()
+                            .and_then(|frame| frame.scheduler_stats.clone()),
+                        _ => None,
+                    };
+                    // A machine stuck in a run would freeze the menu too, so keep what we had
+                    match &self.machine_context {
+                        Some(MachineContext::Running(emulation_thread)) => {
+                            if let Some(machine) = emulation_thread.try_machine() {
+                                self.menu.unmapped_accesses = machine
+                                    .memory_translation_table
+                                    .unmapped_accesses()
+                                    .into_iter()
+                                    .take(UNMAPPED_ACCESSES_SHOWN)
+                                    .map(|unmapped_access| {
+                                        let component_name = unmapped_access
+                                            .component
+                                            .and_then(|component_id| {
+                                                machine.component_store.get(component_id)
+                                            })
+                                            .map(|table| table.name);
+
+                                        (unmapped_access, component_name)
+                                    })
+                                    .collect();
+                                self.menu.component_debug_state = machine
+                                    .debug_components()
+                                    .map(|(component_id, component_name, component_info)| {
+                                        (
+                                            component_id,
+                                            component_name,
+                                            component_info.component.debug_state(),
+                                        )
+                                    })
+                                    .collect();
+                                self.menu.access_tracing =
+                                    machine.memory_translation_table.is_tracing();
+                                self.menu.access_trace = machine
+                                    .memory_translation_table
+                                    .traced_accesses()
+                                    .into_iter()
+                                    .rev()
+                                    .take(ACCESS_TRACE_SHOWN)
+                                    .map(|access| {
+                                        let component_name = access
+                                            .component
+                                            .and_then(|component_id| {
+                                                machine.component_store.get(component_id)
+                                            })
+                                            .map(|table| table.name);
+
+                                        (access, component_name)
+                                    })
+                                    .collect();
+                                self.menu.collecting_access_heatmap =
+                                    machine.memory_translation_table.is_collecting_heatmap();
+                                self.menu.access_heatmap =
+                                    machine.memory_translation_table.access_heatmap();
+                                self.menu.frame_comparison =
+                                    machine.frame_comparison_summary().cloned();
+                                self.menu.tape = machine
+                                    .find_component::<TapeDeck>()
+                                    .and_then(|tape_deck| tape_deck.status());
+                            }
+                        }
+                        _ => {
+                            self.menu.unmapped_accesses.clear();
+                            self.menu.component_debug_state.clear();
+                            self.menu.access_tracing = false;
+                            self.menu.access_trace.clear();
+                            self.menu.collecting_access_heatmap = false;
+                            self.menu.access_heatmap.clear();
+                            self.menu.frame_comparison = None;
+                            self.menu.tape = None;
+                        }
+                    }
+
                     // We put the ui output like this so multipassing egui gui building works
                     let mut ui_output = None;
                     let full_output = self.menu.egui_context.clone().run(
@@ -205,95 +664,836 @@ impl<RS: RenderingBackendState<DisplayApiHandle = Arc<Window>>> ApplicationHandl
                         },
                     );
 
+                    if let (Some(play_session), Some(feature)) = (
+                        &mut self.play_session,
+                        ui_output.as_ref().and_then(UiOutput::feature),
+                    ) {
+                        play_session.record_feature(feature);
+                    }
+
                     match ui_output {
                         None => {}
                         Some(UiOutput::OpenGame { path }) => {
-                            tracing::info!("Opening rom at {}", path.display());
+                            match open_machine(&self.rom_manager, &path) {
+                                Err(err) => {
+                                    tracing::error!("{}", err);
+                                    self.menu.machine_build_error = Some(err);
+                                }
+                                Ok(machine) => {
+                                    let rom_id = machine.user_specified_roms[0];
+                                    let global_config_guard = GLOBAL_CONFIG.read().unwrap();
 
-                            let mut rom_file = File::open(&path).unwrap();
-                            let rom_id = RomId::from_read(&mut rom_file);
+                                    // Stop the old machine before the new one takes over the renderer
+                                    self.machine_context = None;
+                                    finish_play_session(&mut self.play_session, &self.rom_manager);
 
-                            // Check if we know about the game from the manager
-                            if let Some(system) = self
-                                .rom_manager
-                                .rom_information
-                                .r_transaction()
-                                .unwrap()
-                                .get()
-                                .primary::<RomInfo>(rom_id)
-                                .unwrap()
-                                .map(|info| info.system)
-                                .or_else(|| GameSystem::guess(&path))
+                                    if global_config_guard.usage_statistics {
+                                        self.play_session =
+                                            Some(PlaySession::start(rom_id, machine.system));
+                                    }
+
+                                    // Initialize graphics components
+                                    window_context.runtime_state.initialize_machine(&machine);
+                                    window_context.runtime_state.set_video_preset(
+                                        &global_config_guard.video_preset_for(rom_id),
+                                    );
+                                    self.menu.media_slots = machine
+                                        .media_slots()
+                                        .map(|(slot, _)| slot.clone())
+                                        .collect();
+                                    self.menu.machine_fault = None;
+                                    self.menu.machine_build_error = None;
+                                    self.menu.media_error = None;
+                                    self.menu.runaway_component = None;
+                                    self.menu.tas_session = None;
+                                    self.machine_context = Some(MachineContext::Running(
+                                        EmulationThread::spawn(machine, &self.executor),
+                                    ));
+                                    // Close the menu
+                                    self.menu.active = false;
+                                }
+                            }
+                        }
+                        Some(UiOutput::OpenInstance { path }) => {
+                            match open_machine(&self.rom_manager, &path) {
+                                Err(err) => {
+                                    tracing::error!("{}", err);
+                                    self.menu.machine_build_error = Some(err);
+                                }
+                                Ok(machine) => {
+                                    let machine_instance =
+                                        MachineInstance::open(event_loop, machine, &self.executor);
+
+                                    self.machine_instances
+                                        .insert(machine_instance.window_id(), machine_instance);
+                                    self.menu.machine_build_error = None;
+                                }
+                            }
+                        }
+                        Some(UiOutput::ChangeMedia { slot, path }) => {
+                            if let Some(MachineContext::Running(emulation_thread)) =
+                                &self.machine_context
                             {
-                                self.rom_manager.rom_paths.insert(rom_id, path.clone());
+                                let result = path
+                                    .map(|path| {
+                                        tracing::info!(
+                                            "Inserting rom at {} into {}",
+                                            path.display(),
+                                            slot
+                                        );
+
+                                        let mut rom_file = File::open(&path).map_err(|err| {
+                                            format!("Could not open {}: {}", path.display(), err)
+                                        })?;
+                                        let rom_id = RomId::from_read(&mut rom_file);
+                                        self.rom_manager.rom_paths.insert(rom_id, path);
 
-                                let machine = match system {
-                                    GameSystem::Other(OtherSystem::Chip8) => {
-                                        chip8_machine(vec![rom_id], self.rom_manager.clone())
+                                        Ok::<_, String>(rom_id)
+                                    })
+                                    .transpose()
+                                    .and_then(|rom_id| {
+                                        emulation_thread
+                                            .machine()
+                                            .swap_media(&slot, rom_id)
+                                            .map_err(|err| err.to_string())
+                                    });
+
+                                match result {
+                                    Ok(()) => {
+                                        self.menu.media_error = None;
+                                        // Close the menu
+                                        self.menu.active = false;
                                     }
-                                    _ => {
-                                        unimplemented!()
+                                    Err(err) => {
+                                        tracing::error!("Could not change media: {}", err);
+                                        self.menu.media_error = Some(err);
                                     }
-                                };
+                                }
+                            } else {
+                                tracing::warn!("Tried to change media without a running machine");
+                            }
+                        }
+                        Some(UiOutput::TapeControl(control)) => {
+                            if let Some(MachineContext::Running(emulation_thread)) =
+                                &self.machine_context
+                            {
+                                if let Some(tape_deck) =
+                                    emulation_thread.machine().find_component::<TapeDeck>()
+                                {
+                                    tape_deck.control(control);
+                                }
+                            }
+                        }
+                        Some(UiOutput::ImportVideoPreset { path }) => {
+                            match VideoPreset::load(&path) {
+                                Ok(video_preset) => {
+                                    let rom_id = match &self.machine_context {
+                                        Some(MachineContext::Running(emulation_thread)) => {
+                                            Some(emulation_thread.machine().user_specified_roms[0])
+                                        }
+                                        _ => None,
+                                    };
+                                    let mut global_config_guard = GLOBAL_CONFIG.write().unwrap();
+                                    self.menu.video_preset_status =
+                                        Some(format!("Using preset {}", video_preset.name));
+                                    video_preset.apply_to_config(&mut global_config_guard);
 
-                                // HACK: Wire the keyboard to port 0
-                                machine
-                                    .input_manager
-                                    .set_real_to_emulated_mapping(KEYBOARD_GAMEPAD_ID, 0);
+                                    if let Some(rom_id) = rom_id {
+                                        window_context.runtime_state.set_video_preset(
+                                            &global_config_guard.video_preset_for(rom_id),
+                                        );
+                                    }
+                                }
+                                Err(err) => {
+                                    tracing::error!(
+                                        "Could not import video preset {}: {}",
+                                        path.display(),
+                                        err
+                                    );
+                                    self.menu.video_preset_status = Some(err.to_string());
+                                }
+                            }
+                        }
+                        Some(UiOutput::AttachVideoPreset { name }) => {
+                            if let Some(MachineContext::Running(emulation_thread)) =
+                                &self.machine_context
+                            {
+                                let rom_id = emulation_thread.machine().user_specified_roms[0];
+                                let mut global_config_guard = GLOBAL_CONFIG.write().unwrap();
+                                let video_preset =
+                                    VideoPreset::from_config(&global_config_guard, name);
 
-                                // Make sure the system being run has a default mapping
+                                global_config_guard
+                                    .game_video_presets
+                                    .insert(rom_id, video_preset);
+                                window_context.runtime_state.set_video_preset(
+                                    &global_config_guard.video_preset_for(rom_id),
+                                );
+                                self.menu.video_preset_status =
+                                    Some(format!("Attached to {}", rom_id));
+                            }
+                        }
+                        Some(UiOutput::DetachVideoPreset) => {
+                            if let Some(MachineContext::Running(emulation_thread)) =
+                                &self.machine_context
+                            {
+                                let rom_id = emulation_thread.machine().user_specified_roms[0];
                                 let mut global_config_guard = GLOBAL_CONFIG.write().unwrap();
 
-                                for (gamepad_type, metadata) in
-                                    machine.input_manager.gamepad_types.iter()
-                                {
-                                    global_config_guard
-                                        .gamepad_configs
-                                        .entry(machine.system)
-                                        .or_default()
-                                        .entry(gamepad_type.clone())
-                                        .or_insert_with(|| {
-                                            IndexMap::from_iter(metadata.default_bindings.clone())
-                                        });
+                                global_config_guard.game_video_presets.shift_remove(&rom_id);
+                                window_context.runtime_state.set_video_preset(
+                                    &global_config_guard.video_preset_for(rom_id),
+                                );
+                                self.menu.video_preset_status =
+                                    Some(format!("Detached from {}", rom_id));
+                            }
+                        }
+                        Some(UiOutput::SetAccessTracing(tracing)) => {
+                            if let Some(MachineContext::Running(emulation_thread)) =
+                                &self.machine_context
+                            {
+                                emulation_thread
+                                    .machine()
+                                    .memory_translation_table
+                                    .set_access_trace(tracing.then(|| {
+                                        AccessTraceSink::ring_buffer(ACCESS_TRACE_CAPACITY)
+                                    }));
+                            }
+                        }
+                        Some(UiOutput::SetAccessHeatmap(collecting)) => {
+                            if let Some(MachineContext::Running(emulation_thread)) =
+                                &self.machine_context
+                            {
+                                emulation_thread
+                                    .machine()
+                                    .memory_translation_table
+                                    .set_heatmap(collecting);
+                            }
+                        }
+                        Some(UiOutput::ClearAccessHeatmap) => {
+                            if let Some(MachineContext::Running(emulation_thread)) =
+                                &self.machine_context
+                            {
+                                emulation_thread
+                                    .machine()
+                                    .memory_translation_table
+                                    .clear_access_heatmap();
+                            }
+                        }
+                        Some(UiOutput::StartFrameComparison { references }) => {
+                            if let Some(MachineContext::Running(emulation_thread)) =
+                                &self.machine_context
+                            {
+                                let heatmaps = references.join("diff");
+
+                                self.menu.frame_comparison_status = emulation_thread
+                                    .machine()
+                                    .start_comparing_frames(&references, heatmaps)
+                                    .err()
+                                    .map(|err| format!("Could not start comparing: {}", err));
+                            }
+                        }
+                        Some(UiOutput::StopFrameComparison) => {
+                            if let Some(MachineContext::Running(emulation_thread)) =
+                                &self.machine_context
+                            {
+                                emulation_thread.machine().stop_comparing_frames();
+                            }
+                        }
+                        Some(UiOutput::ResetMachine(kind)) => {
+                            if let Some(MachineContext::Running(emulation_thread)) =
+                                &self.machine_context
+                            {
+                                let mut machine = emulation_thread.machine();
+
+                                // A reset in the middle of a movie would not be recorded
+                                if let Some(tas_session) = self.menu.tas_session.take() {
+                                    tas_session.stop(&mut machine);
                                 }
 
-                                // Initialize graphics components
-                                window_context.runtime_state.initialize_machine(&machine);
-                                self.machine_context = Some(MachineContext::Running(machine));
+                                machine.reset(kind);
+                                self.menu.machine_fault = None;
+                                self.menu.runaway_component = None;
                                 // Close the menu
                                 self.menu.active = false;
+                            }
+                        }
+                        Some(UiOutput::SaveState { slot }) => {
+                            if let Some(MachineContext::Running(emulation_thread)) =
+                                &self.machine_context
+                            {
+                                self.menu.savestate_status =
+                                    Some(save_state(&emulation_thread.machine(), slot));
+                            }
+                        }
+                        Some(UiOutput::LoadState { slot }) => {
+                            if let Some(MachineContext::Running(emulation_thread)) =
+                                &self.machine_context
+                            {
+                                self.menu.savestate_status =
+                                    Some(load_state(&mut emulation_thread.machine(), slot));
+                                emulation_thread.flush_audio();
+                            }
+                        }
+                        Some(UiOutput::QuitGame) => {
+                            close_machine(
+                                &mut self.machine_context,
+                                &mut self.menu,
+                                &mut self.play_session,
+                                &self.rom_manager,
+                                &mut window_context.runtime_state,
+                            );
+                            self.fast_loading = false;
+                        }
+                        Some(UiOutput::DumpMachineState) => {
+                            if let Some(MachineContext::Running(emulation_thread)) =
+                                &self.machine_context
+                            {
+                                let snapshot_directory =
+                                    GLOBAL_CONFIG.read().unwrap().snapshot_directory.clone();
+                                let timestamp = SystemTime::now()
+                                    .duration_since(UNIX_EPOCH)
+                                    .unwrap_or_default()
+                                    .as_secs();
+                                let path =
+                                    snapshot_directory.join(format!("crash_dump_{}", timestamp));
+
+                                match create_dir_all(&snapshot_directory) {
+                                    Ok(()) => {
+                                        emulation_thread.machine().save_snapshot(&path);
+                                        tracing::info!(
+                                            "Dumped machine state to {}",
+                                            path.display()
+                                        );
+                                    }
+                                    Err(err) => {
+                                        tracing::error!("Could not dump machine state: {}", err);
+                                    }
+                                }
+                            }
+                        }
+                        Some(UiOutput::DumpMemory {
+                            address_space,
+                            range,
+                        }) => {
+                            if let Some(MachineContext::Running(emulation_thread)) =
+                                &self.machine_context
+                            {
+                                let snapshot_directory =
+                                    GLOBAL_CONFIG.read().unwrap().snapshot_directory.clone();
+                                let timestamp = SystemTime::now()
+                                    .duration_since(UNIX_EPOCH)
+                                    .unwrap_or_default()
+                                    .as_secs();
+                                let path = snapshot_directory
+                                    .join(format!("memory_{}_{}.bin", address_space, timestamp));
+
+                                let result = create_dir_all(&snapshot_directory)
+                                    .and_then(|_| File::create(&path))
+                                    .map_err(MemoryDumpError::from)
+                                    .and_then(|file| {
+                                        emulation_thread
+                                            .machine()
+                                            .memory_translation_table
+                                            .dump_range(range, address_space, file)
+                                    });
+
+                                self.menu.memory_dump_status = Some(match result {
+                                    Ok(0) => format!("Dumped to {}", path.display()),
+                                    Ok(missing) => format!(
+                                        "Dumped to {}, {} unmapped bytes written as zero",
+                                        path.display(),
+                                        missing
+                                    ),
+                                    Err(err) => format!("Could not dump memory: {}", err),
+                                });
+                            }
+                        }
+                        Some(UiOutput::LoadMemory {
+                            address_space,
+                            address,
+                            path,
+                        }) => {
+                            if let Some(MachineContext::Running(emulation_thread)) =
+                                &self.machine_context
+                            {
+                                let result = File::open(&path)
+                                    .map_err(MemoryDumpError::from)
+                                    .and_then(|file| {
+                                        emulation_thread
+                                            .machine()
+                                            .memory_translation_table
+                                            .restore_range(address, address_space, file)
+                                    });
+
+                                self.menu.memory_dump_status = Some(match result {
+                                    Ok(written) => {
+                                        format!("Loaded {} bytes to {:#x}", written, address)
+                                    }
+                                    Err(err) => format!("Could not load memory: {}", err),
+                                });
+                            }
+                        }
+                        Some(UiOutput::ImportDatabases { paths }) => {
+                            let database_import_progress =
+                                self.menu.database_import_progress.clone();
+                            let rom_manager = self.rom_manager.clone();
+
+                            database_import_progress.lock().unwrap().files = paths
+                                .iter()
+                                .map(|path| (path.clone(), DatabaseImportStatus::Queued))
+                                .collect();
+
+                            self.executor.spawn("database_import", move |context| {
+                                for path in paths {
+                                    if context.should_stop() {
+                                        return;
+                                    }
+
+                                    database_import_progress
+                                        .lock()
+                                        .unwrap()
+                                        .files
+                                        .insert(path.clone(), DatabaseImportStatus::Importing);
+
+                                    let status = match import_database(&rom_manager, &path) {
+                                        Ok(imported_entries) => {
+                                            DatabaseImportStatus::Imported(imported_entries)
+                                        }
+                                        Err(err) => {
+                                            tracing::error!(
+                                                "Failed to import database {}: {}",
+                                                path.display(),
+                                                err
+                                            );
+
+                                            DatabaseImportStatus::Failed(err.to_string())
+                                        }
+                                    };
+
+                                    database_import_progress
+                                        .lock()
+                                        .unwrap()
+                                        .files
+                                        .insert(path, status);
+                                }
+                            });
+                        }
+                        Some(UiOutput::CheckForUpdates) => {
+                            check_for_updates(&self.executor, &self.menu.update_status);
+                        }
+                        Some(UiOutput::ExportBackup { passphrase }) => {
+                            let backup_status = self.menu.backup_status.clone();
+                            let timestamp = SystemTime::now()
+                                .duration_since(UNIX_EPOCH)
+                                .unwrap_or_default()
+                                .as_secs();
+                            let path = STORAGE_DIRECTORY
+                                .join("backups")
+                                .join(format!("backup_{}.zip", timestamp));
+
+                            *backup_status.lock().unwrap() = Some(BackupStatus::Running);
+
+                            self.executor.spawn("backup_export", move |_| {
+                                let status = match export_backup(&path, passphrase.as_deref()) {
+                                    Ok(()) => BackupStatus::Finished(format!(
+                                        "Exported backup to {}",
+                                        path.display()
+                                    )),
+                                    Err(err) => {
+                                        tracing::error!("Failed to export backup: {}", err);
+
+                                        BackupStatus::Failed(err.to_string())
+                                    }
+                                };
+
+                                *backup_status.lock().unwrap() = Some(status);
+                            });
+                        }
+                        Some(UiOutput::ImportBackup { path, passphrase }) => {
+                            let backup_status = self.menu.backup_status.clone();
+                            let rom_manager = self.rom_manager.clone();
+
+                            *backup_status.lock().unwrap() = Some(BackupStatus::Running);
+
+                            self.executor.spawn("backup_import", move |_| {
+                                let status =
+                                    match import_backup(&path, passphrase.as_deref(), &rom_manager)
+                                    {
+                                        Ok(()) => BackupStatus::Finished(format!(
+                                            "Imported backup from {}",
+                                            path.display()
+                                        )),
+                                        Err(err) => {
+                                            tracing::error!(
+                                                "Failed to import backup {}: {}",
+                                                path.display(),
+                                                err
+                                            );
+
+                                            BackupStatus::Failed(err.to_string())
+                                        }
+                                    };
+
+                                *backup_status.lock().unwrap() = Some(status);
+                            });
+                        }
+                        Some(UiOutput::StartTas) => {
+                            if let Some(MachineContext::Running(emulation_thread)) =
+                                &self.machine_context
+                            {
+                                let mut tas_session =
+                                    TasSession::start(&mut emulation_thread.machine());
+                                tas_session.set_greenzone_budget(
+                                    GLOBAL_CONFIG.read().unwrap().memory_budgets.tas_greenzone,
+                                );
+
+                                self.menu.tas_session = Some(tas_session);
+                                self.menu.tas_error = None;
+                            } else {
+                                tracing::warn!("Tried to start a movie without a running machine");
+                            }
+                        }
+                        Some(UiOutput::StopTas) => {
+                            if let (
+                                Some(tas_session),
+                                Some(MachineContext::Running(emulation_thread)),
+                            ) = (self.menu.tas_session.take(), &self.machine_context)
+                            {
+                                tas_session.stop(&mut emulation_thread.machine());
+                            }
+                        }
+                        Some(UiOutput::TasAdvance) => {
+                            if let (
+                                Some(tas_session),
+                                Some(MachineContext::Running(emulation_thread)),
+                            ) = (&mut self.menu.tas_session, &self.machine_context)
+                            {
+                                tas_session.advance(&mut emulation_thread.machine());
+                            }
+                        }
+                        Some(UiOutput::TasSeek { frame }) => {
+                            if let (
+                                Some(tas_session),
+                                Some(MachineContext::Running(emulation_thread)),
+                            ) = (&mut self.menu.tas_session, &self.machine_context)
+                            {
+                                tas_session.seek(&mut emulation_thread.machine(), frame);
+                                emulation_thread.flush_audio();
+                            }
+                        }
+                        Some(UiOutput::ExportMovie) => {
+                            if let Some(tas_session) = &self.menu.tas_session {
+                                let movie_directory = STORAGE_DIRECTORY.join("movies");
+                                let timestamp = SystemTime::now()
+                                    .duration_since(UNIX_EPOCH)
+                                    .unwrap_or_default()
+                                    .as_secs();
+                                let path = movie_directory.join(format!("movie_{}.ron", timestamp));
+
+                                match create_dir_all(&movie_directory)
+                                    .map_err(MovieError::from)
+                                    .and_then(|()| tas_session.movie.save(&path))
+                                {
+                                    Ok(()) => {
+                                        tracing::info!("Exported movie to {}", path.display());
+                                        self.menu.tas_error = None;
+                                    }
+                                    Err(err) => {
+                                        tracing::error!("Failed to export movie: {}", err);
+                                        self.menu.tas_error = Some(err.to_string());
+                                    }
+                                }
+                            }
+                        }
+                        Some(UiOutput::ImportMovie { path }) => {
+                            if let Some(MachineContext::Running(emulation_thread)) =
+                                &self.machine_context
+                            {
+                                let mut machine = emulation_thread.machine();
+
+                                if let Some(tas_session) = self.menu.tas_session.take() {
+                                    tas_session.stop(&mut machine);
+                                }
+
+                                match Movie::load(&path)
+                                    .and_then(|movie| TasSession::from_movie(&mut machine, movie))
+                                {
+                                    Ok(mut tas_session) => {
+                                        tas_session.set_greenzone_budget(
+                                            GLOBAL_CONFIG
+                                                .read()
+                                                .unwrap()
+                                                .memory_budgets
+                                                .tas_greenzone,
+                                        );
+                                        self.menu.tas_session = Some(tas_session);
+                                        self.menu.tas_error = None;
+                                    }
+                                    Err(err) => {
+                                        tracing::error!(
+                                            "Failed to import movie {}: {}",
+                                            path.display(),
+                                            err
+                                        );
+                                        self.menu.tas_error = Some(err.to_string());
+                                    }
+                                }
                             } else {
-                                tracing::error!("Could not identify rom at {}", path.display());
+                                tracing::warn!("Tried to import a movie without a running machine");
+                            }
+                        }
+                        Some(UiOutput::RefreshLibrary) => {
+                            let roms_directory =
+                                GLOBAL_CONFIG.read().unwrap().roms_directory.clone();
+
+                            match scan_library(&self.rom_manager, &roms_directory) {
+                                Ok(library) => {
+                                    self.menu.library = Some(library);
+                                    self.menu.library_status = None;
+                                }
+                                Err(err) => {
+                                    tracing::error!(
+                                        "Failed to scan {}: {}",
+                                        roms_directory.display(),
+                                        err
+                                    );
+                                    self.menu.library = Some(Vec::default());
+                                    self.menu.library_status = Some(err.to_string());
+                                }
+                            }
+
+                            // Show what was scanned without waiting on input
+                            self.menu.egui_context.request_repaint();
+                        }
+                        Some(UiOutput::SetRomNotes { rom_notes }) => {
+                            if let Err(err) = self.rom_manager.set_rom_notes(rom_notes) {
+                                tracing::error!("Failed to save rom notes: {}", err);
+                                self.menu.library_status = Some(err.to_string());
+                            }
+                        }
+                        Some(UiOutput::InspectRom { rom_id }) => {
+                            let rom_header = self
+                                .rom_manager
+                                .open(rom_id, RomRequirement::Required)
+                                .ok_or_else(|| "Could not open the ROM".to_string())
+                                .and_then(|file| {
+                                    RomHeader::read(file).map_err(|err| err.to_string())
+                                })
+                                .and_then(|rom_header| {
+                                    rom_header.ok_or_else(|| "No header to show".to_string())
+                                })
+                                .map(|rom_header| {
+                                    let mut fields =
+                                        vec![("System", rom_header.system().to_string())];
+                                    fields.extend(rom_header.fields());
+                                    fields
+                                });
+
+                            self.menu.rom_header = Some((rom_id, rom_header));
+                        }
+                        Some(UiOutput::ExportRomNotes) => {
+                            let notes_directory = STORAGE_DIRECTORY.join("notes");
+                            let timestamp = SystemTime::now()
+                                .duration_since(UNIX_EPOCH)
+                                .unwrap_or_default()
+                                .as_secs();
+                            let path = notes_directory.join(format!("notes_{}.ron", timestamp));
+
+                            match create_dir_all(&notes_directory)
+                                .map_err(Into::into)
+                                .and_then(|()| self.rom_manager.export_rom_notes(&path))
+                            {
+                                Ok(count) => {
+                                    tracing::info!(
+                                        "Exported {} notes to {}",
+                                        count,
+                                        path.display()
+                                    );
+                                    self.menu.library_status = Some(format!(
+                                        "Exported {} notes to {}",
+                                        count,
+                                        path.display()
+                                    ));
+                                }
+                                Err(err) => {
+                                    tracing::error!("Failed to export rom notes: {}", err);
+                                    self.menu.library_status = Some(err.to_string());
+                                }
+                            }
+                        }
+                        Some(UiOutput::RefreshStatistics) => {
+                            match self.rom_manager.usage_statistics() {
+                                Ok(usage_statistics) => {
+                                    self.menu.usage_statistics = Some(usage_statistics);
+                                    self.menu.statistics_status = None;
+                                }
+                                Err(err) => {
+                                    tracing::error!("Failed to read usage statistics: {}", err);
+                                    self.menu.usage_statistics = Some(Default::default());
+                                    self.menu.statistics_status = Some(err.to_string());
+                                }
+                            }
+                        }
+                        Some(UiOutput::ExportStatistics) => {
+                            let statistics_directory = STORAGE_DIRECTORY.join("statistics");
+                            let timestamp = SystemTime::now()
+                                .duration_since(UNIX_EPOCH)
+                                .unwrap_or_default()
+                                .as_secs();
+                            let path =
+                                statistics_directory.join(format!("statistics_{}.json", timestamp));
+
+                            match create_dir_all(&statistics_directory)
+                                .map_err(Into::into)
+                                .and_then(|()| self.rom_manager.export_usage_statistics(&path))
+                            {
+                                Ok(()) => {
+                                    tracing::info!(
+                                        "Exported usage statistics to {}",
+                                        path.display()
+                                    );
+                                    self.menu.statistics_status =
+                                        Some(format!("Exported to {}", path.display()));
+                                }
+                                Err(err) => {
+                                    tracing::error!("Failed to export usage statistics: {}", err);
+                                    self.menu.statistics_status = Some(err.to_string());
+                                }
+                            }
+                        }
+                        Some(UiOutput::ClearStatistics) => {
+                            match self.rom_manager.clear_play_sessions() {
+                                Ok(()) => {
+                                    self.menu.usage_statistics = None;
+                                    self.menu.statistics_status = None;
+                                }
+                                Err(err) => {
+                                    tracing::error!("Failed to clear play sessions: {}", err);
+                                    self.menu.statistics_status = Some(err.to_string());
+                                }
                             }
                         }
                     }
 
+                    // Keep redrawing while something in the menu is animating, like import progress, or if the menu got closed
+                    let repaint = !self.menu.active
+                        || full_output
+                            .viewport_output
+                            .values()
+                            .any(|viewport_output| viewport_output.repaint_delay.is_zero());
+
                     window_context
                         .runtime_state
                         .redraw_menu(&self.menu.egui_context, full_output);
-                } else if let Some(MachineContext::Running(machine)) = &mut self.machine_context {
-                    let now = Instant::now();
-                    
-                    self.timing_tracker.frame_rendering_starting();
-                    machine.run();
-                    window_context.runtime_state.redraw(machine);
-                    self.timing_tracker.frame_rendering_ending();
-
-                    let total_time_taken = Instant::now() - now;
-                    let average_timings = self.timing_tracker.average_frame_timings();
-                    
-                    if total_time_taken > average_timings {
-                        machine.scheduler.too_slow();
-                    } 
-
-                    if total_time_taken < average_timings {
-                        machine.scheduler.too_fast();
+
+                    if repaint {
+                        window_context.window.request_redraw();
                     }
+                } else if let Some(MachineContext::Running(emulation_thread)) =
+                    &mut self.machine_context
+                {
+                    let (
+                        motion_smoothing,
+                        refresh_rate_sync,
+                        vsync,
+                        power_profile_settings,
+                        max_catch_up,
+                        pacing,
+                        watchdog_budget,
+                        rewind_budget,
+                        input_display,
+                    ) = {
+                        let global_config_guard = GLOBAL_CONFIG.read().unwrap();
 
-                    tracing::debug!(
-                        "Average framerate is {}",
-                        Duration::from_secs(1).as_secs_f32() / average_timings.as_secs_f32()
-                    );
+                        (
+                            global_config_guard.motion_smoothing,
+                            global_config_guard.refresh_rate_sync,
+                            global_config_guard.vsync(),
+                            global_config_guard.power_profile.settings(),
+                            global_config_guard.max_catch_up,
+                            global_config_guard.pacing,
+                            global_config_guard.watchdog_budget,
+                            global_config_guard.memory_budgets.rewind,
+                            InputDisplay::capture(
+                                &emulation_thread.input_manager,
+                                &global_config_guard.input_display,
+                            ),
+                        )
+                    };
+                    emulation_thread.set_max_catch_up(max_catch_up);
+                    emulation_thread.set_pacing(pacing);
+                    self.menu.audio_output = emulation_thread.has_audio_output();
+                    emulation_thread.set_watchdog_budget(watchdog_budget);
+                    emulation_thread.set_rewind_budget(rewind_budget);
+                    window_context
+                        .runtime_state
+                        .set_input_display(input_display);
+
+                    // Frames stop coming while a component is stuck, so say why instead of sitting there frozen
+                    if let Some(runaway) = emulation_thread.take_runaway() {
+                        self.menu.runaway_component = Some(format!(
+                            "{} ({}) has been running for {:?} without returning",
+                            runaway.name, runaway.component_id.0, runaway.running_for
+                        ));
+                        self.menu.active = true;
+                    }
+
+                    match self.frame_presentation_pacer.next_present(motion_smoothing) {
+                        PresentKind::Fresh => {
+                            // Syncing to the host refresh rate is pointless if presenting does not wait for it, and fast forwarding should not wait on it either
+                            let fast_forwarding = emulation_thread.speed() > NORMAL_SPEED;
+
+                            emulation_thread.lock_allotted_time(
+                                self.frame_presentation_pacer
+                                    .scheduler_allotted_time(
+                                        refresh_rate_sync,
+                                        motion_smoothing,
+                                        power_profile_settings.speed_tolerance,
+                                    )
+                                    .filter(|_| vsync && !fast_forwarding),
+                            );
+
+                            // Bring up the menu so the user can decide what to do with the crashed machine
+                            if emulation_thread.receive_frame() {
+                                if let Some(fault) = emulation_thread.fault() {
+                                    self.menu.machine_fault = Some(fault.to_string());
+                                    self.menu.active = true;
+                                }
+                            }
+
+                            if !self
+                                .frame_presentation_pacer
+                                .skip_fresh_frame(power_profile_settings.frame_skip)
+                            {
+                                if let Some(frame) = emulation_thread.frame() {
+                                    let draw_start = Instant::now();
+                                    window_context.runtime_state.redraw(&frame.framebuffers);
+                                    self.frame_presentation_pacer
+                                        .finished_drawing(draw_start.elapsed());
+                                }
+                            }
+                        }
+                        PresentKind::Duplicate => {
+                            if let Some(frame) = emulation_thread.frame() {
+                                window_context.runtime_state.redraw(&frame.framebuffers);
+                            }
+                        }
+                        PresentKind::Black => {
+                            window_context.runtime_state.present_black_frame();
+                        }
+                    }
+
+                    if let Some(notice) = window_context.runtime_state.take_notice() {
+                        self.menu.renderer_notice = Some(notice);
+                    }
+
+                    // Displays are still drawing with whatever the backend had before it started over
+                    if window_context.runtime_state.display_data_lost() {
+                        window_context
+                            .runtime_state
+                            .initialize_machine(&emulation_thread.machine());
+                    }
 
                     window_context.window.request_redraw();
                 } else {
@@ -305,10 +1505,156 @@ impl<RS: RenderingBackendState<DisplayApiHandle = Arc<Window>>> ApplicationHandl
     }
 }
 
-fn setup_window(event_loop: &ActiveEventLoop) -> Arc<Window> {
+impl<RS: RenderingBackendState> PlatformRuntime<RS> {
+    /// Pauses emulation and writes out anything that would be lost if we never come back
+    fn suspend(&mut self) {
+        if self.suspended {
+            return;
+        }
+        self.suspended = true;
+
+        if let Some(MachineContext::Running(emulation_thread)) = &mut self.machine_context {
+            emulation_thread.pause();
+        }
+
+        if let Err(err) = GLOBAL_CONFIG.read().unwrap().save() {
+            tracing::error!("Could not save the config while suspending: {}", err);
+        }
+    }
+}
+
+/// Asks for the latest release in the background, the menu picks up what it finds in `update_status`
+///
+/// Not a method so it can be called while a window is borrowed out of the runtime
+pub(super) fn check_for_updates(
+    executor: &Executor,
+    update_status: &Arc<Mutex<Option<UpdateStatus>>>,
+) {
+    *update_status.lock().unwrap() = Some(UpdateStatus::Checking);
+    let update_status = update_status.clone();
+
+    executor.spawn("update_check", move |_| {
+        let status = check_for_update();
+        *update_status.lock().unwrap() = Some(status);
+    });
+}
+
+/// Builds a machine for the ROM at `path`, wired up to the keyboard like every machine we run
+fn open_machine(rom_manager: &Arc<RomManager>, path: &Path) -> Result<Machine, String> {
+    tracing::info!("Opening rom at {}", path.display());
+
+    let mut rom_file =
+        File::open(path).map_err(|err| format!("Could not open {}: {}", path.display(), err))?;
+    let rom_id = RomId::from_read(&mut rom_file);
+
+    // Check if we know about the game from the manager
+    let system = rom_manager
+        .rom_information
+        .r_transaction()
+        .unwrap()
+        .get()
+        .primary::<RomInfo>(rom_id)
+        .unwrap()
+        .map(|info| info.system)
+        .or_else(|| GameSystem::guess(path))
+        .ok_or_else(|| format!("Could not identify rom at {}", path.display()))?;
+
+    rom_manager.rom_paths.insert(rom_id, path.to_path_buf());
+
+    let machine = Machine::from_system(vec![rom_id], rom_manager.clone(), system)
+        .map_err(|err| format!("Could not build a {} machine: {}", system, err))?;
+
+    // HACK: Wire the keyboard to port 0
+    machine
+        .input_manager
+        .set_real_to_emulated_mapping(KEYBOARD_GAMEPAD_ID, 0);
+
+    // Make sure the system being run has a default mapping
+    GLOBAL_CONFIG
+        .write()
+        .unwrap()
+        .seed_gamepad_configs(machine.system, &machine.input_manager);
+
+    Ok(machine)
+}
+
+/// Stores the session that was running, if any
+fn finish_play_session(play_session: &mut Option<PlaySession>, rom_manager: &RomManager) {
+    if let Some(play_session) = play_session.take() {
+        if let Err(err) = rom_manager.record_play_session(play_session.finish()) {
+            tracing::error!("Failed to record play session: {}", err);
+        }
+    }
+}
+
+/// Takes down the running machine and everything the frontend set up for it, leaving the menu up
+fn close_machine<RS: RenderingBackendState>(
+    machine_context: &mut Option<MachineContext>,
+    menu: &mut MenuState,
+    play_session: &mut Option<PlaySession>,
+    rom_manager: &RomManager,
+    runtime_state: &mut RS,
+) {
+    if let Some(MachineContext::Running(emulation_thread)) = machine_context {
+        if let Some(tas_session) = menu.tas_session.take() {
+            tas_session.stop(&mut emulation_thread.machine());
+        }
+    }
+
+    // Stops the emulation thread, which has the machine write out save RAM and drop its display surfaces
+    *machine_context = None;
+    // Only now is nothing left drawing into what the renderer still holds onto
+    runtime_state.release_machine();
+    finish_play_session(play_session, rom_manager);
+
+    if let Err(err) = GLOBAL_CONFIG.read().unwrap().save() {
+        tracing::error!("Could not save the config: {}", err);
+    }
+
+    menu.active = true;
+    menu.machine_running = false;
+    menu.media_slots.clear();
+    menu.machine_fault = None;
+    menu.runaway_component = None;
+    menu.savestate_status = None;
+}
+
+/// Saves into a savestate slot, returning what to tell the user
+fn save_state(machine: &Machine, slot: u8) -> String {
+    match machine.save_state(slot) {
+        Ok(path) => {
+            tracing::info!("Saved state to {}", path.display());
+            format!("Saved slot {}", slot)
+        }
+        Err(err) => {
+            tracing::error!("Could not save state: {}", err);
+            format!("Could not save slot {}: {}", slot, err)
+        }
+    }
+}
+
+/// Loads a savestate slot, returning what to tell the user
+fn load_state(machine: &mut Machine, slot: u8) -> String {
+    match machine.load_state(slot) {
+        Ok(()) => format!("Loaded slot {}", slot),
+        Err(err) => {
+            tracing::error!("Could not load state: {}", err);
+            format!("Could not load slot {}: {}", slot, err)
+        }
+    }
+}
+
+pub(super) fn setup_window(event_loop: &ActiveEventLoop, title: &str) -> Arc<Window> {
     let window_attributes = Window::default_attributes()
-        .with_title("MultiEMU")
+        .with_title(title)
         .with_resizable(true)
         .with_transparent(false);
     Arc::new(event_loop.create_window(window_attributes).unwrap())
 }
+
+fn monitor_refresh_rate(window: &Window) -> Option<f32> {
+    window
+        .current_monitor()
+        .and_then(|monitor| monitor.refresh_rate_millihertz())
+        .map(|refresh_rate| refresh_rate as f32 / 1000.0)
+}
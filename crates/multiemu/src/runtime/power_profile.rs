@@ -0,0 +1,69 @@
+//! Power profiles bundle the settings that trade smoothness and looks for battery life, for laptops and handhelds
+
+use serde::{Deserialize, Serialize};
+use strum::{Display, EnumIter};
+
+/// Settings a [PowerProfile] decides, on top of what the user picked
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PowerProfileSettings {
+    /// How many fresh frames in a row can go undrawn while drawing cannot keep up
+    pub frame_skip: u32,
+    /// Forces vsync on or off, [None] leaves it to [crate::config::GlobalConfig::vsync]
+    pub vsync: Option<bool>,
+    /// How far off the host refresh rate can be from the emulated one before
+    /// [crate::runtime::frame_presentation::RefreshRateSync::MicroAdjust] gives up, 60.0988hz on 59.94hz is about 0.3%
+    pub speed_tolerance: f32,
+    /// Bounds in samples for the audio buffer, see [crate::runtime::audio::AdaptiveAudioBuffer::new]
+    pub audio_latency: (usize, usize),
+    /// Filters costing more than this are dropped, see [crate::runtime::video_filter::VideoFilter::cost]
+    pub max_video_filter_cost: u8,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, EnumIter, Display, PartialEq, Eq, Default)]
+pub enum PowerProfile {
+    /// Skips frames, always waits on vsync and sticks to cheap filters
+    BatterySaver,
+    /// Whatever the user configured
+    #[default]
+    Balanced,
+    /// Never skips frames and keeps audio latency as low as it goes
+    Performance,
+}
+
+impl PowerProfile {
+    pub fn settings(&self) -> PowerProfileSettings {
+        match self {
+            PowerProfile::BatterySaver => PowerProfileSettings {
+                frame_skip: 2,
+                // Presenting as fast as possible is the most power hungry thing we do
+                vsync: Some(true),
+                speed_tolerance: 0.02,
+                audio_latency: (1024, 8192),
+                max_video_filter_cost: 1,
+            },
+            PowerProfile::Balanced => PowerProfileSettings {
+                frame_skip: 1,
+                vsync: None,
+                speed_tolerance: 0.01,
+                audio_latency: (512, 4096),
+                max_video_filter_cost: u8::MAX,
+            },
+            PowerProfile::Performance => PowerProfileSettings {
+                frame_skip: 0,
+                vsync: None,
+                speed_tolerance: 0.01,
+                audio_latency: (256, 4096),
+                max_video_filter_cost: u8::MAX,
+            },
+        }
+    }
+
+    /// The profile after this one, wrapping around, for switching with a hotkey
+    pub fn next(&self) -> Self {
+        match self {
+            PowerProfile::BatterySaver => PowerProfile::Balanced,
+            PowerProfile::Balanced => PowerProfile::Performance,
+            PowerProfile::Performance => PowerProfile::BatterySaver,
+        }
+    }
+}
@@ -1,8 +1,12 @@
+use super::{input_display::InputDisplay, video_preset::VideoPreset};
 use crate::machine::Machine;
 use egui::FullOutput;
 use nalgebra::DMatrix;
 use palette::Srgba;
-use std::sync::{Arc, Mutex};
+use std::{
+    fmt::Debug,
+    sync::{Arc, Mutex},
+};
 
 pub enum DisplayComponentInitializationData {
     Software,
@@ -10,6 +14,67 @@ pub enum DisplayComponentInitializationData {
     Vulkan(super::platform::desktop::renderer::vulkan::VulkanDisplayComponentInitializationData),
 }
 
+impl DisplayComponentInitializationData {
+    /// A surface of `width` by `height` pixels on whichever backend handed this out, starting out black
+    pub fn create_surface(&self, width: usize, height: usize) -> Box<dyn RenderSurface> {
+        match self {
+            DisplayComponentInitializationData::Software => {
+                Box::new(SoftwareRenderSurface::new(width, height))
+            }
+            #[cfg(graphics_vulkan)]
+            DisplayComponentInitializationData::Vulkan(initialization_data) => Box::new(
+                super::platform::desktop::renderer::vulkan::surface::VulkanRenderSurface::new(
+                    initialization_data,
+                    width,
+                    height,
+                ),
+            ),
+        }
+    }
+}
+
+/// A texture a display component draws into, so display components do not need to know which backend is in use
+///
+/// Frames go in with [RenderSurface::upload] and show up once [RenderSurface::commit] is called
+pub trait RenderSurface: Debug + Send + Sync {
+    /// Copies a whole frame into the surface, it has to be the size the surface was created with
+    fn upload(&self, frame: &DMatrix<Srgba<u8>>);
+    /// Makes the last uploaded frame the one the backend draws
+    fn commit(&self);
+    /// Handle the backend draws from
+    fn framebuffer(&self) -> DisplayComponentFramebuffer;
+}
+
+/// Lives in regular memory, the software backend reads it directly so there is nothing to commit
+#[derive(Debug)]
+pub struct SoftwareRenderSurface {
+    framebuffer: Arc<Mutex<DMatrix<Srgba<u8>>>>,
+}
+
+impl SoftwareRenderSurface {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            framebuffer: Arc::new(Mutex::new(DMatrix::from_element(
+                width,
+                height,
+                Srgba::new(0, 0, 0, 255),
+            ))),
+        }
+    }
+}
+
+impl RenderSurface for SoftwareRenderSurface {
+    fn upload(&self, frame: &DMatrix<Srgba<u8>>) {
+        self.framebuffer.lock().unwrap().clone_from(frame);
+    }
+
+    fn commit(&self) {}
+
+    fn framebuffer(&self) -> DisplayComponentFramebuffer {
+        DisplayComponentFramebuffer::Software(self.framebuffer.clone())
+    }
+}
+
 #[derive(Clone)]
 pub enum DisplayComponentFramebuffer {
     Software(Arc<Mutex<DMatrix<Srgba<u8>>>>),
@@ -21,8 +86,26 @@ pub trait RenderingBackendState: Sized {
     type DisplayApiHandle: Clone + 'static;
 
     fn new(display_api_handle: Self::DisplayApiHandle) -> Self;
-    fn redraw(&mut self, machine: &Machine);
+    /// Draws the framebuffers of a finished frame, in the order of [Machine::display_components]
+    fn redraw(&mut self, framebuffers: &[DisplayComponentFramebuffer]);
     fn redraw_menu(&mut self, egui_context: &egui::Context, full_output: FullOutput);
+    /// Presents a fully black frame, used for black frame insertion
+    fn present_black_frame(&mut self);
     fn surface_resized(&mut self) {}
     fn initialize_machine(&mut self, machine: &Machine);
+    /// The machine is gone, let go of anything still pointing at what its display components handed out
+    fn release_machine(&mut self) {}
+    /// If what [RenderingBackendState::initialize_machine] handed out can no longer be used, like after the gpu was lost,
+    /// in which case it has to be called again
+    fn display_data_lost(&mut self) -> bool {
+        false
+    }
+    /// Backends that do not support a filter should draw unfiltered
+    fn set_video_preset(&mut self, _video_preset: &VideoPreset) {}
+    /// Drawn over every frame until replaced, backends that cannot draw it leave it out
+    fn set_input_display(&mut self, _input_display: Option<InputDisplay>) {}
+    /// Something the backend decided on its own that the user should hear about, like turning off a filter
+    fn take_notice(&mut self) -> Option<String> {
+        None
+    }
 }
@@ -34,7 +34,7 @@ impl TimingTracker {
         );
         self.recent_frame_timings.push(time_taken);
     }
-    
+
     pub fn average_frame_timings(&self) -> Duration {
         self.recent_frame_timings
             .iter()
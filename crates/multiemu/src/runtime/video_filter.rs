@@ -0,0 +1,413 @@
+use crate::host_cpu::multiversion;
+use nalgebra::DMatrix;
+use palette::Srgba;
+use rayon::{
+    iter::{IndexedParallelIterator, ParallelIterator},
+    slice::ParallelSliceMut,
+};
+use serde::{Deserialize, Serialize};
+use strum::{Display, EnumIter};
+
+/// Upscaling filters applied to a display component framebuffer before it is drawn
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, EnumIter, Display, PartialEq, Eq, Default)]
+pub enum VideoFilter {
+    #[default]
+    None,
+    Scale2x,
+    Scale3x,
+    Hq2x,
+    Xbr2x,
+    /// Only implemented as a compute shader
+    CrtLite,
+}
+
+/// Knobs for the filters that have them
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct VideoFilterParameters {
+    /// How much darker every third row is
+    pub scanline_strength: f32,
+    /// How much the aperture grille dims the other two phosphors
+    pub mask_strength: f32,
+}
+
+impl Default for VideoFilterParameters {
+    fn default() -> Self {
+        Self {
+            scanline_strength: 0.5,
+            mask_strength: 0.3,
+        }
+    }
+}
+
+impl VideoFilter {
+    pub fn scale(&self) -> usize {
+        match self {
+            VideoFilter::None => 1,
+            VideoFilter::Scale2x | VideoFilter::Hq2x | VideoFilter::Xbr2x => 2,
+            VideoFilter::Scale3x | VideoFilter::CrtLite => 3,
+        }
+    }
+
+    /// Rough relative cost of running the filter, for [crate::runtime::power_profile::PowerProfile] to cap
+    pub fn cost(&self) -> u8 {
+        match self {
+            VideoFilter::None => 0,
+            VideoFilter::Scale2x | VideoFilter::Scale3x => 1,
+            VideoFilter::Hq2x | VideoFilter::Xbr2x | VideoFilter::CrtLite => 2,
+        }
+    }
+
+    /// Runs the filter, returning [None] if this filter does nothing
+    pub fn apply(&self, source: &DMatrix<Srgba<u8>>) -> Option<DMatrix<Srgba<u8>>> {
+        let row_filter: fn(&Neighborhood, &mut [Srgba<u8>], usize) = match self {
+            VideoFilter::None | VideoFilter::CrtLite => return None,
+            VideoFilter::Scale2x => scale2x_row,
+            VideoFilter::Scale3x => scale3x_row,
+            VideoFilter::Hq2x => hq2x_row,
+            VideoFilter::Xbr2x => xbr2x_row,
+        };
+
+        let scale = self.scale();
+        let source_width = source.nrows();
+        let destination_width = source_width * scale;
+        let mut destination = DMatrix::from_element(
+            destination_width,
+            source.ncols() * scale,
+            Srgba::new(0, 0, 0, 0xff),
+        );
+
+        // Columns of the matrix are rows of the image, so each chunk is one output row
+        destination
+            .as_mut_slice()
+            .par_chunks_mut(destination_width)
+            .enumerate()
+            .for_each(|(destination_y, row)| {
+                let neighborhood = Neighborhood {
+                    source,
+                    y: destination_y / scale,
+                };
+
+                row_filter(&neighborhood, row, destination_y % scale);
+            });
+
+        Some(destination)
+    }
+}
+
+/// Runs a kernel over one output row, inlined into every filter so each gets its own vectorized copies
+#[inline(always)]
+fn filter_row(
+    neighborhood: &Neighborhood,
+    row: &mut [Srgba<u8>],
+    scale: usize,
+    sub_y: usize,
+    kernel: impl Fn(&Neighborhood, usize, usize, usize) -> Srgba<u8>,
+) {
+    for (destination_x, pixel) in row.iter_mut().enumerate() {
+        *pixel = kernel(
+            neighborhood,
+            destination_x / scale,
+            destination_x % scale,
+            sub_y,
+        );
+    }
+}
+
+multiversion! {
+    fn scale2x_row(neighborhood: &Neighborhood, row: &mut [Srgba<u8>], sub_y: usize) {
+        filter_row(neighborhood, row, 2, sub_y, scale2x)
+    }
+}
+
+multiversion! {
+    fn scale3x_row(neighborhood: &Neighborhood, row: &mut [Srgba<u8>], sub_y: usize) {
+        filter_row(neighborhood, row, 3, sub_y, scale3x)
+    }
+}
+
+multiversion! {
+    fn hq2x_row(neighborhood: &Neighborhood, row: &mut [Srgba<u8>], sub_y: usize) {
+        filter_row(neighborhood, row, 2, sub_y, hq2x)
+    }
+}
+
+multiversion! {
+    fn xbr2x_row(neighborhood: &Neighborhood, row: &mut [Srgba<u8>], sub_y: usize) {
+        filter_row(neighborhood, row, 2, sub_y, xbr2x)
+    }
+}
+
+struct Neighborhood<'a> {
+    source: &'a DMatrix<Srgba<u8>>,
+    y: usize,
+}
+
+impl Neighborhood<'_> {
+    /// Samples relative to the current row, clamping at the edges
+    #[inline]
+    fn get(&self, x: usize, offset_x: isize, offset_y: isize) -> Srgba<u8> {
+        let x = x
+            .saturating_add_signed(offset_x)
+            .min(self.source.nrows() - 1);
+        let y = self
+            .y
+            .saturating_add_signed(offset_y)
+            .min(self.source.ncols() - 1);
+
+        self.source[(x, y)]
+    }
+}
+
+fn scale2x(neighborhood: &Neighborhood, x: usize, sub_x: usize, sub_y: usize) -> Srgba<u8> {
+    let b = neighborhood.get(x, 0, -1);
+    let d = neighborhood.get(x, -1, 0);
+    let e = neighborhood.get(x, 0, 0);
+    let f = neighborhood.get(x, 1, 0);
+    let h = neighborhood.get(x, 0, 1);
+
+    if b == h || d == f {
+        return e;
+    }
+
+    match (sub_x, sub_y) {
+        (0, 0) if d == b => d,
+        (1, 0) if b == f => f,
+        (0, 1) if d == h => d,
+        (1, 1) if h == f => f,
+        _ => e,
+    }
+}
+
+fn scale3x(neighborhood: &Neighborhood, x: usize, sub_x: usize, sub_y: usize) -> Srgba<u8> {
+    let a = neighborhood.get(x, -1, -1);
+    let b = neighborhood.get(x, 0, -1);
+    let c = neighborhood.get(x, 1, -1);
+    let d = neighborhood.get(x, -1, 0);
+    let e = neighborhood.get(x, 0, 0);
+    let f = neighborhood.get(x, 1, 0);
+    let g = neighborhood.get(x, -1, 1);
+    let h = neighborhood.get(x, 0, 1);
+    let i = neighborhood.get(x, 1, 1);
+
+    if b == h || d == f {
+        return e;
+    }
+
+    match (sub_x, sub_y) {
+        (0, 0) if d == b => d,
+        (1, 0) if (d == b && e != c) || (b == f && e != a) => b,
+        (2, 0) if b == f => f,
+        (0, 1) if (d == b && e != g) || (d == h && e != a) => d,
+        (2, 1) if (b == f && e != i) || (h == f && e != c) => f,
+        (0, 2) if d == h => d,
+        (1, 2) if (d == h && e != i) || (h == f && e != g) => h,
+        (2, 2) if h == f => f,
+        _ => e,
+    }
+}
+
+/// hq2x worked out one corner at a time, mirrored like [xbr2x] so the neighbors toward the corner are always at +1
+///
+/// The original picks from a 256 case table of which neighbors differ from the center, this uses the same
+/// interpolations but only decides on the three neighbors touching the corner
+fn hq2x(neighborhood: &Neighborhood, x: usize, sub_x: usize, sub_y: usize) -> Srgba<u8> {
+    let direction_x: isize = if sub_x == 0 { -1 } else { 1 };
+    let direction_y: isize = if sub_y == 0 { -1 } else { 1 };
+    let sample =
+        |offset_x, offset_y| neighborhood.get(x, offset_x * direction_x, offset_y * direction_y);
+
+    let e = sample(0, 0);
+    let side = sample(1, 0);
+    let vertical = sample(0, 1);
+    let diagonal = sample(1, 1);
+
+    let side_differs = yuv_differs(e, side);
+    let vertical_differs = yuv_differs(e, vertical);
+    let diagonal_differs = yuv_differs(e, diagonal);
+
+    // An edge runs across the corner, rounded off harder if the other side is one solid color
+    if side_differs && vertical_differs && !yuv_differs(side, vertical) {
+        return if diagonal_differs && !yuv_differs(diagonal, side) {
+            interpolate([(e, 2), (side, 3), (vertical, 3)])
+        } else {
+            interpolate([(e, 2), (side, 1), (vertical, 1)])
+        };
+    }
+
+    // Something only touching the corner bleeds in a little
+    if diagonal_differs && !side_differs && !vertical_differs {
+        return interpolate([(e, 3), (diagonal, 1)]);
+    }
+
+    e
+}
+
+/// The thresholds hq2x uses to call two colors different
+#[inline]
+fn yuv_differs(a: Srgba<u8>, b: Srgba<u8>) -> bool {
+    let yuv = |pixel: Srgba<u8>| {
+        let (red, green, blue) = (pixel.red as i32, pixel.green as i32, pixel.blue as i32);
+
+        [
+            (red + green + blue) / 4,
+            (red - blue) / 4,
+            (2 * green - red - blue) / 8,
+        ]
+    };
+    let [a_y, a_u, a_v] = yuv(a);
+    let [b_y, b_u, b_v] = yuv(b);
+
+    a_y.abs_diff(b_y) > 48 || a_u.abs_diff(b_u) > 7 || a_v.abs_diff(b_v) > 6
+}
+
+/// Weighted average of a few pixels
+#[inline]
+fn interpolate<const N: usize>(weighted: [(Srgba<u8>, u32); N]) -> Srgba<u8> {
+    let total: u32 = weighted.iter().map(|(_, weight)| weight).sum();
+    let channel = |channel: fn(&Srgba<u8>) -> u8| {
+        (weighted
+            .iter()
+            .map(|(pixel, weight)| channel(pixel) as u32 * weight)
+            .sum::<u32>()
+            / total) as u8
+    };
+
+    Srgba::new(
+        channel(|pixel| pixel.red),
+        channel(|pixel| pixel.green),
+        channel(|pixel| pixel.blue),
+        channel(|pixel| pixel.alpha),
+    )
+}
+
+/// Level 1 xBR, each output pixel looks at the edge running past its own corner
+fn xbr2x(neighborhood: &Neighborhood, x: usize, sub_x: usize, sub_y: usize) -> Srgba<u8> {
+    // Mirror the kernel so the corner being computed is always the bottom right one
+    let direction_x: isize = if sub_x == 0 { -1 } else { 1 };
+    let direction_y: isize = if sub_y == 0 { -1 } else { 1 };
+    let sample =
+        |offset_x, offset_y| neighborhood.get(x, offset_x * direction_x, offset_y * direction_y);
+
+    let e = sample(0, 0);
+    let f = sample(1, 0);
+    let h = sample(0, 1);
+
+    if e == f || e == h {
+        return e;
+    }
+
+    let b = sample(0, -1);
+    let c = sample(1, -1);
+    let d = sample(-1, 0);
+    let g = sample(-1, 1);
+    let i = sample(1, 1);
+    let f4 = sample(2, 0);
+    let i4 = sample(2, 1);
+    let h5 = sample(0, 2);
+    let i5 = sample(1, 2);
+
+    let edge_across = color_distance(e, c)
+        + color_distance(e, g)
+        + color_distance(i, f4)
+        + color_distance(i, h5)
+        + 4 * color_distance(h, f);
+    let edge_along = color_distance(h, d)
+        + color_distance(h, i5)
+        + color_distance(f, i4)
+        + color_distance(f, b)
+        + 4 * color_distance(e, i);
+
+    if edge_across >= edge_along {
+        return e;
+    }
+
+    let new_pixel = if color_distance(e, f) <= color_distance(e, h) {
+        f
+    } else {
+        h
+    };
+
+    blend(e, new_pixel)
+}
+
+/// Weighted YUV distance used by xBR
+#[inline]
+fn color_distance(a: Srgba<u8>, b: Srgba<u8>) -> u32 {
+    let [a_y, a_u, a_v] = to_yuv(a);
+    let [b_y, b_u, b_v] = to_yuv(b);
+
+    48 * a_y.abs_diff(b_y) + 7 * a_u.abs_diff(b_u) + 6 * a_v.abs_diff(b_v)
+}
+
+#[inline]
+fn to_yuv(pixel: Srgba<u8>) -> [i32; 3] {
+    let (red, green, blue) = (pixel.red as i32, pixel.green as i32, pixel.blue as i32);
+
+    [
+        (299 * red + 587 * green + 114 * blue) / 1000,
+        (-169 * red - 331 * green + 500 * blue) / 1000,
+        (500 * red - 419 * green - 81 * blue) / 1000,
+    ]
+}
+
+#[inline]
+fn blend(a: Srgba<u8>, b: Srgba<u8>) -> Srgba<u8> {
+    Srgba::new(
+        a.red.midpoint(b.red),
+        a.green.midpoint(b.green),
+        a.blue.midpoint(b.blue),
+        a.alpha.midpoint(b.alpha),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use strum::IntoEnumIterator;
+
+    const BLACK: Srgba<u8> = Srgba::new(0, 0, 0, 0xff);
+    const WHITE: Srgba<u8> = Srgba::new(0xff, 0xff, 0xff, 0xff);
+
+    #[test]
+    fn flat_images_stay_flat() {
+        let source = DMatrix::from_element(8, 6, WHITE);
+
+        for filter in VideoFilter::iter()
+            .filter(|filter| ![VideoFilter::None, VideoFilter::CrtLite].contains(filter))
+        {
+            let destination = filter.apply(&source).unwrap();
+
+            assert_eq!(destination.nrows(), 8 * filter.scale());
+            assert_eq!(destination.ncols(), 6 * filter.scale());
+            assert!(destination.iter().all(|pixel| *pixel == WHITE));
+        }
+    }
+
+    #[test]
+    fn scale2x_smooths_diagonals() {
+        // A white staircase running from the top left to the bottom right
+        let source = DMatrix::from_fn(3, 3, |x, y| if x <= y { WHITE } else { BLACK });
+        let destination = VideoFilter::Scale2x.apply(&source).unwrap();
+
+        // The top right of the center pixel is taken over by the black above and right of it
+        assert_eq!(destination[(2, 2)], WHITE);
+        assert_eq!(destination[(3, 2)], BLACK);
+        assert_eq!(destination[(2, 3)], WHITE);
+        assert_eq!(destination[(3, 3)], WHITE);
+    }
+
+    #[test]
+    fn hq2x_rounds_off_lone_pixels() {
+        let source = DMatrix::from_fn(3, 3, |x, y| if (x, y) == (1, 1) { WHITE } else { BLACK });
+        let destination = VideoFilter::Hq2x.apply(&source).unwrap();
+        let rounded = Srgba::new(63, 63, 63, 0xff);
+
+        for (x, y) in [(2, 2), (3, 2), (2, 3), (3, 3)] {
+            assert_eq!(destination[(x, y)], rounded);
+        }
+
+        // The black pixel diagonal to it only gets a hint of it in the touching corner
+        assert_eq!(destination[(1, 1)], rounded);
+        assert_eq!(destination[(0, 0)], BLACK);
+    }
+}
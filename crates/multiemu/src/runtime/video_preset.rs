@@ -0,0 +1,207 @@
+//! Filter, filter knobs, scaling and bezel bundled up so a look can be handed to someone else
+
+use super::video_filter::{VideoFilter, VideoFilterParameters};
+use crate::config::{GlobalConfig, STORAGE_DIRECTORY};
+use nalgebra::Vector2;
+use ron::ser::PrettyConfig;
+use serde::{Deserialize, Serialize};
+use std::{
+    error::Error,
+    fs::{copy, create_dir_all, File},
+    path::{Path, PathBuf},
+    sync::LazyLock,
+};
+use strum::{Display, EnumIter};
+
+pub const VIDEO_PRESET_EXTENSION: &str = "ron";
+
+/// Shared between profiles, a look is not something personal
+pub static VIDEO_PRESET_DIRECTORY: LazyLock<PathBuf> =
+    LazyLock::new(|| STORAGE_DIRECTORY.join("video_presets"));
+
+/// How the picture is fit into the window
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, EnumIter, Display, PartialEq, Eq, Default)]
+pub enum ScalingMode {
+    /// Fills the whole window, aspect ratio be damned
+    #[default]
+    Stretch,
+    /// As big as it gets while keeping the aspect ratio
+    Fit,
+    /// Only whole multiples, so every source pixel is the same size
+    Integer,
+}
+
+impl ScalingMode {
+    /// Where in `destination` a picture of `source` size goes, as an offset and a size
+    pub fn viewport(
+        &self,
+        source: Vector2<usize>,
+        destination: Vector2<usize>,
+    ) -> (Vector2<usize>, Vector2<usize>) {
+        if source.min() == 0 {
+            return (Vector2::zeros(), destination);
+        }
+
+        let size = match self {
+            ScalingMode::Stretch => return (Vector2::zeros(), destination),
+            ScalingMode::Fit => {
+                let scale = (destination.x as f32 / source.x as f32)
+                    .min(destination.y as f32 / source.y as f32);
+
+                source
+                    .map(|dimension| (dimension as f32 * scale).round() as usize)
+                    .zip_map(&destination, usize::min)
+            }
+            ScalingMode::Integer => {
+                // Too small a window still gets a picture, just not a pixel perfect one
+                let scale = (destination.x / source.x).min(destination.y / source.y);
+
+                if scale == 0 {
+                    return ScalingMode::Fit.viewport(source, destination);
+                }
+
+                source * scale
+            }
+        };
+
+        ((destination - size) / 2, size)
+    }
+}
+
+/// A look for the screen, in a form that can be passed around
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct VideoPreset {
+    pub name: String,
+    pub video_filter: VideoFilter,
+    #[serde(default)]
+    pub video_filter_parameters: VideoFilterParameters,
+    #[serde(default)]
+    pub scaling_mode: ScalingMode,
+    /// Image to frame the screen with, just a file name next to the preset when exported
+    #[serde(default)]
+    pub bezel: Option<PathBuf>,
+}
+
+impl VideoPreset {
+    /// Grabs the look the user currently has configured
+    pub fn from_config(global_config: &GlobalConfig, name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            video_filter: global_config.video_filter,
+            video_filter_parameters: global_config.video_filter_parameters,
+            scaling_mode: global_config.scaling_mode,
+            bezel: global_config.bezel.clone(),
+        }
+    }
+
+    /// Makes this the look for every game without one of its own
+    pub fn apply_to_config(self, global_config: &mut GlobalConfig) {
+        global_config.video_filter = self.video_filter;
+        global_config.video_filter_parameters = self.video_filter_parameters;
+        global_config.scaling_mode = self.scaling_mode;
+        global_config.bezel = self.bezel;
+    }
+
+    /// Where exporting a preset named like this puts it
+    pub fn path(name: &str) -> PathBuf {
+        VIDEO_PRESET_DIRECTORY
+            .join(name)
+            .with_extension(VIDEO_PRESET_EXTENSION)
+    }
+
+    /// Writes the preset with its bezel copied next to it, so the directory can be zipped up and shared as is
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), Box<dyn Error>> {
+        let path = path.as_ref();
+        let directory = path.parent().unwrap_or(Path::new(""));
+        create_dir_all(directory)?;
+
+        let mut preset = self.clone();
+        if let Some(bezel) = &self.bezel {
+            let bezel_name = PathBuf::from(bezel.file_name().ok_or("Bezel is not a file")?);
+
+            if bezel != &directory.join(&bezel_name) {
+                copy(bezel, directory.join(&bezel_name))?;
+            }
+            preset.bezel = Some(bezel_name);
+        }
+
+        let preset_file = File::create(path)?;
+        ron::ser::to_writer_pretty(preset_file, &preset, PrettyConfig::default())?;
+
+        Ok(())
+    }
+
+    /// Reads a preset, resolving its bezel against where the preset is
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Box<dyn Error>> {
+        let path = path.as_ref();
+        let preset_file = File::open(path)?;
+        let mut preset: Self = ron::de::from_reader(preset_file)?;
+
+        if let Some(bezel) = &mut preset.bezel {
+            *bezel = path.parent().unwrap_or(Path::new("")).join(&*bezel);
+        }
+
+        Ok(preset)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn viewports() {
+        let source = Vector2::new(256, 240);
+        let destination = Vector2::new(1000, 600);
+
+        assert_eq!(
+            ScalingMode::Stretch.viewport(source, destination),
+            (Vector2::zeros(), destination)
+        );
+        assert_eq!(
+            ScalingMode::Fit.viewport(source, destination),
+            (Vector2::new(180, 0), Vector2::new(640, 600))
+        );
+        assert_eq!(
+            ScalingMode::Integer.viewport(source, destination),
+            (Vector2::new(244, 60), Vector2::new(512, 480))
+        );
+        // Smaller than the source
+        assert_eq!(
+            ScalingMode::Integer.viewport(source, Vector2::new(128, 240)),
+            (Vector2::new(0, 60), Vector2::new(128, 120))
+        );
+    }
+
+    #[test]
+    fn bezel_travels_with_the_preset() {
+        let test_directory =
+            std::env::temp_dir().join(format!("multiemu_video_preset_{}", std::process::id()));
+        let bezel = test_directory.join("artwork").join("tv.png");
+        create_dir_all(bezel.parent().unwrap()).unwrap();
+        std::fs::write(&bezel, "bezel").unwrap();
+
+        let preset = VideoPreset {
+            name: "CRT".to_string(),
+            video_filter: VideoFilter::CrtLite,
+            video_filter_parameters: VideoFilterParameters::default(),
+            scaling_mode: ScalingMode::Integer,
+            bezel: Some(bezel),
+        };
+        let preset_path = test_directory.join("shared").join("crt.ron");
+        preset.save(&preset_path).unwrap();
+
+        let loaded = VideoPreset::load(&preset_path).unwrap();
+        assert_eq!(
+            loaded.bezel.as_deref(),
+            Some(test_directory.join("shared").join("tv.png").as_path())
+        );
+        assert_eq!(
+            std::fs::read_to_string(loaded.bezel.unwrap()).unwrap(),
+            "bezel"
+        );
+        assert_eq!(loaded.scaling_mode, ScalingMode::Integer);
+
+        std::fs::remove_dir_all(test_directory).unwrap();
+    }
+}
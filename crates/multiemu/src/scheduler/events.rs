@@ -0,0 +1,88 @@
+use crate::component::ComponentId;
+use std::{
+    collections::BTreeSet,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+
+/// Wakeups event driven components asked for, so they can sleep instead of running every tick
+///
+/// Components that need this should take [crate::machine::MachineBuilder::events] while being built
+#[derive(Debug, Default)]
+pub struct EventQueue {
+    /// Ordered by when they are due, then by component so the same time always wakes in the same order
+    pending: Mutex<BTreeSet<(Duration, ComponentId)>>,
+    /// Emulated time in nanoseconds as of the tick being run
+    now: AtomicU64,
+}
+
+impl EventQueue {
+    /// Wakes `component_id` on the first tick at or after `at` in emulated time
+    pub fn schedule_event(&self, component_id: ComponentId, at: Duration) {
+        self.pending.lock().unwrap().insert((at, component_id));
+    }
+
+    /// Wakes `component_id` once `delay` of emulated time has passed
+    pub fn schedule_event_in(&self, component_id: ComponentId, delay: Duration) {
+        self.schedule_event(component_id, self.now() + delay);
+    }
+
+    /// Drops every wakeup `component_id` has pending, like when a timer gets reprogrammed
+    pub fn cancel_events(&self, component_id: ComponentId) {
+        self.pending
+            .lock()
+            .unwrap()
+            .retain(|(_, pending_component_id)| *pending_component_id != component_id);
+    }
+
+    /// Emulated time as of the tick being run
+    pub fn now(&self) -> Duration {
+        Duration::from_nanos(self.now.load(Ordering::Relaxed))
+    }
+
+    pub(super) fn set_now(&self, now: Duration) {
+        self.now.store(
+            now.as_nanos().try_into().unwrap_or(u64::MAX),
+            Ordering::Relaxed,
+        );
+    }
+
+    /// When the next wakeup is due
+    pub(super) fn next_due(&self) -> Option<Duration> {
+        self.pending.lock().unwrap().first().map(|(at, _)| *at)
+    }
+
+    /// Takes out every wakeup due by now, in the order they are due
+    pub(super) fn take_due(&self) -> Vec<ComponentId> {
+        let now = self.now();
+        let mut pending = self.pending.lock().unwrap();
+        let mut due = Vec::default();
+
+        while let Some((at, component_id)) = pending.first().copied() {
+            if at > now {
+                break;
+            }
+
+            pending.pop_first();
+            due.push(component_id);
+        }
+
+        due
+    }
+
+    pub(super) fn pending(&self) -> Vec<(Duration, ComponentId)> {
+        self.pending.lock().unwrap().iter().copied().collect()
+    }
+
+    /// Replaces every pending wakeup, like when restoring a snapshot
+    pub(super) fn set_pending(&self, pending: impl IntoIterator<Item = (Duration, ComponentId)>) {
+        *self.pending.lock().unwrap() = pending.into_iter().collect();
+    }
+
+    pub fn clear(&self) {
+        self.pending.lock().unwrap().clear();
+    }
+}
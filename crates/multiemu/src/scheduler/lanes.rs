@@ -0,0 +1,174 @@
+use super::{stats::Profiler, watchdog::Watchdog};
+use crate::{
+    component::{schedulable::RunContext, ComponentId},
+    machine::component_store::ComponentStore,
+    memory::set_accessing_component,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    time::{Duration, Instant},
+};
+
+/// How the components sharing a time slice get run
+#[derive(Debug, Clone, Default)]
+pub struct ExecutionLanes {
+    /// Run one after another on the thread driving the scheduler, in this order
+    ///
+    /// Displays go here since they own graphics resources that are not safe to touch from the pool, as does anything
+    /// with an ordering relationship to another component in the slice
+    serial: Vec<ComponentId>,
+    /// Nothing else in the slice cares about these, so they go on the rayon pool
+    parallel: Vec<ComponentId>,
+}
+
+impl ExecutionLanes {
+    /// `component_ids` are expected to already be in the order they have to run in
+    pub fn new(components: &ComponentStore, component_ids: &[ComponentId]) -> Self {
+        // Ordering only matters between components that are actually in this slice
+        let ordered: HashSet<_> = component_ids
+            .iter()
+            .flat_map(|component_id| {
+                let component_info = components
+                    .get(*component_id)
+                    .and_then(|table| table.as_schedulable.as_ref())
+                    .expect("Schedule referencing non existant component");
+
+                component_info
+                    .run_after
+                    .iter()
+                    .chain(component_info.run_before.iter())
+                    .filter(|other| component_ids.contains(other))
+                    .flat_map(move |other| [*component_id, *other])
+            })
+            .collect();
+
+        let (serial, parallel) = component_ids.iter().partition(|component_id| {
+            let is_display = components
+                .get(**component_id)
+                .is_some_and(|table| table.as_display.is_some());
+
+            is_display || ordered.contains(*component_id)
+        });
+
+        Self { serial, parallel }
+    }
+
+    /// `cycles` is how many times each component ran before, see [RunContext::cycles]
+    pub fn run(
+        &self,
+        components: &ComponentStore,
+        ticks_run: u64,
+        now: Duration,
+        cycles: &HashMap<ComponentId, u64>,
+        profiler: Option<&Profiler>,
+        watchdog: &Watchdog,
+    ) {
+        let context = |component_id: &ComponentId| RunContext {
+            timestamp: now,
+            cycles: cycles.get(component_id).copied().unwrap_or_default(),
+        };
+
+        if self.parallel.is_empty() {
+            for component_id in self.serial.iter() {
+                run_component(
+                    components,
+                    *component_id,
+                    ticks_run,
+                    context(component_id),
+                    profiler,
+                    watchdog,
+                );
+            }
+
+            return;
+        }
+
+        // The scope body stays on this thread, so the serial lane never leaves it
+        rayon::in_place_scope(|scope| {
+            for component_id in self.parallel.iter() {
+                let context = context(component_id);
+
+                scope.spawn(move |_| {
+                    run_component(
+                        components,
+                        *component_id,
+                        ticks_run,
+                        context,
+                        profiler,
+                        watchdog,
+                    )
+                });
+            }
+
+            for component_id in self.serial.iter() {
+                run_component(
+                    components,
+                    *component_id,
+                    ticks_run,
+                    context(component_id),
+                    profiler,
+                    watchdog,
+                );
+            }
+        });
+    }
+}
+
+/// Runs a component under `watchdog`, adding how long it took to `profiler` if we are profiling
+pub fn run_component(
+    components: &ComponentStore,
+    component_id: ComponentId,
+    ticks_run: u64,
+    context: RunContext,
+    profiler: Option<&Profiler>,
+    watchdog: &Watchdog,
+) {
+    if let Some(component_info) = components
+        .get(component_id)
+        .and_then(|table| table.as_schedulable.as_ref())
+    {
+        let run_start = profiler.map(|_| Instant::now());
+        set_accessing_component(Some(component_id));
+        watchdog.run_started(component_id);
+        component_info.component.run(ticks_run, context);
+        watchdog.run_finished(component_id);
+        set_accessing_component(None);
+
+        if let (Some(profiler), Some(run_start)) = (profiler, run_start) {
+            profiler.record(component_id, run_start.elapsed());
+        }
+    } else {
+        panic!("Schedule referencing non existant component");
+    }
+}
+
+/// Wakes an event driven component under `watchdog`, adding how long it took to `profiler` if we are profiling
+pub fn wake_component(
+    components: &ComponentStore,
+    component_id: ComponentId,
+    now: Duration,
+    profiler: Option<&Profiler>,
+    watchdog: &Watchdog,
+) {
+    let Some(component_info) = components
+        .get(component_id)
+        .and_then(|table| table.as_event_driven.as_ref())
+    else {
+        tracing::warn!(
+            "Component {:?} scheduled an event but is not event driven",
+            component_id
+        );
+        return;
+    };
+
+    let run_start = profiler.map(|_| Instant::now());
+    set_accessing_component(Some(component_id));
+    watchdog.run_started(component_id);
+    component_info.component.wake(now);
+    watchdog.run_finished(component_id);
+    set_accessing_component(None);
+
+    if let (Some(profiler), Some(run_start)) = (profiler, run_start) {
+        profiler.record(component_id, run_start.elapsed());
+    }
+}
@@ -1,199 +1,770 @@
-use crate::component::ComponentId;
+use crate::component::{schedulable::RunContext, ComponentId};
 use crate::machine::component_store::ComponentStore;
-use itertools::Itertools;
+use crate::machine::fault::FaultReporter;
+use lanes::{run_component, wake_component, ExecutionLanes};
+use num::rational::Ratio;
 use num::ToPrimitive;
-use num::{integer::lcm, rational::Ratio, Integer};
-use rangemap::RangeMap;
+use schedule::{Schedule, TimeSlice};
 use serde::{Deserialize, Serialize};
+use stats::Profiler;
+pub use stats::{ComponentStats, SchedulerStats};
 use std::{
     collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
     time::{Duration, Instant},
 };
+pub use watchdog::{RunawayComponent, Watchdog};
 
-#[derive(Serialize, Deserialize, Clone)]
+mod events;
+mod lanes;
+mod pacing;
+mod schedule;
+mod stats;
+mod watchdog;
+
+pub use events::EventQueue;
+pub use pacing::{AudioClock, Pacing};
+
+/// Emulated time passing exactly as fast as real time
+pub const NORMAL_SPEED: Ratio<u64> = Ratio::new_raw(1, 1);
+
+/// How much real time [Scheduler::run] catches up on after falling behind until told otherwise
+pub const DEFAULT_MAX_CATCH_UP: Duration = Duration::from_millis(250);
+
+/// How long a single component run can take before the watchdog reports it, until told otherwise
+pub const DEFAULT_WATCHDOG_BUDGET: Duration = Duration::from_secs(2);
+
+/// How much [Scheduler::run] does per step while stepping
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepGranularity {
+    /// As much emulated time as one normal run covers
+    Frame,
+    /// A single entry of the schedule
+    Tick,
+}
+
+/// Lets components change how often they run while the machine is running, like a processor switching clock speeds
+///
+/// Components that need this should take [crate::machine::MachineBuilder::frequency_changes] while being built
+#[derive(Debug, Default)]
+pub struct FrequencyChanges {
+    requested: Mutex<Vec<(ComponentId, Ratio<u64>)>>,
+    /// Checked after every time slice, so the lock is only taken when something was requested
+    pending: AtomicBool,
+}
+
+impl FrequencyChanges {
+    /// Has `component_id` run `frequency` times per second once the current time slice finishes
+    pub fn request(&self, component_id: ComponentId, frequency: Ratio<u64>) {
+        self.requested
+            .lock()
+            .unwrap()
+            .push((component_id, frequency));
+        self.pending.store(true, Ordering::Release);
+    }
+
+    fn take(&self) -> Option<Vec<(ComponentId, Ratio<u64>)>> {
+        if !self.pending.swap(false, Ordering::AcqRel) {
+            return None;
+        }
+
+        Some(std::mem::take(&mut self.requested.lock().unwrap()))
+    }
+}
+
+/// Where a scheduler is in emulated time, which is everything about it a snapshot has to capture
+///
+/// The schedule itself is left out since it is rebuilt from the components on restore
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SchedulerState {
+    current_tick: u64,
+    #[serde(default)]
+    elapsed_ticks: u64,
+    #[serde(default)]
+    frequency_overrides: HashMap<ComponentId, Ratio<u64>>,
+    #[serde(default)]
+    rebuilt_at: (u64, Duration),
+    #[serde(default)]
+    events: Vec<(Duration, ComponentId)>,
+    #[serde(default)]
+    cycles: HashMap<ComponentId, u64>,
+}
+
+#[derive(Clone, Debug)]
 pub struct Scheduler {
     current_tick: u64,
-    rollover_tick: u64,
-    tick_real_time: Ratio<u64>,
-    // Stores precomputed periods for each component
-    schedule: RangeMap<u64, Vec<ComponentId>>,
+    /// Ticks run since the machine started, unlike the current tick this never wraps around
+    elapsed_ticks: u64,
+    /// How many times each schedulable component has run since the machine started, see [RunContext::cycles]
+    cycles: HashMap<ComponentId, u64>,
+    schedule: Schedule,
+    /// Components due at the current tick, kept around so working that out does not allocate every time
+    due: Vec<ComponentId>,
+    /// Frequencies components switched to while running, in place of the ones they were built with
+    frequency_overrides: HashMap<ComponentId, Ratio<u64>>,
+    /// Elapsed ticks and emulated time when the schedule was last rebuilt, ticks before then can be a different length
+    rebuilt_at: (u64, Duration),
     allotted_time: Duration,
+    /// Set when the frontend is pacing us to the host refresh rate
+    allotted_time_locked: bool,
+    paused: bool,
+    /// Real time spent running, time spent paused is never added so resuming does not try to catch up on it
+    running_time: Duration,
+    /// When we last ran, [None] right after being created or resumed
+    last_run: Option<Instant>,
+    /// Emulated nanoseconds real time has moved on by that we have not run yet
+    ///
+    /// Negative when the last run had to overshoot its target to finish a tick
+    time_owed: i128,
+    /// See [Scheduler::set_max_catch_up]
+    max_catch_up: Duration,
+    pacing: Pacing,
+    audio_clock: Arc<AudioClock>,
+    /// Set while debugging, in which case we only run when told to
+    step_granularity: Option<StepGranularity>,
+    pending_steps: u32,
+    /// Set when every run should cover exactly this many ticks, no matter how much real time passes
+    fixed_timestep: Option<u64>,
+    /// How much emulated time passes for every bit of real time
+    speed: Ratio<u64>,
+    /// How each time slice with more than one component runs, keyed by the components in it and filled in as we go
+    lanes: HashMap<Vec<ComponentId>, ExecutionLanes>,
+    frequency_changes: Arc<FrequencyChanges>,
+    events: Arc<EventQueue>,
+    watchdog: Arc<Watchdog>,
+    /// Set while profiling, see [Scheduler::set_profiling]
+    profiler: Option<Arc<Profiler>>,
+    stats: Option<SchedulerStats>,
 }
 
 impl Scheduler {
-    pub fn new(components: &ComponentStore) -> Self {
-        let component_infos: HashMap<_, _> = components
-            .iter()
-            .filter_map(|(component_id, table)| {
-                if let Some(schedulable_component) = &table.as_schedulable {
-                    return Some((component_id, schedulable_component.timings));
-                }
+    pub fn new(
+        components: &ComponentStore,
+        frequency_changes: Arc<FrequencyChanges>,
+        events: Arc<EventQueue>,
+    ) -> Self {
+        Self {
+            current_tick: 0,
+            elapsed_ticks: 0,
+            cycles: HashMap::default(),
+            schedule: Schedule::new(components, &HashMap::default()),
+            due: Vec::default(),
+            frequency_overrides: HashMap::default(),
+            rebuilt_at: (0, Duration::ZERO),
+            allotted_time: Duration::from_millis(16),
+            allotted_time_locked: false,
+            paused: false,
+            running_time: Duration::ZERO,
+            last_run: None,
+            time_owed: 0,
+            max_catch_up: DEFAULT_MAX_CATCH_UP,
+            pacing: Pacing::default(),
+            audio_clock: Arc::default(),
+            step_granularity: None,
+            pending_steps: 0,
+            fixed_timestep: None,
+            speed: NORMAL_SPEED,
+            lanes: HashMap::default(),
+            frequency_changes,
+            events,
+            watchdog: Arc::new(Watchdog::new(components)),
+            profiler: None,
+            stats: None,
+        }
+    }
 
-                None
-            })
-            .collect();
+    /// Stops early if a component reports a fault, and does nothing while paused, returning how many ticks were run
+    pub fn run(&mut self, components: &ComponentStore, fault_reporter: &FaultReporter) -> u64 {
+        if self.paused {
+            return 0;
+        }
+
+        if let Some(step_granularity) = self.step_granularity {
+            return self.run_step(components, fault_reporter, step_granularity);
+        }
+
+        let mut ticks_run = 0;
+        let timestamp = Instant::now();
+
+        let real_time = match self.last_run.replace(timestamp) {
+            Some(last_run) => {
+                let real_time = timestamp.saturating_duration_since(last_run);
+                self.running_time += real_time;
 
-        for (component, component_timings) in component_infos.iter() {
+                real_time
+            }
+            None => self.allotted_time,
+        };
+
+        if let Some(fixed_timestep) = self.fixed_timestep {
+            return self.run_ticks(components, fault_reporter, fixed_timestep);
+        }
+
+        // Audio paced runs cover exactly what was played, and pinned runs are paced by the frontend so each one covers
+        // the same emulated time however late it is
+        let real_time = if self.pacing == Pacing::Audio && self.audio_clock.is_active() {
+            self.audio_clock.take()
+        } else if self.allotted_time_locked {
+            self.allotted_time
+        } else {
+            real_time
+        };
+        let max_owed = self.scale_by_speed(self.max_catch_up);
+        self.time_owed += self.scale_by_speed(real_time);
+
+        if self.time_owed > max_owed {
             tracing::debug!(
-                "Component {:?} will run {} times per second",
-                component,
-                component_timings
+                "Fell {:?} behind, dropping everything past {:?}",
+                Duration::from_nanos(self.time_owed.try_into().unwrap_or(u64::MAX)),
+                self.max_catch_up
             );
+            self.time_owed = max_owed;
         }
 
-        let common_denominator = component_infos
-            .values()
-            .map(|ratio| *ratio.recip().denom())
-            .fold(1, |acc, denom| acc.lcm(&denom));
+        // Tracked in emulated time since a component changing frequency changes how long a tick is
+        let start = self.emulated_time();
+        let target = start + Duration::from_nanos(self.time_owed.max(0) as u64);
 
-        // Adjust numerators to the common denominator
-        let adjusted_numerators: HashMap<_, _> = component_infos
-            .iter()
-            .map(|(component_id, ratio)| {
-                let factor = common_denominator / ratio.denom();
-                (*component_id, ratio.numer() * factor)
-            })
-            .collect();
+        // Ensure we don't overstep the framerate
+        while self.allotted_time > timestamp.elapsed()
+            // ensure we don't overstate the emulated timespace
+            && self.emulated_time() < target
+            && !fault_reporter.is_faulted()
+        {
+            let tick_budget = self.ticks_covering(target - self.emulated_time());
+            ticks_run += self.run_tick(components, tick_budget);
+        }
 
-        let common_multiple = adjusted_numerators
-            .clone()
-            .into_values()
-            .reduce(lcm)
-            .unwrap_or(1);
+        // Whatever the allotted time did not leave room for is picked up next run
+        self.time_owed -= (self.emulated_time() - start).as_nanos() as i128;
 
-        let ratios: HashMap<_, _> = adjusted_numerators
-            .iter()
-            .map(|(component_id, numerator)| (*component_id, common_multiple / numerator))
-            .collect();
+        self.finish_profiling(components, timestamp, ticks_run);
 
-        // Fill out the schedule
-        let mut schedule = RangeMap::default();
+        ticks_run
+    }
 
-        let mut current_tick = 0;
-        while current_tick < common_denominator {
-            // This is (component_id, tick_rate, run_indication)
-            let to_run: Vec<_> = ratios
-                .iter()
-                .map(|(component_id, tick_rate)| {
-                    (*component_id, current_tick % *tick_rate, *tick_rate)
-                })
-                .sorted_by_key(|(_, run_indication, _)| *run_indication)
-                .collect();
-
-            if to_run.len() == 1 {
-                let (component_id, _, tick_rate) = to_run[0];
-                let time_slice = tick_rate;
-                schedule.insert(current_tick..current_tick + time_slice, vec![component_id]);
-                current_tick += time_slice;
-                continue;
+    /// How many emulated nanoseconds pass in `real_time` at our speed
+    fn scale_by_speed(&self, real_time: Duration) -> i128 {
+        (real_time.as_nanos() * *self.speed.numer() as u128 / *self.speed.denom() as u128)
+            .try_into()
+            .unwrap_or(i128::MAX)
+    }
+
+    /// Runs one step if one was requested, with no regard for how much real time it takes
+    fn run_step(
+        &mut self,
+        components: &ComponentStore,
+        fault_reporter: &FaultReporter,
+        step_granularity: StepGranularity,
+    ) -> u64 {
+        // Time between steps is not counted as running
+        self.last_run = None;
+
+        if self.pending_steps == 0 || fault_reporter.is_faulted() {
+            return 0;
+        }
+        self.pending_steps -= 1;
+
+        match step_granularity {
+            StepGranularity::Frame => {
+                let ticks_run = self.run_for(components, fault_reporter, self.allotted_time);
+
+                tracing::debug!("Stepped a frame of {} ticks", ticks_run);
+
+                ticks_run
             }
+            StepGranularity::Tick => {
+                let ticks_run = self.run_tick(components, u64::MAX);
 
-            // do the different scenarios for how many should run this turn
-            match to_run
-                .iter()
-                .filter(|(_, run_indication, _)| *run_indication == 0)
-                .count()
-            {
-                // Nothing is set to run here
-                0 => {
-                    current_tick += 1;
-                }
-                // Full efficient batching
-                1 => {
-                    let batch_size = to_run[1].2 - to_run[1].1;
-                    let (component_id, _, tick_rate) = to_run[0];
-                    let normalized_batch_size = batch_size / tick_rate;
-                    schedule.insert(
-                        current_tick..current_tick + normalized_batch_size,
-                        vec![component_id],
+                tracing::debug!(
+                    "Stepped {} ticks, now at tick {}",
+                    ticks_run,
+                    self.current_tick
+                );
+
+                ticks_run
+            }
+        }
+    }
+
+    /// Runs exactly `emulated_time` worth of ticks no matter how long it takes or if we are paused, returning how many ticks that was
+    pub fn run_for(
+        &mut self,
+        components: &ComponentStore,
+        fault_reporter: &FaultReporter,
+        emulated_time: Duration,
+    ) -> u64 {
+        let mut ticks_run = 0;
+        let timestamp = Instant::now();
+        let target = self.emulated_time() + emulated_time;
+
+        while self.emulated_time() < target && !fault_reporter.is_faulted() {
+            let tick_budget = self.ticks_covering(target - self.emulated_time());
+            ticks_run += self.run_tick(components, tick_budget);
+        }
+
+        self.finish_profiling(components, timestamp, ticks_run);
+
+        ticks_run
+    }
+
+    /// Runs exactly `ticks` ticks no matter how long it takes or if we are paused, returning how many were run before any fault
+    pub fn run_ticks(
+        &mut self,
+        components: &ComponentStore,
+        fault_reporter: &FaultReporter,
+        ticks: u64,
+    ) -> u64 {
+        let mut ticks_run = 0;
+        let timestamp = Instant::now();
+
+        while ticks_run < ticks && !fault_reporter.is_faulted() {
+            ticks_run += self.run_tick(components, ticks - ticks_run);
+        }
+
+        self.finish_profiling(components, timestamp, ticks_run);
+
+        ticks_run
+    }
+
+    /// How many ticks it takes to cover `emulated_time`
+    fn ticks_covering(&self, emulated_time: Duration) -> u64 {
+        let tick_real_time = self.schedule.tick_real_time();
+        let tick_nanoseconds = *tick_real_time.numer() as u128 * 1_000_000_000;
+        let ticks =
+            (emulated_time.as_nanos() * *tick_real_time.denom() as u128).div_ceil(tick_nanoseconds);
+
+        ticks.try_into().unwrap_or(u64::MAX)
+    }
+
+    /// Runs whatever is scheduled at the current tick, up to `max_ticks` of it, returning how many ticks that covered
+    fn run_tick(&mut self, components: &ComponentStore, max_ticks: u64) -> u64 {
+        let now = self.emulated_time();
+        self.events.set_now(now);
+
+        for component_id in self.events.take_due() {
+            wake_component(
+                components,
+                component_id,
+                now,
+                self.profiler.as_deref(),
+                &self.watchdog,
+            );
+        }
+
+        // Stop short of the next wakeup so it lands on the tick it was due
+        let max_ticks = match self.events.next_due() {
+            Some(due) => max_ticks.min(self.ticks_covering(due.saturating_sub(now)).max(1)),
+            None => max_ticks,
+        };
+
+        let TimeSlice {
+            runs,
+            ticks: ticks_run,
+        } = self
+            .schedule
+            .slice_at(self.current_tick, max_ticks, &mut self.due);
+        let profiler = self.profiler.as_deref();
+
+        match self.due.as_slice() {
+            [] => {}
+            [component_id] => run_component(
+                components,
+                *component_id,
+                runs,
+                RunContext {
+                    timestamp: now,
+                    cycles: self.cycles(*component_id),
+                },
+                profiler,
+                &self.watchdog,
+            ),
+            component_ids => {
+                if !self.lanes.contains_key(component_ids) {
+                    self.lanes.insert(
+                        component_ids.to_vec(),
+                        ExecutionLanes::new(components, component_ids),
                     );
-                    current_tick += batch_size;
                 }
-                // Conflicted components
-                _ => {
-                    schedule.insert(
-                        current_tick..current_tick + 1,
-                        to_run
-                            .into_iter()
-                            .filter_map(|(component_id, run_indication, _)| {
-                                if run_indication == 0 {
-                                    return Some(component_id);
-                                }
-
-                                None
-                            })
-                            .collect(),
-                    );
 
-                    current_tick += 1;
-                }
+                self.lanes[component_ids].run(
+                    components,
+                    runs,
+                    now,
+                    &self.cycles,
+                    profiler,
+                    &self.watchdog,
+                );
+            }
+        }
+
+        for component_id in self.due.iter() {
+            *self.cycles.entry(*component_id).or_default() += runs;
+        }
+
+        self.current_tick = (self.current_tick + ticks_run) % self.schedule.rollover_tick();
+        self.elapsed_ticks = self.elapsed_ticks.saturating_add(ticks_run);
+
+        if let Some(frequency_changes) = self.frequency_changes.take() {
+            for (component_id, frequency) in frequency_changes {
+                self.override_frequency(components, component_id, frequency);
             }
+            self.rebuild_schedule(components);
         }
 
-        let tick_real_time = Ratio::new(common_multiple, common_denominator).recip();
+        ticks_run
+    }
+
+    /// Has `component_id` run `frequency` times per second from now on, without restarting anything
+    pub fn set_component_frequency(
+        &mut self,
+        components: &ComponentStore,
+        component_id: ComponentId,
+        frequency: Ratio<u64>,
+    ) {
+        self.override_frequency(components, component_id, frequency);
+        self.rebuild_schedule(components);
+    }
+
+    fn override_frequency(
+        &mut self,
+        components: &ComponentStore,
+        component_id: ComponentId,
+        frequency: Ratio<u64>,
+    ) {
+        if frequency == Ratio::from_integer(0) {
+            tracing::warn!(
+                "Ignoring a frequency of zero for component {:?}",
+                component_id
+            );
+            return;
+        }
+
+        if components
+            .get(component_id)
+            .is_none_or(|table| table.as_schedulable.is_none())
+        {
+            tracing::warn!(
+                "Component {:?} is not schedulable, so its frequency cannot change",
+                component_id
+            );
+            return;
+        }
 
         tracing::debug!(
-            "Schedule ticks take {:?} and restarts at tick {}",
-            Duration::from_secs_f64(tick_real_time.to_f64().unwrap()),
-            common_denominator
+            "Component {:?} will now run {} times per second",
+            component_id,
+            frequency
         );
 
-        Self {
-            current_tick: 0,
-            rollover_tick: common_denominator,
-            tick_real_time,
-            schedule,
-            allotted_time: Duration::from_millis(16),
+        self.frequency_overrides.insert(component_id, frequency);
+    }
+
+    /// Lays the schedule out again from its start, keeping emulated time counting from where it is now
+    fn rebuild_schedule(&mut self, components: &ComponentStore) {
+        self.rebuilt_at = (self.elapsed_ticks, self.emulated_time());
+        self.schedule = Schedule::new(components, &self.frequency_overrides);
+        self.current_tick = 0;
+        self.lanes.clear();
+    }
+
+    /// Goes back to the start of the schedule, like the machine was just built
+    pub fn restart(&mut self, components: &ComponentStore) {
+        // Wakeups stay the same distance away as they were
+        let discarded_time = self.emulated_time();
+        self.events.set_pending(
+            self.events
+                .pending()
+                .into_iter()
+                .map(|(at, component_id)| (at.saturating_sub(discarded_time), component_id)),
+        );
+        self.events.set_now(Duration::ZERO);
+
+        self.elapsed_ticks = 0;
+        self.cycles.clear();
+        self.rebuilt_at = (0, Duration::ZERO);
+
+        if !self.frequency_overrides.is_empty() {
+            self.frequency_overrides.clear();
+            self.rebuild_schedule(components);
         }
+        self.current_tick = 0;
     }
 
-    pub fn run(&mut self, components: &ComponentStore) {
-        // TODO: This should actually be calculating how much time is between frames minus draw time
-        let starting_tick = self.current_tick;
-        let timestamp = Instant::now();
+    pub fn events(&self) -> &Arc<EventQueue> {
+        &self.events
+    }
 
-        // Ensure we don't overstep the framerate
-        while self.allotted_time > timestamp.elapsed()
-            // ensure we don't overstate the emulated timespace
-            && (self.current_tick.wrapping_sub(starting_tick) as f32
-                * self.tick_real_time.to_f32().unwrap())
-                <  self.allotted_time.as_secs_f32()
-        {
-            if let Some((time_slice, component_ids)) =
-                self.schedule.get_key_value(&self.current_tick)
-            {
-                // TODO: Run this through rayon once we can stop vulkan related concurrency issues
-                for component_id in component_ids {
-                    if let Some(component_info) = components
-                        .get(*component_id)
-                        .and_then(|table| table.as_schedulable.as_ref())
-                    {
-                        component_info
-                            .component
-                            .run(time_slice.clone().count() as u64);
-                    } else {
-                        panic!("Schedule referencing non existant component");
-                    }
+    pub fn elapsed_ticks(&self) -> u64 {
+        self.elapsed_ticks
+    }
+
+    /// How many times `component_id` has run since the machine started, see [RunContext::cycles]
+    pub fn cycles(&self, component_id: ComponentId) -> u64 {
+        self.cycles.get(&component_id).copied().unwrap_or_default()
+    }
+
+    /// How much emulated time has passed since the machine started
+    pub fn emulated_time(&self) -> Duration {
+        let (rebuilt_at_tick, rebuilt_at_time) = self.rebuilt_at;
+        let tick_real_time = self.schedule.tick_real_time();
+        let nanoseconds = (self.elapsed_ticks - rebuilt_at_tick) as u128
+            * *tick_real_time.numer() as u128
+            * 1_000_000_000
+            / *tick_real_time.denom() as u128;
+
+        rebuilt_at_time + Duration::from_nanos(nanoseconds.try_into().unwrap_or(u64::MAX))
+    }
+
+    pub fn state(&self) -> SchedulerState {
+        SchedulerState {
+            current_tick: self.current_tick,
+            elapsed_ticks: self.elapsed_ticks,
+            frequency_overrides: self.frequency_overrides.clone(),
+            rebuilt_at: self.rebuilt_at,
+            events: self.events.pending(),
+            cycles: self.cycles.clone(),
+        }
+    }
+
+    /// Puts the scheduler back where `state` was taken, leaving what the frontend set alone, like pausing
+    pub fn restore_state(&mut self, components: &ComponentStore, state: SchedulerState) {
+        // Anything asked for after the snapshot was taken never happened
+        self.frequency_changes.take();
+
+        if self.frequency_overrides != state.frequency_overrides {
+            self.frequency_overrides = state.frequency_overrides;
+            self.rebuild_schedule(components);
+        }
+
+        if state.current_tick < self.schedule.rollover_tick() {
+            self.current_tick = state.current_tick;
+        } else {
+            tracing::warn!(
+                "Snapshot was taken at tick {} but the schedule restarts at tick {}, starting it over",
+                state.current_tick,
+                self.schedule.rollover_tick()
+            );
+        }
+
+        self.elapsed_ticks = state.elapsed_ticks;
+        self.cycles = state.cycles;
+        self.rebuilt_at = state.rebuilt_at;
+        self.events.set_pending(state.events);
+        self.pending_steps = 0;
+    }
+
+    /// Times every component while running, which costs a little per component run so it is off by default
+    pub fn set_profiling(&mut self, profiling: bool) {
+        if profiling == self.profiler.is_some() {
+            return;
+        }
+
+        tracing::debug!("Scheduler profiling set to {}", profiling);
+
+        self.profiler = profiling.then(Arc::default);
+        self.stats = None;
+    }
+
+    /// Where the time of the last run went, if profiling
+    pub fn stats(&self) -> Option<&SchedulerStats> {
+        self.stats.as_ref()
+    }
+
+    fn finish_profiling(
+        &mut self,
+        components: &ComponentStore,
+        run_start: Instant,
+        ticks_run: u64,
+    ) {
+        if let Some(profiler) = &self.profiler {
+            self.stats = Some(profiler.finish(components, run_start.elapsed(), ticks_run));
+        }
+    }
+
+    /// Switches to only running when [Scheduler::step] is called, or back to normal with [None]
+    pub fn set_step_granularity(&mut self, step_granularity: Option<StepGranularity>) {
+        if self.step_granularity != step_granularity {
+            tracing::debug!("Scheduler step granularity set to {:?}", step_granularity);
+        }
+
+        self.step_granularity = step_granularity;
+        self.pending_steps = 0;
+    }
+
+    pub fn step_granularity(&self) -> Option<StepGranularity> {
+        self.step_granularity
+    }
+
+    /// Makes every [Scheduler::run] cover exactly `ticks` ticks, ignoring real time and speed entirely, or goes back to
+    /// pacing against real time with [None]
+    ///
+    /// For when the same inputs have to give the same results every time, like headless tests or movie playback
+    pub fn set_fixed_timestep(&mut self, ticks: Option<u64>) {
+        if ticks == Some(0) {
+            tracing::warn!("Ignoring a fixed timestep of zero ticks, pause instead");
+            return;
+        }
+
+        if self.fixed_timestep != ticks {
+            tracing::debug!("Scheduler fixed timestep set to {:?} ticks", ticks);
+        }
+
+        self.fixed_timestep = ticks;
+    }
+
+    pub fn fixed_timestep(&self) -> Option<u64> {
+        self.fixed_timestep
+    }
+
+    /// Queues up a step for the next [Scheduler::run], does nothing unless stepping
+    pub fn step(&mut self) {
+        if self.step_granularity.is_some() {
+            self.pending_steps = self.pending_steps.saturating_add(1);
+        }
+    }
+
+    /// Makes each run cover `speed` times as much emulated time, without taking any more real time
+    pub fn set_speed(&mut self, speed: Ratio<u64>) {
+        if speed == Ratio::from_integer(0) {
+            tracing::warn!("Ignoring a speed of zero, pause instead");
+            return;
+        }
+
+        if self.speed != speed {
+            tracing::info!("Running at {}x speed", speed.to_f32().unwrap());
+        }
+
+        self.speed = speed;
+    }
+
+    pub fn speed(&self) -> Ratio<u64> {
+        self.speed
+    }
+
+    /// The most real time a run will try to catch up on after the host stalls, anything past it is dropped so a hitch
+    /// does not turn into a burst of fast forwarding
+    pub fn set_max_catch_up(&mut self, max_catch_up: Duration) {
+        if self.max_catch_up != max_catch_up {
+            tracing::debug!("Scheduler will catch up on at most {:?}", max_catch_up);
+        }
+
+        self.max_catch_up = max_catch_up;
+    }
+
+    pub fn max_catch_up(&self) -> Duration {
+        self.max_catch_up
+    }
+
+    /// Has runs cover however much time `pacing` says passed, pinned allotted time included
+    pub fn set_pacing(&mut self, pacing: Pacing) {
+        if self.pacing != pacing {
+            tracing::info!("Scheduler pacing by {}", pacing);
+            // Whatever played under the old pacing was already accounted for
+            self.audio_clock.take();
+        }
+
+        self.pacing = pacing;
+    }
+
+    pub fn pacing(&self) -> Pacing {
+        self.pacing
+    }
+
+    /// See [Watchdog::set_budget]
+    pub fn set_watchdog_budget(&mut self, budget: Option<Duration>) {
+        self.watchdog.set_budget(budget);
+    }
+
+    /// Shared so a component that never returns can be noticed without waiting on the machine
+    pub fn watchdog(&self) -> &Arc<Watchdog> {
+        &self.watchdog
+    }
+
+    /// What the host audio callback reports played audio to, see [Pacing::Audio]
+    pub fn audio_clock(&self) -> &Arc<AudioClock> {
+        &self.audio_clock
+    }
+
+    /// Freezes emulated time until [Scheduler::resume] is called
+    pub fn pause(&mut self) {
+        if self.paused {
+            return;
+        }
+
+        if let Some(last_run) = self.last_run.take() {
+            self.running_time += last_run.elapsed();
+        }
+
+        self.paused = true;
+        tracing::debug!("Scheduler paused after running for {:?}", self.running_time);
+    }
+
+    pub fn resume(&mut self) {
+        if !self.paused {
+            return;
+        }
+
+        // The time between now and pausing is not counted
+        self.audio_clock.take();
+        self.paused = false;
+        tracing::debug!("Scheduler resumed");
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// How much real time we have spent running, not including any time spent paused
+    pub fn running_time(&self) -> Duration {
+        self.running_time
+            + self
+                .last_run
+                .map(|last_run| last_run.elapsed())
+                .unwrap_or_default()
+    }
+
+    /// Pins how much emulated time each run covers, [Scheduler::too_slow] and [Scheduler::too_fast] do nothing while pinned
+    pub fn lock_allotted_time(&mut self, allotted_time: Option<Duration>) {
+        match allotted_time {
+            Some(allotted_time) => {
+                if !self.allotted_time_locked || self.allotted_time != allotted_time {
+                    tracing::debug!("Scheduler allotted time locked to {:?}", allotted_time);
                 }
 
-                self.current_tick = self
-                    .current_tick
-                    .saturating_add(time_slice.clone().count() as u64);
-            } else {
-                self.current_tick = self.current_tick.saturating_add(1);
+                self.allotted_time = allotted_time;
+                self.allotted_time_locked = true;
             }
+            None => {
+                if self.allotted_time_locked {
+                    tracing::debug!("Scheduler allotted time unlocked");
+                }
 
-            self.current_tick %= self.rollover_tick;
+                self.allotted_time_locked = false;
+            }
         }
     }
 
+    /// How much emulated time each run covers
+    pub fn allotted_time(&self) -> Duration {
+        self.allotted_time
+    }
+
     pub fn too_slow(&mut self) {
+        // Steps should stay the same size while debugging
+        if self.allotted_time_locked || self.step_granularity.is_some() {
+            return;
+        }
+
         // Set our allotted time to lower but not lower than one tick
         self.allotted_time = self
             .allotted_time
             .saturating_sub(Duration::from_nanos(500))
             .max(Duration::from_secs_f32(
-                self.tick_real_time.to_f32().unwrap(),
+                self.schedule.tick_real_time().to_f32().unwrap(),
             ));
 
         tracing::trace!(
@@ -203,12 +774,19 @@ impl Scheduler {
     }
 
     pub fn too_fast(&mut self) {
+        // Steps should stay the same size while debugging
+        if self.allotted_time_locked || self.step_granularity.is_some() {
+            return;
+        }
+
         // Set our allotted time higher but not higher than what one period takes
         self.allotted_time = self
             .allotted_time
             .saturating_add(Duration::from_nanos(500))
             .min(Duration::from_secs_f32(
-                (self.tick_real_time * self.rollover_tick).to_f32().unwrap(),
+                (self.schedule.tick_real_time() * self.schedule.rollover_tick())
+                    .to_f32()
+                    .unwrap(),
             ));
 
         tracing::trace!(
@@ -217,3 +795,527 @@ impl Scheduler {
         );
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{EventQueue, FrequencyChanges, Pacing};
+    use crate::{
+        component::{
+            event_driven::EventDrivenComponent,
+            schedulable::{RunContext, SchedulableComponent},
+            Component, ComponentId, FromConfig,
+        },
+        machine::{ComponentBuilder, Machine, MachineBuildError},
+        rom::{manager::RomManager, system::GameSystem},
+    };
+    use itertools::Itertools;
+    use num::rational::Ratio;
+    use std::{
+        sync::{
+            atomic::{AtomicU32, AtomicU64, Ordering},
+            Arc, Mutex,
+        },
+        time::Duration,
+    };
+
+    #[derive(Debug)]
+    struct Recorder {
+        id: ComponentId,
+        log: Arc<Mutex<Vec<ComponentId>>>,
+    }
+
+    impl Component for Recorder {}
+
+    impl FromConfig for Recorder {
+        type Config = (Arc<Mutex<Vec<ComponentId>>>, Option<ComponentId>);
+
+        fn from_config(
+            component_builder: &mut ComponentBuilder<Self>,
+            config: Self::Config,
+        ) -> Result<(), MachineBuildError> {
+            let (log, run_after) = config;
+            let id = component_builder.id();
+
+            component_builder
+                .set_component(Self { id, log })
+                .set_schedulable(Ratio::from_integer(60), run_after, []);
+
+            Ok(())
+        }
+    }
+
+    impl SchedulableComponent for Recorder {
+        fn run(&self, _period: u64, _context: RunContext) {
+            self.log.lock().unwrap().push(self.id);
+        }
+    }
+
+    /// Keeps what every run was told about the time
+    #[derive(Debug)]
+    struct Clock {
+        contexts: Arc<Mutex<Vec<RunContext>>>,
+    }
+
+    impl Component for Clock {}
+
+    impl FromConfig for Clock {
+        type Config = Arc<Mutex<Vec<RunContext>>>;
+
+        fn from_config(
+            component_builder: &mut ComponentBuilder<Self>,
+            contexts: Self::Config,
+        ) -> Result<(), MachineBuildError> {
+            component_builder
+                .set_component(Self { contexts })
+                .set_schedulable(Ratio::from_integer(60), [], []);
+
+            Ok(())
+        }
+    }
+
+    impl SchedulableComponent for Clock {
+        fn run(&self, _period: u64, context: RunContext) {
+            self.contexts.lock().unwrap().push(context);
+        }
+    }
+
+    /// Counts its runs at whatever frequency it is given
+    #[derive(Debug)]
+    struct Counter {
+        runs: Arc<AtomicU64>,
+    }
+
+    impl Component for Counter {}
+
+    impl FromConfig for Counter {
+        type Config = (Arc<AtomicU64>, Ratio<u64>);
+
+        fn from_config(
+            component_builder: &mut ComponentBuilder<Self>,
+            config: Self::Config,
+        ) -> Result<(), MachineBuildError> {
+            let (runs, frequency) = config;
+
+            component_builder
+                .set_component(Self { runs })
+                .set_schedulable(frequency, [], []);
+
+            Ok(())
+        }
+    }
+
+    impl SchedulableComponent for Counter {
+        fn run(&self, period: u64, _context: RunContext) {
+            self.runs.fetch_add(period, Ordering::Relaxed);
+        }
+    }
+
+    /// Switches to double speed after running 10 times
+    #[derive(Debug)]
+    struct DoubleSpeed {
+        id: ComponentId,
+        runs: Arc<AtomicU32>,
+        frequency_changes: Arc<FrequencyChanges>,
+    }
+
+    impl Component for DoubleSpeed {}
+
+    impl FromConfig for DoubleSpeed {
+        type Config = Arc<AtomicU32>;
+
+        fn from_config(
+            component_builder: &mut ComponentBuilder<Self>,
+            runs: Self::Config,
+        ) -> Result<(), MachineBuildError> {
+            let id = component_builder.id();
+            let frequency_changes = component_builder.machine().frequency_changes.clone();
+
+            component_builder
+                .set_component(Self {
+                    id,
+                    runs,
+                    frequency_changes,
+                })
+                .set_schedulable(Ratio::from_integer(60), [], []);
+
+            Ok(())
+        }
+    }
+
+    impl SchedulableComponent for DoubleSpeed {
+        fn run(&self, period: u64, _context: RunContext) {
+            for _ in 0..period {
+                if self.runs.fetch_add(1, Ordering::Relaxed) + 1 == 10 {
+                    self.frequency_changes
+                        .request(self.id, Ratio::from_integer(120));
+                }
+            }
+        }
+    }
+
+    /// Wakes up every 100 milliseconds, keeping track of when
+    #[derive(Debug)]
+    struct Timer {
+        id: ComponentId,
+        wakeups: Arc<Mutex<Vec<Duration>>>,
+        events: Arc<EventQueue>,
+    }
+
+    impl Component for Timer {}
+
+    impl FromConfig for Timer {
+        type Config = Arc<Mutex<Vec<Duration>>>;
+
+        fn from_config(
+            component_builder: &mut ComponentBuilder<Self>,
+            wakeups: Self::Config,
+        ) -> Result<(), MachineBuildError> {
+            let id = component_builder.id();
+            let events = component_builder.machine().events.clone();
+            events.schedule_event_in(id, Duration::from_millis(100));
+
+            component_builder
+                .set_component(Self {
+                    id,
+                    wakeups,
+                    events,
+                })
+                .set_event_driven();
+
+            Ok(())
+        }
+    }
+
+    impl EventDrivenComponent for Timer {
+        fn wake(&self, now: Duration) {
+            self.wakeups.lock().unwrap().push(now);
+            self.events
+                .schedule_event_in(self.id, Duration::from_millis(100));
+        }
+    }
+
+    #[test]
+    fn events_wake_components_on_time() {
+        // Something has to be on the schedule for ticks to have a length
+        let log = Arc::new(Mutex::new(Vec::default()));
+        let wakeups = Arc::new(Mutex::new(Vec::default()));
+        let rom_manager = Arc::new(RomManager::new(None).unwrap());
+        let mut machine = Machine::build(GameSystem::Unknown, rom_manager)
+            .build_component::<Recorder>((log.clone(), None))
+            .unwrap()
+            .0
+            .build_component::<Timer>(wakeups.clone())
+            .unwrap()
+            .0
+            .build()
+            .unwrap();
+
+        machine.run_frame(Duration::from_secs(1));
+        let state = machine.snapshot();
+        machine.run_frame(Duration::from_millis(500));
+
+        // The wakeup at a second is where the next frame starts
+        let expected: Vec<_> = (1..15).map(|n| Duration::from_millis(n * 100)).collect();
+        assert_eq!(*wakeups.lock().unwrap(), expected);
+
+        // Going back has the same wakeups happen again
+        machine.restore_snapshot(state).unwrap();
+        machine.run_frame(Duration::from_millis(500));
+        assert_eq!(wakeups.lock().unwrap()[14..], expected[9..]);
+    }
+
+    #[test]
+    fn runs_know_what_time_it_is() {
+        let contexts = Arc::new(Mutex::new(Vec::default()));
+        let rom_manager = Arc::new(RomManager::new(None).unwrap());
+        let (machine_builder, clock_id) = Machine::build(GameSystem::Unknown, rom_manager)
+            .build_component::<Clock>(contexts.clone())
+            .unwrap();
+        let mut machine = machine_builder.build().unwrap();
+
+        for _ in 0..3 {
+            machine.run_frame(Duration::from_secs(1) / 60);
+        }
+        let state = machine.snapshot();
+
+        let expected: Vec<_> = (0..3)
+            .map(|cycles| RunContext {
+                timestamp: Duration::from_secs(cycles) / 60,
+                cycles,
+            })
+            .collect();
+        assert_eq!(*contexts.lock().unwrap(), expected);
+        assert_eq!(machine.scheduler.cycles(clock_id), 3);
+
+        // The count goes back with everything else
+        machine.run_frame(Duration::from_secs(1) / 60);
+        machine.restore_snapshot(state).unwrap();
+        assert_eq!(machine.scheduler.cycles(clock_id), 3);
+    }
+
+    #[test]
+    fn audio_pacing_runs_what_was_played() {
+        let runs = Arc::new(AtomicU64::default());
+        let rom_manager = Arc::new(RomManager::new(None).unwrap());
+        let mut machine = Machine::build(GameSystem::Unknown, rom_manager)
+            .build_component::<Counter>((runs.clone(), Ratio::from_integer(1000)))
+            .unwrap()
+            .0
+            .build()
+            .unwrap();
+        machine.scheduler.set_pacing(Pacing::Audio);
+        machine.scheduler.audio_clock().set_active(true);
+
+        machine
+            .scheduler
+            .audio_clock()
+            .played(Duration::from_millis(5));
+        machine.run();
+        assert_eq!(runs.load(Ordering::Relaxed), 5);
+
+        // Nothing played, so nothing runs no matter how much real time passes
+        std::thread::sleep(Duration::from_millis(5));
+        machine.run();
+        assert_eq!(runs.load(Ordering::Relaxed), 5);
+    }
+
+    /// Takes far longer to run than anything should
+    #[derive(Debug)]
+    struct Sleeper;
+
+    impl Component for Sleeper {}
+
+    impl FromConfig for Sleeper {
+        type Config = ();
+
+        fn from_config(
+            component_builder: &mut ComponentBuilder<Self>,
+            _config: Self::Config,
+        ) -> Result<(), MachineBuildError> {
+            component_builder
+                .set_component(Self)
+                .set_schedulable(Ratio::from_integer(60), [], []);
+
+            Ok(())
+        }
+    }
+
+    impl SchedulableComponent for Sleeper {
+        fn run(&self, _period: u64, _context: RunContext) {
+            std::thread::sleep(Duration::from_millis(200));
+        }
+    }
+
+    #[test]
+    fn watchdog_reports_runaway_components() {
+        let rom_manager = Arc::new(RomManager::new(None).unwrap());
+        let mut machine = Machine::build(GameSystem::Unknown, rom_manager)
+            .default_component::<Sleeper>()
+            .unwrap()
+            .0
+            .build()
+            .unwrap();
+        machine
+            .scheduler
+            .set_watchdog_budget(Some(Duration::from_millis(50)));
+
+        machine.run_frame(Duration::from_secs(1) / 60);
+
+        let runaway = machine.scheduler.watchdog().take_runaway().unwrap();
+        assert_eq!(runaway.component_id, ComponentId(0));
+        assert!(runaway.name.ends_with("Sleeper"));
+        assert!(runaway.running_for > Duration::from_millis(50));
+    }
+
+    #[test]
+    fn far_apart_frequencies() {
+        let processor_runs = Arc::new(AtomicU64::default());
+        let display_runs = Arc::new(AtomicU64::default());
+        let rom_manager = Arc::new(RomManager::new(None).unwrap());
+        let mut machine = Machine::build(GameSystem::Unknown, rom_manager)
+            .build_component::<Counter>((processor_runs.clone(), Ratio::from_integer(1789773)))
+            .unwrap()
+            .0
+            .build_component::<Counter>((display_runs.clone(), Ratio::from_integer(60)))
+            .unwrap()
+            .0
+            .build()
+            .unwrap();
+
+        for _ in 0..60 {
+            machine.run_frame(Duration::from_secs(1) / 60);
+        }
+
+        assert_eq!(processor_runs.load(Ordering::Relaxed), 1789773);
+        assert_eq!(display_runs.load(Ordering::Relaxed), 60);
+        assert_eq!(machine.scheduler.emulated_time(), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn frequency_changes_while_running() {
+        let runs = Arc::default();
+        let rom_manager = Arc::new(RomManager::new(None).unwrap());
+        let mut machine = Machine::build(GameSystem::Unknown, rom_manager)
+            .build_component::<DoubleSpeed>(Arc::clone(&runs))
+            .unwrap()
+            .0
+            .build()
+            .unwrap();
+
+        for _ in 0..60 {
+            machine.run_frame(Duration::from_secs(1) / 60);
+        }
+
+        // A sixth of a second at normal speed, then the rest at double
+        assert_eq!(runs.load(Ordering::Relaxed), 110);
+        // Emulated time is kept to the nanosecond, so a change can round it down by one
+        assert!(
+            Duration::from_secs(1).abs_diff(machine.scheduler.emulated_time())
+                <= Duration::from_nanos(1)
+        );
+
+        machine.set_component_frequency(ComponentId(0), Ratio::from_integer(60));
+        machine.run_frame(Duration::from_secs(1));
+        assert_eq!(runs.load(Ordering::Relaxed), 170);
+    }
+
+    #[test]
+    fn snapshots_keep_frequency_changes() {
+        let runs = Arc::new(AtomicU32::default());
+        let rom_manager = Arc::new(RomManager::new(None).unwrap());
+        let mut machine = Machine::build(GameSystem::Unknown, rom_manager)
+            .build_component::<DoubleSpeed>(Arc::clone(&runs))
+            .unwrap()
+            .0
+            .build()
+            .unwrap();
+
+        for _ in 0..20 {
+            machine.run_frame(Duration::from_secs(1) / 60);
+        }
+        let state = machine.snapshot();
+        let emulated_time = machine.scheduler.emulated_time();
+
+        machine.set_component_frequency(ComponentId(0), Ratio::from_integer(60));
+        machine.restore_snapshot(state).unwrap();
+        assert_eq!(machine.scheduler.emulated_time(), emulated_time);
+
+        let runs_before = runs.load(Ordering::Relaxed);
+        machine.run_frame(Duration::from_secs(1) / 60);
+        assert_eq!(runs.load(Ordering::Relaxed) - runs_before, 2);
+    }
+
+    #[test]
+    fn profiling_times_every_component() {
+        let log = Arc::new(Mutex::new(Vec::default()));
+        let rom_manager = Arc::new(RomManager::new(None).unwrap());
+        let mut machine = Machine::build(GameSystem::Unknown, rom_manager)
+            .build_component::<Recorder>((log.clone(), None))
+            .unwrap()
+            .0
+            .build_component::<Recorder>((log.clone(), None))
+            .unwrap()
+            .0
+            .build()
+            .unwrap();
+
+        machine.run_frame(Duration::from_secs(1) / 60);
+        assert!(machine.scheduler.stats().is_none());
+
+        machine.scheduler.set_profiling(true);
+        machine.run_frame(Duration::from_secs(1) / 60);
+
+        let stats = machine.scheduler.stats().unwrap();
+        assert_eq!(stats.ticks_run, 1);
+        assert_eq!(
+            stats
+                .components
+                .iter()
+                .map(|component_stats| component_stats.component_id.0)
+                .sorted()
+                .collect::<Vec<_>>(),
+            [0, 1]
+        );
+        assert!(stats
+            .components
+            .iter()
+            .all(|component_stats| component_stats.name.ends_with("Recorder")));
+    }
+
+    #[test]
+    fn fixed_timestep_ignores_real_time() {
+        let log = Arc::new(Mutex::new(Vec::default()));
+        let rom_manager = Arc::new(RomManager::new(None).unwrap());
+        let mut machine = Machine::build(GameSystem::Unknown, rom_manager)
+            .build_component::<Recorder>((log, None))
+            .unwrap()
+            .0
+            .build()
+            .unwrap();
+
+        machine.set_fixed_timestep(Some(5));
+        for _ in 0..3 {
+            machine.run();
+        }
+
+        assert_eq!(machine.scheduler.elapsed_ticks(), 15);
+        assert_eq!(
+            machine.scheduler.emulated_time(),
+            Duration::from_millis(250)
+        );
+    }
+
+    #[test]
+    fn hitches_are_only_partly_caught_up() {
+        let log = Arc::new(Mutex::new(Vec::default()));
+        let rom_manager = Arc::new(RomManager::new(None).unwrap());
+        let mut machine = Machine::build(GameSystem::Unknown, rom_manager)
+            .build_component::<Recorder>((log, None))
+            .unwrap()
+            .0
+            .build()
+            .unwrap();
+
+        machine.run();
+        let before_hitch = machine.scheduler.emulated_time();
+        std::thread::sleep(Duration::from_secs(1));
+        machine.run();
+
+        let caught_up = machine.scheduler.emulated_time() - before_hitch;
+        assert!(caught_up >= super::DEFAULT_MAX_CATCH_UP - Duration::from_millis(20));
+        assert!(caught_up <= super::DEFAULT_MAX_CATCH_UP + Duration::from_millis(20));
+
+        // Turning catching up off entirely means only ever running what one run covers
+        machine.scheduler.set_max_catch_up(Duration::ZERO);
+        let before_hitch = machine.scheduler.emulated_time();
+        std::thread::sleep(Duration::from_millis(100));
+        machine.run();
+        assert!(machine.scheduler.emulated_time() - before_hitch <= Duration::from_millis(17));
+    }
+
+    #[test]
+    fn run_order_is_honored() {
+        let log = Arc::new(Mutex::new(Vec::default()));
+        let rom_manager = Arc::new(RomManager::new(None).unwrap());
+        // Built in the opposite order they have to run in
+        let mut machine = Machine::build(GameSystem::Unknown, rom_manager)
+            .build_component::<Recorder>((log.clone(), Some(ComponentId(1))))
+            .unwrap()
+            .0
+            .build_component::<Recorder>((log.clone(), Some(ComponentId(2))))
+            .unwrap()
+            .0
+            .build_component::<Recorder>((log.clone(), None))
+            .unwrap()
+            .0
+            .build()
+            .unwrap();
+
+        machine.run_frame(Duration::from_secs(1) / 60);
+
+        assert_eq!(
+            log.lock().unwrap()[..3],
+            [ComponentId(2), ComponentId(1), ComponentId(0)]
+        );
+    }
+}
@@ -0,0 +1,49 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    sync::atomic::{AtomicBool, AtomicU64, Ordering},
+    time::Duration,
+};
+use strum::{Display, EnumIter};
+
+/// What [super::Scheduler::run] measures how much time has passed by
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, EnumIter, Display, PartialEq, Eq, Default)]
+pub enum Pacing {
+    /// The host clock, which drifts against the audio device and crackles once it does
+    #[default]
+    WallClock,
+    /// How much audio the host has played, falling back to the host clock while nothing is playing
+    Audio,
+}
+
+/// How much audio the host has played since the scheduler last looked, shared with the host audio callback
+///
+/// See [crate::runtime::audio::AdaptiveAudioBuffer::set_clock]
+#[derive(Debug, Default)]
+pub struct AudioClock {
+    /// Nanoseconds of audio played and not yet run
+    played: AtomicU64,
+    /// Set while something is feeding the clock, otherwise pacing by it would never run anything
+    active: AtomicBool,
+}
+
+impl AudioClock {
+    pub fn played(&self, duration: Duration) {
+        self.played.fetch_add(
+            duration.as_nanos().try_into().unwrap_or(u64::MAX),
+            Ordering::Relaxed,
+        );
+    }
+
+    pub fn set_active(&self, active: bool) {
+        self.active.store(active, Ordering::Release);
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active.load(Ordering::Acquire)
+    }
+
+    /// Takes out everything played since last time
+    pub(super) fn take(&self) -> Duration {
+        Duration::from_nanos(self.played.swap(0, Ordering::Relaxed))
+    }
+}
@@ -0,0 +1,222 @@
+use crate::{component::ComponentId, machine::component_store::ComponentStore};
+use itertools::Itertools;
+use num::{integer::lcm, rational::Ratio, Integer, ToPrimitive};
+use petgraph::{algo::toposort, graph::DiGraph};
+use std::{collections::HashMap, time::Duration};
+
+/// Where each schedulable component goes in a time slice it shares, honoring what it asked to run after and before
+fn run_order(components: &ComponentStore) -> HashMap<ComponentId, usize> {
+    let mut graph = DiGraph::<ComponentId, ()>::new();
+    let nodes: HashMap<_, _> = components
+        .iter()
+        .filter(|(_, table)| table.as_schedulable.is_some())
+        .map(|(component_id, _)| (component_id, graph.add_node(component_id)))
+        .collect();
+
+    for (component_id, table) in components.iter() {
+        let Some(component_info) = &table.as_schedulable else {
+            continue;
+        };
+        let node = nodes[&component_id];
+
+        for run_after in component_info
+            .run_after
+            .iter()
+            .filter_map(|other| nodes.get(other))
+        {
+            graph.add_edge(*run_after, node, ());
+        }
+
+        for run_before in component_info
+            .run_before
+            .iter()
+            .filter_map(|other| nodes.get(other))
+        {
+            graph.add_edge(node, *run_before, ());
+        }
+    }
+
+    let ordered: Vec<_> = match toposort(&graph, None) {
+        Ok(ordered) => ordered.into_iter().map(|node| graph[node]).collect(),
+        Err(cycle) => {
+            tracing::warn!(
+                "Component {:?} is part of a run ordering cycle, running components in the order they were built",
+                graph[cycle.node_id()]
+            );
+
+            nodes
+                .keys()
+                .copied()
+                .sorted_by_key(|component_id| component_id.0)
+                .collect()
+        }
+    };
+
+    ordered
+        .into_iter()
+        .enumerate()
+        .map(|(index, component_id)| (component_id, index))
+        .collect()
+}
+
+/// What runs at a tick of a [Schedule] and for how long
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeSlice {
+    /// How many times each due component runs
+    pub runs: u64,
+    /// Ticks until something else is due
+    pub ticks: u64,
+}
+
+/// When each schedulable component runs, worked out as we go so it takes the same memory no matter how far apart the
+/// frequencies of the components are
+#[derive(Debug, Clone)]
+pub struct Schedule {
+    /// Each schedulable component and how many ticks apart its runs are, in the order they run when due together
+    periods: Vec<(ComponentId, u64)>,
+    /// Where every component lines back up and the schedule starts over
+    rollover_tick: u64,
+    tick_real_time: Ratio<u64>,
+}
+
+impl Schedule {
+    /// Works out how far apart each schedulable component runs, `frequency_overrides` taking the place of the
+    /// frequencies they were built with
+    pub fn new(
+        components: &ComponentStore,
+        frequency_overrides: &HashMap<ComponentId, Ratio<u64>>,
+    ) -> Self {
+        let component_infos: HashMap<_, _> = components
+            .iter()
+            .filter_map(|(component_id, table)| {
+                if let Some(schedulable_component) = &table.as_schedulable {
+                    let timings = frequency_overrides
+                        .get(&component_id)
+                        .copied()
+                        .unwrap_or(schedulable_component.timings);
+
+                    // Would never come due, and there is no period to work out for it
+                    if timings == Ratio::from_integer(0) {
+                        tracing::warn!(
+                            "Component {:?} has a frequency of zero, it will not be scheduled",
+                            component_id
+                        );
+
+                        return None;
+                    }
+
+                    return Some((component_id, timings));
+                }
+
+                None
+            })
+            .collect();
+
+        for (component, component_timings) in component_infos.iter() {
+            tracing::debug!(
+                "Component {:?} will run {} times per second",
+                component,
+                component_timings
+            );
+        }
+
+        let common_denominator = component_infos
+            .values()
+            .map(|ratio| *ratio.recip().denom())
+            .fold(1, |acc, denom| acc.lcm(&denom));
+
+        // Adjust numerators to the common denominator
+        let adjusted_numerators: HashMap<_, _> = component_infos
+            .iter()
+            .map(|(component_id, ratio)| {
+                let factor = common_denominator / ratio.denom();
+                (*component_id, ratio.numer() * factor)
+            })
+            .collect();
+
+        let common_multiple = adjusted_numerators
+            .clone()
+            .into_values()
+            .reduce(lcm)
+            .unwrap_or(1);
+
+        let ratios: HashMap<_, _> = adjusted_numerators
+            .iter()
+            .map(|(component_id, numerator)| (*component_id, common_multiple / numerator))
+            .collect();
+
+        let run_order = run_order(components);
+        let periods = ratios
+            .into_iter()
+            .sorted_by_key(|(component_id, _)| run_order[component_id])
+            .collect();
+
+        let tick_real_time = Ratio::new(common_multiple, common_denominator).recip();
+
+        tracing::debug!(
+            "Schedule ticks take {:?} and restarts at tick {}",
+            Duration::from_secs_f64(tick_real_time.to_f64().unwrap()),
+            common_denominator
+        );
+
+        Self {
+            periods,
+            rollover_tick: common_denominator,
+            tick_real_time,
+        }
+    }
+
+    pub fn rollover_tick(&self) -> u64 {
+        self.rollover_tick
+    }
+
+    pub fn tick_real_time(&self) -> Ratio<u64> {
+        self.tick_real_time
+    }
+
+    /// Fills `due` with the components due at `tick` in the order they have to run, covering at most `max_ticks`
+    ///
+    /// Components sharing a period run for as long as nothing else comes due, otherwise each runs once
+    pub fn slice_at(&self, tick: u64, max_ticks: u64, due: &mut Vec<ComponentId>) -> TimeSlice {
+        due.clear();
+
+        let mut end = self
+            .rollover_tick
+            .min(tick.saturating_add(max_ticks.max(1)));
+        let mut due_periods = None;
+
+        for (component_id, period) in self.periods.iter() {
+            let into_period = tick % period;
+
+            if into_period == 0 {
+                due.push(*component_id);
+
+                let (shortest, longest) = due_periods.get_or_insert((*period, *period));
+                *shortest = (*shortest).min(*period);
+                *longest = (*longest).max(*period);
+            } else {
+                end = end.min(tick + period - into_period);
+            }
+        }
+
+        match due_periods {
+            None => TimeSlice {
+                runs: 0,
+                ticks: end - tick,
+            },
+            Some((shortest, longest)) if shortest == longest => {
+                let ticks = end - tick;
+
+                TimeSlice {
+                    runs: ticks.div_ceil(shortest),
+                    ticks,
+                }
+            }
+            // They only line up every so often, so go one run at a time until the next of them is due
+            Some((shortest, _)) => TimeSlice {
+                runs: 1,
+                ticks: end.min(tick + shortest) - tick,
+            },
+        }
+    }
+}
@@ -0,0 +1,67 @@
+use crate::{component::ComponentId, machine::component_store::ComponentStore};
+use std::{collections::HashMap, sync::Mutex, time::Duration};
+
+/// Where the real time of the last run went, see [super::Scheduler::set_profiling]
+#[derive(Debug, Clone, Default)]
+pub struct SchedulerStats {
+    /// Real time the whole run took, including the scheduler itself
+    pub run_time: Duration,
+    pub ticks_run: u64,
+    /// Slowest first
+    pub components: Vec<ComponentStats>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ComponentStats {
+    pub component_id: ComponentId,
+    pub name: &'static str,
+    /// Real time spent inside the component during the run
+    pub run_time: Duration,
+}
+
+/// Adds up how long each component takes, shared with the threads parallel lanes run on
+#[derive(Debug, Default)]
+pub(super) struct Profiler {
+    component_times: Mutex<HashMap<ComponentId, Duration>>,
+}
+
+impl Profiler {
+    pub fn record(&self, component_id: ComponentId, run_time: Duration) {
+        *self
+            .component_times
+            .lock()
+            .unwrap()
+            .entry(component_id)
+            .or_default() += run_time;
+    }
+
+    /// Wraps up what was recorded so far and starts over
+    pub fn finish(
+        &self,
+        components: &ComponentStore,
+        run_time: Duration,
+        ticks_run: u64,
+    ) -> SchedulerStats {
+        let mut component_stats: Vec<_> = self
+            .component_times
+            .lock()
+            .unwrap()
+            .drain()
+            .map(|(component_id, run_time)| ComponentStats {
+                component_id,
+                name: components
+                    .get(component_id)
+                    .map(|table| table.name)
+                    .unwrap_or_default(),
+                run_time,
+            })
+            .collect();
+        component_stats.sort_by(|a, b| b.run_time.cmp(&a.run_time));
+
+        SchedulerStats {
+            run_time,
+            ticks_run,
+            components: component_stats,
+        }
+    }
+}
@@ -0,0 +1,169 @@
+use crate::{component::ComponentId, machine::component_store::ComponentStore};
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex, Weak,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+/// How often the watchdog looks at what is running, as a fraction of the budget
+const CHECKS_PER_BUDGET: u32 = 4;
+
+/// A component that has been inside a single run for longer than the watchdog allows
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RunawayComponent {
+    pub component_id: ComponentId,
+    pub name: &'static str,
+    /// As of when it was noticed, it may well still be going
+    pub running_for: Duration,
+}
+
+/// Notices a component run that never returns, since that freezes the whole machine with nothing to show for it
+///
+/// Shared with a thread of its own that does the noticing, which is only started once a budget is set
+#[derive(Debug)]
+pub struct Watchdog {
+    epoch: Instant,
+    /// Nanoseconds since the epoch each component started its current run, plus one so zero means not running
+    started: Box<[AtomicU64]>,
+    /// The start of the run each component was last reported for, so a run is only reported once
+    reported: Box<[AtomicU64]>,
+    names: Box<[&'static str]>,
+    /// Nanoseconds a run may take, zero while disabled
+    budget: AtomicU64,
+    thread_started: AtomicBool,
+    runaway: Mutex<Option<RunawayComponent>>,
+}
+
+impl Watchdog {
+    pub(super) fn new(components: &ComponentStore) -> Self {
+        let names: Box<[_]> = components.components().map(|table| table.name).collect();
+
+        Self {
+            epoch: Instant::now(),
+            started: names.iter().map(|_| AtomicU64::default()).collect(),
+            reported: names.iter().map(|_| AtomicU64::default()).collect(),
+            names,
+            budget: AtomicU64::default(),
+            thread_started: AtomicBool::default(),
+            runaway: Mutex::default(),
+        }
+    }
+
+    /// How long a single component run can take before it is reported, [None] to stop watching
+    pub fn set_budget(self: &Arc<Self>, budget: Option<Duration>) {
+        let budget_nanos = budget
+            .map(|budget| budget.as_nanos().try_into().unwrap_or(u64::MAX).max(1))
+            .unwrap_or_default();
+
+        if self.budget.swap(budget_nanos, Ordering::Relaxed) != budget_nanos {
+            tracing::debug!("Watchdog budget set to {:?}", budget);
+        }
+
+        if budget.is_some() && !self.thread_started.swap(true, Ordering::Relaxed) {
+            let watchdog = Arc::downgrade(self);
+
+            thread::Builder::new()
+                .name("watchdog".to_string())
+                .spawn(move || watch(watchdog))
+                .expect("Failed to spawn watchdog thread");
+        }
+    }
+
+    pub fn budget(&self) -> Option<Duration> {
+        match self.budget.load(Ordering::Relaxed) {
+            0 => None,
+            budget_nanos => Some(Duration::from_nanos(budget_nanos)),
+        }
+    }
+
+    /// Takes the last component reported since this was last called
+    pub fn take_runaway(&self) -> Option<RunawayComponent> {
+        self.runaway.lock().unwrap().take()
+    }
+
+    #[inline]
+    pub(super) fn run_started(&self, component_id: ComponentId) {
+        if self.budget.load(Ordering::Relaxed) != 0 {
+            self.started[component_id.0 as usize].store(self.now() + 1, Ordering::Relaxed);
+        }
+    }
+
+    #[inline]
+    pub(super) fn run_finished(&self, component_id: ComponentId) {
+        let started = self.started[component_id.0 as usize].swap(0, Ordering::Relaxed);
+
+        // It came back after all, which is worth knowing when looking into why it was reported
+        if started != 0 && self.reported[component_id.0 as usize].load(Ordering::Relaxed) == started
+        {
+            tracing::warn!(
+                "{} ({}) finally returned after {:?}",
+                self.names[component_id.0 as usize],
+                component_id.0,
+                Duration::from_nanos(self.now() + 1 - started)
+            );
+        }
+    }
+
+    fn now(&self) -> u64 {
+        self.epoch
+            .elapsed()
+            .as_nanos()
+            .try_into()
+            .unwrap_or(u64::MAX - 1)
+    }
+
+    /// Reports every run that went over budget and has not been reported yet, returning the budget checked against
+    fn check(&self) -> Option<Duration> {
+        let budget = self.budget()?;
+        let now = self.now() + 1;
+
+        for (index, started) in self.started.iter().enumerate() {
+            let started = started.load(Ordering::Relaxed);
+            let running_for = Duration::from_nanos(now.saturating_sub(started));
+
+            if started == 0
+                || running_for <= budget
+                || self.reported[index].swap(started, Ordering::Relaxed) == started
+            {
+                continue;
+            }
+
+            let runaway = RunawayComponent {
+                component_id: ComponentId(index as u16),
+                name: self.names[index],
+                running_for,
+            };
+            tracing::error!(
+                "{} ({}) has been running for {:?}, longer than the watchdog allows",
+                runaway.name,
+                index,
+                running_for
+            );
+
+            *self.runaway.lock().unwrap() = Some(runaway);
+        }
+
+        Some(budget)
+    }
+}
+
+fn watch(watchdog: Weak<Watchdog>) {
+    loop {
+        // Stops with the machine
+        let Some(watchdog) = watchdog.upgrade() else {
+            return;
+        };
+        let budget = watchdog.check();
+        drop(watchdog);
+
+        thread::sleep(
+            budget
+                .map(|budget| budget / CHECKS_PER_BUDGET)
+                .unwrap_or(Duration::from_secs(1))
+                .max(Duration::from_millis(10)),
+        );
+    }
+}
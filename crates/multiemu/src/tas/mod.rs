@@ -0,0 +1,352 @@
+//! Tool assisted runs, where a [Movie] is built up frame by frame with the freedom to go back and change any of it
+
+use crate::{
+    component::ResetKind,
+    input::{EmulatedGamepadId, Input, InputState},
+    machine::{serialization::MachineState, Machine},
+    scheduler::StepGranularity,
+};
+use indexmap::IndexMap;
+use movie::{Movie, MovieError, MovieFrame};
+use std::collections::{BTreeMap, BTreeSet};
+
+pub mod movie;
+
+/// Memory the greenzone can take up before the least recently used states are dropped, until told otherwise
+pub const DEFAULT_GREENZONE_BUDGET: usize = 256 * 1024 * 1024;
+
+#[derive(Debug, Clone)]
+struct GreenzoneState {
+    state: MachineState,
+    /// See [MachineState::estimated_size]
+    size: usize,
+    /// Which use of the greenzone last touched this, to find the least recently used state
+    last_used: u64,
+}
+
+/// A movie being edited against a running machine
+///
+/// The machine is kept frame stepping for as long as the session lasts so nothing runs behind our back
+#[derive(Debug, Clone)]
+pub struct TasSession {
+    pub movie: Movie,
+    /// The next frame to run, the machine is sitting right at its start
+    current_frame: usize,
+    /// Every input the movie can hold, in the order the editor shows them
+    columns: Vec<(EmulatedGamepadId, Input)>,
+    /// Machine state at the start of frames that were run with the inputs the movie has now
+    greenzone: BTreeMap<usize, GreenzoneState>,
+    /// Memory the greenzone states take up altogether
+    greenzone_size: usize,
+    greenzone_budget: usize,
+    /// Counts up every time a greenzone state is stored or run from
+    greenzone_uses: u64,
+    /// Other takes on the movie inputs
+    branches: IndexMap<String, Vec<MovieFrame>>,
+    /// Frames run so far where the emulated software never polled input
+    lag_frames: BTreeSet<usize>,
+}
+
+impl TasSession {
+    /// Starts a new movie from a reset of the machine
+    pub fn start(machine: &mut Machine) -> Self {
+        let movie = Movie::new(
+            machine.system,
+            machine.user_specified_roms.clone(),
+            rand::random(),
+            machine.frame_duration(),
+        );
+
+        Self::begin(machine, movie)
+    }
+
+    /// Plays an existing movie from the start, it can be edited from there like any other
+    pub fn from_movie(machine: &mut Machine, movie: Movie) -> Result<Self, MovieError> {
+        movie.validate(machine.system, &machine.user_specified_roms)?;
+
+        Ok(Self::begin(machine, movie))
+    }
+
+    fn begin(machine: &mut Machine, movie: Movie) -> Self {
+        machine.set_step_granularity(Some(StepGranularity::Frame));
+        machine.reset(ResetKind::Hard);
+        machine.rng.reseed(movie.rng_seed);
+
+        let columns = machine
+            .input_manager
+            .emulated_gamepads()
+            .into_iter()
+            .flat_map(|(port, kind)| {
+                let mut present_inputs: Vec<_> = machine
+                    .input_manager
+                    .gamepad_types
+                    .get(&kind)
+                    .map(|metadata| metadata.present_inputs.iter().copied().collect())
+                    .unwrap_or_default();
+                present_inputs.sort();
+
+                present_inputs.into_iter().map(move |input| (port, input))
+            })
+            .collect();
+
+        tracing::info!(
+            "Starting a movie of {} frames with seed {}",
+            movie.frames.len(),
+            movie.rng_seed
+        );
+
+        let mut tas_session = Self {
+            movie,
+            current_frame: 0,
+            columns,
+            greenzone: BTreeMap::default(),
+            greenzone_size: 0,
+            greenzone_budget: DEFAULT_GREENZONE_BUDGET,
+            greenzone_uses: 0,
+            branches: IndexMap::default(),
+            lag_frames: BTreeSet::default(),
+        };
+        tas_session.store_greenzone(0, machine.snapshot());
+
+        tas_session
+    }
+
+    /// Hands the machine back to running normally
+    pub fn stop(self, machine: &mut Machine) {
+        machine.set_step_granularity(None);
+    }
+
+    pub fn current_frame(&self) -> usize {
+        self.current_frame
+    }
+
+    pub fn columns(&self) -> &[(EmulatedGamepadId, Input)] {
+        &self.columns
+    }
+
+    pub fn is_greenzone(&self, frame: usize) -> bool {
+        self.greenzone.contains_key(&frame)
+    }
+
+    /// Memory the greenzone states take up right now
+    pub fn greenzone_size(&self) -> usize {
+        self.greenzone_size
+    }
+
+    pub fn greenzone_len(&self) -> usize {
+        self.greenzone.len()
+    }
+
+    pub fn greenzone_budget(&self) -> usize {
+        self.greenzone_budget
+    }
+
+    /// Caps how much memory the greenzone takes up, dropping the least recently used states to fit
+    pub fn set_greenzone_budget(&mut self, greenzone_budget: usize) {
+        self.greenzone_budget = greenzone_budget;
+        self.trim_greenzone();
+    }
+
+    pub fn is_lag_frame(&self, frame: usize) -> bool {
+        self.lag_frames.contains(&frame)
+    }
+
+    /// Lag frames before the cursor
+    pub fn lag_frame_count(&self) -> usize {
+        self.lag_frames.range(..self.current_frame).count()
+    }
+
+    pub fn is_held(&self, frame: usize, port: EmulatedGamepadId, input: Input) -> bool {
+        self.movie
+            .frames
+            .get(frame)
+            .is_some_and(|movie_frame| movie_frame.is_held(port, input))
+    }
+
+    /// Changes an input, returning if the machine already ran past it and has to seek again to match
+    pub fn set_held(
+        &mut self,
+        frame: usize,
+        port: EmulatedGamepadId,
+        input: Input,
+        held: bool,
+    ) -> bool {
+        if frame >= self.movie.frames.len() {
+            self.movie.frames.resize(frame + 1, MovieFrame::default());
+        }
+
+        self.movie.frames[frame].set_held(port, input, held);
+        self.invalidate_after(frame);
+
+        self.current_frame > frame
+    }
+
+    /// Runs the frame at the cursor, recording whatever is held right now if the movie ends here
+    pub fn advance(&mut self, machine: &mut Machine) {
+        if self.current_frame >= self.movie.frames.len() {
+            let mut movie_frame = MovieFrame::default();
+
+            for (port, input) in self.columns.iter() {
+                movie_frame.set_held(
+                    *port,
+                    *input,
+                    machine.input_manager.peek_input(*port, *input).as_digital(),
+                );
+            }
+
+            self.movie.frames.push(movie_frame);
+        }
+
+        self.run_frame(machine);
+    }
+
+    /// Puts the machine at the start of `frame`, running forward from the closest greenzone state before it
+    pub fn seek(&mut self, machine: &mut Machine, frame: usize) {
+        let frame = frame.min(self.movie.frames.len());
+        self.greenzone_uses += 1;
+        let (start, greenzone_state) = self
+            .greenzone
+            .range_mut(..=frame)
+            .next_back()
+            .expect("The first frame is always in the greenzone");
+        greenzone_state.last_used = self.greenzone_uses;
+
+        self.current_frame = *start;
+        machine
+            .restore_snapshot(greenzone_state.state.clone())
+            .expect("Greenzone states are taken from this machine");
+
+        while self.current_frame < frame {
+            self.run_frame(machine);
+        }
+    }
+
+    pub fn branches(&self) -> impl Iterator<Item = &str> {
+        self.branches.keys().map(String::as_str)
+    }
+
+    /// Keeps the current inputs under `name`, replacing any branch already called that
+    pub fn save_branch(&mut self, name: impl Into<String>) {
+        self.branches.insert(name.into(), self.movie.frames.clone());
+    }
+
+    /// Swaps the movie inputs for a branch, returning if the machine has to seek again to match
+    pub fn load_branch(&mut self, name: &str) -> bool {
+        let Some(frames) = self.branches.get(name) else {
+            return false;
+        };
+
+        let first_difference = self
+            .movie
+            .frames
+            .iter()
+            .zip(frames)
+            .position(|(current, branch)| current != branch)
+            .unwrap_or(self.movie.frames.len().min(frames.len()));
+
+        self.movie.frames = frames.clone();
+        self.invalidate_after(first_difference);
+
+        self.current_frame > first_difference
+    }
+
+    pub fn remove_branch(&mut self, name: &str) {
+        self.branches.shift_remove(name);
+    }
+
+    fn run_frame(&mut self, machine: &mut Machine) {
+        let movie_frame = &self.movie.frames[self.current_frame];
+
+        for (port, input) in self.columns.iter() {
+            machine.input_manager.set_emulated_input(
+                *port,
+                *input,
+                InputState::Digital(movie_frame.is_held(*port, *input)),
+            );
+        }
+
+        if machine.run_frame(self.movie.frame_duration) {
+            self.lag_frames.insert(self.current_frame);
+        } else {
+            self.lag_frames.remove(&self.current_frame);
+        }
+        self.current_frame += 1;
+
+        // Snapshots reseed the rng, so this has to happen every frame or playing back from the greenzone would drift
+        self.store_greenzone(self.current_frame, machine.snapshot());
+        self.trim_greenzone();
+    }
+
+    fn store_greenzone(&mut self, frame: usize, state: MachineState) {
+        self.greenzone_uses += 1;
+        let greenzone_state = GreenzoneState {
+            size: state.estimated_size(),
+            state,
+            last_used: self.greenzone_uses,
+        };
+        self.greenzone_size += greenzone_state.size;
+
+        if let Some(replaced) = self.greenzone.insert(frame, greenzone_state) {
+            self.greenzone_size -= replaced.size;
+        }
+    }
+
+    /// Forgets every state that came after `frame` changed
+    fn invalidate_after(&mut self, frame: usize) {
+        for (_, removed) in self.greenzone.split_off(&(frame + 1)) {
+            self.greenzone_size -= removed.size;
+        }
+        self.lag_frames.split_off(&frame);
+    }
+
+    fn trim_greenzone(&mut self) {
+        while self.greenzone_size > self.greenzone_budget {
+            // The first frame has to stay, everything else can be run again
+            let Some(least_recently_used) = self
+                .greenzone
+                .iter()
+                .filter(|(frame, _)| **frame != 0)
+                .min_by_key(|(_, greenzone_state)| greenzone_state.last_used)
+                .map(|(frame, _)| *frame)
+            else {
+                break;
+            };
+
+            if let Some(removed) = self.greenzone.remove(&least_recently_used) {
+                self.greenzone_size -= removed.size;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rom::{manager::RomManager, system::GameSystem};
+    use std::sync::Arc;
+
+    #[test]
+    fn greenzone_stays_within_budget() {
+        let rom_manager = Arc::new(RomManager::new(None).unwrap());
+        let mut machine = Machine::build(GameSystem::Unknown, rom_manager)
+            .build()
+            .unwrap();
+        let mut tas_session = TasSession::start(&mut machine);
+        let state_size = tas_session.greenzone_size();
+
+        for _ in 0..10 {
+            tas_session.advance(&mut machine);
+        }
+        assert_eq!(tas_session.greenzone_len(), 11);
+
+        // Running from the fifth frame makes it the most recently used one to keep
+        tas_session.seek(&mut machine, 5);
+        tas_session.set_greenzone_budget(state_size * 3);
+
+        assert!(tas_session.greenzone_size() <= state_size * 3);
+        assert!(tas_session.is_greenzone(0));
+        assert!(tas_session.is_greenzone(5));
+        assert!(tas_session.is_greenzone(10));
+        assert!(!tas_session.is_greenzone(9));
+    }
+}
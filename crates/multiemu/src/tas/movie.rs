@@ -0,0 +1,168 @@
+//! Movies record everything needed to play a run back exactly
+//!
+//! A movie file is a [ron] file holding a single [Movie]:
+//!
+//! - `version` is [MOVIE_VERSION] for movies written by this build, anything newer is refused
+//! - `system` and `roms` are what the machine was built from, with roms being their sha-1, a movie only plays back on
+//!   the same ones
+//! - `rng_seed` seeds the machine randomness when the movie starts, see [crate::machine::rng::MachineRng]
+//! - `frame_duration` is how much emulated time every frame covers
+//! - `frames` has an entry for every frame in order, listing the inputs held on each emulated gamepad port for
+//!   the whole frame, anything not listed is released
+//!
+//! Playback always starts from a reset of the machine
+//!
+//! ```ron
+//! (
+//!     version: 1,
+//!     system: Other(Chip8),
+//!     roms: [((218, 57, 163, 238, 94, 107, 75, 13, 50, 85, 191, 239, 149, 96, 24, 144, 175, 216, 7, 9))],
+//!     rng_seed: 42,
+//!     frame_duration: (secs: 0, nanos: 16666666),
+//!     frames: [
+//!         (held: {}),
+//!         (held: {0: [Keyboard(Numpad5)]}),
+//!     ],
+//! )
+//! ```
+
+use crate::{
+    input::{EmulatedGamepadId, Input},
+    rom::{id::RomId, system::GameSystem},
+};
+use ron::ser::PrettyConfig;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs::File,
+    path::Path,
+    time::Duration,
+};
+use thiserror::Error;
+
+pub const MOVIE_VERSION: u32 = 1;
+
+#[derive(Error, Debug)]
+pub enum MovieError {
+    #[error("Movie version {0} is newer than this build supports")]
+    UnsupportedVersion(u32),
+    #[error("Movie was recorded on {0}")]
+    WrongSystem(GameSystem),
+    #[error("Movie was recorded with different roms")]
+    WrongRoms,
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Deserialize(#[from] ron::de::SpannedError),
+    #[error(transparent)]
+    Serialize(#[from] ron::Error),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct MovieFrame {
+    pub held: BTreeMap<EmulatedGamepadId, BTreeSet<Input>>,
+}
+
+impl MovieFrame {
+    pub fn is_held(&self, port: EmulatedGamepadId, input: Input) -> bool {
+        self.held
+            .get(&port)
+            .is_some_and(|held| held.contains(&input))
+    }
+
+    pub fn set_held(&mut self, port: EmulatedGamepadId, input: Input, held: bool) {
+        if held {
+            self.held.entry(port).or_default().insert(input);
+        } else if let Some(port_held) = self.held.get_mut(&port) {
+            port_held.remove(&input);
+
+            if port_held.is_empty() {
+                self.held.remove(&port);
+            }
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Movie {
+    pub version: u32,
+    pub system: GameSystem,
+    pub roms: Vec<RomId>,
+    pub rng_seed: u64,
+    pub frame_duration: Duration,
+    pub frames: Vec<MovieFrame>,
+}
+
+impl Movie {
+    pub fn new(
+        system: GameSystem,
+        roms: Vec<RomId>,
+        rng_seed: u64,
+        frame_duration: Duration,
+    ) -> Self {
+        Self {
+            version: MOVIE_VERSION,
+            system,
+            roms,
+            rng_seed,
+            frame_duration,
+            frames: Vec::default(),
+        }
+    }
+
+    /// Checks the movie was made for the machine it is about to be played on
+    pub fn validate(&self, system: GameSystem, roms: &[RomId]) -> Result<(), MovieError> {
+        if self.system != system {
+            return Err(MovieError::WrongSystem(self.system));
+        }
+
+        if self.roms != roms {
+            return Err(MovieError::WrongRoms);
+        }
+
+        Ok(())
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), MovieError> {
+        let movie_file = File::create(path)?;
+        ron::ser::to_writer_pretty(movie_file, self, PrettyConfig::default())?;
+
+        Ok(())
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, MovieError> {
+        let movie_file = File::open(path)?;
+        let movie: Movie = ron::de::from_reader(movie_file)?;
+
+        if movie.version > MOVIE_VERSION {
+            return Err(MovieError::UnsupportedVersion(movie.version));
+        }
+
+        Ok(movie)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::input::keyboard::KeyboardInput;
+
+    #[test]
+    fn movie_round_trip() {
+        let mut movie = Movie::new(
+            GameSystem::Unknown,
+            vec![RomId::default()],
+            42,
+            Duration::from_millis(16),
+        );
+        movie.frames.resize(3, MovieFrame::default());
+        movie.frames[1].set_held(0, Input::Keyboard(KeyboardInput::Numpad5), true);
+
+        let path = std::env::temp_dir().join(format!("multiemu_movie_{}.ron", std::process::id()));
+        movie.save(&path).unwrap();
+        let loaded = Movie::load(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.unwrap(), movie);
+    }
+}
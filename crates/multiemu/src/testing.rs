@@ -0,0 +1,272 @@
+//! Stands up a single component for unit tests, so CPU and mapper tests do not have to put together whole machines
+
+use crate::{
+    component::{
+        memory::MemoryComponent,
+        schedulable::{RunContext, SchedulableComponent},
+        Component, ComponentId, FromConfig,
+    },
+    machine::{
+        fault::MachineFault, signal::SignalDriver, ComponentBuilder, Machine, MachineBuildError,
+        MachineBuilder,
+    },
+    memory::{AddressSpaceId, MemoryTranslationTable, ReadMemoryRecord, WriteMemoryRecord},
+    rom::{manager::RomManager, system::GameSystem},
+};
+use rangemap::RangeMap;
+use std::{collections::HashMap, sync::Arc, sync::Mutex};
+
+/// Memory that is nothing but a vec starting at address zero, with none of the options [StandardMemory] has
+///
+/// [StandardMemory]: crate::definitions::misc::memory::standard::StandardMemory
+#[derive(Debug)]
+pub struct VecMemory {
+    contents: Mutex<Vec<u8>>,
+}
+
+impl VecMemory {
+    pub fn contents(&self) -> Vec<u8> {
+        self.contents.lock().unwrap().clone()
+    }
+
+    /// Pokes `bytes` in at `address` without going through the bus
+    pub fn set_contents(&self, address: usize, bytes: &[u8]) {
+        self.contents.lock().unwrap()[address..address + bytes.len()].copy_from_slice(bytes);
+    }
+}
+
+impl Component for VecMemory {}
+
+impl FromConfig for VecMemory {
+    type Config = (AddressSpaceId, Vec<u8>);
+
+    fn from_config(
+        component_builder: &mut ComponentBuilder<Self>,
+        config: Self::Config,
+    ) -> Result<(), MachineBuildError> {
+        let (address_space, contents) = config;
+        let len = contents.len();
+
+        component_builder
+            .set_component(Self {
+                contents: Mutex::new(contents),
+            })
+            .set_memory([(address_space, 0..len)]);
+
+        Ok(())
+    }
+}
+
+impl MemoryComponent for VecMemory {
+    fn read_memory(
+        &self,
+        address: usize,
+        buffer: &mut [u8],
+        _address_space: AddressSpaceId,
+        errors: &mut RangeMap<usize, ReadMemoryRecord>,
+    ) {
+        let contents = self.contents.lock().unwrap();
+
+        match contents.get(address..address + buffer.len()) {
+            Some(bytes) => buffer.copy_from_slice(bytes),
+            None => errors.insert(address..address + buffer.len(), ReadMemoryRecord::Denied),
+        }
+    }
+
+    fn write_memory(
+        &self,
+        address: usize,
+        buffer: &[u8],
+        _address_space: AddressSpaceId,
+        errors: &mut RangeMap<usize, WriteMemoryRecord>,
+    ) {
+        let mut contents = self.contents.lock().unwrap();
+
+        match contents.get_mut(address..address + buffer.len()) {
+            Some(bytes) => bytes.copy_from_slice(buffer),
+            None => errors.insert(address..address + buffer.len(), WriteMemoryRecord::Denied),
+        }
+    }
+}
+
+/// Sets up the busses a component under test sees, each backed by a single [VecMemory]
+pub struct ComponentHarnessBuilder {
+    machine_builder: MachineBuilder,
+    memories: Vec<(AddressSpaceId, ComponentId)>,
+}
+
+impl ComponentHarnessBuilder {
+    /// Adds a bus `width` bits wide with `contents` at the start of it
+    pub fn memory(
+        self,
+        address_space: AddressSpaceId,
+        width: u8,
+        contents: impl Into<Vec<u8>>,
+    ) -> Self {
+        let Self {
+            machine_builder,
+            mut memories,
+        } = self;
+        let (machine_builder, component_id) = machine_builder
+            .insert_bus(address_space, width)
+            .build_component::<VecMemory>((address_space, contents.into()))
+            .expect("Harness memory could not be built");
+        memories.push((address_space, component_id));
+
+        Self {
+            machine_builder,
+            memories,
+        }
+    }
+
+    /// Builds the component under test on top of the memory set up so far
+    pub fn component<C: FromConfig>(self, config: C::Config) -> ComponentHarness<C> {
+        let (machine_builder, component_id) = self
+            .machine_builder
+            .build_component::<C>(config)
+            .expect("Component under test could not be built");
+        let machine = machine_builder
+            .build()
+            .expect("Component under test could not be built");
+
+        let component = machine
+            .component_store
+            .get(component_id)
+            .unwrap()
+            .component
+            .clone()
+            .into_any_arc()
+            .downcast::<C>()
+            .unwrap();
+        let memories = memories(&machine, &self.memories);
+
+        ComponentHarness {
+            machine,
+            component,
+            component_id,
+            memories,
+        }
+    }
+
+    /// Just the memory, for testing things like decoders that only need a bus to read from
+    pub fn memory_translation_table(self) -> Arc<MemoryTranslationTable> {
+        self.machine_builder
+            .build()
+            .expect("Harness memory could not be built")
+            .memory_translation_table
+    }
+}
+
+fn memories(
+    machine: &Machine,
+    memory_ids: &[(AddressSpaceId, ComponentId)],
+) -> HashMap<AddressSpaceId, Arc<VecMemory>> {
+    memory_ids
+        .iter()
+        .map(|(address_space, component_id)| {
+            let memory = machine
+                .component_store
+                .get(*component_id)
+                .unwrap()
+                .component
+                .clone()
+                .into_any_arc()
+                .downcast::<VecMemory>()
+                .unwrap();
+
+            (*address_space, memory)
+        })
+        .collect()
+}
+
+/// A single component wired up to nothing but [VecMemory]
+pub struct ComponentHarness<C: Component> {
+    /// Only kept around for what the component was handed while being built
+    machine: Machine,
+    component: Arc<C>,
+    component_id: ComponentId,
+    memories: HashMap<AddressSpaceId, Arc<VecMemory>>,
+}
+
+impl ComponentHarness<VecMemory> {
+    pub fn build() -> ComponentHarnessBuilder {
+        let rom_manager = Arc::new(RomManager::new(None).unwrap());
+
+        ComponentHarnessBuilder {
+            machine_builder: Machine::build(GameSystem::Unknown, rom_manager),
+            memories: Vec::default(),
+        }
+    }
+}
+
+impl<C: Component> ComponentHarness<C> {
+    pub fn component(&self) -> &C {
+        &self.component
+    }
+
+    pub fn component_id(&self) -> ComponentId {
+        self.component_id
+    }
+
+    pub fn memory(&self, address_space: AddressSpaceId) -> &VecMemory {
+        &self.memories[&address_space]
+    }
+
+    pub fn memory_translation_table(&self) -> &Arc<MemoryTranslationTable> {
+        &self.machine.memory_translation_table
+    }
+
+    pub fn fault(&self) -> Option<MachineFault> {
+        self.machine.fault()
+    }
+
+    /// Something to hold an input of the component with, like an interrupt line
+    pub fn signal_driver(&self, name: &str) -> SignalDriver {
+        self.machine
+            .component_store
+            .get(self.component_id)
+            .and_then(|table| table.signal_driver(name))
+            .expect("Component has no signal line by that name")
+    }
+
+    /// Runs the component directly, no scheduler involved, so every run looks like the first one
+    pub fn run(&self, period: u64)
+    where
+        C: SchedulableComponent,
+    {
+        self.component.run(period, RunContext::default());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::definitions::misc::memory::mirror::{MirrorMemory, MirrorMemoryConfig};
+
+    const ADDRESS_SPACE: AddressSpaceId = 0;
+
+    #[test]
+    fn component_sees_vec_memory() {
+        let harness = ComponentHarness::build()
+            .memory(ADDRESS_SPACE, 16, [0x12, 0x34])
+            .component::<MirrorMemory>(MirrorMemoryConfig {
+                readable: true,
+                writable: true,
+                assigned_ranges: RangeMap::from_iter([(0x100..0x102, 0x0000)]),
+                assigned_address_space: ADDRESS_SPACE,
+            });
+        let mut buffer = [0; 2];
+
+        harness
+            .memory_translation_table()
+            .read(0x100, &mut buffer, ADDRESS_SPACE)
+            .unwrap();
+        assert_eq!(buffer, [0x12, 0x34]);
+
+        harness
+            .memory_translation_table()
+            .write(0x100, &[0x56], ADDRESS_SPACE)
+            .unwrap();
+        assert_eq!(harness.memory(ADDRESS_SPACE).contents(), [0x56, 0x34]);
+    }
+}
@@ -0,0 +1,115 @@
+//! Asking GitHub if there is a newer release, which only ever happens if [crate::config::GlobalConfig::network_access]
+//! allows it
+
+use crate::config::GLOBAL_CONFIG;
+use serde::Deserialize;
+use std::error::Error;
+
+pub const RELEASES_URL: &str =
+    "https://api.github.com/repos/fullbrightkay/multiemu/releases/latest";
+pub const CURRENT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// The parts of a GitHub release we care about
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Release {
+    pub tag_name: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Release notes, in markdown
+    #[serde(default)]
+    pub body: Option<String>,
+    pub html_url: String,
+}
+
+impl Release {
+    /// Tags that do not look like a version are never newer, so a stray tag cannot nag everyone
+    pub fn is_newer_than(&self, version: &str) -> bool {
+        match (parse_version(&self.tag_name), parse_version(version)) {
+            (Some(release_version), Some(version)) => release_version > version,
+            _ => false,
+        }
+    }
+}
+
+/// Pre-release suffixes are ignored, and so are trailing zeros so 1.0 and 1.0.0 are the same
+fn parse_version(version: &str) -> Option<Vec<u64>> {
+    let version = version.trim_start_matches('v').split(['-', '+']).next()?;
+    let mut parts = version
+        .split('.')
+        .map(|part| part.parse().ok())
+        .collect::<Option<Vec<u64>>>()?;
+
+    while parts.last() == Some(&0) {
+        parts.pop();
+    }
+
+    Some(parts)
+}
+
+#[derive(Clone, Debug)]
+pub enum UpdateStatus {
+    Checking,
+    UpToDate,
+    Available(Release),
+    Failed(String),
+}
+
+#[cfg(all(platform_desktop, feature = "update_check"))]
+pub fn latest_release() -> Result<Release, Box<dyn Error>> {
+    let release = ureq::get(RELEASES_URL)
+        // GitHub turns away requests without one
+        .set(
+            "User-Agent",
+            concat!("multiemu/", env!("CARGO_PKG_VERSION")),
+        )
+        .call()?
+        .into_json()?;
+
+    Ok(release)
+}
+
+#[cfg(not(all(platform_desktop, feature = "update_check")))]
+pub fn latest_release() -> Result<Release, Box<dyn Error>> {
+    Err("This build cannot check for updates".into())
+}
+
+/// Blocks on the network, so keep it off the main thread
+pub fn check_for_update() -> UpdateStatus {
+    if !GLOBAL_CONFIG.read().unwrap().network_access {
+        return UpdateStatus::Failed("Network access is turned off".to_string());
+    }
+
+    match latest_release() {
+        Ok(release) if release.is_newer_than(CURRENT_VERSION) => UpdateStatus::Available(release),
+        Ok(_) => UpdateStatus::UpToDate,
+        Err(err) => {
+            tracing::warn!("Could not check for updates: {}", err);
+
+            UpdateStatus::Failed(err.to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn version_ordering() {
+        let release = |tag_name: &str| Release {
+            tag_name: tag_name.to_string(),
+            name: None,
+            body: None,
+            html_url: String::default(),
+        };
+
+        assert!(release("v0.2.0").is_newer_than("0.1.0"));
+        assert!(release("0.1.1").is_newer_than("0.1.0"));
+        assert!(release("v0.10.0").is_newer_than("0.9.3"));
+        assert!(release("v1.0.0-rc1").is_newer_than("0.9.0"));
+        assert!(!release("v0.1.0").is_newer_than("0.1.0"));
+        assert!(!release("v0.1").is_newer_than("0.1.0"));
+        assert!(!release("v0.0.9").is_newer_than("0.1.0"));
+        assert!(!release("nightly").is_newer_than("0.1.0"));
+    }
+}